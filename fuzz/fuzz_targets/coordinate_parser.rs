@@ -0,0 +1,15 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use uurs24::data::{Boei, format_coordinate};
+
+// Any string either fails to parse or gives a finite coordinate, which
+// survives formatting and parsing again
+fuzz_target!(|input: &str| {
+    if let Ok(value) = Boei::parse_coordinate_string(input) {
+        assert!(value.is_finite());
+        let formatted = format_coordinate(value, ['N', 'S']);
+        let parsed = Boei::parse_coordinate_string(&formatted).unwrap();
+        assert!((parsed - value).abs() < 0.001 / 60.0);
+    }
+});
@@ -0,0 +1,2652 @@
+use crate::approach::LatestDeparture;
+use crate::data::{Boei, DataWarning, RegattaData, WindData};
+use crate::live::WindObservation;
+use crate::i18n::{Lang, message};
+use crate::leaderboard::Standing;
+use crate::optimize::{LegPerformance, Path, PointOfSail, SearchStats, Step};
+use crate::plans::SavedPlan;
+use crate::profile::ArrivalProfile;
+use crate::rules::RuleViolation;
+use crate::simulate::{Simulation, SimulationSample};
+use crate::track::TrackPoint;
+use crate::explorer::{ExploreOption, Exploration};
+use crate::training::{TrainingChoice, TrainingGame};
+use serde::Serialize;
+use serde_json::json;
+
+/// Response of the estimate endpoints
+#[derive(Clone, Debug, Serialize)]
+pub struct EstimateResponse {
+    pub from: String,
+    pub to: String,
+    pub time: f64,
+    pub clock: Option<String>, // wall clock time of `time`
+    pub estimated_speed: f64,
+    pub polar_speed: f64, // before the correction of the leg
+    pub course_bearing: f64,
+    pub wind_direction: f64,
+    pub relative_bearing: f64,
+    pub wind_speed: f64,
+    pub official_distance: Option<f64>, // None if the buoys are not connected by a start or leg
+    pub great_circle_distance: f64,
+    pub travel_time: f64,
+    pub arrival_time: f64,
+    pub arrival_clock: Option<String>,
+    pub point_of_sail: PointOfSail,
+    pub interpretation: String, // the point of sail in the language of the request
+}
+
+impl EstimateResponse {
+    /// Build the response for a leg performance estimate
+    pub fn new(data: &RegattaData, from: &str, to: &str, time: f64, performance: &LegPerformance, lang: Lang) -> Self {
+        let point_of_sail = performance.point_of_sail();
+        Self {
+            from: from.to_string(),
+            to: to.to_string(),
+            time,
+            clock: data.race_clock.format_clock(time),
+            estimated_speed: performance.estimated_speed,
+            polar_speed: performance.polar_speed,
+            course_bearing: performance.course_bearing,
+            wind_direction: performance.wind_direction,
+            relative_bearing: performance.relative_bearing,
+            wind_speed: performance.wind_speed,
+            official_distance: performance.official_distance,
+            great_circle_distance: performance.great_circle_distance,
+            travel_time: performance.travel_time,
+            arrival_time: performance.arrival_time,
+            arrival_clock: data.race_clock.format_clock(performance.arrival_time),
+            point_of_sail,
+            interpretation: message(lang, point_of_sail.message_key()).to_string(),
+        }
+    }
+}
+
+/// A single step of a path, with buoy names resolved
+#[derive(Clone, Debug, Serialize)]
+pub struct StepResponse {
+    pub from: usize,
+    pub to: usize,
+    pub from_name: String,
+    pub to_name: String,
+    pub distance: f64,
+    pub speed: f64,
+    pub start_time: f64,
+    pub end_time: f64,
+    pub start_clock: Option<String>,
+    pub end_clock: Option<String>,
+    pub wait: f64, // hours waited at the buoy before start_time
+}
+
+impl StepResponse {
+    /// Build the response for a step of a path
+    pub fn new(data: &RegattaData, step: &Step) -> Self {
+        Self {
+            from: step.from,
+            to: step.to,
+            from_name: data.boeien[step.from].name.clone(),
+            to_name: data.boeien[step.to].name.clone(),
+            distance: step.distance,
+            speed: step.speed,
+            start_time: step.start_time,
+            end_time: step.end_time,
+            start_clock: data.race_clock.format_clock(step.start_time),
+            end_clock: data.race_clock.format_clock(step.end_time),
+            wait: step.wait,
+        }
+    }
+}
+
+/// A complete path with its steps
+#[derive(Clone, Debug, Serialize)]
+pub struct PathResponse {
+    pub steps: Vec<StepResponse>,
+    pub total_distance: f64,
+    pub scored_distance: f64,
+    pub qualification_margin: Option<f64>,
+    pub end_time: f64,
+    pub end_clock: Option<String>,
+    pub average_speed: f64, // in knots from the start to the end, waits included
+}
+
+impl PathResponse {
+    /// Build the response for a path
+    pub fn new(data: &RegattaData, path: &Path) -> Self {
+        Self {
+            steps: path.steps.iter().map(|step| StepResponse::new(data, step)).collect(),
+            total_distance: path.total_distance,
+            scored_distance: path.scored_distance,
+            qualification_margin: path.qualification_margin,
+            end_time: path.end_time,
+            end_clock: data.race_clock.format_clock(path.end_time),
+            average_speed: path.average_speed(),
+        }
+    }
+}
+
+/// Response of the find-paths and find-targets endpoints
+#[derive(Clone, Debug, Serialize)]
+pub struct FindPathsResponse {
+    pub start: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub target: Option<String>,
+    pub start_time: f64,
+    pub steps: usize,
+    pub truncated: bool, // the search stopped at the path limit or its budget, there are more paths
+    pub stats: SearchStats, // what the search used of its budget, pruned, and how long it took
+    pub paths: Vec<PathResponse>,
+}
+
+/// Response of the plan endpoint, also the result of a search job
+#[derive(Clone, Debug, Serialize)]
+pub struct PlanResponse {
+    pub start: String,
+    pub target: Option<String>,
+    pub start_time: f64,
+    pub steps: usize,
+    pub strategy: String,
+    pub objective: String,
+    pub paths_found: usize,
+    pub truncated: bool, // the search stopped at the path limit or its budget, there are more paths
+    pub stats: SearchStats, // what the search used of its budget, pruned, and how long it took
+    pub paths: Vec<PathResponse>,
+}
+
+/// Response of the plan saving endpoint, the plan with its link and route
+#[derive(Clone, Debug, Serialize)]
+pub struct SavedPlanResponse {
+    #[serde(flatten)]
+    pub plan: SavedPlan,
+    pub url: String, // path of the read-only page of the plan
+    pub path: PathResponse,
+}
+
+/// Status of an asynchronous search job
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+/// Response when a search job was created or cancelled
+#[derive(Clone, Debug, Serialize)]
+pub struct JobCreatedResponse {
+    pub id: u64,
+    pub status: JobStatus,
+}
+
+/// Progress counters of a search job
+#[derive(Clone, Debug, Serialize)]
+pub struct JobProgress {
+    pub nodes_explored: usize,
+    pub paths_found: usize,
+}
+
+/// Full state of a search job
+#[derive(Clone, Debug, Serialize)]
+pub struct JobResponse {
+    pub id: u64,
+    pub status: JobStatus,
+    pub created: String,
+    pub progress: JobProgress,
+    pub result: Option<PlanResponse>,
+    pub error: Option<ErrorResponse>,
+}
+
+/// Message streamed over the search WebSocket
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum SearchUpdate {
+    /// Periodic progress report while the search runs
+    Progress {
+        nodes_explored: usize,
+        paths_found: usize,
+        best: Option<PathResponse>,
+    },
+    /// Final result once the search has finished
+    Result(PlanResponse),
+    /// The request was invalid or the search failed
+    Error(ErrorResponse),
+}
+
+/// Response of the reload endpoint
+#[derive(Clone, Debug, Serialize)]
+pub struct ReloadResponse {
+    pub version: u64,
+    pub buoys: usize,
+    pub legs: usize,
+    pub templates: usize,
+}
+
+/// Response of the course update endpoint
+#[derive(Clone, Debug, Serialize)]
+pub struct CourseUpdateResponse {
+    pub version: u64,
+    pub cached_responses: usize, // cached responses the update didn't affect, kept for the new version
+}
+
+/// Response of the wind override endpoint, the forecast now in use
+#[derive(Clone, Debug, Serialize)]
+pub struct WindOverrideResponse {
+    pub version: u64,
+    pub hours: usize, // hours of the forecast replaced
+    pub wind: Vec<WindResponse>,
+}
+
+/// Event of the feed at /api/events, sent with its `type` as the event name
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerEvent {
+    /// Hours of the wind forecast were replaced
+    Wind { version: u64, hours: usize },
+    /// The data served changed: reloaded, the course changed, or recalibrated
+    Data { version: u64 },
+    /// A boat reported a position, the server's own boat without a name
+    Position {
+        boat: Option<String>,
+        time: f64,
+        lat: f64,
+        long: f64,
+    },
+    /// The leg the own boat sails or the best continuation after it changed
+    Recommendation {
+        route: Vec<String>, // the active leg followed by the continuation
+        eta: Option<f64>,   // arrival at the next buoy in hours since race start
+        clock: Option<String>,
+    },
+}
+
+impl ServerEvent {
+    /// Name of the event in the feed
+    pub fn name(&self) -> &'static str {
+        match self {
+            ServerEvent::Wind { .. } => "wind",
+            ServerEvent::Data { .. } => "data",
+            ServerEvent::Position { .. } => "position",
+            ServerEvent::Recommendation { .. } => "recommendation",
+        }
+    }
+}
+
+/// Response of the position endpoint
+#[derive(Clone, Debug, Serialize)]
+pub struct PositionResponse {
+    pub points: usize, // number of points in the track
+}
+
+/// Response of the observation endpoint
+#[derive(Clone, Debug, Serialize)]
+pub struct ObservationResponse {
+    pub observations: usize, // number of observations of all legs
+}
+
+/// Fitted correction factor of a leg
+#[derive(Clone, Debug, Serialize)]
+pub struct LegCalibrationResponse {
+    pub from: String,
+    pub to: String,
+    pub observations: usize,
+    pub factor: Option<f64>, // fitted from the observations, `None` with too few of them
+    pub applied: f64,        // factor in use by the estimates
+}
+
+/// Response of the calibration endpoints
+#[derive(Clone, Debug, Serialize)]
+pub struct CalibrationResponse {
+    pub version: u64,
+    pub observations: usize,
+    pub min_observations: usize, // needed on a leg before it is calibrated
+    pub legs: Vec<LegCalibrationResponse>,
+}
+
+/// Response of the track endpoint
+#[derive(Clone, Debug, Serialize)]
+pub struct TrackResponse {
+    pub distance: f64, // distance sailed along the returned points in nm
+    pub points: Vec<TrackPoint>,
+}
+
+/// A boat of the fleet with its latest position
+#[derive(Clone, Debug, Serialize)]
+pub struct BoatResponse {
+    pub name: String,
+    pub custom_polars: bool, // false if the boat sails with the polars of the data files
+    pub class: Option<String>, // class whose start the race times of the boat count from
+    pub session: Option<u64>,
+    pub registered: String,
+    pub points: usize,
+    pub distance_sailed: f64,
+    pub position: Option<TrackPoint>, // latest reported position
+}
+
+/// A boat on the leaderboard with the clock of its last rounding
+#[derive(Clone, Debug, Serialize)]
+pub struct StandingResponse {
+    #[serde(flatten)]
+    pub standing: Standing,
+    pub last_rounding_clock: Option<String>,
+}
+
+/// Response of the leaderboard endpoint
+#[derive(Clone, Debug, Serialize)]
+pub struct LeaderboardResponse {
+    pub rated: bool, // whether any boat has a rating correcting its miles
+    pub boats: Vec<StandingResponse>,
+}
+
+impl LeaderboardResponse {
+    /// Build the response for the standings of the fleet
+    pub fn new(data: &RegattaData, standings: Vec<Standing>) -> Self {
+        Self {
+            rated: standings.iter().any(|standing| standing.rating.is_some()),
+            boats: standings
+                .into_iter()
+                .map(|standing| StandingResponse {
+                    last_rounding_clock: standing.last_rounding.and_then(|time| data.race_clock.format_clock(time)),
+                    standing,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// A buoy with its point of no return on the way to the finish
+#[derive(Clone, Debug, Serialize)]
+pub struct NoReturnResponse {
+    pub buoy: String,
+    pub lat: Option<f64>,
+    pub long: Option<f64>,
+    pub latest: f64, // latest departure which still reaches the finish by the deadline
+    pub latest_clock: Option<String>,
+    pub next: String, // buoy to sail to from it
+}
+
+/// The leg of the boat against the point of no return of the buoy it sails to
+#[derive(Clone, Debug, Serialize)]
+pub struct ApproachPositionResponse {
+    pub lat: f64,
+    pub long: f64,
+    pub from: String, // leg guessed from the track
+    pub to: String,
+    pub eta: Option<f64>, // arrival at the next buoy in hours since race start
+    pub eta_clock: Option<String>,
+    pub margin: Option<f64>, // hours from the ETA to the point of no return of the next buoy
+}
+
+/// Response of the finish approach endpoint
+#[derive(Clone, Debug, Serialize)]
+pub struct FinishApproachResponse {
+    pub time: f64, // from which on departures count
+    pub clock: Option<String>,
+    pub deadline: f64,
+    pub finish: BuoyResponse,
+    pub hours: Vec<u32>, // remaining full hours of the race
+    pub buoys: Vec<NoReturnResponse>, // latest departure first
+    pub unreachable: Vec<BuoyResponse>, // buoys from which the finish can't be reached in time anymore
+    pub position: Option<ApproachPositionResponse>,
+}
+
+impl FinishApproachResponse {
+    /// Build the response for the points of no return from a time on
+    pub fn new(
+        data: &RegattaData,
+        time: f64,
+        finish: usize,
+        departures: &[LatestDeparture],
+        position: Option<ApproachPositionResponse>,
+    ) -> Self {
+        let deadline = data.rules.scoring.deadline;
+        let reachable: Vec<usize> = departures.iter().map(|departure| departure.buoy).collect();
+        Self {
+            time,
+            clock: data.race_clock.format_clock(time),
+            deadline,
+            finish: BuoyResponse::new(&data.boeien[finish]),
+            hours: (time.ceil().max(0.0) as u32..).take_while(|hour| (*hour as f64) < deadline).collect(),
+            buoys: departures
+                .iter()
+                .map(|departure| {
+                    let buoy = &data.boeien[departure.buoy];
+                    NoReturnResponse {
+                        buoy: buoy.name.clone(),
+                        lat: buoy.lat,
+                        long: buoy.long,
+                        latest: departure.latest,
+                        latest_clock: data.race_clock.format_clock(departure.latest),
+                        next: data.boeien[departure.next].name.clone(),
+                    }
+                })
+                .collect(),
+            unreachable: (0..data.boeien.len())
+                .filter(|buoy| *buoy != finish && !reachable.contains(buoy))
+                .map(|buoy| BuoyResponse::new(&data.boeien[buoy]))
+                .collect(),
+            position,
+        }
+    }
+}
+
+/// The earliest arrivals at a buoy, one per departure of the profile
+#[derive(Clone, Debug, Serialize)]
+pub struct BuoyArrivalsResponse {
+    pub buoy: String,
+    pub lat: Option<f64>,
+    pub long: Option<f64>,
+    pub arrivals: Vec<Option<f64>>, // in hours since race start, none if it can't be reached
+    pub via: Vec<Option<String>>,   // buoy the last leg comes from
+}
+
+/// Response of the arrival profile endpoint
+#[derive(Clone, Debug, Serialize)]
+pub struct ArrivalProfileResponse {
+    pub start: String,
+    pub interval: f64,        // minutes between the departures
+    pub departures: Vec<f64>, // in hours since race start
+    pub departure_clocks: Vec<Option<String>>,
+    pub buoys: Vec<BuoyArrivalsResponse>, // reached by some departure
+    pub unreachable: Vec<BuoyResponse>,   // reached by no departure
+}
+
+impl ArrivalProfileResponse {
+    /// Build the response for a profile
+    pub fn new(data: &RegattaData, profile: &ArrivalProfile, interval: f64) -> Self {
+        let (reached, unreachable): (Vec<usize>, Vec<usize>) = (0..data.boeien.len())
+            .filter(|&buoy| buoy != profile.start)
+            .partition(|&buoy| profile.arrivals.iter().any(|arrivals| arrivals[buoy].is_some()));
+        Self {
+            start: data.boeien[profile.start].name.clone(),
+            interval: interval * 60.0,
+            departures: profile.departures.clone(),
+            departure_clocks: profile.departures.iter().map(|&time| data.race_clock.format_clock(time)).collect(),
+            buoys: reached
+                .into_iter()
+                .map(|index| {
+                    let buoy = &data.boeien[index];
+                    let arrivals = || profile.arrivals.iter().map(move |arrivals| arrivals[index]);
+                    BuoyArrivalsResponse {
+                        buoy: buoy.name.clone(),
+                        lat: buoy.lat,
+                        long: buoy.long,
+                        arrivals: arrivals().map(|arrival| arrival.map(|arrival| arrival.time)).collect(),
+                        via: arrivals()
+                            .map(|arrival| arrival.map(|arrival| data.boeien[arrival.via].name.clone()))
+                            .collect(),
+                    }
+                })
+                .collect(),
+            unreachable: unreachable.into_iter().map(|buoy| BuoyResponse::new(&data.boeien[buoy])).collect(),
+        }
+    }
+}
+
+/// The leg being sailed as shown on the dashboard
+#[derive(Clone, Debug, Serialize)]
+pub struct ActiveLegResponse {
+    pub from: String,
+    pub to: String,
+    pub inferred: bool,      // guessed from the track instead of given by the client
+    pub distance_to_go: f64, // in nm
+    pub course: f64,         // to steer to the next buoy in degrees
+    pub speed: f64,          // estimated speed in knots
+    pub eta: Option<f64>,    // arrival at the next buoy in hours since race start
+    pub eta_clock: Option<String>,
+}
+
+/// Response of the dashboard endpoint
+#[derive(Clone, Debug, Serialize)]
+pub struct DashboardResponse {
+    pub time: f64, // race time of the latest reported position
+    pub clock: Option<String>,
+    pub position: TrackPoint,
+    pub wind_direction: f64, // forecast
+    pub wind_speed: f64,
+    pub measured_wind: Option<WindObservation>, // latest reading of the instruments
+    pub distance_sailed: f64, // along the track in nm
+    pub leg: ActiveLegResponse,
+    pub objective: String,
+    pub continuation: Option<PathResponse>, // best path from the next buoy
+}
+
+/// Planned and actual rounding time of a mark
+#[derive(Clone, Debug, Serialize)]
+pub struct MarkResponse {
+    pub buoy: String,
+    pub planned: f64,
+    pub actual: Option<f64>, // `None` until the mark is rounded
+    pub delta: Option<f64>,  // actual minus planned in hours, positive when behind
+}
+
+/// Response of the progress endpoint comparing the track with a plan
+#[derive(Clone, Debug, Serialize)]
+pub struct ProgressResponse {
+    pub session: u64,
+    pub time: f64, // race time of the latest reported position
+    pub clock: Option<String>,
+    pub from: String, // step of the plan being sailed
+    pub to: String,
+    pub distance_along: f64, // sailed along the planned route in nm
+    pub total_distance: f64,
+    pub time_behind: f64, // hours behind the plan, negative when ahead
+    pub planned_finish: f64,
+    pub projected_finish: Option<f64>, // at the current pace, `None` if the route can't be sailed
+    pub projected_clock: Option<String>,
+    pub late: bool, // the projected finish is after the end of the race
+    pub marks: Vec<MarkResponse>,
+}
+
+/// A leg of a simulated route with the buoy names and clock times
+#[derive(Clone, Debug, Serialize)]
+pub struct SimulatedLegResponse {
+    pub from: String,
+    pub to: String,
+    pub distance: f64,
+    pub start_time: f64,
+    pub end_time: f64,
+    pub start_clock: Option<String>,
+    pub end_clock: Option<String>,
+}
+
+/// Response of the simulation endpoint, the track of a route in time steps
+#[derive(Clone, Debug, Serialize)]
+pub struct SimulationResponse {
+    pub legs: Vec<SimulatedLegResponse>,
+    pub samples: Vec<SimulationSample>,
+    pub total_distance: f64,
+    pub end_time: f64,
+    pub end_clock: Option<String>,
+}
+
+impl SimulationResponse {
+    /// Build the response for a simulation
+    pub fn new(data: &RegattaData, simulation: Simulation) -> Self {
+        Self {
+            legs: simulation
+                .legs
+                .iter()
+                .map(|leg| SimulatedLegResponse {
+                    from: data.boeien[leg.from].name.clone(),
+                    to: data.boeien[leg.to].name.clone(),
+                    distance: leg.distance,
+                    start_time: leg.start_time,
+                    end_time: leg.end_time,
+                    start_clock: data.race_clock.format_clock(leg.start_time),
+                    end_clock: data.race_clock.format_clock(leg.end_time),
+                })
+                .collect(),
+            samples: simulation.samples,
+            total_distance: simulation.total_distance,
+            end_time: simulation.end_time,
+            end_clock: data.race_clock.format_clock(simulation.end_time),
+        }
+    }
+}
+
+/// Response of the route validation endpoint, every rule the route breaks
+#[derive(Clone, Debug, Serialize)]
+pub struct ValidateRouteResponse {
+    pub valid: bool,
+    pub edition: Option<u32>, // of the rules the route was checked against
+    pub path: PathResponse,
+    pub violations: Vec<RuleViolation>,
+}
+
+/// State of a training game with the legs to choose from next
+#[derive(Clone, Debug, Serialize)]
+pub struct TrainingResponse {
+    #[serde(flatten)]
+    pub game: TrainingGame,
+    pub clock: Option<String>,
+    pub score: f64,          // distance of the legs which ended within the race in nm
+    pub deadline: f64,       // end of the race in hours since race start
+    pub wind_direction: f64, // forecast at the current time
+    pub wind_speed: f64,
+    pub choices: Vec<TrainingChoice>,
+    pub samples: Option<Vec<SimulationSample>>, // simulation of the leg just sailed
+}
+
+impl TrainingResponse {
+    /// Build the response for a game, with the simulation of its last leg
+    pub fn new(data: &RegattaData, game: TrainingGame, samples: Option<Vec<SimulationSample>>) -> Self {
+        let wind = data.wind_data.get_wind_at_time(game.time);
+        Self {
+            clock: data.race_clock.format_clock(game.time),
+            score: game.score(),
+            deadline: data.rules.scoring.deadline,
+            wind_direction: wind.as_ref().map_or(0.0, |wind| wind.wind_angle),
+            wind_speed: wind.as_ref().map_or(0.0, |wind| wind.wind_speed),
+            choices: game.choices(data),
+            samples,
+            game,
+        }
+    }
+}
+
+/// State of an exploration with the legs which may be added next
+#[derive(Clone, Debug, Serialize)]
+pub struct ExplorationResponse {
+    #[serde(flatten)]
+    pub exploration: Exploration,
+    pub clock: Option<String>,
+    pub time: f64,           // the route reaches its last buoy, in hours since race start
+    pub distance: f64,       // of the legs of the route in nm
+    pub deadline: f64,       // end of the race in hours since race start
+    pub wind_direction: f64, // forecast at the time of the last buoy
+    pub wind_speed: f64,
+    pub options: Vec<ExploreOption>,
+}
+
+impl ExplorationResponse {
+    pub fn new(data: &RegattaData, exploration: Exploration) -> Self {
+        let time = exploration.time();
+        let wind = data.wind_data.get_wind_at_time(time);
+        Self {
+            clock: data.race_clock.format_clock(time),
+            time,
+            distance: exploration.distance(),
+            deadline: data.rules.scoring.deadline,
+            wind_direction: wind.as_ref().map_or(0.0, |wind| wind.wind_angle),
+            wind_speed: wind.as_ref().map_or(0.0, |wind| wind.wind_speed),
+            options: exploration.options(data),
+            exploration,
+        }
+    }
+}
+
+/// A buoy with its decimal coordinates
+#[derive(Clone, Debug, Serialize)]
+pub struct BuoyResponse {
+    pub name: String,
+    pub buoy_type: Option<String>,
+    pub description: Option<String>,
+    pub lat: Option<f64>,
+    pub long: Option<f64>,
+}
+
+impl BuoyResponse {
+    pub fn new(boei: &Boei) -> Self {
+        Self {
+            name: boei.name.clone(),
+            buoy_type: boei.buoy_type.clone(),
+            description: boei.description.clone(),
+            lat: boei.lat,
+            long: boei.long,
+        }
+    }
+}
+
+/// A start or leg between two buoys
+#[derive(Clone, Debug, Serialize)]
+pub struct ConnectionResponse {
+    pub from: String,
+    pub to: String,
+    pub distance: f64,
+}
+
+/// Polar table, `boat_speeds[i][j]` is the speed at `wind_angles[i]` and
+/// `wind_speeds[j]`
+#[derive(Clone, Debug, Serialize)]
+pub struct PolarResponse {
+    pub wind_speeds: Vec<f64>,
+    pub wind_angles: Vec<f64>,
+    pub boat_speeds: Vec<Vec<f64>>,
+}
+
+/// A warning about the loaded data, with the message the command line shows
+#[derive(Clone, Debug, Serialize)]
+pub struct DataWarningResponse {
+    #[serde(flatten)]
+    pub warning: DataWarning,
+    pub message: String,
+}
+
+impl From<DataWarning> for DataWarningResponse {
+    fn from(warning: DataWarning) -> Self {
+        let message = warning.to_string();
+        Self { warning, message }
+    }
+}
+
+/// Forecast wind of one race hour
+#[derive(Clone, Debug, Serialize)]
+pub struct WindResponse {
+    pub time: u32,
+    pub wind_speed: f64,
+    pub wind_angle: f64,
+}
+
+impl WindResponse {
+    /// The hours of a wind forecast
+    pub fn forecast(wind_data: &WindData) -> Vec<Self> {
+        wind_data
+            .get_all_conditions()
+            .iter()
+            .map(|condition| Self {
+                time: condition.time,
+                wind_speed: condition.wind_speed,
+                wind_angle: condition.wind_angle,
+            })
+            .collect()
+    }
+}
+
+/// Response of the data endpoint, the loaded regatta data
+#[derive(Clone, Debug, Serialize)]
+pub struct DataResponse {
+    pub buoys: Vec<BuoyResponse>,
+    pub starts: Vec<ConnectionResponse>,
+    pub legs: Vec<ConnectionResponse>,
+    pub polars: PolarResponse,
+    pub wind: Vec<WindResponse>,
+}
+
+impl DataResponse {
+    /// Build the response for the regatta data
+    pub fn new(data: &RegattaData) -> Self {
+        Self {
+            buoys: data
+                .boeien
+                .iter()
+                .map(BuoyResponse::new)
+                .collect(),
+            starts: data
+                .starts
+                .iter()
+                .map(|start| ConnectionResponse {
+                    from: start.from.clone(),
+                    to: start.to.clone(),
+                    distance: start.distance,
+                })
+                .collect(),
+            legs: data
+                .rakken
+                .iter()
+                .map(|rak| ConnectionResponse {
+                    from: rak.from.clone(),
+                    to: rak.to.clone(),
+                    distance: rak.distance,
+                })
+                .collect(),
+            polars: PolarResponse {
+                wind_speeds: data.polar_data.wind_speeds.clone(),
+                wind_angles: data.polar_data.wind_angles.clone(),
+                boat_speeds: data.polar_data.boat_speeds.clone(),
+            },
+            wind: WindResponse::forecast(&data.wind_data),
+        }
+    }
+}
+
+/// Response of the version endpoint
+#[derive(Clone, Debug, Serialize)]
+pub struct VersionResponse {
+    pub version: String,
+}
+
+/// Response of the health endpoint
+#[derive(Clone, Debug, Serialize)]
+pub struct HealthResponse {
+    pub status: String,
+    pub timestamp: String,
+}
+
+/// Body of every error response
+#[derive(Clone, Debug, Serialize)]
+pub struct ErrorResponse {
+    pub status: u16,
+    pub error: String,
+    pub message: String,
+}
+
+/// Build the OpenAPI document describing the HTTP API
+pub fn openapi_document() -> serde_json::Value {
+    let number = json!({ "type": "number" });
+    let integer = json!({ "type": "integer", "minimum": 0 });
+    let string = json!({ "type": "string" });
+    // Times are hours since the race start, a clock time "HH:MM", or a date
+    // and time "YYYY-MM-DD HH:MM" if the server knows the date of the start
+    let race_time = json!({ "oneOf": [{ "type": "number" }, { "type": "string", "example": "07:40" }] });
+    // The buoys as the data files describe them, with what they leave out null
+    let buoy = json!({
+        "type": "object",
+        "properties": {
+            "name": string,
+            "buoy_type": { "type": "string", "nullable": true },
+            "description": { "type": "string", "nullable": true },
+            "lat": { "type": "number", "nullable": true },
+            "long": { "type": "number", "nullable": true }
+        }
+    });
+
+    let query_param = |name: &str, schema: &serde_json::Value, required: bool, description: &str| {
+        json!({
+            "name": name,
+            "in": "query",
+            "required": required,
+            "description": description,
+            "schema": schema
+        })
+    };
+    let json_response = |description: &str, schema: &str| {
+        json!({
+            "description": description,
+            "content": {
+                "application/json": {
+                    "schema": { "$ref": format!("#/components/schemas/{schema}") }
+                }
+            }
+        })
+    };
+    let file_response = |description: &str, content_type: &str| {
+        json!({
+            "description": description,
+            "content": { content_type: { "schema": { "type": "string" } } }
+        })
+    };
+    let error_response = json_response("Error", "ErrorResponse");
+    let id_param = json!({
+        "name": "id",
+        "in": "path",
+        "required": true,
+        "schema": integer
+    });
+
+    let name_param = json!({
+        "name": "name",
+        "in": "path",
+        "required": true,
+        "schema": string
+    });
+
+    let mut paths = json!({
+        "/version": {
+            "get": {
+                "summary": "Program version",
+                "responses": { "200": json_response("Version", "VersionResponse") }
+            }
+        },
+        "/health": {
+            "get": {
+                "summary": "Health check",
+                "responses": { "200": json_response("Health", "HealthResponse") }
+            }
+        },
+        "/api/data": {
+            "get": {
+                "summary": "Loaded regatta data: buoys, starts, legs, polars, and wind forecast",
+                "responses": {
+                    "200": json_response("Regatta data", "DataResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/warnings": {
+            "get": {
+                "summary": "Suspicious findings in the loaded data: duplicate or unused buoys, and distances of starts and legs far off the buoys",
+                "responses": {
+                    "200": {
+                        "description": "Data warnings, empty if there are none",
+                        "content": {
+                            "application/json": {
+                                "schema": { "type": "array", "items": { "$ref": "#/components/schemas/DataWarningResponse" } }
+                            }
+                        }
+                    },
+                    "default": error_response
+                }
+            }
+        },
+        "/api/estimate": {
+            "get": {
+                "summary": "Estimate boat performance between two buoys",
+                "parameters": [
+                    query_param("from", &string, true, "Starting buoy name"),
+                    query_param("to", &string, true, "Destination buoy name"),
+                    query_param("time", &race_time, true, "Time in hours after race start, HH:MM, or YYYY-MM-DD HH:MM")
+                ],
+                "responses": {
+                    "200": json_response("Estimate", "EstimateResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/estimateleg": {
+            "get": {
+                "summary": "Estimate boat performance for a start or leg, 422 with the legal destinations if there is none between the buoys",
+                "parameters": [
+                    query_param("from", &string, true, "Starting buoy name"),
+                    query_param("to", &string, true, "Destination buoy name"),
+                    query_param("reverse", &json!({ "type": "boolean" }), false, "Sail the leg in reverse direction"),
+                    query_param("time", &race_time, true, "Time in hours after race start, HH:MM, or YYYY-MM-DD HH:MM")
+                ],
+                "responses": {
+                    "200": json_response("Estimate", "EstimateResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/find-paths": {
+            "get": {
+                "summary": "Find all paths with a number of steps from a starting buoy",
+                "parameters": [
+                    query_param("start", &string, true, "Starting buoy name"),
+                    query_param("time", &race_time, true, "Starting time in hours after race start, HH:MM, or YYYY-MM-DD HH:MM"),
+                    query_param("steps", &integer, true, "Number of steps"),
+                    query_param("max_paths", &integer, false, "Maximum number of paths, counting only the paths passing the filters"),
+                    query_param("include", &string, false, "Only paths starting at, rounding, or ending at this buoy"),
+                    query_param("finish", &string, false, "Only paths ending at this buoy"),
+                    query_param("sort", &json!({ "type": "string", "enum": ["distance", "end_time", "speed"] }), false, "Order of the paths: longest, earliest finishing, or fastest first; the order of the search by default")
+                ],
+                "responses": {
+                    "200": json_response("Paths", "FindPathsResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/find-paths.html": {
+            "get": {
+                "summary": "Paths from a starting buoy as HTML table for the web interface, with the parameters of /api/find-paths",
+                "parameters": [
+                    query_param("start", &string, true, "Starting buoy name"),
+                    query_param("time", &race_time, true, "Starting time in hours after race start, HH:MM, or YYYY-MM-DD HH:MM"),
+                    query_param("steps", &integer, true, "Number of steps"),
+                    query_param("max_paths", &integer, false, "Maximum number of paths, counting only the paths passing the filters"),
+                    query_param("include", &string, false, "Only paths starting at, rounding, or ending at this buoy"),
+                    query_param("finish", &string, false, "Only paths ending at this buoy"),
+                    query_param("sort", &json!({ "type": "string", "enum": ["distance", "end_time", "speed"] }), false, "Order of the paths: longest, earliest finishing, or fastest first; the order of the search by default")
+                ],
+                "responses": {
+                    "200": file_response("Table of the paths", "text/html"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/find-targets": {
+            "get": {
+                "summary": "Find paths from a starting buoy to a target buoy",
+                "parameters": [
+                    query_param("start", &string, true, "Starting buoy name"),
+                    query_param("target", &string, true, "Target buoy name"),
+                    query_param("time", &race_time, true, "Starting time in hours after race start, HH:MM, or YYYY-MM-DD HH:MM"),
+                    query_param("steps", &integer, true, "Maximum number of steps"),
+                    query_param("max_paths", &integer, false, "Maximum number of paths")
+                ],
+                "responses": {
+                    "200": json_response("Paths", "FindPathsResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/find-targets.csv": {
+            "get": {
+                "summary": "Paths from a starting buoy to a target buoy as CSV leg table",
+                "parameters": [
+                    query_param("start", &string, true, "Starting buoy name"),
+                    query_param("target", &string, true, "Target buoy name"),
+                    query_param("time", &race_time, true, "Starting time in hours after race start, HH:MM, or YYYY-MM-DD HH:MM"),
+                    query_param("steps", &integer, true, "Maximum number of steps"),
+                    query_param("max_paths", &integer, false, "Maximum number of paths")
+                ],
+                "responses": {
+                    "200": file_response("One row per step of every path", "text/csv"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/plan": {
+            "post": {
+                "summary": "Plan paths with all search options in the body",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/PlanRequest" }
+                        }
+                    }
+                },
+                "responses": {
+                    "200": json_response("Plan", "PlanResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/jobs": {
+            "post": {
+                "summary": "Start a plan search in the background",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/PlanRequest" }
+                        }
+                    }
+                },
+                "responses": {
+                    "202": json_response("Job created", "JobCreatedResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/jobs/{id}": {
+            "get": {
+                "summary": "Status, progress, and result of a background search",
+                "parameters": [id_param],
+                "responses": {
+                    "200": json_response("Job", "JobResponse"),
+                    "default": error_response
+                }
+            },
+            "delete": {
+                "summary": "Cancel a background search",
+                "parameters": [id_param],
+                "responses": {
+                    "200": json_response("Job cancelled", "JobCreatedResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/sessions": {
+            "get": {
+                "summary": "List planning sessions",
+                "responses": {
+                    "200": {
+                        "description": "Sessions ordered by id",
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "array",
+                                    "items": { "$ref": "#/components/schemas/Session" }
+                                }
+                            }
+                        }
+                    },
+                    "default": error_response
+                }
+            },
+            "post": {
+                "summary": "Create a planning session",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/SessionInput" }
+                        }
+                    }
+                },
+                "responses": {
+                    "201": json_response("Session created", "Session"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/sessions/{id}": {
+            "get": {
+                "summary": "Get a planning session",
+                "parameters": [id_param],
+                "responses": {
+                    "200": json_response("Session", "Session"),
+                    "default": error_response
+                }
+            },
+            "put": {
+                "summary": "Replace the plan of a planning session",
+                "parameters": [id_param],
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/SessionInput" }
+                        }
+                    }
+                },
+                "responses": {
+                    "200": json_response("Session", "Session"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/reload": {
+            "post": {
+                "summary": "Reload data files and templates, only allowed from the server machine",
+                "responses": {
+                    "200": json_response("Reloaded", "ReloadResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/course": {
+            "post": {
+                "summary": "Move a buoy or change how often a start or leg may be sailed, allowed like reloading",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/CourseUpdate" }
+                        }
+                    }
+                },
+                "responses": {
+                    "200": json_response("Course updated", "CourseUpdateResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/position": {
+            "post": {
+                "summary": "Report the current position of the boat",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/PositionReport" }
+                        }
+                    }
+                },
+                "responses": {
+                    "201": json_response("Position added", "PositionResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/observations": {
+            "get": {
+                "summary": "Leg speeds submitted by the crews, in the order they were submitted",
+                "responses": {
+                    "200": {
+                        "description": "Observations",
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "array",
+                                    "items": { "$ref": "#/components/schemas/LegObservation" }
+                                }
+                            }
+                        }
+                    },
+                    "default": error_response
+                }
+            },
+            "post": {
+                "summary": "Submit the speed actually achieved on a leg",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/ObservationReport" }
+                        }
+                    }
+                },
+                "responses": {
+                    "201": json_response("Observation added", "ObservationResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/calibration": {
+            "get": {
+                "summary": "Correction factors fitted to the observations and the ones in use",
+                "responses": {
+                    "200": json_response("Calibration", "CalibrationResponse"),
+                    "default": error_response
+                }
+            },
+            "post": {
+                "summary": "Fit the correction factors again and apply them, only allowed from the server machine",
+                "responses": {
+                    "200": json_response("Calibrated", "CalibrationResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/track": {
+            "get": {
+                "summary": "Reported positions ordered by race time",
+                "parameters": [
+                    query_param("since", &number, false, "Only points at or after this time in hours after race start")
+                ],
+                "responses": {
+                    "200": json_response("Track", "TrackResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/dashboard": {
+            "get": {
+                "summary": "Wind, active leg, ETA, and recommended continuation at the latest reported position",
+                "parameters": [
+                    query_param("from", &string, false, "Buoy the current leg started at, guessed from the track if missing"),
+                    query_param("to", &string, false, "Next buoy, guessed from the track if missing"),
+                    query_param("steps", &integer, false, "Steps of the recommended continuation (default: 3)"),
+                    query_param("objective", &string, false, "Objective as for /api/plan (default: max_distance)")
+                ],
+                "responses": {
+                    "200": json_response("Dashboard", "DashboardResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/route-profile.svg": {
+            "get": {
+                "summary": "Distance/time and speed chart for a route",
+                "parameters": [
+                    query_param("path", &string, true, "Comma separated buoy names"),
+                    query_param("time", &race_time, false, "Starting time in hours after race start, HH:MM, or YYYY-MM-DD HH:MM")
+                ],
+                "responses": {
+                    "200": file_response("Route profile chart", "image/svg+xml"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/route-map.svg": {
+            "get": {
+                "summary": "Course map with a route drawn over it, its legs numbered in the order they are sailed",
+                "parameters": [
+                    query_param("path", &string, true, "Comma separated buoy names"),
+                    query_param("time", &race_time, false, "Starting time in hours after race start, HH:MM, or YYYY-MM-DD HH:MM")
+                ],
+                "responses": {
+                    "200": file_response("Course map with the route", "image/svg+xml"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/route.gpx": {
+            "get": {
+                "summary": "Route as GPX file for chartplotters",
+                "parameters": [
+                    query_param("path", &string, true, "Comma separated buoy names"),
+                    query_param("time", &race_time, false, "Starting time in hours after race start, HH:MM, or YYYY-MM-DD HH:MM")
+                ],
+                "responses": {
+                    "200": file_response("GPX 1.1 route with one point per buoy", "application/gpx+xml"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/route.geojson": {
+            "get": {
+                "summary": "Route as GeoJSON for web maps",
+                "parameters": [
+                    query_param("path", &string, true, "Comma separated buoy names"),
+                    query_param("time", &race_time, false, "Starting time in hours after race start, HH:MM, or YYYY-MM-DD HH:MM")
+                ],
+                "responses": {
+                    "200": file_response("GeoJSON feature collection with a line string per leg", "application/geo+json"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/progress": {
+            "get": {
+                "summary": "Progress against the plan of a planning session at the latest reported position",
+                "parameters": [
+                    query_param("session", &integer, false, "Planning session with the plan, by default the one of the boat or the server")
+                ],
+                "responses": {
+                    "200": json_response("Progress", "ProgressResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/boats": {
+            "get": {
+                "summary": "List the boats of the fleet",
+                "responses": {
+                    "200": {
+                        "description": "Boats ordered by name",
+                        "content": {
+                            "application/json": {
+                                "schema": {
+                                    "type": "array",
+                                    "items": { "$ref": "#/components/schemas/BoatResponse" }
+                                }
+                            }
+                        }
+                    },
+                    "default": error_response
+                }
+            },
+            "post": {
+                "summary": "Register a boat, or replace the polars, class, and session of a registered one",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/BoatRegistration" }
+                        }
+                    }
+                },
+                "responses": {
+                    "201": json_response("Boat registered", "BoatResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/boats/{name}": {
+            "delete": {
+                "summary": "Remove a boat from the fleet",
+                "parameters": [name_param],
+                "responses": {
+                    "200": json_response("Boat removed", "BoatResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/fleet.svg": {
+            "get": {
+                "summary": "Course map with the tracks and latest positions of all boats",
+                "responses": {
+                    "200": file_response("Fleet map", "image/svg+xml"),
+                    "default": error_response
+                }
+            }
+        }
+    });
+
+    // Endpoints of the simulator and the virtual regatta
+    let training_paths = json!({
+        "/api/simulate": {
+            "post": {
+                "summary": "Simulate a route in fixed time steps, as JSON or GeoJSON track for animations",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/SimulateRequest" }
+                        }
+                    }
+                },
+                "responses": {
+                    "200": {
+                        "description": "Simulated track",
+                        "content": {
+                            "application/json": {
+                                "schema": { "$ref": "#/components/schemas/SimulationResponse" }
+                            },
+                            "application/geo+json": {
+                                "schema": { "type": "object", "description": "Feature collection with the track and the roundings" }
+                            }
+                        }
+                    },
+                    "default": error_response
+                }
+            }
+        },
+        "/api/training": {
+            "post": {
+                "summary": "Start a training game at a start buoy",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/TrainingStart" }
+                        }
+                    }
+                },
+                "responses": {
+                    "201": json_response("Game started", "TrainingResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/training/{id}": {
+            "get": {
+                "summary": "Get a training game with the legs to choose from",
+                "parameters": [id_param],
+                "responses": {
+                    "200": json_response("Game", "TrainingResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/training/{id}/legs": {
+            "post": {
+                "summary": "Sail the next leg of a training game in simulated time",
+                "parameters": [id_param],
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": {
+                                "type": "object",
+                                "required": ["to"],
+                                "properties": { "to": { "type": "string", "description": "Buoy at the end of the leg" } }
+                            }
+                        }
+                    }
+                },
+                "responses": {
+                    "200": json_response("Game with the simulation of the leg in samples", "TrainingResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/course.svg": {
+            "get": {
+                "summary": "Course map with a group per buoy to click on, id buoy-INDEX and data-buoy NAME",
+                "responses": {
+                    "200": file_response("Course map", "image/svg+xml"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/explore": {
+            "post": {
+                "summary": "Start exploring routes at a buoy",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/ExplorationStart" }
+                        }
+                    }
+                },
+                "responses": {
+                    "201": json_response("Exploration started", "ExplorationResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/explore/{id}": {
+            "get": {
+                "summary": "Get an exploration with the estimates of the legs which may be added next",
+                "parameters": [id_param],
+                "responses": {
+                    "200": json_response("Exploration", "ExplorationResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/explore/{id}/legs": {
+            "post": {
+                "summary": "Add the leg from the last buoy of the route to another one",
+                "parameters": [id_param],
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": {
+                                "type": "object",
+                                "required": ["to"],
+                                "properties": { "to": { "type": "string", "description": "Buoy at the end of the leg" } }
+                            }
+                        }
+                    }
+                },
+                "responses": {
+                    "200": json_response("Exploration with the leg added", "ExplorationResponse"),
+                    "default": error_response
+                }
+            },
+            "delete": {
+                "summary": "Take back the last leg of the route",
+                "parameters": [id_param],
+                "responses": {
+                    "200": json_response("Exploration without its last leg", "ExplorationResponse"),
+                    "default": error_response
+                }
+            }
+        }
+    });
+    paths
+        .as_object_mut()
+        .unwrap()
+        .extend(training_paths.as_object().unwrap().clone());
+    let rule_paths = json!({
+        "/api/validate-route": {
+            "post": {
+                "summary": "Check a planned or sailed route against every rule of the race",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/ValidateRouteRequest" }
+                        }
+                    }
+                },
+                "responses": {
+                    "200": json_response("The violated rules, none for a valid route", "ValidateRouteResponse"),
+                    "default": error_response
+                }
+            }
+        }
+    });
+    paths
+        .as_object_mut()
+        .unwrap()
+        .extend(rule_paths.as_object().unwrap().clone());
+    // Endpoints on the graph of starts and legs of the course
+    let graph_paths = json!({
+        "/api/neighbors": {
+            "get": {
+                "summary": "Buoys reached from a buoy by a single start or leg, ordered by name",
+                "parameters": [
+                    query_param("buoy", &string, true, "Buoy name")
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Starts and legs from the buoy",
+                        "content": {
+                            "application/json": {
+                                "schema": { "type": "array", "items": { "$ref": "#/components/schemas/ConnectionResponse" } }
+                            }
+                        }
+                    },
+                    "default": error_response
+                }
+            }
+        }
+    });
+    paths
+        .as_object_mut()
+        .unwrap()
+        .extend(graph_paths.as_object().unwrap().clone());
+    let wind_paths = json!({
+        "/api/wind": {
+            "post": {
+                "summary": "Replace the forecast wind of race hours with what is seen on deck, allowed like reloading; a reload restores the forecast of the files",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/WindOverride" }
+                        }
+                    }
+                },
+                "responses": {
+                    "200": json_response("Forecast changed", "WindOverrideResponse"),
+                    "default": error_response
+                }
+            }
+        }
+    });
+    paths
+        .as_object_mut()
+        .unwrap()
+        .extend(wind_paths.as_object().unwrap().clone());
+    let plan_paths = json!({
+        "/api/plans": {
+            "post": {
+                "summary": "Save a route under a short id, for a link to its read-only page /plans/ID which opens without a token",
+                "requestBody": {
+                    "required": true,
+                    "content": {
+                        "application/json": {
+                            "schema": { "$ref": "#/components/schemas/PlanSave" }
+                        }
+                    }
+                },
+                "responses": {
+                    "201": json_response("Plan saved", "SavedPlanResponse"),
+                    "default": error_response
+                }
+            }
+        }
+    });
+    paths
+        .as_object_mut()
+        .unwrap()
+        .extend(plan_paths.as_object().unwrap().clone());
+    let event_paths = json!({
+        "/api/events": {
+            "get": {
+                "summary": "Server-sent events: wind when the forecast is changed, data when it is reloaded, the course changed, or recalibrated, position when a boat reports one, and recommendation when the active leg of the own boat or the best continuation after it changes",
+                "parameters": [
+                    query_param("token", &string, false, "The access token, for browsers whose EventSource can't send the Authorization header")
+                ],
+                "responses": {
+                    "200": {
+                        "description": "Endless stream of events, their data as JSON",
+                        "content": {
+                            "text/event-stream": {
+                                "schema": { "$ref": "#/components/schemas/ServerEvent" }
+                            }
+                        }
+                    },
+                    "default": error_response
+                }
+            }
+        }
+    });
+    paths
+        .as_object_mut()
+        .unwrap()
+        .extend(event_paths.as_object().unwrap().clone());
+    let leaderboard_paths = json!({
+        "/api/leaderboard": {
+            "get": {
+                "summary": "Rank the boats of the fleet by the miles scored on their tracks so far, corrected by their ratings",
+                "responses": {
+                    "200": json_response("Boats ranked by their corrected miles", "LeaderboardResponse"),
+                    "default": error_response
+                }
+            }
+        }
+    });
+    paths
+        .as_object_mut()
+        .unwrap()
+        .extend(leaderboard_paths.as_object().unwrap().clone());
+    let approach_paths = json!({
+        "/api/finish-approach": {
+            "get": {
+                "summary": "Latest departure from every buoy which still reaches the finish before the deadline, searched backwards in time from the finish",
+                "parameters": [
+                    query_param("time", &race_time, false, "Departures from this time on, in hours after race start, HH:MM, or YYYY-MM-DD HH:MM; by default from the latest reported position, measuring its leg against them")
+                ],
+                "responses": {
+                    "200": json_response("Points of no return of the buoys, latest first", "FinishApproachResponse"),
+                    "default": error_response
+                }
+            }
+        },
+        "/api/finish-approach.svg": {
+            "get": {
+                "summary": "Course map with the point of no return of every buoy under its name",
+                "parameters": [
+                    query_param("time", &race_time, false, "Departures from this time on, in hours after race start, HH:MM, or YYYY-MM-DD HH:MM; by default from the latest reported position")
+                ],
+                "responses": {
+                    "200": file_response("Course map with the latest departures", "image/svg+xml"),
+                    "default": error_response
+                }
+            }
+        }
+    });
+    paths
+        .as_object_mut()
+        .unwrap()
+        .extend(approach_paths.as_object().unwrap().clone());
+    let profile_paths = json!({
+        "/api/arrival-profile": {
+            "get": {
+                "summary": "Earliest arrival at every buoy from a start as a function of the departure time, evaluated every interval over a period",
+                "parameters": [
+                    query_param("start", &string, true, "Buoy to depart from"),
+                    query_param("from", &race_time, false, "First departure, in hours after race start, HH:MM, or YYYY-MM-DD HH:MM; race start by default"),
+                    query_param("until", &race_time, false, "Last departure; the end of the race by default"),
+                    query_param("interval", &json!({ "type": "number", "minimum": 5 }), false, "Minutes between the departures, 30 by default"),
+                    query_param("avoid", &string, false, "Comma separated buoys not to visit"),
+                    query_param("avoid_leg", &string, false, "Comma separated legs FROM-TO not to sail")
+                ],
+                "responses": {
+                    "200": json_response("Earliest arrivals at the buoys, one per departure", "ArrivalProfileResponse"),
+                    "default": error_response
+                }
+            }
+        }
+    });
+    paths
+        .as_object_mut()
+        .unwrap()
+        .extend(profile_paths.as_object().unwrap().clone());
+
+    // The planning and live endpoints answer for a boat of the fleet if asked to
+    let boat_param = query_param(
+        "boat",
+        &string,
+        false,
+        "Boat of the fleet to answer for, with its polars and track",
+    );
+    for path in [
+        "/api/data",
+        "/api/estimate",
+        "/api/estimateleg",
+        "/api/find-paths",
+        "/api/find-targets",
+        "/api/find-targets.csv",
+        "/api/find-paths.html",
+        "/api/plan",
+        "/api/plans",
+        "/api/jobs",
+        "/api/position",
+        "/api/track",
+        "/api/dashboard",
+        "/api/finish-approach",
+        "/api/finish-approach.svg",
+        "/api/arrival-profile",
+        "/api/progress",
+        "/api/route-profile.svg",
+        "/api/route-map.svg",
+        "/api/route.gpx",
+        "/api/route.geojson",
+        "/api/simulate",
+        "/api/validate-route",
+    ] {
+        for operation in paths[path].as_object_mut().unwrap().values_mut() {
+            let parameters = operation
+                .as_object_mut()
+                .unwrap()
+                .entry("parameters")
+                .or_insert_with(|| json!([]));
+            parameters.as_array_mut().unwrap().push(boat_param.clone());
+        }
+    }
+
+    // The estimates describe the point of sail in the language asked for,
+    // by this parameter or else by the Accept-Language header
+    let lang_param = query_param(
+        "lang",
+        &json!({ "type": "string", "enum": ["en", "nl"] }),
+        false,
+        "Language of the interpretation",
+    );
+    for path in ["/api/estimate", "/api/estimateleg"] {
+        paths[path]["get"]["parameters"].as_array_mut().unwrap().push(lang_param.clone());
+    }
+
+    // The searches keep clear of the buoys and legs to avoid, e.g. shallow
+    // banks at low water, without changing the data
+    let avoid_params = [
+        query_param("avoid", &string, false, "Comma separated buoys the paths must not visit"),
+        query_param("avoid_leg", &string, false, "Comma separated legs FROM-TO the paths must not sail in either direction"),
+    ];
+    for path in ["/api/find-paths", "/api/find-paths.html", "/api/find-targets", "/api/find-targets.csv", "/api/dashboard"] {
+        paths[path]["get"]["parameters"].as_array_mut().unwrap().extend(avoid_params.iter().cloned());
+    }
+
+    // The charts are drawn in the theme asked for, by this parameter or else
+    // by the cookie of the pages
+    let theme_param = query_param(
+        "theme",
+        &json!({ "type": "string", "enum": ["light", "dark"] }),
+        false,
+        "Colors of the chart, dark in shades of red for reading at night",
+    );
+    for path in [
+        "/api/route-profile.svg",
+        "/api/route-map.svg",
+        "/api/fleet.svg",
+        "/api/course.svg",
+        "/api/finish-approach.svg",
+    ] {
+        let operation = paths[path]["get"].as_object_mut().unwrap();
+        let parameters = operation.entry("parameters").or_insert_with(|| json!([]));
+        parameters.as_array_mut().unwrap().push(theme_param.clone());
+    }
+
+    let mut schemas = json!({
+        "VersionResponse": {
+            "type": "object",
+            "properties": { "version": string }
+        },
+        "HealthResponse": {
+            "type": "object",
+            "properties": { "status": string, "timestamp": string }
+        },
+        "DataWarningResponse": {
+            "type": "object",
+            "properties": {
+                "kind": {
+                    "type": "string",
+                    "enum": ["duplicate_buoy", "unknown_buoy", "missing_coordinates", "unused_buoy", "zero_distance", "distance_mismatch"]
+                },
+                "message": string,
+                "name": string,
+                "file": string,
+                "from": string,
+                "to": string,
+                "distance": number,
+                "great_circle": number
+            },
+            "required": ["kind", "message"]
+        },
+        "DataResponse": {
+            "type": "object",
+            "properties": {
+                "buoys": { "type": "array", "items": buoy },
+                "starts": { "type": "array", "items": { "$ref": "#/components/schemas/ConnectionResponse" } },
+                "legs": { "type": "array", "items": { "$ref": "#/components/schemas/ConnectionResponse" } },
+                "polars": {
+                    "type": "object",
+                    "properties": {
+                        "wind_speeds": { "type": "array", "items": number },
+                        "wind_angles": { "type": "array", "items": number },
+                        "boat_speeds": { "type": "array", "items": { "type": "array", "items": number } }
+                    }
+                },
+                "wind": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": { "time": integer, "wind_speed": number, "wind_angle": number }
+                    }
+                }
+            }
+        },
+        "ConnectionResponse": {
+            "type": "object",
+            "properties": { "from": string, "to": string, "distance": number }
+        },
+        "EstimateResponse": {
+            "type": "object",
+            "properties": {
+                "from": string,
+                "to": string,
+                "time": number,
+                "clock": { "type": "string", "nullable": true },
+                "estimated_speed": number,
+                "polar_speed": number,
+                "course_bearing": number,
+                "wind_direction": number,
+                "relative_bearing": number,
+                "wind_speed": number,
+                "official_distance": { "type": "number", "nullable": true },
+                "great_circle_distance": number,
+                "travel_time": number,
+                "arrival_time": number,
+                "arrival_clock": { "type": "string", "nullable": true },
+                "point_of_sail": {
+                    "type": "string",
+                    "enum": ["close_hauled", "close_reach", "beam_reach", "broad_reach", "downwind"]
+                },
+                "interpretation": { "type": "string", "description": "The point of sail in the language of the request" }
+            }
+        },
+        "StepResponse": {
+            "type": "object",
+            "properties": {
+                "from": integer,
+                "to": integer,
+                "from_name": string,
+                "to_name": string,
+                "distance": number,
+                "speed": number,
+                "start_time": number,
+                "end_time": number,
+                "start_clock": { "type": "string", "nullable": true },
+                "end_clock": { "type": "string", "nullable": true },
+                "wait": { "type": "number", "description": "Hours waited at the buoy before start_time" }
+            }
+        },
+        "PathResponse": {
+            "type": "object",
+            "properties": {
+                "steps": {
+                    "type": "array",
+                    "items": { "$ref": "#/components/schemas/StepResponse" }
+                },
+                "total_distance": number,
+                "scored_distance": { "type": "number", "description": "Miles counted by the scoring rules, ranks the paths of max_distance" },
+                "qualification_margin": { "type": "number", "nullable": true, "description": "Scored miles above the qualification distance of the rules, negative if short of it, null without a minimum" },
+                "end_time": number,
+                "end_clock": { "type": "string", "nullable": true },
+                "average_speed": { "type": "number", "description": "Knots from the start to the end of the path, waits included" }
+            }
+        },
+        "FindPathsResponse": {
+            "type": "object",
+            "properties": {
+                "start": string,
+                "target": string,
+                "start_time": number,
+                "steps": integer,
+                "truncated": { "type": "boolean", "description": "The search stopped at max_paths or its budget, there are more paths" },
+                "stats": { "$ref": "#/components/schemas/SearchStats" },
+                "paths": {
+                    "type": "array",
+                    "items": { "$ref": "#/components/schemas/PathResponse" }
+                }
+            }
+        },
+        "SearchStats": {
+            "type": "object",
+            "properties": {
+                "nodes_expanded": integer,
+                "resident_paths": integer,
+                "memory_estimate": { "type": "integer", "description": "Bytes of the paths kept while searching" },
+                "exhausted": {
+                    "type": "string",
+                    "nullable": true,
+                    "enum": ["nodes", "paths", "memory"],
+                    "description": "The part of the server's search budget which stopped the search"
+                },
+                "pruned": {
+                    "type": "object",
+                    "description": "Branches the search cut off, by the reason",
+                    "properties": {
+                        "constraints": { "type": "integer", "description": "Legs to buoys or over legs the constraints exclude" },
+                        "leg_limit": { "type": "integer", "description": "Starts and legs sailed as often as the rules allow already" },
+                        "forbidden_sequence": { "type": "integer", "description": "Legs completing a sequence of buoys the rules forbid" },
+                        "no_estimate": { "type": "integer", "description": "Legs without a speed estimate, e.g. to buoys without coordinates" },
+                        "pins": { "type": "integer", "description": "Branches which can't meet the pins any more" },
+                        "unmatched": { "type": "integer", "description": "Complete paths missing the filter or a pin" }
+                    }
+                },
+                "cache_hits": { "type": "integer", "description": "Hop searches of a hierarchical plan answered from the ones done before" },
+                "cache_misses": integer,
+                "elapsed_ms": { "type": "number", "description": "Wall clock time of the search in milliseconds" }
+            }
+        },
+        "PlanRequest": {
+            "type": "object",
+            "required": ["start", "start_time", "steps"],
+            "properties": {
+                "start": string,
+                "target": string,
+                "start_time": race_time,
+                "steps": integer,
+                "constraints": {
+                    "type": "object",
+                    "properties": {
+                        "forbidden_buoys": { "type": "array", "items": string },
+                        "forbidden_legs": {
+                            "type": "array",
+                            "items": { "$ref": "#/components/schemas/Leg" }
+                        }
+                    }
+                },
+                "pins": {
+                    "type": "array",
+                    "description": "Buoys, or legs to them, the paths must pass in this order",
+                    "items": { "$ref": "#/components/schemas/Pin" }
+                },
+                "strategy": {
+                    "type": "string",
+                    "enum": ["exhaustive", "hierarchical"],
+                    "description": "exhaustive (default) searches all paths; hierarchical chooses a skeleton of key marks first and fills in the legs between them, for routes of up to the route length of the rules without pins"
+                },
+                "objective": {
+                    "type": "string",
+                    "description": "earliest_arrival (default), max_distance, qualify, max_margin, or weighted:MEASURE=WEIGHT,... with the measures scored_distance, total_distance, margin, and average_speed",
+                    "example": "weighted:scored_distance=1,margin=2"
+                },
+                "max_results": integer,
+                "max_paths": integer,
+                "max_wait": { "type": "number", "description": "Hours the search may wait at a buoy when the next leg then finishes earlier, 0 by default" },
+                "diversity": { "type": "number", "description": "Jaccard distance of their legs every path returned keeps from the better ones, between 0 (default) and 1" }
+            }
+        },
+        "PlanResponse": {
+            "type": "object",
+            "properties": {
+                "start": string,
+                "target": { "type": "string", "nullable": true },
+                "start_time": number,
+                "steps": integer,
+                "strategy": string,
+                "objective": string,
+                "paths_found": integer,
+                "truncated": { "type": "boolean", "description": "The search stopped at max_paths or its budget, there are more paths" },
+                "stats": { "$ref": "#/components/schemas/SearchStats" },
+                "paths": {
+                    "type": "array",
+                    "items": { "$ref": "#/components/schemas/PathResponse" }
+                }
+            }
+        },
+        "JobCreatedResponse": {
+            "type": "object",
+            "properties": {
+                "id": integer,
+                "status": { "type": "string", "enum": ["running", "completed", "failed", "cancelled"] }
+            }
+        },
+        "JobResponse": {
+            "type": "object",
+            "properties": {
+                "id": integer,
+                "status": { "type": "string", "enum": ["running", "completed", "failed", "cancelled"] },
+                "created": string,
+                "progress": {
+                    "type": "object",
+                    "properties": { "nodes_explored": integer, "paths_found": integer }
+                },
+                "result": { "allOf": [{ "$ref": "#/components/schemas/PlanResponse" }], "nullable": true },
+                "error": { "allOf": [{ "$ref": "#/components/schemas/ErrorResponse" }], "nullable": true }
+            }
+        },
+        "ReloadResponse": {
+            "type": "object",
+            "properties": {
+                "version": integer,
+                "buoys": integer,
+                "legs": integer,
+                "templates": integer
+            }
+        },
+        "CourseUpdate": {
+            "type": "object",
+            "required": ["kind"],
+            "properties": {
+                "kind": { "type": "string", "enum": ["move_buoy", "set_max_number"] },
+                "name": string,
+                "lat": number,
+                "long": number,
+                "from": string,
+                "to": string,
+                "max_number": integer
+            }
+        },
+        "CourseUpdateResponse": {
+            "type": "object",
+            "properties": { "version": integer, "cached_responses": integer }
+        },
+        "Leg": {
+            "type": "object",
+            "required": ["from", "to"],
+            "properties": { "from": string, "to": string }
+        },
+        "Pin": {
+            "type": "object",
+            "required": ["buoy"],
+            "properties": {
+                "buoy": string,
+                "from": { "type": "string", "description": "Pins the leg from this buoy to the buoy" },
+                "slot": { "type": "integer", "description": "Legs sailed before reaching the buoy, 0 for the start" },
+                "time": race_time,
+                "tolerance": { "type": "number", "description": "Hours the buoy may be reached before or after the time, 0.5 by default" }
+            }
+        },
+        "SessionInput": {
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": string,
+                "start": { "type": "string", "nullable": true },
+                "start_time": { "type": "number", "nullable": true },
+                "pinned_legs": { "type": "array", "items": { "$ref": "#/components/schemas/Leg" } },
+                "completed_legs": { "type": "array", "items": { "$ref": "#/components/schemas/Leg" } },
+                "notes": string
+            }
+        },
+        "Session": {
+            "allOf": [
+                { "$ref": "#/components/schemas/SessionInput" },
+                {
+                    "type": "object",
+                    "properties": {
+                        "id": integer,
+                        "created": { "type": "string", "format": "date-time" },
+                        "updated": { "type": "string", "format": "date-time" }
+                    }
+                }
+            ]
+        },
+        "ErrorResponse": {
+            "type": "object",
+            "properties": { "status": integer, "error": string, "message": string }
+        }
+    });
+
+    // Schemas of the live tracking and fleet endpoints
+    let live_schemas = json!({
+        "PositionReport": {
+            "type": "object",
+            "required": ["lat", "long", "time"],
+            "properties": {
+                "lat": { "type": "number", "description": "Latitude in decimal degrees" },
+                "long": { "type": "number", "description": "Longitude in decimal degrees" },
+                "time": { "type": "number", "description": "Time in hours after race start" },
+                "speed": { "type": "number", "description": "Speed over ground in knots" },
+                "course": { "type": "number", "description": "Course over ground in degrees" }
+            }
+        },
+        "PositionResponse": {
+            "type": "object",
+            "properties": { "points": integer }
+        },
+        "ObservationReport": {
+            "type": "object",
+            "required": ["from", "to", "time", "speed"],
+            "properties": {
+                "from": string,
+                "to": string,
+                "time": { "type": "number", "description": "Time in hours after race start" },
+                "speed": { "type": "number", "description": "Achieved speed along the leg in knots" },
+                "wind_direction": { "type": "number", "description": "Measured wind direction in degrees, with wind_speed" },
+                "wind_speed": { "type": "number", "description": "Measured wind speed in knots, with wind_direction" },
+                "boat": string
+            }
+        },
+        "LegObservation": {
+            "type": "object",
+            "properties": {
+                "from": string,
+                "to": string,
+                "time": number,
+                "speed": number,
+                "wind_direction": { "type": "number", "nullable": true },
+                "wind_speed": { "type": "number", "nullable": true },
+                "boat": { "type": "string", "nullable": true },
+                "received": { "type": "string", "format": "date-time" }
+            }
+        },
+        "ObservationResponse": {
+            "type": "object",
+            "properties": { "observations": integer }
+        },
+        "CalibrationResponse": {
+            "type": "object",
+            "properties": {
+                "version": integer,
+                "observations": integer,
+                "min_observations": integer,
+                "legs": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "from": string,
+                            "to": string,
+                            "observations": integer,
+                            "factor": { "type": "number", "nullable": true },
+                            "applied": number
+                        }
+                    }
+                }
+            }
+        },
+        "TrackPoint": {
+            "type": "object",
+            "properties": {
+                "lat": number,
+                "long": number,
+                "time": number,
+                "speed": { "type": "number", "nullable": true },
+                "course": { "type": "number", "nullable": true },
+                "received": { "type": "string", "format": "date-time" }
+            }
+        },
+        "TrackResponse": {
+            "type": "object",
+            "properties": {
+                "distance": number,
+                "points": { "type": "array", "items": { "$ref": "#/components/schemas/TrackPoint" } }
+            }
+        },
+        "DashboardResponse": {
+            "type": "object",
+            "properties": {
+                "time": number,
+                "clock": { "type": "string", "nullable": true },
+                "position": { "$ref": "#/components/schemas/TrackPoint" },
+                "wind_direction": number,
+                "wind_speed": number,
+                "measured_wind": {
+                    "type": "object",
+                    "nullable": true,
+                    "properties": {
+                        "direction": number,
+                        "speed": number,
+                        "time": number,
+                        "received": { "type": "string", "format": "date-time" }
+                    }
+                },
+                "distance_sailed": number,
+                "leg": {
+                    "type": "object",
+                    "properties": {
+                        "from": string,
+                        "to": string,
+                        "inferred": { "type": "boolean" },
+                        "distance_to_go": number,
+                        "course": number,
+                        "speed": number,
+                        "eta": { "type": "number", "nullable": true },
+                        "eta_clock": { "type": "string", "nullable": true }
+                    }
+                },
+                "objective": string,
+                "continuation": { "allOf": [{ "$ref": "#/components/schemas/PathResponse" }], "nullable": true }
+            }
+        },
+        "ProgressResponse": {
+            "type": "object",
+            "properties": {
+                "session": integer,
+                "time": number,
+                "clock": { "type": "string", "nullable": true },
+                "from": string,
+                "to": string,
+                "distance_along": number,
+                "total_distance": number,
+                "time_behind": { "type": "number", "description": "Hours behind the plan, negative when ahead" },
+                "planned_finish": number,
+                "projected_finish": { "type": "number", "nullable": true },
+                "projected_clock": { "type": "string", "nullable": true },
+                "late": { "type": "boolean", "description": "The projected finish is after the end of the race" },
+                "marks": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": { "buoy": string, "planned": number, "actual": { "type": "number", "nullable": true }, "delta": { "type": "number", "nullable": true } }
+                    }
+                }
+            }
+        },
+        "BoatRegistration": {
+            "type": "object",
+            "required": ["name"],
+            "properties": {
+                "name": { "type": "string", "description": "Letters, digits, '-' and '_', at most 32 characters" },
+                "polars": { "type": "string", "description": "Polar table in the format of data/polars.csv" },
+                "class": { "type": "string", "description": "Class of data/classes.csv, the race times of the boat count from its start" },
+                "session": { "type": "integer", "description": "Planning session holding the plan of the boat" }
+            }
+        },
+        "BoatResponse": {
+            "type": "object",
+            "properties": {
+                "name": string,
+                "custom_polars": { "type": "boolean" },
+                "class": { "type": "string", "nullable": true },
+                "session": { "type": "integer", "minimum": 0, "nullable": true },
+                "registered": { "type": "string", "format": "date-time" },
+                "points": integer,
+                "distance_sailed": number,
+                "position": { "allOf": [{ "$ref": "#/components/schemas/TrackPoint" }], "nullable": true }
+            }
+        },
+        "LeaderboardResponse": {
+            "type": "object",
+            "properties": {
+                "rated": { "type": "boolean" },
+                "boats": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "rank": integer,
+                            "boat": string,
+                            "legs": integer,
+                            "scored_distance": number,
+                            "corrected_distance": number,
+                            "rating": { "type": "number", "nullable": true },
+                            "last_mark": { "type": "string", "nullable": true },
+                            "last_rounding": { "type": "number", "nullable": true },
+                            "last_rounding_clock": { "type": "string", "nullable": true },
+                            "last_position": { "type": "number", "nullable": true }
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    // Schemas of the simulator and the virtual regatta
+    let sample = json!({
+        "type": "object",
+        "properties": {
+            "time": number,
+            "lat": number,
+            "long": number,
+            "leg": integer,
+            "speed": number,
+            "speed_over_ground": number,
+            "distance": number,
+            "wind_direction": number,
+            "wind_speed": number
+        }
+    });
+    let training_schemas = json!({
+        "SimulateRequest": {
+            "type": "object",
+            "required": ["route"],
+            "properties": {
+                "route": { "type": "array", "items": string, "description": "Buoy names in the order they are sailed" },
+                "time": race_time,
+                "step_minutes": { "type": "number", "description": "Length of a time step, 1 by default" },
+                "current": {
+                    "type": "object",
+                    "properties": {
+                        "direction": { "type": "number", "description": "Direction the water flows to in degrees" },
+                        "speed": { "type": "number", "description": "In knots" }
+                    }
+                },
+                "rounding_penalty": { "type": "number", "description": "Minutes lost at every mark rounding" },
+                "format": { "type": "string", "enum": ["json", "geojson"] }
+            }
+        },
+        "SimulationResponse": {
+            "type": "object",
+            "properties": {
+                "legs": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "from": string,
+                            "to": string,
+                            "distance": number,
+                            "start_time": number,
+                            "end_time": number,
+                            "start_clock": { "type": "string", "nullable": true },
+                            "end_clock": { "type": "string", "nullable": true }
+                        }
+                    }
+                },
+                "samples": { "type": "array", "items": sample },
+                "total_distance": number,
+                "end_time": number,
+                "end_clock": { "type": "string", "nullable": true }
+            }
+        },
+        "TrainingStart": {
+            "type": "object",
+            "required": ["start"],
+            "properties": {
+                "start": { "type": "string", "description": "Start buoy" },
+                "time": race_time
+            }
+        },
+        "TrainingResponse": {
+            "type": "object",
+            "properties": {
+                "id": integer,
+                "route": { "type": "array", "items": string },
+                "time": { "type": "number", "description": "Current time in hours after race start" },
+                "clock": { "type": "string", "nullable": true },
+                "legs": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "from": string,
+                            "to": string,
+                            "distance": number,
+                            "start_time": number,
+                            "end_time": number,
+                            "scored": { "type": "boolean", "description": "False if the leg ended after the end of the race" }
+                        }
+                    }
+                },
+                "finished": { "type": "boolean" },
+                "created": { "type": "string", "format": "date-time" },
+                "score": { "type": "number", "description": "Distance of the legs sailed within the race in nm" },
+                "deadline": number,
+                "wind_direction": number,
+                "wind_speed": number,
+                "choices": {
+                    "type": "array",
+                    "items": { "type": "object", "properties": { "to": string, "distance": number } }
+                },
+                "samples": {
+                    "type": "array",
+                    "nullable": true,
+                    "description": "Simulation of the leg just sailed",
+                    "items": sample
+                }
+            }
+        },
+        "ExplorationStart": {
+            "type": "object",
+            "required": ["start"],
+            "properties": {
+                "start": { "type": "string", "description": "Any buoy with legs" },
+                "time": race_time
+            }
+        },
+        "ExplorationResponse": {
+            "type": "object",
+            "properties": {
+                "id": integer,
+                "start_time": number,
+                "route": { "type": "array", "items": string },
+                "legs": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "from": string,
+                            "to": string,
+                            "distance": number,
+                            "speed": { "type": "number", "description": "Estimated speed in knots" },
+                            "start_time": number,
+                            "end_time": number
+                        }
+                    }
+                },
+                "created": { "type": "string", "format": "date-time" },
+                "clock": { "type": "string", "nullable": true },
+                "time": { "type": "number", "description": "The route reaches its last buoy, in hours after race start" },
+                "distance": { "type": "number", "description": "Distance of the legs of the route in nm" },
+                "deadline": number,
+                "wind_direction": number,
+                "wind_speed": number,
+                "options": {
+                    "type": "array",
+                    "description": "Legs which may be added next, estimated at the time of the last buoy",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "to": string,
+                            "distance": number,
+                            "speed": number,
+                            "travel_time": number,
+                            "arrival_time": number,
+                            "wind_direction": number,
+                            "wind_speed": number,
+                            "late": { "type": "boolean", "description": "The leg ends after the deadline" }
+                        }
+                    }
+                }
+            }
+        }
+    });
+    schemas
+        .as_object_mut()
+        .unwrap()
+        .extend(live_schemas.as_object().unwrap().clone());
+    schemas
+        .as_object_mut()
+        .unwrap()
+        .extend(training_schemas.as_object().unwrap().clone());
+    let rule_schemas = json!({
+        "ValidateRouteRequest": {
+            "type": "object",
+            "required": ["route"],
+            "properties": {
+                "route": { "type": "array", "items": string, "description": "Buoy names in the order they are sailed" },
+                "time": race_time,
+                "passing_times": {
+                    "type": "array",
+                    "items": race_time,
+                    "description": "When a sailed route passed each of its buoys; without them the route is sailed at the speeds of the polars from time"
+                }
+            }
+        },
+        "RuleViolation": {
+            "type": "object",
+            "properties": {
+                "leg": { "type": "integer", "nullable": true, "description": "Number of the leg breaking the rule from 1, null for the whole route" },
+                "rule": { "type": "string", "description": "Entry of the rule set or data file, e.g. rules.toml: forbidden_sequences[0]" },
+                "message": string
+            }
+        },
+        "ValidateRouteResponse": {
+            "type": "object",
+            "properties": {
+                "valid": { "type": "boolean" },
+                "edition": { "type": "integer", "nullable": true },
+                "path": { "$ref": "#/components/schemas/PathResponse" },
+                "violations": {
+                    "type": "array",
+                    "items": { "$ref": "#/components/schemas/RuleViolation" }
+                }
+            }
+        }
+    });
+    schemas
+        .as_object_mut()
+        .unwrap()
+        .extend(rule_schemas.as_object().unwrap().clone());
+    let wind_hour = json!({
+        "type": "object",
+        "required": ["time", "wind_speed", "wind_angle"],
+        "properties": {
+            "time": { "type": "integer", "minimum": 0, "description": "Hour of the forecast" },
+            "wind_speed": { "type": "number", "minimum": 0, "description": "Wind speed in knots" },
+            "wind_angle": { "type": "number", "minimum": 0, "maximum": 360, "description": "Wind direction in degrees" }
+        }
+    });
+    let wind_schemas = json!({
+        "WindOverride": {
+            "type": "object",
+            "required": ["wind"],
+            "properties": {
+                "wind": { "type": "array", "items": wind_hour, "description": "Hours of the forecast to replace, the others stay" }
+            }
+        },
+        "WindOverrideResponse": {
+            "type": "object",
+            "properties": {
+                "version": integer,
+                "hours": integer,
+                "wind": { "type": "array", "items": wind_hour }
+            }
+        }
+    });
+    schemas
+        .as_object_mut()
+        .unwrap()
+        .extend(wind_schemas.as_object().unwrap().clone());
+    let plan_schemas = json!({
+        "PlanSave": {
+            "type": "object",
+            "required": ["route"],
+            "properties": {
+                "name": { "type": "string", "description": "Name shown on the page, the buoys of the route by default" },
+                "route": { "type": "array", "items": string, "description": "Buoy names in the order they are sailed, at least two" },
+                "time": race_time,
+                "notes": string
+            }
+        },
+        "SavedPlanResponse": {
+            "type": "object",
+            "properties": {
+                "id": string,
+                "name": string,
+                "route": { "type": "array", "items": string },
+                "start_time": number,
+                "boat": { "type": "string", "nullable": true },
+                "notes": string,
+                "created": { "type": "string", "format": "date-time" },
+                "url": { "type": "string", "description": "Path of the read-only page of the plan" },
+                "path": { "$ref": "#/components/schemas/PathResponse" }
+            }
+        }
+    });
+    schemas
+        .as_object_mut()
+        .unwrap()
+        .extend(plan_schemas.as_object().unwrap().clone());
+    let event_schemas = json!({
+        "ServerEvent": {
+            "type": "object",
+            "properties": {
+                "type": { "type": "string", "enum": ["wind", "data", "position", "recommendation"] },
+                "version": { "type": "integer", "description": "Version of the data, for wind and data" },
+                "hours": { "type": "integer", "description": "Hours of the forecast replaced, for wind" },
+                "boat": { "type": "string", "nullable": true, "description": "Boat of the fleet, null for the own boat, for position" },
+                "time": number,
+                "lat": number,
+                "long": number,
+                "route": { "type": "array", "items": string, "description": "The active leg followed by the best continuation, for recommendation" },
+                "eta": { "type": "number", "nullable": true },
+                "clock": { "type": "string", "nullable": true }
+            }
+        }
+    });
+    schemas
+        .as_object_mut()
+        .unwrap()
+        .extend(event_schemas.as_object().unwrap().clone());
+    let approach_schemas = json!({
+        "FinishApproachResponse": {
+            "type": "object",
+            "properties": {
+                "time": number,
+                "clock": { "type": "string", "nullable": true },
+                "deadline": number,
+                "finish": buoy,
+                "hours": { "type": "array", "items": integer, "description": "Remaining full hours of the race" },
+                "buoys": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "buoy": string,
+                            "lat": { "type": "number", "nullable": true },
+                            "long": { "type": "number", "nullable": true },
+                            "latest": { "type": "number", "description": "Latest departure which still reaches the finish by the deadline" },
+                            "latest_clock": { "type": "string", "nullable": true },
+                            "next": { "type": "string", "description": "Buoy to sail to from it" }
+                        }
+                    }
+                },
+                "unreachable": { "type": "array", "items": buoy },
+                "position": {
+                    "type": "object",
+                    "nullable": true,
+                    "properties": {
+                        "lat": number,
+                        "long": number,
+                        "from": string,
+                        "to": string,
+                        "eta": { "type": "number", "nullable": true },
+                        "eta_clock": { "type": "string", "nullable": true },
+                        "margin": { "type": "number", "nullable": true, "description": "Hours from the ETA to the point of no return of the next buoy" }
+                    }
+                }
+            }
+        }
+    });
+    schemas
+        .as_object_mut()
+        .unwrap()
+        .extend(approach_schemas.as_object().unwrap().clone());
+    let profile_schemas = json!({
+        "ArrivalProfileResponse": {
+            "type": "object",
+            "properties": {
+                "start": string,
+                "interval": { "type": "number", "description": "Minutes between the departures" },
+                "departures": { "type": "array", "items": number },
+                "departure_clocks": { "type": "array", "items": { "type": "string", "nullable": true } },
+                "buoys": {
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "buoy": string,
+                            "lat": { "type": "number", "nullable": true },
+                            "long": { "type": "number", "nullable": true },
+                            "arrivals": { "type": "array", "items": { "type": "number", "nullable": true }, "description": "Earliest arrival per departure, null if it can't be reached" },
+                            "via": { "type": "array", "items": { "type": "string", "nullable": true }, "description": "Buoy the last leg comes from per departure" }
+                        }
+                    }
+                },
+                "unreachable": { "type": "array", "items": buoy }
+            }
+        }
+    });
+    schemas
+        .as_object_mut()
+        .unwrap()
+        .extend(profile_schemas.as_object().unwrap().clone());
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "uurs24",
+            "description": "24-hour regatta performance estimation and route planning",
+            "version": env!("CARGO_PKG_VERSION")
+        },
+        "paths": paths,
+        "security": [{ "bearerAuth": [] }, {}],
+        "components": {
+            "securitySchemes": {
+                "bearerAuth": {
+                    "type": "http",
+                    "scheme": "bearer",
+                    "description": "Required by the API endpoints if the server runs with an access token"
+                }
+            },
+            "schemas": schemas
+        }
+    })
+}
@@ -0,0 +1,122 @@
+use crate::data::RegattaData;
+use crate::optimize::estimate_leg_performance;
+use serde::Serialize;
+
+/// Name of the buoy of the finish line in the data
+pub const FINISH_BUOY: &str = "FINISH";
+
+/// Departures are tried in steps of a minute
+const TIME_STEP: f64 = 1.0 / 60.0;
+
+/// The point of no return of a buoy: the latest departure from it which still
+/// reaches the finish by the deadline
+#[derive(Clone, Debug, Serialize)]
+pub struct LatestDeparture {
+    pub buoy: usize,
+    pub latest: f64, // in hours since race start
+    pub next: usize, // buoy to sail to from it, the finish on the last leg
+}
+
+/// Search backwards in time from the finish for the latest departure from
+/// every buoy which still reaches it by the deadline, ordered latest first
+///
+/// The finish is reached over the legs of the course to it; if the data has
+/// none, as the finish line can be approached from anywhere, it is sailed to
+/// straight from every buoy with coordinates. Other buoys are reached over
+/// the legs of the course only. Departures are tried back from when the next
+/// buoy must be reached in steps of a minute, sailing off at once at the
+/// speed estimated at the departure as the path searches do. Buoys which
+/// can't reach the finish when leaving at `earliest` or later are left out.
+pub fn latest_departures(data: &RegattaData, finish: usize, deadline: f64, earliest: f64) -> Vec<LatestDeparture> {
+    let count = data.boeien.len();
+    let mut legs_into = vec![Vec::new(); count];
+    for rak in &data.rakken {
+        if let (Some(a), Some(b)) = (data.get_boei_index(&rak.from), data.get_boei_index(&rak.to)) {
+            legs_into[b].push(a);
+            legs_into[a].push(b);
+        }
+    }
+    if legs_into[finish].is_empty() {
+        legs_into[finish] = (0..count)
+            .filter(|&buoy| buoy != finish && data.boeien[buoy].has_coordinates())
+            .collect();
+    }
+
+    // Like Dijkstra's algorithm backwards: the buoy which can be left latest
+    // is settled first, departing later than it never reaches it in time
+    let mut latest: Vec<Option<(f64, usize)>> = vec![None; count];
+    latest[finish] = Some((deadline, finish));
+    let mut settled = vec![false; count];
+    while let Some((buoy, (by, _))) = (0..count)
+        .filter(|&buoy| !settled[buoy])
+        .filter_map(|buoy| latest[buoy].map(|departure| (buoy, departure)))
+        .max_by(|a, b| a.1.0.total_cmp(&b.1.0))
+    {
+        settled[buoy] = true;
+        for &from in &legs_into[buoy] {
+            if settled[from] {
+                continue;
+            }
+            let Some(departure) = latest_leg_departure(data, from, buoy, by, earliest) else {
+                continue;
+            };
+            if latest[from].is_none_or(|(known, _)| departure > known) {
+                latest[from] = Some((departure, buoy));
+            }
+        }
+    }
+
+    let mut departures: Vec<LatestDeparture> = latest
+        .into_iter()
+        .enumerate()
+        .filter(|&(buoy, _)| buoy != finish)
+        .filter_map(|(buoy, departure)| departure.map(|(latest, next)| LatestDeparture { buoy, latest, next }))
+        .collect();
+    departures.sort_by(|a, b| b.latest.total_cmp(&a.latest).then(a.buoy.cmp(&b.buoy)));
+    departures
+}
+
+/// Latest departure from a buoy, at `earliest` or later, which reaches the
+/// next one by a time
+fn latest_leg_departure(data: &RegattaData, from: usize, to: usize, by: f64, earliest: f64) -> Option<f64> {
+    (1..)
+        .map(|step| by - step as f64 * TIME_STEP)
+        .take_while(|departure| *departure >= earliest)
+        .find(|&departure| {
+            estimate_leg_performance(data, from, to, departure).is_ok_and(|leg| leg.arrival_time <= by)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latest_departures() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let finish = data.get_boei_index(FINISH_BUOY).unwrap();
+        let deadline = data.rules.scoring.deadline;
+        let departures = latest_departures(&data, finish, deadline, 0.0);
+        assert!(!departures.is_empty());
+        assert!(departures.windows(2).all(|pair| pair[0].latest >= pair[1].latest));
+
+        // Leaving at the point of no return makes it, a minute later doesn't
+        let mut reached = 0;
+        for departure in &departures {
+            let by = match departures.iter().find(|d| d.buoy == departure.next) {
+                Some(next) => next.latest,
+                None => deadline,
+            };
+            let leg = |time: f64| estimate_leg_performance(&data, departure.buoy, departure.next, time).unwrap();
+            assert!(leg(departure.latest).arrival_time <= by + 1e-9);
+            if departure.next == finish {
+                assert!(leg(departure.latest + TIME_STEP).arrival_time > by);
+                reached += 1;
+            }
+        }
+        assert!(reached > 0);
+
+        // Later in the race fewer buoys make it
+        assert!(latest_departures(&data, finish, deadline, deadline - 1.0).len() < departures.len());
+    }
+}
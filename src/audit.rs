@@ -0,0 +1,290 @@
+use crate::data::{RegattaData, WindCondition, WindData, build_regatta_graph};
+use crate::error::UursError;
+use crate::scoring::PartialLegCredit;
+use crate::optimize::{
+    Path, PlanObjective, SearchConstraints, SearchOptions, best_continuation, evaluate_route, validate_route,
+};
+use petgraph::visit::Bfs;
+use serde::Serialize;
+
+/// Share of the best routes sailing a leg from which on the leg is a
+/// bottleneck where the fleet converges
+pub const BOTTLENECK_SHARE: f64 = 0.75;
+
+/// Wind scenarios and search depth of a course audit
+#[derive(Clone, Debug, Serialize)]
+pub struct AuditOptions {
+    pub directions: usize, // wind directions evenly spread around the compass
+    pub wind_speed: f64,   // in knots, constant over the race
+    pub lookahead: usize,  // legs looked ahead when planning the best race
+}
+
+impl Default for AuditOptions {
+    fn default() -> Self {
+        Self {
+            directions: 8,
+            wind_speed: 12.0,
+            lookahead: 2,
+        }
+    }
+}
+
+/// The best race from a start in a constant wind
+#[derive(Clone, Debug, Serialize)]
+pub struct ScenarioRace {
+    pub start: String,
+    pub route: Vec<String>,
+    pub scored_distance: f64, // in nm
+    pub end_time: f64,        // in hours since race start
+}
+
+/// The best races from every start in a constant wind
+#[derive(Clone, Debug, Serialize)]
+pub struct WindScenario {
+    pub wind_direction: f64, // in degrees
+    pub wind_speed: f64,     // in knots
+    pub races: Vec<ScenarioRace>,
+}
+
+impl WindScenario {
+    /// The race scoring the most miles
+    pub fn best(&self) -> Option<&ScenarioRace> {
+        self.races.iter().max_by(|a, b| a.scored_distance.total_cmp(&b.scored_distance))
+    }
+}
+
+/// How many of the best races of all scenarios sail a leg
+#[derive(Clone, Debug, Serialize)]
+pub struct LegUsage {
+    pub from: String,
+    pub to: String,
+    pub distance: f64, // in nm
+    pub races: usize,  // best races sailing the leg in either direction
+    pub share: f64,    // of all best races
+}
+
+/// Structured report on the feasibility of a course for the race committee
+#[derive(Clone, Debug, Serialize)]
+pub struct AuditReport {
+    pub unconnected_buoys: Vec<String>, // without any start or leg
+    pub unreachable_buoys: Vec<String>, // with legs, but not reachable from a start
+    pub scenarios: Vec<WindScenario>,
+    pub leg_usage: Vec<LegUsage>,       // most used first
+    pub unused_legs: usize,             // sailed by none of the best races
+    pub concentration: f64,             // share of all sailings on the tenth of the legs used most
+    pub bottlenecks: Vec<LegUsage>,     // sailed by at least `BOTTLENECK_SHARE` of the best races
+}
+
+/// Audit a proposed course
+///
+/// Checks that every buoy can be reached from a start, then plans the best
+/// race from every start in a constant wind from each of the directions:
+/// rolling forward, the first leg of the best continuation of `lookahead`
+/// legs is sailed, within the maximum numbers of the legs and the rules of
+/// the data. The legs these races sail show which legs are skewed towards
+/// being sailed by everyone, or by no one.
+pub fn audit_course(data: &RegattaData, options: &AuditOptions) -> Result<AuditReport, UursError> {
+    if options.directions == 0 || options.wind_speed <= 0.0 || options.lookahead == 0 {
+        return Err(UursError::InvalidParameter(
+            "The audit needs at least one wind direction, a positive wind speed, and a lookahead".to_string(),
+        ));
+    }
+
+    // Reachability from the starts
+    let (graph, node_indices) = build_regatta_graph(data);
+    let mut reachable = vec![false; data.boeien.len()];
+    for start in &data.starts {
+        if let Some(&node) = node_indices.get(&start.from) {
+            let mut bfs = Bfs::new(&graph, node);
+            while let Some(node) = bfs.next(&graph) {
+                reachable[node.index()] = true;
+            }
+        }
+    }
+    let connected = |buoy: usize| graph.neighbors_undirected(petgraph::graph::NodeIndex::new(buoy)).next().is_some();
+    let unconnected_buoys = (0..data.boeien.len())
+        .filter(|&buoy| !connected(buoy))
+        .map(|buoy| data.boeien[buoy].name.clone())
+        .collect();
+    let unreachable_buoys = (0..data.boeien.len())
+        .filter(|&buoy| connected(buoy) && !reachable[buoy])
+        .map(|buoy| data.boeien[buoy].name.clone())
+        .collect();
+
+    // The best races in every wind direction
+    let mut start_buoys: Vec<usize> = data.starts.iter().filter_map(|start| data.get_boei_index(&start.from)).collect();
+    start_buoys.sort_unstable();
+    start_buoys.dedup();
+    let mut scenarios = Vec::with_capacity(options.directions);
+    let mut usage = vec![0usize; data.rakken.len()];
+    let mut race_count = 0;
+    for i in 0..options.directions {
+        let wind_direction = i as f64 * 360.0 / options.directions as f64;
+        let scenario_data = RegattaData {
+            wind_data: constant_wind(&data.wind_data, wind_direction, options.wind_speed),
+            ..data.clone()
+        };
+        let mut races = Vec::with_capacity(start_buoys.len());
+        for &start in &start_buoys {
+            let Some(path) = best_race(&scenario_data, start, options.lookahead)? else {
+                continue;
+            };
+            race_count += 1;
+            // Every race counts once per leg, however often it sails it
+            let mut sailed = vec![false; data.rakken.len()];
+            let mut route = vec![data.boeien[start].name.clone()];
+            for step in &path.steps {
+                let (from, to) = (&data.boeien[step.from].name, &data.boeien[step.to].name);
+                if let Some(index) = data
+                    .rakken
+                    .iter()
+                    .position(|rak| (rak.from == *from && rak.to == *to) || (rak.from == *to && rak.to == *from))
+                {
+                    sailed[index] = true;
+                }
+                route.push(to.clone());
+            }
+            for (count, sailed) in usage.iter_mut().zip(sailed) {
+                *count += usize::from(sailed);
+            }
+            races.push(ScenarioRace {
+                start: data.boeien[start].name.clone(),
+                route,
+                scored_distance: path.scored_distance,
+                end_time: path.end_time,
+            });
+        }
+        scenarios.push(WindScenario {
+            wind_direction,
+            wind_speed: options.wind_speed,
+            races,
+        });
+    }
+
+    let mut leg_usage: Vec<LegUsage> = data
+        .rakken
+        .iter()
+        .zip(&usage)
+        .map(|(rak, &races)| LegUsage {
+            from: rak.from.clone(),
+            to: rak.to.clone(),
+            distance: rak.distance,
+            races,
+            share: if race_count > 0 { races as f64 / race_count as f64 } else { 0.0 },
+        })
+        .collect();
+    leg_usage.sort_by(|a, b| b.races.cmp(&a.races).then(b.distance.total_cmp(&a.distance)));
+
+    let total: usize = usage.iter().sum();
+    let top = leg_usage.len().div_ceil(10);
+    let concentration = if total > 0 {
+        leg_usage[..top].iter().map(|leg| leg.races).sum::<usize>() as f64 / total as f64
+    } else {
+        0.0
+    };
+    Ok(AuditReport {
+        unconnected_buoys,
+        unreachable_buoys,
+        scenarios,
+        unused_legs: leg_usage.iter().filter(|leg| leg.races == 0).count(),
+        concentration,
+        bottlenecks: leg_usage.iter().filter(|leg| leg.share >= BOTTLENECK_SHARE).cloned().collect(),
+        leg_usage,
+    })
+}
+
+/// The wind forecast with every hour replaced by the same wind
+fn constant_wind(wind: &WindData, direction: f64, speed: f64) -> WindData {
+    let conditions = wind
+        .get_all_conditions()
+        .iter()
+        .map(|condition| WindCondition {
+            time: condition.time,
+            wind_speed: speed,
+            wind_angle: direction,
+        })
+        .collect();
+    let mut constant = WindData::from_conditions(conditions);
+    constant.start_offset = wind.start_offset;
+    constant
+}
+
+/// Plan the race from a start by sailing the first leg of the best
+/// continuation again and again until the time limit, `None` if not even the
+/// start can be sailed
+fn best_race(data: &RegattaData, start: usize, lookahead: usize) -> Result<Option<Path>, UursError> {
+    let scoring = &data.rules.scoring;
+    let time_limit = scoring.deadline + scoring.late_allowance;
+    let mut route = vec![start];
+    let mut time = 0.0;
+    let mut options = SearchOptions::default();
+    loop {
+        let current = *route.last().unwrap();
+        let Some(continuation) = (1..=lookahead)
+            .rev()
+            .find_map(|steps| best_continuation(data, current, time, steps, &PlanObjective::MaxDistance, &options).transpose())
+            .transpose()?
+        else {
+            break;
+        };
+        let step = &continuation.steps[0];
+        if step.end_time > time_limit {
+            // Some editions still credit the leg being sailed at the finish gun
+            if scoring.partial_legs == PartialLegCredit::Fraction && step.start_time < scoring.deadline {
+                route.push(step.to);
+            }
+            break;
+        }
+        route.push(step.to);
+        time = step.end_time;
+        if route.len() > data.rules.limits.max_path_length {
+            break;
+        }
+
+        // Legs sailed as often as allowed are closed for the rest of the
+        // race: sailing back would be one time too many
+        let mut candidate = route.clone();
+        candidate.push(current);
+        if matches!(
+            validate_route(data, &candidate, &SearchConstraints::default()),
+            Err(UursError::LegOverused { .. } | UursError::NoLeg { .. })
+        ) {
+            options.constraints.forbidden_legs.push((current, step.to));
+        }
+    }
+    if route.len() < 2 {
+        return Ok(None);
+    }
+    evaluate_route(data, &route, 0.0).map(Some)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_audit_course() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let options = AuditOptions {
+            directions: 2,
+            ..AuditOptions::default()
+        };
+        let report = audit_course(&data, &options).unwrap();
+        // The finish buoy has no legs on this course
+        assert!(report.unconnected_buoys.contains(&"FINISH".to_string()));
+        assert_eq!(report.scenarios.len(), 2);
+        assert_eq!(report.scenarios[1].wind_direction, 180.0);
+
+        for scenario in &report.scenarios {
+            let best = scenario.best().unwrap();
+            assert!(best.end_time <= data.rules.scoring.deadline + data.rules.scoring.late_allowance);
+            assert!(best.scored_distance > 0.0);
+            // The best races keep to the maximum numbers of the legs
+            let route: Vec<usize> = best.route.iter().map(|name| data.get_boei_index(name).unwrap()).collect();
+            assert!(validate_route(&data, &route, &SearchConstraints::default()).is_ok());
+        }
+        assert_eq!(report.leg_usage.len(), data.rakken.len());
+        assert!(report.leg_usage.windows(2).all(|pair| pair[0].races >= pair[1].races));
+        assert!(report.bottlenecks.iter().all(|leg| leg.share >= BOTTLENECK_SHARE));
+    }
+}
@@ -0,0 +1,104 @@
+use crate::data::RegattaData;
+use crate::error::UursError;
+use crate::export::escape_xml;
+use crate::optimize::{Path, estimate_leg_performance};
+use crate::plot::{BoatTrack, PlotConfig, create_fleet_plot, create_polar_plot, create_wind_chart};
+use std::fmt::Write;
+
+/// Write the briefing pack of a planned route as one self-contained HTML
+/// document for printing: the course map with the route, the leg table with
+/// the planned times, the wind forecast, and the polar diagram
+///
+/// Night is shaded on the wind chart if `daylight` gives the sunrise and
+/// sunset in minutes since midnight.
+pub fn briefing_to_html(
+    data: &RegattaData,
+    plan: &Path,
+    title: &str,
+    daylight: Option<(f64, f64)>,
+) -> Result<String, UursError> {
+    let name = |buoy: usize| escape_xml(&data.boeien[buoy].name);
+    let first = plan
+        .steps
+        .first()
+        .ok_or_else(|| UursError::InvalidParameter("The plan has no steps".to_string()))?;
+    let buoys: Vec<usize> = std::iter::once(first.from)
+        .chain(plan.steps.iter().map(|step| step.to))
+        .collect();
+
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, r#"<html lang="en"><head><meta charset="UTF-8">"#)?;
+    writeln!(html, "<title>{}</title>", escape_xml(title))?;
+    writeln!(
+        html,
+        "<style>body {{ font-family: sans-serif; margin: 2em; color: #2c3e50; }} \
+         table {{ border-collapse: collapse; margin: 1em 0; }} \
+         th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: right; }} \
+         th {{ background: #ecf0f1; }} td.leg {{ text-align: left; }} \
+         h2 {{ page-break-before: always; }} h2.first {{ page-break-before: avoid; }} \
+         svg {{ max-width: 100%; height: auto; }}</style>"
+    )?;
+    writeln!(html, "</head><body>")?;
+    writeln!(html, "<h1>{}</h1>", escape_xml(title))?;
+
+    // Summary
+    let route: Vec<String> = buoys.iter().map(|&buoy| name(buoy)).collect();
+    writeln!(html, r#"<h2 class="first">Route</h2><ul>"#)?;
+    writeln!(html, "<li>{} ({:.2} nm)</li>", route.join(" → "), plan.total_distance)?;
+    writeln!(html, "<li>Start: {}</li>", data.race_clock.describe(first.start_time))?;
+    writeln!(html, "<li>Finish: {}</li>", data.race_clock.describe(plan.end_time))?;
+    let margin = data.rules.scoring.deadline - plan.end_time;
+    if margin >= 0.0 {
+        writeln!(html, "<li>{margin:.2} h to spare before the end of the race</li>")?;
+    } else {
+        writeln!(html, "<li>Finishes {:.2} h after the end of the race</li>", -margin)?;
+    }
+    writeln!(html, "</ul>")?;
+
+    // Leg table
+    writeln!(html, "<table>")?;
+    writeln!(
+        html,
+        "<tr><th>#</th><th>Leg</th><th>Distance (nm)</th><th>Total (nm)</th><th>Course</th>\
+         <th>Wind</th><th>Speed (kn)</th><th>Start</th><th>Rounding</th></tr>"
+    )?;
+    let mut total = 0.0;
+    for (i, step) in plan.steps.iter().enumerate() {
+        let performance = estimate_leg_performance(data, step.from, step.to, step.start_time)?;
+        total += step.distance;
+        writeln!(
+            html,
+            r#"<tr><td>{}</td><td class="leg">{} → {}</td><td>{:.2}</td><td>{:.2}</td><td>{:.0}°</td><td>{:.0}° {:.1} kn</td><td>{:.2}</td><td>{}</td><td>{}</td></tr>"#,
+            i + 1,
+            name(step.from),
+            name(step.to),
+            step.distance,
+            total,
+            performance.course_bearing,
+            performance.wind_direction,
+            performance.wind_speed,
+            step.speed,
+            data.race_clock.describe(step.start_time),
+            data.race_clock.describe(step.end_time),
+        )?;
+    }
+    writeln!(html, "</table>")?;
+
+    // Charts
+    let route_track = BoatTrack {
+        name: "Finish".to_string(),
+        positions: buoys.iter().filter_map(|&buoy| data.boeien[buoy].coordinates()).collect(),
+    };
+    let course = create_fleet_plot(data, &[route_track], PlotConfig::default()).map_err(UursError::export)?;
+    let wind = create_wind_chart(data, PlotConfig::default(), daylight).map_err(UursError::export)?;
+    let polars = create_polar_plot(data, PlotConfig::default()).map_err(UursError::export)?;
+    writeln!(html, "<h2>Course</h2>")?;
+    writeln!(html, "{course}")?;
+    writeln!(html, "<h2>Wind Forecast</h2>")?;
+    writeln!(html, "{wind}")?;
+    writeln!(html, "<h2>Polars</h2>")?;
+    writeln!(html, "{polars}")?;
+    writeln!(html, "</body></html>")?;
+    Ok(html)
+}
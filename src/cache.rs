@@ -0,0 +1,152 @@
+use crate::metrics::Metrics;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+/// Larger response bodies are not cached, so the cache stays below
+/// capacity times this size in memory
+const MAX_CACHED_BODY: usize = 1024 * 1024;
+
+/// Least recently used map from keys to values with a fixed capacity
+#[derive(Debug)]
+struct LruMap<V> {
+    capacity: usize,
+    tick: u64,                          // incremented on every access
+    entries: HashMap<String, (u64, V)>, // key -> (last access, value)
+    order: BTreeMap<u64, String>,       // last access -> key, oldest first
+}
+
+impl<V: Clone> LruMap<V> {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            tick: 0,
+            entries: HashMap::new(),
+            order: BTreeMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<V> {
+        self.tick += 1;
+        let (last_access, value) = self.entries.get_mut(key)?;
+        let key = self.order.remove(last_access)?;
+        *last_access = self.tick;
+        self.order.insert(self.tick, key);
+        Some(value.clone())
+    }
+
+    fn insert(&mut self, key: String, value: V) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.tick += 1;
+        if let Some((last_access, _)) = self.entries.remove(&key) {
+            self.order.remove(&last_access);
+        } else if self.entries.len() >= self.capacity
+            && let Some((_, oldest)) = self.order.pop_first()
+        {
+            self.entries.remove(&oldest);
+        }
+        self.order.insert(self.tick, key.clone());
+        self.entries.insert(key, (self.tick, value));
+    }
+
+    /// Give every entry the key `rekey` returns for it, keeping the order of
+    /// access, and drop the entries it returns `None` for
+    fn rekey(&mut self, rekey: impl Fn(&str) -> Option<String>) {
+        let mut entries = std::mem::take(&mut self.entries);
+        for (last_access, key) in std::mem::take(&mut self.order) {
+            let (Some((_, value)), Some(key)) = (entries.remove(&key), rekey(&key)) else {
+                continue;
+            };
+            if !self.entries.contains_key(&key) {
+                self.order.insert(last_access, key.clone());
+                self.entries.insert(key, (last_access, value));
+            }
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+}
+
+/// Cache of serialized API responses keyed by the normalized query and the
+/// data version
+#[derive(Debug)]
+pub struct ResponseCache {
+    entries: Mutex<LruMap<Arc<String>>>,
+    metrics: Arc<Metrics>, // records hits and misses
+}
+
+impl ResponseCache {
+    /// Create a cache holding up to `capacity` responses, 0 disables caching
+    pub fn new(capacity: usize, metrics: Arc<Metrics>) -> Self {
+        Self {
+            entries: Mutex::new(LruMap::new(capacity)),
+            metrics,
+        }
+    }
+
+    /// Look up a response body, counting the hit or miss
+    pub fn get(&self, key: &str) -> Option<Arc<String>> {
+        let response = self.entries.lock().unwrap().get(key);
+        self.metrics.record_cache_lookup(response.is_some());
+        response
+    }
+
+    /// Store a response body unless it is too large, and return it for sending
+    pub fn insert(&self, key: String, body: String) -> String {
+        if body.len() <= MAX_CACHED_BODY {
+            let mut entries = self.entries.lock().unwrap();
+            entries.insert(key, Arc::new(body.clone()));
+            self.metrics.set_cache_entries(entries.len());
+        }
+        body
+    }
+
+    /// Move the responses still valid after a change of the data to their
+    /// new keys and drop all others, returning how many were kept
+    pub fn rekey(&self, rekey: impl Fn(&str) -> Option<String>) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        entries.rekey(rekey);
+        self.metrics.set_cache_entries(entries.len());
+        entries.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lru_map_evicts_least_recently_used() {
+        let mut map = LruMap::new(2);
+        map.insert("a".to_string(), 1);
+        map.insert("b".to_string(), 2);
+        assert_eq!(map.get("a"), Some(1));
+
+        // "b" is now the least recently used entry
+        map.insert("c".to_string(), 3);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("b"), None);
+        assert_eq!(map.get("a"), Some(1));
+        assert_eq!(map.get("c"), Some(3));
+
+        // Replacing an entry doesn't evict anything
+        map.insert("a".to_string(), 4);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get("a"), Some(4));
+        assert_eq!(map.get("c"), Some(3));
+
+        // Rekeyed entries keep their order of access
+        map.rekey(|key| (key != "c").then(|| key.to_uppercase()));
+        map.insert("d".to_string(), 5);
+        map.insert("e".to_string(), 6);
+        assert_eq!(map.get("A"), None);
+        assert_eq!(map.get("d"), Some(5));
+
+        let mut disabled = LruMap::new(0);
+        disabled.insert("a".to_string(), 1);
+        assert_eq!(disabled.get("a"), None);
+    }
+}
@@ -0,0 +1,154 @@
+use crate::data::RegattaData;
+use crate::error::UursError;
+use crate::export::RouteExporter;
+use crate::optimize::Path;
+use crate::watches::{WatchBlock, WatchRotation};
+use chrono::{NaiveDateTime, Utc};
+
+/// Minutes before a planned rounding at which the phone reminds the crew
+const ROUNDING_ALARM_MINUTES: u32 = 10;
+
+/// iCalendar file with one event per leg of the route, which reminds of the
+/// rounding at its end, and optionally the blocks of a watch rotation
+#[derive(Clone, Debug, Default)]
+pub struct IcsExporter {
+    pub watches: Option<WatchRotation>,
+}
+
+impl RouteExporter for IcsExporter {
+    fn name(&self) -> &'static str {
+        "ics"
+    }
+
+    fn export(&self, data: &RegattaData, path: &Path, name: &str) -> Result<String, UursError> {
+        let watches = match (&self.watches, path.steps.first()) {
+            (Some(watches), Some(first)) => watches.blocks(first.start_time, path.end_time)?,
+            _ => Vec::new(),
+        };
+        path_to_ics(data, path, name, &watches)
+    }
+}
+
+/// Write the schedule of a path as iCalendar file
+///
+/// The times are local wall clock times derived from the race start, so this
+/// fails without `race_clock.start`. Every leg is an event from its planned
+/// start to the rounding of its mark with an alarm shortly before the
+/// rounding. The watch blocks become events of their own.
+pub fn path_to_ics(
+    data: &RegattaData,
+    path: &Path,
+    name: &str,
+    watches: &[WatchBlock],
+) -> Result<String, UursError> {
+    let date = |time: f64| {
+        data.race_clock.date_at(time).ok_or_else(|| {
+            UursError::InvalidParameter(
+                "The calendar needs the date and time of the race start, see --race-start".to_string(),
+            )
+        })
+    };
+    let stamp = Utc::now().format("%Y%m%dT%H%M%SZ").to_string();
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:-//uurs24//{}//EN", env!("CARGO_PKG_VERSION")),
+        format!("X-WR-CALNAME:{}", escape_text(name)),
+    ];
+
+    for (i, step) in path.steps.iter().enumerate() {
+        let (from, to) = (&data.boeien[step.from].name, &data.boeien[step.to].name);
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{stamp}-leg-{}@uurs24", i + 1),
+            format!("DTSTAMP:{stamp}"),
+            format!("DTSTART:{}", format_date(date(step.start_time)?)),
+            format!("DTEND:{}", format_date(date(step.end_time)?)),
+            format!("SUMMARY:{}", escape_text(&format!("Leg {}: {from} - {to}", i + 1))),
+            format!(
+                "DESCRIPTION:{}",
+                escape_text(&format!("{:.2} nm at {:.2} kn, rounding {to}", step.distance, step.speed))
+            ),
+            "BEGIN:VALARM".to_string(),
+            "ACTION:DISPLAY".to_string(),
+            format!("DESCRIPTION:{}", escape_text(&format!("Rounding {to} soon"))),
+            format!("TRIGGER;RELATED=END:-PT{ROUNDING_ALARM_MINUTES}M"),
+            "END:VALARM".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+
+    for (i, block) in watches.iter().enumerate() {
+        lines.extend([
+            "BEGIN:VEVENT".to_string(),
+            format!("UID:{stamp}-watch-{}@uurs24", i + 1),
+            format!("DTSTAMP:{stamp}"),
+            format!("DTSTART:{}", format_date(date(block.start)?)),
+            format!("DTEND:{}", format_date(date(block.end)?)),
+            format!("SUMMARY:{}", escape_text(&format!("Watch {}", block.watch))),
+            "TRANSP:TRANSPARENT".to_string(),
+            "END:VEVENT".to_string(),
+        ]);
+    }
+
+    lines.push("END:VCALENDAR".to_string());
+    let mut ics = String::new();
+    for line in &lines {
+        write_folded(&mut ics, line);
+    }
+    Ok(ics)
+}
+
+/// Format a local time as floating date and time of iCalendar
+fn format_date(date: NaiveDateTime) -> String {
+    date.format("%Y%m%dT%H%M%S").to_string()
+}
+
+/// Escape the characters with a special meaning in iCalendar text values
+fn escape_text(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+/// Append a content line, folded after 75 octets as iCalendar requires
+fn write_folded(ics: &mut String, line: &str) {
+    let mut length = 0;
+    for c in line.chars() {
+        if length + c.len_utf8() > 75 {
+            ics.push_str("\r\n ");
+            length = 1;
+        }
+        ics.push(c);
+        length += c.len_utf8();
+    }
+    ics.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_to_ics() {
+        let mut data = crate::data::load_regatta_data().unwrap();
+        let route = ["OEVE", "WV12", "GVS3-M8"].map(|name| data.get_boei_index(name).unwrap());
+        let path = crate::optimize::evaluate_route(&data, &route, 0.0).unwrap();
+        let watches = WatchRotation {
+            names: vec!["A".to_string(), "B".to_string()],
+            length: 1.0,
+        };
+        assert!(path_to_ics(&data, &path, "Test", &[]).is_err());
+
+        data.race_clock.start = Some("2026-06-12T18:00:00".parse().unwrap());
+        let watches = watches.blocks(path.steps[0].start_time, path.end_time).unwrap();
+        let ics = path_to_ics(&data, &path, "Test", &watches).unwrap();
+        assert!(ics.contains("DTSTART:20260612T180000\r\n"));
+        assert_eq!(ics.matches("BEGIN:VALARM").count(), 2);
+        // Blocks of an hour until the end of the route at about 19:42
+        assert!(ics.contains("SUMMARY:Watch B\r\n"));
+        assert_eq!(ics.matches("SUMMARY:Watch").count(), 2);
+        assert!(ics.lines().all(|line| line.trim_end().len() <= 75));
+    }
+}
@@ -0,0 +1,252 @@
+use crate::data::{RegattaData, build_regatta_graph};
+use crate::error::UursError;
+use crate::optimize::estimate_leg_performance;
+use crate::params::RaceHours;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::error::Error;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Observations needed on a leg before its correction factor is fitted
+pub const MIN_OBSERVATIONS: usize = 3;
+
+/// Correction factors are clamped to this range, so a few wild reports can't
+/// make a leg look unsailable or impossibly fast
+const MIN_FACTOR: f64 = 0.5;
+const MAX_FACTOR: f64 = 1.5;
+
+/// Speed a crew actually achieved on a leg, as submitted to the server
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LegObservation {
+    pub from: String,
+    pub to: String,
+    pub time: f64,                   // in hours since race start
+    pub speed: f64,                  // achieved speed along the leg in knots
+    pub wind_direction: Option<f64>, // measured on board in degrees, the forecast if not given
+    pub wind_speed: Option<f64>,     // measured on board in knots, the forecast if not given
+    pub boat: Option<String>,        // name of the submitting boat, for reference only
+    pub received: DateTime<Utc>,
+}
+
+impl LegObservation {
+    /// Check that the leg exists and the values are in range
+    pub fn validate(&self, data: &RegattaData) -> Result<(), String> {
+        let (from, to) = self.leg(data)?;
+        let (graph, _) = build_regatta_graph(data);
+        if graph
+            .find_edge(petgraph::graph::NodeIndex::new(from), petgraph::graph::NodeIndex::new(to))
+            .is_none()
+        {
+            return Err(format!("No start or leg from '{}' to '{}'", self.from, self.to));
+        }
+        RaceHours::new(data, self.time).map_err(|e| e.to_string())?;
+        if !(0.0..=30.0).contains(&self.speed) {
+            return Err("Speed must be between 0 and 30 knots".to_string());
+        }
+        if self.wind_direction.is_some_and(|direction| !(0.0..=360.0).contains(&direction)) {
+            return Err("Wind direction must be between 0 and 360 degrees".to_string());
+        }
+        if self.wind_speed.is_some_and(|speed| !(0.0..=100.0).contains(&speed)) {
+            return Err("Wind speed must be between 0 and 100 knots".to_string());
+        }
+        if self.wind_direction.is_some() != self.wind_speed.is_some() {
+            return Err("Wind direction and wind speed must be given together".to_string());
+        }
+        Ok(())
+    }
+
+    /// Buoy indices of the leg
+    fn leg(&self, data: &RegattaData) -> Result<(usize, usize), String> {
+        let index = |name: &str| {
+            data.get_boei_index(name)
+                .ok_or_else(|| format!("Buoy '{name}' not found"))
+        };
+        Ok((index(&self.from)?, index(&self.to)?))
+    }
+
+    /// Speed predicted by the polars for the conditions of the observation,
+    /// without any correction
+    fn predicted_speed(&self, data: &RegattaData, from: usize, to: usize) -> Result<f64, UursError> {
+        let performance = estimate_leg_performance(data, from, to, self.time)?;
+        Ok(match (self.wind_direction, self.wind_speed) {
+            (Some(direction), Some(speed)) => {
+                let mut relative_bearing = (direction - performance.course_bearing).abs();
+                if relative_bearing > 180.0 {
+                    relative_bearing = 360.0 - relative_bearing;
+                }
+                data.polar_data.get_boat_speed(relative_bearing, speed)
+            }
+            _ => performance.polar_speed,
+        })
+    }
+}
+
+/// Fitted correction of one leg
+#[derive(Clone, Debug, PartialEq)]
+pub struct LegCalibration {
+    pub from: usize,
+    pub to: usize,
+    pub observations: usize,
+    pub factor: Option<f64>, // `None` until there are enough observations
+}
+
+/// Fit a correction factor per leg, the factor by which the achieved speeds
+/// differ from the polar predictions in the least squares sense
+///
+/// Observations of buoys or legs which no longer exist in the data, or of
+/// buoys without coordinates, are skipped. The legs are directed, beating up a leg and running down it are
+/// affected differently by shelter and chop.
+pub fn calibrate(data: &RegattaData, observations: &[LegObservation]) -> Vec<LegCalibration> {
+    // Sums of achieved times predicted speed and of predicted speed squared
+    let mut sums: BTreeMap<(usize, usize), (usize, f64, f64)> = BTreeMap::new();
+    for observation in observations {
+        let Ok((from, to)) = observation.leg(data) else {
+            continue;
+        };
+        let Ok(predicted) = observation.predicted_speed(data, from, to) else {
+            continue;
+        };
+        let entry = sums.entry((from, to)).or_default();
+        entry.0 += 1;
+        entry.1 += observation.speed * predicted;
+        entry.2 += predicted * predicted;
+    }
+
+    sums.into_iter()
+        .map(|((from, to), (count, achieved, predicted))| LegCalibration {
+            from,
+            to,
+            observations: count,
+            factor: (count >= MIN_OBSERVATIONS && predicted > 0.0)
+                .then(|| (achieved / predicted).clamp(MIN_FACTOR, MAX_FACTOR)),
+        })
+        .collect()
+}
+
+/// Correction factors of the calibrated legs, as used by `RegattaData`
+pub fn leg_corrections(calibration: &[LegCalibration]) -> HashMap<(usize, usize), f64> {
+    calibration
+        .iter()
+        .filter_map(|leg| Some(((leg.from, leg.to), leg.factor?)))
+        .collect()
+}
+
+/// All submitted observations, optionally appended to a log file with one
+/// JSON observation per line
+#[derive(Debug, Default)]
+pub struct ObservationLog {
+    observations: Mutex<Vec<LegObservation>>,
+    log: Option<Mutex<File>>,
+}
+
+impl ObservationLog {
+    /// Create an empty log which is only kept in memory
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a log file, loading the observations already in it, and append
+    /// new observations to it
+    pub fn open(path: &Path) -> Result<Self, Box<dyn Error>> {
+        let mut observations = Vec::new();
+        if path.exists() {
+            let reader = BufReader::new(File::open(path)?);
+            for (line_number, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+                let observation: LegObservation = serde_json::from_str(&line).map_err(|e| {
+                    format!("{}:{}: invalid observation: {e}", path.display(), line_number + 1)
+                })?;
+                observations.push(observation);
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self {
+            observations: Mutex::new(observations),
+            log: Some(Mutex::new(file)),
+        })
+    }
+
+    /// Add an observation and return the number of observations. It is
+    /// logged first, so a failure to write the log leaves nothing behind.
+    pub fn add(&self, observation: LegObservation) -> Result<usize, Box<dyn Error>> {
+        if let Some(log) = &self.log {
+            let mut line = serde_json::to_string(&observation)?;
+            line.push('\n');
+            log.lock().unwrap().write_all(line.as_bytes())?;
+        }
+
+        let mut observations = self.observations.lock().unwrap();
+        observations.push(observation);
+        Ok(observations.len())
+    }
+
+    /// Get all observations in the order they were submitted
+    pub fn all(&self) -> Vec<LegObservation> {
+        self.observations.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::load_regatta_data;
+
+    #[test]
+    fn test_calibrate_leg() {
+        let mut data = load_regatta_data().unwrap();
+        let observation = |from: &str, to: &str, time: f64, factor: f64| {
+            let mut observation = LegObservation {
+                from: from.to_string(),
+                to: to.to_string(),
+                time,
+                speed: 0.0,
+                wind_direction: None,
+                wind_speed: None,
+                boat: None,
+                received: Utc::now(),
+            };
+            let (a, b) = observation.leg(&data).unwrap();
+            observation.speed = observation.predicted_speed(&data, a, b).unwrap() * factor;
+            observation
+        };
+
+        // Three reports of a sheltered leg, a single one of another leg
+        let observations = vec![
+            observation("KR-A", "LC1", 2.0, 0.8),
+            observation("KR-A", "LC1", 5.0, 0.8),
+            observation("KR-A", "LC1", 9.0, 0.8),
+            observation("SPORT-A", "KR-A", 0.0, 0.1),
+        ];
+        assert!(observations.iter().all(|o| o.validate(&data).is_ok()));
+        let calibration = calibrate(&data, &observations);
+        assert_eq!(calibration.len(), 2);
+        let corrections = leg_corrections(&calibration);
+        assert_eq!(corrections.len(), 1);
+
+        let (a, b) = (data.get_boei_index("KR-A").unwrap(), data.get_boei_index("LC1").unwrap());
+        assert!((corrections[&(a, b)] - 0.8).abs() < 1e-9);
+
+        // The correction slows the leg down in the estimates, but not the way back
+        let before = estimate_leg_performance(&data, a, b, 3.0).unwrap().estimated_speed;
+        let back = estimate_leg_performance(&data, b, a, 3.0).unwrap().estimated_speed;
+        data.leg_corrections = corrections;
+        assert!((estimate_leg_performance(&data, a, b, 3.0).unwrap().estimated_speed - 0.8 * before).abs() < 1e-9);
+        assert_eq!(estimate_leg_performance(&data, b, a, 3.0).unwrap().estimated_speed, back);
+        // Calibrating again on corrected data gives the same factor
+        let again = calibrate(&data, &observations);
+        let leg = again.iter().find(|leg| (leg.from, leg.to) == (a, b)).unwrap();
+        assert!((leg.factor.unwrap() - 0.8).abs() < 1e-9);
+
+        let mut unknown = observations[0].clone();
+        unknown.to = "NOWHERE".to_string();
+        assert!(unknown.validate(&data).is_err());
+    }
+}
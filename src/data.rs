@@ -1,9 +1,14 @@
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Timelike};
 #[cfg(test)]
 use petgraph::Direction;
 use petgraph::graph::{DiGraph, NodeIndex};
 use serde::{Deserialize, Deserializer, Serialize};
+use crate::error::UursError;
+use crate::rules::{RuleSet, load_rule_set};
+use crate::geo::distance_nm;
+use serde::de::DeserializeOwned;
 use std::collections::HashMap;
-use std::error::Error;
+use std::str::FromStr;
 
 /// Custom deserializer for European decimal format (comma as decimal separator)
 fn deserialize_european_float<'de, D>(deserializer: D) -> Result<f64, D::Error>
@@ -25,6 +30,25 @@ where
     s.parse::<u32>().map_err(serde::de::Error::custom)
 }
 
+/// Side on which a buoy has to be kept when rounding it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RoundingSide {
+    #[serde(alias = "Port", alias = "BB")]
+    Port,
+    #[serde(alias = "Starboard", alias = "SB")]
+    Starboard,
+}
+
+impl std::fmt::Display for RoundingSide {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Port => "port",
+            Self::Starboard => "starboard",
+        })
+    }
+}
+
 /// Represents a buoy (boei) with its properties
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Boei {
@@ -42,6 +66,9 @@ pub struct Boei {
     pub lat_min: Option<String>,
     #[serde(rename = "Long_min")]
     pub long_min: Option<String>,
+    // Optional column: port (BB) or starboard (SB), empty if either side will do
+    #[serde(rename = "Rounding", default)]
+    pub rounding: Option<RoundingSide>,
 
     // Parsed coordinates in decimal degrees
     #[serde(skip)]
@@ -52,13 +79,13 @@ pub struct Boei {
 
 impl Boei {
     /// Parse the coordinate strings and populate the lat/long fields
-    pub fn parse_coordinates(&mut self) -> Result<(), Box<dyn Error>> {
+    pub fn parse_coordinates(&mut self) -> Result<(), UursError> {
         if let Some(lat_str) = &self.lat_min {
-            self.lat = Some(Self::parse_coordinate_string(lat_str)?);
+            self.lat = Some(Self::parse_coordinate(lat_str, "NS")?);
         }
 
         if let Some(long_str) = &self.long_min {
-            self.long = Some(Self::parse_coordinate_string(long_str)?);
+            self.long = Some(Self::parse_coordinate(long_str, "EW")?);
         }
 
         Ok(())
@@ -77,22 +104,58 @@ impl Boei {
         self.lat.is_some() && self.long.is_some()
     }
 
-    /// Parse a coordinate string in the format "53° 5,020'" or "53° 5' 1.20"" to decimal degrees
-    fn parse_coordinate_string(coord_str: &str) -> Result<f64, Box<dyn Error>> {
+    /// Parse a coordinate string in the format "53° 5,020'" or "53° 5' 1.20"" to
+    /// decimal degrees. A hemisphere N, S, E, or W may follow, south and west
+    /// are negative, and so are degrees with a minus sign.
+    pub fn parse_coordinate_string(coord_str: &str) -> Result<f64, UursError> {
+        Self::parse_coordinate(coord_str, "NSEW")
+    }
+
+    /// Parse a coordinate string with one of the given hemispheres, if any
+    fn parse_coordinate(coord_str: &str, hemispheres: &str) -> Result<f64, UursError> {
         // Remove any extra whitespace and quotes
         let coord_str = coord_str.trim().trim_matches('"');
+        let invalid = |reason: &str| UursError::CoordinateParse {
+            value: coord_str.to_string(),
+            reason: reason.to_string(),
+        };
+
+        // A hemisphere after the minutes or seconds
+        let (rest, hemisphere) = match coord_str.chars().next_back() {
+            Some(last @ ('N' | 'S' | 'E' | 'W')) => (coord_str[..coord_str.len() - 1].trim_end(), Some(last)),
+            _ => (coord_str, None),
+        };
+        if let Some(hemisphere) = hemisphere
+            && !hemispheres.contains(hemisphere)
+        {
+            return Err(invalid(&format!("hemisphere must be one of {hemispheres}")));
+        }
+        let rest = rest.trim_end_matches('"');
+
+        // Or a sign before the degrees
+        let (rest, negative) = match rest.strip_prefix('-') {
+            Some(rest) => (rest.trim_start(), true),
+            None => (rest, false),
+        };
+        if negative && hemisphere.is_some() {
+            return Err(invalid("expected either a sign or a hemisphere"));
+        }
 
         // Split by degree symbol
-        let parts: Vec<&str> = coord_str.split('°').collect();
+        let parts: Vec<&str> = rest.split('°').collect();
         if parts.len() != 2 {
-            return Err(format!("Invalid coordinate format: {coord_str}").into());
+            return Err(invalid("expected degrees followed by '°'"));
         }
 
         let degrees_str = parts[0].trim();
         let minutes_part = parts[1].trim();
 
-        // Parse degrees
-        let degrees: f64 = degrees_str.parse()?;
+        // Parse degrees, the sign is taken already
+        let degrees: f64 = degrees_str.parse().map_err(|_| invalid("invalid degrees"))?;
+        if !(degrees >= 0.0 && degrees.is_finite()) {
+            return Err(invalid("invalid degrees"));
+        }
+        let in_range = |value: f64| (0.0..60.0).contains(&value);
 
         // Parse minutes part - handle both formats
         let minutes: f64;
@@ -108,27 +171,61 @@ impl Boei {
                 // Remove the trailing single quote from seconds
                 let seconds_str = seconds_part.trim_end_matches('\'');
 
-                let minutes_val: f64 = minutes_str.parse()?;
-                let seconds_val: f64 = seconds_str.parse()?;
+                let minutes_val: f64 = minutes_str.parse().map_err(|_| invalid("invalid minutes"))?;
+                let seconds_val: f64 = seconds_str.parse().map_err(|_| invalid("invalid seconds"))?;
+                if !in_range(minutes_val) {
+                    return Err(invalid("minutes must be between 0 and 60"));
+                }
+                if !in_range(seconds_val) {
+                    return Err(invalid("seconds must be between 0 and 60"));
+                }
 
                 // Convert to decimal minutes: minutes + seconds/60
                 minutes = minutes_val + seconds_val / 60.0;
             } else {
-                return Err(format!("Invalid minutes format: {minutes_part}").into());
+                return Err(invalid("invalid minutes"));
             }
         } else {
             // Format: "5,020'" (decimal minutes)
             let minutes_str = minutes_part.trim_end_matches('\'');
-            minutes = minutes_str.replace(',', ".").parse()?;
+            minutes = minutes_str
+                .replace(',', ".")
+                .parse()
+                .map_err(|_| invalid("invalid minutes"))?;
+            if !in_range(minutes) {
+                return Err(invalid("minutes must be between 0 and 60"));
+            }
         }
 
         // Convert to decimal degrees: degrees + minutes/60
         let decimal_degrees = degrees + minutes / 60.0;
+        let max_degrees = if hemispheres == "NS" { 90.0 } else { 180.0 };
+        if decimal_degrees > max_degrees {
+            return Err(invalid(&format!("must be at most {max_degrees}°")));
+        }
 
-        Ok(decimal_degrees)
+        if negative || matches!(hemisphere, Some('S' | 'W')) {
+            Ok(-decimal_degrees)
+        } else {
+            Ok(decimal_degrees)
+        }
     }
 }
 
+/// Format decimal degrees like the columns `Lat_min` and `Long_min`, in
+/// decimal minutes with a hemisphere, e.g. "52° 47,230' N"; `hemispheres`
+/// are the ones of positive and negative values, `['N', 'S']` or `['E', 'W']`
+pub fn format_coordinate(value: f64, hemispheres: [char; 2]) -> String {
+    let thousandths = (value.abs() * 60_000.0).round() as u64;
+    let hemisphere = if value < 0.0 && thousandths > 0 { hemispheres[1] } else { hemispheres[0] };
+    format!(
+        "{}° {},{:03}' {hemisphere}",
+        thousandths / 60_000,
+        thousandths % 60_000 / 1000,
+        thousandths % 1000
+    )
+}
+
 /// Represents a start line between two points
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Start {
@@ -155,8 +252,38 @@ pub struct Rak {
     pub max_number: u32,
 }
 
+/// Handicap of a boat, e.g. its SW rating, by which its miles are corrected
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Rating {
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Rating", deserialize_with = "deserialize_european_float")]
+    pub rating: f64,
+}
+
+/// Start of a class in a race with several starts, in hours after the first
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StartWave {
+    #[serde(rename = "Class")]
+    pub class: String,
+    #[serde(rename = "StartOffset", deserialize_with = "deserialize_european_float")]
+    pub start_offset: f64,
+}
+
+/// A small change to the course made during the race, applied to the data
+/// in memory with [`RegattaData::apply_update`] instead of reloading the files
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CourseUpdate {
+    /// A buoy laid at another position, in decimal degrees
+    MoveBuoy { name: String, lat: f64, long: f64 },
+    /// Another limit on how often a start, or a leg in either direction, may
+    /// be sailed
+    SetMaxNumber { from: String, to: String, max_number: u32 },
+}
+
 /// Represents polar performance data for a boat
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct PolarData {
     /// Wind speeds in knots (from the first row)
     pub wind_speeds: Vec<f64>,
@@ -169,47 +296,43 @@ pub struct PolarData {
 impl PolarData {
     /// Create a new empty PolarData instance
     pub fn new() -> Self {
-        Self {
-            wind_speeds: Vec::new(),
-            wind_angles: Vec::new(),
-            boat_speeds: Vec::new(),
-        }
+        Self::default()
     }
 
     /// Get boat speed for a given true wind angle and wind speed
     #[allow(dead_code)]
     pub fn get_boat_speed(&self, wind_angle: f64, wind_speed: f64) -> f64 {
-        // Find the last row in the polar wind angle table which is smaller than the wind_angle:
-        let mut left: usize = 0;
-        let mut right: usize = self.wind_angles.len();
-        // Invariants: left angle <= bearing_relative < right angle, right <= length
-        while right - left > 1 {
-            let mid = (left + right) / 2; // left < mid < right
-            if self.wind_angles[mid] > wind_angle {
-                right = mid;
-            } else {
-                left = mid
-            }
-        }
-        // Now right = left + 1 and the invariants still hold, so left is the last which is <=
-        let angle_index = left;
-
-        // Find the last row in the polar wind speed table which is smaller than the current wind:
-        left = 0;
-        right = self.wind_speeds.len();
-        // Invariants: left speed <= wind_speed < right speed, right <= length
-        while right - left > 1 {
-            let mid = (left + right) / 2; // left < mid < right
-            if self.wind_speeds[mid] > wind_speed {
-                right = mid;
-            } else {
-                left = mid
-            }
-        }
-        // Now right = left + 1 and the invariants still hold, so left is the last which is <=
-        let windspeed_index = left;
+        let angle_index = last_at_most(&self.wind_angles, wind_angle);
+        let windspeed_index = last_at_most(&self.wind_speeds, wind_speed);
+        self.speed_at(angle_index, windspeed_index, wind_angle)
+    }
 
-        // Return the boat speed at this intersection, but distinguish if we have to beat:
+    /// Get the boat speeds for many pairs of true wind angle and wind speed
+    /// at once, the same as looking them up one by one
+    ///
+    /// The rows and columns of the polars a lookup falls between are kept
+    /// for the next one, which only searches the table again if it falls
+    /// between others. The wind rarely changes much from one lookup of a
+    /// simulation to the next, so most lookups skip both searches.
+    pub fn get_boat_speeds(&self, lookups: &[(f64, f64)]) -> Vec<f64> {
+        let (mut angle_index, mut windspeed_index) = (0, 0);
+        lookups
+            .iter()
+            .map(|&(wind_angle, wind_speed)| {
+                if !brackets(&self.wind_angles, angle_index, wind_angle) {
+                    angle_index = last_at_most(&self.wind_angles, wind_angle);
+                }
+                if !brackets(&self.wind_speeds, windspeed_index, wind_speed) {
+                    windspeed_index = last_at_most(&self.wind_speeds, wind_speed);
+                }
+                self.speed_at(angle_index, windspeed_index, wind_angle)
+            })
+            .collect()
+    }
+
+    /// The boat speed in the row and column of the polars for a wind angle,
+    /// distinguishing if we have to beat
+    fn speed_at(&self, angle_index: usize, windspeed_index: usize, wind_angle: f64) -> f64 {
         if angle_index > 0 {
             return self.boat_speeds[angle_index][windspeed_index];
         }
@@ -226,6 +349,29 @@ impl PolarData {
     }
 }
 
+/// Index of the last value of an ascending table which is at most `value`,
+/// the first if there is none
+fn last_at_most(table: &[f64], value: f64) -> usize {
+    let mut left: usize = 0;
+    let mut right: usize = table.len();
+    // Invariants: left value <= value < right value, right <= length
+    while right - left > 1 {
+        let mid = (left + right) / 2; // left < mid < right
+        if table[mid] > value {
+            right = mid;
+        } else {
+            left = mid
+        }
+    }
+    // Now right = left + 1 and the invariants still hold, so left is the last which is <=
+    left
+}
+
+/// Whether `index` is the one [`last_at_most`] finds for `value`
+fn brackets(table: &[f64], index: usize, value: f64) -> bool {
+    (index == 0 || table[index] <= value) && table.get(index + 1).is_none_or(|&next| next > value)
+}
+
 /// Represents wind conditions at a specific time
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct WindCondition {
@@ -237,57 +383,113 @@ pub struct WindCondition {
     pub wind_angle: f64,
 }
 
+/// Minutes per hour of the wind table, whose lookups are an index
+const WIND_TABLE_RESOLUTION: usize = 60;
+
 /// Represents wind data for the entire regatta duration
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct WindData {
     /// Wind conditions indexed by hour
-    pub conditions: Vec<WindCondition>,
-    /// Wind conditions by hour for quick lookup
-    pub conditions_by_hour: HashMap<u32, WindCondition>,
+    conditions: Vec<WindCondition>,
+    /// Wind conditions by hour
+    conditions_by_hour: HashMap<u32, WindCondition>,
+    /// Hours the start of the boat's class is after the first start, to
+    /// which the hours of the forecast refer
+    pub start_offset: f64,
+    /// The wind of every minute of the forecast, interpolated between the
+    /// hours, so that the lookups of the searches are an index. Minutes of
+    /// hours missing in the forecast are `None`.
+    minutes: Vec<Option<WindCondition>>,
 }
 
 impl WindData {
     /// Create a new empty WindData instance
     pub fn new() -> Self {
-        Self {
-            conditions: Vec::new(),
-            conditions_by_hour: HashMap::new(),
+        Self::default()
+    }
+
+    /// Create wind data from the conditions of the forecast hours
+    pub fn from_conditions(conditions: Vec<WindCondition>) -> Self {
+        let mut wind_data = Self {
+            conditions_by_hour: conditions.iter().map(|condition| (condition.time, condition.clone())).collect(),
+            conditions,
+            ..Self::default()
+        };
+        wind_data.index_minutes();
+        wind_data
+    }
+
+    // Build the table of the minutes again from `conditions_by_hour`
+    fn index_minutes(&mut self) {
+        let len = self.conditions_by_hour.keys().max().map_or(0, |&max| max as usize + 1);
+        self.minutes = (0..len * WIND_TABLE_RESOLUTION)
+            .map(|minute| {
+                let hour = (minute / WIND_TABLE_RESOLUTION) as u32;
+                let current = self.conditions_by_hour.get(&hour)?;
+                let Some(next) = self.conditions_by_hour.get(&(hour + 1)) else {
+                    // No next hour data, the current hour holds
+                    return Some(current.clone());
+                };
+                // For wind angle, we need to handle the case where we cross 0°/360°
+                let angle_diff = (next.wind_angle - current.wind_angle + 180.0) % 360.0 - 180.0;
+                let fraction = (minute % WIND_TABLE_RESOLUTION) as f64 / WIND_TABLE_RESOLUTION as f64;
+                Some(WindCondition {
+                    time: hour,
+                    wind_speed: current.wind_speed + (next.wind_speed - current.wind_speed) * fraction,
+                    wind_angle: (current.wind_angle + angle_diff * fraction + 360.0) % 360.0,
+                })
+            })
+            .collect();
+    }
+
+    /// Replace the wind of hours of the forecast, e.g. with what the crew
+    /// sees on deck. Only hours in the forecast can be changed; nothing is
+    /// changed if any of the conditions is invalid.
+    pub fn override_hours(&mut self, conditions: &[WindCondition]) -> Result<(), UursError> {
+        for condition in conditions {
+            if !self.conditions_by_hour.contains_key(&condition.time) {
+                return Err(UursError::InvalidParameter(format!(
+                    "Hour {} is not in the wind forecast",
+                    condition.time
+                )));
+            }
+            if !(condition.wind_speed.is_finite() && condition.wind_speed >= 0.0) {
+                return Err(UursError::InvalidParameter(format!(
+                    "Wind speed {} of hour {} must be at least 0 knots",
+                    condition.wind_speed, condition.time
+                )));
+            }
+            if !(0.0..=360.0).contains(&condition.wind_angle) {
+                return Err(UursError::InvalidParameter(format!(
+                    "Wind direction {} of hour {} must be between 0 and 360 degrees",
+                    condition.wind_angle, condition.time
+                )));
+            }
+        }
+        for condition in conditions {
+            let condition = WindCondition {
+                wind_angle: condition.wind_angle % 360.0,
+                ..condition.clone()
+            };
+            for existing in self.conditions.iter_mut().filter(|existing| existing.time == condition.time) {
+                *existing = condition.clone();
+            }
+            self.conditions_by_hour.insert(condition.time, condition);
         }
+        self.index_minutes();
+        Ok(())
     }
 
     /// Get wind conditions for a specific hour
     pub fn get_wind_at_hour(&self, hour: u32) -> Option<&WindCondition> {
-        self.conditions_by_hour.get(&hour)
+        self.minutes.get(hour as usize * WIND_TABLE_RESOLUTION)?.as_ref()
     }
 
-    /// Get wind conditions for a specific time (interpolates between hours if needed)
+    /// Get wind conditions for a specific time, interpolated between the
+    /// hours to the minute
     pub fn get_wind_at_time(&self, time_hours: f64) -> Option<WindCondition> {
-        let hour = time_hours.floor() as u32;
-        let next_hour = hour + 1;
-
-        let current = self.get_wind_at_hour(hour)?;
-        let next = self.get_wind_at_hour(next_hour);
-
-        if let Some(next_condition) = next {
-            // Interpolate between hours
-            let fraction = time_hours - hour as f64;
-            let interpolated_speed =
-                current.wind_speed + (next_condition.wind_speed - current.wind_speed) * fraction;
-
-            // For wind angle, we need to handle the case where we cross 0°/360°
-            let angle_diff =
-                (next_condition.wind_angle - current.wind_angle + 180.0) % 360.0 - 180.0;
-            let interpolated_angle = (current.wind_angle + angle_diff * fraction + 360.0) % 360.0;
-
-            Some(WindCondition {
-                time: hour,
-                wind_speed: interpolated_speed,
-                wind_angle: interpolated_angle,
-            })
-        } else {
-            // No next hour data, return current hour
-            Some(current.clone())
-        }
+        let minute = ((time_hours + self.start_offset) * WIND_TABLE_RESOLUTION as f64).floor() as usize;
+        self.minutes.get(minute)?.clone()
     }
 
     /// Get all wind conditions
@@ -296,8 +498,170 @@ impl WindData {
     }
 }
 
+/// Maps a race hour to the wall clock time (HHMM) at that hour
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClockEntry {
+    pub time: u32,
+    pub real: u32,
+}
+
+/// Mapping between race time (hours since start) and wall clock time
+#[derive(Debug, Clone, Default)]
+pub struct RaceClock {
+    /// Clock entries indexed by race hour
+    pub entries: Vec<ClockEntry>,
+    /// Date and time of the race start, needed to read dates
+    pub start: Option<NaiveDateTime>,
+    /// Hours the start of the boat's class is after the first start, to
+    /// which the race hours of the entries and the date of the start refer
+    pub start_offset: f64,
+}
+
+impl RaceClock {
+    /// Create a new empty RaceClock instance
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Get the wall clock time in minutes since midnight for a race time
+    ///
+    /// The clock is taken from the last full race hour and advanced by the
+    /// remaining fraction, wrapping around midnight.
+    pub fn clock_minutes_at(&self, time_hours: f64) -> Option<f64> {
+        let time_hours = time_hours + self.start_offset;
+        let hour = time_hours.floor().max(0.0) as u32;
+        let entry = self
+            .entries
+            .iter()
+            .rev()
+            .find(|e| e.time <= hour)?;
+        let base_minutes = ((entry.real / 100) * 60 + entry.real % 100) as f64;
+        let minutes = base_minutes + (time_hours - entry.time as f64) * 60.0;
+        Some(minutes.rem_euclid(24.0 * 60.0))
+    }
+
+    /// Format the wall clock time for a race time as "HH:MM"
+    pub fn format_clock(&self, time_hours: f64) -> Option<String> {
+        let minutes = self.clock_minutes_at(time_hours)?.round() as u32 % (24 * 60);
+        Some(format!("{:02}:{:02}", minutes / 60, minutes % 60))
+    }
+
+    /// Get the earliest race time at which the wall clock shows the given
+    /// minutes since midnight, the inverse of `clock_minutes_at`
+    pub fn race_time_at(&self, clock_minutes: f64) -> Option<f64> {
+        self.entries.iter().find_map(|entry| {
+            let base_minutes = ((entry.real / 100) * 60 + entry.real % 100) as f64;
+            let offset = (clock_minutes - base_minutes).rem_euclid(24.0 * 60.0);
+            (offset < 60.0).then(|| entry.time as f64 + offset / 60.0 - self.start_offset)
+        })
+    }
+
+    /// Get the race time of a moment, reading the wall clock in local time
+    pub fn race_time_of<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> Option<f64> {
+        let local = time.with_timezone(&Local);
+        let minutes = (local.hour() * 60 + local.minute()) as f64 + local.second() as f64 / 60.0;
+        self.race_time_at(minutes)
+    }
+
+    /// Get the race time in hours since the start for a time given by a user
+    pub fn resolve(&self, time: &RaceTime) -> Result<f64, UursError> {
+        match *time {
+            RaceTime::Hours(hours) => Ok(hours),
+            RaceTime::Clock(minutes) => self.race_time_at(minutes as f64).ok_or_else(|| {
+                UursError::InvalidParameter(format!(
+                    "The clock time {:02}:{:02} is not during the race",
+                    minutes / 60,
+                    minutes % 60
+                ))
+            }),
+            RaceTime::Date(date) => {
+                let start = self.start.ok_or_else(|| {
+                    UursError::InvalidParameter(
+                        "A date and time needs the date of the race start, see --race-start".to_string(),
+                    )
+                })?;
+                Ok((date - start).num_seconds() as f64 / 3600.0 - self.start_offset)
+            }
+        }
+    }
+
+    /// Get the date and time of a race time, None without the race start
+    pub fn date_at(&self, time_hours: f64) -> Option<NaiveDateTime> {
+        let start = self.start?;
+        Some(start + chrono::Duration::seconds(((time_hours + self.start_offset) * 3600.0).round() as i64))
+    }
+
+    /// Format a race time with its wall clock time, like "13.67h (07:40)"
+    pub fn describe(&self, time_hours: f64) -> String {
+        match self.format_clock(time_hours) {
+            Some(clock) => format!("{time_hours:.2}h ({clock})"),
+            None => format!("{time_hours:.2}h"),
+        }
+    }
+}
+
+/// A time as a user gives it: hours since the race start like "13.67", a wall
+/// clock time like "07:40", or a date and time like "2026-06-13 07:40"
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RaceTime {
+    Hours(f64),
+    Clock(u32), // minutes since midnight
+    Date(NaiveDateTime),
+}
+
+impl FromStr for RaceTime {
+    type Err = UursError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(hours) = s.parse::<f64>() {
+            return Ok(Self::Hours(hours));
+        }
+        if let Some(minutes) = parse_clock_minutes(s) {
+            return Ok(Self::Clock(minutes as u32));
+        }
+        for format in ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M:%S"] {
+            if let Ok(date) = NaiveDateTime::parse_from_str(s, format) {
+                return Ok(Self::Date(date));
+            }
+        }
+        Err(UursError::InvalidParameter(format!(
+            "Invalid time '{s}': use hours since the race start, HH:MM, or YYYY-MM-DD HH:MM"
+        )))
+    }
+}
+
+impl<'de> Deserialize<'de> for RaceTime {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Input {
+            Hours(f64),
+            Text(String),
+        }
+        match Input::deserialize(deserializer)? {
+            Input::Hours(hours) => Ok(Self::Hours(hours)),
+            Input::Text(text) => text.parse().map_err(serde::de::Error::custom),
+        }
+    }
+}
+
+/// Parse a wall clock time "HH:MM" into minutes since midnight
+pub fn parse_clock_minutes(clock: &str) -> Option<f64> {
+    let (hours, minutes) = clock.trim().split_once(':')?;
+    let hours: u32 = hours.parse().ok()?;
+    let minutes: u32 = minutes.parse().ok()?;
+    if hours > 23 || minutes > 59 {
+        return None;
+    }
+    Some((hours * 60 + minutes) as f64)
+}
+
 /// Main data structure containing all loaded data
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct RegattaData {
     pub boeien: Vec<Boei>,
     pub starts: Vec<Start>,
@@ -305,19 +669,134 @@ pub struct RegattaData {
     pub boeien_by_name: HashMap<String, Boei>,
     pub polar_data: PolarData,
     pub wind_data: WindData,
+    pub race_clock: RaceClock,
+    pub leg_corrections: HashMap<(usize, usize), f64>, // speed factors of calibrated legs by buoy indices
+    pub leg_distances: HashMap<(usize, usize), f64>, // official distances of starts and legs by buoy indices
+    pub rules: RuleSet,                              // the rules of the edition the data is for
+    pub ratings: HashMap<String, f64>,               // handicaps of the boats by name
+    pub start_offsets: HashMap<String, f64>,         // starts of the classes after the first start in hours
 }
 
 impl RegattaData {
     /// Create a new empty RegattaData instance
     pub fn new() -> Self {
-        Self {
-            boeien: Vec::new(),
-            starts: Vec::new(),
-            rakken: Vec::new(),
-            boeien_by_name: HashMap::new(),
-            polar_data: PolarData::new(),
-            wind_data: WindData::new(),
+        Self::default()
+    }
+
+    /// Get the rating of a boat, None if it sails without handicap
+    pub fn rating(&self, boat: &str) -> Option<f64> {
+        self.ratings.get(boat).copied()
+    }
+
+    /// Get the start of a class in hours after the first start, None for an
+    /// unknown class
+    pub fn start_offset(&self, class: &str) -> Option<f64> {
+        self.start_offsets.get(class).copied()
+    }
+
+    /// Sail as a boat of a class: race times count from the start of the
+    /// class from now on, the wind forecast and the race clock are read that
+    /// many hours later
+    pub fn select_class(&mut self, class: &str) -> Result<(), UursError> {
+        let start_offset = self.start_offset(class).ok_or_else(|| {
+            let mut classes: Vec<_> = self.start_offsets.keys().map(String::as_str).collect();
+            classes.sort_unstable();
+            UursError::InvalidParameter(if classes.is_empty() {
+                format!("Unknown class '{class}', there is no {CLASSES_FILE}")
+            } else {
+                format!("Unknown class '{class}', expected one of {}", classes.join(", "))
+            })
+        })?;
+        self.wind_data.start_offset = start_offset;
+        self.race_clock.start_offset = start_offset;
+        Ok(())
+    }
+
+    /// Get the factor applied to the polar speed on a leg, 1 for legs which
+    /// are not calibrated
+    pub fn leg_correction(&self, from: usize, to: usize) -> f64 {
+        self.leg_corrections.get(&(from, to)).copied().unwrap_or(1.0)
+    }
+
+    /// Get the official distance of the start or leg between two buoys, None
+    /// if they are not connected
+    pub fn leg_distance(&self, from: usize, to: usize) -> Option<f64> {
+        self.leg_distances.get(&(from, to)).copied()
+    }
+
+    /// Buoys reached from a buoy by a single start or leg with the official
+    /// distances, ordered by name
+    pub fn neighbors(&self, buoy: usize) -> Vec<(usize, f64)> {
+        let mut neighbors: Vec<(usize, f64)> = self
+            .leg_distances
+            .iter()
+            .filter(|&(&(from, _), _)| from == buoy)
+            .map(|(&(_, to), &distance)| (to, distance))
+            .collect();
+        neighbors.sort_by(|a, b| self.boeien[a.0].name.cmp(&self.boeien[b.0].name));
+        neighbors
+    }
+
+    /// Index the official distances of the starts and legs, starts in their
+    /// direction and legs in both
+    fn index_leg_distances(&mut self) {
+        let starts = self.starts.iter().map(|start| (&start.from, &start.to, start.distance, false));
+        let rakken = self.rakken.iter().map(|rak| (&rak.from, &rak.to, rak.distance, true));
+        let mut distances = HashMap::new();
+        for (from, to, distance, both_ways) in starts.chain(rakken) {
+            let (Some(a), Some(b)) = (self.get_boei_index(from), self.get_boei_index(to)) else {
+                continue;
+            };
+            distances.entry((a, b)).or_insert(distance);
+            if both_ways {
+                distances.entry((b, a)).or_insert(distance);
+            }
         }
+        self.leg_distances = distances;
+    }
+
+    /// Apply a change of the course to the data. The buoys keep their
+    /// indices and the official distances stay, so the searches, which build
+    /// their graph from the starts and legs, see the change right away and
+    /// nothing else has to be indexed again.
+    pub fn apply_update(&mut self, update: CourseUpdate) -> Result<(), UursError> {
+        match update {
+            CourseUpdate::MoveBuoy { name, lat, long } => {
+                if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&long) {
+                    return Err(UursError::InvalidParameter(format!(
+                        "Position {lat}, {long} of {name} is not in decimal degrees"
+                    )));
+                }
+                let index = self.get_boei_index(&name).ok_or_else(|| UursError::UnknownBuoy(name.clone()))?;
+                let boei = &mut self.boeien[index];
+                boei.lat_min = Some(format_coordinate(lat, ['N', 'S']));
+                boei.long_min = Some(format_coordinate(long, ['E', 'W']));
+                (boei.lat, boei.long) = (Some(lat), Some(long));
+                self.boeien_by_name.insert(name, boei.clone());
+            }
+            CourseUpdate::SetMaxNumber { from, to, max_number } => {
+                let max_length = self.rules.limits.max_path_length;
+                if max_number == 0 || max_number as usize > max_length {
+                    return Err(UursError::InvalidParameter(format!(
+                        "MaxNumber must be between 1 and {max_length}, the limit of the path length"
+                    )));
+                }
+                let starts = self.starts.iter_mut().filter(|start| start.from == from && start.to == to);
+                let starts = starts.map(|start| &mut start.max_number);
+                let rakken = self.rakken.iter_mut().filter(|rak| {
+                    (rak.from == from && rak.to == to) || (rak.from == to && rak.to == from)
+                });
+                let mut changed = false;
+                for number in starts.chain(rakken.map(|rak| &mut rak.max_number)) {
+                    *number = max_number;
+                    changed = true;
+                }
+                if !changed {
+                    return Err(UursError::NoLeg { from, to });
+                }
+            }
+        }
+        Ok(())
     }
 
     /// Get a buoy by name
@@ -330,6 +809,28 @@ impl RegattaData {
         self.boeien.iter().position(|b| b.name == name)
     }
 
+    /// Get the indices of the buoys of a route given by name
+    pub fn route_indices(&self, route: &[String]) -> Result<Vec<usize>, UursError> {
+        route
+            .iter()
+            .map(|name| self.get_boei_index(name).ok_or_else(|| UursError::UnknownBuoy(name.clone())))
+            .collect()
+    }
+
+    /// Names of the buoys on a start or leg which have no coordinates, whose
+    /// legs can't be estimated and which the searches pass by
+    pub fn buoys_without_coordinates(&self) -> Vec<&str> {
+        let on_course = |name: &str| {
+            self.starts.iter().any(|start| start.from == name || start.to == name)
+                || self.rakken.iter().any(|rak| rak.from == name || rak.to == name)
+        };
+        self.boeien
+            .iter()
+            .filter(|boei| !boei.has_coordinates() && on_course(&boei.name))
+            .map(|boei| boei.name.as_str())
+            .collect()
+    }
+
     /// Get all buoys of a specific type
     pub fn get_boeien_by_type(&self, buoy_type: &str) -> Vec<&Boei> {
         self.boeien
@@ -359,32 +860,145 @@ impl RegattaData {
     }
 }
 
+/// Longest buoy name the data may have
+pub const MAX_BUOY_NAME_LENGTH: usize = 32;
+
+/// Check a buoy name of the data: buoy names end up in web pages, maps, and
+/// exported files, so they may only have letters, digits, spaces, '-', '_',
+/// and '.'
+pub fn validate_buoy_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() || name.chars().count() > MAX_BUOY_NAME_LENGTH {
+        return Err(format!("Buoy name must have 1 to {MAX_BUOY_NAME_LENGTH} characters"));
+    }
+    if !name.chars().all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.')) {
+        return Err(format!(
+            "Invalid buoy name {name:?}: only letters, digits, spaces, '-', '_', and '.' are allowed"
+        ));
+    }
+    Ok(())
+}
+
+/// Relative difference between the official distance of a start or leg and
+/// the great circle distance between its buoys above which it is suspicious
+pub const DISTANCE_TOLERANCE: f64 = 0.1;
+
+/// Something suspicious in the data which doesn't stop it from loading
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum DataWarning {
+    /// Several buoys with the same name, which is ambiguous
+    DuplicateBuoy { name: String },
+    /// A start or leg from or to a buoy which is not in boeien.csv
+    UnknownBuoy { file: String, name: String },
+    /// A buoy of the course without coordinates, passed by the searches
+    MissingCoordinates { name: String },
+    /// A buoy on no start or leg
+    UnusedBuoy { name: String },
+    /// A start or leg without distance
+    ZeroDistance { file: String, from: String, to: String },
+    /// A start or leg whose official distance is far off the great circle
+    DistanceMismatch { file: String, from: String, to: String, distance: f64, great_circle: f64 },
+}
+
+impl std::fmt::Display for DataWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::DuplicateBuoy { name } => write!(f, "data/boeien.csv: buoy {name} is listed more than once"),
+            Self::UnknownBuoy { file, name } => {
+                write!(f, "{file}: buoy {name} is not in data/boeien.csv")
+            }
+            Self::MissingCoordinates { name } => {
+                write!(f, "data/boeien.csv: buoy {name} has no coordinates, the searches pass it by")
+            }
+            Self::UnusedBuoy { name } => write!(f, "data/boeien.csv: buoy {name} is on no start or leg"),
+            Self::ZeroDistance { file, from, to } => write!(f, "{file}: {from} - {to} has no distance"),
+            Self::DistanceMismatch { file, from, to, distance, great_circle } => write!(
+                f,
+                "{file}: {from} - {to} is {distance:.2} nm, but the buoys are {great_circle:.2} nm apart"
+            ),
+        }
+    }
+}
+
+impl RegattaData {
+    /// Check the data for mistakes which don't stop it from loading, but
+    /// which silently change the results
+    pub fn warnings(&self) -> Vec<DataWarning> {
+        let mut warnings = Vec::new();
+        let mut seen = std::collections::HashSet::new();
+        for boei in &self.boeien {
+            if !seen.insert(boei.name.as_str()) {
+                warnings.push(DataWarning::DuplicateBuoy { name: boei.name.clone() });
+            }
+        }
+
+        let starts = self.starts.iter().map(|start| ("data/starts.csv", &start.from, &start.to, start.distance));
+        let rakken = self.rakken.iter().map(|rak| ("data/rakken.csv", &rak.from, &rak.to, rak.distance));
+        for (file, from, to, distance) in starts.chain(rakken) {
+            let (source, target) = (self.get_boei(from), self.get_boei(to));
+            for (name, boei) in [(from, source), (to, target)] {
+                if boei.is_none() {
+                    warnings.push(DataWarning::UnknownBuoy { file: file.to_string(), name: name.clone() });
+                }
+            }
+            if distance <= 0.0 {
+                warnings.push(DataWarning::ZeroDistance { file: file.to_string(), from: from.clone(), to: to.clone() });
+                continue;
+            }
+            let (Some(source), Some(target)) = (source.and_then(Boei::coordinates), target.and_then(Boei::coordinates)) else {
+                continue;
+            };
+            let great_circle = distance_nm(source, target);
+            if (distance - great_circle).abs() > DISTANCE_TOLERANCE * great_circle.max(distance) {
+                warnings.push(DataWarning::DistanceMismatch {
+                    file: file.to_string(),
+                    from: from.clone(),
+                    to: to.clone(),
+                    distance,
+                    great_circle,
+                });
+            }
+        }
+
+        for name in self.buoys_without_coordinates() {
+            warnings.push(DataWarning::MissingCoordinates { name: name.to_string() });
+        }
+        let used = |name: &str| {
+            self.starts.iter().any(|start| start.from == name || start.to == name)
+                || self.rakken.iter().any(|rak| rak.from == name || rak.to == name)
+        };
+        for boei in self.boeien.iter().filter(|boei| !used(&boei.name)) {
+            warnings.push(DataWarning::UnusedBuoy { name: boei.name.clone() });
+        }
+        warnings
+    }
+}
+
+/// Load all regatta data from CSV files together with the warnings about it
+pub fn load_regatta_data_with_warnings() -> Result<(RegattaData, Vec<DataWarning>), UursError> {
+    let data = load_regatta_data()?;
+    let warnings = data.warnings();
+    Ok((data, warnings))
+}
+
 /// Load all regatta data from CSV files
-pub fn load_regatta_data() -> Result<RegattaData, Box<dyn Error>> {
+pub fn load_regatta_data() -> Result<RegattaData, UursError> {
     let mut data = RegattaData::new();
 
     // Load boeien data
-    let mut boeien_reader = csv::Reader::from_path("data/boeien.csv")?;
-    for result in boeien_reader.deserialize() {
-        let mut boei: Boei = result?;
-        boei.parse_coordinates()?;
+    for mut boei in read_csv_file::<Boei>("data/boeien.csv")? {
+        validate_buoy_name(&boei.name).map_err(|e| UursError::data_load("data/boeien.csv", e))?;
+        boei.parse_coordinates().map_err(|e| UursError::data_load("data/boeien.csv", e))?;
         data.boeien.push(boei.clone());
         data.boeien_by_name.insert(boei.name.clone(), boei);
     }
 
     // Load starts data
-    let mut starts_reader = csv::Reader::from_path("data/starts.csv")?;
-    for result in starts_reader.deserialize() {
-        let start: Start = result?;
-        data.starts.push(start);
-    }
+    data.starts = read_csv_file("data/starts.csv")?;
 
     // Load rakken data
-    let mut rakken_reader = csv::Reader::from_path("data/rakken.csv")?;
-    for result in rakken_reader.deserialize() {
-        let rak: Rak = result?;
-        data.rakken.push(rak);
-    }
+    data.rakken = read_csv_file("data/rakken.csv")?;
+    data.index_leg_distances();
 
     // Load polar data
     data.polar_data = load_polar_data()?;
@@ -392,19 +1006,104 @@ pub fn load_regatta_data() -> Result<RegattaData, Box<dyn Error>> {
     // Load wind data
     data.wind_data = load_wind_data()?;
 
+    // Load the race clock
+    data.race_clock = load_race_clock()?;
+
+    // Load the rules, which refer to the buoys
+    data.rules = load_rule_set(&data)?;
+
+    // A start or leg is sailed at least once, and not more often than a
+    // route may have legs
+    let max_length = data.rules.limits.max_path_length;
+    let starts = data.starts.iter().map(|start| ("data/starts.csv", &start.from, &start.to, start.max_number));
+    let rakken = data.rakken.iter().map(|rak| ("data/rakken.csv", &rak.from, &rak.to, rak.max_number));
+    for (file, from, to, max_number) in starts.chain(rakken) {
+        if max_number == 0 || max_number as usize > max_length {
+            return Err(UursError::data_load(
+                file,
+                format!("MaxNumber of {from} - {to} must be between 1 and {max_length}, the limit of the path length"),
+            ));
+        }
+    }
+
+    // Load the ratings of the boats, if there are any
+    if std::path::Path::new(RATINGS_FILE).exists() {
+        for rating in read_csv_file::<Rating>(RATINGS_FILE)? {
+            if rating.rating <= 0.0 {
+                return Err(UursError::data_load(RATINGS_FILE, format!("rating of {} must be positive", rating.name)));
+            }
+            data.ratings.insert(rating.name, rating.rating);
+        }
+    }
+
+    // Load the starts of the classes, if the race has several
+    if std::path::Path::new(CLASSES_FILE).exists() {
+        for wave in read_csv_file::<StartWave>(CLASSES_FILE)? {
+            let deadline = data.rules.scoring.deadline;
+            if !(0.0..deadline).contains(&wave.start_offset) {
+                return Err(UursError::data_load(
+                    CLASSES_FILE,
+                    format!("start of class {} must be within the {deadline} hours of the race", wave.class),
+                ));
+            }
+            data.start_offsets.insert(wave.class, wave.start_offset);
+        }
+    }
+
     Ok(data)
 }
 
+/// Optional file with the ratings of the boats
+pub const RATINGS_FILE: &str = "data/ratings.csv";
+
+/// Optional file with the starts of the classes
+pub const CLASSES_FILE: &str = "data/classes.csv";
+
+/// Read all records of a CSV file with a header row
+fn read_csv_file<T: DeserializeOwned>(file: &str) -> Result<Vec<T>, UursError> {
+    let mut reader = csv::Reader::from_path(file).map_err(|e| UursError::data_load(file, e))?;
+    reader
+        .deserialize()
+        .collect::<Result<Vec<T>, _>>()
+        .map_err(|e| UursError::data_load(file, e))
+}
+
+/// Read a data file into a string
+fn read_data_file(file: &str) -> Result<String, UursError> {
+    std::fs::read_to_string(file).map_err(|e| UursError::data_load(file, e))
+}
+
+/// Parse a number in a data file, naming the line and column on failure
+fn parse_field<T: std::str::FromStr>(
+    file: &str,
+    line: usize,
+    column: &str,
+    value: &str,
+) -> Result<T, UursError> {
+    value.trim().parse().map_err(|_| {
+        UursError::data_load(file, format!("line {}: invalid {column} '{value}'", line + 1))
+    })
+}
+
 /// Load polar performance data from CSV file
-fn load_polar_data() -> Result<PolarData, Box<dyn Error>> {
+fn load_polar_data() -> Result<PolarData, UursError> {
+    let content = read_data_file("data/polars.csv")?;
+    parse_polar_data(&content).map_err(|e| match e {
+        UursError::InvalidPolars(message) => UursError::data_load("data/polars.csv", message),
+        e => e,
+    })
+}
+
+/// Parse polar performance data in the format of `data/polars.csv`: a header
+/// row "twa/tws;6;8;..." with the wind speeds, then one row per wind angle
+pub fn parse_polar_data(content: &str) -> Result<PolarData, UursError> {
     let mut polar_data = PolarData::new();
 
-    // Read the CSV file manually since it has a specific format
-    let content = std::fs::read_to_string("data/polars.csv")?;
+    // Read the CSV content manually since it has a specific format
     let lines: Vec<&str> = content.lines().collect();
 
     if lines.is_empty() {
-        return Err("Empty polar data file".into());
+        return Err(UursError::InvalidPolars("no header row".to_string()));
     }
 
     // Parse the first line to get wind speeds
@@ -412,26 +1111,29 @@ fn load_polar_data() -> Result<PolarData, Box<dyn Error>> {
     let wind_speed_parts: Vec<&str> = first_line.split(';').collect();
 
     // Skip the first part (twa/tws) and parse wind speeds
+    let invalid = |line: usize, value: &str| {
+        UursError::InvalidPolars(format!("line {}: invalid number '{value}'", line + 1))
+    };
     for part in wind_speed_parts.iter().skip(1) {
-        let wind_speed: f64 = part.parse()?;
+        let wind_speed: f64 = part.parse().map_err(|_| invalid(0, part))?;
         polar_data.wind_speeds.push(wind_speed);
     }
 
     // Parse the remaining lines to get wind angles and boat speeds
-    for line in lines.iter().skip(1) {
+    for (line_number, line) in lines.iter().enumerate().skip(1) {
         let parts: Vec<&str> = line.split(';').collect();
         if parts.len() != wind_speed_parts.len() {
             continue; // Skip malformed lines
         }
 
         // Parse wind angle (first column)
-        let wind_angle: f64 = parts[0].parse()?;
+        let wind_angle: f64 = parts[0].parse().map_err(|_| invalid(line_number, parts[0]))?;
         polar_data.wind_angles.push(wind_angle);
 
         // Parse boat speeds for this wind angle
         let mut speeds = Vec::new();
         for part in parts.iter().skip(1) {
-            let speed: f64 = part.parse()?;
+            let speed: f64 = part.parse().map_err(|_| invalid(line_number, part))?;
             speeds.push(speed);
         }
         polar_data.boat_speeds.push(speeds);
@@ -441,59 +1143,74 @@ fn load_polar_data() -> Result<PolarData, Box<dyn Error>> {
 }
 
 /// Load wind data from CSV file
-fn load_wind_data() -> Result<WindData, Box<dyn Error>> {
-    let mut wind_data = WindData::new();
+fn load_wind_data() -> Result<WindData, UursError> {
+    const FILE: &str = "data/wind.csv";
+    let mut conditions = Vec::new();
 
     // Read the CSV file manually since it has a specific format
-    let content = std::fs::read_to_string("data/wind.csv")?;
+    let content = read_data_file(FILE)?;
     let lines: Vec<&str> = content.lines().collect();
 
     if lines.is_empty() {
-        return Err("Empty wind data file".into());
+        return Err(UursError::data_load(FILE, "no header row"));
     }
 
     // Parse the header line to get column names
     let header_parts: Vec<&str> = lines[0].split(';').collect();
-    let time_idx = header_parts
-        .iter()
-        .position(|&s| s == "Time")
-        .ok_or("Time column not found")?;
-    let wind_speed_idx = header_parts
-        .iter()
-        .position(|&s| s == "Wind_speed")
-        .ok_or("Wind_speed column not found")?;
-    let wind_angle_idx = header_parts
-        .iter()
-        .position(|&s| s == "Wind_angle")
-        .ok_or("Wind_angle column not found")?;
+    let column = |name: &str| {
+        header_parts
+            .iter()
+            .position(|&s| s == name)
+            .ok_or_else(|| UursError::data_load(FILE, format!("{name} column not found")))
+    };
+    let time_idx = column("Time")?;
+    let wind_speed_idx = column("Wind_speed")?;
+    let wind_angle_idx = column("Wind_angle")?;
 
     // Parse the data lines
-    for line in lines.iter().skip(1) {
+    for (line_number, line) in lines.iter().enumerate().skip(1) {
         let parts: Vec<&str> = line.split(';').collect();
         if parts.len() < 3 {
             continue; // Skip malformed lines
         }
 
-        let time: u32 = parts[time_idx].parse()?;
-        let wind_speed: f64 = parts[wind_speed_idx].parse()?;
-        let wind_angle: f64 = parts[wind_angle_idx].parse()?;
+        let time: u32 = parse_field(FILE, line_number, "Time", parts[time_idx])?;
+        let wind_speed: f64 = parse_field(FILE, line_number, "Wind_speed", parts[wind_speed_idx])?;
+        let wind_angle: f64 = parse_field(FILE, line_number, "Wind_angle", parts[wind_angle_idx])?;
 
-        wind_data.conditions.push(WindCondition {
+        conditions.push(WindCondition {
             time,
             wind_speed,
             wind_angle,
         });
-        wind_data.conditions_by_hour.insert(
-            time,
-            WindCondition {
-                time,
-                wind_speed,
-                wind_angle,
-            },
-        );
     }
 
-    Ok(wind_data)
+    Ok(WindData::from_conditions(conditions))
+}
+
+/// Load the mapping from race hours to wall clock times from CSV file
+fn load_race_clock() -> Result<RaceClock, UursError> {
+    const FILE: &str = "data/zeiten.csv";
+    let mut race_clock = RaceClock::new();
+
+    // Read the CSV file manually since it has a specific format
+    let content = read_data_file(FILE)?;
+
+    // The first line is a header, every further line is "hour;HHMM"
+    for (line_number, line) in content.lines().enumerate().skip(1) {
+        let parts: Vec<&str> = line.split(';').collect();
+        if parts.len() < 2 {
+            continue; // Skip malformed lines
+        }
+
+        let time: u32 = parse_field(FILE, line_number, "hour", parts[0])?;
+        let real: u32 = parse_field(FILE, line_number, "time", parts[1])?;
+        race_clock.entries.push(ClockEntry { time, real });
+    }
+
+    race_clock.entries.sort_by_key(|e| e.time);
+
+    Ok(race_clock)
 }
 
 /// Edge data for the regatta graph
@@ -627,6 +1344,7 @@ mod tests {
             long_min_sec: None,
             lat_min: Some("53° 5,020'".to_string()),
             long_min: Some("5° 20,293'".to_string()),
+            rounding: None,
             lat: None,
             long: None,
         };
@@ -669,6 +1387,10 @@ mod tests {
             ("53° 5,020'", 53.0 + 5.020 / 60.0),
             ("0° 0,000'", 0.0),
             ("90° 30,500'", 90.0 + 30.5 / 60.0),
+            ("53° 5,020' N", 53.0 + 5.020 / 60.0),
+            ("5° 7,420'W", -(5.0 + 7.420 / 60.0)),
+            ("-5° 7,420'", -(5.0 + 7.420 / 60.0)),
+            ("\"33° 52' 10.80\" S\"", -(33.0 + 52.18 / 60.0)),
         ];
 
         for (input, expected) in test_cases {
@@ -687,6 +1409,83 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parse_errors() {
+        for input in [
+            "53 5,020'",
+            "N° 5,020'",
+            "53° x'",
+            "53° 5' x'",
+            "-53° 5,020' S",
+            "53° 65,000'",
+            "53° 5' 60.5\"",
+            "inf° 5,020'",
+            "181° 5,020'",
+            "- -53° 5,020'",
+        ] {
+            assert!(
+                matches!(
+                    Boei::parse_coordinate_string(input),
+                    Err(UursError::CoordinateParse { .. })
+                ),
+                "'{input}' should not parse"
+            );
+        }
+
+        assert!(Boei::parse_coordinate("53° 5,020' E", "NS").is_err());
+        assert!(Boei::parse_coordinate("90° 30,500'", "NS").is_err());
+
+        let error = parse_polar_data("twa/tws;6;8\n52;4.1;x\n").unwrap_err();
+        assert!(matches!(error, UursError::InvalidPolars(_)));
+        assert_eq!(error.to_string(), "Invalid polar data: line 2: invalid number 'x'");
+        assert!(matches!(parse_polar_data(""), Err(UursError::InvalidPolars(_))));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn test_coordinate_round_trip(value in -180.0f64..180.0, latitude: bool) {
+            let hemispheres = if latitude { ['N', 'S'] } else { ['E', 'W'] };
+            let formatted = format_coordinate(value, hemispheres);
+            let parsed = Boei::parse_coordinate_string(&formatted).unwrap();
+            proptest::prop_assert!((parsed - value).abs() < 0.001 / 60.0);
+            proptest::prop_assert_eq!(format_coordinate(parsed, hemispheres), formatted);
+        }
+
+        #[test]
+        fn test_coordinate_variants(degrees in 0u32..180, minutes in 0u32..60, hundredths in 0u32..6000) {
+            // Seconds and decimal minutes, with a sign or a hemisphere, quoted or not
+            let seconds = format!("{degrees}° {minutes}' {}.{:02}\"", hundredths / 100, hundredths % 100);
+            let expected = f64::from(degrees) + (f64::from(minutes) + f64::from(hundredths) / 6000.0) / 60.0;
+            for (input, sign) in [
+                (seconds.clone(), 1.0),
+                (format!("\"{seconds}\""), 1.0),
+                (format!("{seconds} S"), -1.0),
+                (format!("-{seconds}"), -1.0),
+                (format!("{degrees}° {minutes},{:04}'E", hundredths * 10 / 6), 1.0),
+            ] {
+                let parsed = Boei::parse_coordinate_string(&input).unwrap();
+                proptest::prop_assert!((parsed - sign * expected).abs() < 1e-3 / 60.0, "{input}: {parsed}");
+            }
+        }
+
+        #[test]
+        fn test_coordinate_parser_never_panics(input in "\\PC{0,30}") {
+            if let Ok(value) = Boei::parse_coordinate_string(&input) {
+                proptest::prop_assert!(value.is_finite());
+            }
+        }
+
+        #[test]
+        fn test_batched_boat_speeds(lookups in proptest::collection::vec((0.0f64..180.0, 0.0f64..30.0), 0..50)) {
+            let polar_data = load_polar_data().unwrap();
+            let speeds = polar_data.get_boat_speeds(&lookups);
+            proptest::prop_assert_eq!(speeds.len(), lookups.len());
+            for (&(angle, speed), batched) in lookups.iter().zip(speeds) {
+                proptest::prop_assert_eq!(polar_data.get_boat_speed(angle, speed), batched);
+            }
+        }
+    }
+
     #[test]
     fn test_coordinate_convenience_methods() {
         let mut boei = Boei {
@@ -697,6 +1496,7 @@ mod tests {
             long_min_sec: None,
             lat_min: Some("53° 5,020'".to_string()),
             long_min: Some("5° 20,293'".to_string()),
+            rounding: None,
             lat: None,
             long: None,
         };
@@ -754,6 +1554,77 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_override_wind_hours() {
+        let condition = |time, wind_speed, wind_angle| WindCondition { time, wind_speed, wind_angle };
+        let mut wind_data = WindData::from_conditions(vec![condition(0, 10.0, 180.0), condition(1, 12.0, 200.0)]);
+        wind_data.override_hours(&[condition(1, 20.0, 360.0)]).unwrap();
+        assert_eq!(wind_data.conditions[1].wind_speed, 20.0);
+        assert_eq!(wind_data.get_wind_at_hour(1).unwrap().wind_angle, 0.0);
+        assert!((wind_data.get_wind_at_time(0.5).unwrap().wind_speed - 15.0).abs() < 1e-9);
+
+        // Nothing changes if one of the hours is invalid
+        for invalid in [condition(2, 10.0, 180.0), condition(0, -1.0, 180.0), condition(0, 10.0, f64::NAN)] {
+            assert!(wind_data.override_hours(&[condition(0, 5.0, 90.0), invalid]).is_err());
+        }
+        assert_eq!(wind_data.get_wind_at_hour(0).unwrap().wind_speed, 10.0);
+    }
+
+    #[test]
+    fn test_course_update() {
+        let mut data = load_regatta_data().unwrap();
+        let (oeve, wv12) = (data.get_boei_index("OEVE").unwrap(), data.get_boei_index("WV12").unwrap());
+        let leg = data.rakken.iter().position(|rak| rak.from == "WV12" || rak.to == "WV12").unwrap();
+        let (from, to) = (data.rakken[leg].from.clone(), data.rakken[leg].to.clone());
+
+        data.apply_update(CourseUpdate::MoveBuoy { name: "WV12".to_string(), lat: 52.9, long: -0.5 }).unwrap();
+        assert_eq!(data.boeien[wv12].coordinates(), Some((52.9, -0.5)));
+        assert_eq!(data.get_boei("WV12").unwrap().coordinates(), Some((52.9, -0.5)));
+        assert_eq!(data.get_boei_index("OEVE"), Some(oeve));
+        let mut reparsed = data.boeien[wv12].clone();
+        reparsed.parse_coordinates().unwrap();
+        assert!((reparsed.long.unwrap() + 0.5).abs() < 1e-4);
+
+        // A leg may be named in either direction
+        data.apply_update(CourseUpdate::SetMaxNumber { from: to.clone(), to: from, max_number: 1 }).unwrap();
+        assert_eq!(data.rakken[leg].max_number, 1);
+
+        let invalid = [
+            CourseUpdate::MoveBuoy { name: "NOWHERE".to_string(), lat: 52.9, long: 5.0 },
+            CourseUpdate::MoveBuoy { name: "WV12".to_string(), lat: f64::NAN, long: 5.0 },
+            CourseUpdate::SetMaxNumber { from: "OEVE".to_string(), to: "OEVE".to_string(), max_number: 1 },
+            CourseUpdate::SetMaxNumber { from: to.clone(), to: to.clone(), max_number: 0 },
+        ];
+        for update in invalid {
+            assert!(data.clone().apply_update(update).is_err());
+        }
+    }
+
+    #[test]
+    fn test_wind_hours() {
+        let condition = |time, wind_speed, wind_angle| WindCondition {
+            time,
+            wind_speed,
+            wind_angle,
+        };
+        // A gap at hour 2 in the forecast
+        let mut wind = WindData::from_conditions(vec![condition(0, 10.0, 170.0), condition(1, 14.0, 190.0), condition(3, 8.0, 90.0)]);
+        let between = wind.get_wind_at_time(0.25).unwrap();
+        assert_eq!((between.time, between.wind_speed, between.wind_angle), (0, 11.0, 175.0));
+        assert_eq!(wind.get_wind_at_time(1.5).unwrap().wind_speed, 14.0);
+        assert!(wind.get_wind_at_time(2.5).is_none());
+        assert!(wind.get_wind_at_hour(4).is_none());
+
+        wind.start_offset = 1.0;
+        assert_eq!(wind.get_wind_at_time(2.0).unwrap().wind_speed, 8.0);
+        wind.override_hours(&[condition(1, 12.0, 90.0)]).unwrap();
+        assert_eq!(wind.get_wind_at_time(0.5).unwrap().wind_speed, 12.0);
+        assert_eq!(wind.get_wind_at_hour(1).unwrap().wind_angle, 90.0);
+
+        // Without a forecast there is no wind to look up
+        assert!(WindData::new().get_wind_at_time(0.0).is_none());
+    }
+
     #[test]
     fn test_build_regatta_graph() {
         let data = load_regatta_data().unwrap();
@@ -784,6 +1655,19 @@ mod tests {
             }
         }
 
+        // The neighbors of a buoy are the ends of its edges
+        use petgraph::visit::EdgeRef;
+        for boei in &data.boeien {
+            let index = data.get_boei_index(&boei.name).unwrap();
+            let neighbors = data.neighbors(index);
+            let ends: std::collections::HashSet<usize> = graph
+                .edges_directed(node_indices[&boei.name], Direction::Outgoing)
+                .map(|edge| edge.target().index())
+                .collect();
+            assert_eq!(neighbors.len(), ends.len());
+            assert!(neighbors.iter().all(|&(to, distance)| data.leg_distance(index, to) == Some(distance)));
+        }
+
         // Check that edge data contains distance and speed
         for edge_idx in graph.edge_indices() {
             let edge_weight = graph.edge_weight(edge_idx).unwrap();
@@ -824,4 +1708,80 @@ mod tests {
         let speed = polar_data.get_boat_speed(135.0, 20.0);
         assert!((speed - 8.83).abs() < 0.01);
     }
+
+    #[test]
+    fn test_race_clock() {
+        let data = load_regatta_data().unwrap();
+        let clock = &data.race_clock;
+
+        assert!(!clock.entries.is_empty(), "Race clock should be loaded");
+
+        // The race starts at 18:00 and wraps around midnight
+        assert_eq!(clock.format_clock(0.0).as_deref(), Some("18:00"));
+        assert_eq!(clock.format_clock(1.5).as_deref(), Some("19:30"));
+        assert_eq!(clock.format_clock(6.25).as_deref(), Some("00:15"));
+        assert_eq!(clock.clock_minutes_at(12.0), Some(6.0 * 60.0));
+
+        // Wall clock times map back to the race time
+        assert_eq!(clock.race_time_at(18.0 * 60.0), Some(0.0));
+        assert_eq!(clock.race_time_at(15.0), Some(6.25));
+        assert_eq!(clock.race_time_at(17.0 * 60.0 + 30.0), Some(23.5));
+
+        // Times as users give them
+        let mut clock = clock.clone();
+        let resolve = |clock: &RaceClock, time: &str| clock.resolve(&time.parse().unwrap());
+        assert_eq!(resolve(&clock, "13.5").unwrap(), 13.5);
+        assert_eq!(resolve(&clock, "00:15").unwrap(), 6.25);
+        assert!(resolve(&clock, "2026-06-13 00:15").is_err());
+        clock.start = NaiveDateTime::parse_from_str("2026-06-12 18:00", "%Y-%m-%d %H:%M").ok();
+        assert_eq!(resolve(&clock, "2026-06-13T00:15").unwrap(), 6.25);
+        assert!("25:00".parse::<RaceTime>().is_err());
+        assert_eq!(clock.describe(6.25), "6.25h (00:15)");
+    }
+
+    #[test]
+    fn test_select_class() {
+        let mut data = load_regatta_data().unwrap();
+        data.start_offsets.insert("Multihull".to_string(), 0.5);
+        assert!(data.select_class("Sportboat").is_err());
+        let first_start = data.clone();
+        data.select_class("Multihull").unwrap();
+
+        // The class starts at 18:30, its hour 1 is hour 1.5 of the race
+        assert_eq!(data.race_clock.format_clock(0.0).as_deref(), Some("18:30"));
+        assert_eq!(data.race_clock.race_time_at(19.0 * 60.0), Some(0.5));
+        let wind = data.wind_data.get_wind_at_time(1.0).unwrap();
+        let expected = first_start.wind_data.get_wind_at_time(1.5).unwrap();
+        assert_eq!(wind.wind_speed, expected.wind_speed);
+        assert_eq!(wind.wind_angle, expected.wind_angle);
+    }
+
+    #[test]
+    fn test_buoy_names() {
+        assert!(validate_buoy_name("MH4-M1").is_ok());
+        assert!(validate_buoy_name("Oude Veer 2.1").is_ok());
+        assert!(validate_buoy_name("").is_err());
+        assert!(validate_buoy_name("   ").is_err());
+        assert!(validate_buoy_name(&"A".repeat(MAX_BUOY_NAME_LENGTH + 1)).is_err());
+        assert!(validate_buoy_name("<script>").is_err());
+        assert!(validate_buoy_name("A\"B").is_err());
+        assert!(validate_buoy_name("A\nB").is_err());
+    }
+
+    #[test]
+    fn test_data_warnings() {
+        let (mut data, warnings) = load_regatta_data_with_warnings().unwrap();
+        assert!(warnings.contains(&DataWarning::UnusedBuoy { name: "FINISH".to_string() }));
+        let count = warnings.len();
+
+        // A second WV12, a leg without distance, and one far too long
+        data.boeien.push(data.get_boei("WV12").unwrap().clone());
+        data.rakken[0].distance = 0.0;
+        data.rakken[1].distance *= 2.0;
+        let warnings = data.warnings();
+        assert!(warnings.contains(&DataWarning::DuplicateBuoy { name: "WV12".to_string() }));
+        assert!(warnings.iter().any(|w| matches!(w, DataWarning::ZeroDistance { from, .. } if *from == data.rakken[0].from)));
+        assert!(warnings.iter().any(|w| matches!(w, DataWarning::DistanceMismatch { from, .. } if *from == data.rakken[1].from)));
+        assert_eq!(warnings.len(), count + 3);
+    }
 }
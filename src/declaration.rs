@@ -0,0 +1,258 @@
+use crate::data::RegattaData;
+use crate::error::UursError;
+use crate::export::{csv_text, escape_xml};
+use crate::live::{RoundingCheck, check_rounding, match_track, sailed_path};
+use crate::scoring::{Score, score_path};
+use crate::track::TrackPoint;
+use serde::Serialize;
+use std::fmt::Write;
+
+/// A leg as declared to the organizer
+#[derive(Clone, Debug, Serialize)]
+pub struct DeclaredLeg {
+    pub number: usize,
+    pub from: String,
+    pub to: String,
+    pub distance: f64,          // official distance in nm
+    pub start: f64,             // passing of `from` in hours since race start
+    pub rounding: f64,          // passing of `to` in hours since race start
+    pub counted: f64,           // distance counted by the rules in nm
+    pub remark: Option<String>, // why the leg doesn't count
+    pub check: Option<RoundingCheck>, // how the track rounded `to`
+}
+
+/// The declaration of a crew after the race: every leg sailed with its
+/// times, and the rule violations found on the way
+#[derive(Clone, Debug, Serialize)]
+pub struct Declaration {
+    pub legs: Vec<DeclaredLeg>,
+    pub score: Score,
+    pub violations: Vec<String>,
+}
+
+/// Fill in the declaration of a recorded race
+///
+/// The track is matched onto the course, and the legs found are scored by
+/// the rules of the data. Besides legs which don't count, a declaration
+/// which doesn't begin with a start, gaps between legs where the track lost
+/// the course, roundings which can't be verified on the track, because the
+/// buoy was not passed close enough or on the side required by the data, and
+/// finishing late are violations.
+pub fn declare_race(data: &RegattaData, track: &[TrackPoint]) -> Result<Declaration, UursError> {
+    let sailed = match_track(data, track);
+    let Some(first) = sailed.first() else {
+        return Err(UursError::InvalidParameter("No leg of the course found on the track".to_string()));
+    };
+
+    let path = sailed_path(data, &sailed);
+    let score = score_path(data, &path, &data.rules.scoring);
+
+    let name = |buoy: usize| &data.boeien[buoy].name;
+    let mut violations = Vec::new();
+    if !data.starts.iter().any(|start| start.from == *name(first.from) && start.to == *name(first.to)) {
+        violations.push(format!("The first leg {} - {} is not a start", name(first.from), name(first.to)));
+    }
+    for pair in sailed.windows(2) {
+        if pair[0].to != pair[1].from {
+            violations.push(format!(
+                "No leg found between {} at {} and {} at {}",
+                name(pair[0].to),
+                data.race_clock.describe(pair[0].end),
+                name(pair[1].from),
+                data.race_clock.describe(pair[1].start)
+            ));
+        }
+    }
+    for (i, leg) in score.legs.iter().enumerate().filter(|(_, leg)| !leg.partial) {
+        if let Some(remark) = &leg.remark {
+            violations.push(format!("Leg {} {} - {} doesn't count: {remark}", i + 1, leg.from, leg.to));
+        }
+    }
+    // The rounding of a buoy ends at the latest with the leg from it
+    let checks: Vec<Option<RoundingCheck>> = sailed
+        .iter()
+        .enumerate()
+        .map(|(i, leg)| {
+            let until = sailed.get(i + 1).map_or(f64::INFINITY, |next| next.end);
+            check_rounding(data, track, leg.to, leg.start, until)
+        })
+        .collect();
+    for (i, check) in checks.iter().enumerate() {
+        if let Some(problem) = check.as_ref().and_then(RoundingCheck::problem) {
+            violations.push(format!("Leg {}: {problem}", i + 1));
+        }
+    }
+    if score.late_penalty > 0.0 {
+        violations.push(format!("Finished late, penalty of {:.2} nm", score.late_penalty));
+    }
+
+    let legs = sailed
+        .iter()
+        .zip(&score.legs)
+        .zip(checks)
+        .enumerate()
+        .map(|(i, ((leg, scored), check))| DeclaredLeg {
+            number: i + 1,
+            from: scored.from.clone(),
+            to: scored.to.clone(),
+            distance: scored.distance,
+            start: leg.start,
+            rounding: leg.end,
+            counted: scored.counted,
+            remark: scored.remark.clone(),
+            check,
+        })
+        .collect();
+    Ok(Declaration { legs, score, violations })
+}
+
+/// Format a time of the race for the organizer: the date and time if the
+/// race start is known, the wall clock time otherwise
+fn declared_time(data: &RegattaData, time: f64) -> String {
+    // Rounded to the minute like the clock times
+    match data.race_clock.date_at(time + 0.5 / 60.0) {
+        Some(date) => date.format("%d-%m-%Y %H:%M").to_string(),
+        None => data.race_clock.format_clock(time).unwrap_or_else(|| format!("{time:.2}h")),
+    }
+}
+
+/// Write a declaration as CSV like the data files of the organizer: every
+/// field quoted and decimal commas
+pub fn declaration_to_csv(data: &RegattaData, declaration: &Declaration) -> Result<String, UursError> {
+    let decimal = |value: f64| format!("{value:.4}").replace('.', ",");
+    let mut writer = csv::WriterBuilder::new()
+        .quote_style(csv::QuoteStyle::Always)
+        .from_writer(Vec::new());
+    writer
+        .write_record(["Leg", "From", "To", "Distance", "Start", "Rounding", "Counted", "Remark"])
+        .map_err(UursError::export)?;
+    for leg in &declaration.legs {
+        writer.write_record([
+            leg.number.to_string(),
+            leg.from.clone(),
+            leg.to.clone(),
+            decimal(leg.distance),
+            declared_time(data, leg.start),
+            declared_time(data, leg.rounding),
+            decimal(leg.counted),
+            leg.remark.clone().unwrap_or_default(),
+        ])
+        .map_err(UursError::export)?;
+    }
+    csv_text(writer)
+}
+
+/// Write a declaration as HTML document to print and sign
+pub fn declaration_to_html(
+    data: &RegattaData,
+    declaration: &Declaration,
+    boat: &str,
+) -> Result<String, UursError> {
+    let rounded = |leg: &DeclaredLeg| match &leg.check {
+        Some(check) => format!(
+            "{} at {:.2} nm{}",
+            check.side.map_or("–".to_string(), |side| side.to_string()),
+            check.distance,
+            if check.verified() { "" } else { " ✗" }
+        ),
+        None => "–".to_string(),
+    };
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, r#"<html lang="en"><head><meta charset="UTF-8">"#)?;
+    writeln!(html, "<title>Declaration {}</title>", escape_xml(boat))?;
+    writeln!(
+        html,
+        "<style>body {{ font-family: sans-serif; margin: 2em; color: #2c3e50; }} \
+         table {{ border-collapse: collapse; margin: 1em 0; }} \
+         th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: right; }} \
+         th {{ background: #ecf0f1; }} td.leg {{ text-align: left; }} \
+         .signature {{ margin-top: 4em; border-top: 1px solid #2c3e50; width: 20em; }}</style>"
+    )?;
+    writeln!(html, "</head><body>")?;
+    writeln!(html, "<h1>Declaration {}</h1>", escape_xml(boat))?;
+
+    writeln!(html, "<table>")?;
+    writeln!(
+        html,
+        "<tr><th>#</th><th>From</th><th>To</th><th>Distance (nm)</th><th>Start</th><th>Rounding</th>\
+         <th>Counted (nm)</th><th>Rounded</th><th>Remark</th></tr>"
+    )?;
+    for leg in &declaration.legs {
+        writeln!(
+            html,
+            r#"<tr><td>{}</td><td class="leg">{}</td><td class="leg">{}</td><td>{:.2}</td><td>{}</td><td>{}</td><td>{:.2}</td><td class="leg">{}</td><td class="leg">{}</td></tr>"#,
+            leg.number,
+            escape_xml(&leg.from),
+            escape_xml(&leg.to),
+            leg.distance,
+            declared_time(data, leg.start),
+            declared_time(data, leg.rounding),
+            leg.counted,
+            rounded(leg),
+            escape_xml(leg.remark.as_deref().unwrap_or("")),
+        )?;
+    }
+    writeln!(html, "</table>")?;
+
+    let score = &declaration.score;
+    writeln!(html, "<ul>")?;
+    writeln!(html, "<li>Distance sailed: {:.2} nm</li>", score.raw_distance)?;
+    if score.late_penalty > 0.0 {
+        writeln!(html, "<li>Penalty for finishing late: {:.2} nm</li>", score.late_penalty)?;
+    }
+    writeln!(html, "<li><strong>Declared distance: {:.2} nm</strong></li>", score.scored_distance)?;
+    writeln!(html, "</ul>")?;
+
+    if !declaration.violations.is_empty() {
+        writeln!(html, "<h2>Rule violations</h2><ul>")?;
+        for violation in &declaration.violations {
+            writeln!(html, "<li>{}</li>", escape_xml(violation))?;
+        }
+        writeln!(html, "</ul>")?;
+    }
+    writeln!(html, r#"<p class="signature">Signature of the skipper</p>"#)?;
+    writeln!(html, "</body></html>")?;
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::RoundingSide;
+
+    #[test]
+    fn test_declare_race() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let route = ["OEVE", "WV12", "GVS3-M8", "WV12"].map(|name| data.get_boei_index(name).unwrap());
+        let path = crate::optimize::evaluate_route(&data, &route, 0.0).unwrap();
+        let track = crate::track::track_along(&data, &path);
+
+        let declaration = declare_race(&data, &track).unwrap();
+        assert_eq!(declaration.legs.len(), 3);
+        assert!(declaration.violations.is_empty());
+        assert!((declaration.score.scored_distance - path.total_distance).abs() < 1e-9);
+        assert!((declaration.legs[1].rounding - path.steps[1].end_time).abs() < 0.05);
+        assert!(declaration.legs.iter().all(|leg| leg.check.as_ref().is_some_and(RoundingCheck::verified)));
+
+        let csv = declaration_to_csv(&data, &declaration).unwrap();
+        assert!(csv.starts_with("\"Leg\",\"From\",\"To\",\"Distance\""));
+        assert!(csv.contains("\"2,7160\""));
+        assert_eq!(csv.lines().count(), 4);
+
+        // Without the start the declaration is flagged
+        let declaration = declare_race(&data, &track[11..]).unwrap();
+        assert_eq!(declaration.violations.len(), 1);
+
+        // So is a buoy kept on the wrong side, if the data requires one
+        let mut data = data;
+        let wv12 = data.get_boei_index("WV12").unwrap();
+        let side = declare_race(&data, &track).unwrap().legs[0].check.as_ref().unwrap().side.unwrap();
+        data.boeien[wv12].rounding = Some(match side {
+            RoundingSide::Port => RoundingSide::Starboard,
+            RoundingSide::Starboard => RoundingSide::Port,
+        });
+        let declaration = declare_race(&data, &track).unwrap();
+        assert!(declaration.violations.iter().any(|violation| violation.contains("instead of")));
+    }
+}
@@ -0,0 +1,226 @@
+use crate::data::{RegattaData, WindCondition, WindData};
+use crate::error::UursError;
+use crate::simulate::{Simulation, SimulationOptions, simulate_route};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rand_distr::StandardNormal;
+use rayon::prelude::*;
+use serde::Serialize;
+
+/// Random deviations of the real wind from the forecast
+///
+/// The errors of speed and direction each follow an autoregressive process
+/// over the hours of the forecast, so a realization which is windier than
+/// forecast in one hour tends to stay windier in the next.
+#[derive(Clone, Debug, Serialize)]
+pub struct WindPerturbation {
+    pub speed_sigma: f64,     // standard deviation of the wind speed in knots
+    pub direction_sigma: f64, // standard deviation of the wind direction in degrees
+    pub correlation: f64,     // of the errors of consecutive hours, 0 to 1
+}
+
+impl Default for WindPerturbation {
+    fn default() -> Self {
+        Self {
+            speed_sigma: 2.0,
+            direction_sigma: 15.0,
+            correlation: 0.8,
+        }
+    }
+}
+
+impl WindPerturbation {
+    /// Draw one realization of the wind forecast
+    pub fn realize<R: Rng + ?Sized>(&self, wind: &WindData, rng: &mut R) -> WindData {
+        let innovation = (1.0 - self.correlation * self.correlation).max(0.0).sqrt();
+        let mut speed_error: f64 = rng.sample(StandardNormal);
+        let mut direction_error: f64 = rng.sample(StandardNormal);
+        let mut conditions = Vec::with_capacity(wind.get_all_conditions().len());
+        for (i, condition) in wind.get_all_conditions().iter().enumerate() {
+            if i > 0 {
+                speed_error = self.correlation * speed_error + innovation * rng.sample::<f64, _>(StandardNormal);
+                direction_error =
+                    self.correlation * direction_error + innovation * rng.sample::<f64, _>(StandardNormal);
+            }
+            conditions.push(WindCondition {
+                time: condition.time,
+                wind_speed: (condition.wind_speed + self.speed_sigma * speed_error).max(0.0),
+                wind_angle: (condition.wind_angle + self.direction_sigma * direction_error).rem_euclid(360.0),
+            });
+        }
+        let mut realization = WindData::from_conditions(conditions);
+        realization.start_offset = wind.start_offset;
+        realization
+    }
+}
+
+/// How many realizations of the wind are drawn and how
+#[derive(Clone, Debug, Serialize)]
+pub struct EnsembleOptions {
+    pub perturbation: WindPerturbation,
+    pub runs: usize,
+    pub seed: u64, // the same seed gives the same realizations, run i draws with seed + i
+}
+
+/// The 10th, 50th, and 90th percentile of a quantity over the runs
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Percentiles {
+    pub p10: f64,
+    pub p50: f64,
+    pub p90: f64,
+}
+
+impl Percentiles {
+    fn of(mut values: Vec<f64>) -> Self {
+        values.sort_by(f64::total_cmp);
+        let at = |p: f64| {
+            let rank = p * (values.len() - 1) as f64;
+            let (lower, upper) = (rank.floor() as usize, rank.ceil() as usize);
+            values[lower] + (rank - lower as f64) * (values[upper] - values[lower])
+        };
+        Self {
+            p10: at(0.1),
+            p50: at(0.5),
+            p90: at(0.9),
+        }
+    }
+}
+
+/// Distance sailed on the route at a full hour over the runs
+#[derive(Clone, Debug, Serialize)]
+pub struct DistanceBand {
+    pub time: f64,            // in hours since race start
+    pub distance: Percentiles, // in nm
+}
+
+/// What the statistics need of one run: when it finished and the distance
+/// sailed at every full hour up to then
+struct RunSummary {
+    end_time: f64,
+    distances: Vec<f64>, // from the first full hour after the start on
+}
+
+impl RunSummary {
+    fn new(simulation: &Simulation, start_time: f64) -> Self {
+        let mut distances = Vec::new();
+        let mut hour = start_time.ceil();
+        while hour <= simulation.end_time.ceil() {
+            distances.push(simulation.position_at(hour).0);
+            hour += 1.0;
+        }
+        Self {
+            end_time: simulation.end_time,
+            distances,
+        }
+    }
+
+    /// The distance at the full hour with an index, the whole distance of
+    /// the route after the finish
+    fn distance(&self, index: usize) -> f64 {
+        self.distances.get(index).or(self.distances.last()).copied().unwrap_or_default()
+    }
+}
+
+/// Result of simulating a route in many realizations of the wind
+#[derive(Clone, Debug, Serialize)]
+pub struct Ensemble {
+    pub runs: usize,
+    pub seed: u64,
+    pub end_time: Percentiles, // in hours since race start
+    pub hours: Vec<DistanceBand>,
+}
+
+/// Simulate a route in random realizations of the wind forecast
+pub fn simulate_ensemble(
+    data: &RegattaData,
+    route: &[usize],
+    start_time: f64,
+    options: &SimulationOptions,
+    ensemble: &EnsembleOptions,
+) -> Result<Ensemble, UursError> {
+    let (perturbation, runs, seed) = (&ensemble.perturbation, ensemble.runs, ensemble.seed);
+    if runs == 0 {
+        return Err(UursError::InvalidParameter("At least one run is needed".to_string()));
+    }
+    if !(0.0..=1.0).contains(&perturbation.correlation) {
+        return Err(UursError::InvalidParameter("The correlation must be between 0 and 1".to_string()));
+    }
+
+    // Each run draws its wind with a seed of its own, so the runs are
+    // simulated on all cores in any order and still give the same ensemble.
+    // A worker clones the data once for all the runs it takes.
+    let summaries = (0..runs)
+        .into_par_iter()
+        .map_init(
+            || data.clone(),
+            |run_data, run| {
+                let mut rng = StdRng::seed_from_u64(seed.wrapping_add(run as u64));
+                run_data.wind_data = perturbation.realize(&data.wind_data, &mut rng);
+                let simulation = simulate_route(run_data, route, start_time, options)?;
+                Ok(RunSummary::new(&simulation, start_time))
+            },
+        )
+        .collect::<Result<Vec<_>, UursError>>()?;
+
+    let last = summaries.iter().map(|summary| summary.end_time).fold(start_time, f64::max);
+    let mut hours = Vec::new();
+    let mut hour = start_time.ceil();
+    while hour <= last.ceil() {
+        let index = hours.len();
+        hours.push(DistanceBand {
+            time: hour,
+            distance: Percentiles::of(summaries.iter().map(|summary| summary.distance(index)).collect()),
+        });
+        hour += 1.0;
+    }
+    Ok(Ensemble {
+        runs,
+        seed,
+        end_time: Percentiles::of(summaries.iter().map(|summary| summary.end_time).collect()),
+        hours,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_simulate_ensemble() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let route = ["OEVE", "WV12", "GVS3-M8"].map(|name| data.get_boei_index(name).unwrap());
+        let options = SimulationOptions::default();
+
+        let ensemble_options = EnsembleOptions {
+            perturbation: WindPerturbation::default(),
+            runs: 20,
+            seed: 7,
+        };
+        let ensemble = simulate_ensemble(&data, &route, 0.0, &options, &ensemble_options).unwrap();
+        assert!(ensemble.end_time.p10 <= ensemble.end_time.p50);
+        assert!(ensemble.end_time.p50 <= ensemble.end_time.p90);
+        assert!(ensemble.end_time.p10 < ensemble.end_time.p90);
+        assert!(ensemble.hours.iter().all(|band| band.distance.p10 <= band.distance.p90));
+
+        // Reproducible with the same seed, on any number of threads
+        let single_thread = rayon::ThreadPoolBuilder::new().num_threads(1).build().unwrap();
+        let again = single_thread.install(|| simulate_ensemble(&data, &route, 0.0, &options, &ensemble_options)).unwrap();
+        assert_eq!(again.end_time.p50, ensemble.end_time.p50);
+        assert_eq!(again.hours.len(), ensemble.hours.len());
+        assert!(again.hours.iter().zip(&ensemble.hours).all(|(a, b)| a.distance.p90 == b.distance.p90));
+
+        // Without any perturbation every run sails the forecast
+        let forecast_options = EnsembleOptions {
+            perturbation: WindPerturbation {
+                speed_sigma: 0.0,
+                direction_sigma: 0.0,
+                correlation: 0.5,
+            },
+            runs: 3,
+            seed: 7,
+        };
+        let forecast = simulate_ensemble(&data, &route, 0.0, &options, &forecast_options).unwrap();
+        let simulation = simulate_route(&data, &route, 0.0, &options).unwrap();
+        assert!((forecast.end_time.p90 - simulation.end_time).abs() < 1e-9);
+    }
+}
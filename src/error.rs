@@ -0,0 +1,80 @@
+use std::fmt::Display;
+use thiserror::Error;
+
+/// Errors of the library, telling mistakes in the input apart from failures
+/// to load the data or render output
+#[derive(Debug, Error)]
+pub enum UursError {
+    /// A data file is missing or malformed
+    #[error("Error loading {file}: {message}")]
+    DataLoad { file: String, message: String },
+    /// A buoy coordinate is in none of the supported formats
+    #[error("Invalid coordinate '{value}': {reason}")]
+    CoordinateParse { value: String, reason: String },
+    /// Polar data, from a file or uploaded, is malformed
+    #[error("Invalid polar data: {0}")]
+    InvalidPolars(String),
+    /// A buoy name or index which is not in the data
+    #[error("Buoy '{0}' not found")]
+    UnknownBuoy(String),
+    /// A buoy without coordinates, whose legs can't be estimated
+    #[error("Buoy '{0}' has no coordinates")]
+    MissingCoordinates(String),
+    /// Consecutive buoys of a route without a start or leg between them
+    #[error("No start or leg from '{from}' to '{to}'")]
+    NoLeg { from: String, to: String },
+    /// A route which sails a start or leg more often than allowed
+    #[error("The route sails from '{from}' to '{to}' more than {max_number} times")]
+    LegOverused { from: String, to: String, max_number: u32 },
+    /// A route which rounds buoys in an order the rules forbid
+    #[error("The route rounds {} in a row, which the rules forbid", .0.join(" - "))]
+    ForbiddenSequence(Vec<String>),
+    /// A parameter which is out of range or makes no sense
+    #[error("{0}")]
+    InvalidParameter(String),
+    /// A search estimated to explore more nodes than allowed
+    #[error("The search would explore about {estimated:.0} nodes, the limit is {limit}; use fewer steps")]
+    SearchLimitExceeded { estimated: f64, limit: u64 },
+    /// A client runs the maximum number of searches already
+    #[error("At most {limit} searches may run at the same time per client; wait for the running ones to finish")]
+    TooManySearches { limit: usize },
+    /// A search stopped through its monitor
+    #[error("Search cancelled")]
+    Cancelled,
+    /// A page template failed to render
+    #[error("Template '{template}' could not be rendered: {message}")]
+    TemplateRender { template: String, message: String },
+    /// A recorded track is malformed
+    #[error("Invalid track: {0}")]
+    InvalidTrack(String),
+    /// Tracks, sessions, or plans could not be stored or read back
+    #[error("Storage failed: {0}")]
+    Storage(String),
+    /// A route or table could not be written in an export format
+    #[error("Export failed: {0}")]
+    Export(String),
+    /// Reading or writing a file failed
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+}
+
+impl UursError {
+    /// Error for a data file which could not be read or parsed
+    pub fn data_load(file: &str, message: impl Display) -> Self {
+        Self::DataLoad {
+            file: file.to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Error for an export format which could not be written
+    pub fn export(message: impl Display) -> Self {
+        Self::Export(message.to_string())
+    }
+}
+
+impl From<std::fmt::Error> for UursError {
+    fn from(e: std::fmt::Error) -> Self {
+        Self::export(e)
+    }
+}
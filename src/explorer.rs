@@ -0,0 +1,212 @@
+use crate::data::RegattaData;
+use crate::error::UursError;
+use crate::optimize::{SearchConstraints, estimate_leg_performance, validate_route};
+use crate::params::RaceHours;
+use crate::storage::Registry;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Most explorations kept at the same time, the oldest ones are dropped beyond
+const MAX_EXPLORATIONS: usize = 1000;
+
+/// A leg of an explored route with the estimate it was added with
+#[derive(Clone, Debug, Serialize)]
+pub struct ExploredLeg {
+    pub from: String,
+    pub to: String,
+    pub distance: f64,   // in nm
+    pub speed: f64,      // estimated, in knots
+    pub start_time: f64, // in hours since race start
+    pub end_time: f64,
+}
+
+/// A leg which may be added next, estimated at the time the route reaches its
+/// last buoy
+#[derive(Clone, Debug, Serialize)]
+pub struct ExploreOption {
+    pub to: String,
+    pub distance: f64,       // in nm
+    pub speed: f64,          // estimated, in knots
+    pub travel_time: f64,    // in hours
+    pub arrival_time: f64,   // in hours since race start
+    pub wind_direction: f64, // in degrees
+    pub wind_speed: f64,     // in knots
+    pub late: bool,          // arrives after the deadline of the rules
+}
+
+/// A route put together leg by leg on the course map, each leg estimated in
+/// the forecast wind at the time the route reaches its buoy
+///
+/// The buoys are kept by name, so an exploration survives a reload of the data.
+#[derive(Clone, Debug, Serialize)]
+pub struct Exploration {
+    pub id: u64,
+    pub start_time: f64,    // in hours since race start
+    pub route: Vec<String>, // buoys of the route, starting with the first one
+    pub legs: Vec<ExploredLeg>,
+    pub created: DateTime<Utc>,
+}
+
+impl Exploration {
+    /// Time the route reaches its last buoy in hours since race start
+    pub fn time(&self) -> f64 {
+        self.legs.last().map_or(self.start_time, |leg| leg.end_time)
+    }
+
+    /// Distance of the legs of the route in nm
+    pub fn distance(&self) -> f64 {
+        self.legs.iter().fold(0.0, |distance, leg| distance + leg.distance)
+    }
+
+    /// The legs which may be added next with their estimates, ordered by name.
+    /// Legs to buoys without coordinates can't be estimated and are left out.
+    pub fn options(&self, data: &RegattaData) -> Vec<ExploreOption> {
+        let Ok(mut route) = data.route_indices(&self.route) else {
+            return Vec::new();
+        };
+        let current = route[route.len() - 1];
+        let time = self.time();
+        let mut options = Vec::new();
+        for (&(from, to), &distance) in &data.leg_distances {
+            if from != current {
+                continue;
+            }
+            route.push(to);
+            let valid = validate_route(data, &route, &SearchConstraints::default()).is_ok();
+            route.pop();
+            if !valid {
+                continue;
+            }
+            if let Ok(performance) = estimate_leg_performance(data, from, to, time) {
+                options.push(ExploreOption {
+                    to: data.boeien[to].name.clone(),
+                    distance,
+                    speed: performance.estimated_speed,
+                    travel_time: performance.travel_time,
+                    arrival_time: performance.arrival_time,
+                    wind_direction: performance.wind_direction,
+                    wind_speed: performance.wind_speed,
+                    late: performance.arrival_time > data.rules.scoring.deadline,
+                });
+            }
+        }
+        options.sort_by(|a, b| a.to.cmp(&b.to));
+        options
+    }
+
+    /// Add the leg from the last buoy of the route to another one
+    pub fn extend(&mut self, data: &RegattaData, to: &str) -> Result<&ExploredLeg, UursError> {
+        let mut route = data.route_indices(&self.route)?;
+        let from = route[route.len() - 1];
+        let target = data.get_boei_index(to).ok_or_else(|| UursError::UnknownBuoy(to.to_string()))?;
+        route.push(target);
+        validate_route(data, &route, &SearchConstraints::default())?;
+
+        let time = self.time();
+        let performance = estimate_leg_performance(data, from, target, time)?;
+        self.legs.push(ExploredLeg {
+            from: data.boeien[from].name.clone(),
+            to: to.to_string(),
+            distance: performance.official_distance.unwrap_or(performance.great_circle_distance),
+            speed: performance.estimated_speed,
+            start_time: time,
+            end_time: performance.arrival_time,
+        });
+        self.route.push(to.to_string());
+        Ok(&self.legs[self.legs.len() - 1])
+    }
+
+    /// Take back the last leg of the route, false if there is none
+    pub fn retract(&mut self) -> bool {
+        if self.legs.pop().is_none() {
+            return false;
+        }
+        self.route.pop();
+        true
+    }
+}
+
+/// The explorations of all users of the path explorer, kept in memory only
+#[derive(Debug, Default)]
+pub struct Explorations {
+    store: Registry<Exploration, MAX_EXPLORATIONS>,
+}
+
+impl Explorations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start an exploration at any buoy with legs at a time in hours since
+    /// race start
+    pub fn start(&self, data: &RegattaData, start: &str, time: f64) -> Result<Exploration, UursError> {
+        let index = data.get_boei_index(start).ok_or_else(|| UursError::UnknownBuoy(start.to_string()))?;
+        if !data.leg_distances.keys().any(|&(from, _)| from == index) {
+            return Err(UursError::InvalidParameter(format!("There is no leg from '{start}'")));
+        }
+        let time = RaceHours::new(data, time)?.hours();
+        Ok(self.store.insert(|id| Exploration {
+            id,
+            start_time: time,
+            route: vec![start.to_string()],
+            legs: Vec::new(),
+            created: Utc::now(),
+        }))
+    }
+
+    /// Get an exploration by id
+    pub fn get(&self, id: u64) -> Option<Exploration> {
+        self.store.get(id)
+    }
+
+    /// Add a leg to an exploration, `None` if there is no exploration with the id
+    pub fn extend(&self, id: u64, data: &RegattaData, to: &str) -> Result<Option<Exploration>, UursError> {
+        let extended = self.store.update(id, |exploration| exploration.extend(data, to).map(|_| ()))?;
+        Ok(extended.map(|(exploration, ())| exploration))
+    }
+
+    /// Take back the last leg of an exploration, `None` if there is no
+    /// exploration with the id
+    pub fn retract(&self, id: u64) -> Result<Option<Exploration>, UursError> {
+        let retracted = self.store.update(id, |exploration| {
+            if exploration.retract() {
+                Ok(())
+            } else {
+                Err(UursError::InvalidParameter("The route has no leg to take back".to_string()))
+            }
+        })?;
+        Ok(retracted.map(|(exploration, ())| exploration))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exploration() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let explorations = Explorations::new();
+        assert!(explorations.start(&data, "NOPE", 0.0).is_err());
+        assert!(explorations.start(&data, "OEVE", 30.0).is_err());
+
+        let exploration = explorations.start(&data, "OEVE", 0.0).unwrap();
+        let options = exploration.options(&data);
+        assert_eq!(options.len(), 1);
+        assert_eq!(options[0].to, "WV12");
+        assert!(options[0].speed > 0.0 && !options[0].late);
+
+        assert!(explorations.extend(exploration.id, &data, "GVS3-M8").is_err());
+        let extended = explorations.extend(exploration.id, &data, "WV12").unwrap().unwrap();
+        assert_eq!(extended.route, ["OEVE", "WV12"]);
+        assert_eq!(extended.distance(), options[0].distance);
+        assert!((extended.time() - options[0].arrival_time).abs() < 1e-9);
+        assert!(extended.options(&data).iter().all(|option| option.to != "OEVE"));
+
+        let retracted = explorations.retract(exploration.id).unwrap().unwrap();
+        assert_eq!(retracted.route, ["OEVE"]);
+        assert_eq!(retracted.time(), 0.0);
+        assert!(explorations.retract(exploration.id).is_err());
+        assert!(explorations.retract(exploration.id + 1).unwrap().is_none());
+    }
+}
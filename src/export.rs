@@ -0,0 +1,444 @@
+use crate::calendar::IcsExporter;
+use crate::data::{Boei, RegattaData};
+use crate::error::UursError;
+use crate::optimize::Path;
+use crate::simulate::Simulation;
+use serde_json::json;
+use std::fmt::Write;
+
+/// A file format for navigation software which a route can be exported to
+pub trait RouteExporter {
+    /// Name of the format, as given to `export-route --format`
+    fn name(&self) -> &'static str;
+
+    /// Write a path as route with the given name
+    fn export(&self, data: &RegattaData, path: &Path, name: &str) -> Result<String, UursError>;
+}
+
+/// GPX 1.1 route for OpenCPN, Navionics, and chartplotters
+pub struct GpxExporter;
+
+impl RouteExporter for GpxExporter {
+    fn name(&self) -> &'static str {
+        "gpx"
+    }
+
+    fn export(&self, data: &RegattaData, path: &Path, name: &str) -> Result<String, UursError> {
+        path_to_gpx(data, path, name)
+    }
+}
+
+/// KML document with the planned times for Google Earth
+pub struct KmlExporter;
+
+impl RouteExporter for KmlExporter {
+    fn name(&self) -> &'static str {
+        "kml"
+    }
+
+    fn export(&self, data: &RegattaData, path: &Path, name: &str) -> Result<String, UursError> {
+        path_to_kml(data, path, name)
+    }
+}
+
+/// GeoJSON feature collection for web maps like Leaflet or Mapbox
+pub struct GeoJsonExporter;
+
+impl RouteExporter for GeoJsonExporter {
+    fn name(&self) -> &'static str {
+        "geojson"
+    }
+
+    fn export(&self, data: &RegattaData, path: &Path, name: &str) -> Result<String, UursError> {
+        serde_json::to_string_pretty(&path_to_geojson(data, path, name)?).map_err(UursError::export)
+    }
+}
+
+/// Mark table as imported by Expedition: comma separated with the position
+/// in decimal degrees, one row per buoy in the order of the route
+pub struct ExpeditionExporter;
+
+impl RouteExporter for ExpeditionExporter {
+    fn name(&self) -> &'static str {
+        "expedition"
+    }
+
+    fn export(&self, data: &RegattaData, path: &Path, _name: &str) -> Result<String, UursError> {
+        let mut writer = csv::Writer::from_writer(Vec::new());
+        writer.write_record(["Name", "Lat", "Lon"]).map_err(UursError::export)?;
+        for (boei, lat, long) in route_positions(data, path)? {
+            writer
+                .write_record([boei.name.clone(), format!("{lat:.6}"), format!("{long:.6}")])
+                .map_err(UursError::export)?;
+        }
+        csv_text(writer)
+    }
+}
+
+/// Waypoint table as imported by Adrena: semicolon separated with the
+/// position in degrees and decimal minutes, one row per buoy in the order of
+/// the route
+pub struct AdrenaExporter;
+
+impl RouteExporter for AdrenaExporter {
+    fn name(&self) -> &'static str {
+        "adrena"
+    }
+
+    fn export(&self, data: &RegattaData, path: &Path, _name: &str) -> Result<String, UursError> {
+        let mut writer = csv::WriterBuilder::new().delimiter(b';').from_writer(Vec::new());
+        writer.write_record(["Name", "Latitude", "Longitude"]).map_err(UursError::export)?;
+        for (boei, lat, long) in route_positions(data, path)? {
+            writer
+                .write_record([
+                    boei.name.clone(),
+                    degrees_minutes(lat, ['N', 'S']),
+                    degrees_minutes(long, ['E', 'W']),
+                ])
+                .map_err(UursError::export)?;
+        }
+        csv_text(writer)
+    }
+}
+
+/// All formats routes can be exported to
+pub fn route_exporters() -> Vec<Box<dyn RouteExporter>> {
+    vec![
+        Box::new(GpxExporter),
+        Box::new(KmlExporter),
+        Box::new(IcsExporter::default()),
+        Box::new(GeoJsonExporter),
+        Box::new(ExpeditionExporter),
+        Box::new(AdrenaExporter),
+    ]
+}
+
+/// Look up a route format by its name
+pub fn route_exporter(name: &str) -> Option<Box<dyn RouteExporter>> {
+    route_exporters().into_iter().find(|exporter| exporter.name() == name)
+}
+
+/// The buoys of a path in the order they are sailed with their positions,
+/// failing if a buoy has no coordinates
+fn route_positions<'a>(data: &'a RegattaData, path: &Path) -> Result<Vec<(&'a Boei, f64, f64)>, UursError> {
+    let first = path.steps.first().map(|step| step.from);
+    first
+        .into_iter()
+        .chain(path.steps.iter().map(|step| step.to))
+        .map(|buoy_index| {
+            let boei = &data.boeien[buoy_index];
+            let (lat, long) = boei
+                .coordinates()
+                .ok_or_else(|| UursError::MissingCoordinates(boei.name.clone()))?;
+            Ok((boei, lat, long))
+        })
+        .collect()
+}
+
+/// Text of a CSV table written to memory
+pub(crate) fn csv_text(writer: csv::Writer<Vec<u8>>) -> Result<String, UursError> {
+    let bytes = writer.into_inner().map_err(UursError::export)?;
+    String::from_utf8(bytes).map_err(UursError::export)
+}
+
+/// Format a latitude or longitude in degrees and decimal minutes, like
+/// "52 55.498 N", with the hemisphere letters for positive and negative
+fn degrees_minutes(value: f64, hemispheres: [char; 2]) -> String {
+    let hemisphere = if value < 0.0 { hemispheres[1] } else { hemispheres[0] };
+    let minutes = (value.abs() * 60.0 * 1000.0).round() / 1000.0;
+    let degrees = (minutes / 60.0).floor();
+    format!("{:.0} {:06.3} {hemisphere}", degrees, minutes - degrees * 60.0)
+}
+
+/// Write paths as a CSV leg table with one row per step
+///
+/// The `path` column numbers the paths starting at 1, so several paths can
+/// share one table. Clock columns are empty if the race clock doesn't cover
+/// the time.
+pub fn paths_to_csv(data: &RegattaData, paths: &[Path]) -> Result<String, UursError> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "path",
+        "step",
+        "from",
+        "to",
+        "distance_nm",
+        "speed_kn",
+        "start_time_h",
+        "end_time_h",
+        "start_clock",
+        "end_clock",
+    ])
+    .map_err(UursError::export)?;
+
+    for (path_index, path) in paths.iter().enumerate() {
+        for (step_index, step) in path.steps.iter().enumerate() {
+            writer.write_record([
+                (path_index + 1).to_string(),
+                (step_index + 1).to_string(),
+                data.boeien[step.from].name.clone(),
+                data.boeien[step.to].name.clone(),
+                format!("{:.2}", step.distance),
+                format!("{:.2}", step.speed),
+                format!("{:.3}", step.start_time),
+                format!("{:.3}", step.end_time),
+                data.race_clock.format_clock(step.start_time).unwrap_or_default(),
+                data.race_clock.format_clock(step.end_time).unwrap_or_default(),
+            ])
+            .map_err(UursError::export)?;
+        }
+    }
+
+    csv_text(writer)
+}
+
+/// Write a path as GPX 1.1 route with one route point per buoy
+///
+/// Each route point carries the leg sailed to it with its planned times in its
+/// comment and the estimated arrival in its description. Fails if a buoy of
+/// the path has no coordinates.
+pub fn path_to_gpx(data: &RegattaData, path: &Path, name: &str) -> Result<String, UursError> {
+    let mut gpx = String::new();
+    writeln!(gpx, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        gpx,
+        r#"<gpx version="1.1" creator="uurs24 {}" xmlns="http://www.topografix.com/GPX/1/1">"#,
+        env!("CARGO_PKG_VERSION")
+    )?;
+    writeln!(gpx, "  <rte>")?;
+    writeln!(gpx, "    <name>{}</name>", escape_xml(name))?;
+
+    // The start buoy followed by the destination of every step
+    let first = path.steps.first().map(|step| (step.from, step.start_time, None));
+    let points = first
+        .into_iter()
+        .chain(path.steps.iter().map(|step| (step.to, step.end_time, Some(step))));
+    for (buoy_index, time, step) in points {
+        let boei = &data.boeien[buoy_index];
+        let (lat, long) = boei
+            .coordinates()
+            .ok_or_else(|| UursError::MissingCoordinates(boei.name.clone()))?;
+        writeln!(gpx, r#"    <rtept lat="{lat:.6}" lon="{long:.6}">"#)?;
+        writeln!(gpx, "      <name>{}</name>", escape_xml(&boei.name))?;
+        let comment = match step {
+            Some(step) => format!(
+                "Leg {} - {}, {:.2} nm at {:.2} kn, {} - {}",
+                data.boeien[step.from].name,
+                data.boeien[step.to].name,
+                step.distance,
+                step.speed,
+                data.race_clock.describe(step.start_time),
+                data.race_clock.describe(step.end_time)
+            ),
+            None => format!("Start at {}", data.race_clock.describe(time)),
+        };
+        writeln!(gpx, "      <cmt>{}</cmt>", escape_xml(&comment))?;
+        let eta = match data.race_clock.format_clock(time) {
+            Some(clock) => format!("ETA {clock} (race time {time:.2} h)"),
+            None => format!("ETA race time {time:.2} h"),
+        };
+        writeln!(gpx, "      <desc>{eta}</desc>")?;
+        writeln!(gpx, "    </rtept>")?;
+    }
+
+    writeln!(gpx, "  </rte>")?;
+    writeln!(gpx, "</gpx>")?;
+    Ok(gpx)
+}
+
+/// Write a path as KML document with a placemark per buoy and a `gx:Track`
+/// of the planned positions for the time slider of Google Earth
+///
+/// The time stamps are local times derived from the race start, so this
+/// fails without `race_clock.start`, and if a buoy of the path has no
+/// coordinates.
+pub fn path_to_kml(data: &RegattaData, path: &Path, name: &str) -> Result<String, UursError> {
+    // The start buoy followed by the destination of every step
+    let first = path.steps.first().map(|step| (step.from, step.start_time));
+    let mut points = Vec::with_capacity(path.steps.len() + 1);
+    for (buoy_index, time) in first
+        .into_iter()
+        .chain(path.steps.iter().map(|step| (step.to, step.end_time)))
+    {
+        let boei = &data.boeien[buoy_index];
+        let (lat, long) = boei
+            .coordinates()
+            .ok_or_else(|| UursError::MissingCoordinates(boei.name.clone()))?;
+        let date = data.race_clock.date_at(time).ok_or_else(|| {
+            UursError::InvalidParameter(
+                "The time stamps need the date and time of the race start, see --race-start".to_string(),
+            )
+        })?;
+        points.push((boei, lat, long, date.format("%Y-%m-%dT%H:%M:%S").to_string()));
+    }
+
+    let mut kml = String::new();
+    writeln!(kml, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        kml,
+        r#"<kml xmlns="http://www.opengis.net/kml/2.2" xmlns:gx="http://www.google.com/kml/ext/2.2">"#
+    )?;
+    writeln!(kml, "  <Document>")?;
+    writeln!(kml, "    <name>{}</name>", escape_xml(name))?;
+    for (boei, lat, long, when) in &points {
+        writeln!(kml, "    <Placemark>")?;
+        writeln!(kml, "      <name>{}</name>", escape_xml(&boei.name))?;
+        writeln!(kml, "      <TimeStamp><when>{when}</when></TimeStamp>")?;
+        writeln!(kml, "      <Point><coordinates>{long:.6},{lat:.6},0</coordinates></Point>")?;
+        writeln!(kml, "    </Placemark>")?;
+    }
+    writeln!(kml, "    <Placemark>")?;
+    writeln!(kml, "      <name>{}</name>", escape_xml(name))?;
+    writeln!(kml, "      <gx:Track>")?;
+    for (_, _, _, when) in &points {
+        writeln!(kml, "        <when>{when}</when>")?;
+    }
+    for (_, lat, long, _) in &points {
+        writeln!(kml, "        <gx:coord>{long:.6} {lat:.6} 0</gx:coord>")?;
+    }
+    writeln!(kml, "      </gx:Track>")?;
+    writeln!(kml, "    </Placemark>")?;
+    writeln!(kml, "  </Document>")?;
+    writeln!(kml, "</kml>")?;
+    Ok(kml)
+}
+
+/// Write a path as GeoJSON feature collection with a line string per step
+///
+/// Every feature carries the buoys, distance, speed, and planned times of its
+/// step as properties, the collection the name of the route. Fails if a buoy
+/// of the path has no coordinates.
+pub fn path_to_geojson(data: &RegattaData, path: &Path, name: &str) -> Result<serde_json::Value, UursError> {
+    let coordinates = |buoy: usize| {
+        let boei = &data.boeien[buoy];
+        boei.coordinates()
+            .map(|(lat, long)| [long, lat])
+            .ok_or_else(|| UursError::MissingCoordinates(boei.name.clone()))
+    };
+    let features = path
+        .steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| {
+            Ok(json!({
+                "type": "Feature",
+                "geometry": {
+                    "type": "LineString",
+                    "coordinates": [coordinates(step.from)?, coordinates(step.to)?],
+                },
+                "properties": {
+                    "step": i + 1,
+                    "from": data.boeien[step.from].name,
+                    "to": data.boeien[step.to].name,
+                    "distance": step.distance,
+                    "speed": step.speed,
+                    "start_time": step.start_time,
+                    "end_time": step.end_time,
+                    "start_clock": data.race_clock.format_clock(step.start_time),
+                    "end_clock": data.race_clock.format_clock(step.end_time),
+                },
+            }))
+        })
+        .collect::<Result<Vec<_>, UursError>>()?;
+
+    Ok(json!({
+        "type": "FeatureCollection",
+        "name": name,
+        "features": features,
+    }))
+}
+
+/// Write a simulation as GeoJSON feature collection for animating it
+///
+/// The first feature is the line string of the simulated track, whose
+/// properties list the time, speed, and distance of every point in the same
+/// order as its coordinates. A point feature per leg marks the rounding at its
+/// end with the time.
+pub fn simulation_to_geojson(data: &RegattaData, simulation: &Simulation, name: &str) -> serde_json::Value {
+    let samples = &simulation.samples;
+    let mut features = vec![json!({
+        "type": "Feature",
+        "geometry": {
+            "type": "LineString",
+            "coordinates": samples.iter().map(|sample| [sample.long, sample.lat]).collect::<Vec<_>>(),
+        },
+        "properties": {
+            "name": name,
+            "times": samples.iter().map(|sample| sample.time).collect::<Vec<_>>(),
+            "speeds": samples.iter().map(|sample| sample.speed_over_ground).collect::<Vec<_>>(),
+            "distances": samples.iter().map(|sample| sample.distance).collect::<Vec<_>>(),
+        },
+    })];
+    for (i, leg) in simulation.legs.iter().enumerate() {
+        if let Some((lat, long)) = data.boeien[leg.to].coordinates() {
+            features.push(json!({
+                "type": "Feature",
+                "geometry": { "type": "Point", "coordinates": [long, lat] },
+                "properties": {
+                    "step": i + 1,
+                    "buoy": data.boeien[leg.to].name,
+                    "time": leg.end_time,
+                    "clock": data.race_clock.format_clock(leg.end_time),
+                },
+            }));
+        }
+    }
+
+    json!({
+        "type": "FeatureCollection",
+        "name": name,
+        "features": features,
+    })
+}
+
+/// Escape the characters with a special meaning in XML text and attributes
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_xml() {
+        assert_eq!(escape_xml("A & <B> \"C\""), "A &amp; &lt;B&gt; &quot;C&quot;");
+        assert_eq!(escape_xml("SPORT-A"), "SPORT-A");
+    }
+
+    #[test]
+    fn test_degrees_minutes() {
+        assert_eq!(degrees_minutes(52.924967, ['N', 'S']), "52 55.498 N");
+        assert_eq!(degrees_minutes(-4.999999, ['E', 'W']), "5 00.000 W");
+        assert!(route_exporter("adrena").is_some());
+        assert!(route_exporter("pdf").is_none());
+    }
+
+    #[test]
+    fn test_path_to_kml() {
+        let mut data = crate::data::load_regatta_data().unwrap();
+        let route = [
+            data.get_boei_index("OEVE").unwrap(),
+            data.get_boei_index("WV12").unwrap(),
+        ];
+        let path = crate::optimize::evaluate_route(&data, &route, 1.5).unwrap();
+        // The time stamps need the date of the race start
+        assert!(path_to_kml(&data, &path, "Test").is_err());
+
+        data.race_clock.start = Some("2026-06-12T18:00:00".parse().unwrap());
+        let kml = path_to_kml(&data, &path, "Test").unwrap();
+        assert!(kml.contains("<when>2026-06-12T19:30:00</when>"));
+        assert_eq!(kml.matches("<gx:coord>").count(), 2);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::data::PolarData;
+use crate::storage::Storage;
+use crate::track::Track;
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::sync::{Arc, RwLock};
+
+/// Longest boat name accepted
+const MAX_NAME_LENGTH: usize = 32;
+
+/// A boat of the fleet with its own polars, track, and planning session
+#[derive(Debug)]
+pub struct Boat {
+    pub name: String,
+    pub registration: u64, // changes whenever the boat is registered again
+    pub polar: Option<PolarData>, // `None` sails with the polars of the data files
+    pub class: Option<String>, // class of data/classes.csv whose start the clock of the boat counts from
+    pub session: Option<u64>, // planning session holding the plan of the boat
+    pub track: Arc<Track>,
+    pub registered: DateTime<Utc>,
+}
+
+#[derive(Debug, Default)]
+struct Boats {
+    next_registration: u64,
+    boats: BTreeMap<String, Arc<Boat>>,
+}
+
+/// All boats registered with the server. The tracks of the boats are kept
+/// in a storage under their names, so a boat registered again after a
+/// restart continues its track.
+#[derive(Debug)]
+pub struct Fleet {
+    storage: Arc<dyn Storage>,
+    boats: RwLock<Boats>,
+}
+
+impl Fleet {
+    pub fn new(storage: Arc<dyn Storage>) -> Self {
+        Self {
+            storage,
+            boats: RwLock::default(),
+        }
+    }
+
+    /// Register a boat or replace the polars, class, and session of a
+    /// registered one, which keeps its track
+    pub fn register(
+        &self,
+        name: &str,
+        polar: Option<PolarData>,
+        class: Option<String>,
+        session: Option<u64>,
+    ) -> Result<Arc<Boat>, Box<dyn Error>> {
+        validate_boat_name(name)?;
+        let mut boats = self.boats.write().unwrap();
+        let track = match boats.boats.get(name) {
+            Some(boat) => boat.track.clone(),
+            None => Arc::new(Track::with_storage(self.storage.clone(), Some(name))?),
+        };
+        boats.next_registration += 1;
+        let boat = Arc::new(Boat {
+            name: name.to_string(),
+            registration: boats.next_registration,
+            polar,
+            class,
+            session,
+            track,
+            registered: Utc::now(),
+        });
+        boats.boats.insert(name.to_string(), boat.clone());
+        Ok(boat)
+    }
+
+    /// Get a boat by name
+    pub fn get(&self, name: &str) -> Option<Arc<Boat>> {
+        self.boats.read().unwrap().boats.get(name).cloned()
+    }
+
+    /// List all boats ordered by name
+    pub fn list(&self) -> Vec<Arc<Boat>> {
+        self.boats.read().unwrap().boats.values().cloned().collect()
+    }
+
+    /// Remove a boat, its track stays in the storage
+    pub fn remove(&self, name: &str) -> Option<Arc<Boat>> {
+        self.boats.write().unwrap().boats.remove(name)
+    }
+}
+
+/// Boat names end up in URLs and file names, so only letters, digits, '-'
+/// and '_' are allowed
+pub fn validate_boat_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+        return Err(format!("Boat name must have 1 to {MAX_NAME_LENGTH} characters"));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        return Err(format!(
+            "Invalid boat name '{name}': only letters, digits, '-' and '_' are allowed"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::track::TrackPoint;
+
+    #[test]
+    fn test_register_keeps_track() {
+        let fleet = Fleet::new(Arc::new(crate::storage::MemoryStorage::new()));
+        let boat = fleet.register("Zeezwaluw", None, None, None).unwrap();
+        let point = TrackPoint {
+            lat: 52.9,
+            long: 5.1,
+            time: 1.0,
+            speed: None,
+            course: None,
+            received: Utc::now(),
+        };
+        boat.track.add(point).unwrap();
+
+        let again = fleet.register("Zeezwaluw", Some(PolarData::new()), None, Some(3)).unwrap();
+        assert!(again.registration > boat.registration);
+        assert_eq!(again.track.points_since(0.0).len(), 1);
+        assert_eq!(fleet.list().len(), 1);
+
+        assert!(fleet.register("../etc", None, None, None).is_err());
+        assert!(fleet.remove("Zeezwaluw").is_some());
+        assert!(fleet.get("Zeezwaluw").is_none());
+    }
+}
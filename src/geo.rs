@@ -0,0 +1,103 @@
+/// Mean radius of the earth in nautical miles
+pub const EARTH_RADIUS_NM: f64 = 3440.065;
+
+/// Great circle distance between two positions in nautical miles
+pub fn distance_nm(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, long1) = (from.0.to_radians(), from.1.to_radians());
+    let (lat2, long2) = (to.0.to_radians(), to.1.to_radians());
+    let a = ((lat2 - lat1) / 2.0).sin().powi(2)
+        + lat1.cos() * lat2.cos() * ((long2 - long1) / 2.0).sin().powi(2);
+    2.0 * EARTH_RADIUS_NM * a.sqrt().asin()
+}
+
+/// Initial bearing of the great circle from one position to another in
+/// degrees, between 0 and 360
+pub fn initial_bearing(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (lat1, lat2) = (from.0.to_radians(), to.0.to_radians());
+    let d_long = (to.1 - from.1).to_radians();
+    let bearing = (d_long.sin() * lat2.cos())
+        .atan2(lat1.cos() * lat2.sin() - lat1.sin() * lat2.cos() * d_long.cos());
+    bearing.to_degrees().rem_euclid(360.0)
+}
+
+/// Bearing of the great circle on arrival at `to` in degrees
+pub fn final_bearing(from: (f64, f64), to: (f64, f64)) -> f64 {
+    (initial_bearing(to, from) + 180.0).rem_euclid(360.0)
+}
+
+/// Mean of the initial and final bearing from one position to another in
+/// degrees, the course to steer on average over a leg
+pub fn mean_bearing(from: (f64, f64), to: (f64, f64)) -> f64 {
+    let (initial, last) = (initial_bearing(from, to).to_radians(), final_bearing(from, to).to_radians());
+    (initial.sin() + last.sin()).atan2(initial.cos() + last.cos()).to_degrees().rem_euclid(360.0)
+}
+
+/// Distance of a position from the great circle through `from` and `to` in
+/// nautical miles, positive to the right of the course, negative to the left
+pub fn cross_track_nm(position: (f64, f64), from: (f64, f64), to: (f64, f64)) -> f64 {
+    let angular = distance_nm(from, position) / EARTH_RADIUS_NM;
+    let off_course = (initial_bearing(from, position) - initial_bearing(from, to)).to_radians();
+    (angular.sin() * off_course.sin()).asin() * EARTH_RADIUS_NM
+}
+
+/// Distance from `from` along the great circle to `to` to the point closest
+/// to a position in nautical miles, negative if the position is behind `from`
+pub fn along_track_nm(position: (f64, f64), from: (f64, f64), to: (f64, f64)) -> f64 {
+    let angular = distance_nm(from, position) / EARTH_RADIUS_NM;
+    let off_course = (initial_bearing(from, position) - initial_bearing(from, to)).to_radians();
+    (angular.sin() * off_course.cos()).atan2(angular.cos()) * EARTH_RADIUS_NM
+}
+
+/// Position reached from `from` sailing a distance in nautical miles on the
+/// great circle with an initial bearing in degrees
+pub fn destination(from: (f64, f64), bearing: f64, distance: f64) -> (f64, f64) {
+    let (lat1, long1) = (from.0.to_radians(), from.1.to_radians());
+    let (bearing, angular) = (bearing.to_radians(), distance / EARTH_RADIUS_NM);
+    let lat2 = (lat1.sin() * angular.cos() + lat1.cos() * angular.sin() * bearing.cos()).asin();
+    let long2 = long1
+        + (bearing.sin() * angular.sin() * lat1.cos()).atan2(angular.cos() - lat1.sin() * lat2.sin());
+    (lat2.to_degrees(), (long2.to_degrees() + 540.0).rem_euclid(360.0) - 180.0)
+}
+
+/// Absolute difference between two directions in degrees, between 0 and 180
+pub fn angle_between(a: f64, b: f64) -> f64 {
+    let difference = (a - b).rem_euclid(360.0);
+    difference.min(360.0 - difference)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_great_circle() {
+        // One minute of latitude is one nautical mile
+        assert!((distance_nm((52.0, 5.0), (52.5, 5.0)) - 30.0).abs() < 0.1);
+        assert!((initial_bearing((52.0, 5.0), (52.5, 5.0)) - 0.0).abs() < 1e-9);
+        assert!((initial_bearing((52.5, 5.0), (52.0, 5.0)) - 180.0).abs() < 1e-9);
+
+        // Sailing east the great circle bends south of the parallel
+        let (from, to) = ((52.0, 4.0), (52.0, 6.0));
+        assert!(initial_bearing(from, to) < 90.0 && final_bearing(from, to) > 90.0);
+        assert!((mean_bearing(from, to) - 90.0).abs() < 1e-6);
+        assert_eq!(angle_between(350.0, 10.0), 20.0);
+
+        // There and back again
+        let there = destination(from, 45.0, 12.0);
+        assert!((distance_nm(from, there) - 12.0).abs() < 1e-6);
+        assert!((initial_bearing(from, there) - 45.0).abs() < 1e-6);
+        let back = destination(there, final_bearing(from, there) + 180.0, 12.0);
+        assert!(distance_nm(from, back) < 1e-6);
+    }
+
+    #[test]
+    fn test_cross_and_along_track() {
+        let (from, to) = ((52.0, 5.0), (53.0, 5.0));
+        // Sailing north, a position east is right of the course
+        let east = destination((52.5, 5.0), 90.0, 2.0);
+        assert!((cross_track_nm(east, from, to) - 2.0).abs() < 1e-3);
+        assert!((cross_track_nm(destination((52.5, 5.0), 270.0, 2.0), from, to) + 2.0).abs() < 1e-3);
+        assert!((along_track_nm(east, from, to) - distance_nm(from, (52.5, 5.0))).abs() < 1e-2);
+        assert!(along_track_nm((51.9, 5.0), from, to) < 0.0);
+    }
+}
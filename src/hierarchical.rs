@@ -0,0 +1,484 @@
+use crate::data::{RegattaData, build_regatta_graph};
+use crate::error::UursError;
+use crate::optimize::{
+    Path, PrunedBranches, RouteObjective, SearchConstraints, SearchOptions, SearchStats, Step, estimate_search_nodes,
+    sail_leg, search_target_paths, validate_route,
+};
+use crate::params::check_route_length;
+use petgraph::graph::NodeIndex;
+use petgraph::visit::EdgeRef;
+use std::collections::{BTreeMap, HashMap};
+use std::time::Instant;
+
+/// Buoys with at least this many starts and legs leaving them are key marks
+pub const KEY_MARK_LEGS: usize = 6;
+
+/// Legs a hop of the contracted graph takes at most from one key mark to the next
+pub const MAX_HOP_LEGS: usize = 3;
+
+/// Legs the tactical phase may sail at most between two key marks
+pub const MAX_FILL_LEGS: usize = 5;
+
+/// Partial skeletons the strategic phase keeps for every number of legs
+pub const SKELETON_BEAM: usize = 50;
+
+/// Best skeletons the tactical phase fills in
+pub const SKELETON_CANDIDATES: usize = 10;
+
+/// The shortest connection from one key mark to another over buoys which
+/// aren't key marks
+#[derive(Clone, Debug, PartialEq)]
+pub struct Hop {
+    pub buoys: Vec<usize>,   // indices of the buoys sailed, both key marks included
+    pub distances: Vec<f64>, // of the legs between them in nm
+}
+
+impl Hop {
+    /// The key mark the hop ends at
+    pub fn to(&self) -> usize {
+        self.buoys[self.buoys.len() - 1]
+    }
+
+    /// Number of legs of the hop
+    pub fn legs(&self) -> usize {
+        self.distances.len()
+    }
+
+    /// Distance of the hop in nm
+    pub fn distance(&self) -> f64 {
+        self.distances.iter().sum()
+    }
+}
+
+/// The course contracted to its key marks: the buoys where many legs meet,
+/// the start, and the target, joined by the shortest hops between them
+pub struct ContractedGraph {
+    pub start: usize,
+    pub target: Option<usize>, // key mark the skeletons must end at, if any
+    pub marks: Vec<usize>,     // indices of the key marks
+    hops: Vec<Vec<Hop>>,       // hops leaving each buoy, none for buoys which aren't key marks
+}
+
+impl ContractedGraph {
+    /// Contract the course for a plan from `start`, to `target` if given,
+    /// leaving out the buoys and legs excluded by the constraints
+    pub fn new(data: &RegattaData, start: usize, target: Option<usize>, constraints: &SearchConstraints) -> Self {
+        let (graph, _node_indices) = build_regatta_graph(data);
+        let count = data.boeien.len();
+        let is_mark: Vec<bool> = (0..count)
+            .map(|buoy| {
+                buoy == start
+                    || Some(buoy) == target
+                    || (graph.edges(NodeIndex::new(buoy)).count() >= KEY_MARK_LEGS
+                        && !constraints.forbidden_buoys.contains(&buoy))
+            })
+            .collect();
+
+        // Search every key mark for the shortest hops to the others
+        let mut hops = vec![Vec::new(); count];
+        for from in (0..count).filter(|&buoy| is_mark[buoy]) {
+            let mut shortest = BTreeMap::new();
+            let mut hop = Hop { buoys: vec![from], distances: Vec::new() };
+            collect_hops(&graph, &is_mark, constraints, &mut hop, &mut shortest);
+            hops[from] = shortest.into_values().collect();
+        }
+
+        Self {
+            start,
+            target,
+            marks: (0..count).filter(|&buoy| is_mark[buoy]).collect(),
+            hops,
+        }
+    }
+
+    /// The hops leaving a key mark, by the index of the mark they end at
+    pub fn hops(&self, mark: usize) -> &[Hop] {
+        &self.hops[mark]
+    }
+}
+
+/// Extend `hop` by the legs leaving its last buoy, keeping the shortest hop
+/// to every key mark reached before `MAX_HOP_LEGS` legs
+fn collect_hops(
+    graph: &petgraph::Graph<Option<String>, crate::data::RegattaEdge>,
+    is_mark: &[bool],
+    constraints: &SearchConstraints,
+    hop: &mut Hop,
+    shortest: &mut BTreeMap<usize, Hop>,
+) {
+    let current = hop.to();
+    for edge_ref in graph.edges(NodeIndex::new(current)) {
+        let next = edge_ref.target().index();
+        if hop.buoys.contains(&next) || !constraints.allows(current, next) {
+            continue;
+        }
+        hop.buoys.push(next);
+        hop.distances.push(edge_ref.weight().distance);
+        if is_mark[next] {
+            if shortest.get(&next).is_none_or(|other: &Hop| hop.distance() < other.distance()) {
+                shortest.insert(next, hop.clone());
+            }
+        } else if hop.legs() < MAX_HOP_LEGS {
+            collect_hops(graph, is_mark, constraints, hop, shortest);
+        }
+        hop.buoys.pop();
+        hop.distances.pop();
+    }
+}
+
+/// A coarse plan as the key marks to round, each reached over the shortest
+/// hop from the one before
+#[derive(Clone)]
+pub struct Skeleton {
+    pub marks: Vec<usize>, // indices of the key marks, the start first
+    pub legs: Vec<usize>,  // legs of the hop to each mark after the start
+    pub start_time: f64,   // in hours since race start
+    pub estimate: Path,    // the hops sailed at once, leg by leg
+}
+
+impl Skeleton {
+    /// The buoys of the estimate, the start first
+    pub fn route(&self) -> Vec<usize> {
+        let mut route = vec![self.marks[0]];
+        route.extend(self.estimate.steps.iter().map(|step| step.to));
+        route
+    }
+
+    fn last_mark(&self) -> usize {
+        self.marks[self.marks.len() - 1]
+    }
+
+    /// The skeleton sailing on over a hop, `None` if that breaks the rules
+    /// or the constraints or a leg of it can't be estimated, counted by the
+    /// reason in `pruned`
+    fn extend(
+        &self,
+        data: &RegattaData,
+        hop: &Hop,
+        constraints: &SearchConstraints,
+        pruned: &mut PrunedBranches,
+    ) -> Option<Self> {
+        let mut route = self.route();
+        route.extend(&hop.buoys[1..]);
+        if let Err(e) = validate_route(data, &route, constraints) {
+            pruned.count_invalid(&e);
+            return None;
+        }
+
+        let mut steps = self.estimate.steps.clone();
+        let (mut time, mut total_distance) = (self.estimate.end_time, self.estimate.total_distance);
+        for (pair, &distance) in hop.buoys.windows(2).zip(&hop.distances) {
+            let Ok(step) = sail_leg(data, pair[0], pair[1], distance, time, 0.0) else {
+                pruned.no_estimate += 1;
+                return None;
+            };
+            time = step.end_time;
+            total_distance += distance;
+            steps.push(step);
+        }
+
+        let mut skeleton = self.clone();
+        skeleton.marks.push(hop.to());
+        skeleton.legs.push(hop.legs());
+        skeleton.estimate = Path::new(data, steps, total_distance, time);
+        Some(skeleton)
+    }
+}
+
+/// Strategic phase: the best skeletons by an objective, best first
+///
+/// The skeletons are searched on the contracted graph leg count by leg
+/// count, keeping the `SKELETON_BEAM` best partial skeletons for each. A
+/// skeleton is complete when it reaches the target of the graph in at most
+/// `steps` legs, or without a target when it has exactly `steps` legs.
+pub fn search_skeletons(
+    data: &RegattaData,
+    graph: &ContractedGraph,
+    start_time: f64,         // time in hours since race start
+    steps: usize,            // legs of the hops of a skeleton at most
+    objective: &dyn RouteObjective,
+    options: &SearchOptions, // constraints and monitoring
+    stats: &mut SearchStats, // counts the skeletons expanded and cut off
+) -> Result<Vec<Skeleton>, UursError> {
+    let mut levels: Vec<Vec<Skeleton>> = vec![Vec::new(); steps + 1];
+    levels[0].push(Skeleton {
+        marks: vec![graph.start],
+        legs: Vec::new(),
+        start_time,
+        estimate: Path::new(data, Vec::new(), 0.0, start_time),
+    });
+
+    let mut complete = Vec::new();
+    for legs in 0..=steps {
+        let mut level = std::mem::take(&mut levels[legs]);
+        level.sort_by(|a, b| objective.compare(data, &a.estimate, &b.estimate));
+        level.truncate(SKELETON_BEAM);
+        for skeleton in level {
+            if let Some(monitor) = options.monitor {
+                monitor.visit_node()?;
+            }
+            stats.nodes_expanded += 1;
+            let completes = match graph.target {
+                Some(target) => legs > 0 && skeleton.last_mark() == target,
+                None => legs == steps,
+            };
+            if completes {
+                complete.push(skeleton);
+                continue;
+            }
+            for hop in graph.hops(skeleton.last_mark()) {
+                if legs + hop.legs() > steps {
+                    continue;
+                }
+                if let Some(next) = skeleton.extend(data, hop, &options.constraints, &mut stats.pruned) {
+                    levels[legs + hop.legs()].push(next);
+                }
+            }
+        }
+    }
+
+    complete.sort_by(|a, b| objective.compare(data, &a.estimate, &b.estimate));
+    complete.truncate(SKELETON_CANDIDATES);
+    Ok(complete)
+}
+
+/// The searches of the tactical phase, kept for the skeletons which reach the
+/// same mark at the same time, and the statistics of all of them
+#[derive(Default)]
+pub struct HopSearches {
+    found: HashMap<(usize, usize, u64, usize), Vec<Path>>, // by from, to, time bits, and legs
+    pub stats: SearchStats,
+}
+
+impl HopSearches {
+    /// The paths from one mark to the next at a time with at most `legs`
+    /// legs, best first by the objective
+    fn search(
+        &mut self,
+        data: &RegattaData,
+        (from, to): (usize, usize),
+        time: f64,
+        legs: usize,
+        objective: &dyn RouteObjective,
+        options: &SearchOptions,
+    ) -> Result<Vec<Path>, UursError> {
+        let key = (from, to, time.to_bits(), legs);
+        if let Some(paths) = self.found.get(&key) {
+            self.stats.cache_hits += 1;
+            return Ok(paths.clone());
+        }
+        self.stats.cache_misses += 1;
+        let results = search_target_paths(data, from, to, time, legs, options)?;
+        self.stats.add(&results.stats);
+        let mut paths = results.paths(data);
+        objective.sort_paths(data, &mut paths);
+        self.found.insert(key, paths.clone());
+        Ok(paths)
+    }
+}
+
+/// Tactical phase: the detailed legs of a skeleton, `None` if a hop can't be
+/// filled in without breaking the rules or the constraints
+///
+/// Each hop is searched for the best legs by the objective from the mark it
+/// leaves to the next one, waiting as the options allow, the route staying
+/// within `steps` legs. The legs a hop saves over its estimate may be sailed
+/// by the hops after it.
+pub fn fill_skeleton(
+    data: &RegattaData,
+    skeleton: &Skeleton,
+    steps: usize,            // legs of the route at most
+    objective: &dyn RouteObjective,
+    options: &SearchOptions, // waits, constraints, budget, and monitoring
+    searches: &mut HopSearches,
+) -> Result<Option<Path>, UursError> {
+    let segment_options = SearchOptions {
+        constraints: options.constraints.clone(),
+        monitor: options.monitor,
+        max_wait: options.max_wait,
+        budget: options.budget,
+        ..Default::default()
+    };
+    let mut route = vec![skeleton.marks[0]];
+    let mut filled: Vec<Step> = Vec::new();
+    let (mut time, mut total_distance) = (skeleton.start_time, 0.0);
+    let mut slack = steps.saturating_sub(skeleton.legs.iter().sum());
+
+    for (pair, &legs) in skeleton.marks.windows(2).zip(&skeleton.legs) {
+        let budget = (legs + slack).min(MAX_FILL_LEGS);
+        let segments = searches.search(data, (pair[0], pair[1]), time, budget, objective, &segment_options)?;
+        let segment = segments.into_iter().find(|segment| {
+            let mut candidate = route.clone();
+            candidate.extend(segment.steps.iter().map(|step| step.to));
+            let valid = validate_route(data, &candidate, &options.constraints);
+            if let Err(e) = &valid {
+                searches.stats.pruned.count_invalid(e);
+            }
+            valid.is_ok()
+        });
+        let Some(segment) = segment else {
+            return Ok(None);
+        };
+
+        slack = legs + slack - segment.steps.len();
+        route.extend(segment.steps.iter().map(|step| step.to));
+        time = segment.end_time;
+        total_distance += segment.total_distance;
+        filled.extend(segment.steps);
+    }
+
+    Ok(Some(Path::new(data, filled, total_distance, time)))
+}
+
+/// Estimate the number of nodes a hierarchical plan with routes of the given
+/// number of legs visits: the partial skeletons of the beam and the searches
+/// filling in the hops of the best ones, as long as hops usually are
+pub fn estimate_hierarchical_nodes(data: &RegattaData, steps: usize) -> f64 {
+    let skeletons = (SKELETON_BEAM * (steps + 1)) as f64;
+    skeletons + (SKELETON_CANDIDATES * steps) as f64 * estimate_search_nodes(data, MAX_HOP_LEGS)
+}
+
+/// Routes of a hierarchical plan with the statistics of both phases
+pub struct HierarchicalPlan {
+    pub paths: Vec<Path>, // best first
+    pub stats: SearchStats,
+}
+
+/// Plan routes in two phases: choose the best skeletons of key marks on the
+/// contracted course, then fill in the legs between their marks
+///
+/// This searches a tiny part of what an exhaustive search does, so it can
+/// plan routes over the whole race, up to the route length of the rules,
+/// at the price of missing routes which the skeletons don't lead to. The
+/// routes are returned best first by the objective, each once.
+pub fn plan_hierarchical(
+    data: &RegattaData,
+    start: usize,            // index of the starting buoy
+    target: Option<usize>,   // index of the buoy to end at, none for routes of `steps` legs
+    start_time: f64,         // time in hours since race start
+    steps: usize,            // legs of the routes at most
+    objective: &dyn RouteObjective,
+    options: &SearchOptions, // waits, constraints, budget, and monitoring
+) -> Result<HierarchicalPlan, UursError> {
+    if steps == 0 {
+        return Err(UursError::InvalidParameter("A route needs at least one leg".to_string()));
+    }
+    check_route_length(data, steps)?;
+    if let Some(&invalid) = [Some(start), target].iter().flatten().find(|&&buoy| buoy >= data.boeien.len()) {
+        return Err(UursError::UnknownBuoy(invalid.to_string()));
+    }
+
+    let started = Instant::now();
+    let graph = ContractedGraph::new(data, start, target, &options.constraints);
+    let mut stats = SearchStats::default();
+    let skeletons = search_skeletons(data, &graph, start_time, steps, objective, options, &mut stats)?;
+    let mut searches = HopSearches::default();
+    let mut paths: Vec<Path> = Vec::new();
+    for skeleton in skeletons {
+        let Some(path) = fill_skeleton(data, &skeleton, steps, objective, options, &mut searches)? else {
+            continue;
+        };
+        // Skeletons may fill in to the same legs, or to paths the objective
+        // doesn't accept
+        let same_legs = |other: &Path| {
+            other.steps.len() == path.steps.len()
+                && other.steps.iter().zip(&path.steps).all(|(a, b)| a.to == b.to)
+        };
+        if objective.accepts(data, &path) && !paths.iter().any(same_legs) {
+            if let Some(monitor) = options.monitor {
+                monitor.found_path(data, &path);
+            }
+            paths.push(path);
+        }
+    }
+    objective.sort_paths(data, &mut paths);
+
+    stats.add(&searches.stats);
+    stats.resident_paths = paths.len();
+    stats.elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+    Ok(HierarchicalPlan { paths, stats })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimize::PlanObjective;
+    use crate::data::load_regatta_data;
+
+    #[test]
+    fn test_contracted_graph() {
+        let data = load_regatta_data().unwrap();
+        let start = data.get_boei_index("WV12").unwrap();
+        let graph = ContractedGraph::new(&data, start, None, &SearchConstraints::default());
+
+        assert!(graph.marks.contains(&start));
+        assert!(graph.marks.len() < data.boeien.len() / 2);
+        for &mark in &graph.marks {
+            for hop in graph.hops(mark) {
+                assert_eq!(hop.buoys[0], mark);
+                assert!(graph.marks.contains(&hop.to()) && hop.to() != mark);
+                assert!((1..=MAX_HOP_LEGS).contains(&hop.legs()));
+                // Only the ends of a hop are key marks
+                assert!(hop.buoys[1..hop.legs()].iter().all(|buoy| !graph.marks.contains(buoy)));
+                assert!(validate_route(&data, &hop.buoys, &SearchConstraints::default()).is_ok());
+            }
+        }
+        assert!(!graph.hops(start).is_empty());
+
+        // Avoided buoys are left out of the hops
+        let kg = data.get_boei_index("KG").unwrap();
+        let constraints = SearchConstraints { forbidden_buoys: vec![kg], ..Default::default() };
+        let graph = ContractedGraph::new(&data, start, None, &constraints);
+        assert!(graph.marks.iter().all(|&mark| graph.hops(mark).iter().all(|hop| !hop.buoys.contains(&kg))));
+    }
+
+    #[test]
+    fn test_skeletons_and_fill() {
+        let data = load_regatta_data().unwrap();
+        let start = data.get_boei_index("WV12").unwrap();
+        let options = SearchOptions::default();
+        let objective = PlanObjective::MaxDistance;
+        let graph = ContractedGraph::new(&data, start, None, &options.constraints);
+
+        let mut stats = SearchStats::default();
+        let skeletons = search_skeletons(&data, &graph, 2.0, 8, &objective, &options, &mut stats).unwrap();
+        assert!(stats.nodes_expanded > 0);
+        assert!(!skeletons.is_empty() && skeletons.len() <= SKELETON_CANDIDATES);
+        for skeleton in &skeletons {
+            assert_eq!(skeleton.marks[0], start);
+            assert!(skeleton.marks.iter().all(|mark| graph.marks.contains(mark)));
+            assert_eq!(skeleton.legs.iter().sum::<usize>(), 8);
+            assert_eq!(skeleton.estimate.steps.len(), 8);
+            assert!(validate_route(&data, &skeleton.route(), &options.constraints).is_ok());
+        }
+        assert!(skeletons.windows(2).all(|pair| {
+            objective.compare(&data, &pair[0].estimate, &pair[1].estimate) != std::cmp::Ordering::Greater
+        }));
+
+        // The filled route rounds the marks of the skeleton in their order
+        let mut searches = HopSearches::default();
+        let path = fill_skeleton(&data, &skeletons[0], 8, &objective, &options, &mut searches).unwrap().unwrap();
+        assert_eq!(searches.stats.cache_misses, skeletons[0].marks.len() - 1);
+        assert!(!path.steps.is_empty() && path.steps.len() <= 8);
+        let mut route = vec![start];
+        route.extend(path.steps.iter().map(|step| step.to));
+        assert!(validate_route(&data, &route, &options.constraints).is_ok());
+        let mut marks = skeletons[0].marks.iter().peekable();
+        for buoy in &route {
+            if marks.peek() == Some(&buoy) {
+                marks.next();
+            }
+        }
+        assert!(marks.next().is_none());
+
+        // Plans to a target end there and go beyond the steps of an exhaustive search
+        let target = data.get_boei_index("LC1").unwrap();
+        let plan = plan_hierarchical(&data, start, Some(target), 2.0, 20, &objective, &options).unwrap();
+        let paths = plan.paths;
+        assert!(!paths.is_empty());
+        // Skeletons starting alike fill in their first hops from the cache
+        assert!(plan.stats.cache_hits > 0);
+        assert!(paths.iter().all(|path| path.steps.last().unwrap().to == target && path.steps.len() <= 20));
+        assert!(plan_hierarchical(&data, start, None, 2.0, 0, &objective, &options).is_err());
+    }
+}
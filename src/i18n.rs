@@ -0,0 +1,211 @@
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Languages of the web interface and the command line output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Lang {
+    #[default]
+    En,
+    Nl,
+}
+
+impl Lang {
+    /// Code of the language as in `--lang` and `?lang=`
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::Nl => "nl",
+        }
+    }
+
+    /// The language of a code like `nl`, `nl-BE`, or `nl_NL.UTF-8`, `None`
+    /// for the languages without messages
+    pub fn from_code(code: &str) -> Option<Self> {
+        let language = code.split(['-', '_', '.']).next()?.trim();
+        if language.eq_ignore_ascii_case("en") {
+            Some(Lang::En)
+        } else if language.eq_ignore_ascii_case("nl") {
+            Some(Lang::Nl)
+        } else {
+            None
+        }
+    }
+
+    /// The language with messages a client prefers most by its
+    /// `Accept-Language` header, `None` if it accepts none of them
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut best: Option<(f64, Lang)> = None;
+        for range in header.split(',') {
+            let mut parts = range.split(';');
+            let Some(lang) = parts.next().and_then(Lang::from_code) else {
+                continue;
+            };
+            let quality = parts
+                .find_map(|part| part.trim().strip_prefix("q="))
+                .map_or(Some(1.0), |q| q.trim().parse::<f64>().ok());
+            // The first of equally preferred languages wins
+            if let Some(quality) = quality.filter(|&q| q > 0.0)
+                && best.is_none_or(|(best, _)| quality > best)
+            {
+                best = Some((quality, lang));
+            }
+        }
+        best.map(|(_, lang)| lang)
+    }
+
+    /// The language of the locale of the environment, `None` if it has no
+    /// messages in it
+    pub fn from_env() -> Option<Self> {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty())
+            .and_then(|value| Lang::from_code(&value))
+    }
+}
+
+/// The message catalog: key, English, and Dutch text
+const MESSAGES: &[(&str, &str, &str)] = &[
+    // Pages
+    ("subtitle", "Navigating the waters of performance", "Op koers naar de beste prestatie"),
+    ("planning_for", "Planning for", "Planning voor"),
+    ("theme_dark", "Night", "Nacht"),
+    ("theme_light", "Day", "Dag"),
+    ("token_prompt", "This server requires an access token:", "Deze server vraagt om een toegangscode:"),
+    ("back", "Back", "Terug"),
+    ("back_to_menu", "Back to Main Menu", "Terug naar het hoofdmenu"),
+    ("submit", "Submit", "Berekenen"),
+    ("network_error", "Network error: Could not connect to the server.", "Netwerkfout: geen verbinding met de server."),
+    ("fill_in_all", "Please fill in all fields with valid values.", "Vul alle velden met geldige waarden in."),
+    ("title_menu", "Main Menu", "Hoofdmenu"),
+    ("title_estimate", "Estimate Speed", "Snelheid schatten"),
+    ("title_estimate_leg", "Estimate Leg Speed", "Snelheid op een rak schatten"),
+    ("title_find_paths", "Find Paths", "Routes zoeken"),
+    ("title_find_target", "Find Target Paths", "Routes naar een doel zoeken"),
+    ("title_dashboard", "Live Dashboard", "Live dashboard"),
+    ("title_next", "Next Leg", "Volgend rak"),
+    ("title_finish", "Point of No Return", "Laatste vertrek naar de finish"),
+    ("title_fleet", "Fleet Overview", "Vlootoverzicht"),
+    ("title_leaderboard", "Leaderboard", "Tussenstand"),
+    ("title_training", "Virtual Regatta", "Virtuele wedstrijd"),
+    ("title_explore", "Path Explorer", "Route verkennen"),
+    ("title_map", "Course Map", "Baankaart"),
+    ("title_wind", "Wind Editor", "Wind aanpassen"),
+    // Next leg
+    ("next_course", "Steer", "Sturen"),
+    ("next_speed", "Target", "Doel"),
+    ("next_eta", "ETA", "Aankomst"),
+    ("next_to_go", "To Go", "Te gaan"),
+    ("next_then", "Then", "Daarna"),
+    ("next_position", "Position of", "Positie van"),
+    ("next_error", "The next leg could not be determined.", "Het volgende rak kon niet worden bepaald."),
+    // Main menu
+    ("version", "Version", "Versie"),
+    ("created_by", "Created by", "Gemaakt door"),
+    ("menu_estimate", "Estimate Speed between two points", "Snelheid tussen twee punten schatten"),
+    ("menu_map", "Course Map with Routes", "Baankaart met routes"),
+    ("menu_wind", "Edit the Wind Forecast", "Windverwachting aanpassen"),
+    ("menu_graph", "Show Regatta Graph as PDF", "Wedstrijdgraaf als PDF tonen"),
+    ("menu_course", "Show Regatta Map", "Wedstrijdkaart tonen"),
+    ("menu_hint", "Select an option to get started with your sailing performance analysis", "Kies een onderdeel om met de analyse van je zeilprestaties te beginnen"),
+    // Estimate forms
+    ("from_buoy", "From Boei:", "Van boei:"),
+    ("to_buoy", "To Boei:", "Naar boei:"),
+    ("select_start_buoy", "Select starting boei...", "Kies de vertrekboei..."),
+    ("select_start_first", "Select starting boei first...", "Kies eerst de vertrekboei..."),
+    ("select_destination", "Select destination boei...", "Kies de bestemming..."),
+    ("select_leg", "Select Leg:", "Kies een rak:"),
+    ("select_a_leg", "Select a leg...", "Kies een rak..."),
+    ("reverse_direction", "Reverse Direction", "Omgekeerde richting"),
+    ("time_hours", "Time (hours):", "Tijd (uren):"),
+    ("time_placeholder", "Enter time in hours", "Tijd in uren"),
+    ("calculating", "Calculating performance estimate...", "De prestatie wordt geschat..."),
+    ("calculating_leg", "Calculating leg performance estimate...", "De prestatie op het rak wordt geschat..."),
+    ("estimate_heading", "Performance Estimate", "Geschatte prestatie"),
+    ("leg_estimate_heading", "Leg Performance Estimate", "Geschatte prestatie op het rak"),
+    ("error_destinations", "An error occurred while loading the destinations.", "De bestemmingen konden niet worden geladen."),
+    ("no_leg_from", "There is no start or leg from", "Er is geen start of rak vanaf"),
+    ("buoys_must_differ", "From and To boeien must be different.", "Van- en naar-boei moeten verschillen."),
+    ("error_estimate", "An error occurred while estimating performance.", "De prestatie kon niet worden geschat."),
+    // Estimates, on the pages and on the command line
+    ("from", "From:", "Van:"),
+    ("to", "To:", "Naar:"),
+    ("time", "Time:", "Tijd:"),
+    ("estimated_speed", "Estimated Speed:", "Geschatte snelheid:"),
+    ("course_bearing", "Course Bearing:", "Koers:"),
+    ("wind_direction", "Wind Direction:", "Windrichting:"),
+    ("relative_bearing", "Relative Bearing:", "Hoek met de wind:"),
+    ("wind_speed", "Wind Speed:", "Windsnelheid:"),
+    ("point_of_sail", "Point of Sail:", "Koers ten opzichte van de wind:"),
+    ("distance", "Distance:", "Afstand:"),
+    ("travel_time", "Travel Time:", "Vaartijd:"),
+    ("arrival", "Arrival:", "Aankomst:"),
+    ("hours", "hours", "uur"),
+    ("knots", "knots", "knopen"),
+    ("after_race_start", "after race start", "na de start"),
+    ("great_circle", "great circle", "grootcirkel"),
+    ("no_start_or_leg", "no start or leg", "geen start of rak"),
+    ("unknown", "Unknown", "Onbekend"),
+    ("cli_estimate", "Leg Performance Estimate:", "Geschatte prestatie op het rak:"),
+    ("cli_results", "Results:", "Resultaten:"),
+    ("cli_interpretation", "Interpretation:", "Interpretatie:"),
+    ("sail_close_hauled", "Sailing close-hauled (into the wind)", "Aan de wind (tegen de wind in)"),
+    ("sail_close_reach", "Sailing on a close reach", "Krappe halve wind"),
+    ("sail_beam_reach", "Sailing on a beam reach", "Halve wind"),
+    ("sail_broad_reach", "Sailing on a broad reach", "Ruime wind"),
+    ("sail_downwind", "Sailing downwind", "Voor de wind"),
+    // Shared plans
+    ("start", "Start:", "Start:"),
+    ("finish", "Finish:", "Finish:"),
+    ("scored", "scored", "gewaardeerd"),
+    ("average_speed", "Average Speed:", "Gemiddelde snelheid:"),
+    ("boat", "Boat:", "Boot:"),
+    ("plan_unsailable", "can't be sailed with the data served now:", "kan met de huidige gegevens niet worden gezeild:"),
+    ("the_route", "The route", "De route"),
+    ("legs", "Legs", "Rakken"),
+    ("leg", "Leg", "Rak"),
+    ("distance_column", "Distance", "Afstand"),
+    ("speed_column", "Speed", "Snelheid"),
+    ("start_column", "Start", "Start"),
+    ("arrival_column", "Arrival", "Aankomst"),
+    ("saved", "Saved", "Opgeslagen"),
+    ("plan_estimated_now", "the legs are estimated with the wind and data the server uses now.", "de rakken zijn geschat met de wind en gegevens die de server nu gebruikt."),
+];
+
+/// The text of a message in a language, the key itself if the catalog
+/// doesn't have it
+pub fn message(lang: Lang, key: &str) -> &str {
+    match MESSAGES.iter().find(|(k, _, _)| *k == key) {
+        Some((_, en, nl)) => match lang {
+            Lang::En => en,
+            Lang::Nl => nl,
+        },
+        None => key,
+    }
+}
+
+/// All messages of a language by key, for the templates
+pub fn catalog(lang: Lang) -> BTreeMap<&'static str, &'static str> {
+    MESSAGES.iter().map(|&(key, _, _)| (key, message(lang, key))).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_languages() {
+        assert_eq!(Lang::from_code("nl_NL.UTF-8"), Some(Lang::Nl));
+        assert_eq!(Lang::from_code("EN-gb"), Some(Lang::En));
+        assert_eq!(Lang::from_code("de"), None);
+        assert_eq!(Lang::from_accept_language("de-DE,nl;q=0.8,en;q=0.9"), Some(Lang::En));
+        assert_eq!(Lang::from_accept_language("nl-BE, en"), Some(Lang::Nl));
+        assert_eq!(Lang::from_accept_language("en;q=0, fr"), None);
+
+        assert_eq!(message(Lang::Nl, "sail_downwind"), "Voor de wind");
+        assert_eq!(message(Lang::Nl, "no_such_message"), "no_such_message");
+        let keys: std::collections::HashSet<_> = MESSAGES.iter().map(|(key, _, _)| key).collect();
+        assert_eq!(keys.len(), MESSAGES.len(), "duplicate keys in the catalog");
+    }
+}
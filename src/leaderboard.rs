@@ -0,0 +1,96 @@
+use crate::data::RegattaData;
+use crate::live::{match_track, sailed_path};
+use crate::scoring::{corrected_distance, score_path};
+use crate::track::TrackPoint;
+use serde::Serialize;
+
+/// The place of a boat on the leaderboard
+#[derive(Clone, Debug, Serialize)]
+pub struct Standing {
+    pub rank: usize, // boats with the same miles and last rounding share a rank
+    pub boat: String,
+    pub legs: usize,                // starts and legs found on the track
+    pub scored_distance: f64,       // by the rules of the race in nm
+    pub corrected_distance: f64,    // scored distance corrected by the rating
+    pub rating: Option<f64>,
+    pub last_mark: Option<String>,  // buoy rounded last
+    pub last_rounding: Option<f64>, // in hours since race start
+    pub last_position: Option<f64>, // time of the latest position reported
+}
+
+/// Rank boats by the miles scored on their tracks so far
+///
+/// Every track is matched onto the course and the legs found on it are
+/// scored by the rules of the data, so the leg a boat is sailing counts once
+/// it rounds the next mark. Boats rank by their scored miles corrected by
+/// their ratings; of boats with the same miles, the one which rounded its
+/// last mark first ranks higher.
+pub fn leaderboard(data: &RegattaData, tracks: &[(String, Vec<TrackPoint>)]) -> Vec<Standing> {
+    let mut standings: Vec<Standing> = tracks
+        .iter()
+        .map(|(boat, track)| {
+            let sailed = match_track(data, track);
+            let score = score_path(data, &sailed_path(data, &sailed), &data.rules.scoring);
+            let rating = data.rating(boat);
+            Standing {
+                rank: 0,
+                boat: boat.clone(),
+                legs: sailed.len(),
+                scored_distance: score.scored_distance,
+                corrected_distance: corrected_distance(score.scored_distance, rating),
+                rating,
+                last_mark: sailed.last().map(|leg| data.boeien[leg.to].name.clone()),
+                last_rounding: sailed.last().map(|leg| leg.end),
+                last_position: track.last().map(|point| point.time),
+            }
+        })
+        .collect();
+
+    let rounded_by = |standing: &Standing| standing.last_rounding.unwrap_or(f64::INFINITY);
+    standings.sort_by(|a, b| {
+        b.corrected_distance
+            .total_cmp(&a.corrected_distance)
+            .then(rounded_by(a).total_cmp(&rounded_by(b)))
+            .then_with(|| a.boat.cmp(&b.boat))
+    });
+    for i in 0..standings.len() {
+        standings[i].rank = match i.checked_sub(1).map(|j| &standings[j]) {
+            Some(before)
+                if before.corrected_distance == standings[i].corrected_distance
+                    && rounded_by(before) == rounded_by(&standings[i]) =>
+            {
+                before.rank
+            }
+            _ => i + 1,
+        };
+    }
+    standings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_leaderboard() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let route = ["OEVE", "WV12", "GVS3-M8"].map(|name| data.get_boei_index(name).unwrap());
+        let path = crate::optimize::evaluate_route(&data, &route, 0.0).unwrap();
+        let track = crate::track::track_along(&data, &path);
+
+        // One boat sailed both legs, one only the first, two haven't started
+        let tracks = [
+            ("Second".to_string(), track[..=10].to_vec()),
+            ("First".to_string(), track.clone()),
+            ("Waiting B".to_string(), Vec::new()),
+            ("Waiting A".to_string(), track[..1].to_vec()),
+        ];
+        let standings = leaderboard(&data, &tracks);
+        let ranks: Vec<_> = standings.iter().map(|s| (s.rank, s.boat.as_str())).collect();
+        assert_eq!(ranks, [(1, "First"), (2, "Second"), (3, "Waiting A"), (3, "Waiting B")]);
+        assert!((standings[0].scored_distance - path.total_distance).abs() < 1e-9);
+        assert_eq!(standings[0].last_mark.as_deref(), Some("GVS3-M8"));
+        assert_eq!((standings[1].legs, standings[3].legs), (1, 0));
+        assert_eq!(standings[3].last_position, None);
+    }
+}
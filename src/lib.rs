@@ -0,0 +1,129 @@
+//! Performance estimation and route planning for the 24 Uurs Zeilrace
+//!
+//! The regatta data (buoys, starts, legs, polars, wind forecast, and race
+//! clock) is loaded from the CSV files in `data/` with
+//! [`data::load_regatta_data`]. The functions in [`optimize`] estimate the
+//! boat speed on a leg and search the course for the best paths:
+//!
+//! ```no_run
+//! use uurs24::data::load_regatta_data;
+//! use uurs24::optimize::{SearchOptions, explore_target_paths};
+//!
+//! let data = load_regatta_data()?;
+//! let start = data.get_boei_index("SPORT-A").ok_or("unknown buoy")?;
+//! let target = data.get_boei_index("LC1").ok_or("unknown buoy")?;
+//! let paths = explore_target_paths(&data, start, target, 0.0, 4, &SearchOptions::default())?;
+//! for path in &paths {
+//!     println!("{:.2} nm, finished after {:.2} h", path.total_distance, path.end_time);
+//! }
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+//!
+//! # Features
+//!
+//! - `plot`: SVG charts of the course and routes ([`plot`]), post-race
+//!   reports ([`report`]), and briefing packs ([`briefing`])
+//! - `server`: the HTTP server with the web interface and JSON API
+//!   ([`server`]), implies `plot`
+//! - `cli`: the `uurs24` command line tool, implies `server`
+//! - `sqlite`: keeping tracks, sessions, and plans in an SQLite database
+//!   (`storage::SqliteStorage`), opt-in
+//!
+//! All features but `sqlite` are enabled by default. Tools which only plan
+//! routes can depend on the crate with `default-features = false`.
+
+/// Errors of the library
+pub mod error;
+/// Regatta data, its CSV files, and the graph of the course
+pub mod data;
+/// Leg performance estimates and path searches
+pub mod optimize;
+/// Two-phase planning: a skeleton of key marks on the contracted course, then the legs between them
+pub mod hierarchical;
+/// Routes as CSV leg tables and as files for navigation software
+pub mod export;
+/// Schedules of routes as iCalendar files
+pub mod calendar;
+/// Watch rotations of the crew and watch plans clear of roundings and beats
+pub mod watches;
+/// The official scoring of the miles of a route
+pub mod scoring;
+/// The rules of an edition of the race from `data/rules.toml`
+pub mod rules;
+/// Checked times, step counts, and path counts given by users
+pub mod params;
+/// Great circle distances, bearings, cross-track errors, and destinations
+pub mod geo;
+/// Reported boat positions, track logs, and recorded GPX and CSV tracks
+pub mod track;
+/// Active leg, ETA, and progress against a planned path
+pub mod live;
+/// Points of no return: the latest departures from the buoys which still reach the finish
+pub mod approach;
+/// Earliest arrivals at every buoy from a start over the departures of the day
+pub mod profile;
+/// Recorded races replayed hour by hour against the optimizer
+pub mod replay;
+/// Declarations of the legs sailed in a race for the organizer
+pub mod declaration;
+/// Feasibility audits of proposed courses for the race committee
+pub mod audit;
+/// Manifests of runs with the hashes of their inputs, to regenerate results
+pub mod manifest;
+/// Routes sailed forward in time in fixed steps
+pub mod simulate;
+/// Random realizations of the wind forecast and simulations over many of them
+pub mod ensemble;
+/// Virtual regatta in which navigators sail the course leg by leg
+pub mod training;
+/// Routes put together leg by leg on the course map with live estimates
+pub mod explorer;
+/// Leg speed observations and the correction factors fitted to them
+pub mod calibration;
+/// Boats of a fleet with their own polars and tracks
+pub mod fleet;
+/// Live ranking of the boats of a fleet by the miles scored on their tracks
+pub mod leaderboard;
+/// Planning sessions of the crew
+pub mod sessions;
+/// Plans saved under short ids for sharing links
+pub mod plans;
+/// Storage of tracks, sessions, and plans in memory, JSON files, or SQLite
+pub mod storage;
+/// Messages of the web interface and the command line in English and Dutch
+pub mod i18n;
+
+/// SVG charts of the course, the wind forecast, and routes
+#[cfg(feature = "plot")]
+pub mod plot;
+/// Post-race analysis of a recorded track against the plan
+#[cfg(feature = "plot")]
+pub mod report;
+/// Printable briefing pack of a planned route
+#[cfg(feature = "plot")]
+pub mod briefing;
+
+/// HTTP server with the web interface and JSON API
+#[cfg(feature = "server")]
+pub mod server;
+/// Response types of the JSON API and its OpenAPI document
+#[cfg(feature = "server")]
+pub mod api;
+/// LRU cache of serialized API responses
+#[cfg(feature = "server")]
+pub mod cache;
+/// Limits on the size of searches and on concurrent searches per client
+#[cfg(feature = "server")]
+pub mod limits;
+/// Request and search statistics in Prometheus format
+#[cfg(feature = "server")]
+pub mod metrics;
+/// NMEA 0183 parsing and the listener for the onboard instruments
+#[cfg(feature = "server")]
+pub mod nmea;
+/// JSON notifications posted to an HTTP endpoint
+#[cfg(feature = "server")]
+pub mod webhook;
+/// JSON notifications published on a topic of an MQTT broker
+#[cfg(feature = "server")]
+pub mod mqtt;
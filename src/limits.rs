@@ -0,0 +1,117 @@
+use crate::error::UursError;
+use crate::optimize::SearchBudget;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio::task::JoinError;
+
+/// Limits protecting the server from expensive searches: a budget for the
+/// estimated size of a single search, one for what it actually uses while it
+/// runs, a maximum number of searches running at the same time per client IP
+/// address, and the threads searches run on
+#[derive(Debug)]
+pub struct SearchLimits {
+    max_search_nodes: u64,
+    budget: SearchBudget, // enforced by every search the server runs
+    max_searches_per_client: usize,
+    running: Mutex<HashMap<IpAddr, usize>>, // client -> number of running searches
+    threads: Arc<Semaphore>, // one permit per search running at the same time
+}
+
+impl SearchLimits {
+    /// Create limits, 0 disables the respective limit. At most
+    /// `search_threads` searches run at the same time, further ones wait;
+    /// 0 takes the number of CPUs.
+    pub fn new(
+        max_search_nodes: u64,
+        budget: SearchBudget,
+        max_searches_per_client: usize,
+        search_threads: usize,
+    ) -> Self {
+        let search_threads = match search_threads {
+            0 => std::thread::available_parallelism().map_or(4, usize::from),
+            threads => threads,
+        };
+        Self {
+            max_search_nodes,
+            budget,
+            max_searches_per_client,
+            running: Mutex::new(HashMap::new()),
+            threads: Arc::new(Semaphore::new(search_threads)),
+        }
+    }
+
+    /// The budget of every search
+    pub fn budget(&self) -> SearchBudget {
+        self.budget
+    }
+
+    /// Run an admitted search on the blocking thread pool once one of the
+    /// search threads is free, so that long searches don't stall the other
+    /// requests. The search keeps its thread and its permit until it ends,
+    /// even if the caller stops waiting for it.
+    pub async fn run<T: Send + 'static>(
+        &self,
+        permit: SearchPermit,
+        search: impl FnOnce() -> T + Send + 'static,
+    ) -> Result<T, JoinError> {
+        let thread = self.threads.clone().acquire_owned().await.expect("the search threads are never closed");
+        tokio::task::spawn_blocking(move || {
+            let result = search();
+            drop((thread, permit));
+            result
+        })
+        .await
+    }
+
+    /// Admit a search of the estimated size for a client. The returned permit
+    /// counts as running search until it is dropped.
+    pub fn admit(
+        self: &Arc<Self>,
+        client: Option<IpAddr>,
+        estimated_nodes: f64,
+    ) -> Result<SearchPermit, UursError> {
+        if self.max_search_nodes > 0 && estimated_nodes > self.max_search_nodes as f64 {
+            return Err(UursError::SearchLimitExceeded {
+                estimated: estimated_nodes,
+                limit: self.max_search_nodes,
+            });
+        }
+
+        // Clients without a known address share one slot count
+        let client = client.unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+        let mut running = self.running.lock().unwrap();
+        let count = running.entry(client).or_default();
+        if self.max_searches_per_client > 0 && *count >= self.max_searches_per_client {
+            return Err(UursError::TooManySearches {
+                limit: self.max_searches_per_client,
+            });
+        }
+        *count += 1;
+
+        Ok(SearchPermit {
+            limits: self.clone(),
+            client,
+        })
+    }
+}
+
+/// A running search admitted by the limits
+#[derive(Debug)]
+pub struct SearchPermit {
+    limits: Arc<SearchLimits>,
+    client: IpAddr,
+}
+
+impl Drop for SearchPermit {
+    fn drop(&mut self) {
+        let mut running = self.limits.running.lock().unwrap();
+        if let Some(count) = running.get_mut(&self.client) {
+            *count -= 1;
+            if *count == 0 {
+                running.remove(&self.client);
+            }
+        }
+    }
+}
@@ -0,0 +1,552 @@
+use crate::data::{RegattaData, RoundingSide};
+use crate::optimize::{Path, Step, estimate_leg_performance, evaluate_route};
+use crate::geo::{along_track_nm, angle_between, cross_track_nm, distance_nm, initial_bearing};
+use crate::track::TrackPoint;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Progress along the leg being sailed at the latest reported position
+#[derive(Clone, Debug)]
+pub struct LegProgress {
+    pub distance_to_go: f64, // from the position to the next buoy in nm
+    pub course: f64,         // to steer from the position to the next buoy in degrees
+    pub speed: f64,          // estimated speed on the leg in knots
+    pub eta: Option<f64>,    // arrival at the next buoy in hours since race start
+}
+
+/// A mark counts as rounded once the boat is this close to it along the
+/// route in nm, positions are never exactly on the buoy
+const MARK_RADIUS: f64 = 0.05;
+
+/// A recorded track passes a buoy when it comes this close to it in nm,
+/// tracks logged every minute or so miss the buoy by more than a boat length
+const PASSING_RADIUS: f64 = 0.1;
+
+/// Positions of a track closer than this in nm are the same, the side of a
+/// buoy can't be told from the course between them
+const SAME_POSITION: f64 = 0.001;
+
+/// A start or leg of the course matched to a recorded track
+#[derive(Clone, Debug)]
+pub struct SailedLeg {
+    pub from: usize,
+    pub to: usize,
+    pub start: f64, // passing of `from` in hours since race start
+    pub end: f64,   // passing of `to` in hours since race start
+}
+
+/// How a recorded track rounded a buoy
+#[derive(Clone, Debug, Serialize)]
+pub struct RoundingCheck {
+    pub buoy: String,
+    pub time: f64,                      // closest approach in hours since race start
+    pub distance: f64,                  // closest approach in nm
+    pub side: Option<RoundingSide>,     // side the buoy was kept on, `None` if the boat didn't move
+    pub required: Option<RoundingSide>, // side the buoy has to be kept on by the data
+}
+
+impl RoundingCheck {
+    /// Whether the buoy was passed within the passing radius and on the
+    /// required side, if there is one
+    pub fn verified(&self) -> bool {
+        self.distance <= PASSING_RADIUS && self.required.is_none_or(|required| self.side == Some(required))
+    }
+
+    /// Why the rounding can't be verified, `None` if it is verified
+    pub fn problem(&self) -> Option<String> {
+        if self.distance > PASSING_RADIUS {
+            return Some(format!(
+                "{} was not passed within {PASSING_RADIUS} nm, the track came within {:.2} nm",
+                self.buoy, self.distance
+            ));
+        }
+        let required = self.required.filter(|&required| self.side != Some(required))?;
+        Some(match self.side {
+            Some(side) => format!("{} was kept on {side} instead of {required}", self.buoy),
+            None => format!("The side {} was kept on can't be told from the track", self.buoy),
+        })
+    }
+}
+
+/// Planned and actual rounding time of a mark of a plan
+#[derive(Clone, Debug)]
+pub struct MarkTime {
+    pub buoy: usize,
+    pub planned: f64,        // in hours since race start
+    pub actual: Option<f64>, // interpolated from the track, `None` if not rounded yet
+}
+
+/// Progress of the boat along a planned path
+#[derive(Clone, Debug)]
+pub struct PlanProgress {
+    pub marks: Vec<MarkTime>, // start buoy first, then the end of every step
+    pub step: usize,          // index of the step being sailed
+    pub distance_along: f64,  // distance sailed along the planned route in nm
+    pub time_behind: f64,     // hours behind the plan at the latest position, negative when ahead
+    pub projected_finish: Option<f64>, // `None` if the rest of the route can't be sailed
+}
+
+/// True wind measured on board, as reported by the instruments
+#[derive(Clone, Debug, Serialize)]
+pub struct WindObservation {
+    pub direction: f64, // where the wind comes from in degrees
+    pub speed: f64,     // in knots
+    pub time: f64,      // in hours since race start
+    pub received: DateTime<Utc>,
+}
+
+/// Guess the leg being sailed from the track: the start or leg closest to the
+/// latest position, sailed towards the buoy the boat is approaching
+///
+/// The direction is taken from the movement between the last two points, or
+/// from the reported course if there is only one point. Without either, the
+/// boat is assumed to have just rounded the nearer buoy.
+pub fn locate_leg(data: &RegattaData, track: &[TrackPoint]) -> Option<(usize, usize)> {
+    let position = track.last()?;
+    let legs = data
+        .starts
+        .iter()
+        .map(|start| (&start.from, &start.to))
+        .chain(data.rakken.iter().map(|rak| (&rak.from, &rak.to)));
+
+    let mut nearest: Option<(f64, usize, usize)> = None;
+    for (from, to) in legs {
+        let (Some(a), Some(b)) = (data.get_boei_index(from), data.get_boei_index(to)) else {
+            continue;
+        };
+        let (Some(pa), Some(pb)) = (data.boeien[a].coordinates(), data.boeien[b].coordinates())
+        else {
+            continue;
+        };
+        let distance = distance_to_segment((position.lat, position.long), pa, pb);
+        if nearest.is_none_or(|(best, _, _)| distance < best) {
+            nearest = Some((distance, a, b));
+        }
+    }
+    let (_, a, b) = nearest?;
+
+    // Decide which end of the leg the boat is heading for
+    let pa = data.boeien[a].coordinates()?;
+    let pb = data.boeien[b].coordinates()?;
+    let here = (position.lat, position.long);
+    let towards_b = if let Some(previous) = track.len().checked_sub(2).map(|i| &track[i]) {
+        let before = (previous.lat, previous.long);
+        distance_nm(here, pb) - distance_nm(before, pb) < distance_nm(here, pa) - distance_nm(before, pa)
+    } else if let Some(course) = position.course {
+        angle_between(course, initial_bearing(here, pb)) < angle_between(course, initial_bearing(here, pa))
+    } else {
+        distance_nm(here, pa) <= distance_nm(here, pb)
+    };
+
+    Some(if towards_b { (a, b) } else { (b, a) })
+}
+
+/// Match a recorded track onto the course: which starts and legs were sailed
+/// and when
+///
+/// The track passes a buoy at its closest approach whenever it comes within
+/// `PASSING_RADIUS` of it. Consecutive passings of buoys connected by a start
+/// or leg make up the sailed legs, passings of other buoys, e.g. on the way
+/// along a leg, are skipped. Before the first leg the latest passed buoy
+/// counts as the start, so sailing around before the start is ignored.
+pub fn match_track(data: &RegattaData, track: &[TrackPoint]) -> Vec<SailedLeg> {
+    // Closest approach to every buoy each time the track comes near it
+    let mut passings: Vec<(f64, usize)> = Vec::new();
+    for (buoy, boei) in data.boeien.iter().enumerate() {
+        let Some(position) = boei.coordinates() else {
+            continue;
+        };
+        let mut closest: Option<(f64, f64)> = None; // distance and time of the current passing
+        for pair in track.windows(2) {
+            let (a, b) = ((pair[0].lat, pair[0].long), (pair[1].lat, pair[1].long));
+            let (t, distance) = project_onto_segment(position, a, b);
+            if distance <= PASSING_RADIUS {
+                let time = pair[0].time + t * (pair[1].time - pair[0].time);
+                if closest.is_none_or(|(best, _)| distance < best) {
+                    closest = Some((distance, time));
+                }
+            } else if let Some((_, time)) = closest.take() {
+                passings.push((time, buoy));
+            }
+        }
+        if let Some((_, time)) = closest {
+            passings.push((time, buoy));
+        }
+    }
+    passings.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut legs: Vec<SailedLeg> = Vec::new();
+    let mut current: Option<(usize, f64)> = None;
+    for (time, buoy) in passings {
+        match current {
+            Some((from, start)) if from != buoy && data.leg_distance(from, buoy).is_some() => {
+                legs.push(SailedLeg {
+                    from,
+                    to: buoy,
+                    start,
+                    end: time,
+                });
+                current = Some((buoy, time));
+            }
+            _ if legs.is_empty() => current = Some((buoy, time)),
+            _ => {}
+        }
+    }
+    legs
+}
+
+/// The legs matched onto a track as a path, sailed at the times of the track
+pub fn sailed_path(data: &RegattaData, legs: &[SailedLeg]) -> Path {
+    let steps: Vec<Step> = legs
+        .iter()
+        .map(|leg| {
+            let distance = data.leg_distance(leg.from, leg.to).unwrap_or(0.0);
+            Step {
+                from: leg.from,
+                to: leg.to,
+                distance,
+                speed: if leg.end > leg.start { distance / (leg.end - leg.start) } else { 0.0 },
+                start_time: leg.start,
+                end_time: leg.end,
+                wait: 0.0,
+            }
+        })
+        .collect();
+    Path {
+        total_distance: steps.iter().fold(0.0, |total, step| total + step.distance),
+        scored_distance: 0.0,
+        qualification_margin: None,
+        end_time: legs.last().map_or(0.0, |leg| leg.end),
+        steps,
+    }
+}
+
+/// Check how the track rounded a buoy between two times
+///
+/// The rounding is the closest approach of the track to the buoy in the
+/// window. The side the buoy was kept on follows from the course of the track
+/// there: a buoy left of the course is kept on port. If the track runs right
+/// over the buoy, the inside of its turn there is the side. `None` if the
+/// buoy has no coordinates or the track no segment in the window.
+pub fn check_rounding(
+    data: &RegattaData,
+    track: &[TrackPoint],
+    buoy: usize,
+    from: f64,
+    until: f64,
+) -> Option<RoundingCheck> {
+    let position = data.boeien[buoy].coordinates()?;
+    // Directions in the flat projection, east and north
+    let scale = position.0.to_radians().cos();
+    let towards = |a: &TrackPoint, to: (f64, f64)| ((to.1 - a.long) * scale, to.0 - a.lat);
+    let cross = |u: (f64, f64), v: (f64, f64)| u.0 * v.1 - u.1 * v.0;
+
+    let mut closest: Option<(f64, f64, f64)> = None; // distance, time, and side as sign
+    for i in 0..track.len().saturating_sub(1) {
+        let (a, b) = (&track[i], &track[i + 1]);
+        if b.time < from || a.time > until || distance_nm((a.lat, a.long), (b.lat, b.long)) < SAME_POSITION {
+            continue;
+        }
+        let (t, distance) = project_onto_segment(position, (a.lat, a.long), (b.lat, b.long));
+        if closest.is_some_and(|(best, _, _)| distance >= best) {
+            continue;
+        }
+        let side = if t > 0.0 && t < 1.0 {
+            if distance < SAME_POSITION { 0.0 } else { cross(towards(a, (b.lat, b.long)), towards(a, position)) }
+        } else {
+            // Closest at a point of the track: the buoy lies outside of the
+            // turn there, or right on it
+            let v = if t <= 0.0 { i } else { i + 1 };
+            let here = (track[v].lat, track[v].long);
+            let elsewhere = |p: &&TrackPoint| distance_nm((p.lat, p.long), here) >= SAME_POSITION;
+            let incoming = track[..v].iter().rev().find(elsewhere).map(|p| towards(p, here));
+            let outgoing = track[v + 1..].iter().find(elsewhere).map(|p| towards(&track[v], (p.lat, p.long)));
+            match (incoming, outgoing) {
+                (Some(incoming), Some(outgoing)) if distance < SAME_POSITION => cross(incoming, outgoing),
+                _ if distance < SAME_POSITION => 0.0,
+                _ => {
+                    let to_buoy = towards(&track[v], position);
+                    incoming.into_iter().chain(outgoing).map(|course| cross(course, to_buoy)).sum()
+                }
+            }
+        };
+        closest = Some((distance, a.time + t * (b.time - a.time), side));
+    }
+    let (distance, time, side) = closest?;
+    Some(RoundingCheck {
+        buoy: data.boeien[buoy].name.clone(),
+        time,
+        distance,
+        side: if side > 0.0 {
+            Some(RoundingSide::Port)
+        } else if side < 0.0 {
+            Some(RoundingSide::Starboard)
+        } else {
+            None
+        },
+        required: data.boeien[buoy].rounding,
+    })
+}
+
+/// Compute the progress towards the next buoy of a leg from a reported position
+pub fn leg_progress(
+    data: &RegattaData,
+    position: &TrackPoint,
+    from: usize,
+    to: usize,
+) -> Result<LegProgress, String> {
+    let speed = estimate_leg_performance(data, from, to, position.time)
+        .map_err(|e| e.to_string())?
+        .estimated_speed;
+    let target = data.boeien[to]
+        .coordinates()
+        .ok_or_else(|| format!("Buoy '{}' has no coordinates", data.boeien[to].name))?;
+
+    let distance_to_go = distance_nm((position.lat, position.long), target);
+    let eta = (speed > 0.0).then(|| position.time + distance_to_go / speed);
+    Ok(LegProgress {
+        distance_to_go,
+        course: initial_bearing((position.lat, position.long), target),
+        speed,
+        eta,
+    })
+}
+
+/// Compare the track with a planned path: when the marks were rounded, how far
+/// along the route the boat is, and when it will finish at the current pace
+///
+/// Every position is matched to the step of the plan closest to it, where
+/// steps which would move the boat backwards along the route are penalized
+/// by the distance it would lose. Rounding times are interpolated between
+/// the positions before and after a mark.
+pub fn plan_progress(
+    data: &RegattaData,
+    plan: &Path,
+    track: &[TrackPoint],
+) -> Result<Option<PlanProgress>, String> {
+    let Some(last) = track.last() else {
+        return Ok(None);
+    };
+    let first = plan.steps.first().ok_or("The plan has no steps")?;
+    let coordinates = |buoy: usize| {
+        data.boeien[buoy]
+            .coordinates()
+            .ok_or_else(|| format!("Buoy '{}' has no coordinates", data.boeien[buoy].name))
+    };
+
+    // Distance along the route at the start of every step, and at the finish
+    let mut cumulative = vec![0.0];
+    for step in &plan.steps {
+        cumulative.push(cumulative[cumulative.len() - 1] + step.distance);
+    }
+    let segments = plan
+        .steps
+        .iter()
+        .map(|step| Ok((coordinates(step.from)?, coordinates(step.to)?)))
+        .collect::<Result<Vec<_>, String>>()?;
+
+    // Time and distance along the route of every position
+    let mut step = 0;
+    let mut along = 0.0;
+    let mut progress = Vec::with_capacity(track.len());
+    for point in track {
+        let here = (point.lat, point.long);
+        let mut best: Option<(f64, usize, f64)> = None;
+        for (i, &(a, b)) in segments.iter().enumerate().skip(step) {
+            let (t, distance) = project_onto_segment(here, a, b);
+            let candidate = cumulative[i] + t * plan.steps[i].distance;
+            let score = distance + (along - candidate).max(0.0);
+            if best.is_none_or(|(best_score, _, _)| score < best_score) {
+                best = Some((score, i, candidate));
+            }
+        }
+        let (_, i, candidate) = best.expect("a plan has at least one step");
+        step = i;
+        along = candidate.max(along);
+        progress.push((point.time, along));
+    }
+
+    let marks = (0..=plan.steps.len())
+        .map(|k| {
+            let (buoy, planned) = match k {
+                0 => (first.from, first.start_time),
+                _ => (plan.steps[k - 1].to, plan.steps[k - 1].end_time),
+            };
+            let target = (cumulative[k] - MARK_RADIUS).max(0.0);
+            let actual = match progress.iter().position(|&(_, along)| along >= target) {
+                None => None,
+                Some(0) => (progress[0].1 <= cumulative[k] + MARK_RADIUS).then_some(progress[0].0),
+                Some(i) => {
+                    let ((t0, a0), (t1, a1)) = (progress[i - 1], progress[i]);
+                    Some(t0 + (target - a0) / (a1 - a0) * (t1 - t0))
+                }
+            };
+            MarkTime {
+                buoy,
+                planned,
+                actual,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // Compare with the planned time at the same distance along the route
+    let current = &plan.steps[step];
+    let fraction = if current.distance > 0.0 {
+        ((along - cumulative[step]) / current.distance).clamp(0.0, 1.0)
+    } else {
+        1.0
+    };
+    let planned_here = current.start_time + fraction * (current.end_time - current.start_time);
+    let time_behind = last.time - planned_here;
+
+    // Sail the rest of the current step and the remaining steps from here
+    let projected_finish = match marks.last().and_then(|mark| mark.actual) {
+        Some(finished) => Some(finished),
+        None => match leg_progress(data, last, current.from, current.to)?.eta {
+            Some(eta) if step + 1 < plan.steps.len() => {
+                let route: Vec<usize> = plan.steps[step..].iter().map(|s| s.to).collect();
+                evaluate_route(data, &route, eta).ok().map(|rest| rest.end_time)
+            }
+            eta => eta,
+        },
+    };
+
+    Ok(Some(PlanProgress {
+        marks,
+        step,
+        distance_along: along,
+        time_behind,
+        projected_finish,
+    }))
+}
+
+/// Distance from a position to the segment between two positions in nm
+fn distance_to_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    project_onto_segment(p, a, b).1
+}
+
+/// Project a position onto the segment between two positions, returning
+/// where it lands as fraction of the segment and the distance to it in nm
+fn project_onto_segment(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> (f64, f64) {
+    // Rounding errors must not move an end point of the segment inside it
+    const EPSILON: f64 = 1e-9;
+    let length = distance_nm(a, b);
+    let along = if length > 0.0 { along_track_nm(p, a, b) } else { 0.0 };
+    if along <= EPSILON {
+        (0.0, distance_nm(p, a))
+    } else if along >= length - EPSILON {
+        (1.0, distance_nm(p, b))
+    } else {
+        (along / length, cross_track_nm(p, a, b).abs())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance_to_segment() {
+        let (a, b) = ((52.0, 5.0), (53.0, 5.0));
+        // Beside the middle of the segment
+        let beside = distance_to_segment((52.5, 5.1), a, b);
+        assert!((beside - 0.1 * 60.0 * 52.5f64.to_radians().cos()).abs() < 0.01);
+        // Beyond the end the distance is the one to the end point
+        assert!((distance_to_segment((53.5, 5.0), a, b) - distance_nm(b, (53.5, 5.0))).abs() < 0.01);
+        assert_eq!(angle_between(350.0, 10.0), 20.0);
+    }
+
+    #[test]
+    fn test_plan_progress() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let route = [
+            data.get_boei_index("SPORT-A").unwrap(),
+            data.get_boei_index("KR-A").unwrap(),
+        ];
+        let plan = evaluate_route(&data, &route, 0.0).unwrap();
+        let (a, b) = (
+            data.boeien[route[0]].coordinates().unwrap(),
+            data.boeien[route[1]].coordinates().unwrap(),
+        );
+        let point = |fraction: f64, time: f64| TrackPoint {
+            lat: a.0 + fraction * (b.0 - a.0),
+            long: a.1 + fraction * (b.1 - a.1),
+            time,
+            speed: None,
+            course: None,
+            received: Utc::now(),
+        };
+
+        // Halfway along the leg an hour later than planned
+        let halfway = plan.end_time / 2.0 + 1.0;
+        let track = [point(0.0, 0.0), point(0.5, halfway)];
+        let progress = plan_progress(&data, &plan, &track).unwrap().unwrap();
+        assert_eq!(progress.marks[0].actual, Some(0.0));
+        assert_eq!(progress.marks[1].actual, None);
+        assert!((progress.distance_along - plan.total_distance / 2.0).abs() < 0.1);
+        assert!((progress.time_behind - 1.0).abs() < 0.05);
+        assert!(progress.projected_finish.unwrap() > plan.end_time + 0.9);
+
+        assert!(plan_progress(&data, &plan, &[]).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_match_track() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let buoys = ["OEVE", "WV12", "GVS3-M8"].map(|name| data.get_boei_index(name).unwrap());
+        let positions = buoys.map(|buoy| data.boeien[buoy].coordinates().unwrap());
+        let point = |(lat, long): (f64, f64), time: f64| TrackPoint {
+            lat,
+            long,
+            time,
+            speed: None,
+            course: None,
+            received: Utc::now(),
+        };
+        // Past the buoys a little off and back again on the last one
+        let track = [
+            point((positions[0].0 + 0.0005, positions[0].1), 0.0),
+            point((positions[1].0, positions[1].1 + 0.0005), 0.5),
+            point((positions[2].0 - 0.0005, positions[2].1), 2.0),
+            point(((positions[1].0 + positions[2].0) / 2.0, (positions[1].1 + positions[2].1) / 2.0), 3.0),
+        ];
+        let legs = match_track(&data, &track);
+        assert_eq!(legs.len(), 2);
+        assert_eq!((legs[0].from, legs[0].to, legs[1].to), (buoys[0], buoys[1], buoys[2]));
+        assert!((legs[0].end - 0.5).abs() < 0.01);
+        assert!((legs[1].end - 2.0).abs() < 0.01);
+
+        assert!(match_track(&data, &track[..1]).is_empty());
+    }
+
+    #[test]
+    fn test_check_rounding() {
+        let mut data = crate::data::load_regatta_data().unwrap();
+        let buoy = data.get_boei_index("WV12").unwrap();
+        let (lat, long) = data.boeien[buoy].coordinates().unwrap();
+        let point = |lat: f64, long: f64, time: f64| TrackPoint {
+            lat,
+            long,
+            time,
+            speed: None,
+            course: None,
+            received: Utc::now(),
+        };
+        // Sailing north just west of the buoy keeps it on starboard
+        let track = [point(lat - 0.01, long - 0.001, 0.0), point(lat + 0.01, long - 0.001, 1.0)];
+        let check = check_rounding(&data, &track, buoy, 0.0, 1.0).unwrap();
+        assert_eq!(check.side, Some(RoundingSide::Starboard));
+        assert!((check.time - 0.5).abs() < 0.01);
+        assert!(check.verified());
+
+        data.boeien[buoy].rounding = Some(RoundingSide::Port);
+        let check = check_rounding(&data, &track, buoy, 0.0, 1.0).unwrap();
+        assert!(!check.verified());
+        assert!(check.problem().unwrap().contains("kept on starboard instead of port"));
+
+        // Too far off to count as rounded
+        let far = [point(lat - 0.01, long + 0.01, 0.0), point(lat + 0.01, long + 0.01, 1.0)];
+        assert!(!check_rounding(&data, &far, buoy, 0.0, 1.0).unwrap().verified());
+        assert!(check_rounding(&data, &track, buoy, 2.0, 3.0).is_none());
+    }
+}
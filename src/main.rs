@@ -1,150 +1,151 @@
-mod data;
-mod optimize;
-mod plot;
-mod server;
-
 use clap::Command;
-use data::{build_regatta_graph, load_regatta_data};
-use optimize::{estimate_leg_performance, explore_paths, explore_target_paths};
-use plot::save_regatta_plot;
+use serde_json::json;
+use uurs24::api::{DataResponse, EstimateResponse, FindPathsResponse, FinishApproachResponse, PathResponse, VersionResponse};
+use uurs24::data::{DataWarning, RaceTime, build_regatta_graph, load_regatta_data_with_warnings, parse_clock_minutes};
+use uurs24::i18n::{Lang, message};
+use uurs24::optimize::{RouteObjective, SearchOptions, estimate_leg_performance, search_paths, search_target_paths};
+use uurs24::hierarchical::plan_hierarchical;
+use uurs24::params::{RaceHours, StepCount, WaitHours, check_route_length};
+use uurs24::plot::{PlotConfig, Theme, create_deadline_map, save_regatta_plot, save_wind_chart};
+use uurs24::{approach, audit, briefing, calendar, data, declaration, ensemble, export, live, manifest, mqtt, optimize, replay, report, scoring, server, simulate, track, watches, webhook};
 
 #[tokio::main]
 async fn main() {
-    let matches = Command::new("uurs24")
-        .about("24-hour regatta data management tool")
-        .version(env!("CARGO_PKG_VERSION"))
-        .subcommand_negates_reqs(true)
-        .subcommand(Command::new("show").about("Show regatta data and statistics"))
-        .subcommand(
-            Command::new("plot")
-                .about("Generate SVG visualization of the regatta course")
-                .arg(
-                    clap::Arg::new("output")
-                        .short('o')
-                        .long("output")
-                        .value_name("FILE")
-                        .help("Output SVG file path (default: regatta_course.svg)")
-                        .default_value("regatta_course.svg"),
-                ),
-        )
-        .subcommand(
-            Command::new("graph")
-                .about("Export the regatta graph to a DOT file for graphviz")
-                .arg(
-                    clap::Arg::new("output")
-                        .short('o')
-                        .long("output")
-                        .value_name("FILE")
-                        .help("Output DOT file path (default: regatta_graph.dot)")
-                        .default_value("regatta_graph.dot"),
-                ),
-        )
-        .subcommand(
-            Command::new("estimate")
-                .about("Estimate boat performance between two buoys at a specific time")
-                .arg(
-                    clap::Arg::new("from")
-                        .help("Name of the starting buoy")
-                        .required(true),
-                )
-                .arg(
-                    clap::Arg::new("to")
-                        .help("Name of the destination buoy")
-                        .required(true),
-                )
-                .arg(
-                    clap::Arg::new("time")
-                        .help("Time in hours after race start")
-                        .required(true),
-                ),
-        )
-        .subcommand(
-            Command::new("serve")
-                .about("Start HTTP server to serve regatta data")
-                .arg(
-                    clap::Arg::new("port")
-                        .short('p')
-                        .long("port")
-                        .value_name("PORT")
-                        .help("Port to bind the server to (default: 3030)")
-                        .default_value("3030"),
-                ),
-        )
-        .subcommand(
-            Command::new("paths")
-                .about("Explore all possible paths from a starting point")
-                .arg(
-                    clap::Arg::new("start")
-                        .help("Name of the starting buoy")
-                        .required(true),
-                )
-                .arg(
-                    clap::Arg::new("time")
-                        .help("Starting time in hours after race start")
-                        .required(true),
-                )
-                .arg(
-                    clap::Arg::new("steps")
-                        .help("Number of steps to explore")
-                        .required(true),
-                ),
-        )
-        .subcommand(
-            Command::new("target")
-                .about("Explore paths from a starting point to a specific target")
-                .arg(
-                    clap::Arg::new("start")
-                        .help("Name of the starting buoy")
-                        .required(true),
-                )
-                .arg(
-                    clap::Arg::new("target")
-                        .help("Name of the target buoy")
-                        .required(true),
-                )
-                .arg(
-                    clap::Arg::new("time")
-                        .help("Starting time in hours after race start")
-                        .required(true),
-                )
-                .arg(
-                    clap::Arg::new("steps")
-                        .help("Maximum number of steps to explore")
-                        .required(true),
-                ),
-        )
-        .subcommand(Command::new("version").about("Display version information"))
-        .get_matches();
+    let matches = cli().get_matches();
+
+    let format = match matches.get_one::<String>("output").map(String::as_str) {
+        Some("json") => OutputFormat::Json,
+        _ => OutputFormat::Text,
+    };
+    // The language of the text output, the server's default for its pages
+    let lang_arg = matches.get_one::<String>("lang").and_then(|code| Lang::from_code(code));
+    let lang = lang_arg.or_else(Lang::from_env).unwrap_or_default();
+
+    // Completions and the man page are generated without any data files
+    match matches.subcommand() {
+        Some(("completions", completions_matches)) => {
+            let shell = *completions_matches.get_one::<clap_complete::Shell>("shell").unwrap();
+            clap_complete::generate(shell, &mut cli(), "uurs24", &mut std::io::stdout());
+            return;
+        }
+        Some(("man", _)) => {
+            if let Err(e) = clap_mangen::Man::new(cli()).render(&mut std::io::stdout()) {
+                eprintln!("Error writing man page: {e}");
+                std::process::exit(1);
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    // Diagnostics go to stderr, so stdout only carries the results
+    tracing_subscriber::fmt()
+        .with_target(false)
+        .with_writer(std::io::stderr)
+        .with_max_level(log_level(&matches))
+        .init();
 
-    // Load data for every subcommand
-    println!("Loading regatta data...");
+    // Load data for every other subcommand
+    tracing::info!("Loading regatta data...");
 
-    let data = match load_regatta_data() {
-        Ok(data) => data,
+    let (mut data, warnings) = match load_regatta_data_with_warnings() {
+        Ok(loaded) => loaded,
         Err(e) => {
             eprintln!("Error loading regatta data: {e}");
             std::process::exit(1);
         }
     };
+    if let Some(start) = matches.get_one::<String>("race-start") {
+        match start.parse::<RaceTime>() {
+            Ok(RaceTime::Date(start)) => data.race_clock.start = Some(start),
+            _ => {
+                eprintln!("Error: race start must be a date and time, e.g. 2026-06-12 18:00");
+                std::process::exit(1);
+            }
+        }
+    }
+    if matches.get_flag("strict") && !warnings.is_empty() {
+        for warning in &warnings {
+            eprintln!("Error: {warning}");
+        }
+        std::process::exit(1);
+    }
+    for warning in &warnings {
+        tracing::warn!("{warning}");
+    }
+    if let Some(class) = matches.get_one::<String>("class")
+        && let Err(e) = data.select_class(class)
+    {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+
+    // Seed of the random numbers drawn by the run, for its manifest
+    let mut run_seed = None;
 
     match matches.subcommand() {
         Some(("show", _)) => {
-            show_regatta_data(&data);
+            show_command(&data, &warnings, format);
         }
         Some(("plot", plot_matches)) => {
-            let output_path = plot_matches.get_one::<String>("output").unwrap();
-            match save_regatta_plot(&data, output_path, None) {
-                Ok(()) => println!("Successfully generated SVG plot!"),
-                Err(e) => {
-                    eprintln!("Error generating SVG plot: {e}");
-                    std::process::exit(1);
+            let theme = plot_matches
+                .get_one::<String>("theme")
+                .and_then(|name| Theme::from_name(name))
+                .unwrap_or_default();
+            let config = PlotConfig { theme, ..PlotConfig::default() };
+            if plot_matches.get_flag("wind-chart") {
+                let output_path = plot_matches
+                    .get_one::<String>("output")
+                    .map_or("wind_chart.svg", |s| s.as_str());
+                let sunrise_str = plot_matches.get_one::<String>("sunrise").unwrap();
+                let sunset_str = plot_matches.get_one::<String>("sunset").unwrap();
+                let daylight = match (parse_clock_minutes(sunrise_str), parse_clock_minutes(sunset_str)) {
+                    (Some(sunrise), Some(sunset)) => (sunrise, sunset),
+                    _ => {
+                        eprintln!("Error: sunrise and sunset must be given as HH:MM");
+                        std::process::exit(1);
+                    }
+                };
+                match save_wind_chart(&data, output_path, Some(config), Some(daylight)) {
+                    Ok(()) => match format {
+                        OutputFormat::Text => {
+                            println!("Wind chart saved to: {output_path}");
+                            println!("Successfully generated wind chart!");
+                        }
+                        OutputFormat::Json => print_json(&json!({ "output": output_path })),
+                    },
+                    Err(e) => {
+                        eprintln!("Error generating wind chart: {e}");
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                let output_path = plot_matches
+                    .get_one::<String>("output")
+                    .map_or("regatta_course.svg", |s| s.as_str());
+                match save_regatta_plot(&data, output_path, Some(config)) {
+                    Ok(()) => match format {
+                        OutputFormat::Text => {
+                            println!("SVG plot saved to: {output_path}");
+                            println!("Successfully generated SVG plot!");
+                        }
+                        OutputFormat::Json => print_json(&json!({ "output": output_path })),
+                    },
+                    Err(e) => {
+                        eprintln!("Error generating SVG plot: {e}");
+                        std::process::exit(1);
+                    }
                 }
             }
         }
         Some(("graph", graph_matches)) => {
             let output_path = graph_matches.get_one::<String>("output").unwrap();
             match export_regatta_graph(&data, output_path) {
-                Ok(()) => println!("Successfully exported graph to DOT file: {output_path} and generated PDF: regatta_graph.pdf"),
+                Ok(pdf) => match format {
+                    OutputFormat::Text if pdf => println!("Successfully exported graph to DOT file: {output_path} and generated PDF: regatta_graph.pdf"),
+                    OutputFormat::Text => println!("Successfully exported graph to DOT file: {output_path}"),
+                    OutputFormat::Json => print_json(&json!({ "output": output_path, "pdf": pdf })),
+                },
                 Err(e) => {
                     eprintln!("Error exporting graph to DOT file: {e}");
                     std::process::exit(1);
@@ -156,59 +157,200 @@ async fn main() {
             let to_name = estimate_matches.get_one::<String>("to").unwrap();
             let time_str = estimate_matches.get_one::<String>("time").unwrap();
             
-            match time_str.parse::<f64>() {
-                Ok(time) => {
-                    match estimate_leg_performance_command(&data, from_name, to_name, time) {
-                        Ok(()) => {},
-                        Err(e) => {
-                            eprintln!("Error estimating leg performance: {e}");
-                            std::process::exit(1);
-                        }
-                    }
-                }
-                Err(_) => {
-                    eprintln!("Error: time must be a valid number");
+            let time = parse_time_arg(&data, time_str);
+            match estimate_leg_performance_command(&data, from_name, to_name, time, format, lang) {
+                Ok(()) => {},
+                Err(e) => {
+                    eprintln!("Error estimating leg performance: {e}");
                     std::process::exit(1);
                 }
             }
         }
         Some(("serve", serve_matches)) => {
             let port_str = serve_matches.get_one::<String>("port").unwrap();
-            match port_str.parse::<u16>() {
-                Ok(port) => {
-                    println!("Starting HTTP server on port {port}...");
-                    if let Err(e) = server::start_server(data, port).await {
-                        eprintln!("Error starting server: {e}");
-                        std::process::exit(1);
-                    }
-                }
+            let bind_str = serve_matches.get_one::<String>("bind").unwrap();
+            let port = match port_str.parse::<u16>() {
+                Ok(port) => port,
                 Err(_) => {
                     eprintln!("Error: port must be a valid number between 1 and 65535");
                     std::process::exit(1);
                 }
+            };
+            let bind = match bind_str.parse::<std::net::IpAddr>() {
+                Ok(bind) => bind,
+                Err(_) => {
+                    eprintln!("Error: bind address must be a valid IP address, e.g. 127.0.0.1");
+                    std::process::exit(1);
+                }
+            };
+            let tls = match (
+                serve_matches.get_one::<String>("tls-cert"),
+                serve_matches.get_one::<String>("tls-key"),
+            ) {
+                (Some(cert), Some(key)) => Some(server::TlsConfig {
+                    cert_path: cert.into(),
+                    key_path: key.into(),
+                }),
+                _ => None,
+            };
+            let cache_size = match serve_matches.get_one::<String>("cache-size").unwrap().parse::<usize>() {
+                Ok(cache_size) => cache_size,
+                Err(_) => {
+                    eprintln!("Error: cache size must be a non-negative number");
+                    std::process::exit(1);
+                }
+            };
+            let max_search_nodes = match serve_matches.get_one::<String>("max-search-nodes").unwrap().parse::<u64>() {
+                Ok(max_search_nodes) => max_search_nodes,
+                Err(_) => {
+                    eprintln!("Error: maximum search nodes must be a non-negative number");
+                    std::process::exit(1);
+                }
+            };
+            let search_budget_value = |name: &str, what: &str| match serve_matches.get_one::<String>(name).unwrap().parse::<usize>() {
+                Ok(value) => value,
+                Err(_) => {
+                    eprintln!("Error: search budget {what} must be a non-negative number");
+                    std::process::exit(1);
+                }
+            };
+            let search_budget = optimize::SearchBudget {
+                max_nodes: search_budget_value("search-budget-nodes", "nodes"),
+                max_paths: search_budget_value("search-budget-paths", "paths"),
+                max_memory: search_budget_value("search-budget-memory", "memory").saturating_mul(1024 * 1024),
+            };
+            let max_searches_per_client = match serve_matches.get_one::<String>("max-searches-per-client").unwrap().parse::<usize>() {
+                Ok(max_searches_per_client) => max_searches_per_client,
+                Err(_) => {
+                    eprintln!("Error: maximum searches per client must be a non-negative number");
+                    std::process::exit(1);
+                }
+            };
+            let search_threads = match serve_matches.get_one::<String>("search-threads").unwrap().parse::<usize>() {
+                Ok(search_threads) => search_threads,
+                Err(_) => {
+                    eprintln!("Error: search threads must be a non-negative number");
+                    std::process::exit(1);
+                }
+            };
+            let nmea_listen = match serve_matches.get_one::<String>("nmea-listen").map(|addr| addr.parse::<std::net::SocketAddr>()) {
+                None => None,
+                Some(Ok(addr)) => Some(addr),
+                Some(Err(_)) => {
+                    eprintln!("Error: NMEA listen address must be IP:PORT, e.g. 0.0.0.0:10110");
+                    std::process::exit(1);
+                }
+            };
+            let plan_session = match serve_matches.get_one::<String>("plan-session").map(|id| id.parse::<u64>()) {
+                None => None,
+                Some(Ok(id)) => Some(id),
+                Some(Err(_)) => {
+                    eprintln!("Error: plan session must be a session ID");
+                    std::process::exit(1);
+                }
+            };
+            let alert_webhook = match serve_matches.get_one::<String>("alert-webhook").map(|url| webhook::Webhook::parse(url)) {
+                None => None,
+                Some(Ok(webhook)) => Some(webhook),
+                Some(Err(e)) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            };
+            let alert_mqtt = match serve_matches.get_one::<String>("alert-mqtt").map(|url| mqtt::MqttTopic::parse(url)) {
+                None => None,
+                Some(Ok(topic)) => Some(topic),
+                Some(Err(e)) => {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }
+            };
+            let alert_margin = match serve_matches.get_one::<String>("alert-margin").map(|m| m.parse::<u64>()) {
+                None => 0.0,
+                Some(Ok(minutes)) => minutes as f64 / 60.0,
+                Some(Err(_)) => {
+                    eprintln!("Error: alert margin must be a number of minutes");
+                    std::process::exit(1);
+                }
+            };
+            let position_timeout = match serve_matches.get_one::<String>("position-timeout").map(|m| m.parse::<u64>()) {
+                None | Some(Ok(0)) => None,
+                Some(Ok(minutes)) => Some(std::time::Duration::from_secs(minutes * 60)),
+                Some(Err(_)) => {
+                    eprintln!("Error: position timeout must be a number of minutes");
+                    std::process::exit(1);
+                }
+            };
+            let config = server::ServerConfig {
+                bind,
+                port,
+                tls,
+                base_path: serve_matches.get_one::<String>("base-path").unwrap().clone(),
+                templates_dir: serve_matches.get_one::<String>("templates").map(Into::into),
+                cache_size,
+                auth_token: serve_matches
+                    .get_one::<String>("auth-token")
+                    .cloned()
+                    .or_else(|| std::env::var("UURS24_AUTH_TOKEN").ok())
+                    .filter(|token| !token.is_empty()),
+                max_search_nodes,
+                search_budget,
+                max_searches_per_client,
+                search_threads,
+                track_log: serve_matches.get_one::<String>("track-log").map(Into::into),
+                observations_log: serve_matches.get_one::<String>("observations-log").map(Into::into),
+                nmea_listen,
+                sessions_file: serve_matches.get_one::<String>("sessions-file").map(Into::into),
+                plans_file: serve_matches.get_one::<String>("plans-file").map(Into::into),
+                database: serve_matches.get_one::<String>("database").map(Into::into),
+                plan_session,
+                alert_webhook,
+                alert_mqtt,
+                alert_margin,
+                position_timeout,
+                lang: lang_arg,
+            };
+
+            tracing::info!("Starting HTTP server on port {port}...");
+            if let Err(e) = server::start_server(data, config).await {
+                eprintln!("Error starting server: {e}");
+                std::process::exit(1);
             }
         }
         Some(("paths", paths_matches)) => {
             let start_name = paths_matches.get_one::<String>("start").unwrap();
             let time_str = paths_matches.get_one::<String>("time").unwrap();
-            let steps_str = paths_matches.get_one::<String>("steps").unwrap();
+            let max_wait = wait_arg(&data, *paths_matches.get_one::<f64>("max-wait").unwrap());
+            let routes = paths_matches.get_one::<usize>("diverse").copied();
+            let min_distance = match routes {
+                Some(_) => paths_matches.get_one::<f64>("diversity").copied().unwrap_or(0.5),
+                None => 0.0,
+            };
+            if !(0.0..=1.0).contains(&min_distance) {
+                eprintln!("Error: --diversity must be between 0 and 1");
+                std::process::exit(1);
+            }
+            let selection = optimize::PathSelection {
+                objective: std::sync::Arc::new(objective_arg(paths_matches)),
+                routes,
+                min_distance,
+            };
             
-            match (time_str.parse::<f64>(), steps_str.parse::<usize>()) {
-                (Ok(time), Ok(steps)) => {
-                    match explore_paths_command(&data, start_name, time, steps) {
-                        Ok(()) => {},
-                        Err(e) => {
-                            eprintln!("Error exploring paths: {e}");
-                            std::process::exit(1);
-                        }
+            let time = parse_time_arg(&data, time_str);
+            let lookahead = lookahead_args(&data, paths_matches);
+            let options = SearchOptions {
+                max_wait,
+                constraints: avoid_args(&data, paths_matches),
+                ..SearchOptions::default()
+            };
+            match explore_paths_command(&data, start_name, time, lookahead, &options, &selection, format) {
+                Ok(stats) => {
+                    if paths_matches.get_flag("stats") && format == OutputFormat::Text {
+                        print_search_stats(&stats);
                     }
                 }
-                (Err(_), _) => {
-                    eprintln!("Error: time must be a valid number");
-                    std::process::exit(1);
-                }
-                (_, Err(_)) => {
-                    eprintln!("Error: steps must be a valid positive integer");
+                Err(e) => {
+                    eprintln!("Error exploring paths: {e}");
                     std::process::exit(1);
                 }
             }
@@ -217,80 +359,1370 @@ async fn main() {
             let start_name = target_matches.get_one::<String>("start").unwrap();
             let target_name = target_matches.get_one::<String>("target").unwrap();
             let time_str = target_matches.get_one::<String>("time").unwrap();
-            let steps_str = target_matches.get_one::<String>("steps").unwrap();
+            let max_wait = wait_arg(&data, *target_matches.get_one::<f64>("max-wait").unwrap());
             
-            match (time_str.parse::<f64>(), steps_str.parse::<usize>()) {
-                (Ok(time), Ok(steps)) => {
-                    match explore_target_paths_command(&data, start_name, target_name, time, steps) {
-                        Ok(()) => {},
-                        Err(e) => {
-                            eprintln!("Error exploring target paths: {e}");
-                            std::process::exit(1);
-                        }
+            let time = parse_time_arg(&data, time_str);
+            let lookahead = lookahead_args(&data, target_matches);
+            let options = SearchOptions {
+                max_wait,
+                constraints: avoid_args(&data, target_matches),
+                ..SearchOptions::default()
+            };
+            let objective = objective_arg(target_matches);
+            let ends = (start_name.as_str(), target_name.as_str());
+            match explore_target_paths_command(&data, ends, time, lookahead, &options, &objective, format) {
+                Ok(stats) => {
+                    if target_matches.get_flag("stats") && format == OutputFormat::Text {
+                        print_search_stats(&stats);
                     }
                 }
-                (Err(_), _) => {
-                    eprintln!("Error: time must be a valid number");
+                Err(e) => {
+                    eprintln!("Error exploring target paths: {e}");
                     std::process::exit(1);
                 }
-                (_, Err(_)) => {
-                    eprintln!("Error: steps must be a valid positive integer");
+            }
+        }
+        Some(("route", route_matches)) => {
+            let buoys: Vec<&String> = route_matches.get_many::<String>("buoys").unwrap().collect();
+            let time_str = route_matches.get_one::<String>("start-time").unwrap();
+
+            let time = parse_time_arg(&data, time_str);
+            let constraints = avoid_args(&data, route_matches);
+            match route_command(&data, &buoys, time, &constraints, format) {
+                Ok(()) => {},
+                Err(e) => {
+                    eprintln!("Error evaluating route: {e}");
                     std::process::exit(1);
                 }
             }
         }
-        Some(("version", _)) => {
-            println!("uurs24 version {}", env!("CARGO_PKG_VERSION"));
-            println!("24-hour regatta data management tool");
-            println!("Authors: Claude and Max Neunhöffer");
-        }
-        _ => {
-            // Default behavior when no subcommand is provided
-            show_regatta_data(&data);
+        Some(("deadlines", deadlines_matches)) => {
+            let time = parse_time_arg(&data, deadlines_matches.get_one::<String>("time").unwrap());
+            let map = deadlines_matches.get_one::<String>("map").map(|path| {
+                let theme = deadlines_matches
+                    .get_one::<String>("theme")
+                    .and_then(|name| Theme::from_name(name))
+                    .unwrap_or_default();
+                (path.as_str(), theme)
+            });
+            if let Err(e) = deadlines_command(&data, time, map, format) {
+                eprintln!("Error computing the latest departures: {e}");
+                std::process::exit(1);
+            }
         }
-    }
-}
-
-fn show_regatta_data(data: &data::RegattaData) {
-    println!("Successfully loaded regatta data:");
-    println!("  - {} buoys (boeien)", data.boeien.len());
-    println!("  - {} start lines", data.starts.len());
-    println!("  - {} legs (rakken)", data.rakken.len());
+        Some(("simulate", simulate_matches)) => {
+            let buoys: Vec<&String> = simulate_matches.get_many::<String>("buoys").unwrap().collect();
+            let time_str = simulate_matches.get_one::<String>("start-time").unwrap();
+            let step_minutes = *simulate_matches.get_one::<f64>("step-minutes").unwrap();
+            let current_direction = simulate_matches.get_one::<f64>("current-direction");
+            let current_speed = simulate_matches.get_one::<f64>("current-speed");
+            let rounding_penalty = *simulate_matches.get_one::<f64>("rounding-penalty").unwrap();
+            let delay = *simulate_matches.get_one::<f64>("delay").unwrap();
+            let lost_time: Vec<&String> = simulate_matches.get_many::<String>("lost-time").unwrap_or_default().collect();
+            let output_path = simulate_matches.get_one::<String>("output");
 
-    // Find and display the FINISH buoy
-    if let Some(finish_boei) = data.get_boei("FINISH") {
-        println!("\nFINISH buoy details:");
-        println!("  Name: {}", finish_boei.name);
-        if let Some(buoy_type) = &finish_boei.buoy_type {
-            println!("  Type: {buoy_type}");
-        }
-        if let Some(lat) = &finish_boei.lat {
-            println!("  Latitude: {lat:.6}° (decimal)");
-        }
-        if let Some(long) = &finish_boei.long {
-            println!("  Longitude: {long:.6}° (decimal)");
+            let options = simulate::SimulationOptions {
+                time_step: step_minutes / 60.0,
+                current: current_speed.map(|&speed| simulate::Current {
+                    direction: current_direction.copied().unwrap_or(0.0),
+                    speed,
+                }),
+                rounding_penalty: rounding_penalty / 60.0,
+                lost_time: Vec::new(),
+            };
+            let time = parse_time_arg(&data, time_str);
+            let what_if = WhatIf { delay: delay / 60.0, lost_time };
+            match simulate_command(&data, &buoys, time, options, &what_if, output_path.map(String::as_str), format) {
+                Ok(()) => {},
+                Err(e) => {
+                    eprintln!("Error simulating route: {e}");
+                    std::process::exit(1);
+                }
+            }
         }
-        if let Some(lat_str) = &finish_boei.lat_min {
-            println!("  Latitude (original): {lat_str}");
+        Some(("simulate-ensemble", ensemble_matches)) => {
+            let buoys: Vec<&String> = ensemble_matches.get_many::<String>("buoys").unwrap().collect();
+            let time_str = ensemble_matches.get_one::<String>("start-time").unwrap();
+            let step_minutes = *ensemble_matches.get_one::<f64>("step-minutes").unwrap();
+            let runs = *ensemble_matches.get_one::<usize>("runs").unwrap();
+            let seed = ensemble_matches.get_one::<u64>("seed").copied().unwrap_or_else(rand::random);
+            run_seed = Some(seed);
+            let ensemble_options = ensemble::EnsembleOptions {
+                perturbation: ensemble::WindPerturbation {
+                    speed_sigma: *ensemble_matches.get_one::<f64>("speed-sigma").unwrap(),
+                    direction_sigma: *ensemble_matches.get_one::<f64>("direction-sigma").unwrap(),
+                    correlation: *ensemble_matches.get_one::<f64>("correlation").unwrap(),
+                },
+                runs,
+                seed,
+            };
+
+            let options = simulate::SimulationOptions {
+                time_step: step_minutes / 60.0,
+                ..simulate::SimulationOptions::default()
+            };
+            let time = parse_time_arg(&data, time_str);
+            match simulate_ensemble_command(&data, &buoys, time, &options, &ensemble_options, format) {
+                Ok(()) => {},
+                Err(e) => {
+                    eprintln!("Error simulating wind ensemble: {e}");
+                    std::process::exit(1);
+                }
+            }
         }
-        if let Some(long_str) = &finish_boei.long_min {
-            println!("  Longitude (original): {long_str}");
+        Some(("simulate-fleet", fleet_matches)) => {
+            let fleet_path = fleet_matches.get_one::<String>("fleet").unwrap();
+            let route = fleet_matches.get_one::<String>("route");
+            let time_str = fleet_matches.get_one::<String>("time").unwrap();
+            let step_minutes = *fleet_matches.get_one::<f64>("step-minutes").unwrap();
+
+            let options = simulate::SimulationOptions {
+                time_step: step_minutes / 60.0,
+                ..simulate::SimulationOptions::default()
+            };
+            let time = parse_time_arg(&data, time_str);
+            match simulate_fleet_command(&data, fleet_path, route.map(String::as_str), time, &options, format) {
+                Ok(()) => {},
+                Err(e) => {
+                    eprintln!("Error simulating fleet: {e}");
+                    std::process::exit(1);
+                }
+            }
         }
+        Some(("export-route", export_matches)) => {
+            let buoys: Vec<&String> = export_matches.get_many::<String>("buoys").unwrap().collect();
+            let time_str = export_matches.get_one::<String>("start-time").unwrap();
+            let export_format = export_matches.get_one::<String>("format").unwrap();
+            let output_path = export_matches.get_one::<String>("output");
+            let watch_hours = export_matches.get_one::<f64>("watch-hours");
+            let watches = export_matches.get_one::<String>("watches").unwrap();
 
-        // Demonstrate the convenience methods
-        if finish_boei.has_coordinates() {
-            if let Some((lat, long)) = finish_boei.coordinates() {
-                println!("  Coordinates tuple: ({lat:.6}, {long:.6})");
+            let time = parse_time_arg(&data, time_str);
+            let exporter: Box<dyn export::RouteExporter> = match watch_hours {
+                Some(&length) if export_format == "ics" => Box::new(calendar::IcsExporter {
+                    watches: Some(watches::WatchRotation {
+                        names: watches.split(',').map(|name| name.trim().to_string()).collect(),
+                        length,
+                    }),
+                }),
+                _ => export::route_exporter(export_format).unwrap(),
+            };
+            match export_route_command(&data, &buoys, time, exporter.as_ref(), output_path.map(String::as_str), format) {
+                Ok(()) => {},
+                Err(e) => {
+                    eprintln!("Error exporting route: {e}");
+                    std::process::exit(1);
+                }
             }
         }
-    }
+        Some(("watch-plan", watch_matches)) => {
+            let buoys: Vec<&String> = watch_matches.get_many::<String>("buoys").unwrap().collect();
+            let time_str = watch_matches.get_one::<String>("start-time").unwrap();
+            let watch_hours = *watch_matches.get_one::<f64>("watch-hours").unwrap();
+            let names = watch_matches.get_one::<String>("watches").unwrap();
+            let rounding_margin = *watch_matches.get_one::<f64>("rounding-margin").unwrap();
+            let max_shift = *watch_matches.get_one::<f64>("max-shift").unwrap();
+            let output_path = watch_matches.get_one::<String>("output");
 
-    // Show all boeien with coordinates
-    println!("\nAll boeien with geo coordinates:");
-    let mut buoys_with_coords = 0;
-    let mut buoys_without_coords = 0;
-    
-    for boei in &data.boeien {
+            let rotation = watches::WatchRotation {
+                names: names.split(',').map(|name| name.trim().to_string()).collect(),
+                length: watch_hours,
+            };
+            let options = watches::WatchPlanOptions {
+                rounding_margin: rounding_margin / 60.0,
+                max_shift: max_shift / 60.0,
+            };
+            let time = parse_time_arg(&data, time_str);
+            match watch_plan_command(&data, &buoys, time, &rotation, &options, output_path.map(String::as_str), format) {
+                Ok(()) => {},
+                Err(e) => {
+                    eprintln!("Error planning watches: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("briefing", briefing_matches)) => {
+            let route = briefing_matches.get_one::<String>("route").unwrap();
+            let time_str = briefing_matches.get_one::<String>("time").unwrap();
+            let output_path = briefing_matches.get_one::<String>("output").unwrap();
+            let sunrise_str = briefing_matches.get_one::<String>("sunrise").unwrap();
+            let sunset_str = briefing_matches.get_one::<String>("sunset").unwrap();
+            let daylight = match (parse_clock_minutes(sunrise_str), parse_clock_minutes(sunset_str)) {
+                (Some(sunrise), Some(sunset)) => (sunrise, sunset),
+                _ => {
+                    eprintln!("Error: sunrise and sunset must be given as HH:MM");
+                    std::process::exit(1);
+                }
+            };
+
+            let time = parse_time_arg(&data, time_str);
+            match briefing_command(&data, route, time, output_path, daylight, format) {
+                Ok(()) => {},
+                Err(e) => {
+                    eprintln!("Error generating briefing pack: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("report", report_matches)) => {
+            let track_path = report_matches.get_one::<String>("track").unwrap();
+            let route = report_matches.get_one::<String>("route");
+            let time_str = report_matches.get_one::<String>("time");
+            let output_path = report_matches.get_one::<String>("output").unwrap();
+            let boat = report_matches.get_one::<String>("boat");
+            let rating = report_matches
+                .get_one::<f64>("rating")
+                .copied()
+                .or_else(|| boat.and_then(|boat| data.rating(boat)));
+
+            let time = time_str.map(|time_str| parse_time_arg(&data, time_str));
+            match race_report_command(&data, track_path, route.map(String::as_str), time, rating, output_path, format) {
+                Ok(()) => {},
+                Err(e) => {
+                    eprintln!("Error generating race report: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("replay", replay_matches)) => {
+            let track_path = replay_matches.get_one::<String>("track").unwrap();
+            let steps = steps_arg(&data, *replay_matches.get_one::<usize>("steps").unwrap());
+            let options = replay::ReplayOptions {
+                steps,
+                objective: objective_arg(replay_matches),
+            };
+            match replay_command(&data, track_path, &options, format) {
+                Ok(()) => {},
+                Err(e) => {
+                    eprintln!("Error replaying race: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("declaration", declaration_matches)) => {
+            let track_path = declaration_matches.get_one::<String>("track").unwrap();
+            let boat = declaration_matches.get_one::<String>("boat").unwrap();
+            let output_path = declaration_matches.get_one::<String>("output");
+            match declaration_command(&data, track_path, boat, output_path.map(String::as_str), format) {
+                Ok(()) => {},
+                Err(e) => {
+                    eprintln!("Error writing declaration: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("audit", audit_matches)) => {
+            let options = audit::AuditOptions {
+                directions: *audit_matches.get_one::<usize>("directions").unwrap(),
+                wind_speed: *audit_matches.get_one::<f64>("wind-speed").unwrap(),
+                lookahead: *audit_matches.get_one::<usize>("lookahead").unwrap(),
+            };
+            match audit_command(&data, &options, format) {
+                Ok(()) => {},
+                Err(e) => {
+                    eprintln!("Error auditing course: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("check-manifest", check_matches)) => {
+            let manifest_path = check_matches.get_one::<String>("manifest").unwrap();
+            match check_manifest_command(manifest_path, format) {
+                Ok(()) => {},
+                Err(e) => {
+                    eprintln!("Error checking manifest: {e}");
+                    std::process::exit(1);
+                }
+            }
+        }
+        Some(("version", _)) => match format {
+            OutputFormat::Text => {
+                println!("uurs24 version {}", env!("CARGO_PKG_VERSION"));
+                println!("24-hour regatta data management tool");
+                println!("Authors: Claude and Max Neunhöffer");
+            }
+            OutputFormat::Json => print_json(&VersionResponse {
+                version: env!("CARGO_PKG_VERSION").to_string(),
+            }),
+        },
+        _ => {
+            // Default behavior when no subcommand is provided
+            show_command(&data, &warnings, format);
+        }
+    }
+
+    // Failed runs have exited already, so only successful ones get a manifest
+    if let Some(manifest_path) = matches.get_one::<String>("manifest") {
+        let (command, parameters) = match matches.subcommand() {
+            Some((command, sub_matches)) => (command, run_parameters(sub_matches)),
+            None => ("show", json!({})),
+        };
+        let arguments = std::env::args().collect();
+        let written = manifest::RunManifest::new(command, arguments, parameters, run_seed)
+            .and_then(|manifest| manifest.write(std::path::Path::new(manifest_path)));
+        if let Err(e) = written {
+            eprintln!("Error writing run manifest: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The arguments of a subcommand as JSON object, with the defaults filled in
+fn run_parameters(matches: &clap::ArgMatches) -> serde_json::Value {
+    let mut parameters = serde_json::Map::new();
+    for id in matches.ids() {
+        let Ok(Some(values)) = matches.try_get_raw(id.as_str()) else {
+            continue;
+        };
+        let mut values: Vec<serde_json::Value> =
+            values.map(|value| value.to_string_lossy().into_owned().into()).collect();
+        let value = if values.len() == 1 { values.remove(0) } else { values.into() };
+        parameters.insert(id.to_string(), value);
+    }
+    parameters.into()
+}
+
+/// Command line interface of the tool
+fn cli() -> Command {
+    Command::new("uurs24")
+        .about("24-hour regatta data management tool")
+        .version(env!("CARGO_PKG_VERSION"))
+        .subcommand_negates_reqs(true)
+        .arg(
+            clap::Arg::new("output")
+                .long("output")
+                .value_name("FORMAT")
+                .help("Print the results as text or as JSON in the schemas of the HTTP API (default: text)")
+                .value_parser(["text", "json"])
+                .default_value("text"),
+        )
+        .arg(
+            clap::Arg::new("lang")
+                .long("lang")
+                .value_name("LANG")
+                .help("Language of the text output and the default of the web interface (default: from LANG, else English)")
+                .value_parser(["en", "nl"]),
+        )
+        .arg(
+            clap::Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Log more details to stderr, -vv for even more")
+                .action(clap::ArgAction::Count)
+                .global(true),
+        )
+        .arg(
+            clap::Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .help("Only log errors to stderr")
+                .action(clap::ArgAction::SetTrue)
+                .conflicts_with("verbose")
+                .global(true),
+        )
+        .arg(
+            clap::Arg::new("race-start")
+                .long("race-start")
+                .value_name("DATETIME")
+                .help("Date and time of the race start, e.g. \"2026-06-12 18:00\", to give times as dates"),
+        )
+        .arg(
+            clap::Arg::new("class")
+                .long("class")
+                .value_name("CLASS")
+                .help("Sail as a boat of this class of data/classes.csv: times count from the start of the class"),
+        )
+        .arg(
+            clap::Arg::new("manifest")
+                .long("manifest")
+                .value_name("FILE")
+                .help("Write a manifest of the run to this JSON file: version, hashes of the data files, parameters, and seed"),
+        )
+        .arg(
+            clap::Arg::new("strict")
+                .long("strict")
+                .help("Refuse suspicious data: fail on any data warning, like a distance far off the buoys")
+                .action(clap::ArgAction::SetTrue)
+                .global(true),
+        )
+        .subcommand(Command::new("show").about("Show regatta data and statistics"))
+        .subcommand(
+            Command::new("plot")
+                .about("Generate SVG visualization of the regatta course")
+                .arg(
+                    clap::Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Output SVG file path (default: regatta_course.svg, or wind_chart.svg with --wind-chart)"),
+                )
+                .arg(
+                    clap::Arg::new("wind-chart")
+                        .long("wind-chart")
+                        .help("Plot a strip chart of the wind forecast instead of the course")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("sunrise")
+                        .long("sunrise")
+                        .value_name("HH:MM")
+                        .help("Local time of sunrise for the night shading of the wind chart")
+                        .default_value("05:30"),
+                )
+                .arg(
+                    clap::Arg::new("sunset")
+                        .long("sunset")
+                        .value_name("HH:MM")
+                        .help("Local time of sunset for the night shading of the wind chart")
+                        .default_value("21:45"),
+                )
+                .arg(
+                    clap::Arg::new("theme")
+                        .long("theme")
+                        .value_name("THEME")
+                        .help("Colors of the plot: light, or dark in shades of red for reading at night")
+                        .value_parser(["light", "dark"])
+                        .default_value("light"),
+                ),
+        )
+        .subcommand(
+            Command::new("graph")
+                .about("Export the regatta graph to a DOT file for graphviz")
+                .arg(
+                    clap::Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Output DOT file path (default: regatta_graph.dot)")
+                        .default_value("regatta_graph.dot"),
+                ),
+        )
+        .subcommand(
+            Command::new("estimate")
+                .about("Estimate boat performance between two buoys at a specific time")
+                .arg(
+                    clap::Arg::new("from")
+                        .help("Name of the starting buoy")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("to")
+                        .help("Name of the destination buoy")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("time")
+                        .help("Time in hours after race start, or as clock time HH:MM")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Start HTTP server to serve regatta data")
+                .arg(
+                    clap::Arg::new("port")
+                        .short('p')
+                        .long("port")
+                        .value_name("PORT")
+                        .help("Port to bind the server to (default: 3030)")
+                        .default_value("3030"),
+                )
+                .arg(
+                    clap::Arg::new("bind")
+                        .short('b')
+                        .long("bind")
+                        .value_name("ADDRESS")
+                        .help("IP address to bind the server to, e.g. 127.0.0.1 for localhost only (default: 0.0.0.0)")
+                        .default_value("0.0.0.0"),
+                )
+                .arg(
+                    clap::Arg::new("tls-cert")
+                        .long("tls-cert")
+                        .value_name("FILE")
+                        .help("PEM certificate file, serves HTTPS together with --tls-key")
+                        .requires("tls-key"),
+                )
+                .arg(
+                    clap::Arg::new("tls-key")
+                        .long("tls-key")
+                        .value_name("FILE")
+                        .help("PEM private key file, serves HTTPS together with --tls-cert")
+                        .requires("tls-cert"),
+                )
+                .arg(
+                    clap::Arg::new("base-path")
+                        .long("base-path")
+                        .value_name("PATH")
+                        .help("Path prefix of all routes when running behind a reverse proxy, e.g. /uurs24")
+                        .default_value(""),
+                )
+                .arg(
+                    clap::Arg::new("templates")
+                        .long("templates")
+                        .value_name("DIR")
+                        .help("Load the web interface templates from this directory instead of the built-in ones"),
+                )
+                .arg(
+                    clap::Arg::new("cache-size")
+                        .long("cache-size")
+                        .value_name("ENTRIES")
+                        .help("Number of API responses to cache, 0 disables the cache (default: 256)")
+                        .default_value("256"),
+                )
+                .arg(
+                    clap::Arg::new("auth-token")
+                        .long("auth-token")
+                        .value_name("TOKEN")
+                        .help("Require this bearer token for the API and admin endpoints (default: $UURS24_AUTH_TOKEN)"),
+                )
+                .arg(
+                    clap::Arg::new("max-search-nodes")
+                        .long("max-search-nodes")
+                        .value_name("NODES")
+                        .help("Reject searches estimated to explore more nodes, 0 for no limit (default: 5000000)")
+                        .default_value("5000000"),
+                )
+                .arg(
+                    clap::Arg::new("search-budget-nodes")
+                        .long("search-budget-nodes")
+                        .value_name("NODES")
+                        .help("Stop a search after expanding this many nodes and return the paths found so far, 0 for no limit (default: 0)")
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("search-budget-paths")
+                        .long("search-budget-paths")
+                        .value_name("PATHS")
+                        .help("Stop a search once it holds this many paths and return them, 0 for no limit (default: 0)")
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("search-budget-memory")
+                        .long("search-budget-memory")
+                        .value_name("MB")
+                        .help("Stop a search once the paths it holds take about this many megabytes and return them, 0 for no limit (default: 256)")
+                        .default_value("256"),
+                )
+                .arg(
+                    clap::Arg::new("max-searches-per-client")
+                        .long("max-searches-per-client")
+                        .value_name("SEARCHES")
+                        .help("Searches a client IP address may run at the same time, 0 for no limit (default: 2)")
+                        .default_value("2"),
+                )
+                .arg(
+                    clap::Arg::new("search-threads")
+                        .long("search-threads")
+                        .value_name("THREADS")
+                        .help("Searches running at the same time, further ones wait; 0 for the number of CPUs (default: 0)")
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("track-log")
+                        .long("track-log")
+                        .value_name("FILE")
+                        .help("Append reported boat positions to this file and continue its track on startup"),
+                )
+                .arg(
+                    clap::Arg::new("observations-log")
+                        .long("observations-log")
+                        .value_name("FILE")
+                        .help("Append leg speed observations to this file and calibrate with them on startup"),
+                )
+                .arg(
+                    clap::Arg::new("nmea-listen")
+                        .long("nmea-listen")
+                        .value_name("ADDRESS")
+                        .help("Receive NMEA 0183 wind and position sentences over UDP and TCP, e.g. 0.0.0.0:10110"),
+                )
+                .arg(
+                    clap::Arg::new("sessions-file")
+                        .long("sessions-file")
+                        .value_name("FILE")
+                        .help("Keep the planning sessions in this JSON file so they survive restarts"),
+                )
+                .arg(
+                    clap::Arg::new("plans-file")
+                        .long("plans-file")
+                        .value_name("FILE")
+                        .help("Keep the shared plans in this JSON file so their links survive restarts"),
+                )
+                .arg(
+                    clap::Arg::new("database")
+                        .long("database")
+                        .value_name("FILE")
+                        .conflicts_with_all(["track-log", "sessions-file", "plans-file"])
+                        .help("Keep the tracks, sessions, and plans in this SQLite database instead (needs the sqlite feature)"),
+                )
+                .arg(
+                    clap::Arg::new("plan-session")
+                        .long("plan-session")
+                        .value_name("ID")
+                        .help("Planning session with the committed plan of the boat, for progress tracking"),
+                )
+                .arg(
+                    clap::Arg::new("alert-webhook")
+                        .long("alert-webhook")
+                        .alias("progress-webhook")
+                        .value_name("URL")
+                        .help("POST alerts to this http:// URL: late finishes, recommendations changed by the wind, missing positions"),
+                )
+                .arg(
+                    clap::Arg::new("alert-mqtt")
+                        .long("alert-mqtt")
+                        .value_name("URL")
+                        .help("Publish the alerts on an MQTT topic, given as mqtt://[user[:password]@]host[:port]/topic"),
+                )
+                .arg(
+                    clap::Arg::new("alert-margin")
+                        .long("alert-margin")
+                        .value_name("MINUTES")
+                        .help("Alert when a boat is projected to finish with less than this to spare before the deadline (default: 0)"),
+                )
+                .arg(
+                    clap::Arg::new("position-timeout")
+                        .long("position-timeout")
+                        .value_name("MINUTES")
+                        .help("Alert when a boat which reported positions hasn't reported one for this long (default: 0, off)"),
+                ),
+        )
+        .subcommand(
+            Command::new("paths")
+                .about("Explore all possible paths from a starting point")
+                .arg(
+                    clap::Arg::new("start")
+                        .help("Name of the starting buoy")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("time")
+                        .help("Starting time in hours after race start, or as clock time HH:MM")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("steps")
+                        .help("Number of steps to explore")
+                        .value_parser(clap::value_parser!(usize))
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("max-wait")
+                        .long("max-wait")
+                        .value_name("HOURS")
+                        .help("Wait up to this long at a buoy, in steps of 15 minutes, if the next leg then finishes earlier")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("strategy")
+                        .long("strategy")
+                        .value_name("STRATEGY")
+                        .help("exhaustive searches all paths; hierarchical chooses a skeleton of key marks first and fills in the legs between them, the steps then being the legs of the routes at most, up to the route length of the rules")
+                        .value_parser(["exhaustive", "hierarchical"])
+                        .default_value("exhaustive"),
+                )
+                .arg(
+                    clap::Arg::new("stats")
+                        .long("stats")
+                        .help("Print the statistics of the search: nodes expanded, branches pruned by reason, cache hit rate, and elapsed time")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("objective")
+                        .long("objective")
+                        .value_name("OBJECTIVE")
+                        .help("Order of the paths: earliest_arrival, max_distance, qualify for the qualifying paths finishing earliest first, max_margin for the paths ending by the deadline with the most time to spare, or weighted:MEASURE=WEIGHT,... for the highest weighted sum of scored_distance, total_distance, margin, and average_speed")
+                        .default_value("earliest_arrival"),
+                )
+                .arg(
+                    clap::Arg::new("diverse")
+                        .long("diverse")
+                        .value_name("ROUTES")
+                        .help("Print only this many of the best routes which differ from each other by --diversity")
+                        .value_parser(clap::value_parser!(usize)),
+                )
+                .arg(
+                    clap::Arg::new("diversity")
+                        .long("diversity")
+                        .value_name("DISTANCE")
+                        .help("Jaccard distance of the legs each route printed keeps from the better ones, 0 to 1 (default: 0.5)")
+                        .value_parser(clap::value_parser!(f64))
+                        .requires("diverse"),
+                )
+                .arg(
+                    clap::Arg::new("avoid")
+                        .long("avoid")
+                        .value_name("BUOYS")
+                        .help("Comma separated buoy names the paths must not visit, e.g. shallow banks at low water"),
+                )
+                .arg(
+                    clap::Arg::new("avoid-leg")
+                        .long("avoid-leg")
+                        .value_name("LEGS")
+                        .help("Comma separated legs FROM-TO the paths must not sail in either direction, e.g. across a traffic lane at night"),
+                ),
+        )
+        .subcommand(
+            Command::new("target")
+                .about("Explore paths from a starting point to a specific target")
+                .arg(
+                    clap::Arg::new("start")
+                        .help("Name of the starting buoy")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("target")
+                        .help("Name of the target buoy")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("time")
+                        .help("Starting time in hours after race start, or as clock time HH:MM")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("steps")
+                        .help("Maximum number of steps to explore")
+                        .value_parser(clap::value_parser!(usize))
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("max-wait")
+                        .long("max-wait")
+                        .value_name("HOURS")
+                        .help("Wait up to this long at a buoy, in steps of 15 minutes, if the next leg then finishes earlier")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("objective")
+                        .long("objective")
+                        .value_name("OBJECTIVE")
+                        .help("Order of the paths, as for paths")
+                        .default_value("earliest_arrival"),
+                )
+                .arg(
+                    clap::Arg::new("strategy")
+                        .long("strategy")
+                        .value_name("STRATEGY")
+                        .help("exhaustive searches all paths; hierarchical chooses a skeleton of key marks first and fills in the legs between them, the steps then being the legs of the routes at most, up to the route length of the rules")
+                        .value_parser(["exhaustive", "hierarchical"])
+                        .default_value("exhaustive"),
+                )
+                .arg(
+                    clap::Arg::new("stats")
+                        .long("stats")
+                        .help("Print the statistics of the search: nodes expanded, branches pruned by reason, cache hit rate, and elapsed time")
+                        .action(clap::ArgAction::SetTrue),
+                )
+                .arg(
+                    clap::Arg::new("avoid")
+                        .long("avoid")
+                        .value_name("BUOYS")
+                        .help("Comma separated buoy names the paths must not visit, e.g. shallow banks at low water"),
+                )
+                .arg(
+                    clap::Arg::new("avoid-leg")
+                        .long("avoid-leg")
+                        .value_name("LEGS")
+                        .help("Comma separated legs FROM-TO the paths must not sail in either direction, e.g. across a traffic lane at night"),
+                ),
+        )
+        .subcommand(
+            Command::new("route")
+                .about("Evaluate a given sequence of buoys as a route")
+                .arg(
+                    clap::Arg::new("buoys")
+                        .help("Names of the buoys in the order they are sailed, starting with a start buoy")
+                        .num_args(2..)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("start-time")
+                        .long("start-time")
+                        .value_name("TIME")
+                        .help("Start of the route in hours after race start, or as clock time HH:MM")
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("avoid")
+                        .long("avoid")
+                        .value_name("BUOYS")
+                        .help("Comma separated buoy names the route must not visit"),
+                )
+                .arg(
+                    clap::Arg::new("avoid-leg")
+                        .long("avoid-leg")
+                        .value_name("LEGS")
+                        .help("Comma separated legs FROM-TO the route must not sail in either direction"),
+                ),
+        )
+        .subcommand(
+            Command::new("deadlines")
+                .about("Latest departure from every buoy which still reaches the finish by the end of the race")
+                .arg(
+                    clap::Arg::new("time")
+                        .long("time")
+                        .value_name("TIME")
+                        .help("Earliest departure to consider, in hours after race start or as clock time HH:MM")
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("map")
+                        .long("map")
+                        .value_name("FILE")
+                        .help("Save the course map with the latest departure under every buoy as SVG"),
+                )
+                .arg(
+                    clap::Arg::new("theme")
+                        .long("theme")
+                        .value_name("THEME")
+                        .help("Colors of the map: light, or dark in shades of red for reading at night")
+                        .value_parser(["light", "dark"])
+                        .default_value("light"),
+                ),
+        )
+        .subcommand(
+            Command::new("simulate")
+                .about("Sail a given sequence of buoys forward in time in fixed steps")
+                .arg(
+                    clap::Arg::new("buoys")
+                        .help("Names of the buoys in the order they are sailed, starting with a start buoy")
+                        .num_args(2..)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("start-time")
+                        .long("start-time")
+                        .value_name("TIME")
+                        .help("Start of the route in hours after race start, or as clock time HH:MM")
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("step-minutes")
+                        .long("step-minutes")
+                        .value_name("MINUTES")
+                        .help("Length of a time step in minutes")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("1"),
+                )
+                .arg(
+                    clap::Arg::new("current-speed")
+                        .long("current-speed")
+                        .value_name("KNOTS")
+                        .help("Speed of a current over the whole course")
+                        .value_parser(clap::value_parser!(f64)),
+                )
+                .arg(
+                    clap::Arg::new("current-direction")
+                        .long("current-direction")
+                        .value_name("DEGREES")
+                        .help("Direction the current flows to (default: 0)")
+                        .value_parser(clap::value_parser!(f64)),
+                )
+                .arg(
+                    clap::Arg::new("rounding-penalty")
+                        .long("rounding-penalty")
+                        .value_name("MINUTES")
+                        .help("Time lost at every mark rounding")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("delay")
+                        .long("delay")
+                        .value_name("MINUTES")
+                        .help("What if the route starts this much later than planned")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("lost-time")
+                        .long("lost-time")
+                        .value_name("LEG:MINUTES")
+                        .help("What if this much time is lost on a leg, given by its number or the buoy at its end, e.g. 2:15 or WV12:40; may be repeated")
+                        .action(clap::ArgAction::Append),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Write the time series of positions and speeds as CSV file"),
+                ),
+        )
+        .subcommand(
+            Command::new("simulate-ensemble")
+                .about("Simulate a given sequence of buoys in many random realizations of the wind forecast")
+                .arg(
+                    clap::Arg::new("buoys")
+                        .help("Names of the buoys in the order they are sailed, starting with a start buoy")
+                        .num_args(2..)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("start-time")
+                        .long("start-time")
+                        .value_name("TIME")
+                        .help("Start of the route in hours after race start, or as clock time HH:MM")
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("step-minutes")
+                        .long("step-minutes")
+                        .value_name("MINUTES")
+                        .help("Length of a time step in minutes")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("1"),
+                )
+                .arg(
+                    clap::Arg::new("runs")
+                        .long("runs")
+                        .value_name("N")
+                        .help("Number of wind realizations to simulate")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("100"),
+                )
+                .arg(
+                    clap::Arg::new("seed")
+                        .long("seed")
+                        .value_name("SEED")
+                        .help("Seed of the random numbers, to repeat a result (default: random, printed)")
+                        .value_parser(clap::value_parser!(u64)),
+                )
+                .arg(
+                    clap::Arg::new("speed-sigma")
+                        .long("speed-sigma")
+                        .value_name("KNOTS")
+                        .help("Standard deviation of the wind speed from the forecast")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("2"),
+                )
+                .arg(
+                    clap::Arg::new("direction-sigma")
+                        .long("direction-sigma")
+                        .value_name("DEGREES")
+                        .help("Standard deviation of the wind direction from the forecast")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("15"),
+                )
+                .arg(
+                    clap::Arg::new("correlation")
+                        .long("correlation")
+                        .value_name("FACTOR")
+                        .help("Correlation of the deviations of consecutive hours, 0 to 1")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("0.8"),
+                ),
+        )
+        .subcommand(
+            Command::new("simulate-fleet")
+                .about("Simulate boats with their own polars and routes and rank them every hour")
+                .arg(
+                    clap::Arg::new("fleet")
+                        .help("CSV file with the columns Name, Polars (file in the format of data/polars.csv, empty for those of the data), Route (buoy names separated by spaces, empty for --route), Start (empty for --time, or the start of the class), Rating (empty for data/ratings.csv), and Class (empty for --class)")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("route")
+                        .long("route")
+                        .value_name("BUOYS")
+                        .help("Route of the boats without their own as comma separated buoy names, e.g. SPORT-A,KR-A,LC1"),
+                )
+                .arg(
+                    clap::Arg::new("time")
+                        .long("time")
+                        .value_name("TIME")
+                        .help("Start of the boats without their own in hours after race start, or as clock time HH:MM")
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("step-minutes")
+                        .long("step-minutes")
+                        .value_name("MINUTES")
+                        .help("Length of a time step in minutes")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("1"),
+                ),
+        )
+        .subcommand(
+            Command::new("export-route")
+                .about("Export a given sequence of buoys as route file for chart plotters")
+                .arg(
+                    clap::Arg::new("buoys")
+                        .help("Names of the buoys in the order they are sailed, starting with a start buoy")
+                        .num_args(2..)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("start-time")
+                        .long("start-time")
+                        .value_name("TIME")
+                        .help("Start of the route in hours after race start, or as clock time HH:MM")
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("format")
+                        .long("format")
+                        .value_name("FORMAT")
+                        .help("Format of the route file: gpx, kml or ics, which need --race-start for their times, geojson, or the CSV of expedition or adrena (default: gpx)")
+                        .value_parser(["gpx", "kml", "ics", "geojson", "expedition", "adrena"])
+                        .default_value("gpx"),
+                )
+                .arg(
+                    clap::Arg::new("watch-hours")
+                        .long("watch-hours")
+                        .value_name("HOURS")
+                        .help("Add a watch rotation in blocks of this length to the ics calendar")
+                        .value_parser(clap::value_parser!(f64)),
+                )
+                .arg(
+                    clap::Arg::new("watches")
+                        .long("watches")
+                        .value_name("NAMES")
+                        .help("Comma separated names of the watches in the order they take over")
+                        .default_value("A,B"),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Output file path (default: stdout)"),
+                ),
+        )
+        .subcommand(
+            Command::new("watch-plan")
+                .about("Plan the watches of the crew for a route, avoiding watch changes during roundings and beats")
+                .arg(
+                    clap::Arg::new("buoys")
+                        .help("Names of the buoys in the order they are sailed, starting with a start buoy")
+                        .num_args(2..)
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("start-time")
+                        .long("start-time")
+                        .value_name("TIME")
+                        .help("Start of the route in hours after race start, or as clock time HH:MM")
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("watch-hours")
+                        .long("watch-hours")
+                        .value_name("HOURS")
+                        .help("Regular length of a watch")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("3"),
+                )
+                .arg(
+                    clap::Arg::new("watches")
+                        .long("watches")
+                        .value_name("NAMES")
+                        .help("Comma separated names of the watches in the order they take over")
+                        .default_value("A,B"),
+                )
+                .arg(
+                    clap::Arg::new("rounding-margin")
+                        .long("rounding-margin")
+                        .value_name("MINUTES")
+                        .help("No watch change this many minutes before and after a rounding")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("15"),
+                )
+                .arg(
+                    clap::Arg::new("max-shift")
+                        .long("max-shift")
+                        .value_name("MINUTES")
+                        .help("Most minutes a watch change may move away from its regular time")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("30"),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Also write the legs and watches as ics calendar, which needs --race-start"),
+                ),
+        )
+        .subcommand(
+            Command::new("briefing")
+                .about("Write a printable briefing pack with the course, legs, wind, and polars of a route")
+                .arg(
+                    clap::Arg::new("route")
+                        .long("route")
+                        .value_name("BUOYS")
+                        .help("Planned route as comma separated buoy names, e.g. SPORT-A,KR-A,LC1")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("time")
+                        .long("time")
+                        .value_name("TIME")
+                        .help("Planned start of the route in hours after race start, or as clock time HH:MM")
+                        .default_value("0"),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Output HTML file path, or a .pdf file rendered with a headless Chromium")
+                        .default_value("briefing.html"),
+                )
+                .arg(
+                    clap::Arg::new("sunrise")
+                        .long("sunrise")
+                        .value_name("HH:MM")
+                        .help("Local time of sunrise for the night shading of the wind chart")
+                        .default_value("05:30"),
+                )
+                .arg(
+                    clap::Arg::new("sunset")
+                        .long("sunset")
+                        .value_name("HH:MM")
+                        .help("Local time of sunset for the night shading of the wind chart")
+                        .default_value("21:45"),
+                ),
+        )
+        .subcommand(
+            Command::new("report")
+                .about("Analyze a recorded track against the planned route")
+                .arg(
+                    clap::Arg::new("track")
+                        .help("Recorded track: GPX, CSV, or a track log of the server")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("route")
+                        .long("route")
+                        .value_name("BUOYS")
+                        .help("Planned route as comma separated buoy names, e.g. SPORT-A,KR-A,LC1 (default: the legs sailed on the track)"),
+                )
+                .arg(
+                    clap::Arg::new("time")
+                        .long("time")
+                        .value_name("TIME")
+                        .help("Planned start of the route in hours after race start, or as clock time HH:MM (default: 0, or the start on the track without --route)"),
+                )
+                .arg(
+                    clap::Arg::new("boat")
+                        .long("boat")
+                        .value_name("NAME")
+                        .help("Boat whose rating in data/ratings.csv corrects the scored miles"),
+                )
+                .arg(
+                    clap::Arg::new("rating")
+                        .long("rating")
+                        .value_name("RATING")
+                        .help("Rating correcting the scored miles, e.g. the SW handicap (overrides --boat)")
+                        .value_parser(clap::value_parser!(f64)),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Output HTML file path")
+                        .default_value("race_report.html"),
+                ),
+        )
+        .subcommand(
+            Command::new("replay")
+                .about("Replay a recorded race hour by hour against the recommendations of the optimizer")
+                .arg(
+                    clap::Arg::new("track")
+                        .long("track")
+                        .value_name("FILE")
+                        .help("Recorded track: GPX, CSV, or a track log of the server")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("steps")
+                        .long("steps")
+                        .value_name("STEPS")
+                        .help("Legs the optimizer looks ahead from the next buoy")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("3"),
+                )
+                .arg(
+                    clap::Arg::new("objective")
+                        .long("objective")
+                        .value_name("OBJECTIVE")
+                        .help("Criterion of the optimizer, as for paths")
+                        .default_value("max_distance"),
+                ),
+        )
+        .subcommand(
+            Command::new("declaration")
+                .about("Fill in the declaration of the legs sailed with their times from a recorded track")
+                .arg(
+                    clap::Arg::new("track")
+                        .long("track")
+                        .value_name("FILE")
+                        .help("Recorded track: GPX, CSV, or a track log of the server")
+                        .required(true),
+                )
+                .arg(
+                    clap::Arg::new("boat")
+                        .long("boat")
+                        .value_name("NAME")
+                        .help("Name of the boat on the declaration")
+                        .default_value("Boat"),
+                )
+                .arg(
+                    clap::Arg::new("output")
+                        .short('o')
+                        .long("output")
+                        .value_name("FILE")
+                        .help("Write the declaration as CSV, as HTML, or as .pdf rendered with a headless Chromium, by the extension"),
+                ),
+        )
+        .subcommand(
+            Command::new("audit")
+                .about("Audit the course for the race committee: reachability, leg usage, and scorable distance by wind direction")
+                .arg(
+                    clap::Arg::new("directions")
+                        .long("directions")
+                        .value_name("N")
+                        .help("Number of wind directions evenly spread around the compass")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("8"),
+                )
+                .arg(
+                    clap::Arg::new("wind-speed")
+                        .long("wind-speed")
+                        .value_name("KNOTS")
+                        .help("Constant wind speed of the scenarios")
+                        .value_parser(clap::value_parser!(f64))
+                        .default_value("12"),
+                )
+                .arg(
+                    clap::Arg::new("lookahead")
+                        .long("lookahead")
+                        .value_name("STEPS")
+                        .help("Legs looked ahead when planning the best race of a scenario")
+                        .value_parser(clap::value_parser!(usize))
+                        .default_value("2"),
+                ),
+        )
+        .subcommand(
+            Command::new("check-manifest")
+                .about("Check whether the run of a manifest can be regenerated identically and print its command line")
+                .arg(
+                    clap::Arg::new("manifest")
+                        .help("Manifest written with --manifest")
+                        .required(true),
+                ),
+        )
+        .subcommand(Command::new("version").about("Display version information"))
+        .subcommand(
+            Command::new("completions")
+                .about("Print a shell completion script, e.g. uurs24 completions bash > /etc/bash_completion.d/uurs24")
+                .arg(
+                    clap::Arg::new("shell")
+                        .help("Shell to complete in")
+                        .value_parser(clap::value_parser!(clap_complete::Shell))
+                        .required(true),
+                ),
+        )
+        .subcommand(Command::new("man").about("Print the man page in roff format, e.g. uurs24 man > uurs24.1"))
+}
+
+/// Log level chosen with the verbosity flags. The server logs its requests
+/// by default, the other subcommands only warnings.
+fn log_level(matches: &clap::ArgMatches) -> tracing::Level {
+    const LEVELS: [tracing::Level; 5] = [
+        tracing::Level::ERROR,
+        tracing::Level::WARN,
+        tracing::Level::INFO,
+        tracing::Level::DEBUG,
+        tracing::Level::TRACE,
+    ];
+    if matches.get_flag("quiet") {
+        return tracing::Level::ERROR;
+    }
+    let default = if matches.subcommand_name() == Some("serve") { 2 } else { 1 };
+    let verbosity = default + matches.get_count("verbose") as usize;
+    LEVELS[verbosity.min(LEVELS.len() - 1)]
+}
+
+/// Format of the results printed by the subcommands
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum OutputFormat {
+    Text,
+    Json,
+}
+
+/// Print a result as JSON for other tools to read. A reader closing the
+/// pipe early, like `head`, is not an error.
+fn print_json<T: serde::Serialize>(value: &T) {
+    use std::io::Write;
+
+    let mut stdout = std::io::stdout().lock();
+    let written = serde_json::to_writer_pretty(&mut stdout, value)
+        .map_err(std::io::Error::from)
+        .and_then(|()| writeln!(stdout));
+    if let Err(e) = written
+        && e.kind() != std::io::ErrorKind::BrokenPipe
+    {
+        eprintln!("Error writing the output: {e}");
+        std::process::exit(1);
+    }
+}
+
+/// Show the regatta data in the chosen output format
+fn show_command(data: &data::RegattaData, warnings: &[DataWarning], format: OutputFormat) {
+    match format {
+        OutputFormat::Text => {
+            show_regatta_data(data);
+            show_data_warnings(warnings);
+        }
+        OutputFormat::Json => print_json(&DataResponse::new(data)),
+    }
+}
+
+fn show_regatta_data(data: &data::RegattaData) {
+    println!("Successfully loaded regatta data:");
+    println!("  - {} buoys (boeien)", data.boeien.len());
+    println!("  - {} start lines", data.starts.len());
+    println!("  - {} legs (rakken)", data.rakken.len());
+
+    // Find and display the FINISH buoy
+    if let Some(finish_boei) = data.get_boei("FINISH") {
+        println!("\nFINISH buoy details:");
+        println!("  Name: {}", finish_boei.name);
+        if let Some(buoy_type) = &finish_boei.buoy_type {
+            println!("  Type: {buoy_type}");
+        }
+        if let Some(lat) = &finish_boei.lat {
+            println!("  Latitude: {lat:.6}° (decimal)");
+        }
+        if let Some(long) = &finish_boei.long {
+            println!("  Longitude: {long:.6}° (decimal)");
+        }
+        if let Some(lat_str) = &finish_boei.lat_min {
+            println!("  Latitude (original): {lat_str}");
+        }
+        if let Some(long_str) = &finish_boei.long_min {
+            println!("  Longitude (original): {long_str}");
+        }
+
+        // Demonstrate the convenience methods
+        if finish_boei.has_coordinates()
+            && let Some((lat, long)) = finish_boei.coordinates()
+        {
+            println!("  Coordinates tuple: ({lat:.6}, {long:.6})");
+        }
+    }
+
+    // Show all boeien with coordinates
+    println!("\nAll boeien with geo coordinates:");
+    let mut buoys_with_coords = 0;
+    let mut buoys_without_coords = 0;
+    
+    for boei in &data.boeien {
         if boei.has_coordinates() {
             if let Some((lat, long)) = boei.coordinates() {
                 let buoy_type_str = boei.buoy_type.as_ref().map(|t| format!(" ({t})")).unwrap_or_default();
@@ -302,200 +1734,1208 @@ fn show_regatta_data(data: &data::RegattaData) {
         }
     }
     
-    if buoys_without_coords > 0 {
-        println!("  Note: {buoys_without_coords} boeien have no coordinate data");
+    if buoys_without_coords > 0 {
+        println!("  Note: {buoys_without_coords} boeien have no coordinate data");
+    }
+    println!("  Total: {buoys_with_coords} boeien with coordinates, {buoys_without_coords} without");
+
+    // Show start lines
+    println!("\nStart lines:");
+    for start in data.get_starts().iter() {
+        println!("  {} -> {} ({} nm)", start.from, start.to, start.distance);
+    }
+
+    // Show legs
+    println!("\nLegs (rakken):");
+    for rak in data.get_rakken().iter() {
+        println!("  {} -> {} ({} nm)", rak.from, rak.to, rak.distance);
+    }
+
+    // Show complete polar data
+    let polar_data = data.get_polar_data();
+    println!("\nComplete Polar Performance Data:");
+    println!("Wind speeds: {:?} knots", polar_data.wind_speeds);
+    println!(
+        "True Wind Angles (TWA): {:?} degrees",
+        polar_data.wind_angles
+    );
+    println!();
+
+    // Print header row with wind speeds
+    print!("TWA/TWS\t");
+    for &wind_speed in &polar_data.wind_speeds {
+        print!("{wind_speed:>6.0}kt\t");
+    }
+    println!();
+
+    // Print separator line
+    print!("--------");
+    for _ in &polar_data.wind_speeds {
+        print!("!--------------");
+    }
+    println!();
+
+    // Print each row with wind angle and boat speeds
+    for (angle_idx, &wind_angle) in polar_data.wind_angles.iter().enumerate() {
+        print!("{wind_angle:>6.0}°\t");
+        for &boat_speed in &polar_data.boat_speeds[angle_idx] {
+            print!("{boat_speed:>6.2}kt\t");
+        }
+        println!();
+    }
+
+    println!();
+    println!("Note: TWA = True Wind Angle (0° = head to wind, 90° = beam reach, 180° = downwind)");
+    println!("      Boat speeds are in knots (kt)");
+
+    // Show wind data
+    let wind_data = data.get_wind_data();
+    println!("\nWind Conditions During Race:");
+    println!("Time (hrs) | Wind Speed (kts) | Wind Direction (°)");
+    println!("-----------|------------------|-------------------");
+    for condition in wind_data.get_all_conditions() {
+        println!(
+            "     {:2}    |        {:5.1}      |        {:5.1}",
+            condition.time, condition.wind_speed, condition.wind_angle
+        );
+    }
+    println!();
+    println!("Note: Wind direction is the angle FROM which the wind is coming");
+    println!("      (180° = southerly wind, 0° = northerly wind)");
+
+    // Demonstrate wind interpolation
+    println!("\nWind Interpolation Examples:");
+    println!("Time (hrs) | Wind Speed (kts) | Wind Direction (°) | Notes");
+    println!("-----------|------------------|-------------------|-------");
+
+    // Show some interpolated values
+    let test_times = vec![0.0, 0.5, 1.0, 1.5, 2.0, 23.5, 24.0];
+    for time in test_times {
+        if let Some(wind) = wind_data.get_wind_at_time(time) {
+            let notes = if time == time.floor() {
+                "Exact hour"
+            } else {
+                "Interpolated"
+            };
+            println!(
+                "     {:4.1}    |        {:5.1}      |        {:5.1}      | {}",
+                time, wind.wind_speed, wind.wind_angle, notes
+            );
+        }
+    }
+
+    // Show buoys by type
+    let start_boeien = data.get_boeien_by_type("Startboei");
+    println!("\nStart buoys ({} found):", start_boeien.len());
+    for boei in start_boeien.iter() {
+        println!(
+            "  {}: {}",
+            boei.name,
+            boei.description
+                .as_ref()
+                .unwrap_or(&"No description".to_string())
+        );
+    }
+
+    // Create the petgraph from the loaded data
+    println!("\nBuilding regatta graph...");
+    let (graph, _node_indices) = build_regatta_graph(data);
+
+    println!("Graph created successfully:");
+    println!("  - {} nodes (boeien)", graph.node_count());
+    println!("  - {} edges (starts + rakken)", graph.edge_count());
+
+    // Show nodes and their types:
+    println!("\nNodes in the graph:");
+    for (i, node_weight) in graph.node_weights().enumerate() {
+        let boei = &data.boeien[i];
+        let no_type_str = "No type".to_string();
+        let node_type = node_weight.as_ref().unwrap_or(&no_type_str);
+        println!("  {}: {} (type: {})", i, boei.name, node_type);
+    }
+
+    // Show edges with their properties:
+    println!("\nEdges in the graph:");
+    for edge_idx in graph.edge_indices() {
+        let (source, target) = graph.edge_endpoints(edge_idx).unwrap();
+        let sname = &data.boeien[source.index()].name[..];
+        let tname = &data.boeien[target.index()].name[..];
+        let edge_weight = graph.edge_weight(edge_idx).unwrap();
+        println!(
+            "  {} -> {}: distance={:.2} nm, is_start={}, forwards={}, index={}",
+            sname,
+            tname,
+            edge_weight.distance,
+            edge_weight.is_start,
+            edge_weight.forwards,
+            edge_weight.index,
+        );
+    }
+}
+
+fn show_data_warnings(warnings: &[DataWarning]) {
+    println!("\nData warnings:");
+    if warnings.is_empty() {
+        println!("  None");
+    }
+    for warning in warnings {
+        println!("  {warning}");
+    }
+}
+
+/// Parse a time argument, given in hours since the race start, as clock time
+/// "HH:MM", or as date and time, into hours since the race start, which must
+/// be within the race like for the server
+fn parse_time_arg(data: &data::RegattaData, time: &str) -> f64 {
+    match time.parse::<RaceTime>().and_then(|time| RaceHours::resolve(data, &time)) {
+        Ok(hours) => hours.hours(),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Check the steps of a search against the limits of the rules, like the
+/// server does
+fn steps_arg(data: &data::RegattaData, steps: usize) -> usize {
+    match StepCount::new(data, steps) {
+        Ok(steps) => steps.get(),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Check the longest wait at a buoy against the deadline of the rules, like
+/// the server does
+fn wait_arg(data: &data::RegattaData, hours: f64) -> f64 {
+    match WaitHours::new(data, hours) {
+        Ok(hours) => hours.hours(),
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The legs `paths` and `target` look ahead and how they search them
+#[derive(Clone, Copy)]
+struct Lookahead {
+    steps: usize,       // legs of the paths, at most for a target or a hierarchical plan
+    hierarchical: bool, // plan a skeleton of key marks first instead of searching all paths
+}
+
+/// The lookahead of `paths` and `target` from their steps and `--strategy`;
+/// a hierarchical plan may have as many legs as a route, a search only as
+/// many as it may look ahead
+fn lookahead_args(data: &data::RegattaData, matches: &clap::ArgMatches) -> Lookahead {
+    let steps = *matches.get_one::<usize>("steps").unwrap();
+    if matches.get_one::<String>("strategy").map(String::as_str) != Some("hierarchical") {
+        return Lookahead { steps: steps_arg(data, steps), hierarchical: false };
+    }
+    if let Err(e) = check_route_length(data, steps) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
+    if steps == 0 {
+        eprintln!("Error: A route needs at least one leg");
+        std::process::exit(1);
+    }
+    Lookahead { steps, hierarchical: true }
+}
+
+/// The objective of a command from its `--objective`
+fn objective_arg(matches: &clap::ArgMatches) -> optimize::PlanObjective {
+    let name = matches.get_one::<String>("objective").unwrap();
+    match optimize::PlanObjective::from_name(name) {
+        Some(objective) => objective,
+        None => {
+            eprintln!(
+                "Error: Unknown objective '{name}', expected earliest_arrival, max_distance, qualify, max_margin, or weighted: with weights of {}, e.g. weighted:scored_distance=1,margin=2",
+                optimize::ObjectiveWeights::MEASURES.join(", ")
+            );
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The buoys and legs a command must avoid, from its `--avoid` and
+/// `--avoid-leg` options
+fn avoid_args(data: &data::RegattaData, matches: &clap::ArgMatches) -> optimize::SearchConstraints {
+    let list = |name: &str| matches.get_one::<String>(name).map(String::as_str);
+    match optimize::SearchConstraints::from_avoid_lists(data, list("avoid"), list("avoid-leg")) {
+        Ok(constraints) => constraints,
+        Err(e) => {
+            eprintln!("Error: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Estimate leg performance between two buoys at a specific time
+fn estimate_leg_performance_command(
+    data: &data::RegattaData,
+    from_name: &str,
+    to_name: &str,
+    time: f64,
+    format: OutputFormat,
+    lang: Lang,
+) -> Result<(), Box<dyn std::error::Error>> {
+    // Find the buoys by name
+    let from_boei = data.get_boei(from_name)
+        .ok_or_else(|| format!("Buoy '{from_name}' not found"))?;
+    let to_boei = data.get_boei(to_name)
+        .ok_or_else(|| format!("Buoy '{to_name}' not found"))?;
+    
+    // Get the indices of the buoys
+    let from_index = data.get_boei_index(from_name)
+        .ok_or_else(|| format!("Buoy '{from_name}' not found in index"))?;
+    let to_index = data.get_boei_index(to_name)
+        .ok_or_else(|| format!("Buoy '{to_name}' not found in index"))?;
+    
+    // Estimate the leg performance, which needs the coordinates of both buoys
+    let performance = estimate_leg_performance(data, from_index, to_index, time)?;
+    if format == OutputFormat::Json {
+        print_json(&EstimateResponse::new(data, from_name, to_name, time, &performance, lang));
+        return Ok(());
     }
-    println!("  Total: {buoys_with_coords} boeien with coordinates, {buoys_without_coords} without");
 
-    // Show start lines
-    println!("\nStart lines:");
-    for start in data.get_starts().iter() {
-        println!("  {} -> {} ({} nm)", start.from, start.to, start.distance);
+    // Print the results with the values aligned after the longest label
+    let m = |key| message(lang, key);
+    let width = ["estimated_speed", "course_bearing", "wind_direction", "relative_bearing", "wind_speed", "distance", "travel_time", "arrival"]
+        .iter()
+        .map(|key| m(key).chars().count())
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let buoy_type = |boei: &data::Boei| boei.buoy_type.clone().unwrap_or_else(|| m("unknown").to_string());
+    println!("{}", m("cli_estimate"));
+    println!("  {} {} ({})", m("from"), from_name, buoy_type(from_boei));
+    println!("  {} {} ({})", m("to"), to_name, buoy_type(to_boei));
+    println!("  {} {} {}", m("time"), data.race_clock.describe(time), m("after_race_start"));
+    println!();
+    println!("{}", m("cli_results"));
+    println!("  {:<width$}{:.2} {}", m("estimated_speed"), performance.estimated_speed, m("knots"));
+    println!("  {:<width$}{:.1}°", m("course_bearing"), performance.course_bearing);
+    println!("  {:<width$}{:.1}°", m("wind_direction"), performance.wind_direction);
+    println!("  {:<width$}{:.1}°", m("relative_bearing"), performance.relative_bearing);
+    println!("  {:<width$}{:.1} {}", m("wind_speed"), performance.wind_speed, m("knots"));
+    match performance.official_distance {
+        Some(distance) => println!("  {:<width$}{:.2} nm ({} {:.2} nm)", m("distance"), distance, m("great_circle"), performance.great_circle_distance),
+        None => println!("  {:<width$}{:.2} nm {}, {}", m("distance"), performance.great_circle_distance, m("great_circle"), m("no_start_or_leg")),
+    }
+    println!("  {:<width$}{:.2}h", m("travel_time"), performance.travel_time);
+    println!("  {:<width$}{} {}", m("arrival"), data.race_clock.describe(performance.arrival_time), m("after_race_start"));
+
+    // Add some interpretation
+    println!();
+    println!("{}", m("cli_interpretation"));
+    println!("  {}", m(performance.point_of_sail().message_key()));
+
+    Ok(())
+}
+
+/// Check a given sequence of buoys against the rules and print its legs
+/// Print the point of no return of every buoy, searched backwards in time
+/// from the finish, and save them on the course map if asked to
+fn deadlines_command(
+    data: &data::RegattaData,
+    time: f64,
+    map: Option<(&str, Theme)>, // file to save the map to in a theme
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let finish = data
+        .get_boei_index(approach::FINISH_BUOY)
+        .ok_or_else(|| format!("The data has no buoy {}", approach::FINISH_BUOY))?;
+    let deadline = data.rules.scoring.deadline;
+    let departures = approach::latest_departures(data, finish, deadline, time);
+    if let Some((path, theme)) = map {
+        std::fs::write(path, create_deadline_map(data, &departures, PlotConfig { theme, ..PlotConfig::default() })?)?;
+    }
+
+    if format == OutputFormat::Json {
+        print_json(&FinishApproachResponse::new(data, time, finish, &departures, None));
+        return Ok(());
+    }
+
+    println!("Latest departures which still reach {} by {}:", approach::FINISH_BUOY, data.race_clock.describe(deadline));
+    println!();
+    for departure in &departures {
+        println!("  {:<12} leave by {:<16} to {}",
+            data.boeien[departure.buoy].name,
+            data.race_clock.describe(departure.latest),
+            data.boeien[departure.next].name
+        );
+    }
+    let unreachable: Vec<&str> = (0..data.boeien.len())
+        .filter(|&buoy| buoy != finish && departures.iter().all(|departure| departure.buoy != buoy))
+        .map(|buoy| data.boeien[buoy].name.as_str())
+        .collect();
+    if !unreachable.is_empty() {
+        println!();
+        println!("Can't reach the finish in time after {}: {}", data.race_clock.describe(time), unreachable.join(", "));
+    }
+    if let Some((path, _)) = map {
+        println!();
+        println!("Map saved to: {path}");
+    }
+    Ok(())
+}
+
+fn route_command(
+    data: &data::RegattaData,
+    buoys: &[&String],
+    start_time: f64,
+    constraints: &optimize::SearchConstraints,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let route = buoys
+        .iter()
+        .map(|name| data.get_boei_index(name).ok_or_else(|| format!("Buoy '{name}' not found")))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    optimize::validate_route(data, &route, constraints)?;
+    let path = optimize::evaluate_route(data, &route, start_time)?;
+    let deadline = data.rules.scoring.deadline;
+    let in_time = path.end_time <= deadline;
+    let score = scoring::score_path(data, &path, &data.rules.scoring);
+
+    if format == OutputFormat::Json {
+        print_json(&json!({
+            "path": PathResponse::new(data, &path),
+            "deadline": deadline,
+            "in_time": in_time,
+            "score": score,
+        }));
+        return Ok(());
+    }
+
+    println!("Route from {} with {} legs:", buoys[0], path.steps.len());
+    println!("Starting time: {} after race start", data.race_clock.describe(start_time));
+    println!();
+
+    let mut distance = 0.0;
+    for (i, step) in path.steps.iter().enumerate() {
+        distance += step.distance;
+        println!("  Leg {}: {} -> {} ({:.2} nm, {:.2} kts, {} -> {}, total {:.2} nm)",
+            i + 1,
+            data.boeien[step.from].name,
+            data.boeien[step.to].name,
+            step.distance,
+            step.speed,
+            data.race_clock.describe(step.start_time),
+            data.race_clock.describe(step.end_time),
+            distance
+        );
+    }
+    println!();
+    println!("Total: {:.2} nm, End time: {}", path.total_distance, data.race_clock.describe(path.end_time));
+    if in_time {
+        println!("Finishes within the {:.0} hours of the race, {:.2}h to spare",
+            deadline, deadline - path.end_time);
+    } else {
+        println!("Finishes {:.2}h after the end of the race after {:.0} hours",
+            path.end_time - deadline, deadline);
+    }
+    for (i, leg) in score.legs.iter().enumerate() {
+        if leg.partial {
+            println!("Leg {} {} -> {} counts {:.2} of {:.2} nm: {}", i + 1, leg.from, leg.to, leg.counted, leg.distance,
+                leg.remark.as_deref().unwrap_or(""));
+        } else if let Some(remark) = &leg.remark {
+            println!("Leg {} {} -> {} doesn't count: {remark}", i + 1, leg.from, leg.to);
+        }
+    }
+    if score.late_penalty > 0.0 {
+        println!("Penalty for finishing late: {:.2} nm", score.late_penalty);
+    }
+    println!("Scored: {:.2} nm", score.scored_distance);
+    if let (Some(distance), Some(margin)) = (data.rules.qualification_distance, path.qualification_margin) {
+        if margin >= 0.0 {
+            println!("Qualifies with {margin:.2} nm above the minimum of {distance:.2} nm");
+        } else {
+            println!("Doesn't qualify, {:.2} nm short of the minimum of {distance:.2} nm", -margin);
+        }
+    }
+
+    Ok(())
+}
+
+/// Changes to the plan played through with `simulate`
+struct WhatIf<'a> {
+    delay: f64,                // in hours
+    lost_time: Vec<&'a String>, // as LEG:MINUTES
+}
+
+/// Simulate a given sequence of buoys in fixed time steps and compare the
+/// result with the per-leg estimate of `route`, or with the plan if a what-if
+/// delays the start or loses time on legs
+fn simulate_command(
+    data: &data::RegattaData,
+    buoys: &[&String],
+    start_time: f64,
+    mut options: simulate::SimulationOptions,
+    what_if: &WhatIf,
+    output_path: Option<&str>,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let route = buoys
+        .iter()
+        .map(|name| data.get_boei_index(name).ok_or_else(|| format!("Buoy '{name}' not found")))
+        .collect::<Result<Vec<_>, _>>()?;
+    optimize::validate_route(data, &route, &optimize::SearchConstraints::default())?;
+    let plan = simulate::simulate_route(data, &route, start_time, &options)?;
+
+    for lost_time in &what_if.lost_time {
+        let (leg, minutes) = lost_time
+            .split_once(':')
+            .ok_or_else(|| format!("Lost time '{lost_time}' is not given as LEG:MINUTES"))?;
+        let minutes: f64 = minutes
+            .trim()
+            .parse()
+            .map_err(|_| format!("Invalid minutes in lost time '{lost_time}'"))?;
+        // A leg number counts from 1, a buoy name means the first leg ending there
+        let index = match leg.trim().parse::<usize>() {
+            Ok(number) if (1..route.len()).contains(&number) => number - 1,
+            Ok(number) => return Err(format!("The route has no leg {number}").into()),
+            Err(_) => route[1..]
+                .iter()
+                .position(|&buoy| data.boeien[buoy].name == leg.trim())
+                .ok_or_else(|| format!("No leg of the route ends at '{}'", leg.trim()))?,
+        };
+        options.lost_time.push((index, minutes / 60.0));
+    }
+    let changed = what_if.delay != 0.0 || !options.lost_time.is_empty();
+    let simulation = if changed {
+        simulate::simulate_route(data, &route, start_time + what_if.delay, &options)?
+    } else {
+        plan.clone()
+    };
+    let path = optimize::evaluate_route(data, &route, start_time)?;
+
+    if let Some(output_path) = output_path {
+        let mut writer = csv::Writer::from_path(output_path)?;
+        for sample in &simulation.samples {
+            writer.serialize(sample)?;
+        }
+        writer.flush()?;
+    }
+
+    if format == OutputFormat::Json {
+        print_json(&json!({
+            "simulation": simulation,
+            "plan": changed.then_some(&plan),
+            "estimated_end_time": path.end_time,
+            "deadline": data.rules.scoring.deadline,
+            "margin": data.rules.scoring.deadline - simulation.end_time,
+            "output": output_path,
+        }));
+        return Ok(());
+    }
+
+    println!("Simulated route from {} with {} legs in steps of {:.1} min:",
+        buoys[0], simulation.legs.len(), options.time_step * 60.0);
+    println!("Starting time: {} after race start", data.race_clock.describe(simulation.legs[0].start_time));
+    println!();
+
+    for (i, (leg, planned)) in simulation.legs.iter().zip(&plan.legs).enumerate() {
+        let shift = if changed {
+            format!(", {:+.0} min", (leg.end_time - planned.end_time) * 60.0)
+        } else {
+            String::new()
+        };
+        println!("  Leg {}: {} -> {} ({:.2} nm, {:.2} kts over ground, {} -> {}{shift})",
+            i + 1,
+            data.boeien[leg.from].name,
+            data.boeien[leg.to].name,
+            leg.distance,
+            leg.distance / (leg.end_time - leg.start_time - leg.lost_time),
+            data.race_clock.describe(leg.start_time),
+            data.race_clock.describe(leg.end_time)
+        );
+    }
+    println!();
+    println!("Total: {:.2} nm, End time: {}", simulation.total_distance, data.race_clock.describe(simulation.end_time));
+    if changed {
+        println!("Plan: End time {}, finish margin {:.2}h, now {:.2}h",
+            data.race_clock.describe(plan.end_time),
+            data.rules.scoring.deadline - plan.end_time,
+            data.rules.scoring.deadline - simulation.end_time);
+    } else {
+        println!("Per-leg estimate: End time {} ({:+.2}h)",
+            data.race_clock.describe(path.end_time), simulation.end_time - path.end_time);
+    }
+    if let Some(output_path) = output_path {
+        println!("{} samples written to: {output_path}", simulation.samples.len());
+    }
+
+    Ok(())
+}
+
+/// Simulate a given sequence of buoys in random realizations of the wind and
+/// print the bands of the distance sailed every hour and of the finish
+fn simulate_ensemble_command(
+    data: &data::RegattaData,
+    buoys: &[&String],
+    start_time: f64,
+    options: &simulate::SimulationOptions,
+    ensemble_options: &ensemble::EnsembleOptions,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let route = buoys
+        .iter()
+        .map(|name| data.get_boei_index(name).ok_or_else(|| format!("Buoy '{name}' not found")))
+        .collect::<Result<Vec<_>, _>>()?;
+    optimize::validate_route(data, &route, &optimize::SearchConstraints::default())?;
+    let ensemble = ensemble::simulate_ensemble(data, &route, start_time, options, ensemble_options)?;
+    let perturbation = &ensemble_options.perturbation;
+
+    if format == OutputFormat::Json {
+        print_json(&json!({
+            "ensemble": ensemble,
+            "options": ensemble_options,
+        }));
+        return Ok(());
+    }
+
+    println!("Simulated route from {} in {} wind realizations (seed {}):", buoys[0], ensemble.runs, ensemble.seed);
+    println!("Wind: ±{:.1} kts, ±{:.0}°, correlation {:.2} from hour to hour",
+        perturbation.speed_sigma, perturbation.direction_sigma, perturbation.correlation);
+    println!();
+    println!("  {:<16} {:>9} {:>9} {:>9}", "Distance (nm)", "P10", "P50", "P90");
+    for band in &ensemble.hours {
+        println!("  {:<16} {:9.2} {:9.2} {:9.2}",
+            data.race_clock.describe(band.time), band.distance.p10, band.distance.p50, band.distance.p90);
+    }
+    println!();
+    println!("Finish: P10 {}, P50 {}, P90 {}",
+        data.race_clock.describe(ensemble.end_time.p10),
+        data.race_clock.describe(ensemble.end_time.p50),
+        data.race_clock.describe(ensemble.end_time.p90));
+
+    Ok(())
+}
+
+/// Simulate the boats of a fleet file and print their standings every hour
+/// and the order in which they finish
+fn simulate_fleet_command(
+    data: &data::RegattaData,
+    fleet_path: &str,
+    route: Option<&str>,
+    start_time: f64,
+    options: &simulate::SimulationOptions,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let parse_route = |buoys: Vec<&str>| {
+        buoys
+            .into_iter()
+            .map(|name| data.get_boei_index(name).ok_or_else(|| format!("Buoy '{name}' not found")))
+            .collect::<Result<Vec<_>, _>>()
+    };
+    let default_route = route.map(|route| parse_route(route.split(',').map(str::trim).collect())).transpose()?;
+
+    let mut reader = csv::Reader::from_path(fleet_path)?;
+    let headers = reader.headers()?.clone();
+    let column = |name: &str| headers.iter().position(|header| header.eq_ignore_ascii_case(name));
+    let name_column = column("Name").ok_or("The fleet file has no column Name")?;
+    let (polars_column, route_column, start_column) = (column("Polars"), column("Route"), column("Start"));
+    let (rating_column, class_column) = (column("Rating"), column("Class"));
+
+    let mut boats = Vec::new();
+    for record in reader.records() {
+        let record = record?;
+        let field = |column: Option<usize>| column.and_then(|column| record.get(column)).map(str::trim).filter(|value| !value.is_empty());
+        let name = field(Some(name_column)).ok_or("A boat of the fleet file has no name")?.to_string();
+        let polar = match field(polars_column) {
+            Some(path) => Some(data::parse_polar_data(&std::fs::read_to_string(path)?)
+                .map_err(|e| format!("Boat {name}: {e}"))?),
+            None => None,
+        };
+        let route = match field(route_column) {
+            Some(route) => parse_route(route.split_whitespace().collect())?,
+            None => default_route.clone().ok_or_else(|| format!("Boat {name} has no route and there is no --route"))?,
+        };
+        optimize::validate_route(data, &route, &optimize::SearchConstraints::default())
+            .map_err(|e| format!("Boat {name}: {e}"))?;
+        // A boat of a class starts with its class and counts its times from there
+        let class = field(class_column).map(str::to_string);
+        let mut clock = data.race_clock.clone();
+        if let Some(class) = &class {
+            clock.start_offset = data.start_offset(class).ok_or_else(|| format!("Boat {name}: unknown class '{class}'"))?;
+        }
+        let start_time = match (field(start_column), &class) {
+            (Some(time), _) => clock.resolve(&time.parse::<RaceTime>()?)?,
+            (None, Some(_)) => 0.0,
+            (None, None) => start_time,
+        };
+        // A rating in the fleet file overrides the one in the ratings of the data
+        let rating = match field(rating_column) {
+            Some(rating) => Some(rating.replace(',', ".").parse::<f64>()
+                .map_err(|e| format!("Boat {name}: invalid rating '{rating}': {e}"))?),
+            None => data.rating(&name),
+        };
+        boats.push(simulate::FleetBoat { name, polar, route, start_time, rating, class });
+    }
+    let rated = boats.iter().any(|boat| boat.rating.is_some());
+
+    let fleet = simulate::simulate_fleet(data, &boats, options)?;
+
+    if format == OutputFormat::Json {
+        print_json(&json!({
+            "hours": fleet.hours,
+            "finishes": fleet.finishes.iter().map(|(name, end_time)| json!({
+                "name": name,
+                "end_time": end_time,
+                "end_clock": data.race_clock.describe(*end_time),
+            })).collect::<Vec<_>>(),
+        }));
+        return Ok(());
+    }
+
+    println!("Simulated fleet of {} boats:", boats.len());
+    for standings in &fleet.hours {
+        println!();
+        println!("After {}:", data.race_clock.describe(standings.time));
+        for standing in &standings.standings {
+            println!("  {:2}. {:<20} {:7.2} nm  {:>8}{}",
+                standing.rank,
+                standing.name,
+                standing.distance,
+                if standing.rank == 1 { String::new() } else { format!("-{:.2} nm", standing.behind) },
+                if standing.finished { "  finished" } else { "" }
+            );
+        }
+        if rated {
+            let mut corrected: Vec<_> = standings.standings.iter().collect();
+            corrected.sort_by_key(|standing| standing.corrected_rank);
+            println!("  Corrected by rating:");
+            for standing in corrected {
+                println!("  {:2}. {:<20} {:7.2} nm",
+                    standing.corrected_rank,
+                    standing.name,
+                    standing.corrected_distance
+                );
+            }
+        }
+    }
+    println!();
+    println!("Finishes:");
+    for (name, end_time) in &fleet.finishes {
+        println!("  {:<20} {}", name, data.race_clock.describe(*end_time));
+    }
+
+    Ok(())
+}
+
+/// Write a given sequence of buoys as route file to a file or stdout
+fn export_route_command(
+    data: &data::RegattaData,
+    buoys: &[&String],
+    start_time: f64,
+    exporter: &dyn export::RouteExporter,
+    output_path: Option<&str>,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let route = buoys
+        .iter()
+        .map(|name| data.get_boei_index(name).ok_or_else(|| format!("Buoy '{name}' not found")))
+        .collect::<Result<Vec<_>, _>>()?;
+    optimize::validate_route(data, &route, &optimize::SearchConstraints::default())?;
+    let path = optimize::evaluate_route(data, &route, start_time)?;
+
+    let name = format!("{} - {}", buoys[0], buoys[buoys.len() - 1]);
+    let content = exporter.export(data, &path, &name)?;
+
+    let Some(output_path) = output_path else {
+        print!("{content}");
+        return Ok(());
+    };
+    std::fs::write(output_path, content)?;
+    match format {
+        OutputFormat::Text => println!("Route exported to: {output_path}"),
+        OutputFormat::Json => print_json(&json!({ "output": output_path })),
+    }
+    Ok(())
+}
+
+/// Plan the watches of a given sequence of buoys and print them as table,
+/// optionally writing them with the legs as calendar
+fn watch_plan_command(
+    data: &data::RegattaData,
+    buoys: &[&String],
+    start_time: f64,
+    rotation: &watches::WatchRotation,
+    options: &watches::WatchPlanOptions,
+    output_path: Option<&str>,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let route = buoys
+        .iter()
+        .map(|name| data.get_boei_index(name).ok_or_else(|| format!("Buoy '{name}' not found")))
+        .collect::<Result<Vec<_>, _>>()?;
+    optimize::validate_route(data, &route, &optimize::SearchConstraints::default())?;
+    let path = optimize::evaluate_route(data, &route, start_time)?;
+    let plan = watches::plan_watches(data, &path, rotation, options)?;
+
+    if let Some(output_path) = output_path {
+        let name = format!("{} - {}", buoys[0], buoys[buoys.len() - 1]);
+        std::fs::write(output_path, calendar::path_to_ics(data, &path, &name, &plan.blocks)?)?;
     }
 
-    // Show legs
-    println!("\nLegs (rakken):");
-    for rak in data.get_rakken().iter() {
-        println!("  {} -> {} ({} nm)", rak.from, rak.to, rak.distance);
+    if format == OutputFormat::Json {
+        print_json(&json!({
+            "blocks": plan.blocks,
+            "changes": plan.changes,
+            "output": output_path,
+        }));
+        return Ok(());
     }
 
-    // Show complete polar data
-    let polar_data = data.get_polar_data();
-    println!("\nComplete Polar Performance Data:");
-    println!("Wind speeds: {:?} knots", polar_data.wind_speeds);
-    println!(
-        "True Wind Angles (TWA): {:?} degrees",
-        polar_data.wind_angles
-    );
+    println!("Watch plan for the route from {} with {} legs, watches of {:.1}h:",
+        buoys[0], path.steps.len(), rotation.length);
     println!();
-
-    // Print header row with wind speeds
-    print!("TWA/TWS\t");
-    for &wind_speed in &polar_data.wind_speeds {
-        print!("{wind_speed:>6.0}kt\t");
+    println!("  {:<12} {:<18} {:<18} {:>6}", "Watch", "From", "To", "Hours");
+    for block in &plan.blocks {
+        println!("  {:<12} {:<18} {:<18} {:>6.2}",
+            block.watch,
+            data.race_clock.describe(block.start),
+            data.race_clock.describe(block.end),
+            block.end - block.start);
     }
     println!();
+    for change in &plan.changes {
+        let moved = (change.time - change.regular_time) * 60.0;
+        let note = match &change.conflict {
+            Some(conflict) => format!(", unavoidable {conflict}"),
+            None if moved.abs() > 0.5 => format!(", moved {moved:+.0} min"),
+            None => String::new(),
+        };
+        println!("  {} -> {} at {}{note}", change.from, change.to, data.race_clock.describe(change.time));
+    }
+    if let Some(output_path) = output_path {
+        println!();
+        println!("Calendar written to: {output_path}");
+    }
+    Ok(())
+}
 
-    // Print separator line
-    print!("--------");
-    for _ in &polar_data.wind_speeds {
-        print!("!--------------");
+/// Write the briefing pack of a route as HTML, or as PDF printed from the
+/// HTML by a headless Chromium if the output ends in .pdf
+fn briefing_command(
+    data: &data::RegattaData,
+    route: &str,
+    start_time: f64,
+    output_path: &str,
+    daylight: (f64, f64),
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let buoys = route
+        .split(',')
+        .map(|name| {
+            let name = name.trim();
+            data.get_boei_index(name)
+                .ok_or_else(|| format!("Buoy '{name}' not found"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    optimize::validate_route(data, &buoys, &optimize::SearchConstraints::default())?;
+    let plan = optimize::evaluate_route(data, &buoys, start_time)?;
+    let title = format!("Briefing {}", route.replace(',', " - "));
+    let html = briefing::briefing_to_html(data, &plan, &title, Some(daylight))?;
+
+    let output = std::path::Path::new(output_path);
+    if output.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("pdf")) {
+        let html_path = output.with_extension("html");
+        std::fs::write(&html_path, html)?;
+        print_to_pdf(&html_path, output)?;
+    } else {
+        std::fs::write(output, html)?;
     }
-    println!();
 
-    // Print each row with wind angle and boat speeds
-    for (angle_idx, &wind_angle) in polar_data.wind_angles.iter().enumerate() {
-        print!("{wind_angle:>6.0}°\t");
-        for &boat_speed in &polar_data.boat_speeds[angle_idx] {
-            print!("{boat_speed:>6.2}kt\t");
+    match format {
+        OutputFormat::Text => println!("Successfully wrote briefing pack to {output_path}"),
+        OutputFormat::Json => print_json(&json!({ "output": output_path })),
+    }
+    Ok(())
+}
+
+/// Print an HTML file to PDF with the first headless Chromium or Chrome found
+fn print_to_pdf(html_path: &std::path::Path, pdf_path: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+    let url = format!("file://{}", std::fs::canonicalize(html_path)?.display());
+    for browser in ["chromium", "chromium-browser", "google-chrome"] {
+        tracing::info!("Printing {} to PDF with {browser}...", html_path.display());
+        let output = std::process::Command::new(browser)
+            .arg("--headless")
+            .arg("--no-pdf-header-footer")
+            .arg(format!("--print-to-pdf={}", pdf_path.display()))
+            .arg(&url)
+            .output();
+        match output {
+            Ok(output) if output.status.success() => return Ok(()),
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                return Err(format!("{browser} failed to print the PDF: {stderr}").into());
+            }
+            Err(e) => tracing::debug!("Could not execute '{browser}': {e}"),
         }
-        println!();
     }
+    Err(format!(
+        "No headless Chromium found to print the PDF, open {} in a browser and print it instead",
+        html_path.display()
+    )
+    .into())
+}
 
-    println!();
-    println!("Note: TWA = True Wind Angle (0° = head to wind, 90° = beam reach, 180° = downwind)");
-    println!("      Boat speeds are in knots (kt)");
+/// Compare a recorded track with a planned route and write an HTML report
+///
+/// Without a route, the track is compared with the legs it sailed, planned
+/// from the time it crossed the start buoy.
+fn race_report_command(
+    data: &data::RegattaData,
+    track_path: &str,
+    route: Option<&str>,
+    start_time: Option<f64>,
+    rating: Option<f64>,
+    output_path: &str,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let track = track::read_track_file(std::path::Path::new(track_path), data)?;
+    let track_points = track.len();
+    if format == OutputFormat::Text {
+        println!("Read {track_points} track points from {track_path}");
+    }
 
-    // Show wind data
-    let wind_data = data.get_wind_data();
-    println!("\nWind Conditions During Race:");
-    println!("Time (hrs) | Wind Speed (kts) | Wind Direction (°)");
-    println!("-----------|------------------|-------------------");
-    for condition in wind_data.get_all_conditions() {
-        println!(
-            "     {:2}    |        {:5.1}      |        {:5.1}",
-            condition.time, condition.wind_speed, condition.wind_angle
+    let (buoys, start_time) = match route {
+        Some(route) => {
+            let buoys = route
+                .split(',')
+                .map(|name| {
+                    let name = name.trim();
+                    data.get_boei_index(name)
+                        .ok_or_else(|| format!("Buoy '{name}' not found"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            (buoys, start_time.unwrap_or(0.0))
+        }
+        None => {
+            let legs = live::match_track(data, &track);
+            let first = legs.first().ok_or("No leg of the course found on the track, give the route with --route")?;
+            if format == OutputFormat::Text {
+                println!("Matched {} legs on the track:", legs.len());
+                for leg in &legs {
+                    println!("  {} -> {}: {} -> {}",
+                        data.boeien[leg.from].name,
+                        data.boeien[leg.to].name,
+                        data.race_clock.describe(leg.start),
+                        data.race_clock.describe(leg.end)
+                    );
+                }
+            }
+            let buoys = std::iter::once(first.from).chain(legs.iter().map(|leg| leg.to)).collect();
+            (buoys, start_time.unwrap_or(first.start))
+        }
+    };
+    let plan = optimize::evaluate_route(data, &buoys, start_time)?;
+
+    let report = report::analyze_race(data, &plan, track, rating)?;
+    let title = format!("Race Report {}", std::path::Path::new(track_path)
+        .file_name()
+        .map_or(track_path.into(), |name| name.to_string_lossy()));
+    std::fs::write(output_path, report::report_to_html(data, &report, &title)?)?;
+
+    if format == OutputFormat::Json {
+        let legs: Vec<_> = report
+            .legs
+            .iter()
+            .map(|leg| json!({
+                "from": data.boeien[leg.from].name,
+                "to": data.boeien[leg.to].name,
+                "distance": leg.distance,
+                "planned_start": leg.planned_start,
+                "planned_end": leg.planned_end,
+                "actual_start": leg.actual_start,
+                "actual_end": leg.actual_end,
+                "sailed_distance": leg.sailed_distance,
+                "predicted_speed": leg.predicted_speed,
+                "actual_speed": leg.actual_speed,
+                "performance": leg.performance,
+                "time_lost": leg.time_lost,
+            }))
+            .collect();
+        print_json(&json!({
+            "track": track_path,
+            "track_points": track_points,
+            "route": buoys.iter().map(|&buoy| &data.boeien[buoy].name).collect::<Vec<_>>(),
+            "output": output_path,
+            "legs": legs,
+            "total_time_lost": report.total_time_lost(),
+            "scored_distance": report.scored_distance,
+            "rating": report.rating,
+            "corrected_distance": report.corrected_distance(),
+        }));
+        return Ok(());
+    }
+
+    for leg in &report.legs {
+        let performance = leg.performance.map_or("-".to_string(), |p| format!("{p:.0}%"));
+        let lost = leg.time_lost.map_or("-".to_string(), |l| format!("{l:+.2}h"));
+        println!("  {} -> {}: {performance} of polar speed, time lost {lost}",
+            data.boeien[leg.from].name,
+            data.boeien[leg.to].name
         );
     }
-    println!();
-    println!("Note: Wind direction is the angle FROM which the wind is coming");
-    println!("      (180° = southerly wind, 0° = northerly wind)");
+    println!("Total time lost: {:+.2} hours", report.total_time_lost());
+    println!("Scored distance: {:.2} nm", report.scored_distance);
+    if let Some(rating) = report.rating {
+        println!("Corrected by rating {rating}: {:.2} nm", report.corrected_distance());
+    }
+    println!("Successfully wrote race report to {output_path}");
+    Ok(())
+}
 
-    // Demonstrate wind interpolation
-    println!("\nWind Interpolation Examples:");
-    println!("Time (hrs) | Wind Speed (kts) | Wind Direction (°) | Notes");
-    println!("-----------|------------------|-------------------|-------");
+/// Replay a recorded race hour by hour and compare the legs sailed with the
+/// recommendations of the optimizer
+fn replay_command(
+    data: &data::RegattaData,
+    track_path: &str,
+    options: &replay::ReplayOptions,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let track = track::read_track_file(std::path::Path::new(track_path), data)?;
+    let replay = replay::replay_race(data, &track, options)?;
+    let names = |path: &optimize::Path| {
+        path.steps
+            .first()
+            .map(|step| step.from)
+            .into_iter()
+            .chain(path.steps.iter().map(|step| step.to))
+            .map(|buoy| data.boeien[buoy].name.as_str())
+            .collect::<Vec<_>>()
+    };
 
-    // Show some interpolated values
-    let test_times = vec![0.0, 0.5, 1.0, 1.5, 2.0, 23.5, 24.0];
-    for time in test_times {
-        if let Some(wind) = wind_data.get_wind_at_time(time) {
-            let notes = if time == time.floor() {
-                "Exact hour"
-            } else {
-                "Interpolated"
-            };
-            println!(
-                "     {:4.1}    |        {:5.1}      |        {:5.1}      | {}",
-                time, wind.wind_speed, wind.wind_angle, notes
-            );
-        }
+    if format == OutputFormat::Json {
+        let decisions: Vec<_> = replay
+            .decisions
+            .iter()
+            .map(|decision| json!({
+                "time": decision.time,
+                "from": data.boeien[decision.from].name,
+                "to": data.boeien[decision.to].name,
+                "rounding": decision.rounding,
+                "wind_direction": decision.wind_direction,
+                "wind_speed": decision.wind_speed,
+                "recommended": decision.recommended.as_ref().map(|path| PathResponse::new(data, path)),
+                "sailed": PathResponse::new(data, &decision.sailed),
+                "horizon": decision.horizon,
+                "distance_left": decision.distance_left,
+            }))
+            .collect();
+        print_json(&json!({
+            "track": track_path,
+            "track_points": track.len(),
+            "legs": replay.legs.len(),
+            "decisions": decisions,
+            "distance_left": replay.distance_left(),
+        }));
+        return Ok(());
     }
 
-    // Show buoys by type
-    let start_boeien = data.get_boeien_by_type("Startboei");
-    println!("\nStart buoys ({} found):", start_boeien.len());
-    for boei in start_boeien.iter() {
-        println!(
-            "  {}: {}",
-            boei.name,
-            boei.description
-                .as_ref()
-                .unwrap_or(&"No description".to_string())
+    println!("Replay of {track_path} with {} legs matched on the track:", replay.legs.len());
+    println!();
+    for decision in &replay.decisions {
+        println!("{}: sailing {} -> {}, wind {:.0}° at {:.1} kts",
+            data.race_clock.describe(decision.time),
+            data.boeien[decision.from].name,
+            data.boeien[decision.to].name,
+            decision.wind_direction,
+            decision.wind_speed
         );
+        println!("  At {} rounded {}", data.race_clock.describe(decision.rounding), data.boeien[decision.to].name);
+        println!("  Sailed:      {}", names(&decision.sailed).join(" -> "));
+        match &decision.recommended {
+            Some(path) => {
+                println!("  Recommended: {}", names(path).join(" -> "));
+                println!("  Distance left on the table by {}: {:+.2} nm",
+                    data.race_clock.describe(decision.horizon), decision.distance_left);
+            }
+            None => println!("  Recommended: no continuation found"),
+        }
+        println!();
     }
+    println!("Total distance left on the table: {:+.2} nm", replay.distance_left());
+    Ok(())
+}
 
-    // Create the petgraph from the loaded data
-    println!("\nBuilding regatta graph...");
-    let (graph, _node_indices) = build_regatta_graph(data);
+/// Fill in the declaration of a recorded race, print it, and optionally
+/// write it as CSV, HTML, or PDF by the extension of the output file
+fn declaration_command(
+    data: &data::RegattaData,
+    track_path: &str,
+    boat: &str,
+    output_path: Option<&str>,
+    format: OutputFormat,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let track = track::read_track_file(std::path::Path::new(track_path), data)?;
+    let declaration = declaration::declare_race(data, &track)?;
 
-    println!("Graph created successfully:");
-    println!("  - {} nodes (boeien)", graph.node_count());
-    println!("  - {} edges (starts + rakken)", graph.edge_count());
+    if let Some(output_path) = output_path {
+        let output = std::path::Path::new(output_path);
+        let extension = output.extension().and_then(|extension| extension.to_str()).unwrap_or("").to_lowercase();
+        match extension.as_str() {
+            "csv" => std::fs::write(output, declaration::declaration_to_csv(data, &declaration)?)?,
+            "html" | "htm" => std::fs::write(output, declaration::declaration_to_html(data, &declaration, boat)?)?,
+            "pdf" => {
+                let html_path = output.with_extension("html");
+                std::fs::write(&html_path, declaration::declaration_to_html(data, &declaration, boat)?)?;
+                print_to_pdf(&html_path, output)?;
+            }
+            _ => return Err(format!("Unknown format of '{output_path}', use .csv, .html, or .pdf").into()),
+        }
+    }
 
-    // Show nodes and their types:
-    println!("\nNodes in the graph:");
-    for (i, node_weight) in graph.node_weights().enumerate() {
-        let boei = &data.boeien[i];
-        let no_type_str = "No type".to_string();
-        let node_type = node_weight.as_ref().unwrap_or(&no_type_str);
-        println!("  {}: {} (type: {})", i, boei.name, node_type);
+    if format == OutputFormat::Json {
+        print_json(&json!({
+            "boat": boat,
+            "declaration": declaration,
+            "output": output_path,
+        }));
+        return Ok(());
     }
 
-    // Show edges with their properties:
-    println!("\nEdges in the graph:");
-    for edge_idx in graph.edge_indices() {
-        let (source, target) = graph.edge_endpoints(edge_idx).unwrap();
-        let sname = &data.boeien[source.index()].name[..];
-        let tname = &data.boeien[target.index()].name[..];
-        let edge_weight = graph.edge_weight(edge_idx).unwrap();
-        println!(
-            "  {} -> {}: distance={:.2} nm, is_start={}, forwards={}, index={}",
-            sname,
-            tname,
-            edge_weight.distance,
-            edge_weight.is_start,
-            edge_weight.forwards,
-            edge_weight.index,
+    println!("Declaration of {boat} from {track_path}:");
+    println!();
+    for leg in &declaration.legs {
+        let side = leg.check.as_ref().and_then(|check| check.side);
+        println!("  {:>2}. {} -> {} ({:.2} nm, {} -> {}{}){}",
+            leg.number,
+            leg.from,
+            leg.to,
+            leg.distance,
+            data.race_clock.describe(leg.start),
+            data.race_clock.describe(leg.rounding),
+            side.map_or(String::new(), |side| format!(", kept on {side}")),
+            if leg.counted > 0.0 && leg.counted < leg.distance {
+                ", counts in part"
+            } else if leg.remark.is_some() {
+                ", doesn't count"
+            } else {
+                ""
+            }
         );
     }
+    println!();
+    println!("Sailed: {:.2} nm, Declared: {:.2} nm", declaration.score.raw_distance, declaration.score.scored_distance);
+    if !declaration.violations.is_empty() {
+        println!();
+        println!("Rule violations:");
+        for violation in &declaration.violations {
+            println!("  {violation}");
+        }
+    }
+    if let Some(output_path) = output_path {
+        println!();
+        println!("Declaration written to: {output_path}");
+    }
+    Ok(())
 }
 
-/// Estimate leg performance between two buoys at a specific time
-fn estimate_leg_performance_command(
+/// Audit the course and print the report for the race committee
+fn audit_command(
     data: &data::RegattaData,
-    from_name: &str,
-    to_name: &str,
-    time: f64,
+    options: &audit::AuditOptions,
+    format: OutputFormat,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    // Find the buoys by name
-    let from_boei = data.get_boei(from_name)
-        .ok_or_else(|| format!("Buoy '{from_name}' not found"))?;
-    let to_boei = data.get_boei(to_name)
-        .ok_or_else(|| format!("Buoy '{to_name}' not found"))?;
-    
-    // Get the indices of the buoys
-    let from_index = data.get_boei_index(from_name)
-        .ok_or_else(|| format!("Buoy '{from_name}' not found in index"))?;
-    let to_index = data.get_boei_index(to_name)
-        .ok_or_else(|| format!("Buoy '{to_name}' not found in index"))?;
-    
-    // Check if both buoys have coordinates
-    if !from_boei.has_coordinates() || !to_boei.has_coordinates() {
-        return Err("Both buoys must have valid coordinates".into());
+    let report = audit::audit_course(data, options)?;
+
+    if format == OutputFormat::Json {
+        print_json(&json!({
+            "options": options,
+            "report": report,
+        }));
+        return Ok(());
     }
-    
-    // Estimate the leg performance
-    let performance = estimate_leg_performance(data, from_index, to_index, time);
-    
-    // Print the results
-    println!("Leg Performance Estimate:");
-    println!("  From: {} ({})", from_name, from_boei.buoy_type.as_ref().unwrap_or(&"Unknown".to_string()));
-    println!("  To:   {} ({})", to_name, to_boei.buoy_type.as_ref().unwrap_or(&"Unknown".to_string()));
-    println!("  Time: {time:.1} hours after race start");
+
+    println!("Course audit with {} wind directions at {:.1} kts", options.directions, options.wind_speed);
     println!();
-    println!("Results:");
-    println!("  Estimated Speed: {:.2} knots", performance.estimated_speed);
-    println!("  Course Bearing:  {:.1}°", performance.course_bearing);
-    println!("  Wind Direction:  {:.1}°", performance.wind_direction);
-    println!("  Relative Bearing: {:.1}°", performance.relative_bearing);
-    println!("  Wind Speed:      {:.1} knots", performance.wind_speed);
-    
-    // Add some interpretation
+    if report.unconnected_buoys.is_empty() && report.unreachable_buoys.is_empty() {
+        println!("Every buoy can be reached from a start");
+    }
+    if !report.unconnected_buoys.is_empty() {
+        println!("Buoys without any start or leg: {}", report.unconnected_buoys.join(", "));
+    }
+    if !report.unreachable_buoys.is_empty() {
+        println!("Buoys not reachable from any start: {}", report.unreachable_buoys.join(", "));
+    }
+    println!();
+
+    println!("Maximum scorable distance by wind direction:");
+    for scenario in &report.scenarios {
+        match scenario.best() {
+            Some(best) => println!("  {:>5.0}°: {:>6.2} nm from {}, finishing at {}",
+                scenario.wind_direction,
+                best.scored_distance,
+                best.start,
+                data.race_clock.describe(best.end_time)
+            ),
+            None => println!("  {:>5.0}°: no race possible", scenario.wind_direction),
+        }
+    }
+    println!();
+
+    let races = report.scenarios.iter().map(|scenario| scenario.races.len()).sum::<usize>();
+    println!("Leg usage over the best races of all {races} scenarios and starts:");
+    println!("  {} of {} legs are sailed by none of them", report.unused_legs, report.leg_usage.len());
+    println!("  The tenth of the legs used most takes {:.0}% of all sailings", report.concentration * 100.0);
+    for leg in report.leg_usage.iter().take(10).filter(|leg| leg.races > 0) {
+        println!("  {} - {} ({:.2} nm): {} races, {:.0}%", leg.from, leg.to, leg.distance, leg.races, leg.share * 100.0);
+    }
     println!();
-    println!("Interpretation:");
-    if performance.relative_bearing < 45.0 {
-        println!("  Sailing close-hauled (into the wind)");
-    } else if performance.relative_bearing < 90.0 {
-        println!("  Sailing on a close reach");
-    } else if performance.relative_bearing < 135.0 {
-        println!("  Sailing on a beam reach");
-    } else if performance.relative_bearing < 180.0 {
-        println!("  Sailing on a broad reach");
+    if report.bottlenecks.is_empty() {
+        println!("No bottleneck legs sailed by {:.0}% of the races or more", audit::BOTTLENECK_SHARE * 100.0);
     } else {
-        println!("  Sailing downwind");
+        println!("Bottleneck legs where the fleet converges:");
+        for leg in &report.bottlenecks {
+            println!("  {} - {}: {:.0}% of the races", leg.from, leg.to, leg.share * 100.0);
+        }
+    }
+    Ok(())
+}
+
+/// Compare a run manifest with the current version and data files
+fn check_manifest_command(manifest_path: &str, format: OutputFormat) -> Result<(), Box<dyn std::error::Error>> {
+    let manifest = manifest::RunManifest::read(std::path::Path::new(manifest_path))?;
+    let changed = manifest.changed_inputs()?;
+    let same_version = manifest.version == env!("CARGO_PKG_VERSION");
+
+    if format == OutputFormat::Json {
+        print_json(&json!({
+            "manifest": manifest,
+            "same_version": same_version,
+            "changed_inputs": changed,
+            "reproducible": same_version && changed.is_empty(),
+        }));
+        return Ok(());
+    }
+
+    println!("Run of '{}' on {} with version {}", manifest.command, manifest.created.to_rfc3339(), manifest.version);
+    // A seed drawn at random has to be passed explicitly to draw the same numbers again
+    let mut command_line = manifest.arguments.join(" ");
+    if let Some(seed) = manifest.seed {
+        if !manifest.arguments.iter().any(|argument| argument == "--seed" || argument.starts_with("--seed=")) {
+            command_line.push_str(&format!(" --seed {seed}"));
+        }
+        println!("Seed: {seed}");
+    }
+    println!("Command line: {command_line}");
+    if !same_version {
+        println!("The version is now {}, results may differ", env!("CARGO_PKG_VERSION"));
+    }
+    for file in &changed {
+        println!("Changed since the run: {file}");
+    }
+    if same_version && changed.is_empty() {
+        println!("The inputs are unchanged, running the command line again regenerates the results");
     }
-    
     Ok(())
 }
 
@@ -504,8 +2944,12 @@ fn explore_paths_command(
     data: &data::RegattaData,
     start_name: &str,
     start_time: f64,
-    num_steps: usize,
-) -> Result<(), Box<dyn std::error::Error>> {
+    lookahead: Lookahead,
+    options: &SearchOptions, // waits and the buoys and legs to avoid
+    selection: &optimize::PathSelection,
+    format: OutputFormat,
+) -> Result<optimize::SearchStats, Box<dyn std::error::Error>> {
+    let num_steps = lookahead.steps;
     // Find the starting buoy by name
     let start_boei = data.get_boei(start_name)
         .ok_or_else(|| format!("Starting buoy '{start_name}' not found"))?;
@@ -514,47 +2958,89 @@ fn explore_paths_command(
     let start_index = data.get_boei_index(start_name)
         .ok_or_else(|| format!("Starting buoy '{start_name}' not found in index"))?;
     
+    if format == OutputFormat::Json {
+        let (paths, stats) = if lookahead.hierarchical {
+            let plan = plan_hierarchical(data, start_index, None, start_time, num_steps, selection.objective.as_ref(), options)?;
+            (selection.select(data, plan.paths), plan.stats)
+        } else {
+            let results = search_paths(data, start_index, start_time, num_steps, options)?;
+            (results.select(data, selection), results.stats)
+        };
+        print_json(&FindPathsResponse {
+            start: start_name.to_string(),
+            target: None,
+            start_time,
+            steps: num_steps,
+            truncated: false, // the command line searches all paths
+            stats,
+            paths: paths.iter().map(|path| PathResponse::new(data, path)).collect(),
+        });
+        return Ok(stats);
+    }
+
     println!("Exploring paths from: {} ({})", 
         start_name, 
         start_boei.buoy_type.as_ref().unwrap_or(&"Unknown".to_string())
     );
-    println!("Starting time: {start_time:.1} hours after race start");
-    println!("Number of steps: {num_steps}");
+    println!("Starting time: {} after race start", data.race_clock.describe(start_time));
+    if lookahead.hierarchical {
+        println!("Number of steps: at most {num_steps}, planned hierarchically");
+    } else {
+        println!("Number of steps: {num_steps}");
+    }
     println!();
     
-    // Explore all possible paths
-    let paths = explore_paths(data, start_index, start_time, num_steps, None)?;
+    // Explore all possible paths, or those of the best skeletons
+    let (paths, stats) = if lookahead.hierarchical {
+        let plan = plan_hierarchical(data, start_index, None, start_time, num_steps, selection.objective.as_ref(), options)?;
+        (plan.paths, plan.stats)
+    } else {
+        let results = search_paths(data, start_index, start_time, num_steps, options)?;
+        (results.paths(data), results.stats)
+    };
     
     if paths.is_empty() {
         println!("No paths found from this starting point.");
-        return Ok(());
+        return Ok(stats);
     }
     
     println!("Found {} possible path(s):", paths.len());
-    println!();
     
-    // Sort paths by end time for better readability
-    let mut sorted_paths = paths;
-    sorted_paths.sort_by(|a, b| a.end_time.partial_cmp(&b.end_time).unwrap_or(std::cmp::Ordering::Equal));
+    // Sort paths by the objective, by end time unless asked otherwise, and
+    // keep the different routes only if asked to
+    let sorted_paths = selection.select(data, paths);
+    if selection.routes.is_some() {
+        println!("Showing the best {} routes, each differing from the better ones in at least {:.0}% of their legs:",
+            sorted_paths.len(), selection.min_distance * 100.0);
+    }
+    println!();
     
     // Print each path
     for (i, path) in sorted_paths.iter().enumerate() {
-        println!("Path {} (Total: {:.2} nm, End time: {:.2} hours):", 
-            i + 1, path.total_distance, path.end_time);
+        println!("Path {} (Total: {:.2} nm, Scored: {:.2} nm, End time: {}):",
+            i + 1, path.total_distance, path.scored_distance, data.race_clock.describe(path.end_time));
+        if let Some(margin) = path.qualification_margin {
+            println!("  {} with {:+.2} nm against the qualification distance",
+                if margin >= 0.0 { "Qualifies" } else { "Doesn't qualify" }, margin);
+        }
         
         // Print each step in the path
         for (j, step) in path.steps.iter().enumerate() {
             let from_name = &data.boeien[step.from].name;
             let to_name = &data.boeien[step.to].name;
             
-            println!("  Step {}: {} -> {} ({:.2} nm, {:.2} kts, {:.2}h -> {:.2}h)", 
+            if step.wait > 0.0 {
+                println!("  Wait at {} for {:.2}h until {}",
+                    from_name, step.wait, data.race_clock.describe(step.start_time));
+            }
+            println!("  Step {}: {} -> {} ({:.2} nm, {:.2} kts, {} -> {})", 
                 j + 1,
                 from_name,
                 to_name,
                 step.distance,
                 step.speed,
-                step.start_time,
-                step.end_time
+                data.race_clock.describe(step.start_time),
+                data.race_clock.describe(step.end_time)
             );
         }
         println!();
@@ -568,23 +3054,26 @@ fn explore_paths_command(
         let avg_distance: f64 = sorted_paths.iter().map(|p| p.total_distance).sum::<f64>() / sorted_paths.len() as f64;
         
         println!("Summary:");
-        println!("  Fastest path: {:.2} hours", fastest_path.end_time);
-        println!("  Slowest path: {:.2} hours", slowest_path.end_time);
+        println!("  Fastest path: {}", data.race_clock.describe(fastest_path.end_time));
+        println!("  Slowest path: {}", data.race_clock.describe(slowest_path.end_time));
         println!("  Average end time: {avg_end_time:.2} hours");
         println!("  Average distance: {avg_distance:.2} nm");
     }
     
-    Ok(())
+    Ok(stats)
 }
 
 /// Explore paths from a starting buoy to a specific target buoy
 fn explore_target_paths_command(
     data: &data::RegattaData,
-    start_name: &str,
-    target_name: &str,
+    (start_name, target_name): (&str, &str),
     start_time: f64,
-    max_steps: usize,
-) -> Result<(), Box<dyn std::error::Error>> {
+    lookahead: Lookahead,
+    options: &SearchOptions, // waits and the buoys and legs to avoid
+    objective: &dyn RouteObjective,
+    format: OutputFormat,
+) -> Result<optimize::SearchStats, Box<dyn std::error::Error>> {
+    let max_steps = lookahead.steps;
     // Find the starting buoy by name
     let start_boei = data.get_boei(start_name)
         .ok_or_else(|| format!("Starting buoy '{start_name}' not found"))?;
@@ -600,49 +3089,89 @@ fn explore_target_paths_command(
     let target_index = data.get_boei_index(target_name)
         .ok_or_else(|| format!("Target buoy '{target_name}' not found in index"))?;
     
+    // Planned hierarchically, the best skeletons by the objective are filled in
+    let plan = || plan_hierarchical(data, start_index, Some(target_index), start_time, max_steps, objective, options);
+    
+    if format == OutputFormat::Json {
+        let (mut paths, stats) = if lookahead.hierarchical {
+            let plan = plan()?;
+            (plan.paths, plan.stats)
+        } else {
+            let results = search_target_paths(data, start_index, target_index, start_time, max_steps, options)?;
+            (results.paths(data), results.stats)
+        };
+        paths.retain(|path| objective.accepts(data, path));
+        objective.sort_paths(data, &mut paths);
+        print_json(&FindPathsResponse {
+            start: start_name.to_string(),
+            target: Some(target_name.to_string()),
+            start_time,
+            steps: max_steps,
+            truncated: false, // the command line searches all paths
+            stats,
+            paths: paths.iter().map(|path| PathResponse::new(data, path)).collect(),
+        });
+        return Ok(stats);
+    }
+
     println!("Exploring paths from: {} ({}) to: {} ({})", 
         start_name, 
         start_boei.buoy_type.as_ref().unwrap_or(&"Unknown".to_string()),
         target_name,
         target_boei.buoy_type.as_ref().unwrap_or(&"Unknown".to_string())
     );
-    println!("Starting time: {start_time:.1} hours after race start");
-    println!("Maximum steps: {max_steps}");
+    println!("Starting time: {} after race start", data.race_clock.describe(start_time));
+    if lookahead.hierarchical {
+        println!("Maximum steps: {max_steps}, planned hierarchically");
+    } else {
+        println!("Maximum steps: {max_steps}");
+    }
     println!();
     
-    // Explore all possible paths to the target
-    let paths = explore_target_paths(data, start_index, target_index, start_time, max_steps, None)?;
+    // Explore all possible paths to the target, or those of the best skeletons
+    let (mut paths, stats) = if lookahead.hierarchical {
+        let plan = plan()?;
+        (plan.paths, plan.stats)
+    } else {
+        let results = search_target_paths(data, start_index, target_index, start_time, max_steps, options)?;
+        (results.paths(data), results.stats)
+    };
+    paths.retain(|path| objective.accepts(data, path));
     
     if paths.is_empty() {
         println!("No paths found from {start_name} to {target_name}.");
-        return Ok(());
+        return Ok(stats);
     }
     
     println!("Found {} path(s) to target:", paths.len());
     println!();
     
-    // Sort paths by end time for better readability
+    // Sort paths by the objective, by end time unless asked otherwise
     let mut sorted_paths = paths;
-    sorted_paths.sort_by(|a, b| a.end_time.partial_cmp(&b.end_time).unwrap_or(std::cmp::Ordering::Equal));
+    objective.sort_paths(data, &mut sorted_paths);
     
     // Print each path
     for (i, path) in sorted_paths.iter().enumerate() {
-        println!("Path {} (Total: {:.2} nm, End time: {:.2} hours):", 
-            i + 1, path.total_distance, path.end_time);
+        println!("Path {} (Total: {:.2} nm, Scored: {:.2} nm, End time: {}):",
+            i + 1, path.total_distance, path.scored_distance, data.race_clock.describe(path.end_time));
         
         // Print each step in the path
         for (j, step) in path.steps.iter().enumerate() {
             let from_name = &data.boeien[step.from].name;
             let to_name = &data.boeien[step.to].name;
             
-            println!("  Step {}: {} -> {} ({:.2} nm, {:.2} kts, {:.2}h -> {:.2}h)", 
+            if step.wait > 0.0 {
+                println!("  Wait at {} for {:.2}h until {}",
+                    from_name, step.wait, data.race_clock.describe(step.start_time));
+            }
+            println!("  Step {}: {} -> {} ({:.2} nm, {:.2} kts, {} -> {})", 
                 j + 1,
                 from_name,
                 to_name,
                 step.distance,
                 step.speed,
-                step.start_time,
-                step.end_time
+                data.race_clock.describe(step.start_time),
+                data.race_clock.describe(step.end_time)
             );
         }
         println!();
@@ -656,20 +3185,50 @@ fn explore_target_paths_command(
         let avg_distance: f64 = sorted_paths.iter().map(|p| p.total_distance).sum::<f64>() / sorted_paths.len() as f64;
         
         println!("Summary:");
-        println!("  Fastest path to target: {:.2} hours", fastest_path.end_time);
-        println!("  Slowest path to target: {:.2} hours", slowest_path.end_time);
+        println!("  Fastest path to target: {}", data.race_clock.describe(fastest_path.end_time));
+        println!("  Slowest path to target: {}", data.race_clock.describe(slowest_path.end_time));
         println!("  Average end time: {avg_end_time:.2} hours");
         println!("  Average distance: {avg_distance:.2} nm");
     }
     
-    Ok(())
+    Ok(stats)
+}
+
+/// Print the statistics of a search after its paths, for `--stats`
+fn print_search_stats(stats: &optimize::SearchStats) {
+    let pruned = &stats.pruned;
+    println!();
+    println!("Search statistics:");
+    println!("  Nodes expanded: {}", stats.nodes_expanded);
+    println!("  Branches pruned: {}", pruned.total());
+    for (reason, count) in [
+        ("avoided buoys and legs", pruned.constraints),
+        ("leg limits", pruned.leg_limit),
+        ("forbidden sequences", pruned.forbidden_sequence),
+        ("no speed estimate", pruned.no_estimate),
+        ("pins out of reach", pruned.pins),
+        ("paths missing the filter or a pin", pruned.unmatched),
+    ] {
+        if count > 0 {
+            println!("    {reason}: {count}");
+        }
+    }
+    match stats.cache_hit_rate() {
+        Some(rate) => println!("  Cache hit rate: {:.1}% of {} lookups", rate * 100.0, stats.cache_hits + stats.cache_misses),
+        None => println!("  Cache hit rate: no lookups"),
+    }
+    if let Some(limit) = stats.exhausted {
+        println!("  Stopped by the budget: {limit:?}");
+    }
+    println!("  Elapsed: {:.1} ms", stats.elapsed_ms);
 }
 
-/// Export the regatta graph to a DOT file for graphviz visualization and generate PDF
+/// Export the regatta graph to a DOT file for graphviz visualization and
+/// generate PDF, returns whether the PDF was generated
 fn export_regatta_graph(
     data: &data::RegattaData,
     output_path: &str,
-) -> Result<(), Box<dyn std::error::Error>> {
+) -> Result<bool, Box<dyn std::error::Error>> {
     // Build the regatta graph
     let (graph, node_indices) = build_regatta_graph(data);
 
@@ -735,7 +3294,7 @@ fn export_regatta_graph(
     std::fs::write(output_path, dot_content)?;
 
     // Generate PDF from the DOT file using graphviz
-    println!("Generating PDF from DOT file...");
+    tracing::info!("Generating PDF from DOT file...");
     let pdf_output = "regatta_graph.pdf";
     
     let output = std::process::Command::new("dot")
@@ -745,18 +3304,19 @@ fn export_regatta_graph(
     match output {
         Ok(output) => {
             if output.status.success() {
-                println!("Successfully generated PDF: {pdf_output}");
+                tracing::info!("Successfully generated PDF: {pdf_output}");
+                Ok(true)
             } else {
                 let stderr = String::from_utf8_lossy(&output.stderr);
-                eprintln!("Warning: Failed to generate PDF: {stderr}");
-                eprintln!("Note: Make sure 'dot' (graphviz) is installed on your system");
+                tracing::warn!("Failed to generate PDF: {stderr}");
+                tracing::warn!("Make sure 'dot' (graphviz) is installed on your system");
+                Ok(false)
             }
         }
         Err(e) => {
-            eprintln!("Warning: Could not execute 'dot' command: {e}");
-            eprintln!("Note: Make sure 'dot' (graphviz) is installed on your system");
+            tracing::warn!("Could not execute 'dot' command: {e}");
+            tracing::warn!("Make sure 'dot' (graphviz) is installed on your system");
+            Ok(false)
         }
     }
-
-    Ok(())
 }
@@ -0,0 +1,120 @@
+use crate::error::UursError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+
+/// Data files a run depends on, as loaded by [`crate::data::load_regatta_data`]
+pub const INPUT_FILES: [&str; 7] = [
+    "data/boeien.csv",
+    "data/starts.csv",
+    "data/rakken.csv",
+    "data/polars.csv",
+    "data/wind.csv",
+    "data/zeiten.csv",
+    "data/rules.toml",
+];
+
+/// Hash of an input file of a run
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct InputHash {
+    pub file: String,
+    pub sha256: String, // hex encoded
+}
+
+/// Everything needed to regenerate the results of a run identically, e.g.
+/// after the race for a protest: the version, the inputs, the parameters,
+/// and the seed of the random numbers
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct RunManifest {
+    pub version: String,        // of the crate
+    pub command: String,
+    pub arguments: Vec<String>, // the full command line
+    pub parameters: serde_json::Value,
+    pub seed: Option<u64>,      // for runs drawing random numbers
+    pub inputs: Vec<InputHash>,
+    pub created: DateTime<Utc>,
+}
+
+impl RunManifest {
+    /// Describe a run with the current contents of the input files
+    pub fn new(
+        command: &str,
+        arguments: Vec<String>,
+        parameters: serde_json::Value,
+        seed: Option<u64>,
+    ) -> Result<Self, UursError> {
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            command: command.to_string(),
+            arguments,
+            parameters,
+            seed,
+            inputs: hash_inputs()?,
+            created: Utc::now(),
+        })
+    }
+
+    /// Read a manifest written by [`RunManifest::write`]
+    pub fn read(path: &Path) -> Result<Self, UursError> {
+        let file = path.display().to_string();
+        let content = std::fs::read_to_string(path).map_err(|e| UursError::data_load(&file, e))?;
+        serde_json::from_str(&content).map_err(|e| UursError::data_load(&file, e))
+    }
+
+    /// Write the manifest as pretty printed JSON
+    pub fn write(&self, path: &Path) -> Result<(), UursError> {
+        std::fs::write(path, serde_json::to_string_pretty(self).map_err(UursError::export)?)?;
+        Ok(())
+    }
+
+    /// Input files which changed since the run, so the results can't be
+    /// regenerated identically any more
+    pub fn changed_inputs(&self) -> Result<Vec<String>, UursError> {
+        let current = hash_inputs()?;
+        Ok(self
+            .inputs
+            .iter()
+            .filter(|input| !current.contains(input))
+            .map(|input| input.file.clone())
+            .collect())
+    }
+}
+
+/// Hash the input files in their current state
+pub fn hash_inputs() -> Result<Vec<InputHash>, UursError> {
+    INPUT_FILES
+        .iter()
+        .map(|&file| {
+            let content = std::fs::read(file).map_err(|e| UursError::data_load(file, e))?;
+            let sha256 = Sha256::digest(&content).iter().map(|byte| format!("{byte:02x}")).collect();
+            Ok(InputHash {
+                file: file.to_string(),
+                sha256,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_manifest() {
+        let manifest = RunManifest::new(
+            "simulate-ensemble",
+            vec!["uurs24".to_string(), "simulate-ensemble".to_string()],
+            serde_json::json!({ "runs": "100" }),
+            Some(7),
+        )
+        .unwrap();
+        assert_eq!(manifest.inputs.len(), INPUT_FILES.len());
+        assert!(manifest.inputs.iter().all(|input| input.sha256.len() == 64));
+        assert!(manifest.changed_inputs().unwrap().is_empty());
+
+        let mut changed = manifest.clone();
+        changed.inputs[4].sha256 = "0".repeat(64);
+        assert_eq!(changed.changed_inputs().unwrap(), ["data/wind.csv"]);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::optimize::SearchMonitor;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Upper bounds in seconds of the request latency histogram buckets
+const LATENCY_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Latency histogram of the requests to one route
+#[derive(Debug, Default)]
+struct Histogram {
+    buckets: [u64; LATENCY_BUCKETS.len()], // cumulative counts per bucket
+    count: u64,
+    sum: f64, // in seconds
+}
+
+impl Histogram {
+    fn observe(&mut self, seconds: f64) {
+        for (bucket, bound) in self.buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        self.count += 1;
+        self.sum += seconds;
+    }
+}
+
+/// Totals over all searches run by the server
+#[derive(Debug, Default)]
+struct SearchStats {
+    searches: u64,
+    cancelled: u64,
+    nodes_explored: u64,
+    paths_found: u64,
+    seconds: f64,
+}
+
+/// Request and optimizer statistics of the server in Prometheus format
+#[derive(Debug, Default)]
+pub struct Metrics {
+    requests: Mutex<BTreeMap<(String, String, u16), u64>>, // (method, route, status) -> count
+    latencies: Mutex<BTreeMap<String, Histogram>>,         // route -> latency histogram
+    searches: Mutex<SearchStats>,
+    cache_hits: AtomicU64,
+    cache_misses: AtomicU64,
+    cache_entries: AtomicUsize,
+}
+
+impl Metrics {
+    /// Create an empty set of metrics
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a finished HTTP request
+    pub fn record_request(&self, method: &str, path: &str, status: u16, elapsed: Duration) {
+        let route = route_label(path, status);
+        *self
+            .requests
+            .lock()
+            .unwrap()
+            .entry((method.to_string(), route.clone(), status))
+            .or_default() += 1;
+        self.latencies
+            .lock()
+            .unwrap()
+            .entry(route)
+            .or_default()
+            .observe(elapsed.as_secs_f64());
+    }
+
+    /// Record a finished search with the counters of its monitor
+    pub fn record_search(&self, monitor: &SearchMonitor, elapsed: Duration) {
+        let mut stats = self.searches.lock().unwrap();
+        stats.searches += 1;
+        if monitor.is_cancelled() {
+            stats.cancelled += 1;
+        }
+        stats.nodes_explored += monitor.nodes_explored() as u64;
+        stats.paths_found += monitor.paths_found() as u64;
+        stats.seconds += elapsed.as_secs_f64();
+    }
+
+    /// Record a lookup in the response cache
+    pub fn record_cache_lookup(&self, hit: bool) {
+        if hit {
+            self.cache_hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Record the number of responses in the cache
+    pub fn set_cache_entries(&self, entries: usize) {
+        self.cache_entries.store(entries, Ordering::Relaxed);
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+
+        out.push_str("# HELP uurs24_http_requests_total Number of HTTP requests handled.\n");
+        out.push_str("# TYPE uurs24_http_requests_total counter\n");
+        for ((method, route, status), count) in self.requests.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "uurs24_http_requests_total{{method=\"{method}\",route=\"{route}\",status=\"{status}\"}} {count}"
+            );
+        }
+
+        out.push_str("# HELP uurs24_http_request_duration_seconds Time taken to handle HTTP requests.\n");
+        out.push_str("# TYPE uurs24_http_request_duration_seconds histogram\n");
+        for (route, histogram) in self.latencies.lock().unwrap().iter() {
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(histogram.buckets) {
+                let _ = writeln!(
+                    out,
+                    "uurs24_http_request_duration_seconds_bucket{{route=\"{route}\",le=\"{bound}\"}} {count}"
+                );
+            }
+            let _ = writeln!(
+                out,
+                "uurs24_http_request_duration_seconds_bucket{{route=\"{route}\",le=\"+Inf\"}} {}",
+                histogram.count
+            );
+            let _ = writeln!(
+                out,
+                "uurs24_http_request_duration_seconds_sum{{route=\"{route}\"}} {}",
+                histogram.sum
+            );
+            let _ = writeln!(
+                out,
+                "uurs24_http_request_duration_seconds_count{{route=\"{route}\"}} {}",
+                histogram.count
+            );
+        }
+
+        let stats = self.searches.lock().unwrap();
+        for (name, kind, help, value) in [
+            ("searches_total", "counter", "Number of searches run.", stats.searches as f64),
+            ("searches_cancelled_total", "counter", "Number of searches cancelled before finishing.", stats.cancelled as f64),
+            ("search_nodes_explored_total", "counter", "Search nodes visited by all searches.", stats.nodes_explored as f64),
+            ("search_paths_found_total", "counter", "Complete paths found by all searches.", stats.paths_found as f64),
+            ("search_seconds_total", "counter", "Time spent searching.", stats.seconds),
+            ("cache_hits_total", "counter", "Responses served from the cache.", self.cache_hits.load(Ordering::Relaxed) as f64),
+            ("cache_misses_total", "counter", "Cache lookups which had to compute the response.", self.cache_misses.load(Ordering::Relaxed) as f64),
+            ("cache_entries", "gauge", "Responses currently in the cache.", self.cache_entries.load(Ordering::Relaxed) as f64),
+        ] {
+            let _ = writeln!(out, "# HELP uurs24_{name} {help}");
+            let _ = writeln!(out, "# TYPE uurs24_{name} {kind}");
+            let _ = writeln!(out, "uurs24_{name} {value}");
+        }
+
+        out
+    }
+}
+
+/// Label for the route of a request path which keeps the number of distinct
+/// labels small: ids become `{id}` and all 404 responses are lumped together
+fn route_label(path: &str, status: u16) -> String {
+    if status == 404 {
+        return "not_found".to_string();
+    }
+    let segments: Vec<&str> = path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .map(|s| if s.parse::<u64>().is_ok() { "{id}" } else { s })
+        .collect();
+    format!("/{}", segments.join("/"))
+}
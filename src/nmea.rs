@@ -0,0 +1,335 @@
+use crate::track::KNOTS_PER_MS;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::{TcpListener, UdpSocket};
+
+/// Minimum time between two positions taken from the instruments, which
+/// usually report once per second
+const POSITION_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Conversion factor of km/h, used in NMEA sentences besides m/s, to knots
+const KNOTS_PER_KMH: f64 = 0.539957;
+
+/// Data of an NMEA 0183 sentence relevant for the live state
+#[derive(Clone, Debug, PartialEq)]
+pub enum NmeaSentence {
+    /// Position from RMC or GGA, GGA has no speed and course
+    Position {
+        lat: f64,
+        long: f64,
+        speed: Option<f64>,  // speed over ground in knots
+        course: Option<f64>, // course over ground in degrees
+    },
+    /// True wind direction (where it comes from) and speed from MWD
+    WindDirection { direction: f64, speed: f64 },
+    /// Wind angle relative to the bow and speed from MWV, either apparent or true
+    WindAngle { angle: f64, speed: f64, apparent: bool },
+}
+
+/// A reading of the instruments to feed into the live state
+#[derive(Clone, Debug, PartialEq)]
+pub enum InstrumentReading {
+    Position {
+        lat: f64,
+        long: f64,
+        speed: Option<f64>,
+        course: Option<f64>,
+    },
+    Wind { direction: f64, speed: f64 }, // true wind in degrees and knots
+}
+
+/// Parse an NMEA 0183 sentence, returning `None` for valid sentences of other
+/// types and for sentences flagged as invalid by the instrument
+pub fn parse_sentence(line: &str) -> Result<Option<NmeaSentence>, String> {
+    let line = line.trim();
+    let body = line
+        .strip_prefix('$')
+        .ok_or_else(|| format!("Sentence must start with '$': {line}"))?;
+
+    // Verify the checksum if there is one
+    let body = match body.split_once('*') {
+        Some((body, checksum)) => {
+            let expected = u8::from_str_radix(checksum, 16)
+                .map_err(|_| format!("Invalid checksum '{checksum}'"))?;
+            let actual = body.bytes().fold(0u8, |sum, b| sum ^ b);
+            if actual != expected {
+                return Err(format!(
+                    "Checksum mismatch: expected {expected:02X}, computed {actual:02X}"
+                ));
+            }
+            body
+        }
+        None => body,
+    };
+
+    let fields: Vec<&str> = body.split(',').collect();
+    let address = fields[0];
+    if address.len() != 5 || address.starts_with('P') {
+        return Ok(None); // proprietary or malformed address
+    }
+    let field = |i: usize| fields.get(i).copied().unwrap_or("");
+    let number = |i: usize| field(i).parse::<f64>().ok();
+
+    let sentence = match &address[2..] {
+        "RMC" => {
+            if field(2) != "A" {
+                return Ok(None);
+            }
+            let (Some(lat), Some(long)) = (
+                parse_coordinate(field(3), field(4)),
+                parse_coordinate(field(5), field(6)),
+            ) else {
+                return Ok(None);
+            };
+            NmeaSentence::Position {
+                lat,
+                long,
+                speed: number(7),
+                course: number(8),
+            }
+        }
+        "GGA" => {
+            if matches!(field(6), "" | "0") {
+                return Ok(None); // no fix
+            }
+            let (Some(lat), Some(long)) = (
+                parse_coordinate(field(2), field(3)),
+                parse_coordinate(field(4), field(5)),
+            ) else {
+                return Ok(None);
+            };
+            NmeaSentence::Position {
+                lat,
+                long,
+                speed: None,
+                course: None,
+            }
+        }
+        "MWD" => {
+            let speed = number(5).or_else(|| number(7).map(|ms| ms * KNOTS_PER_MS));
+            let (Some(direction), Some(speed)) = (number(1), speed) else {
+                return Ok(None);
+            };
+            NmeaSentence::WindDirection { direction, speed }
+        }
+        "MWV" => {
+            if field(5) != "A" {
+                return Ok(None);
+            }
+            let (Some(angle), Some(speed)) = (number(1), number(3)) else {
+                return Ok(None);
+            };
+            let speed = match field(4) {
+                "N" => speed,
+                "K" => speed * KNOTS_PER_KMH,
+                "M" => speed * KNOTS_PER_MS,
+                unit => return Err(format!("Unknown wind speed unit '{unit}'")),
+            };
+            NmeaSentence::WindAngle {
+                angle,
+                speed,
+                apparent: field(2) == "R",
+            }
+        }
+        _ => return Ok(None),
+    };
+    Ok(Some(sentence))
+}
+
+/// Parse a coordinate in the NMEA format "ddmm.mmmm" or "dddmm.mmmm" with
+/// its hemisphere into decimal degrees
+fn parse_coordinate(value: &str, hemisphere: &str) -> Option<f64> {
+    let value: f64 = value.parse().ok()?;
+    let degrees = (value / 100.0).trunc();
+    let decimal = degrees + (value - degrees * 100.0) / 60.0;
+    match hemisphere {
+        "N" | "E" => Some(decimal),
+        "S" | "W" => Some(-decimal),
+        _ => None,
+    }
+}
+
+/// Compute the true wind angle relative to the bow and the true wind speed
+/// from the apparent wind and the boat speed
+pub fn true_wind(apparent_angle: f64, apparent_speed: f64, boat_speed: f64) -> (f64, f64) {
+    let angle = apparent_angle.to_radians();
+    let x = apparent_speed * angle.cos() - boat_speed;
+    let y = apparent_speed * angle.sin();
+    (y.atan2(x).to_degrees().rem_euclid(360.0), x.hypot(y))
+}
+
+/// Combines the sentences of the instruments into readings: positions are
+/// thinned out, and wind angles are turned into true wind directions using
+/// the latest course and speed over ground
+#[derive(Debug, Default)]
+pub struct InstrumentState {
+    course: Option<f64>,
+    speed: Option<f64>,
+    last_position: Option<Instant>,
+}
+
+impl InstrumentState {
+    /// Process a sentence received at the given instant
+    pub fn update(&mut self, sentence: NmeaSentence, now: Instant) -> Option<InstrumentReading> {
+        match sentence {
+            NmeaSentence::Position {
+                lat,
+                long,
+                speed,
+                course,
+            } => {
+                self.speed = speed.or(self.speed);
+                self.course = course.or(self.course);
+                if self
+                    .last_position
+                    .is_some_and(|last| now.duration_since(last) < POSITION_INTERVAL)
+                {
+                    return None;
+                }
+                self.last_position = Some(now);
+                Some(InstrumentReading::Position {
+                    lat,
+                    long,
+                    speed,
+                    course,
+                })
+            }
+            NmeaSentence::WindDirection { direction, speed } => {
+                Some(InstrumentReading::Wind { direction, speed })
+            }
+            NmeaSentence::WindAngle {
+                angle,
+                speed,
+                apparent,
+            } => {
+                let course = self.course?;
+                let (angle, speed) = if apparent {
+                    true_wind(angle, speed, self.speed?)
+                } else {
+                    (angle, speed)
+                };
+                Some(InstrumentReading::Wind {
+                    direction: (course + angle).rem_euclid(360.0),
+                    speed,
+                })
+            }
+        }
+    }
+}
+
+/// Listen for NMEA sentences on a UDP socket and on TCP connections at the
+/// same address, passing every reading to the handler
+pub async fn listen(
+    addr: SocketAddr,
+    handler: impl Fn(InstrumentReading) + Send + Sync + 'static,
+) -> std::io::Result<()> {
+    let udp = UdpSocket::bind(addr).await?;
+    let tcp = TcpListener::bind(addr).await?;
+    let state = Mutex::new(InstrumentState::default());
+    let process = Arc::new(move |line: &str| match parse_sentence(line) {
+        Ok(Some(sentence)) => {
+            let reading = state.lock().unwrap().update(sentence, Instant::now());
+            if let Some(reading) = reading {
+                handler(reading);
+            }
+        }
+        Ok(None) => {}
+        Err(e) => tracing::debug!("Ignoring NMEA sentence: {e}"),
+    });
+
+    // Datagrams may carry several sentences
+    let udp_process = process.clone();
+    tokio::spawn(async move {
+        let mut buffer = vec![0u8; 4096];
+        loop {
+            match udp.recv_from(&mut buffer).await {
+                Ok((len, _)) => {
+                    for line in String::from_utf8_lossy(&buffer[..len]).lines() {
+                        if !line.trim().is_empty() {
+                            udp_process(line);
+                        }
+                    }
+                }
+                Err(e) => tracing::warn!("NMEA UDP receive failed: {e}"),
+            }
+        }
+    });
+
+    loop {
+        let (stream, peer) = tcp.accept().await?;
+        tracing::info!(%peer, "NMEA connection");
+        let tcp_process = process.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(stream).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if !line.trim().is_empty() {
+                    tcp_process(&line);
+                }
+            }
+            tracing::info!(%peer, "NMEA connection closed");
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sentences() {
+        let rmc = parse_sentence("$GPRMC,123519,A,5257.999,N,00510.000,E,6.2,210.5,130626,,,A*77")
+            .unwrap()
+            .unwrap();
+        let NmeaSentence::Position { lat, long, speed, course } = rmc else {
+            panic!("RMC should be a position");
+        };
+        assert!((lat - (52.0 + 57.999 / 60.0)).abs() < 1e-9);
+        assert!((long - 5.166_666_666).abs() < 1e-6);
+        assert_eq!((speed, course), (Some(6.2), Some(210.5)));
+
+        assert_eq!(
+            parse_sentence("$WIMWD,225.0,T,223.0,M,14.0,N,7.2,M").unwrap(),
+            Some(NmeaSentence::WindDirection { direction: 225.0, speed: 14.0 })
+        );
+        assert_eq!(
+            parse_sentence("$WIMWV,045.0,R,10.0,M,A").unwrap(),
+            Some(NmeaSentence::WindAngle { angle: 45.0, speed: 10.0 * KNOTS_PER_MS, apparent: true })
+        );
+
+        // Invalid fixes and unknown sentences are skipped, bad checksums rejected
+        assert_eq!(parse_sentence("$GPRMC,123519,V,,,,,,,130626,,,N").unwrap(), None);
+        assert_eq!(parse_sentence("$GPGSV,3,1,11,03,03,111,00").unwrap(), None);
+        assert!(parse_sentence("$GPRMC,123519,A,5257.999,N,00510.000,E,6.2,210.5,130626,,,A*00").is_err());
+    }
+
+    #[test]
+    fn test_instrument_state() {
+        let mut state = InstrumentState::default();
+        let start = Instant::now();
+        let position = NmeaSentence::Position {
+            lat: 52.9,
+            long: 5.1,
+            speed: Some(6.0),
+            course: Some(90.0),
+        };
+
+        // Wind angles need a course first
+        let wind = NmeaSentence::WindAngle { angle: 30.0, speed: 12.0, apparent: false };
+        assert_eq!(state.update(wind.clone(), start), None);
+
+        assert!(state.update(position.clone(), start).is_some());
+        assert_eq!(state.update(position.clone(), start + Duration::from_secs(1)), None);
+        assert!(state.update(position, start + POSITION_INTERVAL).is_some());
+
+        assert_eq!(
+            state.update(wind, start),
+            Some(InstrumentReading::Wind { direction: 120.0, speed: 12.0 })
+        );
+
+        // Apparent wind from ahead at twice the boat speed is true wind from ahead
+        let (angle, speed) = true_wind(0.0, 12.0, 6.0);
+        assert!(angle.abs() < 1e-9 && (speed - 6.0).abs() < 1e-9);
+    }
+}
@@ -1,5 +1,14 @@
-use crate::data::{RegattaData, build_regatta_graph};
+use crate::data::{Boei, RegattaData, WindCondition, build_regatta_graph};
+use crate::error::UursError;
+use crate::params::check_route_length;
+use crate::scoring::score_path;
+use crate::geo::{distance_nm, initial_bearing};
 use petgraph::visit::EdgeRef;
+use serde::Serialize;
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::time::Instant;
 
 #[derive(Clone)]
 pub struct Step {
@@ -9,41 +18,840 @@ pub struct Step {
     pub speed: f64,      // in knots, estimated by wind and bearing
     pub start_time: f64, // in hours since race start
     pub end_time: f64,   // in hours since race start
+    pub wait: f64,       // in hours spent at `from` before `start_time`, e.g. anchored in a calm
 }
 
-/// Internal state for path exploration
+/// Internal state for path exploration, one for the whole search: a branch
+/// sails its leg with `advance` before it goes deeper and undoes it with
+/// `retreat` when it returns, so that exploring allocates nothing but the
+/// paths found
 struct PathExplorationState {
     current_point: usize,
     current_time: f64,
     remaining_steps: usize,
-    current_steps: Vec<Step>,
-    edges_used: Vec<u8>,
+    current_steps: Vec<Step>, // preallocated to the depth of the search
+    stored_steps: Vec<Option<usize>>, // where each current step is in the arena, once a path with it was found
+    edges_used: Vec<u32>,
     total_distance: f64,
+    pins_met: usize, // pins of the search the path sailed so far meets, in their order
 }
 
+/// What `PathExplorationState::retreat` needs to undo a step
+#[derive(Clone, Copy)]
+struct Checkpoint {
+    point: usize,
+    time: f64,
+    total_distance: f64,
+    edge_index: usize,
+    pins_met: usize,
+}
+
+impl PathExplorationState {
+    fn new(data: &RegattaData, start_point: usize, start_time: f64, num_steps: usize, pins: &[Pin]) -> Self {
+        let starts_pinned = pins.first().is_some_and(|pin| pin.meets(None, start_point, 0, start_time));
+        Self {
+            current_point: start_point,
+            current_time: start_time,
+            remaining_steps: num_steps,
+            current_steps: Vec::with_capacity(num_steps),
+            stored_steps: Vec::with_capacity(num_steps),
+            edges_used: vec![0; data.starts.len() + data.rakken.len()],
+            total_distance: 0.0,
+            pins_met: usize::from(starts_pinned),
+        }
+    }
+
+    /// Sail a step over the start or leg with the index `edge_index` of
+    /// `edges_used`
+    fn advance(&mut self, step: Step, edge_index: usize, pins: &[Pin]) -> Checkpoint {
+        let checkpoint = Checkpoint {
+            point: self.current_point,
+            time: self.current_time,
+            total_distance: self.total_distance,
+            edge_index,
+            pins_met: self.pins_met,
+        };
+        let slot = self.current_steps.len() + 1;
+        if pins.get(self.pins_met).is_some_and(|pin| pin.meets(Some(step.from), step.to, slot, step.end_time)) {
+            self.pins_met += 1;
+        }
+        self.current_point = step.to;
+        self.current_time = step.end_time;
+        self.remaining_steps -= 1;
+        self.total_distance += step.distance;
+        self.edges_used[edge_index] += 1;
+        self.current_steps.push(step);
+        self.stored_steps.push(None);
+        checkpoint
+    }
+
+    /// Undo the last step, restoring the state from before it exactly
+    fn retreat(&mut self, checkpoint: Checkpoint) {
+        self.current_steps.pop();
+        self.stored_steps.pop();
+        self.edges_used[checkpoint.edge_index] -= 1;
+        self.current_point = checkpoint.point;
+        self.current_time = checkpoint.time;
+        self.total_distance = checkpoint.total_distance;
+        self.pins_met = checkpoint.pins_met;
+        self.remaining_steps += 1;
+    }
+
+    /// Whether the next pin not met yet can still be met by sailing on, so
+    /// that branches which can't are cut off early
+    fn pins_reachable(&self, pins: &[Pin]) -> bool {
+        pins.get(self.pins_met).is_none_or(|pin| {
+            pin.reachable(self.current_steps.len(), self.current_time, self.remaining_steps)
+        })
+    }
+
+    /// The path sailed so far
+    fn path(&self, data: &RegattaData) -> Path {
+        Path::new(data, self.current_steps.clone(), self.total_distance, self.current_time)
+    }
+}
+
+/// A step of the paths found by a search, stored once for all the paths
+/// starting with the same steps
+#[derive(Clone)]
+struct ArenaStep {
+    step: Step,
+    previous: Option<usize>, // index of the step before in the arena
+}
+
+/// A path found by a search, as its last step in the arena
+#[derive(Clone)]
+struct FoundPath {
+    last: Option<usize>, // None for a path without steps
+    total_distance: f64,
+    end_time: f64,
+}
+
+/// Granularity in hours of the waits a search may insert at a buoy
+pub const WAIT_STEP: f64 = 0.25;
+
 #[derive(Clone)]
 pub struct Path {
     pub steps: Vec<Step>,
-    pub total_distance: f64, // total distance in nm
-    pub end_time: f64,       // end time in hours
+    pub total_distance: f64,  // total distance in nm
+    pub scored_distance: f64, // distance counted by the scoring rules of the data in nm
+    pub qualification_margin: Option<f64>, // scored nm above the qualification distance of the rules
+    pub end_time: f64,        // end time in hours
+}
+
+impl Path {
+    /// Create a path from its steps, scored by the rules of the data
+    pub fn new(data: &RegattaData, steps: Vec<Step>, total_distance: f64, end_time: f64) -> Self {
+        let mut path = Self {
+            steps,
+            total_distance,
+            scored_distance: 0.0,
+            qualification_margin: None,
+            end_time,
+        };
+        path.scored_distance = score_path(data, &path, &data.rules.scoring).scored_distance;
+        path.qualification_margin = data.rules.qualification_margin(path.scored_distance);
+        path
+    }
+
+    /// Whether the path scores the qualification distance of the rules, true
+    /// if there is none
+    pub fn qualifies(&self) -> bool {
+        self.qualification_margin.is_none_or(|margin| margin >= 0.0)
+    }
+
+    /// Average speed in knots from the start of the path to its end, waits
+    /// included, 0 for a path without steps
+    pub fn average_speed(&self) -> f64 {
+        let Some(first) = self.steps.first() else {
+            return 0.0;
+        };
+        let hours = self.end_time - (first.start_time - first.wait);
+        if hours > 0.0 { self.total_distance / hours } else { 0.0 }
+    }
+
+    /// Jaccard distance of the legs of two paths: the share of the legs
+    /// sailed by either of them which only one sails, 0 for the same legs in
+    /// any order and direction, 1 for no leg in common
+    pub fn leg_distance(&self, other: &Path) -> f64 {
+        jaccard_distance(&self.legs(), &other.legs())
+    }
+
+    /// The starts and legs sailed, each once whatever its direction
+    fn legs(&self) -> BTreeSet<(usize, usize)> {
+        self.steps.iter().map(|step| (step.from.min(step.to), step.from.max(step.to))).collect()
+    }
+}
+
+/// Which of the paths found are returned: the best ones by an objective, at
+/// most `routes` of them, each at least `min_distance` away from the better
+/// ones returned by [`Path::leg_distance`], so that the navigator sees
+/// different strategies, e.g. a northern and a southern loop, rather than
+/// permutations of the same legs
+#[derive(Clone, Debug)]
+pub struct PathSelection {
+    pub objective: Arc<dyn RouteObjective>,
+    pub routes: Option<usize>, // all of them if none
+    pub min_distance: f64,     // between 0, which returns near-duplicates, and 1
+}
+
+impl Default for PathSelection {
+    fn default() -> Self {
+        Self {
+            objective: Arc::new(PlanObjective::default()),
+            routes: None,
+            min_distance: 0.0,
+        }
+    }
+}
+
+impl PathSelection {
+    /// Select the paths the objective accepts, best first
+    pub fn select(&self, data: &RegattaData, mut paths: Vec<Path>) -> Vec<Path> {
+        paths.retain(|path| self.objective.accepts(data, path));
+        self.objective.sort_paths(data, &mut paths);
+        let routes = self.routes.unwrap_or(usize::MAX);
+        if self.min_distance <= 0.0 {
+            paths.truncate(routes);
+            return paths;
+        }
+        // The legs of each path selected, built once instead of per comparison
+        let mut selected: Vec<(Path, BTreeSet<(usize, usize)>)> = Vec::new();
+        for path in paths {
+            if selected.len() >= routes {
+                break;
+            }
+            let legs = path.legs();
+            if selected.iter().all(|(_, better)| jaccard_distance(better, &legs) >= self.min_distance) {
+                selected.push((path, legs));
+            }
+        }
+        selected.into_iter().map(|(path, _)| path).collect()
+    }
+}
+
+// Share of the legs in either set which are in only one of them
+fn jaccard_distance(a: &BTreeSet<(usize, usize)>, b: &BTreeSet<(usize, usize)>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+    1.0 - a.intersection(b).count() as f64 / union as f64
+}
+
+/// Constraints restricting which buoys and legs a search may use
+#[derive(Clone, Debug, Default)]
+pub struct SearchConstraints {
+    pub forbidden_buoys: Vec<usize>,          // indices of buoys that must not be visited
+    pub forbidden_legs: Vec<(usize, usize)>,  // legs that must not be sailed, in either direction
+}
+
+impl SearchConstraints {
+    /// Constraints from a list of buoys to avoid, as comma separated names,
+    /// and one of legs, each as `FROM-TO`, as the command line and the query
+    /// parameters give them. Buoy names may contain dashes themselves, so a
+    /// leg is split at the dash which leaves known buoys on both sides.
+    pub fn from_avoid_lists(data: &RegattaData, buoys: Option<&str>, legs: Option<&str>) -> Result<Self, UursError> {
+        fn items(list: Option<&str>) -> impl Iterator<Item = &str> {
+            list.into_iter().flat_map(|list| list.split(',')).map(str::trim).filter(|item| !item.is_empty())
+        }
+        let mut constraints = Self::default();
+        for name in items(buoys) {
+            let index = data.get_boei_index(name).ok_or_else(|| UursError::UnknownBuoy(name.to_string()))?;
+            constraints.forbidden_buoys.push(index);
+        }
+        for leg in items(legs) {
+            let ends = leg
+                .match_indices('-')
+                .find_map(|(at, _)| Some((data.get_boei_index(&leg[..at])?, data.get_boei_index(&leg[at + 1..])?)))
+                .ok_or_else(|| {
+                    UursError::InvalidParameter(format!(
+                        "Leg '{leg}' to avoid must be two known buoys joined by a dash, e.g. WV12-LC1"
+                    ))
+                })?;
+            constraints.forbidden_legs.push(ends);
+        }
+        Ok(constraints)
+    }
+
+    /// Check whether a step from one buoy to another is allowed
+    pub fn allows(&self, from: usize, to: usize) -> bool {
+        !self.forbidden_buoys.contains(&to)
+            && !self
+                .forbidden_legs
+                .iter()
+                .any(|&(a, b)| (a == from && b == to) || (a == to && b == from))
+    }
+}
+
+/// Options controlling a path search
+#[derive(Clone, Debug, Default)]
+pub struct SearchOptions<'a> {
+    pub max_paths: Option<usize>,             // maximum number of paths to return
+    pub constraints: SearchConstraints,       // buoys and legs the search must avoid
+    pub monitor: Option<&'a SearchMonitor>,   // progress reporting and cancellation
+    pub max_wait: f64,                        // longest wait in hours at a buoy before a leg, 0 to always sail on
+    pub budget: SearchBudget,                 // resources the search may use before it stops early
+    pub filter: PathFilter,                   // buoys the paths returned must pass or end at
+    pub pins: Vec<Pin>,                       // buoys and legs the paths must pass, in this order
+}
+
+/// A buoy, or the leg to it, which the paths of a search must pass at a
+/// place of the route or around a time, e.g. Stavoren around hour 12 for a
+/// crew change. The pins of a search are met in their order, each by a
+/// later place of the route than the one before.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Pin {
+    pub buoy: usize,         // index of the buoy to be at
+    pub from: Option<usize>, // index of the buoy the leg to it comes from, to pin the leg
+    pub slot: Option<usize>, // place in the route as the legs sailed before, 0 for the start
+    pub time: Option<f64>,   // hours since race start to be at the buoy around
+    pub tolerance: f64,      // hours the buoy may be reached before or after `time`
+}
+
+/// Hours a pinned time may be missed by when no tolerance is given
+pub const PIN_TOLERANCE: f64 = 0.5;
+
+impl Pin {
+    /// Check whether reaching a buoy, from another one unless it is the
+    /// start, at a place of the route and a time meets the pin
+    pub fn meets(&self, from: Option<usize>, buoy: usize, slot: usize, time: f64) -> bool {
+        buoy == self.buoy
+            && self.from.is_none_or(|pinned| from == Some(pinned))
+            && self.slot.is_none_or(|pinned| pinned == slot)
+            && self.time.is_none_or(|pinned| (time - pinned).abs() <= self.tolerance)
+    }
+
+    /// Whether a later place of the route than `slot`, reached at `time` with
+    /// steps remaining, can still meet the pin; times only go forward
+    fn reachable(&self, slot: usize, time: f64, remaining_steps: usize) -> bool {
+        remaining_steps > 0
+            && self.slot.is_none_or(|pinned| pinned > slot && pinned <= slot + remaining_steps)
+            && self.time.is_none_or(|pinned| time <= pinned + self.tolerance)
+    }
+}
+
+/// Buoys the paths of a search must pass or end at. Paths which don't match
+/// are skipped while searching and don't count towards the path limit.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PathFilter {
+    pub include: Option<usize>, // index of a buoy the path must start at, round, or end at
+    pub finish: Option<usize>,  // index of the buoy the path must end at
+}
+
+impl PathFilter {
+    /// Check the steps of a path against the filter
+    pub fn matches(&self, steps: &[Step]) -> bool {
+        let includes = self.include.is_none_or(|buoy| {
+            steps.iter().any(|step| step.from == buoy || step.to == buoy)
+        });
+        let finishes = self.finish.is_none_or(|buoy| steps.last().is_some_and(|step| step.to == buoy));
+        includes && finishes
+    }
+}
+
+/// Resources a single search may use. A search exhausting its budget stops
+/// like one reaching its path limit and returns the paths found so far as
+/// truncated, so that a large request can't take all memory of a small
+/// machine. 0 leaves the respective limit off.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SearchBudget {
+    pub max_nodes: usize,  // nodes the search may expand
+    pub max_paths: usize,  // paths the search may keep while it runs, all of them or only the best are returned
+    pub max_memory: usize, // estimated bytes of the paths kept
+}
+
+/// The part of the budget which stopped a search
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BudgetLimit {
+    Nodes,
+    Paths,
+    Memory,
+}
+
+/// What a search used of its budget and how it went, to tune the pruning
+/// and explain long runtimes
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize)]
+pub struct SearchStats {
+    pub nodes_expanded: usize,
+    pub resident_paths: usize,
+    pub memory_estimate: usize,         // bytes of the steps and paths kept
+    pub exhausted: Option<BudgetLimit>, // the part of the budget which stopped the search
+    pub pruned: PrunedBranches,
+    pub cache_hits: usize,   // searches answered from the searches done before, by hierarchical plans
+    pub cache_misses: usize,
+    pub elapsed_ms: f64,     // wall clock time of the search
+}
+
+impl SearchStats {
+    /// Share of the cache lookups which were hits, `None` without lookups
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let lookups = self.cache_hits + self.cache_misses;
+        (lookups > 0).then(|| self.cache_hits as f64 / lookups as f64)
+    }
+
+    /// Add the counters of another search to these, e.g. of a search the
+    /// search ran as a part of it; the elapsed time is the caller's
+    pub fn add(&mut self, other: &SearchStats) {
+        self.nodes_expanded += other.nodes_expanded;
+        self.resident_paths = self.resident_paths.max(other.resident_paths);
+        self.memory_estimate = self.memory_estimate.max(other.memory_estimate);
+        self.exhausted = self.exhausted.or(other.exhausted);
+        self.pruned.add(&other.pruned);
+        self.cache_hits += other.cache_hits;
+        self.cache_misses += other.cache_misses;
+    }
+}
+
+/// Branches a search cut off, by the reason
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize)]
+pub struct PrunedBranches {
+    pub constraints: usize,        // legs to buoys or over legs the constraints exclude
+    pub leg_limit: usize,          // starts and legs sailed as often as the rules allow already
+    pub forbidden_sequence: usize, // legs completing a sequence of buoys the rules forbid
+    pub no_estimate: usize,        // legs without a speed estimate, e.g. to buoys without coordinates
+    pub pins: usize,               // branches which can't meet the pins any more
+    pub unmatched: usize,          // complete paths missing the filter or a pin
+}
+
+impl PrunedBranches {
+    /// Branches cut off for any reason
+    pub fn total(&self) -> usize {
+        self.constraints + self.leg_limit + self.forbidden_sequence + self.no_estimate + self.pins + self.unmatched
+    }
+
+    fn add(&mut self, other: &PrunedBranches) {
+        self.constraints += other.constraints;
+        self.leg_limit += other.leg_limit;
+        self.forbidden_sequence += other.forbidden_sequence;
+        self.no_estimate += other.no_estimate;
+        self.pins += other.pins;
+        self.unmatched += other.unmatched;
+    }
+
+    /// Count a route `validate_route` rejected by the reason of its error
+    pub fn count_invalid(&mut self, error: &UursError) {
+        match error {
+            UursError::LegOverused { .. } => self.leg_limit += 1,
+            UursError::ForbiddenSequence(_) => self.forbidden_sequence += 1,
+            _ => self.constraints += 1,
+        }
+    }
+}
+
+/// Progress counters and cancellation flag shared with a running search
+pub struct SearchMonitor {
+    cancelled: AtomicBool,
+    nodes_explored: AtomicUsize,
+    paths_found: AtomicUsize,
+    objective: Box<dyn RouteObjective>, // ranks the paths for the best path so far
+    best_path: Mutex<Option<Path>>,     // best path found so far
+}
+
+impl Default for SearchMonitor {
+    fn default() -> Self {
+        Self {
+            cancelled: AtomicBool::default(),
+            nodes_explored: AtomicUsize::default(),
+            paths_found: AtomicUsize::default(),
+            objective: Box::new(PlanObjective::default()),
+            best_path: Mutex::default(),
+        }
+    }
+}
+
+impl std::fmt::Debug for SearchMonitor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SearchMonitor")
+            .field("cancelled", &self.is_cancelled())
+            .field("nodes_explored", &self.nodes_explored())
+            .field("paths_found", &self.paths_found())
+            .field("objective", &self.objective)
+            .finish()
+    }
+}
+
+impl SearchMonitor {
+    /// Create a new monitor for a search that has not started yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a new monitor which tracks the best path according to an objective
+    pub fn with_objective(objective: impl RouteObjective + 'static) -> Self {
+        Self {
+            objective: Box::new(objective),
+            ..Self::default()
+        }
+    }
+
+    /// Ask the search to stop as soon as possible
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    /// Check whether the search was asked to stop
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Number of search nodes visited so far
+    pub fn nodes_explored(&self) -> usize {
+        self.nodes_explored.load(Ordering::Relaxed)
+    }
+
+    /// Number of complete paths found so far
+    pub fn paths_found(&self) -> usize {
+        self.paths_found.load(Ordering::Relaxed)
+    }
+
+    /// The best path found so far according to the monitor's objective
+    pub fn best_path(&self) -> Option<Path> {
+        self.best_path.lock().unwrap().clone()
+    }
+
+    /// Count a visited node, failing if the search was cancelled
+    pub(crate) fn visit_node(&self) -> Result<(), UursError> {
+        if self.is_cancelled() {
+            return Err(UursError::Cancelled);
+        }
+        self.nodes_explored.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Count a complete path and remember it if it is the best so far
+    pub(crate) fn found_path(&self, data: &RegattaData, path: &Path) {
+        self.paths_found.fetch_add(1, Ordering::Relaxed);
+        if !self.objective.accepts(data, path) {
+            return;
+        }
+        let mut best_path = self.best_path.lock().unwrap();
+        let is_better = match best_path.as_ref() {
+            Some(best) => self.objective.compare(data, path, best) == std::cmp::Ordering::Less,
+            None => true,
+        };
+        if is_better {
+            *best_path = Some(path.clone());
+        }
+    }
+}
+
+/// Ranks the paths found by a search and decides which of them may be
+/// returned at all. The searches, selections, and monitors take any
+/// objective; the ones of the command line and the API are the
+/// [`PlanObjective`]s, library users can implement their own.
+pub trait RouteObjective: std::fmt::Debug + Send + Sync {
+    /// Name of the objective, as the command line and the API select it
+    fn name(&self) -> String;
+
+    /// Compare two paths, the better path is less. Paths the objective
+    /// ranks equal should be ordered by [`tie_break`], so that results come
+    /// in the same order in every run.
+    fn compare(&self, data: &RegattaData, a: &Path, b: &Path) -> std::cmp::Ordering;
+
+    /// Whether a path may be returned at all, every path by default
+    fn accepts(&self, _data: &RegattaData, _path: &Path) -> bool {
+        true
+    }
+
+    /// Sort paths so that the best path comes first
+    fn sort_paths(&self, data: &RegattaData, paths: &mut [Path]) {
+        paths.sort_by(|a, b| self.compare(data, a, b));
+    }
+}
+
+/// The objectives of the command line and the API
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PlanObjective {
+    #[default]
+    EarliestArrival, // reach the end of the path as early as possible
+    MaxDistance,     // score as many miles as possible, earlier arrival breaks ties
+    Qualify,         // qualify with the most time to spare, else come as close as possible
+    MaxMargin,       // end by the deadline with the most time to spare, later paths are not accepted
+    Weighted(ObjectiveWeights), // the highest sum of the measures of a path times their weights
+}
+
+/// Weights of the measures of a path for [`PlanObjective::Weighted`], 0 for
+/// a measure which doesn't count
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ObjectiveWeights {
+    pub scored_distance: f64, // per nm scored
+    pub total_distance: f64,  // per nm sailed
+    pub margin: f64,          // per hour to spare before the deadline, negative once it has passed
+    pub average_speed: f64,   // per knot
+}
+
+impl ObjectiveWeights {
+    /// Names of the measures as given in a weighted objective
+    pub const MEASURES: [&'static str; 4] = ["scored_distance", "total_distance", "margin", "average_speed"];
+
+    fn weights(&self) -> [f64; 4] {
+        [self.scored_distance, self.total_distance, self.margin, self.average_speed]
+    }
+
+    /// Weights from a list of measures with their weights, like
+    /// `scored_distance=1,margin=2`
+    pub fn parse(list: &str) -> Option<Self> {
+        let mut weights = Self::default();
+        for item in list.split(',').map(str::trim) {
+            let (measure, weight) = item.split_once('=')?;
+            let weight = weight.trim().parse::<f64>().ok().filter(|weight| weight.is_finite())?;
+            match measure.trim() {
+                "scored_distance" => weights.scored_distance = weight,
+                "total_distance" => weights.total_distance = weight,
+                "margin" => weights.margin = weight,
+                "average_speed" => weights.average_speed = weight,
+                _ => return None,
+            }
+        }
+        Some(weights)
+    }
+
+    /// The weighted sum of the measures of a path
+    pub fn score(&self, data: &RegattaData, path: &Path) -> f64 {
+        let measures = [
+            path.scored_distance,
+            path.total_distance,
+            data.rules.scoring.deadline - path.end_time,
+            path.average_speed(),
+        ];
+        measures.iter().zip(self.weights()).map(|(measure, weight)| measure * weight).sum()
+    }
+}
+
+impl PlanObjective {
+    /// Look up an objective by its name as used in the API, a weighted
+    /// objective as `weighted:` and its measures with their weights, e.g.
+    /// `weighted:scored_distance=1,margin=2`
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "earliest_arrival" => Some(Self::EarliestArrival),
+            "max_distance" => Some(Self::MaxDistance),
+            "qualify" => Some(Self::Qualify),
+            "max_margin" => Some(Self::MaxMargin),
+            _ => name.strip_prefix("weighted:").and_then(ObjectiveWeights::parse).map(Self::Weighted),
+        }
+    }
+}
+
+impl RouteObjective for PlanObjective {
+    fn name(&self) -> String {
+        match self {
+            Self::EarliestArrival => "earliest_arrival".to_string(),
+            Self::MaxDistance => "max_distance".to_string(),
+            Self::Qualify => "qualify".to_string(),
+            Self::MaxMargin => "max_margin".to_string(),
+            Self::Weighted(weights) => {
+                let terms: Vec<String> = ObjectiveWeights::MEASURES
+                    .iter()
+                    .zip(weights.weights())
+                    .filter(|&(_, weight)| weight != 0.0)
+                    .map(|(measure, weight)| format!("{measure}={weight}"))
+                    .collect();
+                format!("weighted:{}", terms.join(","))
+            }
+        }
+    }
+
+    /// For `Qualify` the paths which score the qualification distance come
+    /// first, the one finishing earliest, and so least at risk of missing the
+    /// deadline, first; the paths which don't qualify follow like for
+    /// `MaxDistance`. `MaxMargin` ranks like `EarliestArrival`, but only
+    /// accepts the paths ending by the deadline.
+    fn compare(&self, data: &RegattaData, a: &Path, b: &Path) -> std::cmp::Ordering {
+        match self {
+            Self::EarliestArrival | Self::MaxMargin => tie_break(data, a, b),
+            Self::MaxDistance => {
+                b.scored_distance.total_cmp(&a.scored_distance).then_with(|| tie_break(data, a, b))
+            }
+            Self::Qualify => b.qualifies().cmp(&a.qualifies()).then_with(|| {
+                if a.qualifies() {
+                    a.end_time
+                        .total_cmp(&b.end_time)
+                        .then_with(|| b.scored_distance.total_cmp(&a.scored_distance))
+                        .then_with(|| tie_break(data, a, b))
+                } else {
+                    Self::MaxDistance.compare(data, a, b)
+                }
+            }),
+            Self::Weighted(weights) => weights
+                .score(data, b)
+                .total_cmp(&weights.score(data, a))
+                .then_with(|| tie_break(data, a, b)),
+        }
+    }
+
+    fn accepts(&self, data: &RegattaData, path: &Path) -> bool {
+        match self {
+            Self::MaxMargin => path.end_time <= data.rules.scoring.deadline,
+            _ => true,
+        }
+    }
+}
+
+/// The total order of paths which the objectives and the orders of result
+/// tables fall back to, so that results come in the same order in every run
+/// whatever order the search found them in: the earlier end first, then the
+/// shorter distance, then by the names of the buoys sailed, leg by leg, and
+/// last the earlier departures for the same legs with different waits
+pub fn tie_break(data: &RegattaData, a: &Path, b: &Path) -> std::cmp::Ordering {
+    fn names<'a>(data: &'a RegattaData, path: &'a Path) -> impl Iterator<Item = &'a str> {
+        let first = path.steps.first().map(|step| data.boeien[step.from].name.as_str());
+        first.into_iter().chain(path.steps.iter().map(|step| data.boeien[step.to].name.as_str()))
+    }
+    let departures = || {
+        a.steps
+            .iter()
+            .zip(&b.steps)
+            .map(|(a, b)| a.start_time.total_cmp(&b.start_time))
+            .find(|order| order.is_ne())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    };
+    a.end_time
+        .total_cmp(&b.end_time)
+        .then_with(|| a.total_distance.total_cmp(&b.total_distance))
+        .then_with(|| names(data, a).cmp(names(data, b)))
+        .then_with(departures)
+}
+
+/// Order of the paths in a table of search results, each with the best path
+/// first: the longest, the earliest finishing, or the fastest
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathOrder {
+    Distance, // total distance, longest first
+    EndTime,  // end of the path, earliest first
+    Speed,    // average speed over the path, fastest first
+}
+
+impl PathOrder {
+    /// Look up an order by its name as used in the API
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "distance" => Some(Self::Distance),
+            "end_time" => Some(Self::EndTime),
+            "speed" => Some(Self::Speed),
+            _ => None,
+        }
+    }
+
+    /// The name of the order as used in the API
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Distance => "distance",
+            Self::EndTime => "end_time",
+            Self::Speed => "speed",
+        }
+    }
+
+    /// Sort paths in this order, paths equal in it by [`tie_break`]
+    pub fn sort_paths(&self, data: &RegattaData, paths: &mut [Path]) {
+        match self {
+            Self::Distance => paths.sort_by(|a, b| {
+                b.total_distance.total_cmp(&a.total_distance).then_with(|| tie_break(data, a, b))
+            }),
+            Self::EndTime => paths.sort_by(|a, b| tie_break(data, a, b)),
+            Self::Speed => paths.sort_by(|a, b| {
+                b.average_speed().total_cmp(&a.average_speed()).then_with(|| tie_break(data, a, b))
+            }),
+        }
+    }
 }
 
 /// Detailed performance estimation for a leg between two buoys
 pub struct LegPerformance {
     pub estimated_speed: f64,      // in knots
+    pub polar_speed: f64,          // in knots, from the polars before the correction of the leg
     pub course_bearing: f64,       // bearing of the course in degrees
     pub wind_direction: f64,       // wind direction in degrees
     pub relative_bearing: f64,     // bearing relative to wind in degrees
     pub wind_speed: f64,           // wind speed in knots
+    pub official_distance: Option<f64>, // in nm, of the start or leg, None if the buoys are not connected
+    pub great_circle_distance: f64, // in nm
+    pub travel_time: f64,          // in hours, over the official distance if there is one
+    pub arrival_time: f64,         // in hours since race start
+}
+
+impl LegPerformance {
+    /// The point of sail of the leg by its bearing relative to the wind
+    pub fn point_of_sail(&self) -> PointOfSail {
+        PointOfSail::from_relative_bearing(self.relative_bearing)
+    }
+}
+
+/// How a boat sails relative to the wind
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointOfSail {
+    CloseHauled,
+    CloseReach,
+    BeamReach,
+    BroadReach,
+    Downwind,
+}
+
+impl PointOfSail {
+    /// The point of sail at a bearing relative to the wind in degrees
+    pub fn from_relative_bearing(relative_bearing: f64) -> Self {
+        if relative_bearing < 45.0 {
+            PointOfSail::CloseHauled
+        } else if relative_bearing < 90.0 {
+            PointOfSail::CloseReach
+        } else if relative_bearing < 135.0 {
+            PointOfSail::BeamReach
+        } else if relative_bearing < 180.0 {
+            PointOfSail::BroadReach
+        } else {
+            PointOfSail::Downwind
+        }
+    }
+
+    /// Key of the description in the message catalog
+    pub fn message_key(self) -> &'static str {
+        match self {
+            PointOfSail::CloseHauled => "sail_close_hauled",
+            PointOfSail::CloseReach => "sail_close_reach",
+            PointOfSail::BeamReach => "sail_beam_reach",
+            PointOfSail::BroadReach => "sail_broad_reach",
+            PointOfSail::Downwind => "sail_downwind",
+        }
+    }
+}
+
+/// The wind forecast at a time, that of the closest hour outside of the
+/// forecast, and calm without a forecast
+pub fn forecast_wind(data: &RegattaData, time: f64) -> WindCondition {
+    data.wind_data.get_wind_at_time(time)
+        .unwrap_or_else(|| {
+            // Fallback: use the closest available hour
+            let last = data.rules.scoring.deadline + data.wind_data.start_offset;
+            let hour = (time + data.wind_data.start_offset).floor().clamp(0.0, last) as u32;
+            data.wind_data.get_wind_at_hour(hour)
+                .or_else(|| data.wind_data.get_wind_at_hour(0)) // Final fallback to hour 0
+                .cloned()
+                .unwrap_or(WindCondition { time: hour, wind_speed: 0.0, wind_angle: 0.0 })
+        })
+}
+
+/// Angle between the wind direction and the course in degrees, from 0 head
+/// to wind to 180 dead downwind, which the polars are looked up by
+pub(crate) fn relative_bearing(wind_direction: f64, course: f64) -> f64 {
+    let relative_bearing = (wind_direction - course).abs(); // -360 < relative_bearing < 360
+    if relative_bearing > 180.0 {
+        360.0 - relative_bearing
+    } else {
+        relative_bearing
+    }
 }
 
-/// Estimate the performance for a leg between two buoys at a specific time
+/// Estimate the performance for a leg between two buoys at a specific time,
+/// which fails if either buoy has no coordinates
 pub fn estimate_leg_performance(
     data: &RegattaData,
     from: usize, // index of vertex in graph resp. Boei in data
     to: usize,   // index of vertex in graph resp. Boei in data
     time: f64,
-) -> LegPerformance {
+) -> Result<LegPerformance, UursError> {
     // We proceed as follows:
     //  - compute the initial bearing of the edge
     //  - lookup the wind estimate for the given time
@@ -54,51 +862,396 @@ pub fn estimate_leg_performance(
 
     // Compute initial bearing of the edge:
     let (source, target) = (&data.boeien[from], &data.boeien[to]);
-    let s_lat = source.lat.unwrap() * std::f64::consts::PI / 180.0;
-    let s_lon = source.long.unwrap() * std::f64::consts::PI / 180.0;
-    let t_lat = target.lat.unwrap() * std::f64::consts::PI / 180.0;
-    let t_lon = target.long.unwrap() * std::f64::consts::PI / 180.0;
-    let d_lon = t_lon - s_lon;
-    let course_bearing = (d_lon.sin() * t_lat.cos())
-        .atan2(s_lat.cos() * t_lat.sin() - s_lat.sin() * t_lat.cos() * d_lon.cos())
-        * 180.0
-        / std::f64::consts::PI;
-
-    // Normalize bearing to 0-360 range
-    let course_bearing = (course_bearing + 360.0) % 360.0;
+    let coordinates = |boei: &Boei| boei.coordinates().ok_or_else(|| UursError::MissingCoordinates(boei.name.clone()));
+    let (source_position, target_position) = (coordinates(source)?, coordinates(target)?);
+    let course_bearing = initial_bearing(source_position, target_position);
 
     // Lookup the wind estimate for the given time:
-    let wind = data.wind_data.get_wind_at_time(time)
-        .unwrap_or_else(|| {
-            // Fallback: use the closest available hour
-            let hour = time.floor().clamp(0.0, 24.0) as u32;
-            data.wind_data.get_wind_at_hour(hour)
-                .or_else(|| data.wind_data.get_wind_at_hour(0)) // Final fallback to hour 0
-                .unwrap()
-                .clone()
-        });
+    let wind = forecast_wind(data, time);
     let wind_direction = wind.wind_angle;
     let wind_speed = wind.wind_speed;
 
     // Compute the bearing in relation to the wind:
-    let mut relative_bearing = (wind_direction - course_bearing).abs(); // -360 < relative_bearing < 360
-    if relative_bearing > 180.0 {
-        relative_bearing = 360.0 - relative_bearing;
-    }
+    let relative_bearing = relative_bearing(wind_direction, course_bearing);
 
-    let estimated_speed = data.polar_data
+    let polar_speed = data.polar_data
         .get_boat_speed(relative_bearing, wind_speed);
 
-    LegPerformance {
+    // Some legs are systematically slower or faster than the polars predict
+    let estimated_speed = polar_speed * data.leg_correction(from, to);
+
+    // The travel time is over the official distance, if the buoys are
+    // connected, and over the great circle distance otherwise:
+    let great_circle_distance = distance_nm(source_position, target_position);
+    let official_distance = data.leg_distance(from, to);
+    let speed = if estimated_speed > 0.0 { estimated_speed } else { 1.0 }; // 1 knot as fallback
+    let travel_time = official_distance.unwrap_or(great_circle_distance) / speed;
+
+    Ok(LegPerformance {
         estimated_speed,
+        polar_speed,
         course_bearing,
         wind_direction,
         relative_bearing,
         wind_speed,
+        official_distance,
+        great_circle_distance,
+        travel_time,
+        arrival_time: time + travel_time,
+    })
+}
+
+
+
+/// Sail a leg of the given distance, departing at `time` or, if that arrives
+/// earlier, after waiting at `from` for up to `max_wait` hours
+///
+/// The speed is estimated at the departure, so in a calm with the wind
+/// forecast to fill in waiting can beat sailing off at once. The waits tried
+/// are multiples of [`WAIT_STEP`], the shortest wait wins a tie.
+pub(crate) fn sail_leg(
+    data: &RegattaData,
+    from: usize,
+    to: usize,
+    distance: f64,
+    time: f64,
+    max_wait: f64,
+) -> Result<Step, UursError> {
+    let sail = |wait: f64| -> Result<Step, UursError> {
+        let speed = estimate_leg_performance(data, from, to, time + wait)?.estimated_speed;
+        // 1 knot as fallback if the polars give no speed
+        let travel_time = if speed > 0.0 { distance / speed } else { distance / 1.0 };
+        Ok(Step {
+            from,
+            to,
+            distance,
+            speed,
+            start_time: time + wait,
+            end_time: time + wait + travel_time,
+            wait,
+        })
+    };
+    let mut best = sail(0.0)?;
+    let mut waits = 1;
+    while waits as f64 * WAIT_STEP <= max_wait + 1e-9 {
+        let step = sail(waits as f64 * WAIT_STEP)?;
+        if step.end_time < best.end_time {
+            best = step;
+        }
+        waits += 1;
+    }
+    Ok(best)
+}
+
+/// Evaluate a given sequence of buoys as a path, starting at a specific time
+///
+/// Every consecutive pair of buoys must be connected by a start or a leg
+/// in the regatta graph. The speed for each step is estimated at the time
+/// the step begins, exactly as during path exploration.
+pub fn evaluate_route(
+    data: &RegattaData,
+    route: &[usize],  // indices of the buoys in the order they are sailed
+    start_time: f64,  // time in hours since race start
+) -> Result<Path, UursError> {
+    if route.len() < 2 {
+        return Err(UursError::InvalidParameter("A route needs at least two buoys".to_string()));
+    }
+    check_route_length(data, route.len() - 1)?;
+
+    if let Some(&invalid) = route.iter().find(|&&idx| idx >= data.boeien.len()) {
+        return Err(UursError::UnknownBuoy(invalid.to_string()));
+    }
+
+    // Build the regatta graph
+    let (graph, _node_indices) = build_regatta_graph(data);
+
+    let mut steps = Vec::with_capacity(route.len() - 1);
+    let mut current_time = start_time;
+    let mut total_distance = 0.0;
+
+    for pair in route.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        let edge = graph
+            .find_edge(
+                petgraph::graph::NodeIndex::new(from),
+                petgraph::graph::NodeIndex::new(to),
+            )
+            .ok_or_else(|| UursError::NoLeg {
+                from: data.boeien[from].name.clone(),
+                to: data.boeien[to].name.clone(),
+            })?;
+        let distance = graph[edge].distance;
+
+        // Estimate performance for this leg, sailing on at once
+        let step = sail_leg(data, from, to, distance, current_time, 0.0)?;
+        current_time = step.end_time;
+        total_distance += distance;
+        steps.push(step);
     }
+
+    Ok(Path::new(data, steps, total_distance, current_time))
 }
 
+/// Check a given sequence of buoys against the rules of the race and the
+/// constraints of a search
+///
+/// Every consecutive pair of buoys must be connected by a start or a leg, no
+/// start or leg may be sailed more often than its maximum number in the data
+/// and the rule set allow, counting both directions of a leg, no sequence of
+/// buoys forbidden by the rule set may be rounded, and the route must avoid
+/// the buoys and legs excluded by the constraints.
+pub fn validate_route(
+    data: &RegattaData,
+    route: &[usize],  // indices of the buoys in the order they are sailed
+    constraints: &SearchConstraints,
+) -> Result<(), UursError> {
+    check_route_length(data, route.len().saturating_sub(1))?;
+    if let Some(&invalid) = route.iter().find(|&&idx| idx >= data.boeien.len()) {
+        return Err(UursError::UnknownBuoy(invalid.to_string()));
+    }
+
+    let (graph, _node_indices) = build_regatta_graph(data);
+    let mut edges_used = vec![0u32; data.starts.len() + data.rakken.len()];
+
+    for (i, pair) in route.windows(2).enumerate() {
+        let (from, to) = (pair[0], pair[1]);
+        let (from_name, to_name) = (&data.boeien[from].name, &data.boeien[to].name);
+        let edge = graph
+            .find_edge(
+                petgraph::graph::NodeIndex::new(from),
+                petgraph::graph::NodeIndex::new(to),
+            )
+            .ok_or_else(|| UursError::NoLeg {
+                from: from_name.clone(),
+                to: to_name.clone(),
+            })?;
+
+        if !constraints.allows(from, to) {
+            return Err(UursError::InvalidParameter(format!(
+                "The route from '{from_name}' to '{to_name}' is excluded by the constraints"
+            )));
+        }
 
+        // Count the usage of the start or leg, like the searches do
+        let edge_weight = &graph[edge];
+        let (edge_index, max_number) = if edge_weight.is_start {
+            (edge_weight.index, data.starts[edge_weight.index].max_number)
+        } else {
+            (data.starts.len() + edge_weight.index, data.rules.max_sailings(data.rakken[edge_weight.index].max_number))
+        };
+        edges_used[edge_index] += 1;
+        if edges_used[edge_index] > max_number {
+            return Err(UursError::LegOverused {
+                from: from_name.clone(),
+                to: to_name.clone(),
+                max_number,
+            });
+        }
+
+        if let Some(sequence) = data.rules.forbidden_sequence(data, &route[..i + 2]) {
+            return Err(UursError::ForbiddenSequence(sequence.to_vec()));
+        }
+    }
+
+    Ok(())
+}
+
+/// Estimate the number of nodes an exhaustive search with the given number of
+/// steps visits. Sailing a leg tends to lead to buoys with many legs, so the
+/// branching factor is the number of legs per buoy weighted by that number.
+pub fn estimate_search_nodes(data: &RegattaData, steps: usize) -> f64 {
+    let mut degrees: std::collections::HashMap<&str, f64> = std::collections::HashMap::new();
+    for rak in &data.rakken {
+        *degrees.entry(rak.from.as_str()).or_default() += 1.0;
+        *degrees.entry(rak.to.as_str()).or_default() += 1.0;
+    }
+    let total: f64 = degrees.values().sum();
+    if total == 0.0 {
+        return 1.0;
+    }
+    let branching = degrees.values().map(|d| d * d).sum::<f64>() / total;
+
+    (0..=steps).map(|k| branching.powi(k as i32)).sum()
+}
+
+/// Find the best continuation with a given number of steps from a buoy
+/// according to an objective, `None` if no path of that length exists
+pub fn best_continuation(
+    data: &RegattaData,
+    from: usize,             // index of the buoy to continue from
+    time: f64,               // time in hours since race start when rounding it
+    num_steps: usize,        // number of steps to look ahead
+    objective: &dyn RouteObjective,
+    options: &SearchOptions, // constraints and monitoring
+) -> Result<Option<Path>, UursError> {
+    let results = search_paths(data, from, time, num_steps, options)?;
+    Ok(results.best(data, objective, 1).pop())
+}
+
+/// Paths found by a search, which stops the moment it finds one path more
+/// than the limit of its options or has used up its budget. The paths share the steps they have in
+/// common and are only built as [`Path`]s when asked for, so that a search
+/// finding millions of paths needs memory for their steps once.
+#[derive(Clone, Default)]
+pub struct SearchResults {
+    pub truncated: bool, // the path limit or the budget stopped the search, there are more paths
+    pub stats: SearchStats,
+    arena: Vec<ArenaStep>,
+    found: Vec<FoundPath>,
+}
+
+impl SearchResults {
+    /// Number of paths found
+    pub fn len(&self) -> usize {
+        self.found.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.found.is_empty()
+    }
+
+    /// All paths in the order they were found
+    pub fn paths(&self, data: &RegattaData) -> Vec<Path> {
+        self.found.iter().map(|found| self.build(data, found)).collect()
+    }
+
+    /// The best paths the objective accepts, best first, at most `max` of
+    /// them. Only these are kept as [`Path`]s, the others are built one at a
+    /// time to compare them.
+    pub fn best(&self, data: &RegattaData, objective: &dyn RouteObjective, max: usize) -> Vec<Path> {
+        let mut best: Vec<Path> = Vec::with_capacity(max.min(self.found.len()));
+        for found in &self.found {
+            let path = self.build(data, found);
+            if !objective.accepts(data, &path) {
+                continue;
+            }
+            let position = best.partition_point(|other| objective.compare(data, other, &path) == std::cmp::Ordering::Less);
+            if position < max {
+                if best.len() == max {
+                    best.pop();
+                }
+                best.insert(position, path);
+            }
+        }
+        best
+    }
+
+    /// The paths chosen by a selection, best first. With a distance between
+    /// them, all paths are built to rank them, as the best one differing
+    /// from the others may be far down the ranking.
+    pub fn select(&self, data: &RegattaData, selection: &PathSelection) -> Vec<Path> {
+        if selection.min_distance <= 0.0 {
+            return self.best(data, selection.objective.as_ref(), selection.routes.unwrap_or(usize::MAX));
+        }
+        selection.select(data, self.paths(data))
+    }
+
+    fn build(&self, data: &RegattaData, found: &FoundPath) -> Path {
+        let mut steps = Vec::new();
+        let mut next = found.last;
+        while let Some(index) = next {
+            steps.push(self.arena[index].step.clone());
+            next = self.arena[index].previous;
+        }
+        steps.reverse();
+        Path::new(data, steps, found.total_distance, found.end_time)
+    }
+}
+
+/// Collects the paths of a search up to the limit and within the budget
+struct PathCollector {
+    results: SearchResults,
+    max_paths: usize,
+    budget: SearchBudget,
+}
+
+impl PathCollector {
+    fn new(options: &SearchOptions) -> Self {
+        Self {
+            results: SearchResults::default(),
+            max_paths: options.max_paths.unwrap_or(usize::MAX),
+            budget: options.budget,
+        }
+    }
+
+    fn truncated(&self) -> bool {
+        self.results.truncated
+    }
+
+    /// The counters of the branches the search cut off
+    fn pruned(&mut self) -> &mut PrunedBranches {
+        &mut self.results.stats.pruned
+    }
+
+    /// Stop the search because a part of the budget is used up
+    fn exhaust(&mut self, limit: BudgetLimit) {
+        self.results.truncated = true;
+        self.results.stats.exhausted = Some(limit);
+    }
+
+    /// Count an expanded node, false if the budget has no nodes left, which
+    /// stops the search
+    fn expand(&mut self) -> bool {
+        let stats = &mut self.results.stats;
+        if self.budget.max_nodes > 0 && stats.nodes_expanded >= self.budget.max_nodes {
+            self.exhaust(BudgetLimit::Nodes);
+            return false;
+        }
+        stats.nodes_expanded += 1;
+        true
+    }
+
+    /// Add the path sailed so far, or mark the search as truncated if the
+    /// limit or the budget is reached already, which stops it. The steps not
+    /// stored for an earlier path with the same beginning are added to the
+    /// arena.
+    fn add(&mut self, data: &RegattaData, state: &mut PathExplorationState, options: &SearchOptions) {
+        if !options.filter.matches(&state.current_steps) || state.pins_met < options.pins.len() {
+            self.pruned().unmatched += 1;
+            return;
+        }
+        if self.results.found.len() >= self.max_paths {
+            self.results.truncated = true;
+            return;
+        }
+        let (stats, budget) = (self.results.stats, self.budget);
+        if budget.max_paths > 0 && stats.resident_paths >= budget.max_paths {
+            return self.exhaust(BudgetLimit::Paths);
+        }
+        if budget.max_memory > 0 && stats.memory_estimate >= budget.max_memory {
+            return self.exhaust(BudgetLimit::Memory);
+        }
+        let results = &mut self.results;
+        if let Some(monitor) = options.monitor {
+            monitor.found_path(data, &state.path(data));
+        }
+        let mut last = None;
+        for (step, stored) in state.current_steps.iter().zip(&mut state.stored_steps) {
+            let index = *stored.get_or_insert_with(|| {
+                results.arena.push(ArenaStep {
+                    step: step.clone(),
+                    previous: last,
+                });
+                results.arena.len() - 1
+            });
+            last = Some(index);
+        }
+        results.found.push(FoundPath {
+            last,
+            total_distance: state.total_distance,
+            end_time: state.current_time,
+        });
+        results.stats.resident_paths = results.found.len();
+        results.stats.memory_estimate = results.arena.len() * std::mem::size_of::<ArenaStep>()
+            + results.found.len() * std::mem::size_of::<FoundPath>();
+    }
+
+    /// The results of the search started at `started`
+    fn into_results(mut self, started: Instant) -> SearchResults {
+        self.results.stats.elapsed_ms = started.elapsed().as_secs_f64() * 1000.0;
+        self.results
+    }
+}
 
 /// Explore all possible paths from a starting point with a given number of steps
 pub fn explore_paths(
@@ -106,52 +1259,71 @@ pub fn explore_paths(
     start_point: usize,    // index of the starting buoy
     start_time: f64,       // time in hours since race start
     num_steps: usize,      // number of steps to explore
-    max_paths: Option<usize>, // maximum number of paths to return
-) -> Result<Vec<Path>, Box<dyn std::error::Error>> {
+    options: &SearchOptions, // path limit, constraints, and monitoring
+) -> Result<Vec<Path>, UursError> {
+    search_paths(data, start_point, start_time, num_steps, options).map(|results| results.paths(data))
+}
+
+/// Explore the paths from a starting point like [`explore_paths`], telling
+/// whether the path limit truncated the search
+pub fn search_paths(
+    data: &RegattaData,
+    start_point: usize,    // index of the starting buoy
+    start_time: f64,       // time in hours since race start
+    num_steps: usize,      // number of steps to explore
+    options: &SearchOptions, // path limit, constraints, and monitoring
+) -> Result<SearchResults, UursError> {
     // Build the regatta graph
     let (graph, _node_indices) = build_regatta_graph(data);
     
     if start_point >= data.boeien.len() {
-        return Err(format!("Invalid start point index: {start_point}").into());
+        return Err(UursError::UnknownBuoy(start_point.to_string()));
     }
     
-    let mut all_paths = Vec::new();
-    let initial_edges_used = vec![0u8; data.starts.len() + data.rakken.len()];
+    let started = Instant::now();
+    let mut collector = PathCollector::new(options);
     
     // Start the recursive exploration
-    let initial_state = PathExplorationState {
-        current_point: start_point,
-        current_time: start_time,
-        remaining_steps: num_steps,
-        current_steps: Vec::new(),
-        edges_used: initial_edges_used,
-        total_distance: 0.0,
-    };
+    let mut state = PathExplorationState::new(data, start_point, start_time, num_steps, &options.pins);
+    explore_paths_recursive(data, &graph, &mut state, &mut collector, options)?;
     
-    explore_paths_recursive(data, &graph, initial_state, &mut all_paths, max_paths.unwrap_or(usize::MAX))?;
-    
-    Ok(all_paths)
+    Ok(collector.into_results(started))
+}
+
+/// Check whether sailing on from `current` to `next` completes a sequence of
+/// buoys the rules forbid, `steps` being the path sailed up to `current`
+fn completes_forbidden_sequence(data: &RegattaData, current: usize, steps: &[Step], next: usize) -> bool {
+    if data.rules.forbidden_sequences.is_empty() {
+        return false;
+    }
+    let buoys: Vec<usize> = steps.iter().map(|step| step.from).chain([current, next]).collect();
+    data.rules.forbidden_sequence(data, &buoys).is_some()
 }
 
 /// Recursive helper function for path exploration
 fn explore_paths_recursive(
     data: &RegattaData,
     graph: &petgraph::Graph<Option<String>, crate::data::RegattaEdge>,
-    state: PathExplorationState,
-    all_paths: &mut Vec<Path>,
-    max_paths: usize,
-) -> Result<(), Box<dyn std::error::Error>> {
+    state: &mut PathExplorationState,
+    collector: &mut PathCollector,
+    options: &SearchOptions,
+) -> Result<(), UursError> {
+    if let Some(monitor) = options.monitor {
+        monitor.visit_node()?;
+    }
+    if !collector.expand() {
+        return Ok(());
+    }
+    
     // If no steps remaining, save the current path
     if state.remaining_steps == 0 {
-        all_paths.push(Path {
-            steps: state.current_steps,
-            total_distance: state.total_distance,
-            end_time: state.current_time,
-        });
-        // Exit early if we've reached the maximum number of paths
-        if all_paths.len() >= max_paths {
-            return Ok(());
-        }
+        collector.add(data, state, options);
+        return Ok(());
+    }
+    
+    // Give up on branches which can't meet the pins any more
+    if !state.pins_reachable(&options.pins) {
+        collector.pruned().pins += 1;
         return Ok(());
     }
     
@@ -164,6 +1336,12 @@ fn explore_paths_recursive(
         let target_node = edge_ref.target();
         let target_point = target_node.index();
         
+        // Skip buoys and legs excluded by the search constraints
+        if !options.constraints.allows(state.current_point, target_point) {
+            collector.pruned().constraints += 1;
+            continue;
+        }
+        
         // Check if we can use this edge (based on max_number constraint)
         let edge_index = if edge_weight.is_start {
             edge_weight.index
@@ -176,134 +1354,105 @@ fn explore_paths_recursive(
         let max_usage = if edge_weight.is_start {
             data.starts[edge_weight.index].max_number
         } else {
-            data.rakken[edge_weight.index].max_number
+            data.rules.max_sailings(data.rakken[edge_weight.index].max_number)
         };
         
-        if state.edges_used[edge_index] >= max_usage as u8 {
+        if state.edges_used[edge_index] >= max_usage {
+            collector.pruned().leg_limit += 1;
             continue; // Skip this edge if it's been used too many times
         }
         
-        // Estimate performance for this leg
-        let performance = estimate_leg_performance(data, state.current_point, target_point, state.current_time);
-        let speed = performance.estimated_speed;
-        let distance = edge_weight.distance;
-        
-        // Calculate time to traverse this edge
-        let travel_time = if speed > 0.0 {
-            distance / speed // distance in nm, speed in knots, result in hours
-        } else {
-            // If speed is 0 (shouldn't happen but safety check), use a default slow speed
-            distance / 1.0 // 1 knot as fallback
-        };
-        
-        let end_time = state.current_time + travel_time;
+        // Skip legs completing a sequence of buoys the rules forbid
+        if completes_forbidden_sequence(data, state.current_point, &state.current_steps, target_point) {
+            collector.pruned().forbidden_sequence += 1;
+            continue;
+        }
         
-        // Create the step
-        let step = Step {
-            from: state.current_point,
-            to: target_point,
-            distance,
-            speed,
-            start_time: state.current_time,
-            end_time,
+        // Estimate performance for this leg, possibly after a wait; the
+        // searches pass by buoys without coordinates
+        let distance = edge_weight.distance;
+        let Ok(step) = sail_leg(data, state.current_point, target_point, distance, state.current_time, options.max_wait) else {
+            collector.pruned().no_estimate += 1;
+            continue;
         };
         
-        // Update the path and edge usage
-        let mut new_steps = state.current_steps.clone();
-        new_steps.push(step);
-        
-        let mut new_edges_used = state.edges_used.clone();
-        new_edges_used[edge_index] += 1;
+        // Sail the leg, explore on from its end, and undo it again
+        let checkpoint = state.advance(step, edge_index, &options.pins);
+        let explored = explore_paths_recursive(data, graph, state, collector, options);
+        state.retreat(checkpoint);
+        explored?;
         
-        // Create new state for recursive call
-        let new_state = PathExplorationState {
-            current_point: target_point,
-            current_time: end_time,
-            remaining_steps: state.remaining_steps - 1,
-            current_steps: new_steps,
-            edges_used: new_edges_used,
-            total_distance: state.total_distance + distance,
-        };
-        
-        // Continue exploring recursively
-        explore_paths_recursive(data, graph, new_state, all_paths, max_paths)?;
+        // Stop the whole search once the limit is exceeded or the budget used up
+        if collector.truncated() {
+            return Ok(());
+        }
     }
     
     Ok(())
 }
 
-/// Internal state for target path exploration with Rak usage tracking
-struct TargetPathExplorationState {
-    current_point: usize,
-    target_point: usize,
-    current_time: f64,
-    remaining_steps: usize,
-    current_steps: Vec<Step>,
-    edges_used: Vec<u8>,
-    rak_usage: Vec<u8>,  // Track Rak usage (max 2 per Rak)
-    total_distance: f64,
+/// Explore paths from a starting point to a specific target
+pub fn explore_target_paths(
+    data: &RegattaData,
+    start_point: usize,    // index of the starting buoy
+    target_point: usize,   // index of the target buoy
+    start_time: f64,       // time in hours since race start
+    max_steps: usize,      // maximum number of steps to explore
+    options: &SearchOptions, // path limit, constraints, and monitoring
+) -> Result<Vec<Path>, UursError> {
+    search_target_paths(data, start_point, target_point, start_time, max_steps, options).map(|results| results.paths(data))
 }
 
-/// Explore paths from a starting point to a specific target with Rak usage tracking
-pub fn explore_target_paths(
+/// Explore the paths to a target like [`explore_target_paths`], telling
+/// whether the path limit truncated the search
+pub fn search_target_paths(
     data: &RegattaData,
     start_point: usize,    // index of the starting buoy
     target_point: usize,   // index of the target buoy
     start_time: f64,       // time in hours since race start
     max_steps: usize,      // maximum number of steps to explore
-    max_paths: Option<usize>, // maximum number of paths to return
-) -> Result<Vec<Path>, Box<dyn std::error::Error>> {
+    options: &SearchOptions, // path limit, constraints, and monitoring
+) -> Result<SearchResults, UursError> {
     // Build the regatta graph
     let (graph, _node_indices) = build_regatta_graph(data);
     
     if start_point >= data.boeien.len() {
-        return Err(format!("Invalid start point index: {start_point}").into());
+        return Err(UursError::UnknownBuoy(start_point.to_string()));
     }
     
     if target_point >= data.boeien.len() {
-        return Err(format!("Invalid target point index: {target_point}").into());
+        return Err(UursError::UnknownBuoy(target_point.to_string()));
     }
     
-    let mut all_paths = Vec::new();
-    let initial_edges_used = vec![0u8; data.starts.len() + data.rakken.len()];
-    let initial_rak_usage = vec![0u8; data.rakken.len()];  // Track Rak usage separately
+    let started = Instant::now();
+    let mut collector = PathCollector::new(options);
     
     // Start the recursive exploration
-    let initial_state = TargetPathExplorationState {
-        current_point: start_point,
-        target_point,
-        current_time: start_time,
-        remaining_steps: max_steps,
-        current_steps: Vec::new(),
-        edges_used: initial_edges_used,
-        rak_usage: initial_rak_usage,
-        total_distance: 0.0,
-    };
+    let mut state = PathExplorationState::new(data, start_point, start_time, max_steps, &options.pins);
+    explore_target_paths_recursive(data, &graph, &mut state, target_point, &mut collector, options)?;
     
-    explore_target_paths_recursive(data, &graph, initial_state, &mut all_paths, max_paths.unwrap_or(usize::MAX))?;
-    
-    Ok(all_paths)
+    Ok(collector.into_results(started))
 }
 
-/// Recursive helper function for target path exploration with Rak usage tracking
+/// Recursive helper function for target path exploration
 fn explore_target_paths_recursive(
     data: &RegattaData,
     graph: &petgraph::Graph<Option<String>, crate::data::RegattaEdge>,
-    state: TargetPathExplorationState,
-    all_paths: &mut Vec<Path>,
-    max_paths: usize,
-) -> Result<(), Box<dyn std::error::Error>> {
+    state: &mut PathExplorationState,
+    target_point: usize,
+    collector: &mut PathCollector,
+    options: &SearchOptions,
+) -> Result<(), UursError> {
+    if let Some(monitor) = options.monitor {
+        monitor.visit_node()?;
+    }
+    if !collector.expand() {
+        return Ok(());
+    }
+    
     // If we reached the target, save the current path
-    if state.current_point == state.target_point {
-        all_paths.push(Path {
-            steps: state.current_steps,
-            total_distance: state.total_distance,
-            end_time: state.current_time,
-        });
-        // Exit early if we've reached the maximum number of paths
-        if all_paths.len() >= max_paths {
-            return Ok(());
-        }
+    if state.current_point == target_point {
+        collector.add(data, state, options);
         return Ok(());
     }
     
@@ -312,6 +1461,12 @@ fn explore_target_paths_recursive(
         return Ok(());
     }
     
+    // Give up on branches which can't meet the pins any more
+    if !state.pins_reachable(&options.pins) {
+        collector.pruned().pins += 1;
+        return Ok(());
+    }
+    
     // Convert current_point to NodeIndex
     let current_node = petgraph::graph::NodeIndex::new(state.current_point);
     
@@ -319,7 +1474,13 @@ fn explore_target_paths_recursive(
     for edge_ref in graph.edges(current_node) {
         let edge_weight = edge_ref.weight();
         let target_node = edge_ref.target();
-        let target_point = target_node.index();
+        let next_point = target_node.index();
+        
+        // Skip buoys and legs excluded by the search constraints
+        if !options.constraints.allows(state.current_point, next_point) {
+            collector.pruned().constraints += 1;
+            continue;
+        }
         
         // Check if we can use this edge (based on max_number constraint)
         let edge_index = if edge_weight.is_start {
@@ -333,80 +1494,425 @@ fn explore_target_paths_recursive(
         let max_usage = if edge_weight.is_start {
             data.starts[edge_weight.index].max_number
         } else {
-            data.rakken[edge_weight.index].max_number
+            data.rules.max_sailings(data.rakken[edge_weight.index].max_number)
         };
         
-        if state.edges_used[edge_index] >= max_usage as u8 {
+        if state.edges_used[edge_index] >= max_usage {
+            collector.pruned().leg_limit += 1;
             continue; // Skip this edge if it's been used too many times
         }
         
-        // For Rak edges, check if this Rak has been used more than twice
-        if !edge_weight.is_start {
-            let rak_index = edge_weight.index;
-            if state.rak_usage[rak_index] >= 2 {
-                continue; // Skip this Rak if it's been used twice already
-            }
+        // Skip legs completing a sequence of buoys the rules forbid
+        if completes_forbidden_sequence(data, state.current_point, &state.current_steps, next_point) {
+            collector.pruned().forbidden_sequence += 1;
+            continue;
         }
         
-        // Estimate performance for this leg
-        let performance = estimate_leg_performance(data, state.current_point, target_point, state.current_time);
-        let speed = performance.estimated_speed;
+        // Estimate performance for this leg, possibly after a wait; the
+        // searches pass by buoys without coordinates
         let distance = edge_weight.distance;
-        
-        // Calculate time to traverse this edge
-        let travel_time = if speed > 0.0 {
-            distance / speed // distance in nm, speed in knots, result in hours
-        } else {
-            // If speed is 0 (shouldn't happen but safety check), use a default slow speed
-            distance / 1.0 // 1 knot as fallback
-        };
-        
-        let end_time = state.current_time + travel_time;
-        
-        // Create the step
-        let step = Step {
-            from: state.current_point,
-            to: target_point,
-            distance,
-            speed,
-            start_time: state.current_time,
-            end_time,
+        let Ok(step) = sail_leg(data, state.current_point, next_point, distance, state.current_time, options.max_wait) else {
+            collector.pruned().no_estimate += 1;
+            continue;
         };
         
-        // Update the path and edge usage
-        let mut new_steps = state.current_steps.clone();
-        new_steps.push(step);
-        
-        let mut new_edges_used = state.edges_used.clone();
-        new_edges_used[edge_index] += 1;
+        // Sail the leg, explore on from its end, and undo it again
+        let checkpoint = state.advance(step, edge_index, &options.pins);
+        let explored = explore_target_paths_recursive(data, graph, state, target_point, collector, options);
+        state.retreat(checkpoint);
+        explored?;
         
-        // Update Rak usage if this is a Rak edge
-        let mut new_rak_usage = state.rak_usage.clone();
-        if !edge_weight.is_start {
-            let rak_index = edge_weight.index;
-            new_rak_usage[rak_index] += 1;
-        }
-        
-        // Create new state for recursive call
-        let new_state = TargetPathExplorationState {
-            current_point: target_point,
-            target_point: state.target_point,
-            current_time: end_time,
-            remaining_steps: state.remaining_steps - 1,
-            current_steps: new_steps,
-            edges_used: new_edges_used,
-            rak_usage: new_rak_usage,
-            total_distance: state.total_distance + distance,
-        };
-        
-        // Continue exploring recursively
-        explore_target_paths_recursive(data, graph, new_state, all_paths, max_paths)?;
-        
-        // Exit early if we've reached the maximum number of paths
-        if all_paths.len() >= max_paths {
+        // Stop the whole search once the limit is exceeded or the budget used up
+        if collector.truncated() {
             return Ok(());
         }
     }
     
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::load_regatta_data;
+
+    #[test]
+    fn test_validate_route() {
+        let mut data = load_regatta_data().unwrap();
+        let index = |name: &str| data.get_boei_index(name).unwrap();
+        let (oeve, wv12, gvs3) = (index("OEVE"), index("WV12"), index("GVS3-M8"));
+        let none = SearchConstraints::default();
+
+        assert!(validate_route(&data, &[oeve, wv12, gvs3, wv12], &none).is_ok());
+        // The leg between WV12 and GVS3-M8 may be sailed twice in total
+        assert!(matches!(
+            validate_route(&data, &[oeve, wv12, gvs3, wv12, gvs3], &none),
+            Err(UursError::LegOverused { max_number: 2, .. })
+        ));
+        assert!(matches!(
+            validate_route(&data, &[wv12, oeve], &none),
+            Err(UursError::NoLeg { .. })
+        ));
+        let avoid = SearchConstraints {
+            forbidden_buoys: vec![gvs3],
+            ..SearchConstraints::default()
+        };
+        assert!(validate_route(&data, &[oeve, wv12, gvs3], &avoid).is_err());
+
+        // Avoid lists split the legs at the dash between known buoys
+        let t11 = data.get_boei_index("T11-GVS2").unwrap();
+        let avoid = SearchConstraints::from_avoid_lists(&data, Some("GVS3-M8"), Some("T11-GVS2-GVS3-M8, OEVE-WV12")).unwrap();
+        assert_eq!(avoid.forbidden_buoys, [gvs3]);
+        assert_eq!(avoid.forbidden_legs, [(t11, gvs3), (oeve, wv12)]);
+        assert!(matches!(
+            SearchConstraints::from_avoid_lists(&data, None, Some("WV12-NOWHERE")),
+            Err(UursError::InvalidParameter(_))
+        ));
+
+        // Turning straight back at GVS3-M8 is forbidden by the rules of some editions
+        data.rules.forbidden_sequences = vec![vec!["WV12".to_string(), "GVS3-M8".to_string(), "WV12".to_string()]];
+        assert!(matches!(
+            validate_route(&data, &[oeve, wv12, gvs3, wv12], &none),
+            Err(UursError::ForbiddenSequence(_))
+        ));
+        let paths = explore_paths(&data, oeve, 0.0, 3, &SearchOptions::default()).unwrap();
+        assert!(!paths.is_empty());
+        assert!(paths.iter().all(|path| path.steps[1..].iter().all(|step| step.from != gvs3 || step.to != wv12)));
+    }
+
+    #[test]
+    fn test_qualify_objective() {
+        let mut data = load_regatta_data().unwrap();
+        let oeve = data.get_boei_index("OEVE").unwrap();
+        data.rules.qualification_distance = Some(18.0);
+        let mut paths = explore_paths(&data, oeve, 0.0, 3, &SearchOptions::default()).unwrap();
+        assert!(paths.iter().any(|path| path.qualifies()) && paths.iter().any(|path| !path.qualifies()));
+
+        // The qualifying path finishing earliest comes first
+        PlanObjective::Qualify.sort_paths(&data, &mut paths);
+        let best = &paths[0];
+        assert!(best.qualification_margin.unwrap() >= 0.0);
+        assert!(paths.iter().filter(|path| path.qualifies()).all(|path| path.end_time >= best.end_time));
+        let last = paths.iter().rposition(|path| path.qualifies()).unwrap();
+        assert!(paths[..=last].iter().all(|path| path.qualifies()));
+    }
+
+    #[test]
+    fn test_tie_break() {
+        let data = load_regatta_data().unwrap();
+        let oeve = data.get_boei_index("OEVE").unwrap();
+        let mut paths = explore_paths(&data, oeve, 0.0, 2, &SearchOptions::default()).unwrap();
+        let route = |path: &Path| path.steps.iter().map(|step| data.boeien[step.to].name.clone()).collect::<Vec<_>>();
+
+        // Whatever order the search found them in, the paths come out in the same order
+        let mut reversed = paths.clone();
+        reversed.reverse();
+        for objective in [PlanObjective::EarliestArrival, PlanObjective::MaxDistance] {
+            objective.sort_paths(&data, &mut paths);
+            objective.sort_paths(&data, &mut reversed);
+            assert_eq!(paths.iter().map(route).collect::<Vec<_>>(), reversed.iter().map(route).collect::<Vec<_>>());
+        }
+
+        // Paths as fast and as long as each other are ordered by the buoys they round
+        for path in &mut paths {
+            path.end_time = 2.0;
+            path.total_distance = 10.0;
+        }
+        PlanObjective::EarliestArrival.sort_paths(&data, &mut paths);
+        assert!(paths.windows(2).all(|pair| route(&pair[0]) <= route(&pair[1])));
+
+        // The same legs with other departures are ordered too, even if one is NaN
+        let mut later = paths[0].clone();
+        later.steps[0].start_time += 0.25;
+        assert_eq!(tie_break(&data, &paths[0], &later), std::cmp::Ordering::Less);
+        later.steps[0].start_time = f64::NAN;
+        assert_ne!(tie_break(&data, &paths[0], &later), std::cmp::Ordering::Equal);
+        assert_eq!(tie_break(&data, &later, &later), std::cmp::Ordering::Equal);
+    }
+
+    #[test]
+    fn test_route_objectives() {
+        let mut data = load_regatta_data().unwrap();
+        // A deadline some paths of the search miss
+        data.rules.scoring.deadline = 21.0;
+        let oeve = data.get_boei_index("OEVE").unwrap();
+        let results = search_paths(&data, oeve, 20.0, 3, &SearchOptions::default()).unwrap();
+
+        // The objectives are selected by the names they go by
+        for name in ["earliest_arrival", "max_distance", "qualify", "max_margin", "weighted:scored_distance=1,margin=-0.5"] {
+            assert_eq!(PlanObjective::from_name(name).unwrap().name(), name);
+        }
+        for name in ["fastest", "weighted:", "weighted:scored_distance", "weighted:depth=1", "weighted:margin=x"] {
+            assert!(PlanObjective::from_name(name).is_none());
+        }
+
+        // Paths ending after the deadline are not accepted for the margin to it
+        let late = results.paths(&data).into_iter().filter(|path| path.end_time > 21.0).count();
+        assert!(late > 0);
+        assert_eq!(results.best(&data, &PlanObjective::MaxMargin, usize::MAX).len(), results.len() - late);
+
+        // Weighting only the miles scored ranks like the maximum distance
+        let weighted = PlanObjective::from_name("weighted:scored_distance=1").unwrap();
+        let route = |path: &Path| path.steps.iter().map(|step| step.to).collect::<Vec<_>>();
+        let best = |objective: &dyn RouteObjective| results.best(&data, objective, 5).iter().map(route).collect::<Vec<_>>();
+        assert_eq!(best(&weighted), best(&PlanObjective::MaxDistance));
+
+        // An objective of a library user ranks the paths of a selection
+        #[derive(Debug)]
+        struct AvoidBuoy(usize);
+        impl RouteObjective for AvoidBuoy {
+            fn name(&self) -> String {
+                "avoid_buoy".to_string()
+            }
+            fn compare(&self, data: &RegattaData, a: &Path, b: &Path) -> std::cmp::Ordering {
+                PlanObjective::MaxDistance.compare(data, a, b)
+            }
+            fn accepts(&self, _data: &RegattaData, path: &Path) -> bool {
+                path.steps.iter().all(|step| step.to != self.0)
+            }
+        }
+        let first = results.best(&data, &PlanObjective::MaxDistance, 1)[0].steps[1].to;
+        let selection = PathSelection { objective: Arc::new(AvoidBuoy(first)), ..PathSelection::default() };
+        let selected = results.select(&data, &selection);
+        assert!(!selected.is_empty());
+        assert!(selected.iter().all(|path| path.steps.iter().all(|step| step.to != first)));
+    }
+
+    #[test]
+    fn test_wait_for_wind() {
+        let mut data = load_regatta_data().unwrap();
+        // A calm in the first hour, the wind fills in after it. The boat
+        // doesn't move in less than 8 knots of wind but drifts at the 1 knot
+        // of the fallback.
+        let calm: Vec<_> = (0..2)
+            .map(|hour| WindCondition { wind_speed: 0.0, ..data.wind_data.get_wind_at_hour(hour).unwrap().clone() })
+            .collect();
+        data.wind_data.override_hours(&calm).unwrap();
+        for speeds in &mut data.polar_data.boat_speeds {
+            speeds[0] = 0.0;
+        }
+        let (oeve, wv12) = (data.get_boei_index("OEVE").unwrap(), data.get_boei_index("WV12").unwrap());
+
+        let sail_on = explore_target_paths(&data, oeve, wv12, 0.0, 1, &SearchOptions::default()).unwrap();
+        assert_eq!(sail_on[0].steps[0].wait, 0.0);
+
+        let options = SearchOptions {
+            max_wait: 2.0,
+            ..SearchOptions::default()
+        };
+        let waited = explore_target_paths(&data, oeve, wv12, 0.0, 1, &options).unwrap();
+        let step = &waited[0].steps[0];
+        assert!(step.wait > 0.0);
+        assert_eq!(step.start_time, step.wait);
+        assert!(waited[0].end_time < sail_on[0].end_time);
+    }
+
+    #[test]
+    fn test_path_limit() {
+        let data = load_regatta_data().unwrap();
+        let oeve = data.get_boei_index("OEVE").unwrap();
+        let all = search_paths(&data, oeve, 0.0, 3, &SearchOptions::default()).unwrap();
+        assert!(!all.truncated);
+        assert!(all.len() > 5);
+
+        // The search stops at the limit, across all branches
+        let limited = |max_paths| SearchOptions {
+            max_paths: Some(max_paths),
+            ..SearchOptions::default()
+        };
+        let results = search_paths(&data, oeve, 0.0, 3, &limited(5)).unwrap();
+        assert!(results.truncated);
+        assert_eq!(results.len(), 5);
+
+        // A limit of exactly all paths truncates nothing
+        let results = search_paths(&data, oeve, 0.0, 3, &limited(all.len())).unwrap();
+        assert!(!results.truncated);
+        assert_eq!(results.len(), all.len());
+    }
+
+    #[test]
+    fn test_pins() {
+        let data = load_regatta_data().unwrap();
+        let oeve = data.get_boei_index("OEVE").unwrap();
+        let all = search_paths(&data, oeve, 0.0, 4, &SearchOptions::default()).unwrap().paths(&data);
+        let example = &all[all.len() / 2].steps;
+        let pinned = |pins: Vec<Pin>| {
+            let options = SearchOptions { pins, ..SearchOptions::default() };
+            search_paths(&data, oeve, 0.0, 4, &options).unwrap().paths(&data)
+        };
+
+        // A buoy at a place of the route, then the leg after it around a time:
+        // the paths found are exactly the ones of the search without pins
+        // meeting them
+        let at_slot = Pin { buoy: example[1].to, slot: Some(2), ..Pin::default() };
+        let leg = Pin {
+            buoy: example[3].to,
+            from: Some(example[3].from),
+            time: Some(example[3].end_time),
+            tolerance: 0.1,
+            ..Pin::default()
+        };
+        let found = pinned(vec![at_slot, leg]);
+        let expected = all.iter().filter(|path| {
+            let steps = &path.steps;
+            steps[1].to == example[1].to
+                && steps[2..].iter().any(|step| {
+                    (step.from, step.to) == (example[3].from, example[3].to)
+                        && (step.end_time - example[3].end_time).abs() <= 0.1
+                })
+        });
+        assert!(!found.is_empty());
+        assert_eq!(found.len(), expected.count());
+
+        // Pins out of reach of the search find nothing
+        assert!(pinned(vec![Pin { buoy: example[1].to, slot: Some(5), ..Pin::default() }]).is_empty());
+        assert!(pinned(vec![Pin { buoy: example[1].to, time: Some(-1.0), ..Pin::default() }]).is_empty());
+
+        // The start meets a pin of the first place
+        assert_eq!(pinned(vec![Pin { buoy: oeve, slot: Some(0), ..Pin::default() }]).len(), all.len());
+    }
+
+    #[test]
+    fn test_search_stats() {
+        let data = load_regatta_data().unwrap();
+        let index = |name: &str| data.get_boei_index(name).unwrap();
+        let options = SearchOptions {
+            constraints: SearchConstraints { forbidden_buoys: vec![index("GVS3-M8")], ..Default::default() },
+            pins: vec![Pin { buoy: index("LC1"), slot: Some(2), ..Pin::default() }],
+            ..SearchOptions::default()
+        };
+        let stats = search_paths(&data, index("OEVE"), 0.0, 4, &options).unwrap().stats;
+        assert!(stats.nodes_expanded > 0 && stats.elapsed_ms >= 0.0);
+        assert!(stats.pruned.constraints > 0 && stats.pruned.pins > 0);
+        // Exhaustive searches look nothing up
+        assert_eq!(stats.cache_hit_rate(), None);
+    }
+
+    #[test]
+    fn test_search_budget() {
+        let data = load_regatta_data().unwrap();
+        let oeve = data.get_boei_index("OEVE").unwrap();
+        let all = search_paths(&data, oeve, 0.0, 3, &SearchOptions::default()).unwrap();
+        assert_eq!(all.stats.exhausted, None);
+        assert_eq!(all.stats.resident_paths, all.len());
+        let budgeted = |budget| SearchOptions {
+            budget,
+            ..SearchOptions::default()
+        };
+
+        // Each part of the budget stops the search with the paths found so far
+        let nodes = SearchBudget { max_nodes: 10, ..SearchBudget::default() };
+        let results = search_paths(&data, oeve, 0.0, 3, &budgeted(nodes)).unwrap();
+        assert!(results.truncated && results.len() < all.len());
+        assert_eq!(results.stats.nodes_expanded, 10);
+        assert_eq!(results.stats.exhausted, Some(BudgetLimit::Nodes));
+        let paths = SearchBudget { max_paths: 3, ..SearchBudget::default() };
+        let results = search_paths(&data, oeve, 0.0, 3, &budgeted(paths)).unwrap();
+        assert_eq!((results.len(), results.stats.exhausted), (3, Some(BudgetLimit::Paths)));
+        let memory = SearchBudget { max_memory: all.stats.memory_estimate / 2, ..SearchBudget::default() };
+        let results = search_paths(&data, oeve, 0.0, 3, &budgeted(memory)).unwrap();
+        assert_eq!(results.stats.exhausted, Some(BudgetLimit::Memory));
+        assert!(results.stats.memory_estimate >= memory.max_memory && results.len() < all.len());
+
+        // A budget the search doesn't use up leaves it complete
+        let ample = SearchBudget { max_nodes: all.stats.nodes_expanded, ..SearchBudget::default() };
+        assert!(!search_paths(&data, oeve, 0.0, 3, &budgeted(ample)).unwrap().truncated);
+    }
+
+    #[test]
+    fn test_compact_results() {
+        let data = load_regatta_data().unwrap();
+        let oeve = data.get_boei_index("OEVE").unwrap();
+        let results = search_paths(&data, oeve, 0.0, 4, &SearchOptions::default()).unwrap();
+        let paths = results.paths(&data);
+        assert_eq!(paths.len(), results.len());
+
+        // The paths share their first steps in the arena
+        let steps: usize = paths.iter().map(|path| path.steps.len()).sum();
+        assert!(results.arena.len() < steps);
+        for path in &paths {
+            assert_eq!(path.steps.len(), 4);
+            assert_eq!(path.steps[0].from, oeve);
+            assert!(path.steps.windows(2).all(|pair| pair[0].to == pair[1].from));
+        }
+
+        // The best paths are the first ones sorted by the objective
+        let mut sorted = paths.clone();
+        let objective = PlanObjective::MaxDistance;
+        objective.sort_paths(&data, &mut sorted);
+        let best = results.best(&data, &objective, 3);
+        assert_eq!(best.len(), 3);
+        for (best, sorted) in best.iter().zip(&sorted) {
+            assert_eq!(best.end_time, sorted.end_time);
+            assert_eq!(best.total_distance, sorted.total_distance);
+        }
+    }
+
+    #[test]
+    fn test_diverse_selection() {
+        let data = load_regatta_data().unwrap();
+        let oeve = data.get_boei_index("OEVE").unwrap();
+        let results = search_paths(&data, oeve, 0.0, 4, &SearchOptions::default()).unwrap();
+        let selection = |min_distance| PathSelection {
+            objective: Arc::new(PlanObjective::MaxDistance),
+            routes: Some(4),
+            min_distance,
+        };
+
+        // Without a distance the selection is the best paths
+        let best = results.best(&data, &PlanObjective::MaxDistance, 4);
+        let same = results.select(&data, &selection(0.0));
+        assert_eq!(same.len(), 4);
+        assert!(same.iter().zip(&best).all(|(a, b)| a.end_time == b.end_time));
+
+        // The best path comes first, the others share at most half the legs
+        // with any better one
+        let diverse = results.select(&data, &selection(0.5));
+        assert!(diverse.len() > 1);
+        assert_eq!(diverse[0].scored_distance, best[0].scored_distance);
+        for (i, path) in diverse.iter().enumerate() {
+            assert!(diverse[..i].iter().all(|better| better.leg_distance(path) >= 0.5));
+        }
+        assert_eq!(diverse[0].leg_distance(&diverse[0]), 0.0);
+        assert!(best.windows(2).any(|pair| pair[0].leg_distance(&pair[1]) < 0.5));
+    }
+
+    #[test]
+    fn test_buoy_without_coordinates() {
+        let mut data = load_regatta_data().unwrap();
+        let index = |name: &str| data.get_boei_index(name).unwrap();
+        let (oeve, wv12, gvs3) = (index("OEVE"), index("WV12"), index("GVS3-M8"));
+        data.boeien[gvs3].lat = None;
+        assert_eq!(data.buoys_without_coordinates(), ["GVS3-M8"]);
+
+        assert!(matches!(
+            estimate_leg_performance(&data, wv12, gvs3, 0.0),
+            Err(UursError::MissingCoordinates(name)) if name == "GVS3-M8"
+        ));
+        assert!(matches!(evaluate_route(&data, &[oeve, wv12, gvs3], 0.0), Err(UursError::MissingCoordinates(_))));
+
+        // The searches pass the buoy by
+        let paths = explore_paths(&data, oeve, 0.0, 2, &SearchOptions::default()).unwrap();
+        assert!(!paths.is_empty());
+        assert!(paths.iter().all(|path| path.steps.iter().all(|step| step.to != gvs3)));
+        assert!(explore_target_paths(&data, oeve, gvs3, 0.0, 2, &SearchOptions::default()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_large_max_numbers() {
+        let mut data = load_regatta_data().unwrap();
+        let oeve = data.get_boei_index("OEVE").unwrap();
+
+        // 256 sailings of the start, which a counter of a byte would take for none
+        data.starts[0].max_number = 256;
+        data.rules.limits.max_path_length = 300;
+        assert_eq!(explore_paths(&data, oeve, 0.0, 1, &SearchOptions::default()).unwrap().len(), 1);
+
+        // Routes longer than the limit of the rules are refused
+        data.rules.limits.max_path_length = 2;
+        let wv12 = data.get_boei_index("WV12").unwrap();
+        let gvs3 = data.get_boei_index("GVS3-M8").unwrap();
+        assert!(evaluate_route(&data, &[oeve, wv12, gvs3], 0.0).is_ok());
+        assert!(evaluate_route(&data, &[oeve, wv12, gvs3, wv12], 0.0).is_err());
+    }
+}
@@ -0,0 +1,152 @@
+use crate::data::{RaceTime, RegattaData};
+use crate::error::UursError;
+use serde::{Deserialize, Serialize};
+
+/// Limits of the search parameters users may give, on the command line and
+/// to the server alike
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ParamLimits {
+    pub max_steps: usize, // legs a search may look ahead
+    pub max_paths: usize, // paths a search may return
+    pub max_path_length: usize, // legs a route may have, and a leg may be sailed at most
+}
+
+impl Default for ParamLimits {
+    fn default() -> Self {
+        Self {
+            max_steps: 10,
+            max_paths: 100_000,
+            max_path_length: 200,
+        }
+    }
+}
+
+/// A time within the race, from the start to the deadline of the rules, in
+/// hours since the start
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RaceHours(f64);
+
+impl RaceHours {
+    /// Check hours since the start against the race of the data
+    pub fn new(data: &RegattaData, hours: f64) -> Result<Self, UursError> {
+        let deadline = data.rules.scoring.deadline;
+        if !(0.0..=deadline).contains(&hours) {
+            return Err(UursError::InvalidParameter(format!(
+                "Time must be between 0 and {deadline} hours"
+            )));
+        }
+        Ok(Self(hours))
+    }
+
+    /// Resolve a time as a user gives it and check it
+    pub fn resolve(data: &RegattaData, time: &RaceTime) -> Result<Self, UursError> {
+        Self::new(data, data.race_clock.resolve(time)?)
+    }
+
+    pub fn hours(self) -> f64 {
+        self.0
+    }
+}
+
+/// Longest wait at a buoy before sailing a leg, from none to the deadline of
+/// the rules, in hours
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WaitHours(f64);
+
+impl WaitHours {
+    pub fn new(data: &RegattaData, hours: f64) -> Result<Self, UursError> {
+        let deadline = data.rules.scoring.deadline;
+        if !(0.0..=deadline).contains(&hours) {
+            return Err(UursError::InvalidParameter(format!(
+                "Maximum wait must be between 0 and {deadline} hours"
+            )));
+        }
+        Ok(Self(hours))
+    }
+
+    pub fn hours(self) -> f64 {
+        self.0
+    }
+}
+
+/// Number of legs a search looks ahead, at least one and at most the limit
+/// of the rules
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StepCount(usize);
+
+impl StepCount {
+    pub fn new(data: &RegattaData, steps: usize) -> Result<Self, UursError> {
+        check_count(steps, data.rules.limits.max_steps, "Number of steps").map(Self)
+    }
+
+    pub fn get(self) -> usize {
+        self.0
+    }
+}
+
+/// Number of paths a search returns, at least one and at most the limit of
+/// the rules
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MaxPaths(usize);
+
+impl MaxPaths {
+    pub fn new(data: &RegattaData, max_paths: usize) -> Result<Self, UursError> {
+        check_count(max_paths, data.rules.limits.max_paths, "Maximum number of paths").map(Self)
+    }
+
+    /// Check an optional maximum, `None` returns all paths
+    pub fn optional(data: &RegattaData, max_paths: Option<usize>) -> Result<Option<usize>, UursError> {
+        max_paths.map(|max_paths| Self::new(data, max_paths).map(Self::get)).transpose()
+    }
+
+    pub fn get(self) -> usize {
+        self.0
+    }
+}
+
+/// Check the number of legs of a route against the limit of the rules, which
+/// bounds routes given by users as well as the races planned leg by leg
+pub fn check_route_length(data: &RegattaData, legs: usize) -> Result<(), UursError> {
+    let max = data.rules.limits.max_path_length;
+    if legs > max {
+        return Err(UursError::InvalidParameter(format!("A route may have at most {max} legs")));
+    }
+    Ok(())
+}
+
+fn check_count(value: usize, max: usize, what: &str) -> Result<usize, UursError> {
+    if value == 0 || value > max {
+        return Err(UursError::InvalidParameter(format!("{what} must be between 1 and {max}")));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_of_the_rules() {
+        let mut data = crate::data::load_regatta_data().unwrap();
+        assert_eq!(StepCount::new(&data, 10).unwrap().get(), 10);
+        assert!(StepCount::new(&data, 0).is_err());
+        assert!(StepCount::new(&data, 11).is_err());
+        assert_eq!(MaxPaths::optional(&data, None).unwrap(), None);
+        assert!(MaxPaths::optional(&data, Some(100_001)).is_err());
+        assert_eq!(RaceHours::resolve(&data, &"19:00".parse().unwrap()).unwrap().hours(), 1.0);
+        assert!(RaceHours::new(&data, 24.5).is_err());
+        assert_eq!(WaitHours::new(&data, 2.0).unwrap().hours(), 2.0);
+        assert!(WaitHours::new(&data, -1.0).is_err());
+        assert!(WaitHours::new(&data, 1e12).is_err());
+        assert!(WaitHours::new(&data, f64::NAN).is_err());
+        assert!(check_route_length(&data, 200).is_ok());
+        assert!(check_route_length(&data, 201).is_err());
+
+        // A rule set with other limits
+        data.rules.limits.max_steps = 4;
+        data.rules.scoring.deadline = 30.0;
+        assert!(StepCount::new(&data, 5).is_err());
+        assert!(RaceHours::new(&data, 24.5).is_ok());
+    }
+}
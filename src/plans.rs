@@ -0,0 +1,135 @@
+use crate::error::UursError;
+use crate::storage::{JsonFiles, MemoryStorage, Storage};
+use chrono::{DateTime, Utc};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Characters of the ids, without the ones easily mistaken for each other
+/// when a link is read out or typed on a phone
+const ID_ALPHABET: &[u8] = b"23456789abcdefghjkmnpqrstuvwxyz";
+/// Length of the ids, long enough that the links can't be guessed
+const ID_LENGTH: usize = 8;
+
+/// A route saved to be shared, as the navigator planned it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlanInput {
+    pub name: String,
+    pub route: Vec<String>, // buoys in the order they are sailed
+    pub start_time: f64,    // in hours since race start
+    pub boat: Option<String>, // boat of the fleet whose polars the plan was made with
+    #[serde(default)]
+    pub notes: String,
+}
+
+/// A saved plan with the short id of its link
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SavedPlan {
+    pub id: String,
+    #[serde(flatten)]
+    pub input: PlanInput,
+    pub created: DateTime<Utc>,
+}
+
+/// All saved plans, with every new one handed to a storage
+#[derive(Debug)]
+pub struct PlanStore {
+    storage: Arc<dyn Storage>,
+    plans: Mutex<BTreeMap<String, SavedPlan>>,
+}
+
+impl Default for PlanStore {
+    fn default() -> Self {
+        Self {
+            storage: Arc::new(MemoryStorage::new()),
+            plans: Mutex::default(),
+        }
+    }
+}
+
+impl PlanStore {
+    /// Create an empty store which is only kept in memory
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a plans file, starting empty if it doesn't exist yet
+    pub fn open(path: &Path) -> Result<Self, UursError> {
+        let storage = JsonFiles {
+            plans_file: Some(path.to_path_buf()),
+            ..JsonFiles::default()
+        };
+        Self::with_storage(Arc::new(storage))
+    }
+
+    /// Load the plans of a storage and keep saving new ones to it
+    pub fn with_storage(storage: Arc<dyn Storage>) -> Result<Self, UursError> {
+        let plans = storage.load_plans()?.into_iter().map(|p| (p.id.clone(), p)).collect();
+        Ok(Self {
+            storage,
+            plans: Mutex::new(plans),
+        })
+    }
+
+    /// Get a plan by id
+    pub fn get(&self, id: &str) -> Option<SavedPlan> {
+        self.plans.lock().unwrap().get(id).cloned()
+    }
+
+    /// Save a plan under a new random id and return it
+    pub fn save(&self, input: PlanInput) -> Result<SavedPlan, UursError> {
+        let mut plans = self.plans.lock().unwrap();
+        let mut rng = rand::rng();
+        let id = loop {
+            let id: String = (0..ID_LENGTH)
+                .map(|_| ID_ALPHABET[rng.random_range(0..ID_ALPHABET.len())] as char)
+                .collect();
+            if !plans.contains_key(&id) {
+                break id;
+            }
+        };
+        let plan = SavedPlan {
+            id,
+            input,
+            created: Utc::now(),
+        };
+        self.storage.save_plan(&plan)?;
+        plans.insert(plan.id.clone(), plan.clone());
+        Ok(plan)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plans_persist() {
+        let path = std::env::temp_dir().join(format!("uurs24-plans-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let input = PlanInput {
+            name: "Night route".to_string(),
+            route: vec!["OEVE".to_string(), "WV12".to_string()],
+            start_time: 1.5,
+            boat: None,
+            notes: "Reef before midnight".to_string(),
+        };
+        let store = PlanStore::open(&path).unwrap();
+        let plan = store.save(input.clone()).unwrap();
+        assert_eq!(plan.id.len(), ID_LENGTH);
+        assert!(plan.id.bytes().all(|c| ID_ALPHABET.contains(&c)));
+        assert_ne!(store.save(input).unwrap().id, plan.id);
+
+        // A new store reads the plans back from the file
+        let reopened = PlanStore::open(&path).unwrap();
+        let loaded = reopened.get(&plan.id).unwrap();
+        assert_eq!(loaded.input.route, ["OEVE", "WV12"]);
+        assert_eq!(loaded.input.notes, "Reef before midnight");
+        assert!(reopened.get("nothing").is_none());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -1,5 +1,7 @@
+use crate::approach::LatestDeparture;
 use crate::data::RegattaData;
-use svg::node::element::{Line, Text, Group, Definitions, Marker, Polygon};
+use crate::optimize::Path;
+use svg::node::element::{Line, Text, Group, Definitions, Marker, Polygon, Polyline, Rectangle, Circle};
 use svg::Document;
 
 /// Plot configuration for the SVG output
@@ -12,6 +14,7 @@ pub struct PlotConfig {
     pub line_width: f64,
     pub show_grid: bool,
     pub grid_interval: f64, // Grid interval in degrees
+    pub theme: Theme,
 }
 
 impl Default for PlotConfig {
@@ -25,10 +28,127 @@ impl Default for PlotConfig {
             line_width: 2.0,
             show_grid: true,
             grid_interval: 0.1, // 0.1 degrees
+            theme: Theme::default(),
         }
     }
 }
 
+/// Color scheme of the SVG output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Theme {
+    /// Dark lines on a transparent background, for screens by day and print
+    #[default]
+    Light,
+    /// Shades of red on black, bright enough to read but keeping the night
+    /// vision of the watch on deck
+    Dark,
+}
+
+impl Theme {
+    /// Name of the theme as in `--theme` and `?theme=`
+    pub fn name(self) -> &'static str {
+        match self {
+            Theme::Light => "light",
+            Theme::Dark => "dark",
+        }
+    }
+
+    /// The theme of a name, `None` for unknown names
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "light" => Some(Theme::Light),
+            "dark" => Some(Theme::Dark),
+            _ => None,
+        }
+    }
+
+    /// The colors of the theme
+    pub fn palette(self) -> &'static Palette {
+        match self {
+            Theme::Light => &LIGHT,
+            Theme::Dark => &DARK,
+        }
+    }
+}
+
+/// Colors of a theme by what they are used for
+pub struct Palette {
+    pub background: Option<&'static str>, // None to leave the background to the page
+    pub text: &'static str,         // titles, names, and axes
+    pub muted: &'static str,        // grid labels and the coordinate grid
+    pub caption: &'static str,      // notes at the edge of a chart
+    pub grid: &'static str,         // grid lines of the charts
+    pub halo: &'static str,         // outline separating marks from what is under them
+    pub line: &'static str,         // legs, the distance line, and the wind speed
+    pub line_label: &'static str,
+    pub accent: &'static str,       // start legs
+    pub accent_label: &'static str, // start legs, speeds, and slack before the deadline
+    pub bar: &'static str,          // speed bars
+    pub mark: &'static str,         // buoys, roundings, and the deadline
+    pub wind: &'static str,         // wind directions
+    pub night: &'static str,        // shading of the night
+    pub route: &'static str,        // a route drawn over the course map
+    pub series: &'static [&'static str], // boats and polar curves, reused for more of them
+}
+
+const LIGHT: Palette = Palette {
+    background: None,
+    text: "black",
+    muted: "#666",
+    caption: "gray",
+    grid: "#ccc",
+    halo: "white",
+    line: "blue",
+    line_label: "darkblue",
+    accent: "green",
+    accent_label: "darkgreen",
+    bar: "seagreen",
+    mark: "red",
+    wind: "darkred",
+    night: "#1e3c72",
+    route: "#8e44ad",
+    series: &["#e67e22", "#8e44ad", "#16a085", "#c0392b", "#2c3e50", "#d35400"],
+};
+
+// Only long wavelengths, told apart by brightness, width, and dashes rather
+// than by hue
+const DARK: Palette = Palette {
+    background: Some("#0b0000"),
+    text: "#ff6666",
+    muted: "#b34747",
+    caption: "#993d3d",
+    grid: "#4d1f1f",
+    halo: "#0b0000",
+    line: "#cc2929",
+    line_label: "#ff8080",
+    accent: "#e65c00",
+    accent_label: "#ff8533",
+    bar: "#992e2e",
+    mark: "#ff1a1a",
+    wind: "#ff4d4d",
+    night: "#660000",
+    route: "#ff3300",
+    series: &["#ff3300", "#ff9999", "#e65c00", "#cc0033", "#b36b6b", "#ff6633"],
+};
+
+/// An empty SVG document of the size of the configuration, filled with the
+/// background of its theme
+fn new_document(config: &PlotConfig) -> Document {
+    let document = Document::new()
+        .set("width", config.width)
+        .set("height", config.height)
+        .set("viewBox", format!("0 0 {} {}", config.width, config.height));
+    match config.theme.palette().background {
+        Some(color) => document.add(
+            Rectangle::new()
+                .set("width", "100%")
+                .set("height", "100%")
+                .set("fill", color),
+        ),
+        None => document,
+    }
+}
+
 /// Calculate the bounding box for all coordinates
 fn calculate_bounds(data: &RegattaData) -> Option<(f64, f64, f64, f64)> {
     let mut min_lat = f64::INFINITY;
@@ -80,6 +200,7 @@ fn create_coordinate_grid(
     bounds: (f64, f64, f64, f64),
     config: &PlotConfig,
 ) -> Group {
+    let palette = config.theme.palette();
     let (min_lat, max_lat, min_long, max_long) = bounds;
     let mut grid_group = Group::new()
         .set("id", "coordinate-grid")
@@ -107,7 +228,7 @@ fn create_coordinate_grid(
                 .set("y1", 0)
                 .set("x2", x)
                 .set("y2", config.height)
-                .set("stroke", "#666")
+                .set("stroke", palette.muted)
                 .set("stroke-width", "0.5");
             
             grid_group = grid_group.add(line);
@@ -118,7 +239,7 @@ fn create_coordinate_grid(
                 .set("y", 12)
                 .set("text-anchor", "middle")
                 .set("font-size", "10")
-                .set("fill", "#666");
+                .set("fill", palette.muted);
             
             grid_group = grid_group.add(label);
         }
@@ -137,7 +258,7 @@ fn create_coordinate_grid(
                 .set("y1", y)
                 .set("x2", config.width)
                 .set("y2", y)
-                .set("stroke", "#666")
+                .set("stroke", palette.muted)
                 .set("stroke-width", "0.5");
             
             grid_group = grid_group.add(line);
@@ -148,7 +269,7 @@ fn create_coordinate_grid(
                 .set("y", y + 4.0) // Offset to center text on line
                 .set("text-anchor", "start")
                 .set("font-size", "10")
-                .set("fill", "#666");
+                .set("fill", palette.muted);
             
             grid_group = grid_group.add(label);
         }
@@ -158,19 +279,72 @@ fn create_coordinate_grid(
     grid_group
 }
 
+/// Track of a boat to draw on the course map
+pub struct BoatTrack {
+    pub name: String,
+    pub positions: Vec<(f64, f64)>, // latitude and longitude, oldest first
+}
+
 /// Create an SVG visualization of the regatta data
 pub fn create_regatta_plot(data: &RegattaData, config: PlotConfig) -> Result<String, Box<dyn std::error::Error>> {
-    // Calculate bounding box
+    create_fleet_plot(data, &[], config)
+}
+
+/// Create an SVG visualization of the regatta data with the tracks of the
+/// boats and their latest positions on top
+pub fn create_fleet_plot(
+    data: &RegattaData,
+    boats: &[BoatTrack],
+    config: PlotConfig,
+) -> Result<String, Box<dyn std::error::Error>> {
+    create_course_plot(data, boats, None, &[], config)
+}
+
+/// Create an SVG visualization of the regatta data with a route drawn over
+/// the course, each leg numbered in the order it is sailed
+pub fn create_route_map(
+    data: &RegattaData,
+    path: &Path,
+    config: PlotConfig,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if path.steps.is_empty() {
+        return Err("Cannot draw an empty path on the map".into());
+    }
+    create_course_plot(data, &[], Some(path), &[], config)
+}
+
+/// Create an SVG visualization of the regatta data with the point of no
+/// return of every buoy which still reaches the finish under its name
+pub fn create_deadline_map(
+    data: &RegattaData,
+    departures: &[LatestDeparture],
+    config: PlotConfig,
+) -> Result<String, Box<dyn std::error::Error>> {
+    create_course_plot(data, &[], None, departures, config)
+}
+
+fn create_course_plot(
+    data: &RegattaData,
+    boats: &[BoatTrack],
+    route: Option<&Path>,
+    deadlines: &[LatestDeparture],
+    config: PlotConfig,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let palette = config.theme.palette();
+    // Calculate bounding box, widened to boats sailing outside the course
     let bounds = calculate_bounds(data)
         .ok_or("No coordinates found in the data")?;
+    let bounds = boats
+        .iter()
+        .flat_map(|boat| boat.positions.iter())
+        .fold(bounds, |(min_lat, max_lat, min_long, max_long), &(lat, long)| {
+            (min_lat.min(lat), max_lat.max(lat), min_long.min(long), max_long.max(long))
+        });
     
     let (min_lat, max_lat, min_long, max_long) = bounds;
     
     // Create SVG document
-    let mut document = Document::new()
-        .set("width", config.width)
-        .set("height", config.height)
-        .set("viewBox", format!("0 0 {} {}", config.width, config.height));
+    let mut document = new_document(&config);
     
     // Create definitions for arrow markers
     let mut defs = Definitions::new();
@@ -187,10 +361,25 @@ pub fn create_regatta_plot(data: &RegattaData, config: PlotConfig) -> Result<Str
         .add(
             Polygon::new()
                 .set("points", "0,0 0,6 9,3")
-                .set("fill", "green")
+                .set("fill", palette.accent)
         );
     
-    defs = defs.add(green_arrow);
+    // Purple arrow marker for the legs of a route
+    let route_arrow = Marker::new()
+        .set("id", "route-arrow")
+        .set("markerWidth", "10")
+        .set("markerHeight", "10")
+        .set("refX", "8")
+        .set("refY", "3")
+        .set("orient", "auto")
+        .set("markerUnits", "strokeWidth")
+        .add(
+            Polygon::new()
+                .set("points", "0,0 0,6 9,3")
+                .set("fill", palette.route)
+        );
+    
+    defs = defs.add(green_arrow).add(route_arrow);
     document = document.add(defs);
     
     // Add coordinate grid first (as background)
@@ -204,98 +393,148 @@ pub fn create_regatta_plot(data: &RegattaData, config: PlotConfig) -> Result<Str
     
     // Draw start legs first (as green arrows)
     for start in &data.starts {
-        if let (Some(from_boei), Some(to_boei)) = (data.get_boei(&start.from), data.get_boei(&start.to)) {
-            if let (Some((from_lat, from_long)), Some((to_lat, to_long))) = 
-                (from_boei.coordinates(), to_boei.coordinates()) {
-                
-                let (from_x, from_y) = geo_to_svg(from_lat, from_long, bounds, &config);
-                let (to_x, to_y) = geo_to_svg(to_lat, to_long, bounds, &config);
-                
-                // Draw the start leg line with arrow
-                let start_line = Line::new()
-                    .set("x1", from_x)
-                    .set("y1", from_y)
-                    .set("x2", to_x)
-                    .set("y2", to_y)
-                    .set("stroke", "green")
-                    .set("stroke-width", config.line_width * 1.5) // Make start legs slightly thicker
-                    .set("marker-end", "url(#green-arrow)")
-                    .set("opacity", "0.8");
-                
-                main_group = main_group.add(start_line);
-                
-                // Add distance label near the center of the start line
-                let center_x = (from_x + to_x) / 2.0;
-                let center_y = (from_y + to_y) / 2.0 + config.text_size; // Offset to avoid overlap with leg labels
-                
-                let start_distance_text = Text::new(format!("START: {:.1} nm", start.distance))
-                    .set("x", center_x)
-                    .set("y", center_y)
-                    .set("text-anchor", "middle")
-                    .set("dominant-baseline", "middle")
-                    .set("font-size", config.text_size * 0.9) // Slightly smaller than leg labels
-                    .set("fill", "darkgreen")
-                    .set("font-weight", "bold");
-                
-                main_group = main_group.add(start_distance_text);
-            }
+        if let (Some(from_boei), Some(to_boei)) = (data.get_boei(&start.from), data.get_boei(&start.to))
+            && let (Some((from_lat, from_long)), Some((to_lat, to_long))) =
+                (from_boei.coordinates(), to_boei.coordinates())
+        {
+            let (from_x, from_y) = geo_to_svg(from_lat, from_long, bounds, &config);
+            let (to_x, to_y) = geo_to_svg(to_lat, to_long, bounds, &config);
+            
+            // Draw the start leg line with arrow
+            let start_line = Line::new()
+                .set("x1", from_x)
+                .set("y1", from_y)
+                .set("x2", to_x)
+                .set("y2", to_y)
+                .set("stroke", palette.accent)
+                .set("stroke-width", config.line_width * 1.5) // Make start legs slightly thicker
+                .set("marker-end", "url(#green-arrow)")
+                .set("opacity", "0.8")
+                .set("class", "start")
+                .set("data-from", start.from.as_str())
+                .set("data-to", start.to.as_str());
+            
+            main_group = main_group.add(start_line);
+            
+            // Add distance label near the center of the start line
+            let center_x = (from_x + to_x) / 2.0;
+            let center_y = (from_y + to_y) / 2.0 + config.text_size; // Offset to avoid overlap with leg labels
+            
+            let start_distance_text = Text::new(format!("START: {:.1} nm", start.distance))
+                .set("x", center_x)
+                .set("y", center_y)
+                .set("text-anchor", "middle")
+                .set("dominant-baseline", "middle")
+                .set("font-size", config.text_size * 0.9) // Slightly smaller than leg labels
+                .set("fill", palette.accent_label)
+                .set("font-weight", "bold");
+            
+            main_group = main_group.add(start_distance_text);
         }
     }
     
     // Draw all legs (rakken) second (so they appear over start legs but behind buoys)
     for rak in &data.rakken {
-        if let (Some(from_boei), Some(to_boei)) = (data.get_boei(&rak.from), data.get_boei(&rak.to)) {
-            if let (Some((from_lat, from_long)), Some((to_lat, to_long))) = 
-                (from_boei.coordinates(), to_boei.coordinates()) {
-                
-                let (from_x, from_y) = geo_to_svg(from_lat, from_long, bounds, &config);
-                let (to_x, to_y) = geo_to_svg(to_lat, to_long, bounds, &config);
-                
-                // Draw the leg line
-                let line = Line::new()
-                    .set("x1", from_x)
-                    .set("y1", from_y)
-                    .set("x2", to_x)
-                    .set("y2", to_y)
-                    .set("stroke", "blue")
-                    .set("stroke-width", config.line_width)
-                    .set("opacity", "0.7");
-                
-                main_group = main_group.add(line);
-                
-                // Add distance label near the center of the line
-                let center_x = (from_x + to_x) / 2.0;
-                let center_y = (from_y + to_y) / 2.0;
-                
-                let distance_text = Text::new(format!("{:.1} nm", rak.distance))
-                    .set("x", center_x)
-                    .set("y", center_y)
-                    .set("text-anchor", "middle")
-                    .set("dominant-baseline", "middle")
-                    .set("font-size", config.text_size)
-                    .set("fill", "darkblue")
-                    .set("font-weight", "bold");
-                
-                main_group = main_group.add(distance_text);
-            }
+        if let (Some(from_boei), Some(to_boei)) = (data.get_boei(&rak.from), data.get_boei(&rak.to))
+            && let (Some((from_lat, from_long)), Some((to_lat, to_long))) =
+                (from_boei.coordinates(), to_boei.coordinates())
+        {
+            let (from_x, from_y) = geo_to_svg(from_lat, from_long, bounds, &config);
+            let (to_x, to_y) = geo_to_svg(to_lat, to_long, bounds, &config);
+            
+            // Draw the leg line
+            let line = Line::new()
+                .set("x1", from_x)
+                .set("y1", from_y)
+                .set("x2", to_x)
+                .set("y2", to_y)
+                .set("stroke", palette.line)
+                .set("stroke-width", config.line_width)
+                .set("opacity", "0.7")
+                .set("class", "leg")
+                .set("data-from", rak.from.as_str())
+                .set("data-to", rak.to.as_str());
+            
+            main_group = main_group.add(line);
+            
+            // Add distance label near the center of the line
+            let center_x = (from_x + to_x) / 2.0;
+            let center_y = (from_y + to_y) / 2.0;
+            
+            let distance_text = Text::new(format!("{:.1} nm", rak.distance))
+                .set("x", center_x)
+                .set("y", center_y)
+                .set("text-anchor", "middle")
+                .set("dominant-baseline", "middle")
+                .set("font-size", config.text_size)
+                .set("fill", palette.line_label)
+                .set("font-weight", "bold");
+            
+            main_group = main_group.add(distance_text);
         }
     }
     
-    // Draw all buoys
-    for boei in &data.boeien {
+    // Draw the route over the legs but behind the buoys, so their names stay
+    // readable
+    if let Some(path) = route {
+        for (number, step) in path.steps.iter().enumerate() {
+            let (Some(from), Some(to)) = (data.boeien[step.from].coordinates(), data.boeien[step.to].coordinates()) else {
+                continue;
+            };
+            let (from_x, from_y) = geo_to_svg(from.0, from.1, bounds, &config);
+            let (to_x, to_y) = geo_to_svg(to.0, to.1, bounds, &config);
+            
+            let line = Line::new()
+                .set("x1", from_x)
+                .set("y1", from_y)
+                .set("x2", to_x)
+                .set("y2", to_y)
+                .set("stroke", palette.route)
+                .set("stroke-width", config.line_width * 3.0)
+                .set("marker-end", "url(#route-arrow)")
+                .set("opacity", "0.8")
+                .set("class", "route-leg");
+            
+            // Number of the leg a third of the way along, away from the
+            // distance label in the middle
+            let number_text = Text::new((number + 1).to_string())
+                .set("x", from_x + (to_x - from_x) / 3.0)
+                .set("y", from_y + (to_y - from_y) / 3.0)
+                .set("text-anchor", "middle")
+                .set("dominant-baseline", "middle")
+                .set("font-size", config.text_size * 1.2)
+                .set("font-weight", "bold")
+                .set("fill", palette.route)
+                .set("stroke", palette.halo)
+                .set("stroke-width", 0.5);
+            
+            main_group = main_group.add(line).add(number_text);
+        }
+    }
+    
+    // Draw all buoys, each in a group which pages can find by id or name and
+    // click on
+    for (index, boei) in data.boeien.iter().enumerate() {
         if let Some((lat, long)) = boei.coordinates() {
             let (x, y) = geo_to_svg(lat, long, bounds, &config);
             
             // Draw buoy as a cross
             let cross_size = config.buoy_size;
             
+            // Invisible area around the cross which takes the clicks
+            let target = Circle::new()
+                .set("cx", x)
+                .set("cy", y)
+                .set("r", cross_size * 2.5)
+                .set("fill", "transparent");
+            
             // Horizontal line of the cross
             let h_line = Line::new()
                 .set("x1", x - cross_size)
                 .set("y1", y)
                 .set("x2", x + cross_size)
                 .set("y2", y)
-                .set("stroke", "red")
+                .set("stroke", palette.mark)
                 .set("stroke-width", 2.0);
             
             // Vertical line of the cross
@@ -304,11 +543,16 @@ pub fn create_regatta_plot(data: &RegattaData, config: PlotConfig) -> Result<Str
                 .set("y1", y - cross_size)
                 .set("x2", x)
                 .set("y2", y + cross_size)
-                .set("stroke", "red")
+                .set("stroke", palette.mark)
                 .set("stroke-width", 2.0);
             
-            main_group = main_group.add(h_line);
-            main_group = main_group.add(v_line);
+            let mut buoy_group = Group::new()
+                .set("class", "buoy")
+                .set("id", format!("buoy-{index}"))
+                .set("data-buoy", boei.name.as_str())
+                .add(target)
+                .add(h_line)
+                .add(v_line);
             
             // Add buoy name label
             let text_x = x + cross_size + 5.0;
@@ -319,9 +563,26 @@ pub fn create_regatta_plot(data: &RegattaData, config: PlotConfig) -> Result<Str
                 .set("y", text_y)
                 .set("dominant-baseline", "middle")
                 .set("font-size", config.text_size)
-                .set("fill", "black");
+                .set("fill", palette.text);
             
-            main_group = main_group.add(name_text);
+            buoy_group = buoy_group.add(name_text);
+            
+            // Latest departure to still make the finish under the name
+            if let Some(deadline) = deadlines.iter().find(|deadline| deadline.buoy == index) {
+                let latest = data
+                    .race_clock
+                    .format_clock(deadline.latest)
+                    .unwrap_or_else(|| format!("{:.2} h", deadline.latest));
+                let deadline_text = Text::new(format!("leave by {latest}"))
+                    .set("x", text_x)
+                    .set("y", text_y + config.text_size * 1.2)
+                    .set("dominant-baseline", "middle")
+                    .set("font-size", config.text_size * 0.9)
+                    .set("fill", palette.accent_label)
+                    .set("class", "deadline");
+                buoy_group = buoy_group.add(deadline_text);
+            }
+            main_group = main_group.add(buoy_group);
         }
     }
     
@@ -332,7 +593,7 @@ pub fn create_regatta_plot(data: &RegattaData, config: PlotConfig) -> Result<Str
         .set("text-anchor", "middle")
         .set("font-size", 16.0)
         .set("font-weight", "bold")
-        .set("fill", "black");
+        .set("fill", palette.text);
     
     let bounds_text = Text::new(format!(
         "Bounds: {min_lat:.4}°N to {max_lat:.4}°N, {min_long:.4}°E to {max_long:.4}°E"
@@ -340,11 +601,80 @@ pub fn create_regatta_plot(data: &RegattaData, config: PlotConfig) -> Result<Str
         .set("x", 10.0)
         .set("y", config.height as f64 - 10.0)
         .set("font-size", 10.0)
-        .set("fill", "gray");
+        .set("fill", palette.caption);
     
     main_group = main_group.add(title_text);
     main_group = main_group.add(bounds_text);
     
+    if let Some(path) = route {
+        let route_text = Text::new(format!(
+            "Route: {} legs, {:.2} nm, finished after {:.2} h",
+            path.steps.len(),
+            path.total_distance,
+            path.end_time
+        ))
+            .set("x", config.width as f64 / 2.0)
+            .set("y", 38.0)
+            .set("text-anchor", "middle")
+            .set("font-size", 12.0)
+            .set("fill", palette.route);
+        main_group = main_group.add(route_text);
+    }
+    
+    if !deadlines.is_empty() {
+        let deadline_text = Text::new("Latest departures which still reach the finish in time")
+            .set("x", config.width as f64 / 2.0)
+            .set("y", 38.0)
+            .set("text-anchor", "middle")
+            .set("font-size", 12.0)
+            .set("fill", palette.accent_label);
+        main_group = main_group.add(deadline_text);
+    }
+    
+    // Draw the boats last, each with its track and a dot at its latest position
+    for (boat, color) in boats.iter().zip(palette.series.iter().cycle()) {
+        let points: Vec<(f64, f64)> = boat
+            .positions
+            .iter()
+            .map(|&(lat, long)| geo_to_svg(lat, long, bounds, &config))
+            .collect();
+        let Some(&(x, y)) = points.last() else {
+            continue;
+        };
+        
+        let track = Polyline::new()
+            .set(
+                "points",
+                points
+                    .iter()
+                    .map(|(x, y)| format!("{x:.1},{y:.1}"))
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            )
+            .set("fill", "none")
+            .set("stroke", *color)
+            .set("stroke-width", config.line_width)
+            .set("stroke-dasharray", "6,3");
+        
+        let position = Circle::new()
+            .set("cx", x)
+            .set("cy", y)
+            .set("r", config.buoy_size * 1.5)
+            .set("fill", *color)
+            .set("stroke", palette.halo)
+            .set("stroke-width", 1.5);
+        
+        let name_text = Text::new(&boat.name)
+            .set("x", x)
+            .set("y", y - config.buoy_size * 1.5 - 4.0)
+            .set("text-anchor", "middle")
+            .set("font-size", config.text_size)
+            .set("font-weight", "bold")
+            .set("fill", *color);
+        
+        main_group = main_group.add(track).add(position).add(name_text);
+    }
+    
     // Add main group to document
     document = document.add(main_group);
     
@@ -352,6 +682,618 @@ pub fn create_regatta_plot(data: &RegattaData, config: PlotConfig) -> Result<Str
     Ok(document.to_string())
 }
 
+/// Create an SVG profile chart for a path
+///
+/// The upper panel shows the cumulative distance over time, with the finish
+/// deadline marked, and the lower panel shows the estimated speed per leg as
+/// bars spanning the time the leg is sailed.
+pub fn create_route_profile_plot(
+    data: &RegattaData,
+    path: &Path,
+    config: PlotConfig,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let palette = config.theme.palette();
+    if path.steps.is_empty() {
+        return Err("Cannot plot a profile for an empty path".into());
+    }
+
+    let width = config.width as f64;
+    let height = config.height as f64;
+    let margin = config.margin;
+
+    // Layout: distance panel on top, speed bars below
+    let plot_left = margin + 20.0;
+    let plot_right = width - margin;
+    let distance_top = margin;
+    let distance_bottom = height * 0.6;
+    let speed_top = distance_bottom + 2.0 * margin;
+    let speed_bottom = height - margin;
+
+    // Axis ranges
+    let start_time = path.steps[0].start_time;
+    let deadline = data.rules.scoring.deadline;
+    let end_time = path.end_time.max(deadline);
+    let max_speed = path
+        .steps
+        .iter()
+        .map(|step| step.speed)
+        .fold(0.0_f64, f64::max)
+        .max(1.0)
+        .ceil();
+    let max_distance = path.total_distance.max(1.0);
+
+    let time_to_x = |time: f64| {
+        plot_left + (time - start_time) / (end_time - start_time) * (plot_right - plot_left)
+    };
+    let distance_to_y = |distance: f64| {
+        distance_bottom - distance / max_distance * (distance_bottom - distance_top)
+    };
+    let speed_to_y =
+        |speed: f64| speed_bottom - speed / max_speed * (speed_bottom - speed_top);
+
+    let mut document = new_document(&config);
+
+    let mut main_group = Group::new();
+
+    // Hour grid and labels shared by both panels
+    let mut hour = start_time.ceil();
+    while hour <= end_time {
+        let x = time_to_x(hour);
+        main_group = main_group.add(
+            Line::new()
+                .set("x1", x)
+                .set("y1", distance_top)
+                .set("x2", x)
+                .set("y2", speed_bottom)
+                .set("stroke", palette.grid)
+                .set("stroke-width", "0.5"),
+        );
+        main_group = main_group.add(
+            Text::new(format!("{hour:.0}h"))
+                .set("x", x)
+                .set("y", speed_bottom + config.text_size + 2.0)
+                .set("text-anchor", "middle")
+                .set("font-size", config.text_size * 0.8)
+                .set("fill", palette.muted),
+        );
+        hour += 1.0;
+    }
+
+    // Axes for both panels
+    for (top, bottom) in [(distance_top, distance_bottom), (speed_top, speed_bottom)] {
+        main_group = main_group
+            .add(
+                Line::new()
+                    .set("x1", plot_left)
+                    .set("y1", top)
+                    .set("x2", plot_left)
+                    .set("y2", bottom)
+                    .set("stroke", palette.text),
+            )
+            .add(
+                Line::new()
+                    .set("x1", plot_left)
+                    .set("y1", bottom)
+                    .set("x2", plot_right)
+                    .set("y2", bottom)
+                    .set("stroke", palette.text),
+            );
+    }
+
+    main_group = main_group
+        .add(
+            Text::new(format!("Distance (0 - {max_distance:.1} nm)"))
+                .set("x", plot_left + 5.0)
+                .set("y", distance_top - 5.0)
+                .set("font-size", config.text_size)
+                .set("fill", palette.line_label),
+        )
+        .add(
+            Text::new(format!("Speed per leg (0 - {max_speed:.0} kts)"))
+                .set("x", plot_left + 5.0)
+                .set("y", speed_top - 5.0)
+                .set("font-size", config.text_size)
+                .set("fill", palette.accent_label),
+        );
+
+    // Cumulative distance over time
+    let mut points = vec![(time_to_x(start_time), distance_to_y(0.0))];
+    let mut cumulative_distance = 0.0;
+    for step in &path.steps {
+        cumulative_distance += step.distance;
+        points.push((time_to_x(step.end_time), distance_to_y(cumulative_distance)));
+    }
+    let points_str = points
+        .iter()
+        .map(|(x, y)| format!("{x:.1},{y:.1}"))
+        .collect::<Vec<_>>()
+        .join(" ");
+    main_group = main_group.add(
+        Polyline::new()
+            .set("points", points_str)
+            .set("fill", "none")
+            .set("stroke", palette.line)
+            .set("stroke-width", config.line_width),
+    );
+
+    // Mark every buoy rounding on the distance line
+    for (step, &(x, y)) in path.steps.iter().zip(points.iter().skip(1)) {
+        main_group = main_group
+            .add(
+                Circle::new()
+                    .set("cx", x)
+                    .set("cy", y)
+                    .set("r", config.buoy_size * 0.75)
+                    .set("fill", palette.mark),
+            )
+            .add(
+                Text::new(&data.boeien[step.to].name)
+                    .set("x", x + 4.0)
+                    .set("y", y - 4.0)
+                    .set("font-size", config.text_size * 0.8)
+                    .set("fill", palette.text),
+            );
+    }
+
+    // Speed bars, each spanning the time the leg is sailed
+    for step in &path.steps {
+        let x1 = time_to_x(step.start_time);
+        let x2 = time_to_x(step.end_time);
+        let y = speed_to_y(step.speed);
+        main_group = main_group
+            .add(
+                Rectangle::new()
+                    .set("x", x1)
+                    .set("y", y)
+                    .set("width", (x2 - x1 - 1.0).max(1.0))
+                    .set("height", speed_bottom - y)
+                    .set("fill", palette.bar)
+                    .set("opacity", "0.7"),
+            )
+            .add(
+                Text::new(format!("{:.1}", step.speed))
+                    .set("x", (x1 + x2) / 2.0)
+                    .set("y", y - 3.0)
+                    .set("text-anchor", "middle")
+                    .set("font-size", config.text_size * 0.8)
+                    .set("fill", palette.accent_label),
+            );
+    }
+
+    // Finish deadline and the slack (or shortfall) of this path
+    let deadline_x = time_to_x(deadline);
+    let slack = deadline - path.end_time;
+    let slack_text = if slack >= 0.0 {
+        format!("Slack before deadline: {slack:.2}h")
+    } else {
+        format!("Deadline missed by {:.2}h", -slack)
+    };
+    main_group = main_group
+        .add(
+            Line::new()
+                .set("x1", deadline_x)
+                .set("y1", distance_top)
+                .set("x2", deadline_x)
+                .set("y2", speed_bottom)
+                .set("stroke", palette.mark)
+                .set("stroke-width", config.line_width)
+                .set("stroke-dasharray", "6,4"),
+        )
+        .add(
+            Text::new(slack_text)
+                .set("x", deadline_x - 5.0)
+                .set("y", distance_bottom - 5.0)
+                .set("text-anchor", "end")
+                .set("font-size", config.text_size)
+                .set("fill", if slack >= 0.0 { palette.accent_label } else { palette.mark })
+                .set("font-weight", "bold"),
+        );
+
+    // Title
+    let first = &data.boeien[path.steps[0].from].name;
+    let last = &data.boeien[path.steps[path.steps.len() - 1].to].name;
+    main_group = main_group.add(
+        Text::new(format!(
+            "Route profile {first} -> {last}: {:.1} nm, {} legs, finish at {:.2}h",
+            path.total_distance,
+            path.steps.len(),
+            path.end_time
+        ))
+        .set("x", width / 2.0)
+        .set("y", 20.0)
+        .set("text-anchor", "middle")
+        .set("font-size", 16.0)
+        .set("font-weight", "bold")
+        .set("fill", palette.text),
+    );
+
+    document = document.add(main_group);
+
+    Ok(document.to_string())
+}
+
+/// Create an SVG strip chart of the wind forecast over the race
+///
+/// The upper strip shows the wind speed, the lower strip the wind direction
+/// with small arrows pointing where the wind blows to. Night time is shaded
+/// if `daylight` gives the sunrise and sunset in minutes since midnight.
+pub fn create_wind_chart(
+    data: &RegattaData,
+    config: PlotConfig,
+    daylight: Option<(f64, f64)>,
+) -> Result<String, Box<dyn std::error::Error>> {
+    let palette = config.theme.palette();
+    let conditions = data.wind_data.get_all_conditions();
+    if conditions.is_empty() {
+        return Err("No wind data available".into());
+    }
+
+    let width = config.width as f64;
+    let height = config.height as f64;
+    let margin = config.margin;
+
+    // Layout: speed strip on top, direction strip below
+    let plot_left = margin + 20.0;
+    let plot_right = width - margin;
+    let speed_top = margin;
+    let speed_bottom = height * 0.5;
+    let direction_top = speed_bottom + 1.5 * margin;
+    let direction_bottom = height - margin;
+
+    let end_time = conditions
+        .iter()
+        .map(|c| c.time as f64)
+        .fold(data.rules.scoring.deadline, f64::max);
+    let max_speed = (conditions
+        .iter()
+        .map(|c| c.wind_speed)
+        .fold(0.0_f64, f64::max)
+        / 5.0)
+        .ceil()
+        .max(1.0)
+        * 5.0;
+
+    let time_to_x = |time: f64| plot_left + time / end_time * (plot_right - plot_left);
+    let speed_to_y =
+        |speed: f64| speed_bottom - speed / max_speed * (speed_bottom - speed_top);
+    let direction_to_y =
+        |angle: f64| direction_top + angle / 360.0 * (direction_bottom - direction_top);
+
+    let mut document = new_document(&config);
+
+    let mut main_group = Group::new();
+
+    // Shade the night, sampled every 5 minutes of race time
+    if let Some((sunrise, sunset)) = daylight {
+        let is_dark = |time: f64| {
+            data.race_clock
+                .clock_minutes_at(time)
+                .is_some_and(|minutes| minutes < sunrise || minutes >= sunset)
+        };
+        let sample = 5.0 / 60.0;
+        let mut dark_from: Option<f64> = None;
+        let mut time = 0.0;
+        while time <= end_time + sample {
+            let dark = time <= end_time && is_dark(time);
+            match (dark, dark_from) {
+                (true, None) => dark_from = Some(time),
+                (false, Some(from)) => {
+                    let to = time.min(end_time);
+                    for (top, bottom) in
+                        [(speed_top, speed_bottom), (direction_top, direction_bottom)]
+                    {
+                        main_group = main_group.add(
+                            Rectangle::new()
+                                .set("x", time_to_x(from))
+                                .set("y", top)
+                                .set("width", time_to_x(to) - time_to_x(from))
+                                .set("height", bottom - top)
+                                .set("fill", palette.night)
+                                .set("opacity", "0.15"),
+                        );
+                    }
+                    dark_from = None;
+                }
+                _ => {}
+            }
+            time += sample;
+        }
+    }
+
+    // Hour grid with race hours and clock times
+    for hour in 0..=(end_time as u32) {
+        let x = time_to_x(hour as f64);
+        main_group = main_group.add(
+            Line::new()
+                .set("x1", x)
+                .set("y1", speed_top)
+                .set("x2", x)
+                .set("y2", direction_bottom)
+                .set("stroke", palette.grid)
+                .set("stroke-width", "0.5"),
+        );
+        let label = match data.race_clock.format_clock(hour as f64) {
+            Some(clock) if hour % 2 == 0 => format!("{hour}h ({clock})"),
+            _ => format!("{hour}h"),
+        };
+        main_group = main_group.add(
+            Text::new(label)
+                .set("x", x)
+                .set("y", direction_bottom + config.text_size + 2.0)
+                .set("text-anchor", "middle")
+                .set("font-size", config.text_size * 0.7)
+                .set("fill", palette.muted),
+        );
+    }
+
+    // Horizontal grid lines with labels
+    let mut speed = 0.0;
+    while speed <= max_speed {
+        let y = speed_to_y(speed);
+        main_group = main_group
+            .add(
+                Line::new()
+                    .set("x1", plot_left)
+                    .set("y1", y)
+                    .set("x2", plot_right)
+                    .set("y2", y)
+                    .set("stroke", palette.grid)
+                    .set("stroke-width", "0.5"),
+            )
+            .add(
+                Text::new(format!("{speed:.0}"))
+                    .set("x", plot_left - 5.0)
+                    .set("y", y + 4.0)
+                    .set("text-anchor", "end")
+                    .set("font-size", config.text_size * 0.8)
+                    .set("fill", palette.muted),
+            );
+        speed += 5.0;
+    }
+    for (angle, label) in [(0.0, "N"), (90.0, "E"), (180.0, "S"), (270.0, "W"), (360.0, "N")] {
+        let y = direction_to_y(angle);
+        main_group = main_group
+            .add(
+                Line::new()
+                    .set("x1", plot_left)
+                    .set("y1", y)
+                    .set("x2", plot_right)
+                    .set("y2", y)
+                    .set("stroke", palette.grid)
+                    .set("stroke-width", "0.5"),
+            )
+            .add(
+                Text::new(format!("{angle:.0}° {label}"))
+                    .set("x", plot_left - 5.0)
+                    .set("y", y + 4.0)
+                    .set("text-anchor", "end")
+                    .set("font-size", config.text_size * 0.8)
+                    .set("fill", palette.muted),
+            );
+    }
+
+    main_group = main_group
+        .add(
+            Text::new("Wind speed (kts)")
+                .set("x", plot_left + 5.0)
+                .set("y", speed_top - 5.0)
+                .set("font-size", config.text_size)
+                .set("fill", palette.line_label),
+        )
+        .add(
+            Text::new("Wind direction (from)")
+                .set("x", plot_left + 5.0)
+                .set("y", direction_top - 5.0)
+                .set("font-size", config.text_size)
+                .set("fill", palette.wind),
+        );
+
+    // Wind speed line through the hourly forecast values
+    let points_str = conditions
+        .iter()
+        .map(|c| format!("{:.1},{:.1}", time_to_x(c.time as f64), speed_to_y(c.wind_speed)))
+        .collect::<Vec<_>>()
+        .join(" ");
+    main_group = main_group.add(
+        Polyline::new()
+            .set("points", points_str)
+            .set("fill", "none")
+            .set("stroke", palette.line)
+            .set("stroke-width", config.line_width),
+    );
+
+    // Wind direction as points with an arrow showing where the wind blows to
+    let arrow_length = config.text_size;
+    for condition in conditions {
+        let x = time_to_x(condition.time as f64);
+        let y = direction_to_y(condition.wind_angle);
+        let downwind = (condition.wind_angle + 180.0).to_radians();
+        main_group = main_group
+            .add(
+                Circle::new()
+                    .set("cx", x)
+                    .set("cy", y)
+                    .set("r", config.buoy_size * 0.75)
+                    .set("fill", palette.wind),
+            )
+            .add(
+                Line::new()
+                    .set("x1", x)
+                    .set("y1", y)
+                    .set("x2", x + arrow_length * downwind.sin())
+                    .set("y2", y - arrow_length * downwind.cos())
+                    .set("stroke", palette.wind)
+                    .set("stroke-width", 1.5),
+            );
+    }
+
+    main_group = main_group.add(
+        Text::new("24-Hour Regatta Wind Forecast")
+            .set("x", width / 2.0)
+            .set("y", 20.0)
+            .set("text-anchor", "middle")
+            .set("font-size", 16.0)
+            .set("font-weight", "bold")
+            .set("fill", palette.text),
+    );
+
+    document = document.add(main_group);
+
+    Ok(document.to_string())
+}
+
+/// Create an SVG polar diagram of the boat speeds in the polar table
+///
+/// The diagram is the usual half circle with the wind from the top: one curve
+/// per wind speed of the table, the true wind angle running clockwise and the
+/// boat speed outwards.
+pub fn create_polar_plot(data: &RegattaData, config: PlotConfig) -> Result<String, Box<dyn std::error::Error>> {
+    let palette = config.theme.palette();
+    let polars = &data.polar_data;
+    if polars.wind_speeds.is_empty() || polars.wind_angles.is_empty() {
+        return Err("No polar data available".into());
+    }
+
+    let width = config.width as f64;
+    let height = config.height as f64;
+    let margin = config.margin;
+
+    let max_speed = (polars
+        .boat_speeds
+        .iter()
+        .flatten()
+        .fold(0.0_f64, |max, &speed| max.max(speed))
+        / 2.0)
+        .ceil()
+        .max(1.0)
+        * 2.0;
+    let radius = (height / 2.0 - margin).min(width - 2.0 * margin - 150.0).max(1.0);
+    let (center_x, center_y) = ((width - radius) / 2.0, height / 2.0);
+    let to_xy = |angle: f64, speed: f64| {
+        let r = speed / max_speed * radius;
+        (center_x + r * angle.to_radians().sin(), center_y - r * angle.to_radians().cos())
+    };
+
+    let mut document = new_document(&config);
+
+    let mut main_group = Group::new();
+
+    // Speed rings every 2 knots and spokes every 30 degrees
+    let mut speed = 2.0;
+    while speed <= max_speed {
+        let points = (0..=36)
+            .map(|i| to_xy(i as f64 * 5.0, speed))
+            .map(|(x, y)| format!("{x:.1},{y:.1}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let (label_x, label_y) = to_xy(90.0, speed);
+        main_group = main_group
+            .add(
+                Polyline::new()
+                    .set("points", points)
+                    .set("fill", "none")
+                    .set("stroke", palette.grid)
+                    .set("stroke-width", "0.5"),
+            )
+            .add(
+                Text::new(format!("{speed:.0} kts"))
+                    .set("x", label_x + 2.0)
+                    .set("y", label_y - 2.0)
+                    .set("font-size", config.text_size * 0.7)
+                    .set("fill", palette.muted),
+            );
+        speed += 2.0;
+    }
+    for angle in (0..=180).step_by(30) {
+        let (x, y) = to_xy(angle as f64, max_speed);
+        let (label_x, label_y) = to_xy(angle as f64, max_speed * 1.06);
+        main_group = main_group
+            .add(
+                Line::new()
+                    .set("x1", center_x)
+                    .set("y1", center_y)
+                    .set("x2", x)
+                    .set("y2", y)
+                    .set("stroke", palette.grid)
+                    .set("stroke-width", "0.5"),
+            )
+            .add(
+                Text::new(format!("{angle}°"))
+                    .set("x", label_x)
+                    .set("y", label_y + 4.0)
+                    .set("text-anchor", "middle")
+                    .set("font-size", config.text_size * 0.8)
+                    .set("fill", palette.muted),
+            );
+    }
+
+    // One curve per wind speed with its legend entry
+    for (i, (wind_speed, color)) in polars.wind_speeds.iter().zip(palette.series.iter().cycle()).enumerate() {
+        let points = polars
+            .wind_angles
+            .iter()
+            .zip(&polars.boat_speeds)
+            .filter_map(|(&angle, speeds)| speeds.get(i).map(|&speed| to_xy(angle, speed)))
+            .map(|(x, y)| format!("{x:.1},{y:.1}"))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let legend_y = margin + i as f64 * (config.text_size + 6.0);
+        main_group = main_group
+            .add(
+                Polyline::new()
+                    .set("points", points)
+                    .set("fill", "none")
+                    .set("stroke", *color)
+                    .set("stroke-width", config.line_width),
+            )
+            .add(
+                Line::new()
+                    .set("x1", width - margin - 80.0)
+                    .set("y1", legend_y)
+                    .set("x2", width - margin - 60.0)
+                    .set("y2", legend_y)
+                    .set("stroke", *color)
+                    .set("stroke-width", config.line_width),
+            )
+            .add(
+                Text::new(format!("{wind_speed:.0} kts"))
+                    .set("x", width - margin - 55.0)
+                    .set("y", legend_y + 4.0)
+                    .set("font-size", config.text_size)
+                    .set("fill", palette.text),
+            );
+    }
+
+    main_group = main_group.add(
+        Text::new("Polar Diagram")
+            .set("x", width / 2.0)
+            .set("y", 20.0)
+            .set("text-anchor", "middle")
+            .set("font-size", 16.0)
+            .set("font-weight", "bold")
+            .set("fill", palette.text),
+    );
+
+    document = document.add(main_group);
+
+    Ok(document.to_string())
+}
+
+/// Generate and save the wind strip chart to a file
+pub fn save_wind_chart(
+    data: &RegattaData,
+    output_path: &str,
+    config: Option<PlotConfig>,
+    daylight: Option<(f64, f64)>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let config = config.unwrap_or_default();
+    let svg_content = create_wind_chart(data, config, daylight)?;
+
+    std::fs::write(output_path, svg_content)?;
+
+    Ok(())
+}
+
 /// Generate and save the regatta plot to a file
 pub fn save_regatta_plot(
     data: &RegattaData,
@@ -362,7 +1304,6 @@ pub fn save_regatta_plot(
     let svg_content = create_regatta_plot(data, config)?;
     
     std::fs::write(output_path, svg_content)?;
-    println!("SVG plot saved to: {output_path}");
     
     Ok(())
 }
@@ -0,0 +1,157 @@
+use crate::data::RegattaData;
+use crate::error::UursError;
+use crate::optimize::{SearchConstraints, sail_leg};
+
+/// Departures are evaluated every half hour unless asked otherwise
+pub const DEFAULT_INTERVAL: f64 = 0.5;
+
+/// Shortest interval between the departures of a profile, 5 minutes
+pub const MIN_INTERVAL: f64 = 5.0 / 60.0;
+
+/// The earliest arrival at a buoy for a departure from the start
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Arrival {
+    pub time: f64,  // in hours since race start
+    pub via: usize, // buoy the last leg comes from, the start for the start itself
+}
+
+/// The earliest arrival at every buoy from a start as a function of the
+/// departure, evaluated for departures in fixed steps over a period
+#[derive(Clone, Debug)]
+pub struct ArrivalProfile {
+    pub start: usize,
+    pub departures: Vec<f64>,                // in hours since race start, earliest first
+    pub arrivals: Vec<Vec<Option<Arrival>>>, // per departure per buoy, none if it can't be reached
+}
+
+impl ArrivalProfile {
+    /// Earliest arrival at a buoy leaving the start at `time`, bounded by the
+    /// arrival of the last departure of the profile at or before it
+    ///
+    /// As long as leaving later never arrives earlier this is a lower bound a
+    /// search can prune with: a path reaching the buoy later than it is
+    /// known to be slower than the fastest way there. None before the first
+    /// departure or if the buoy can't be reached.
+    pub fn arrival_bound(&self, buoy: usize, time: f64) -> Option<f64> {
+        let index = self.departures.partition_point(|departure| *departure <= time + 1e-9);
+        self.arrivals[index.checked_sub(1)?][buoy].map(|arrival| arrival.time)
+    }
+}
+
+/// Earliest arrival at every buoy leaving a start at a time, sailing on at
+/// once at every buoy
+///
+/// Like Dijkstra's algorithm forwards in time: the buoy reached earliest is
+/// settled first, its starts and legs are sailed from when it is reached
+/// with the speed estimated at the departure as the path searches do. The
+/// constraints of a search are respected, the number of legs, the maximum
+/// numbers of the legs, and the sequences forbidden by the rules are not.
+/// So, as long as leaving later never arrives earlier, no path of a search
+/// sailing on at once arrives earlier.
+pub fn earliest_arrivals(
+    data: &RegattaData,
+    start: usize,
+    time: f64,
+    constraints: &SearchConstraints,
+) -> Vec<Option<Arrival>> {
+    let count = data.boeien.len();
+    let mut arrivals: Vec<Option<Arrival>> = vec![None; count];
+    arrivals[start] = Some(Arrival { time, via: start });
+    let mut settled = vec![false; count];
+    while let Some((buoy, reached)) = (0..count)
+        .filter(|&buoy| !settled[buoy])
+        .filter_map(|buoy| arrivals[buoy].map(|arrival| (buoy, arrival.time)))
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+    {
+        settled[buoy] = true;
+        for (to, distance) in data.neighbors(buoy) {
+            if settled[to] || !constraints.allows(buoy, to) {
+                continue;
+            }
+            let Ok(step) = sail_leg(data, buoy, to, distance, reached, 0.0) else {
+                continue;
+            };
+            if arrivals[to].is_none_or(|known| step.end_time < known.time) {
+                arrivals[to] = Some(Arrival { time: step.end_time, via: buoy });
+            }
+        }
+    }
+    arrivals
+}
+
+/// The earliest arrivals at every buoy from a start for departures from
+/// `from` to `until`, every `interval` hours
+pub fn arrival_profile(
+    data: &RegattaData,
+    start: usize,
+    from: f64,
+    until: f64,
+    interval: f64,
+    constraints: &SearchConstraints,
+) -> Result<ArrivalProfile, UursError> {
+    if start >= data.boeien.len() {
+        return Err(UursError::UnknownBuoy(start.to_string()));
+    }
+    if interval.is_nan() || interval < MIN_INTERVAL - 1e-9 {
+        return Err(UursError::InvalidParameter(format!(
+            "The interval between departures must be at least {:.0} minutes",
+            MIN_INTERVAL * 60.0
+        )));
+    }
+    if until < from {
+        return Err(UursError::InvalidParameter("The last departure must not be before the first".to_string()));
+    }
+
+    let departures: Vec<f64> = (0..)
+        .map(|step| from + step as f64 * interval)
+        .take_while(|departure| *departure <= until + 1e-9)
+        .collect();
+    let arrivals = departures
+        .iter()
+        .map(|&departure| earliest_arrivals(data, start, departure, constraints))
+        .collect();
+    Ok(ArrivalProfile { start, departures, arrivals })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::load_regatta_data;
+
+    #[test]
+    fn test_arrival_profile() {
+        let data = load_regatta_data().unwrap();
+        let oeve = data.get_boei_index("OEVE").unwrap();
+        let profile = arrival_profile(&data, oeve, 0.0, 2.0, DEFAULT_INTERVAL, &SearchConstraints::default()).unwrap();
+        assert_eq!(profile.departures, vec![0.0, 0.5, 1.0, 1.5, 2.0]);
+
+        for (&departure, arrivals) in profile.departures.iter().zip(&profile.arrivals) {
+            assert_eq!(arrivals[oeve], Some(Arrival { time: departure, via: oeve }));
+            assert!(arrivals.iter().filter(|arrival| arrival.is_some()).count() > 10);
+            // Every arrival is the leg from the buoy it comes from, sailed when that one is reached
+            for (buoy, arrival) in arrivals.iter().enumerate().filter(|&(buoy, _)| buoy != oeve) {
+                let Some(arrival) = arrival else { continue };
+                let previous = arrivals[arrival.via].unwrap();
+                let distance = data.leg_distance(arrival.via, buoy).unwrap();
+                let step = sail_leg(&data, arrival.via, buoy, distance, previous.time, 0.0).unwrap();
+                assert!((step.end_time - arrival.time).abs() < 1e-9);
+            }
+        }
+
+        // No path of a search arrives earlier
+        let paths = crate::optimize::explore_paths(&data, oeve, 0.0, 3, &Default::default()).unwrap();
+        for path in &paths {
+            let last = path.steps.last().unwrap();
+            assert!(profile.arrival_bound(last.to, 0.0).unwrap() <= path.end_time + 1e-9);
+        }
+        assert_eq!(profile.arrival_bound(oeve, 0.7), Some(0.5));
+        assert_eq!(profile.arrival_bound(oeve, -1.0), None);
+
+        // Avoided buoys are not reached
+        let first = data.neighbors(oeve)[0].0;
+        let constraints = SearchConstraints { forbidden_buoys: vec![first], ..Default::default() };
+        assert!(earliest_arrivals(&data, oeve, 0.0, &constraints)[first].is_none());
+        assert!(arrival_profile(&data, oeve, 2.0, 1.0, DEFAULT_INTERVAL, &constraints).is_err());
+        assert!(arrival_profile(&data, oeve, 0.0, 1.0, 0.0, &constraints).is_err());
+    }
+}
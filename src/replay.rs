@@ -0,0 +1,172 @@
+use crate::data::RegattaData;
+use crate::error::UursError;
+use crate::live::{SailedLeg, match_track};
+use crate::optimize::{
+    Path, PlanObjective, SearchOptions, best_continuation, estimate_leg_performance,
+    evaluate_route,
+};
+use crate::track::TrackPoint;
+
+/// How the optimizer replans at the decision points of a replay
+#[derive(Clone, Debug)]
+pub struct ReplayOptions {
+    pub steps: usize,             // legs looked ahead from the next buoy
+    pub objective: PlanObjective, // ranks the continuations
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        Self {
+            steps: 3,
+            objective: PlanObjective::MaxDistance,
+        }
+    }
+}
+
+/// What the optimizer would have recommended at a full hour of the race
+#[derive(Clone)]
+pub struct Decision {
+    pub time: f64,           // full hour in hours since race start
+    pub leg: usize,          // index of the matched leg sailed at that time
+    pub from: usize,
+    pub to: usize,           // the next buoy, where the decision is due
+    pub rounding: f64,       // actual rounding of `to` in hours since race start
+    pub wind_direction: f64, // forecast at `time` in degrees
+    pub wind_speed: f64,     // forecast at `time` in knots
+    pub recommended: Option<Path>, // `None` if no continuation could be found
+    pub sailed: Path,        // the legs actually sailed next, in the forecast wind
+    pub horizon: f64,        // both continuations are compared up to this time
+    pub distance_left: f64,  // nm the recommendation covers more by the horizon, negative when the crew did better
+}
+
+/// A recorded race replayed hour by hour against the optimizer
+#[derive(Clone)]
+pub struct Replay {
+    pub legs: Vec<SailedLeg>,
+    pub decisions: Vec<Decision>,
+}
+
+impl Replay {
+    /// Distance left on the table over the whole race in nm, each rounding
+    /// counted once even if it was the next buoy for several hours
+    pub fn distance_left(&self) -> f64 {
+        let mut total = 0.0;
+        let mut counted = None;
+        for decision in &self.decisions {
+            if counted != Some(decision.leg) {
+                total += decision.distance_left;
+                counted = Some(decision.leg);
+            }
+        }
+        total
+    }
+}
+
+/// Replay a recorded track hour by hour
+///
+/// The track is matched onto the course. At every full hour the boat is on
+/// some leg, and at its end the crew decided where to sail next. The
+/// optimizer looks ahead as many legs from there, at the actual rounding
+/// time, as the crew sailed afterwards, at most `steps`. Both continuations
+/// are sailed in the forecast wind, so the comparison measures the choice of
+/// route and not the trim, and the distance covered up to the earlier of
+/// their ends is the distance left on the table.
+pub fn replay_race(
+    data: &RegattaData,
+    track: &[TrackPoint],
+    options: &ReplayOptions,
+) -> Result<Replay, UursError> {
+    if options.steps == 0 {
+        return Err(UursError::InvalidParameter("The replay needs to look ahead at least one leg".to_string()));
+    }
+    let legs = match_track(data, track);
+    let (Some(first), Some(last)) = (legs.first(), legs.last()) else {
+        return Err(UursError::InvalidParameter("No leg of the course found on the track".to_string()));
+    };
+
+    let mut decisions = Vec::new();
+    let mut hour = first.start.ceil();
+    while hour < last.end {
+        let Some(index) = legs.iter().position(|leg| leg.start <= hour && hour < leg.end) else {
+            hour += 1.0;
+            continue;
+        };
+        let leg = &legs[index];
+        let next = &legs[index + 1..];
+        if next.is_empty() {
+            break;
+        }
+        let steps = options.steps.min(next.len());
+        let route: Vec<usize> = std::iter::once(leg.to).chain(next[..steps].iter().map(|leg| leg.to)).collect();
+        let sailed = evaluate_route(data, &route, leg.end)?;
+        let recommended =
+            best_continuation(data, leg.to, leg.end, steps, &options.objective, &SearchOptions::default())?;
+
+        let (horizon, distance_left) = match &recommended {
+            Some(path) => {
+                let horizon = path.end_time.min(sailed.end_time).min(data.rules.scoring.deadline);
+                (horizon, distance_at(path, horizon) - distance_at(&sailed, horizon))
+            }
+            None => (leg.end, 0.0),
+        };
+        let wind = estimate_leg_performance(data, leg.from, leg.to, hour)?;
+        decisions.push(Decision {
+            time: hour,
+            leg: index,
+            from: leg.from,
+            to: leg.to,
+            rounding: leg.end,
+            wind_direction: wind.wind_direction,
+            wind_speed: wind.wind_speed,
+            recommended,
+            sailed,
+            horizon,
+            distance_left,
+        });
+        hour += 1.0;
+    }
+    Ok(Replay { legs, decisions })
+}
+
+/// Distance covered along a path by a time, assuming a constant speed on
+/// every step
+fn distance_at(path: &Path, time: f64) -> f64 {
+    path.steps.iter().fold(0.0, |distance, step| {
+        if time >= step.end_time {
+            distance + step.distance
+        } else if time > step.start_time {
+            distance + step.distance * (time - step.start_time) / (step.end_time - step.start_time)
+        } else {
+            distance
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay_race() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let route = ["OEVE", "WV12", "GVS3-M8", "WV12"].map(|name| data.get_boei_index(name).unwrap());
+        let path = evaluate_route(&data, &route, 0.0).unwrap();
+        let track = crate::track::track_along(&data, &path);
+
+        let replay = replay_race(&data, &track, &ReplayOptions::default()).unwrap();
+        assert_eq!(replay.legs.len(), 3);
+        // At the start the next buoy is WV12, with two more legs after it
+        assert_eq!(replay.decisions.len(), 2);
+        assert_eq!(data.boeien[replay.decisions[0].to].name, "WV12");
+        assert_eq!(replay.decisions[0].sailed.steps.len(), 2);
+        // At 1h the boat is on the way to GVS3-M8, with one leg to sail after it
+        let decision = &replay.decisions[1];
+        assert_eq!(decision.time, 1.0);
+        assert_eq!(data.boeien[decision.to].name, "GVS3-M8");
+        assert_eq!(decision.sailed.steps.len(), 1);
+        assert!(decision.recommended.is_some());
+        assert!(decision.horizon > decision.rounding);
+
+        assert!(replay_race(&data, &track, &ReplayOptions { steps: 0, ..ReplayOptions::default() }).is_err());
+    }
+}
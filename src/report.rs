@@ -0,0 +1,359 @@
+use crate::data::RegattaData;
+use crate::error::UursError;
+use crate::export::escape_xml;
+use crate::live::plan_progress;
+use crate::optimize::{Path, Step, estimate_leg_performance};
+use crate::plot::{BoatTrack, PlotConfig, create_fleet_plot, create_route_profile_plot};
+use crate::scoring::{corrected_distance, score_path};
+use crate::track::{TrackPoint, track_distance};
+use std::error::Error;
+use std::fmt::Write;
+
+/// Number of legs named as the biggest losses in the summary
+const WORST_LEGS: usize = 3;
+
+/// Planned and actual sailing of one leg of the plan
+#[derive(Clone, Debug)]
+pub struct LegAnalysis {
+    pub from: usize,
+    pub to: usize,
+    pub distance: f64,                // length of the leg in nm
+    pub planned_start: f64,           // in hours since race start
+    pub planned_end: f64,             // in hours since race start
+    pub actual_start: Option<f64>,    // `None` if the leg was not reached
+    pub actual_end: Option<f64>,      // `None` if the leg was not finished
+    pub sailed_distance: Option<f64>, // distance over ground on the leg in nm
+    pub predicted_speed: f64,         // polar speed at the actual start, or the planned one
+    pub actual_speed: Option<f64>,    // leg length over the time taken in knots
+    pub performance: Option<f64>,     // actual speed in percent of the predicted speed
+    pub wind_direction: f64,          // forecast when the leg was sailed in degrees
+    pub wind_speed: f64,              // forecast when the leg was sailed in knots
+    pub time_lost: Option<f64>,       // hours slower than predicted, negative when faster
+}
+
+/// Analysis of a recorded race against the planned route
+#[derive(Clone)]
+pub struct RaceReport {
+    pub plan: Path,
+    pub legs: Vec<LegAnalysis>,
+    pub track: Vec<TrackPoint>,
+    pub finish: Option<f64>,    // actual finish in hours since race start
+    pub scored_distance: f64,   // of the finished legs at their actual times in nm
+    pub rating: Option<f64>,    // handicap of the boat, `None` for none
+}
+
+impl RaceReport {
+    /// Total time lost against the predictions on the finished legs in hours
+    pub fn total_time_lost(&self) -> f64 {
+        self.legs.iter().filter_map(|leg| leg.time_lost).fold(0.0, |total, lost| total + lost)
+    }
+
+    /// Finished legs with the most time lost first
+    pub fn worst_legs(&self) -> Vec<&LegAnalysis> {
+        let mut legs: Vec<&LegAnalysis> = self
+            .legs
+            .iter()
+            .filter(|leg| leg.time_lost.is_some_and(|lost| lost > 0.0))
+            .collect();
+        legs.sort_by(|a, b| b.time_lost.unwrap().total_cmp(&a.time_lost.unwrap()));
+        legs
+    }
+
+    /// The scored distance corrected by the rating of the boat in nm
+    pub fn corrected_distance(&self) -> f64 {
+        corrected_distance(self.scored_distance, self.rating)
+    }
+}
+
+/// Compare a recorded track with the planned route leg by leg
+///
+/// Rounding times are taken from the track as for the live progress. The
+/// predicted speed of a leg comes from the polars and the wind forecast at
+/// the time the leg was actually started, so a late boat is measured against
+/// the wind it really had rather than the wind of the plan. The finished legs
+/// are scored at their actual rounding times, and corrected by the rating of
+/// the boat if it has one.
+pub fn analyze_race(
+    data: &RegattaData,
+    plan: &Path,
+    track: Vec<TrackPoint>,
+    rating: Option<f64>,
+) -> Result<RaceReport, Box<dyn Error>> {
+    let progress = plan_progress(data, plan, &track)?.ok_or("The track has no positions")?;
+
+    let legs: Vec<LegAnalysis> = plan
+        .steps
+        .iter()
+        .enumerate()
+        .map(|(i, step)| {
+            let actual_start = progress.marks[i].actual;
+            let actual_end = progress.marks[i + 1].actual.filter(|_| actual_start.is_some());
+            let performance =
+                estimate_leg_performance(data, step.from, step.to, actual_start.unwrap_or(step.start_time))?;
+            let predicted_speed = performance.estimated_speed;
+
+            let (mut sailed_distance, mut actual_speed, mut relative, mut time_lost) = (None, None, None, None);
+            if let (Some(start), Some(end)) = (actual_start, actual_end) {
+                let mut on_leg = vec![position_at(&track, start)];
+                on_leg.extend(track.iter().filter(|p| start < p.time && p.time < end).cloned());
+                on_leg.push(position_at(&track, end));
+                sailed_distance = Some(track_distance(&on_leg));
+                if end > start {
+                    let speed = step.distance / (end - start);
+                    actual_speed = Some(speed);
+                    relative = (predicted_speed > 0.0).then(|| speed / predicted_speed * 100.0);
+                }
+                if predicted_speed > 0.0 {
+                    time_lost = Some((end - start) - step.distance / predicted_speed);
+                }
+            }
+
+            Ok(LegAnalysis {
+                from: step.from,
+                to: step.to,
+                distance: step.distance,
+                planned_start: step.start_time,
+                planned_end: step.end_time,
+                actual_start,
+                actual_end,
+                sailed_distance,
+                predicted_speed,
+                actual_speed,
+                performance: relative,
+                wind_direction: performance.wind_direction,
+                wind_speed: performance.wind_speed,
+                time_lost,
+            })
+        })
+        .collect::<Result<_, UursError>>()?;
+
+    let finish = progress.marks.last().and_then(|mark| mark.actual);
+    let steps: Vec<Step> = legs
+        .iter()
+        .filter_map(|leg| {
+            let (start, end) = (leg.actual_start?, leg.actual_end?);
+            Some(Step {
+                from: leg.from,
+                to: leg.to,
+                distance: leg.distance,
+                speed: leg.actual_speed.unwrap_or(0.0),
+                start_time: start,
+                end_time: end,
+                wait: 0.0,
+            })
+        })
+        .collect();
+    let sailed = Path {
+        total_distance: steps.iter().fold(0.0, |total, step| total + step.distance),
+        scored_distance: 0.0,
+        qualification_margin: None,
+        end_time: steps.last().map_or(0.0, |step| step.end_time),
+        steps,
+    };
+    let scored_distance = score_path(data, &sailed, &data.rules.scoring).scored_distance;
+    Ok(RaceReport {
+        plan: plan.clone(),
+        legs,
+        track,
+        finish,
+        scored_distance,
+        rating,
+    })
+}
+
+/// Position on the track at a time, interpolated between the points around it
+fn position_at(track: &[TrackPoint], time: f64) -> TrackPoint {
+    let i = track.partition_point(|p| p.time < time);
+    match (i.checked_sub(1).map(|j| &track[j]), track.get(i)) {
+        (Some(before), Some(after)) if after.time > before.time => {
+            let t = (time - before.time) / (after.time - before.time);
+            TrackPoint {
+                lat: before.lat + t * (after.lat - before.lat),
+                long: before.long + t * (after.long - before.long),
+                time,
+                ..after.clone()
+            }
+        }
+        (_, Some(point)) | (Some(point), None) => point.clone(),
+        (None, None) => unreachable!("the track has positions"),
+    }
+}
+
+/// Render a race report as a self-contained HTML page, which can be printed
+/// to PDF from the browser
+pub fn report_to_html(data: &RegattaData, report: &RaceReport, title: &str) -> Result<String, Box<dyn Error>> {
+    let clock = |time: f64| match data.race_clock.format_clock(time) {
+        Some(clock) => format!("{time:.2} h ({clock})"),
+        None => format!("{time:.2} h"),
+    };
+    let optional = |value: Option<f64>, format: &dyn Fn(f64) -> String| value.map_or("–".to_string(), format);
+    let name = |buoy: usize| escape_xml(&data.boeien[buoy].name);
+
+    let mut html = String::new();
+    writeln!(html, "<!DOCTYPE html>")?;
+    writeln!(html, r#"<html lang="en"><head><meta charset="UTF-8">"#)?;
+    writeln!(html, "<title>{}</title>", escape_xml(title))?;
+    writeln!(
+        html,
+        "<style>body {{ font-family: sans-serif; margin: 2em; color: #2c3e50; }} \
+         table {{ border-collapse: collapse; margin: 1em 0; }} \
+         th, td {{ border: 1px solid #ccc; padding: 4px 8px; text-align: right; }} \
+         th {{ background: #ecf0f1; }} td.leg {{ text-align: left; }} \
+         .lost {{ color: #c0392b; }} .gained {{ color: #27ae60; }} \
+         svg {{ max-width: 100%; height: auto; }}</style>"
+    )?;
+    writeln!(html, "</head><body>")?;
+    writeln!(html, "<h1>{}</h1>", escape_xml(title))?;
+
+    // Summary
+    let first = report.plan.steps.first().ok_or("The plan has no steps")?;
+    let route: Vec<String> = std::iter::once(first.from)
+        .chain(report.plan.steps.iter().map(|step| step.to))
+        .map(name)
+        .collect();
+    writeln!(html, "<h2>Summary</h2><ul>")?;
+    writeln!(html, "<li>Planned route: {} ({:.2} nm)</li>", route.join(" → "), report.plan.total_distance)?;
+    writeln!(html, "<li>Planned finish: {}</li>", clock(report.plan.end_time))?;
+    match report.finish {
+        Some(finish) => writeln!(
+            html,
+            "<li>Actual finish: {}, {:+.2} h against the plan</li>",
+            clock(finish),
+            finish - report.plan.end_time
+        )?,
+        None => writeln!(html, "<li>The track doesn't reach the end of the planned route</li>")?,
+    }
+    writeln!(html, "<li>Distance over ground: {:.2} nm</li>", track_distance(&report.track))?;
+    writeln!(html, "<li>Scored distance of the finished legs: {:.2} nm</li>", report.scored_distance)?;
+    if let Some(rating) = report.rating {
+        writeln!(
+            html,
+            "<li>Corrected by the rating of {rating}: {:.2} nm</li>",
+            report.corrected_distance()
+        )?;
+    }
+    writeln!(
+        html,
+        "<li>Time lost against the polars on the finished legs: {:+.2} h</li>",
+        report.total_time_lost()
+    )?;
+    let worst = report.worst_legs();
+    if !worst.is_empty() {
+        let legs: Vec<String> = worst
+            .iter()
+            .take(WORST_LEGS)
+            .map(|leg| format!("{} → {} ({:.2} h)", name(leg.from), name(leg.to), leg.time_lost.unwrap()))
+            .collect();
+        writeln!(html, "<li>Most time lost on: {}</li>", legs.join(", "))?;
+    }
+    writeln!(html, "</ul>")?;
+
+    // Leg table
+    writeln!(html, "<h2>Legs</h2><table>")?;
+    writeln!(
+        html,
+        "<tr><th>Leg</th><th>Distance (nm)</th><th>Sailed (nm)</th><th>Planned start</th>\
+         <th>Actual start</th><th>Actual end</th><th>Predicted (kn)</th><th>Actual (kn)</th>\
+         <th>Polar %</th><th>Wind</th><th>Time lost (h)</th><th>Behind plan (h)</th></tr>"
+    )?;
+    for leg in &report.legs {
+        let lost = match leg.time_lost {
+            Some(lost) => {
+                let class = if lost > 0.0 { "lost" } else { "gained" };
+                format!(r#"<td class="{class}">{lost:+.2}</td>"#)
+            }
+            None => "<td>–</td>".to_string(),
+        };
+        writeln!(
+            html,
+            r#"<tr><td class="leg">{} → {}</td><td>{:.2}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.2}</td><td>{}</td><td>{}</td><td>{:.0}° {:.1} kn</td>{lost}<td>{}</td></tr>"#,
+            name(leg.from),
+            name(leg.to),
+            leg.distance,
+            optional(leg.sailed_distance, &|d| format!("{d:.2}")),
+            clock(leg.planned_start),
+            optional(leg.actual_start, &clock),
+            optional(leg.actual_end, &clock),
+            leg.predicted_speed,
+            optional(leg.actual_speed, &|s| format!("{s:.2}")),
+            optional(leg.performance, &|p| format!("{p:.0}")),
+            leg.wind_direction,
+            leg.wind_speed,
+            optional(leg.actual_end, &|end| format!("{:+.2}", end - leg.planned_end)),
+        )?;
+    }
+    writeln!(html, "</table>")?;
+    writeln!(
+        html,
+        "<p>Speeds are measured along the leg, the wind is the forecast at the actual start of \
+         the leg. Time lost compares the time taken with the polar prediction for that wind.</p>"
+    )?;
+
+    // Charts
+    let track = BoatTrack {
+        name: "Track".to_string(),
+        positions: report.track.iter().map(|p| (p.lat, p.long)).collect(),
+    };
+    writeln!(html, "<h2>Track</h2>")?;
+    writeln!(html, "{}", create_fleet_plot(data, &[track], PlotConfig::default())?)?;
+    writeln!(html, "<h2>Planned Route Profile</h2>")?;
+    writeln!(html, "{}", create_route_profile_plot(data, &report.plan, PlotConfig::default())?)?;
+    writeln!(html, "</body></html>")?;
+    Ok(html)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::load_regatta_data;
+    use crate::optimize::evaluate_route;
+    use chrono::Utc;
+
+    #[test]
+    fn test_analyze_race() {
+        let data = load_regatta_data().unwrap();
+        let route: Vec<usize> = ["SPORT-A", "KR-A", "LC1"]
+            .iter()
+            .map(|name| data.get_boei_index(name).unwrap())
+            .collect();
+        let plan = evaluate_route(&data, &route, 0.0).unwrap();
+
+        // Sail the first leg in twice the planned time and stop halfway the second
+        let position = |buoy: usize, time: f64| {
+            let (lat, long) = data.boeien[buoy].coordinates().unwrap();
+            TrackPoint {
+                lat,
+                long,
+                time,
+                speed: None,
+                course: None,
+                received: Utc::now(),
+            }
+        };
+        let first = &plan.steps[0];
+        let (a, b) = (
+            data.boeien[route[1]].coordinates().unwrap(),
+            data.boeien[route[2]].coordinates().unwrap(),
+        );
+        let mut halfway = position(route[1], 2.0 * first.end_time + 0.5);
+        (halfway.lat, halfway.long) = ((a.0 + b.0) / 2.0, (a.1 + b.1) / 2.0);
+        let track = vec![position(route[0], 0.0), position(route[1], 2.0 * first.end_time), halfway];
+
+        let report = analyze_race(&data, &plan, track, Some(110.0)).unwrap();
+        assert_eq!(report.legs.len(), 2);
+        let leg = &report.legs[0];
+        assert!((leg.performance.unwrap() - 50.0).abs() < 1.0);
+        assert!((leg.time_lost.unwrap() - first.end_time).abs() < 0.05);
+        assert!(report.legs[1].actual_start.is_some());
+        assert!(report.legs[1].time_lost.is_none());
+        assert!(report.finish.is_none());
+        assert_eq!(report.worst_legs().len(), 1);
+        // Only the finished first leg scores
+        assert!((report.scored_distance - first.distance).abs() < 1e-9);
+        assert!((report.corrected_distance() - 1.1 * first.distance).abs() < 1e-9);
+
+        let html = report_to_html(&data, &report, "Test <Race>").unwrap();
+        assert!(html.contains("<h1>Test &lt;Race&gt;</h1>"));
+        assert!(html.contains("SPORT-A → KR-A"));
+    }
+}
@@ -0,0 +1,348 @@
+use crate::data::RegattaData;
+use crate::error::UursError;
+use crate::optimize::{Path, Step, estimate_leg_performance};
+use crate::params::ParamLimits;
+use crate::scoring::{ScoringRules, score_path};
+use serde::{Deserialize, Serialize};
+
+/// File with the rules of the edition the data is for
+pub const RULES_FILE: &str = "data/rules.toml";
+
+/// The rules of an edition of the race, which change from year to year
+///
+/// The maximum number of every start and leg comes from the data files, the
+/// rule set caps the legs on top of that. Every field is optional in the
+/// file and defaults to the rules of the current edition.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RuleSet {
+    pub edition: Option<u32>,                  // year of the race the rules are for
+    pub max_leg_sailings: Option<u32>,         // of every leg, both directions together
+    pub forbidden_sequences: Vec<Vec<String>>, // buoys which must not be rounded in this order in a row
+    pub qualification_distance: Option<f64>,   // scored nm needed to qualify, `None` if there is no minimum
+    pub scoring: ScoringRules,                 // the finish window and the penalty for finishing late
+    pub limits: ParamLimits,                   // of the searches users may start
+}
+
+impl Default for RuleSet {
+    fn default() -> Self {
+        Self {
+            edition: None,
+            max_leg_sailings: Some(2),
+            forbidden_sequences: Vec::new(),
+            qualification_distance: None,
+            scoring: ScoringRules::default(),
+            limits: ParamLimits::default(),
+        }
+    }
+}
+
+impl RuleSet {
+    /// Parse a rule set in TOML and check it against the buoys of the data
+    pub fn parse(content: &str, data: &RegattaData) -> Result<Self, UursError> {
+        let rules: Self = toml::from_str(content).map_err(|e| UursError::InvalidParameter(e.to_string()))?;
+        for sequence in &rules.forbidden_sequences {
+            if sequence.len() < 2 {
+                return Err(UursError::InvalidParameter(
+                    "A forbidden sequence needs at least two buoys".to_string(),
+                ));
+            }
+            if let Some(name) = sequence.iter().find(|name| data.get_boei_index(name).is_none()) {
+                return Err(UursError::UnknownBuoy(name.clone()));
+            }
+        }
+        if rules.qualification_distance.is_some_and(|distance| distance <= 0.0) {
+            return Err(UursError::InvalidParameter(
+                "The qualification distance must be positive".to_string(),
+            ));
+        }
+        let scoring = &rules.scoring;
+        if scoring.deadline <= 0.0 || scoring.late_allowance < 0.0 || scoring.late_penalty_factor < 0.0 {
+            return Err(UursError::InvalidParameter(
+                "The deadline must be positive, the late allowance and penalty factor can't be negative".to_string(),
+            ));
+        }
+        if rules.limits.max_steps == 0 || rules.limits.max_paths == 0 || rules.limits.max_path_length == 0 {
+            return Err(UursError::InvalidParameter(
+                "The limits of the steps, paths, and path length must be positive".to_string(),
+            ));
+        }
+        if rules.max_leg_sailings == Some(0) {
+            return Err(UursError::InvalidParameter("max_leg_sailings must be at least 1".to_string()));
+        }
+        Ok(rules)
+    }
+
+    /// How often a leg with the given maximum number in the data may be sailed
+    pub fn max_sailings(&self, max_number: u32) -> u32 {
+        self.max_leg_sailings.map_or(max_number, |max| max.min(max_number))
+    }
+
+    /// Scored miles above the qualification distance, negative if short of
+    /// it, `None` if there is no minimum distance
+    pub fn qualification_margin(&self, scored_distance: f64) -> Option<f64> {
+        self.qualification_distance.map(|distance| scored_distance - distance)
+    }
+
+    /// The forbidden sequence the given buoys end with, if any
+    pub fn forbidden_sequence(&self, data: &RegattaData, buoys: &[usize]) -> Option<&[String]> {
+        self.forbidden_sequences
+            .iter()
+            .find(|sequence| {
+                sequence.len() <= buoys.len()
+                    && buoys[buoys.len() - sequence.len()..]
+                        .iter()
+                        .zip(sequence.iter())
+                        .all(|(&buoy, name)| data.boeien[buoy].name == *name)
+            })
+            .map(|sequence| sequence.as_slice())
+    }
+}
+
+/// A rule broken by a route, with the entry of the rule set or the data file
+/// the rule comes from
+#[derive(Clone, Debug, Serialize)]
+pub struct RuleViolation {
+    pub leg: Option<usize>, // number of the leg, from 1, `None` for the route as a whole
+    pub rule: String,       // e.g. "rules.toml: forbidden_sequences[0]" or "rakken.csv: MaxNumber"
+    pub message: String,
+}
+
+/// A planned or sailed route as path: a sailed route with the times its buoys
+/// were passed, a planned one sailed at the speeds of the polars from the
+/// start time
+///
+/// Unlike `evaluate_route`, buoys without a start or leg between them are
+/// sailed over the direct distance, so such a route can still be checked.
+pub fn route_path(
+    data: &RegattaData,
+    route: &[usize],
+    start_time: f64,
+    passing_times: Option<&[f64]>,
+) -> Result<Path, UursError> {
+    if route.len() < 2 {
+        return Err(UursError::InvalidParameter("A route needs at least two buoys".to_string()));
+    }
+    if let Some(times) = passing_times {
+        if times.len() != route.len() {
+            return Err(UursError::InvalidParameter(format!(
+                "{} passing times given for {} buoys",
+                times.len(),
+                route.len()
+            )));
+        }
+        if times.windows(2).any(|pair| pair[1] < pair[0]) {
+            return Err(UursError::InvalidParameter("The passing times must not go back in time".to_string()));
+        }
+    }
+
+    let mut steps = Vec::with_capacity(route.len() - 1);
+    let mut time = passing_times.map_or(start_time, |times| times[0]);
+    for (i, pair) in route.windows(2).enumerate() {
+        let performance = estimate_leg_performance(data, pair[0], pair[1], time)?;
+        let distance = performance.official_distance.unwrap_or(performance.great_circle_distance);
+        let end_time = match passing_times {
+            Some(times) => times[i + 1],
+            // 1 knot as fallback if the polars give no speed, like the searches
+            None => time + distance / if performance.estimated_speed > 0.0 { performance.estimated_speed } else { 1.0 },
+        };
+        steps.push(Step {
+            from: pair[0],
+            to: pair[1],
+            distance,
+            speed: if end_time > time { distance / (end_time - time) } else { 0.0 },
+            start_time: time,
+            end_time,
+            wait: 0.0,
+        });
+        time = end_time;
+    }
+    let total_distance = steps.iter().fold(0.0, |total, step| total + step.distance);
+    Ok(Path::new(data, steps, total_distance, time))
+}
+
+/// Check a route against every rule of the race, for crews before and after
+/// the race and for the committee alike
+///
+/// A route begins with a start and sails only starts and legs of the course,
+/// none more often than its maximum number and the rule set allow, rounds no
+/// sequence the rule set forbids, rounds no buoy after the time limit, and
+/// finishes before the deadline. With a qualification distance in the rule
+/// set, the scored miles must reach it.
+pub fn check_route(data: &RegattaData, path: &Path) -> Vec<RuleViolation> {
+    let rules = &data.rules;
+    let scoring = &rules.scoring;
+    let violation = |leg: Option<usize>, rule: &str, message: String| RuleViolation {
+        leg,
+        rule: rule.to_string(),
+        message,
+    };
+    let mut violations = Vec::new();
+    let mut sailed: Vec<((bool, usize), u32)> = Vec::new();
+    let mut buoys: Vec<usize> = path.steps.first().map(|step| step.from).into_iter().collect();
+    let score = score_path(data, path, scoring);
+
+    for (i, step) in path.steps.iter().enumerate() {
+        let leg = Some(i + 1);
+        let (from, to) = (&data.boeien[step.from].name, &data.boeien[step.to].name);
+        buoys.push(step.to);
+
+        let start = data.starts.iter().position(|start| start.from == *from && start.to == *to);
+        if i == 0 && start.is_none() {
+            violations.push(violation(leg, "starts.csv", format!("The route doesn't begin with a start, {from} - {to} is none")));
+        }
+        let rak = data
+            .rakken
+            .iter()
+            .position(|rak| (rak.from == *from && rak.to == *to) || (rak.from == *to && rak.to == *from));
+        let limit = match (start, rak) {
+            (Some(index), _) => Some(((true, index), data.starts[index].max_number, "starts.csv: MaxNumber")),
+            (None, Some(index)) => {
+                let max_number = data.rakken[index].max_number;
+                let limit = rules.max_sailings(max_number);
+                let rule = if limit < max_number { "rules.toml: max_leg_sailings" } else { "rakken.csv: MaxNumber" };
+                Some(((false, index), limit, rule))
+            }
+            (None, None) => {
+                violations.push(violation(leg, "rakken.csv", format!("There is no start or leg from {from} to {to}")));
+                None
+            }
+        };
+        if let Some((edge, limit, rule)) = limit {
+            let count = match sailed.iter_mut().find(|(sailed_edge, _)| *sailed_edge == edge) {
+                Some((_, count)) => {
+                    *count += 1;
+                    *count
+                }
+                None => {
+                    sailed.push((edge, 1));
+                    1
+                }
+            };
+            if count > limit {
+                violations.push(violation(leg, rule, format!("{from} - {to} is sailed more than {limit} time(s)")));
+            }
+        }
+
+        if let Some(sequence) = rules.forbidden_sequence(data, &buoys) {
+            let index = rules.forbidden_sequences.iter().position(|forbidden| forbidden == sequence).unwrap();
+            violations.push(violation(
+                leg,
+                &format!("rules.toml: forbidden_sequences[{index}]"),
+                format!("The route rounds {} in a row", sequence.join(" - ")),
+            ));
+        }
+        if step.end_time > scoring.deadline + scoring.late_allowance && !score.legs[i].partial {
+            violations.push(violation(
+                leg,
+                "rules.toml: scoring.late_allowance",
+                format!(
+                    "{to} is rounded at {}, after the time limit of {:.2}h",
+                    data.race_clock.describe(step.end_time),
+                    scoring.deadline + scoring.late_allowance
+                ),
+            ));
+        }
+    }
+
+    if score.late_penalty > 0.0 {
+        violations.push(violation(
+            None,
+            "rules.toml: scoring.deadline",
+            format!(
+                "Finishes {:.2}h after the deadline, a penalty of {:.2} nm",
+                score.finish.unwrap_or(path.end_time) - scoring.deadline,
+                score.late_penalty
+            ),
+        ));
+    }
+    if let Some(margin) = rules.qualification_margin(score.scored_distance).filter(|&margin| margin < 0.0) {
+        violations.push(violation(
+            None,
+            "rules.toml: qualification_distance",
+            format!(
+                "Scores {:.2} nm, {:.2} nm short of the qualification distance",
+                score.scored_distance, -margin
+            ),
+        ));
+    }
+    violations
+}
+
+/// Load the rules from [`RULES_FILE`], the defaults if there is none
+pub fn load_rule_set(data: &RegattaData) -> Result<RuleSet, UursError> {
+    match std::fs::read_to_string(RULES_FILE) {
+        Ok(content) => RuleSet::parse(&content, data).map_err(|e| UursError::data_load(RULES_FILE, e)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(RuleSet::default()),
+        Err(e) => Err(UursError::data_load(RULES_FILE, e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rule_set() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let index = |name: &str| data.get_boei_index(name).unwrap();
+        let rules = RuleSet::parse(
+            r#"
+            edition = 2025
+            max_leg_sailings = 1
+            forbidden_sequences = [["WV12", "GVS3-M8", "WV12"]]
+            qualification_distance = 60.0
+
+            [scoring]
+            late_allowance = 0.25
+            "#,
+            &data,
+        )
+        .unwrap();
+        assert_eq!(rules.edition, Some(2025));
+        assert_eq!(rules.max_sailings(2), 1);
+        assert_eq!(rules.scoring.late_allowance, 0.25);
+        assert_eq!(rules.scoring.deadline, ScoringRules::default().deadline);
+        assert_eq!(rules.qualification_margin(55.5), Some(-4.5));
+        assert_eq!(RuleSet::default().qualification_margin(55.5), None);
+
+        let route = [index("OEVE"), index("WV12"), index("GVS3-M8"), index("WV12")];
+        assert!(rules.forbidden_sequence(&data, &route).is_some());
+        assert!(rules.forbidden_sequence(&data, &route[..3]).is_none());
+
+        assert!(matches!(
+            RuleSet::parse("forbidden_sequences = [[\"NOWHERE\", \"WV12\"]]", &data),
+            Err(UursError::UnknownBuoy(_))
+        ));
+        assert!(RuleSet::parse("max_repetitions = 2", &data).is_err());
+    }
+
+    #[test]
+    fn test_check_route() {
+        let mut data = crate::data::load_regatta_data().unwrap();
+        let index = |name: &str| data.get_boei_index(name).unwrap();
+        let (oeve, wv12, gvs3) = (index("OEVE"), index("WV12"), index("GVS3-M8"));
+
+        let path = route_path(&data, &[oeve, wv12, gvs3, wv12], 0.0, None).unwrap();
+        assert!(check_route(&data, &path).is_empty());
+
+        // Every broken rule is reported with its entry
+        data.rules.forbidden_sequences = vec![vec!["WV12".to_string(), "GVS3-M8".to_string(), "WV12".to_string()]];
+        data.rules.qualification_distance = Some(100.0);
+        let path = route_path(&data, &[oeve, wv12, gvs3, wv12, gvs3], 0.0, None).unwrap();
+        let rules: Vec<String> = check_route(&data, &path).into_iter().map(|violation| violation.rule).collect();
+        assert_eq!(
+            rules,
+            ["rules.toml: forbidden_sequences[0]", "rakken.csv: MaxNumber", "rules.toml: qualification_distance"]
+        );
+
+        // A sailed route with its passing times, rounding the last buoy too late
+        let times = [0.0, 1.0, data.rules.scoring.deadline + 1.0];
+        let path = route_path(&data, &[wv12, gvs3, oeve], 0.0, Some(&times)).unwrap();
+        let violations = check_route(&data, &path);
+        assert_eq!(violations[0].rule, "starts.csv");
+        assert!(violations.iter().any(|violation| violation.rule == "rakken.csv" && violation.leg == Some(2)));
+        assert!(violations.iter().any(|violation| violation.rule == "rules.toml: scoring.late_allowance"));
+        assert!(route_path(&data, &[wv12, gvs3], 0.0, Some(&[1.0, 0.0])).is_err());
+    }
+}
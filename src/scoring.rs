@@ -0,0 +1,220 @@
+use crate::data::RegattaData;
+use crate::optimize::Path;
+use serde::{Deserialize, Serialize};
+
+/// How the leg a boat is sailing at the finish gun counts
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PartialLegCredit {
+    #[default]
+    LastMark, // only the legs up to the last mark rounded in time count
+    Fraction, // the part of the leg sailed by the deadline counts as well
+}
+
+/// The rules by which the miles of a route are counted
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ScoringRules {
+    pub deadline: f64,            // end of the race in hours since the start
+    pub late_allowance: f64,      // hours a boat may finish after the deadline, with a penalty
+    pub late_penalty_factor: f64, // the penalty in multiples of the distance sailed in the overtime
+    pub partial_legs: PartialLegCredit, // credit for the leg being sailed at the finish gun
+}
+
+impl Default for ScoringRules {
+    fn default() -> Self {
+        Self {
+            deadline: 24.0,
+            late_allowance: 0.5,
+            late_penalty_factor: 2.0,
+            partial_legs: PartialLegCredit::LastMark,
+        }
+    }
+}
+
+/// Rating of the reference boat, whose miles are not corrected
+pub const REFERENCE_RATING: f64 = 100.0;
+
+/// Correct scored miles by the rating of a boat like the SW handicap: the
+/// higher the rating, the slower the boat and the more its miles count.
+/// Without a rating the miles stay as they are.
+pub fn corrected_distance(distance: f64, rating: Option<f64>) -> f64 {
+    rating.map_or(distance, |rating| distance * rating / REFERENCE_RATING)
+}
+
+/// A leg of a scored route with the distance it counts
+#[derive(Clone, Debug, Serialize)]
+pub struct ScoredLeg {
+    pub from: String,
+    pub to: String,
+    pub distance: f64,          // official distance in nm
+    pub counted: f64,           // distance counted by the rules in nm
+    pub remark: Option<String>, // why the leg doesn't count, or only in part
+    pub partial: bool,          // sailed at the finish gun and counted up to it
+}
+
+/// The officially counted miles of a route
+#[derive(Clone, Debug, Serialize)]
+pub struct Score {
+    pub legs: Vec<ScoredLeg>,
+    pub raw_distance: f64,     // sum of all legs in nm
+    pub counted_distance: f64, // sum of the legs which count in nm
+    pub finish: Option<f64>,   // rounding of the last leg which counts
+    pub late_penalty: f64,     // in nm
+    pub scored_distance: f64,  // counted distance less the penalty, never negative
+}
+
+/// Score a route by the rules of the race
+///
+/// A start or leg counts at most as often as its maximum number in the data
+/// and the rule set of the data allow, both directions together, further
+/// sailings count nothing, and neither does a leg which completes a
+/// sequence of buoys forbidden by the rule set. Legs rounded
+/// after the deadline plus the late allowance don't count either, except
+/// that with `PartialLegCredit::Fraction` the leg being sailed at the
+/// deadline counts with the part sailed by then, in proportion to its time.
+/// If the last leg which counts in full is rounded after the deadline, the
+/// boat finished late and loses the distance it sails in the overtime at its
+/// average speed, times the penalty factor.
+pub fn score_path(data: &RegattaData, path: &Path, rules: &ScoringRules) -> Score {
+    let mut sailed: Vec<((bool, usize), u32)> = Vec::new();
+    let mut legs = Vec::with_capacity(path.steps.len());
+    let mut finish = None;
+    let mut buoys: Vec<usize> = path.steps.first().map(|step| step.from).into_iter().collect();
+
+    for step in &path.steps {
+        let (from, to) = (&data.boeien[step.from].name, &data.boeien[step.to].name);
+        buoys.push(step.to);
+        let mut partial = false;
+        let remark = match max_number(data, from, to) {
+            Some((edge, max_number)) => {
+                let count = match sailed.iter_mut().find(|(sailed_edge, _)| *sailed_edge == edge) {
+                    Some((_, count)) => {
+                        *count += 1;
+                        *count
+                    }
+                    None => {
+                        sailed.push((edge, 1));
+                        1
+                    }
+                };
+                if count > max_number {
+                    Some(format!("sailed more than {max_number} time(s)"))
+                } else if let Some(sequence) = data.rules.forbidden_sequence(data, &buoys) {
+                    Some(format!("completes the forbidden sequence {}", sequence.join(" - ")))
+                } else if step.end_time > rules.deadline + rules.late_allowance {
+                    partial = rules.partial_legs == PartialLegCredit::Fraction && step.start_time < rules.deadline;
+                    Some(if partial {
+                        "sailed at the finish gun, counts up to the deadline".to_string()
+                    } else {
+                        "rounded after the time limit".to_string()
+                    })
+                } else {
+                    None
+                }
+            }
+            None => Some("not a start or leg of the course".to_string()),
+        };
+        if remark.is_none() {
+            finish = Some(step.end_time);
+        }
+        let counted = if remark.is_none() {
+            step.distance
+        } else if partial {
+            step.distance * (rules.deadline - step.start_time) / (step.end_time - step.start_time)
+        } else {
+            0.0
+        };
+        legs.push(ScoredLeg {
+            from: from.clone(),
+            to: to.clone(),
+            distance: step.distance,
+            counted,
+            remark,
+            partial,
+        });
+    }
+
+    let raw_distance = legs.iter().fold(0.0, |total, leg| total + leg.distance);
+    let counted_distance = legs.iter().fold(0.0, |total, leg| total + leg.counted);
+    let late_penalty = match (finish, path.steps.first()) {
+        (Some(finish), Some(first)) if finish > rules.deadline && finish > first.start_time => {
+            let average_speed = counted_distance / (finish - first.start_time);
+            rules.late_penalty_factor * average_speed * (finish - rules.deadline)
+        }
+        _ => 0.0,
+    };
+
+    Score {
+        legs,
+        raw_distance,
+        counted_distance,
+        finish,
+        late_penalty,
+        scored_distance: (counted_distance - late_penalty).max(0.0),
+    }
+}
+
+/// The start or leg between two buoys, identified by whether it is a start
+/// and its index, with its maximum number under the rule set
+fn max_number(data: &RegattaData, from: &str, to: &str) -> Option<((bool, usize), u32)> {
+    if let Some(index) = data.starts.iter().position(|start| start.from == from && start.to == to) {
+        return Some(((true, index), data.starts[index].max_number));
+    }
+    data.rakken
+        .iter()
+        .position(|rak| (rak.from == from && rak.to == to) || (rak.from == to && rak.to == from))
+        .map(|index| ((false, index), data.rules.max_sailings(data.rakken[index].max_number)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimize::evaluate_route;
+
+    #[test]
+    fn test_score_path() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let index = |name: &str| data.get_boei_index(name).unwrap();
+        let (oeve, wv12, gvs3) = (index("OEVE"), index("WV12"), index("GVS3-M8"));
+        let rules = ScoringRules::default();
+
+        let path = evaluate_route(&data, &[oeve, wv12, gvs3, wv12], 0.0).unwrap();
+        let score = score_path(&data, &path, &rules);
+        assert!((score.scored_distance - path.total_distance).abs() < 1e-9);
+        assert_eq!(score.late_penalty, 0.0);
+
+        // The third time on the leg between WV12 and GVS3-M8 doesn't count
+        let path = evaluate_route(&data, &[oeve, wv12, gvs3, wv12, gvs3], 0.0).unwrap();
+        let score = score_path(&data, &path, &rules);
+        assert_eq!(score.legs[3].counted, 0.0);
+        assert!(score.legs[3].remark.is_some());
+        assert!((score.raw_distance - score.counted_distance - path.steps[3].distance).abs() < 1e-9);
+
+        // Finishing 15 minutes late costs twice the distance sailed in them
+        let late = evaluate_route(&data, &[oeve, wv12], rules.deadline + 0.25 - path.steps[0].end_time).unwrap();
+        let score = score_path(&data, &late, &rules);
+        let overtime = late.end_time - rules.deadline;
+        let speed = late.total_distance / (late.end_time - late.steps[0].start_time);
+        assert!((score.late_penalty - 2.0 * speed * overtime).abs() < 1e-9);
+        assert!(score.scored_distance < late.total_distance);
+
+        // A leg rounded after the time limit doesn't count at all
+        let too_late = evaluate_route(&data, &[oeve, wv12], rules.deadline).unwrap();
+        assert_eq!(score_path(&data, &too_late, &rules).scored_distance, 0.0);
+
+        // Unless the part sailed by the finish gun counts
+        let at_gun = evaluate_route(&data, &[wv12, gvs3], rules.deadline - 0.2).unwrap();
+        let step = &at_gun.steps[0];
+        assert!(step.end_time > rules.deadline + rules.late_allowance);
+        assert_eq!(score_path(&data, &at_gun, &rules).scored_distance, 0.0);
+        let fraction = ScoringRules {
+            partial_legs: PartialLegCredit::Fraction,
+            ..ScoringRules::default()
+        };
+        let score = score_path(&data, &at_gun, &fraction);
+        assert!(score.legs[0].partial);
+        assert!((score.scored_distance - step.distance * 0.2 / (step.end_time - step.start_time)).abs() < 1e-9);
+        assert_eq!(score.late_penalty, 0.0);
+    }
+}
@@ -1,109 +1,1436 @@
-use crate::data::RegattaData;
-use crate::optimize::{estimate_leg_performance, explore_paths, explore_target_paths};
-use serde::Deserialize;
+use crate::api::{
+    ActiveLegResponse, BoatResponse, CalibrationResponse, ConnectionResponse, CourseUpdateResponse, DashboardResponse, DataResponse, DataWarningResponse, EstimateResponse, ErrorResponse, ExplorationResponse, FindPathsResponse,
+    ApproachPositionResponse, ArrivalProfileResponse, FinishApproachResponse, HealthResponse, JobCreatedResponse, LeaderboardResponse, JobProgress, JobResponse, JobStatus, LegCalibrationResponse, MarkResponse,
+    ObservationResponse, PathResponse, PlanResponse, PositionResponse, ProgressResponse, ReloadResponse, SearchUpdate, SimulationResponse, TrackResponse, TrainingResponse,
+    SavedPlanResponse, ServerEvent, ValidateRouteResponse, VersionResponse, WindOverrideResponse, WindResponse,
+    openapi_document,
+};
+use crate::approach::{FINISH_BUOY, latest_departures};
+use crate::profile::{DEFAULT_INTERVAL, arrival_profile};
+use crate::cache::ResponseCache;
+use crate::calibration::{LegObservation, MIN_OBSERVATIONS, ObservationLog, calibrate, leg_corrections};
+use crate::data::{CourseUpdate, RaceTime, RegattaData, WindCondition, load_regatta_data, parse_polar_data};
+use crate::error::UursError;
+use crate::explorer::Explorations;
+use crate::i18n::{Lang, catalog};
+use crate::export::{path_to_geojson, path_to_gpx, paths_to_csv, simulation_to_geojson};
+use crate::fleet::{Boat, Fleet, validate_boat_name};
+use crate::leaderboard::leaderboard;
+use crate::limits::{SearchLimits, SearchPermit};
+use crate::live::{WindObservation, leg_progress, locate_leg, plan_progress};
+use crate::metrics::Metrics;
+use crate::mqtt::MqttTopic;
+use crate::nmea::InstrumentReading;
+use crate::optimize::{
+    Path, PathFilter, PathOrder, PathSelection, Pin, PIN_TOLERANCE, ObjectiveWeights, PlanObjective, RouteObjective, best_continuation, SearchBudget, SearchConstraints, SearchMonitor, SearchOptions, estimate_leg_performance,
+    SearchResults, estimate_search_nodes, evaluate_route, search_paths, search_target_paths, validate_route,
+};
+use crate::hierarchical::{estimate_hierarchical_nodes, plan_hierarchical};
+use crate::params::{MaxPaths, RaceHours, StepCount, WaitHours, check_route_length};
+use crate::plot::{BoatTrack, PlotConfig, Theme, create_deadline_map, create_fleet_plot, create_regatta_plot, create_route_map, create_route_profile_plot};
+use crate::rules::{check_route, route_path};
+use crate::plans::{PlanInput, PlanStore};
+use crate::sessions::{Session, SessionInput, SessionStore};
+use crate::simulate::{Current, SimulationOptions, simulate_route};
+use crate::storage::{JsonFiles, MemoryStorage, Storage};
+use crate::track::{Track, TrackPoint, track_distance};
+use crate::training::TrainingGames;
+use crate::webhook::Webhook;
+use futures_util::stream::SplitSink;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use std::sync::Arc;
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
 use tera::{Context, Tera};
-use warp::Filter;
+use tokio::sync::broadcast;
+use warp::{Filter, Reply};
+use warp::http::StatusCode;
 use warp::reply::html;
+use warp::ws::{Message, WebSocket};
+
+// Where and how the HTTP server listens
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub bind: IpAddr,
+    pub port: u16,
+    pub tls: Option<TlsConfig>, // serve HTTPS instead of plain HTTP
+    pub base_path: String,      // path prefix of all routes, e.g. "/uurs24" behind a reverse proxy
+    pub templates_dir: Option<PathBuf>, // load templates from disk instead of the embedded ones
+    pub cache_size: usize,      // number of responses to cache, 0 disables caching
+    pub auth_token: Option<String>, // bearer token required by the API and admin endpoints
+    pub max_search_nodes: u64,  // budget for the estimated size of a single search, 0 for no limit
+    pub search_budget: SearchBudget, // nodes, paths, and memory a single search may actually use
+    pub max_searches_per_client: usize, // concurrent searches per client IP, 0 for no limit
+    pub search_threads: usize, // searches running at the same time on the blocking thread pool, 0 for the number of CPUs
+    pub track_log: Option<PathBuf>, // append reported positions to this file and load them on startup
+    pub observations_log: Option<PathBuf>, // append leg speed observations to this file and load them on startup
+    pub nmea_listen: Option<SocketAddr>, // receive NMEA 0183 sentences over UDP and TCP here
+    pub sessions_file: Option<PathBuf>, // persist the planning sessions in this JSON file
+    pub plans_file: Option<PathBuf>, // persist the shared plans in this JSON file
+    pub database: Option<PathBuf>, // keep tracks, sessions, and plans in this SQLite database instead of the files
+    pub plan_session: Option<u64>, // planning session with the committed plan of the server's own boat
+    pub alert_webhook: Option<Webhook>, // alerts are posted here
+    pub alert_mqtt: Option<MqttTopic>, // alerts are published on this topic
+    pub alert_margin: f64, // hours to spare at the projected finish below which a boat is late
+    pub position_timeout: Option<Duration>, // alert when a boat reports no position for this long
+    pub lang: Option<Lang>, // language of the pages for clients which don't choose one, else by Accept-Language
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind: IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            port: 3030,
+            tls: None,
+            base_path: String::new(),
+            templates_dir: None,
+            cache_size: 256,
+            auth_token: None,
+            max_search_nodes: 5_000_000,
+            search_budget: SearchBudget {
+                max_memory: 256 * 1024 * 1024,
+                ..SearchBudget::default()
+            },
+            max_searches_per_client: 2,
+            search_threads: 0,
+            track_log: None,
+            observations_log: None,
+            nmea_listen: None,
+            sessions_file: None,
+            plans_file: None,
+            database: None,
+            plan_session: None,
+            alert_webhook: None,
+            alert_mqtt: None,
+            alert_margin: 0.0,
+            position_timeout: None,
+            lang: None,
+        }
+    }
+}
+
+// Certificate and private key files for HTTPS, both PEM encoded
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
+
+// Templates compiled into the binary, so the server runs from any directory
+// Longest query string accepted, longer ones are rejected before any of
+// their parameters is parsed or echoed back in an error message
+const MAX_QUERY_LENGTH: usize = 2048;
+
+const EMBEDDED_TEMPLATES: &[(&str, &str)] = &[
+    ("base.html", include_str!("../templates/base.html")),
+    ("index.html", include_str!("../templates/index.html")),
+    ("estimate.html", include_str!("../templates/estimate.html")),
+    ("estimate-leg.html", include_str!("../templates/estimate-leg.html")),
+    ("find-paths.html", include_str!("../templates/find-paths.html")),
+    ("find-paths-results.html", include_str!("../templates/find-paths-results.html")),
+    ("find-target.html", include_str!("../templates/find-target.html")),
+    ("dashboard.html", include_str!("../templates/dashboard.html")),
+    ("next.html", include_str!("../templates/next.html")),
+    ("fleet.html", include_str!("../templates/fleet.html")),
+    ("leaderboard.html", include_str!("../templates/leaderboard.html")),
+    ("finish.html", include_str!("../templates/finish.html")),
+    ("training.html", include_str!("../templates/training.html")),
+    ("explore.html", include_str!("../templates/explore.html")),
+    ("map.html", include_str!("../templates/map.html")),
+    ("wind.html", include_str!("../templates/wind.html")),
+    ("plan.html", include_str!("../templates/plan.html")),
+];
+
+// Load the templates from a directory on disk if given, the embedded ones otherwise
+fn load_templates(dir: Option<&std::path::Path>) -> Result<Tera, tera::Error> {
+    match dir {
+        Some(dir) => Tera::new(&format!("{}/**/*", dir.display())),
+        None => {
+            let mut tera = Tera::default();
+            tera.add_raw_templates(EMBEDDED_TEMPLATES.iter().copied())?;
+            Ok(tera)
+        }
+    }
+}
+
+// Load the templates and register the template functions
+fn build_tera(templates_dir: Option<&std::path::Path>, base_path: &str) -> Result<Tera, tera::Error> {
+    let mut tera = load_templates(templates_dir)?;
+    // Buoy and boat names end up in the pages, escape every value rendered
+    // into them, whatever the file names of templates loaded from disk
+    tera.autoescape_on(vec![".html", ".htm", ".xml", ".svg"]);
+
+    // Templates prefix their links with `{{ base_path() }}`
+    let template_base_path = base_path.to_string();
+    tera.register_function(
+        "base_path",
+        move |_: &HashMap<String, tera::Value>| Ok(tera::Value::String(template_base_path.clone())),
+    );
+    Ok(tera)
+}
+
+// Regatta data and templates shared by all handlers. A reload swaps them
+// atomically, requests in flight keep using the snapshot they started with.
+struct ServerState {
+    snapshot: RwLock<Snapshot>,
+    tera: RwLock<Arc<Tera>>,
+    cache: Arc<ResponseCache>,
+    templates_dir: Option<PathBuf>,
+    base_path: String,
+    auth_token: Option<String>, // required by the API and admin endpoints if set
+    lang: Option<Lang>, // default language of the pages and estimates
+    limits: Arc<SearchLimits>,
+    track: Arc<Track>, // reported boat positions, kept across reloads
+    wind: Arc<Mutex<Option<WindObservation>>>, // latest wind measured on board
+    sessions: Arc<SessionStore>,
+    plans: Arc<PlanStore>, // plans shared by link
+    fleet: Arc<Fleet>, // boats with their own polars and tracks, selected with `?boat=NAME`
+    plan_session: Option<u64>, // committed plan of the server's own boat
+    observations: Arc<ObservationLog>, // leg speeds submitted by the crews, for calibration
+    training: Arc<TrainingGames>, // games of the virtual regatta, kept in memory only
+    explorations: Arc<Explorations>, // routes of the path explorer, kept in memory only
+    events: broadcast::Sender<ServerEvent>, // feed of /api/events
+}
+
+// Events a subscriber of the feed may fall behind before it misses some
+const EVENT_CAPACITY: usize = 64;
+
+type State = Arc<ServerState>;
+
+// The regatta data served at one point in time together with its version
+#[derive(Clone)]
+struct Snapshot {
+    data: Arc<RegattaData>,
+    version: u64, // incremented on every reload, part of the cache keys
+    boat: Option<u64>, // registration of the boat whose polars are in the data
+}
+
+impl Snapshot {
+    // Start of the cache keys, so answers for different data or boats never mix
+    fn cache_prefix(&self) -> String {
+        match self.boat {
+            Some(registration) => format!("{}:boat{registration}", self.version),
+            None => self.version.to_string(),
+        }
+    }
+}
+
+impl ServerState {
+    fn snapshot(&self) -> Snapshot {
+        self.snapshot.read().unwrap().clone()
+    }
+
+    // The current snapshot with the polars of a boat if it has its own, and
+    // the clock of its class if it has one
+    fn boat_snapshot(&self, boat: Option<&Boat>) -> Snapshot {
+        let snapshot = self.snapshot();
+        let Some(boat) = boat else {
+            return snapshot;
+        };
+        let mut data = None;
+        if let Some(polar) = &boat.polar {
+            data.get_or_insert_with(|| RegattaData::clone(&snapshot.data)).polar_data = polar.clone();
+        }
+        if let Some(class) = &boat.class {
+            // The class may be gone after a reload of the data
+            if let Err(e) = data.get_or_insert_with(|| RegattaData::clone(&snapshot.data)).select_class(class) {
+                tracing::warn!("Boat {}: {e}", boat.name);
+            }
+        }
+        let data = data.map_or(snapshot.data, Arc::new);
+        Snapshot {
+            data,
+            version: snapshot.version,
+            boat: Some(boat.registration),
+        }
+    }
+
+    fn tera(&self) -> Arc<Tera> {
+        self.tera.read().unwrap().clone()
+    }
+
+    // Send an event to the subscribers of the feed, if there are any
+    fn publish(&self, event: ServerEvent) {
+        let _ = self.events.send(event);
+    }
+
+    // Load the data files and templates again and swap them in, leaving the
+    // current state untouched if anything fails to load
+    fn reload(&self) -> Result<ReloadResponse, ApiError> {
+        let mut data = load_regatta_data().map_err(ApiError::from)?;
+        // The race start and the class are configured on the command line,
+        // not in the files
+        let current = self.snapshot().data;
+        data.race_clock.start = current.race_clock.start;
+        data.race_clock.start_offset = current.race_clock.start_offset;
+        data.wind_data.start_offset = current.wind_data.start_offset;
+        for warning in data.warnings() {
+            tracing::warn!("{warning}");
+        }
+        let tera = build_tera(self.templates_dir.as_deref(), &self.base_path).map_err(|e| {
+            ApiError::internal("Reload failed", format!("Error loading templates: {e}"))
+        })?;
+
+        let data = calibrated(data, &self.observations);
+
+        let response = ReloadResponse {
+            version: 0,
+            buoys: data.boeien.len(),
+            legs: data.rakken.len(),
+            templates: tera.get_template_names().count(),
+        };
+        let version = {
+            let mut snapshot = self.snapshot.write().unwrap();
+            *snapshot = Snapshot {
+                data: Arc::new(data),
+                version: snapshot.version + 1,
+                boat: None,
+            };
+            snapshot.version
+        };
+        *self.tera.write().unwrap() = Arc::new(tera);
+        self.publish(ServerEvent::Data { version });
+        Ok(ReloadResponse { version, ..response })
+    }
+
+    // Change the course of the data being served. Unlike a reload, the
+    // cached responses the change can't affect carry over to the new version.
+    fn update_course(&self, update: CourseUpdate) -> Result<CourseUpdateResponse, ApiError> {
+        let mut snapshot = self.snapshot.write().unwrap();
+        let mut data = RegattaData::clone(&snapshot.data);
+        let version = snapshot.version;
+        data.apply_update(update.clone()).map_err(ApiError::from)?;
+        // Under the write lock no response of the new version is cached yet
+        let cached_responses = self.cache.rekey(|key| rekeyed(key, version, &update));
+        *snapshot = Snapshot {
+            data: Arc::new(data),
+            version: version + 1,
+            boat: None,
+        };
+        drop(snapshot);
+        self.publish(ServerEvent::Data { version: version + 1 });
+        Ok(CourseUpdateResponse {
+            version: version + 1,
+            cached_responses,
+        })
+    }
+
+    // Replace hours of the wind forecast being served. Every estimate and
+    // search depends on the wind, so no cached response carries over.
+    fn override_wind(&self, conditions: &[WindCondition]) -> Result<WindOverrideResponse, ApiError> {
+        let mut snapshot = self.snapshot.write().unwrap();
+        let mut data = RegattaData::clone(&snapshot.data);
+        data.wind_data.override_hours(conditions).map_err(ApiError::from)?;
+        let wind = WindResponse::forecast(&data.wind_data);
+        *snapshot = Snapshot {
+            data: Arc::new(data),
+            version: snapshot.version + 1,
+            boat: None,
+        };
+        let version = snapshot.version;
+        drop(snapshot);
+        self.publish(ServerEvent::Wind {
+            version,
+            hours: conditions.len(),
+        });
+        Ok(WindOverrideResponse {
+            version,
+            hours: conditions.len(),
+            wind,
+        })
+    }
+
+    // Fit the leg corrections to the observations again and swap in the data
+    // with them
+    fn recalibrate(&self) -> u64 {
+        let mut snapshot = self.snapshot.write().unwrap();
+        let data = calibrated(RegattaData::clone(&snapshot.data), &self.observations);
+        *snapshot = Snapshot {
+            data: Arc::new(data),
+            version: snapshot.version + 1,
+            boat: None,
+        };
+        let version = snapshot.version;
+        drop(snapshot);
+        self.publish(ServerEvent::Data { version });
+        version
+    }
+}
+
+// The key of a cached response for the next version of the data, `None` if
+// a change of the course may change the response: any search may pass a
+// moved buoy or sail a changed leg, while an estimate only depends on the
+// positions of its own two buoys
+fn rekeyed(key: &str, version: u64, update: &CourseUpdate) -> Option<String> {
+    let rest = key.strip_prefix(&format!("{version}:"))?;
+    let kind_and_query = match rest.split_once(':') {
+        Some((boat, query)) if boat.starts_with("boat") => query,
+        _ => rest,
+    };
+    let (kind, query) = kind_and_query.split_once(':')?;
+    let unaffected = matches!(kind, "estimate" | "estimateleg")
+        && match update {
+            CourseUpdate::MoveBuoy { name, .. } => !query.contains(&format!("{name:?}")),
+            CourseUpdate::SetMaxNumber { .. } => true,
+        };
+    unaffected.then(|| format!("{}:{rest}", version + 1))
+}
+
+// The data with the leg corrections fitted to the observations
+fn calibrated(mut data: RegattaData, observations: &ObservationLog) -> RegattaData {
+    data.leg_corrections = leg_corrections(&calibrate(&data, &observations.all()));
+    data
+}
+
+pub async fn start_server(
+    data: RegattaData,
+    config: ServerConfig,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let base_path = normalize_base_path(&config.base_path);
+
+    // Request and search statistics
+    let metrics = Arc::new(Metrics::new());
+    let state = build_state(data, &config, &base_path, metrics.clone())?;
+
+    // Compare the boats with their plans and watch their positions in the
+    // background
+    let alerts = Alerts {
+        webhook: config.alert_webhook.clone(),
+        mqtt: config.alert_mqtt.clone(),
+        margin: config.alert_margin,
+        position_timeout: config.position_timeout,
+    };
+    tokio::spawn(watch_boats(state.clone(), alerts.clone()));
+
+    // Tell the subscribers of the event feed when the recommendation changes
+    tokio::spawn(watch_recommendation(state.clone(), alerts));
+
+    // Feed the onboard instruments into the live state
+    if let Some(addr) = config.nmea_listen {
+        let nmea_state = state.clone();
+        tokio::spawn(async move {
+            let handler = move |reading| handle_instrument_reading(&nmea_state, reading);
+            if let Err(e) = crate::nmea::listen(addr, handler).await {
+                tracing::error!("NMEA listener on {addr} failed: {e}");
+            }
+        });
+        tracing::info!("Listening for NMEA 0183 sentences on {addr} (UDP and TCP)");
+    }
+
+    // Fail early instead of when the first connection comes in
+    let tls = config.tls.as_ref().map(tls_acceptor).transpose()?;
+
+    let routes = build_routes(state, metrics, base_path.clone());
+
+    let addr = SocketAddr::new(config.bind, config.port);
+    let scheme = if config.tls.is_some() { "https" } else { "http" };
+    if config.bind.is_unspecified() {
+        println!("Starting HTTP server on {scheme}://{addr}{base_path} (all interfaces)");
+    } else {
+        println!("Starting HTTP server on {scheme}://{addr}{base_path}");
+    }
+    println!("Available endpoints:");
+    println!("  GET /              - Main menu");
+    println!("  GET /estimate      - Estimate form");
+    println!("  GET /estimate-leg  - Estimate leg form");
+    println!("  GET /find-paths    - Find paths form");
+    println!("  GET /find-target   - Find target paths form");
+    println!("  GET /dashboard     - Live navigation dashboard");
+    println!("  GET /next          - Next leg in large print for a phone in the cockpit");
+    println!("  GET /finish        - Points of no return of the buoys on the way to the finish");
+    println!("  GET /fleet         - Fleet overview with all boats on the course map");
+    println!("  GET /leaderboard   - Boats of the fleet ranked by the miles scored so far");
+    println!("  GET /training      - Virtual regatta sailing the course leg by leg");
+    println!("  GET /explore       - Path explorer building a route by clicking buoys on the map");
+    println!("  GET /map           - Course map with a path to a target drawn over it");
+    println!("  GET /wind          - Wind editor changing the forecast the estimates and searches use");
+    println!("  GET /plans/ID      - Read-only page of a shared plan with map and leg table");
+    println!("  Pages and estimates in English or Dutch: ?lang=en|nl, the language links, or Accept-Language");
+    println!("  GET /regatta-graph.pdf - Show regatta graph as PDF");
+    println!("  GET /regatta-course.svg - Show regatta map as SVG");
+    println!("  GET /version       - Get program version");
+    println!("  GET /health        - Health check");
+    println!("  GET /metrics       - Request and search statistics in Prometheus format");
+    println!("  GET /api/openapi.json - OpenAPI specification of the JSON API");
+    println!("  GET /api/data      - Buoys, starts, legs, polars, and wind forecast");
+    println!("  GET /api/warnings  - Suspicious findings in the loaded data");
+    println!("  GET /api/estimate?from=X&to=Y&time=Z - Estimate leg performance");
+    println!("  GET /api/estimateleg?from=X&to=Y&reverse=Z&time=W - Estimate leg performance");
+    println!("  GET /api/neighbors?buoy=X - Starts and legs from a buoy");
+    println!("  GET /api/find-paths?start=X&time=Y&steps=Z&max_paths=N - Find paths from starting point (optional include, finish, sort)");
+    println!("  GET /api/find-paths.html?start=X&time=Y&steps=Z - The paths found as sortable HTML table");
+    println!("  GET /api/find-targets?start=X&target=Y&time=Z&steps=W&max_paths=N - Find paths to specific target");
+    println!("  GET /api/find-targets.csv?start=X&target=Y&time=Z&steps=W&max_paths=N - Paths to target as CSV leg table");
+    println!("  POST /api/plan     - Plan paths with a JSON body (start, target, constraints, objective, ...)");
+    println!("  POST /api/jobs     - Start a plan search in the background (same body as /api/plan)");
+    println!("  GET /api/jobs/ID   - Get status, progress, and result of a background search");
+    println!("  DELETE /api/jobs/ID - Cancel a background search");
+    println!("  POST /api/sessions - Create a planning session (name, start, start_time, pinned_legs, completed_legs, notes)");
+    println!("  GET /api/sessions  - List planning sessions");
+    println!("  GET /api/sessions/ID - Get a planning session");
+    println!("  PUT /api/sessions/ID - Replace the plan of a planning session");
+    println!("  POST /api/plans    - Save a route (route, time, name, notes) under a short id for a sharing link");
+    println!("  POST /api/boats    - Register a boat (name, optional polars, class, and session)");
+    println!("  GET /api/boats     - List the boats of the fleet with their latest positions");
+    println!("  DELETE /api/boats/NAME - Remove a boat from the fleet");
+    println!("  GET /api/leaderboard - Boats of the fleet ranked by the miles scored on their tracks");
+    println!("  GET /api/fleet.svg - Course map with the tracks of all boats");
+    println!("  POST /api/simulate - Simulate a route in time steps (route, time, step_minutes, current, format)");
+    println!("  POST /api/validate-route - Check a planned or sailed route against every rule (route, time, passing_times)");
+    println!("  POST /api/training - Start a training game (start, optional time)");
+    println!("  GET /api/training/ID - Get a training game with the legs to choose from");
+    println!("  POST /api/training/ID/legs - Sail the next leg (to) of a training game");
+    println!("  GET /api/course.svg - Course map with clickable buoys");
+    println!("  POST /api/explore  - Start exploring routes at a buoy (start, optional time)");
+    println!("  GET /api/explore/ID - Get an exploration with the estimates of the next legs");
+    println!("  POST /api/explore/ID/legs - Add the next leg (to) to an exploration");
+    println!("  DELETE /api/explore/ID/legs - Take back the last leg of an exploration");
+    println!("  POST /api/reload   - Reload data files and templates (from the server machine only without an access token)");
+    println!("  POST /api/course   - Move a buoy or change a MaxNumber in place (allowed like reloading)");
+    println!("  POST /api/wind     - Replace hours of the wind forecast until the next reload (allowed like reloading)");
+    println!("  POST /api/position - Report the boat position (lat, long, time, optional speed and course)");
+    println!("  GET /api/track?since=T - Reported positions and distance sailed");
+    println!("  POST /api/observations - Submit the speed achieved on a leg (from, to, time, speed, optional wind)");
+    println!("  GET /api/observations - List the submitted leg speeds");
+    println!("  GET /api/calibration - Correction factors fitted per leg and the ones in use");
+    println!("  POST /api/calibration - Fit and apply the correction factors (from the server machine only without an access token)");
+    println!("  GET /api/dashboard?from=X&to=Y&steps=N&objective=O - Wind, active leg, ETA, and recommended continuation");
+    println!("  GET /api/finish-approach?time=T - Latest departure from every buoy which still reaches the finish");
+    println!("  GET /api/finish-approach.svg?time=T - Course map with the latest departure under every buoy");
+    println!("  GET /api/arrival-profile?start=X&interval=M - Earliest arrival at every buoy for departures over the day");
+    println!("  GET /api/events    - Server-sent events of wind, data, position, and recommendation changes");
+    println!("  GET /api/progress?session=ID - Ahead or behind the plan of a planning session, and the projected finish");
+    println!("  GET /ws/search     - WebSocket streaming progress and result of a plan search");
+    println!("  GET /api/route-profile.svg?path=A,B,C&time=T - Distance/time and speed chart for a route");
+    println!("  GET /api/route-map.svg?path=A,B,C&time=T - Course map with a route drawn over it");
+    println!("  GET /api/route.gpx?path=A,B,C&time=T - Route as GPX file for chartplotters");
+    println!("  GET /api/route.geojson?path=A,B,C&time=T - Route as GeoJSON for web maps");
+    println!("  Planning and live endpoints take ?boat=NAME to answer for a boat of the fleet");
+
+    // Start the server
+    serve(routes, addr, tls).await
+}
+
+// Address of the client of a connection, stored in the extensions of each
+// of its requests by `serve`
+#[derive(Clone, Copy, Debug)]
+struct RemoteAddr(SocketAddr);
+
+// Address of the client, none for requests which didn't come in over a
+// connection, like those of `warp::test`
+fn remote_addr() -> impl Filter<Extract = (Option<SocketAddr>,), Error = std::convert::Infallible> + Clone {
+    warp::ext::optional::<RemoteAddr>().map(|remote: Option<RemoteAddr>| remote.map(|remote| remote.0))
+}
+
+// Acceptor of HTTPS connections with the certificate chain and private key
+// of the configuration
+fn tls_acceptor(tls: &TlsConfig) -> Result<tokio_rustls::TlsAcceptor, Box<dyn std::error::Error>> {
+    use tokio_rustls::rustls::pki_types::pem::PemObject;
+    use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+    for path in [&tls.cert_path, &tls.key_path] {
+        if !path.exists() {
+            return Err(format!("TLS file '{}' not found", path.display()).into());
+        }
+    }
+    let certs = CertificateDer::pem_file_iter(&tls.cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Cannot read certificates from '{}': {e}", tls.cert_path.display()))?;
+    let key = PrivateKeyDer::from_pem_file(&tls.key_path)
+        .map_err(|e| format!("Cannot read private key from '{}': {e}", tls.key_path.display()))?;
+    let mut server_config = tokio_rustls::rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)?;
+    server_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(tokio_rustls::TlsAcceptor::from(Arc::new(server_config)))
+}
+
+// Accept connections on the address and serve the routes on them, over TLS
+// with an acceptor. warp 0.4 neither terminates TLS nor tells the filters
+// the address of the client, so the connections are handed to hyper here.
+async fn serve<F, R>(routes: F, addr: SocketAddr, tls: Option<tokio_rustls::TlsAcceptor>) -> Result<(), Box<dyn std::error::Error>>
+where
+    F: Filter<Extract = (R,), Error = warp::Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    use hyper_util::rt::{TokioExecutor, TokioIo};
+    use hyper_util::server::conn::auto::Builder;
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    loop {
+        let (stream, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                // Out of file descriptors and the like, give it a moment
+                tracing::warn!("Accepting a connection failed: {e}");
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                continue;
+            }
+        };
+        let routes = warp::service(routes.clone());
+        let service = hyper::service::service_fn(move |mut request: hyper::Request<hyper::body::Incoming>| {
+            request.extensions_mut().insert(RemoteAddr(peer));
+            tower_service::Service::call(&mut routes.clone(), request)
+        });
+        let tls = tls.clone();
+        tokio::spawn(async move {
+            let builder = Builder::new(TokioExecutor::new());
+            let served = match tls {
+                Some(acceptor) => match acceptor.accept(stream).await {
+                    Ok(stream) => builder.serve_connection_with_upgrades(TokioIo::new(stream), service).await,
+                    Err(e) => {
+                        tracing::debug!("TLS handshake with {peer} failed: {e}");
+                        return;
+                    }
+                },
+                None => builder.serve_connection_with_upgrades(TokioIo::new(stream), service).await,
+            };
+            if let Err(e) = served {
+                tracing::debug!("Connection with {peer} failed: {e}");
+            }
+        });
+    }
+}
+
+// All routes of a server with the data and configuration, without listening
+// anywhere and without the background tasks, for tests with `warp::test`
+pub fn routes(
+    data: RegattaData,
+    config: ServerConfig,
+) -> Result<impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone, Box<dyn std::error::Error>> {
+    let base_path = normalize_base_path(&config.base_path);
+    let metrics = Arc::new(Metrics::new());
+    let state = build_state(data, &config, &base_path, metrics.clone())?;
+    Ok(build_routes(state, metrics, base_path))
+}
+
+// The storage of tracks, sessions, and plans: the SQLite database if there
+// is one, else the files given, else only memory
+fn open_storage(config: &ServerConfig) -> Result<Arc<dyn Storage>, Box<dyn std::error::Error>> {
+    if let Some(database) = &config.database {
+        if config.track_log.is_some() || config.sessions_file.is_some() || config.plans_file.is_some() {
+            return Err("The database replaces the track log, the sessions file, and the plans file".into());
+        }
+        #[cfg(feature = "sqlite")]
+        return match crate::storage::SqliteStorage::open(database) {
+            Ok(storage) => Ok(Arc::new(storage)),
+            Err(e) => Err(format!("Failed to open database '{}': {e}", database.display()).into()),
+        };
+        #[cfg(not(feature = "sqlite"))]
+        return Err(format!(
+            "Can't open database '{}': uurs24 was built without the sqlite feature",
+            database.display()
+        )
+        .into());
+    }
+    if config.track_log.is_none() && config.sessions_file.is_none() && config.plans_file.is_none() {
+        return Ok(Arc::new(MemoryStorage::new()));
+    }
+    Ok(Arc::new(JsonFiles {
+        track_log: config.track_log.clone(),
+        sessions_file: config.sessions_file.clone(),
+        plans_file: config.plans_file.clone(),
+    }))
+}
+
+// The shared state of a server, continuing the logs and sessions of the
+// configuration
+fn build_state(
+    data: RegattaData,
+    config: &ServerConfig,
+    base_path: &str,
+    metrics: Arc<Metrics>,
+) -> Result<State, Box<dyn std::error::Error>> {
+    // The base path is written into the pages and their scripts unescaped
+    if !base_path.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '/' | '-' | '_' | '.')) {
+        return Err(format!("Invalid base path '{base_path}': only letters, digits, '/', '-', '_', and '.' are allowed").into());
+    }
 
-pub async fn start_server(data: RegattaData, port: u16) -> Result<(), Box<dyn std::error::Error>> {
     // Initialize Tera templates
-    let tera = match Tera::new("templates/**/*") {
-        Ok(t) => Arc::new(t),
+    let tera = match build_tera(config.templates_dir.as_deref(), base_path) {
+        Ok(t) => t,
         Err(e) => {
-            eprintln!("Failed to initialize Tera templates: {e}");
+            tracing::error!("Failed to initialize Tera templates: {e}");
             return Err("Template initialization failed".into());
         }
     };
 
+    // Tracks, planning sessions, and shared plans, continued from the
+    // database or the files if there are any
+    let storage = open_storage(config)?;
+    let track = match Track::with_storage(storage.clone(), None) {
+        Ok(track) => track,
+        Err(e) => return Err(format!("Failed to load the track: {e}").into()),
+    };
+    let sessions = match SessionStore::with_storage(storage.clone()) {
+        Ok(sessions) => sessions,
+        Err(e) => return Err(format!("Failed to load the planning sessions: {e}").into()),
+    };
+    let plans = match PlanStore::with_storage(storage.clone()) {
+        Ok(plans) => plans,
+        Err(e) => return Err(format!("Failed to load the shared plans: {e}").into()),
+    };
+
+    // Leg speed observations, continued from the log file if there is one
+    let observations = match &config.observations_log {
+        Some(path) => match ObservationLog::open(path) {
+            Ok(observations) => observations,
+            Err(e) => {
+                return Err(format!("Failed to open observations log '{}': {e}", path.display()).into());
+            }
+        },
+        None => ObservationLog::new(),
+    };
+    let data = calibrated(data, &observations);
+    if !data.leg_corrections.is_empty() {
+        tracing::info!("Calibrated {} legs from the observations", data.leg_corrections.len());
+    }
+
+    Ok(Arc::new(ServerState {
+        snapshot: RwLock::new(Snapshot {
+            data: Arc::new(data),
+            version: 0,
+            boat: None,
+        }),
+        tera: RwLock::new(Arc::new(tera)),
+        cache: Arc::new(ResponseCache::new(config.cache_size, metrics)),
+        templates_dir: config.templates_dir.clone(),
+        base_path: base_path.to_string(),
+        auth_token: config.auth_token.clone(),
+        lang: config.lang,
+        limits: Arc::new(SearchLimits::new(
+            config.max_search_nodes,
+            config.search_budget,
+            config.max_searches_per_client,
+            config.search_threads,
+        )),
+        track: Arc::new(track),
+        wind: Arc::new(Mutex::new(None)),
+        sessions: Arc::new(sessions),
+        plans: Arc::new(plans),
+        fleet: Arc::new(Fleet::new(storage)),
+        plan_session: config.plan_session,
+        observations: Arc::new(observations),
+        training: Arc::new(TrainingGames::new()),
+        explorations: Arc::new(Explorations::new()),
+        events: broadcast::channel(EVENT_CAPACITY).0,
+    }))
+}
+
+// All routes of the server below the base path, with the handling of
+// rejections, CORS, and the request log
+fn build_routes(
+    state: State,
+    metrics: Arc<Metrics>,
+    base_path: String,
+) -> impl Filter<Extract = (impl Reply,), Error = warp::Rejection> + Clone {
+    // Registry of asynchronous search jobs
+    let jobs: Jobs = Arc::new(Mutex::new(JobRegistry::default()));
+
     // Main page route
     let index_route = warp::path::end()
-        .and(with_tera(tera.clone()))
-        .and(with_data(data.clone()))
+        .and(with_tera(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
         .and_then(handle_index);
 
     // Estimate form page route
     let estimate_form_route = warp::path("estimate")
         .and(warp::path::end())
         .and(warp::get())
-        .and(with_tera(tera.clone()))
-        .and(with_data(data.clone()))
+        .and(with_tera(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
         .and_then(handle_estimate_form);
 
     // Estimate leg form page route
     let estimate_leg_form_route = warp::path("estimate-leg")
         .and(warp::path::end())
         .and(warp::get())
-        .and(with_tera(tera.clone()))
-        .and(with_data(data.clone()))
+        .and(with_tera(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
         .and_then(handle_estimate_leg_form);
 
     // Find paths form page route
     let find_paths_form_route = warp::path("find-paths")
         .and(warp::path::end())
         .and(warp::get())
-        .and(with_tera(tera.clone()))
-        .and(with_data(data.clone()))
+        .and(with_tera(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
         .and_then(handle_find_paths_form);
 
     // Find target form page route
     let find_target_form_route = warp::path("find-target")
         .and(warp::path::end())
         .and(warp::get())
-        .and(with_tera(tera.clone()))
-        .and(with_data(data.clone()))
+        .and(with_tera(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
         .and_then(handle_find_target_form);
 
+    // Course map page with a path to a target drawn over it
+    let map_route = warp::path("map")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_tera(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
+        .and_then(handle_map_page);
+
+    // Read-only page of a shared plan, opened from its link without a token
+    let shared_plan_route = warp::path!("plans" / String)
+        .and(warp::get())
+        .and(with_tera(state.clone()))
+        .and(with_state(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
+        .and_then(handle_shared_plan_page);
+
+    // Wind editor page
+    let wind_route = warp::path("wind")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_tera(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
+        .and_then(handle_wind_page);
+
+    // Live navigation dashboard page
+    let dashboard_route = warp::path("dashboard")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_tera(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
+        .and_then(handle_dashboard_page);
+
+    // Next leg page for a phone in the cockpit
+    let next_route = warp::path("next")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_tera(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
+        .and_then(handle_next_page);
+
+    // Fleet overview page
+    let fleet_route = warp::path("fleet")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_tera(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
+        .and_then(handle_fleet_page);
+
+    // Points of no return on the way to the finish
+    let finish_route = warp::path("finish")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_tera(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
+        .and_then(handle_finish_page);
+
+    // Leaderboard page of the race committee
+    let leaderboard_route = warp::path("leaderboard")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_tera(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
+        .and_then(handle_leaderboard_page);
+
+    // Virtual regatta page
+    let training_route = warp::path("training")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_tera(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
+        .and_then(handle_training_page);
+
+    // Path explorer page
+    let explore_route = warp::path("explore")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(with_tera(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_lang(state.clone()))
+        .and(with_theme())
+        .and_then(handle_explore_page);
+
     // Version endpoint
     let version_route = warp::path("version").and(warp::get()).map(|| {
-        let response = json!({
-            "version": env!("CARGO_PKG_VERSION")
-        });
+        let response = VersionResponse {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+        };
         warp::reply::json(&response)
     });
 
     // Health check endpoint
     let health_route = warp::path("health").and(warp::get()).map(|| {
-        let response = json!({
-            "status": "ok",
-            "timestamp": chrono::Utc::now().to_rfc3339()
-        });
+        let response = HealthResponse {
+            status: "ok".to_string(),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        };
         warp::reply::json(&response)
     });
 
+    // OpenAPI specification of the JSON API
+    let openapi_route = warp::path!("api" / "openapi.json")
+        .and(warp::get())
+        .map(|| warp::reply::json(&openapi_document()));
+
+    // Regatta data API endpoint
+    let data_api_route = warp::path!("api" / "data")
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_boat_data(state.clone()))
+        .map(|data: Arc<RegattaData>| warp::reply::json(&DataResponse::new(&data)));
+
+    // Suspicious findings in the loaded data
+    let warnings_route = warp::path!("api" / "warnings")
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_data(state.clone()))
+        .map(|data: Arc<RegattaData>| {
+            let warnings: Vec<DataWarningResponse> = data.warnings().into_iter().map(Into::into).collect();
+            warp::reply::json(&warnings)
+        });
+
     // Estimate leg performance API endpoint
     let estimate_api_route = warp::path("api")
         .and(warp::path("estimate"))
         .and(warp::get())
+        .and(authorized(state.clone()))
         .and(warp::query::<EstimateQuery>())
-        .and(with_data(data.clone()))
+        .and(with_snapshot(state.clone()))
+        .and(with_cache(state.clone()))
+        .and(with_lang(state.clone()))
         .and_then(handle_estimate);
 
     // Estimate leg performance API endpoint
+    // Buoys reached from a buoy by a single start or leg
+    let neighbors_route = warp::path!("api" / "neighbors")
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(warp::query::<NeighborsQuery>())
+        .and(with_data(state.clone()))
+        .and_then(handle_neighbors);
+
+    // Earliest arrivals at every buoy from a start for departures over the day
+    let arrival_profile_route = warp::path!("api" / "arrival-profile")
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(warp::query::<ArrivalProfileQuery>())
+        .and(with_snapshot(state.clone()))
+        .and(with_cache(state.clone()))
+        .and_then(handle_arrival_profile);
+
     let estimate_leg_api_route = warp::path("api")
         .and(warp::path("estimateleg"))
         .and(warp::get())
+        .and(authorized(state.clone()))
         .and(warp::query::<EstimateLegQuery>())
-        .and(with_data(data.clone()))
+        .and(with_snapshot(state.clone()))
+        .and(with_cache(state.clone()))
+        .and(with_lang(state.clone()))
         .and_then(handle_estimate_leg);
 
     // Find paths API endpoint
     let find_paths_api_route = warp::path("api")
         .and(warp::path("find-paths"))
         .and(warp::get())
+        .and(authorized(state.clone()))
         .and(warp::query::<FindPathsQuery>())
-        .and(with_data(data.clone()))
+        .and(with_snapshot(state.clone()))
+        .and(with_cache(state.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and(remote_addr())
+        .and(with_limits(state.clone()))
         .and_then(handle_find_paths);
 
+    // Table of the paths found for the find-paths page
+    let find_paths_html_route = warp::path("api")
+        .and(warp::path("find-paths.html"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(warp::query::<FindPathsQuery>())
+        .and(with_tera(state.clone()))
+        .and(with_snapshot(state.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and(remote_addr())
+        .and(with_limits(state.clone()))
+        .and_then(handle_find_paths_html);
+
     // Find target API endpoint
     let find_target_api_route = warp::path("api")
         .and(warp::path("find-targets"))
         .and(warp::get())
+        .and(authorized(state.clone()))
         .and(warp::query::<FindTargetQuery>())
-        .and(with_data(data.clone()))
+        .and(with_snapshot(state.clone()))
+        .and(with_cache(state.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and(remote_addr())
+        .and(with_limits(state.clone()))
         .and_then(handle_find_target);
 
+    // Find target paths as CSV leg table download
+    let find_target_csv_route = warp::path("api")
+        .and(warp::path("find-targets.csv"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(warp::query::<FindTargetQuery>())
+        .and(with_snapshot(state.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and(remote_addr())
+        .and(with_limits(state.clone()))
+        .and_then(handle_find_target_csv);
+
+    // Plan API endpoint taking all search options as a JSON body
+    let plan_api_route = warp::path("api")
+        .and(warp::path("plan"))
+        .and(warp::path::end())
+        .and(warp::post())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json::<PlanRequest>())
+        .and(with_snapshot(state.clone()))
+        .and(with_cache(state.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and(remote_addr())
+        .and(with_limits(state.clone()))
+        .and_then(handle_plan);
+
+    // Asynchronous job API endpoints for long-running searches
+    let create_job_route = warp::path!("api" / "jobs")
+        .and(warp::post())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json::<PlanRequest>())
+        .and(with_boat_data(state.clone()))
+        .and(with_jobs(jobs.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and(remote_addr())
+        .and(with_limits(state.clone()))
+        .and_then(handle_create_job);
+
+    let get_job_route = warp::path!("api" / "jobs" / u64)
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_jobs(jobs.clone()))
+        .and_then(handle_get_job);
+
+    let cancel_job_route = warp::path!("api" / "jobs" / u64)
+        .and(warp::delete())
+        .and(authorized(state.clone()))
+        .and(with_jobs(jobs.clone()))
+        .and_then(handle_cancel_job);
+
+    // Planning session endpoints
+    let create_session_route = warp::path!("api" / "sessions")
+        .and(warp::post())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json::<SessionInput>())
+        .and(with_data(state.clone()))
+        .and(with_sessions(state.clone()))
+        .and_then(handle_create_session);
+
+    let list_sessions_route = warp::path!("api" / "sessions")
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_sessions(state.clone()))
+        .and_then(handle_list_sessions);
+
+    let get_session_route = warp::path!("api" / "sessions" / u64)
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_sessions(state.clone()))
+        .and_then(handle_get_session);
+
+    let update_session_route = warp::path!("api" / "sessions" / u64)
+        .and(warp::put())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json::<SessionInput>())
+        .and(with_data(state.clone()))
+        .and(with_sessions(state.clone()))
+        .and_then(handle_update_session);
+
+    // Save a plan under a short id for its link
+    let save_plan_route = warp::path!("api" / "plans")
+        .and(warp::post())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(16 * 1024))
+        .and(warp::body::json::<PlanSave>())
+        .and(with_boat(state.clone()))
+        .and(with_state(state.clone()))
+        .and_then(handle_save_plan);
+
+    // Fleet endpoints
+    let register_boat_route = warp::path!("api" / "boats")
+        .and(warp::post())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json::<BoatRegistration>())
+        .and(with_data(state.clone()))
+        .and(with_fleet(state.clone()))
+        .and(with_sessions(state.clone()))
+        .and_then(handle_register_boat);
+
+    let list_boats_route = warp::path!("api" / "boats")
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_fleet(state.clone()))
+        .and_then(handle_list_boats);
+
+    let remove_boat_route = warp::path!("api" / "boats" / String)
+        .and(warp::delete())
+        .and(authorized(state.clone()))
+        .and(with_fleet(state.clone()))
+        .and_then(handle_remove_boat);
+
+    // Boats of the fleet ranked by the miles scored on their tracks
+    let leaderboard_api_route = warp::path!("api" / "leaderboard")
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_fleet(state.clone()))
+        .and_then(handle_leaderboard);
+
+    // Course map with all boats of the fleet
+    let fleet_svg_route = warp::path("api")
+        .and(warp::path("fleet.svg"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_fleet(state.clone()))
+        .and(with_theme())
+        .and_then(handle_fleet_svg);
+
+    // Route simulated in fixed time steps, for dashboards animating a plan
+    let simulate_route_api = warp::path!("api" / "simulate")
+        .and(warp::post())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json::<SimulateRequest>())
+        .and(with_boat_data(state.clone()))
+        .and_then(handle_simulate);
+
+    // Rule check of a planned or sailed route, for crews and the committee
+    let validate_route_api = warp::path!("api" / "validate-route")
+        .and(warp::post())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json::<ValidateRouteRequest>())
+        .and(with_boat_data(state.clone()))
+        .and_then(handle_validate_route);
+
+    // Virtual regatta endpoints
+    let start_training_route = warp::path!("api" / "training")
+        .and(warp::post())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json::<TrainingStart>())
+        .and(with_data(state.clone()))
+        .and(with_training(state.clone()))
+        .and_then(handle_start_training);
+
+    let get_training_route = warp::path!("api" / "training" / u64)
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_training(state.clone()))
+        .and_then(handle_get_training);
+
+    let sail_training_route = warp::path!("api" / "training" / u64 / "legs")
+        .and(warp::post())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json::<TrainingMove>())
+        .and(with_data(state.clone()))
+        .and(with_training(state.clone()))
+        .and_then(handle_sail_training);
+
+    // Path explorer endpoints
+    let course_svg_route = warp::path("api")
+        .and(warp::path("course.svg"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_theme())
+        .and_then(handle_course_svg);
+
+    let start_exploration_route = warp::path!("api" / "explore")
+        .and(warp::post())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json::<ExplorationStart>())
+        .and(with_data(state.clone()))
+        .and(with_explorations(state.clone()))
+        .and_then(handle_start_exploration);
+
+    let get_exploration_route = warp::path!("api" / "explore" / u64)
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_explorations(state.clone()))
+        .and_then(handle_get_exploration);
+
+    let extend_exploration_route = warp::path!("api" / "explore" / u64 / "legs")
+        .and(warp::post())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(64 * 1024))
+        .and(warp::body::json::<ExplorationLeg>())
+        .and(with_data(state.clone()))
+        .and(with_explorations(state.clone()))
+        .and_then(handle_extend_exploration);
+
+    let retract_exploration_route = warp::path!("api" / "explore" / u64 / "legs")
+        .and(warp::delete())
+        .and(authorized(state.clone()))
+        .and(with_data(state.clone()))
+        .and(with_explorations(state.clone()))
+        .and_then(handle_retract_exploration);
+
+    // Prometheus metrics endpoint
+    let metrics_route = warp::path("metrics")
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_metrics(metrics.clone()))
+        .map(|metrics: Arc<Metrics>| {
+            warp::reply::with_header(
+                metrics.render(),
+                "Content-Type",
+                "text/plain; version=0.0.4",
+            )
+        });
+
+    // Log every request and record it in the metrics
+    let log_metrics = metrics.clone();
+    let request_log = warp::log::custom(move |info: warp::log::Info| {
+        log_metrics.record_request(
+            info.method().as_str(),
+            info.path(),
+            info.status().as_u16(),
+            info.elapsed(),
+        );
+        tracing::info!(
+            method = %info.method(),
+            path = info.path(),
+            status = info.status().as_u16(),
+            elapsed_ms = info.elapsed().as_secs_f64() * 1000.0,
+            "request"
+        );
+    });
+
+    // Reload the data files and templates without restarting the server
+    let reload_route = warp::path!("api" / "reload")
+        .and(warp::post())
+        .and(admin_only(state.clone()))
+        .and(with_state(state.clone()))
+        .and_then(handle_reload);
+
+    // Small changes of the course, without reloading the files
+    let course_route = warp::path!("api" / "course")
+        .and(warp::post())
+        .and(admin_only(state.clone()))
+        .and(warp::body::content_length_limit(4 * 1024))
+        .and(warp::body::json::<CourseUpdate>())
+        .and(with_state(state.clone()))
+        .and_then(handle_course_update);
+
+    // The wind seen on deck instead of the forecast, until the next reload
+    let wind_override_route = warp::path!("api" / "wind")
+        .and(warp::post())
+        .and(admin_only(state.clone()))
+        .and(warp::body::content_length_limit(16 * 1024))
+        .and(warp::body::json::<WindOverride>())
+        .and(with_state(state.clone()))
+        .and_then(handle_wind_override);
+
+    // Position reports of the boat
+    let position_route = warp::path!("api" / "position")
+        .and(warp::post())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(4 * 1024))
+        .and(warp::body::json::<PositionReport>())
+        .and(with_boat(state.clone()))
+        .and(with_state(state.clone()))
+        .and_then(handle_position);
+
+    // Leg speeds achieved by the crews, and the calibration fitted to them
+    let observation_route = warp::path!("api" / "observations")
+        .and(warp::post())
+        .and(authorized(state.clone()))
+        .and(warp::body::content_length_limit(4 * 1024))
+        .and(warp::body::json::<ObservationReport>())
+        .and(with_state(state.clone()))
+        .and_then(handle_observation);
+
+    let list_observations_route = warp::path!("api" / "observations")
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_state(state.clone()))
+        .and_then(handle_list_observations);
+
+    let calibration_route = warp::path!("api" / "calibration")
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(with_state(state.clone()))
+        .and_then(handle_calibration);
+
+    let calibrate_route = warp::path!("api" / "calibration")
+        .and(warp::post())
+        .and(admin_only(state.clone()))
+        .and(with_state(state.clone()))
+        .and_then(handle_calibrate);
+
+    // Track sailed so far
+    let track_route = warp::path!("api" / "track")
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(warp::query::<TrackQuery>())
+        .and(with_track(state.clone()))
+        .and_then(handle_track);
+
+    // Progress of a boat against its committed plan
+    let progress_route = warp::path!("api" / "progress")
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(warp::query::<ProgressQuery>())
+        .and(with_snapshot(state.clone()))
+        .and(with_track(state.clone()))
+        .and(with_boat(state.clone()))
+        .and(with_state(state.clone()))
+        .and_then(handle_progress);
+
+    // Live navigation data at the latest reported position
+    let dashboard_api_route = warp::path!("api" / "dashboard")
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(warp::query::<DashboardQuery>())
+        .and(with_snapshot(state.clone()))
+        .and(with_track(state.clone()))
+        .and(with_wind(state.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and(remote_addr())
+        .and(with_limits(state.clone()))
+        .and_then(handle_dashboard);
+
+    // Latest departures from the buoys which still reach the finish
+    let finish_approach_route = warp::path!("api" / "finish-approach")
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(warp::query::<ApproachQuery>())
+        .and(with_snapshot(state.clone()))
+        .and(with_track(state.clone()))
+        .and_then(handle_finish_approach);
+
+    // Course map with the latest departures as labels of the buoys
+    let finish_approach_svg_route = warp::path("api")
+        .and(warp::path("finish-approach.svg"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(warp::query::<ApproachQuery>())
+        .and(with_snapshot(state.clone()))
+        .and(with_track(state.clone()))
+        .and(with_theme())
+        .and_then(handle_finish_approach_svg);
+
+    // Server-sent events of changes to the wind, data, positions, and the
+    // recommendation, so the pages don't have to poll
+    let events_route = warp::path!("api" / "events")
+        .and(warp::get())
+        .and(authorized_events(state.clone()))
+        .and(with_state(state.clone()))
+        .map(handle_events);
+
+    // WebSocket streaming search progress and results
+    let search_ws_route = warp::path!("ws" / "search")
+        .and(warp::ws())
+        .and(authorized(state.clone()))
+        .and(with_boat_data(state.clone()))
+        .and(with_metrics(metrics.clone()))
+        .and(remote_addr())
+        .and(with_limits(state.clone()))
+        .map(
+            |ws: warp::ws::Ws,
+             data: Arc<RegattaData>,
+             metrics: Arc<Metrics>,
+             remote: Option<SocketAddr>,
+             limits: Arc<SearchLimits>| {
+                ws.on_upgrade(move |socket| {
+                    handle_search_socket(socket, data, metrics, remote, limits)
+                })
+            },
+        );
+
+    // Route profile chart endpoint
+    let route_profile_route = warp::path("api")
+        .and(warp::path("route-profile.svg"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(warp::query::<RouteProfileQuery>())
+        .and(with_boat_data(state.clone()))
+        .and(with_theme())
+        .and_then(handle_route_profile);
+
+    // Course map with a route drawn over it
+    let route_map_route = warp::path("api")
+        .and(warp::path("route-map.svg"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(warp::query::<RouteProfileQuery>())
+        .and(with_boat_data(state.clone()))
+        .and(with_theme())
+        .and_then(handle_route_map);
+
+    // Route as GPX download for chartplotters
+    let route_gpx_route = warp::path("api")
+        .and(warp::path("route.gpx"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(warp::query::<RouteProfileQuery>())
+        .and(with_boat_data(state.clone()))
+        .and_then(handle_route_gpx);
+
+    // Route as GeoJSON for web maps
+    let route_geojson_route = warp::path("api")
+        .and(warp::path("route.geojson"))
+        .and(warp::path::end())
+        .and(warp::get())
+        .and(authorized(state.clone()))
+        .and(warp::query::<RouteProfileQuery>())
+        .and(with_boat_data(state.clone()))
+        .and_then(handle_route_geojson);
+
     // PDF file serving route
     let pdf_route = warp::path("regatta-graph.pdf")
         .and(warp::path::end())
@@ -116,44 +1443,103 @@ pub async fn start_server(data: RegattaData, port: u16) -> Result<(), Box<dyn st
         .and(warp::get())
         .and_then(handle_svg);
 
-    // Combine all routes - API routes must come before page routes to avoid conflicts
-    let routes = index_route
+    // Combine all routes - API routes must come before page routes to avoid conflicts.
+    // The groups are boxed to keep the nested filter types within the compiler limits.
+    let page_routes = index_route
         .or(estimate_form_route)
         .or(estimate_leg_form_route)
         .or(find_paths_form_route)
         .or(find_target_form_route)
+        .or(map_route)
+        .or(wind_route)
+        .or(shared_plan_route)
+        .or(dashboard_route)
+        .or(next_route)
+        .or(finish_route)
+        .or(fleet_route)
+        .or(leaderboard_route)
+        .or(training_route)
+        .or(explore_route)
         .or(version_route)
         .or(health_route)
+        .or(metrics_route)
+        .or(openapi_route);
+    let planning_routes = data_api_route
+        .or(warnings_route)
         .or(estimate_api_route)
+        .or(neighbors_route)
+        .or(arrival_profile_route)
+        .or(finish_approach_svg_route)
         .or(estimate_leg_api_route)
+        .or(find_paths_html_route)
         .or(find_paths_api_route)
+        .or(find_target_csv_route)
         .or(find_target_api_route)
+        .or(plan_api_route)
+        .or(create_job_route)
+        .or(get_job_route)
+        .or(cancel_job_route)
+        .or(create_session_route)
+        .or(list_sessions_route)
+        .or(get_session_route)
+        .or(update_session_route)
+        .or(save_plan_route)
+        .or(register_boat_route)
+        .or(list_boats_route)
+        .or(remove_boat_route)
+        .or(leaderboard_api_route)
+        .or(fleet_svg_route)
+        .or(simulate_route_api)
+        .or(validate_route_api)
+        .or(start_training_route)
+        .or(get_training_route)
+        .or(sail_training_route)
+        .or(course_svg_route)
+        .or(start_exploration_route)
+        .or(get_exploration_route)
+        .or(extend_exploration_route)
+        .or(retract_exploration_route);
+    let live_routes = reload_route
+        .or(course_route)
+        .or(wind_override_route)
+        .or(position_route)
+        .or(observation_route)
+        .or(list_observations_route)
+        .or(calibration_route)
+        .or(calibrate_route)
+        .or(track_route)
+        .or(dashboard_api_route)
+        .or(finish_approach_route)
+        .or(events_route)
+        .or(progress_route)
+        .or(search_ws_route)
+        .or(route_profile_route)
+        .or(route_map_route)
+        .or(route_gpx_route)
+        .or(route_geojson_route)
         .or(pdf_route)
-        .or(svg_route)
-        .with(warp::cors().allow_any_origin());
+        .or(svg_route);
+    query_length_limit()
+        .and(base_path_filter(&base_path))
+        .and(boxed_routes(page_routes).or(boxed_routes(planning_routes)).or(boxed_routes(live_routes)))
+        .recover(handle_rejection)
+        .with(
+            warp::cors()
+                .allow_any_origin()
+                .allow_methods(["GET", "POST", "PUT", "DELETE"])
+                .allow_headers(["authorization", "content-type"]),
+        )
+        .with(request_log)
+}
 
-    println!(
-        "Starting HTTP server on http://0.0.0.0:{port} (all interfaces)"
-    );
-    println!("Available endpoints:");
-    println!("  GET /              - Main menu");
-    println!("  GET /estimate      - Estimate form");
-    println!("  GET /estimate-leg  - Estimate leg form");
-    println!("  GET /find-paths    - Find paths form");
-    println!("  GET /find-target   - Find target paths form");
-    println!("  GET /regatta-graph.pdf - Show regatta graph as PDF");
-    println!("  GET /regatta-course.svg - Show regatta map as SVG");
-    println!("  GET /version       - Get program version");
-    println!("  GET /health        - Health check");
-    println!("  GET /api/estimate?from=X&to=Y&time=Z - Estimate leg performance");
-    println!("  GET /api/estimateleg?from=X&to=Y&reverse=Z&time=W - Estimate leg performance");
-    println!("  GET /api/find-paths?start=X&time=Y&steps=Z&max_paths=N - Find paths from starting point");
-    println!("  GET /api/find-targets?start=X&target=Y&time=Z&steps=W&max_paths=N - Find paths to specific target");
-
-    // Start the server
-    warp::serve(routes).run(([0, 0, 0, 0], port)).await;
-
-    Ok(())
+// A group of routes as one boxed filter replying with a plain response, so
+// neither the filters nor the replies of the groups nest into each other
+fn boxed_routes<F, R>(routes: F) -> warp::filters::BoxedFilter<(warp::reply::Response,)>
+where
+    F: Filter<Extract = (R,), Error = warp::Rejection> + Clone + Send + Sync + 'static,
+    R: Reply,
+{
+    routes.map(|reply: R| reply.into_response()).boxed()
 }
 
 // Query parameters for the estimate endpoint
@@ -161,7 +1547,7 @@ pub async fn start_server(data: RegattaData, port: u16) -> Result<(), Box<dyn st
 struct EstimateQuery {
     from: String,
     to: String,
-    time: f64,
+    time: RaceTime,
 }
 
 // Query parameters for the estimate leg endpoint
@@ -170,16 +1556,38 @@ struct EstimateLegQuery {
     from: String,
     to: String,
     reverse: Option<bool>,
-    time: f64,
+    time: RaceTime,
+}
+
+// Query parameters for the neighbors endpoint
+#[derive(Debug, Deserialize)]
+struct NeighborsQuery {
+    buoy: String,
+}
+
+// Query parameters for the arrival profile endpoint
+#[derive(Debug, Deserialize)]
+struct ArrivalProfileQuery {
+    start: String,
+    from: Option<RaceTime>,  // first departure, race start by default
+    until: Option<RaceTime>, // last departure, the end of the race by default
+    interval: Option<f64>,   // minutes between the departures
+    avoid: Option<String>,     // comma separated buoys not to visit
+    avoid_leg: Option<String>, // comma separated legs FROM-TO not to sail
 }
 
 // Query parameters for the find paths endpoint
 #[derive(Debug, Deserialize)]
 struct FindPathsQuery {
     start: String,
-    time: f64,
+    time: RaceTime,
     steps: usize,
     max_paths: Option<usize>,
+    include: Option<String>, // buoy the paths must pass
+    finish: Option<String>,  // buoy the paths must end at
+    sort: Option<String>,    // distance, end_time, or speed
+    avoid: Option<String>,     // comma separated buoys the paths must not visit
+    avoid_leg: Option<String>, // comma separated legs FROM-TO the paths must not sail
 }
 
 // Query parameters for the find target endpoint
@@ -187,38 +1595,574 @@ struct FindPathsQuery {
 struct FindTargetQuery {
     start: String,
     target: String,
-    time: f64,
+    time: RaceTime,
+    steps: usize,
+    max_paths: Option<usize>,
+    avoid: Option<String>,     // comma separated buoys the paths must not visit
+    avoid_leg: Option<String>, // comma separated legs FROM-TO the paths must not sail
+}
+
+// JSON body for the plan endpoint
+#[derive(Debug, Deserialize)]
+struct PlanRequest {
+    start: String,
+    target: Option<String>, // without a target, all paths with exactly `steps` steps are explored
+    start_time: RaceTime,
     steps: usize,
+    #[serde(default)]
+    constraints: PlanConstraints,
+    #[serde(default)]
+    pins: Vec<PinRef>, // buoys and legs the paths must pass, in this order
+    strategy: Option<String>,
+    objective: Option<String>,
+    max_results: Option<usize>,
     max_paths: Option<usize>,
+    max_wait: Option<f64>,   // hours the search may wait at a buoy, none by default
+    diversity: Option<f64>,  // Jaccard distance of the legs between the paths returned, 0 by default
+}
+
+// Constraints part of the plan request, referring to buoys by name
+#[derive(Debug, Default, Deserialize)]
+struct PlanConstraints {
+    #[serde(default)]
+    forbidden_buoys: Vec<String>,
+    #[serde(default)]
+    forbidden_legs: Vec<LegRef>,
+}
+
+// A leg between two buoys given by name
+#[derive(Debug, Deserialize)]
+struct LegRef {
+    from: String,
+    to: String,
+}
+
+// A buoy, or the leg to it from another one, pinned to a place in the route
+// or a time, referring to the buoys by name
+#[derive(Debug, Deserialize)]
+struct PinRef {
+    buoy: String,
+    from: Option<String>,
+    slot: Option<usize>,    // legs sailed before reaching the buoy, 0 for the start
+    time: Option<RaceTime>,
+    tolerance: Option<f64>, // hours, PIN_TOLERANCE by default
+}
+
+// JSON body for the position endpoint
+#[derive(Debug, Deserialize)]
+struct PositionReport {
+    lat: f64,
+    long: f64,
+    time: f64, // in hours since race start
+    speed: Option<f64>,
+    course: Option<f64>,
+}
+
+// JSON body for the observation endpoint
+#[derive(Debug, Deserialize)]
+struct ObservationReport {
+    from: String,
+    to: String,
+    time: f64,  // in hours since race start
+    speed: f64, // achieved speed along the leg in knots
+    wind_direction: Option<f64>,
+    wind_speed: Option<f64>,
+    boat: Option<String>,
+}
+
+// JSON body for the simulation endpoint
+#[derive(Debug, Deserialize)]
+struct SimulateRequest {
+    route: Vec<String>,           // buoy names in the order they are sailed
+    time: Option<RaceTime>,       // start time, race start by default
+    step_minutes: Option<f64>,    // length of a time step, 1 minute by default
+    current: Option<Current>,
+    rounding_penalty: Option<f64>, // minutes lost at every mark rounding
+    format: Option<String>,       // "json" (default) or "geojson"
+}
+
+// JSON body for the route validation endpoint
+#[derive(Debug, Deserialize)]
+struct ValidateRouteRequest {
+    route: Vec<String>,                   // buoy names in the order they are sailed
+    time: Option<RaceTime>,               // start of a planned route, race start by default
+    passing_times: Option<Vec<RaceTime>>, // when a sailed route passed each of its buoys
+}
+
+// JSON body saving a plan to share
+#[derive(Debug, Deserialize)]
+struct PlanSave {
+    name: Option<String>, // the buoys of the route by default
+    route: Vec<String>,   // buoy names in the order they are sailed
+    time: Option<RaceTime>, // start of the route, race start by default
+    notes: Option<String>,
+}
+
+// JSON body for the wind override endpoint
+#[derive(Debug, Deserialize)]
+struct WindOverride {
+    wind: Vec<WindHour>, // hours of the forecast to replace, the others stay
+}
+
+// Wind of one hour of the forecast, shaped like the wind of the data endpoint
+#[derive(Debug, Deserialize)]
+struct WindHour {
+    time: u32,       // hour of the forecast
+    wind_speed: f64, // in knots
+    wind_angle: f64, // in degrees
+}
+
+// JSON body starting a training game
+#[derive(Debug, Deserialize)]
+struct TrainingStart {
+    start: String,
+    time: Option<RaceTime>,
+}
+
+// JSON body sailing the next leg of a training game
+#[derive(Debug, Deserialize)]
+struct TrainingMove {
+    to: String,
+}
+
+// JSON body starting an exploration
+#[derive(Debug, Deserialize)]
+struct ExplorationStart {
+    start: String,
+    time: Option<RaceTime>,
+}
+
+// JSON body adding the next leg to an exploration
+#[derive(Debug, Deserialize)]
+struct ExplorationLeg {
+    to: String,
+}
+
+// Query parameters for the track endpoint
+#[derive(Debug, Deserialize)]
+struct TrackQuery {
+    since: Option<f64>, // only points at or after this race time
+}
+
+// Query parameters for the dashboard endpoint
+#[derive(Debug, Deserialize)]
+struct DashboardQuery {
+    from: Option<String>, // leg being sailed, guessed from the track if missing
+    to: Option<String>,
+    steps: Option<usize>, // steps of the recommended continuation
+    objective: Option<String>,
+    avoid: Option<String>,     // comma separated buoys the continuation must not visit
+    avoid_leg: Option<String>, // comma separated legs FROM-TO the continuation must not sail
+}
+
+// Query parameter of the finish approach endpoint
+#[derive(Debug, Deserialize)]
+struct ApproachQuery {
+    time: Option<RaceTime>, // departures from then on, by default from the latest reported position
+}
+
+// Query parameter of the event feed
+#[derive(Debug, Deserialize)]
+struct EventsQuery {
+    token: Option<String>, // access token, instead of the Authorization header
+}
+
+// Query parameter selecting a boat of the fleet, accepted by all planning
+// and live endpoints next to their own parameters
+#[derive(Debug, Deserialize)]
+struct BoatQuery {
+    boat: Option<String>,
+}
+
+// Query parameter choosing the language of a page or an estimate
+#[derive(Debug, Deserialize)]
+struct LangQuery {
+    lang: Option<String>,
+}
+
+// JSON body for registering a boat
+#[derive(Debug, Deserialize)]
+struct BoatRegistration {
+    name: String,
+    polars: Option<String>, // polar table in the format of data/polars.csv
+    class: Option<String>,  // class of data/classes.csv the boat starts with
+    session: Option<u64>,   // planning session holding the plan of the boat
+}
+
+// Query parameters for the progress endpoint
+#[derive(Debug, Deserialize)]
+struct ProgressQuery {
+    session: Option<u64>, // by default the session of the boat, or the one given with --plan-session
+}
+
+// Query parameters for the route profile and GPX endpoints
+#[derive(Debug, Deserialize)]
+struct RouteProfileQuery {
+    path: String, // comma separated buoy names
+    time: Option<RaceTime>,
+}
+
+// An asynchronous search job with its progress and outcome
+struct Job {
+    status: JobStatus,
+    monitor: Arc<SearchMonitor>,
+    result: Option<PlanResponse>,
+    error: Option<ErrorResponse>,
+    created: chrono::DateTime<chrono::Utc>,
+}
+
+// All asynchronous search jobs known to the server
+#[derive(Default)]
+struct JobRegistry {
+    next_id: u64,
+    jobs: HashMap<u64, Job>,
+}
+
+type Jobs = Arc<Mutex<JobRegistry>>;
+
+// Turn a user supplied base path like "uurs24/" into "/uurs24", or "" for the root
+fn normalize_base_path(base_path: &str) -> String {
+    let segments: Vec<&str> = base_path.split('/').filter(|s| !s.is_empty()).collect();
+    if segments.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", segments.join("/"))
+    }
+}
+
+// Filter matching the base path prefix of all routes
+// Reject requests with a query string longer than MAX_QUERY_LENGTH
+fn query_length_limit() -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::query::raw()
+        .or(warp::any().map(String::new))
+        .unify()
+        .and_then(|query: String| async move {
+            if query.len() > MAX_QUERY_LENGTH {
+                return Err(warp::reject::custom(ApiError::new(
+                    StatusCode::URI_TOO_LONG,
+                    "Query too long",
+                    format!("The query string may have at most {MAX_QUERY_LENGTH} characters"),
+                )));
+            }
+            Ok(())
+        })
+        .untuple_one()
+}
+
+fn base_path_filter(base_path: &str) -> warp::filters::BoxedFilter<()> {
+    base_path
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .fold(warp::any().boxed(), |filter, segment| {
+            filter.and(warp::path(segment.to_string())).boxed()
+        })
+}
+
+// Helper function to inject the job registry into route handlers
+fn with_jobs(
+    jobs: Jobs,
+) -> impl Filter<Extract = (Jobs,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || jobs.clone())
+}
+
+// Helper function to inject the shared server state into route handlers
+fn with_state(
+    state: State,
+) -> impl Filter<Extract = (State,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || state.clone())
+}
+
+// Filter rejecting requests without the access token if the server has one
+fn authorized(state: State) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and_then(move |header: Option<String>| {
+            let state = state.clone();
+            async move { check_token(&state, header.as_deref().and_then(|h| h.strip_prefix("Bearer "))) }
+        })
+        .untuple_one()
+}
+
+// Filter for the routes changing the served data: like `authorized`, and
+// without an access token only from the machine running the server
+fn admin_only(state: State) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    authorized(state.clone())
+        .and(remote_addr())
+        .and_then(move |remote: Option<SocketAddr>| {
+            let local = state.auth_token.is_some() || remote.is_some_and(|addr| addr.ip().is_loopback());
+            async move {
+                if local {
+                    Ok(())
+                } else {
+                    Err(warp::reject::custom(ApiError::new(
+                        StatusCode::FORBIDDEN,
+                        "Forbidden",
+                        "Without an access token, changing the served data is only allowed from the machine running the server",
+                    )))
+                }
+            }
+        })
+        .untuple_one()
+}
+
+// Like `authorized`, also taking the token as `?token=`, for the event feed:
+// the EventSource of the browsers can't send headers
+fn authorized_events(state: State) -> impl Filter<Extract = (), Error = warp::Rejection> + Clone {
+    warp::header::optional::<String>("authorization")
+        .and(warp::query::<EventsQuery>())
+        .and_then(move |header: Option<String>, query: EventsQuery| {
+            let state = state.clone();
+            async move {
+                let given = header.as_deref().and_then(|h| h.strip_prefix("Bearer "));
+                check_token(&state, given.or(query.token.as_deref()))
+            }
+        })
+        .untuple_one()
+}
+
+// Accept the token given if the server requires one
+fn check_token(state: &ServerState, given: Option<&str>) -> Result<(), warp::Rejection> {
+    let Some(token) = &state.auth_token else {
+        return Ok(());
+    };
+    if given.is_some_and(|given| constant_time_eq(given.as_bytes(), token.as_bytes())) {
+        Ok(())
+    } else {
+        Err(warp::reject::custom(ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "Unauthorized",
+            "A valid access token is required: Authorization: Bearer <token>",
+        )))
+    }
+}
+
+// Compare two byte strings in time independent of where they differ
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// Helper function to inject the search limits into route handlers
+fn with_limits(
+    state: State,
+) -> impl Filter<Extract = (Arc<SearchLimits>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || state.limits.clone())
+}
+
+// Helper function to inject the track of the selected boat into route handlers
+fn with_track(
+    state: State,
+) -> impl Filter<Extract = (Arc<Track>,), Error = warp::Rejection> + Clone {
+    with_boat(state.clone()).map(move |boat: Option<Arc<Boat>>| match boat {
+        Some(boat) => boat.track.clone(),
+        None => state.track.clone(),
+    })
+}
+
+// Helper function to inject the measured wind into route handlers
+fn with_wind(
+    state: State,
+) -> impl Filter<Extract = (Arc<Mutex<Option<WindObservation>>>,), Error = std::convert::Infallible> + Clone
+{
+    warp::any().map(move || state.wind.clone())
+}
+
+// Helper function to inject the fleet into route handlers
+fn with_fleet(
+    state: State,
+) -> impl Filter<Extract = (Arc<Fleet>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || state.fleet.clone())
+}
+
+// Helper function to inject the planning sessions into route handlers
+fn with_sessions(
+    state: State,
+) -> impl Filter<Extract = (Arc<SessionStore>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || state.sessions.clone())
+}
+
+// Helper function to inject the training games into route handlers
+fn with_training(
+    state: State,
+) -> impl Filter<Extract = (Arc<TrainingGames>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || state.training.clone())
+}
+
+// Helper function to inject the explorations into route handlers
+fn with_explorations(
+    state: State,
+) -> impl Filter<Extract = (Arc<Explorations>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || state.explorations.clone())
+}
+
+// Helper function to inject the metrics into route handlers
+fn with_metrics(
+    metrics: Arc<Metrics>,
+) -> impl Filter<Extract = (Arc<Metrics>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || metrics.clone())
 }
 
 // Helper function to inject Tera into route handlers
 fn with_tera(
-    tera: Arc<Tera>,
+    state: State,
 ) -> impl Filter<Extract = (Arc<Tera>,), Error = std::convert::Infallible> + Clone {
-    warp::any().map(move || tera.clone())
+    warp::any().map(move || state.tera())
 }
 
-// Helper function to inject data into route handlers
+// Helper function to share the current data with route handlers, without
+// copying it
 fn with_data(
-    data: RegattaData,
-) -> impl Filter<Extract = (RegattaData,), Error = std::convert::Infallible> + Clone {
-    warp::any().map(move || data.clone())
+    state: State,
+) -> impl Filter<Extract = (Arc<RegattaData>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || state.snapshot().data)
+}
+
+// Helper function to inject the current data snapshot into route handlers,
+// with the polars of the selected boat if it has its own
+fn with_snapshot(
+    state: State,
+) -> impl Filter<Extract = (Snapshot,), Error = warp::Rejection> + Clone {
+    with_boat(state.clone()).map(move |boat: Option<Arc<Boat>>| state.boat_snapshot(boat.as_deref()))
+}
+
+// Helper function to inject the data for the selected boat into route handlers
+fn with_boat_data(
+    state: State,
+) -> impl Filter<Extract = (Arc<RegattaData>,), Error = warp::Rejection> + Clone {
+    with_snapshot(state).map(|snapshot: Snapshot| snapshot.data)
+}
+
+// Helper function to inject the boat selected with `?boat=NAME` into route
+// handlers, `None` for the server's own boat
+fn with_boat(
+    state: State,
+) -> impl Filter<Extract = (Option<Arc<Boat>>,), Error = warp::Rejection> + Clone {
+    warp::query::<BoatQuery>().and_then(move |query: BoatQuery| {
+        let state = state.clone();
+        async move {
+            let Some(name) = query.boat else {
+                return Ok(None);
+            };
+            match state.fleet.get(&name) {
+                Some(boat) => Ok(Some(boat)),
+                None => Err(warp::reject::custom(ApiError::not_found(
+                    "Boat not found",
+                    format!("Boat '{name}' is not registered"),
+                ))),
+            }
+        }
+    })
+}
+
+// Name of the cookie in which the pages remember the language chosen
+const LANG_COOKIE: &str = "uurs24-lang";
+
+// Helper function to inject the language of a request into route handlers:
+// `?lang=`, the cookie of the language links, the server's `--lang`, and the
+// Accept-Language header in this order, English if none of them has one
+fn with_lang(state: State) -> impl Filter<Extract = (Lang,), Error = warp::Rejection> + Clone {
+    warp::query::<LangQuery>()
+        .and(warp::cookie::optional::<String>(LANG_COOKIE))
+        .and(warp::header::optional::<String>("accept-language"))
+        .map(move |query: LangQuery, cookie: Option<String>, accept: Option<String>| {
+            query
+                .lang
+                .as_deref()
+                .and_then(Lang::from_code)
+                .or_else(|| cookie.as_deref().and_then(Lang::from_code))
+                .or(state.lang)
+                .or_else(|| accept.as_deref().and_then(Lang::from_accept_language))
+                .unwrap_or_default()
+        })
+}
+
+// Name of the cookie in which the pages remember the theme chosen
+const THEME_COOKIE: &str = "uurs24-theme";
+
+// Query parameter choosing the theme of a page or a chart
+#[derive(Debug, Deserialize)]
+struct ThemeQuery {
+    theme: Option<String>,
+}
+
+// Helper function to inject the theme of a request into route handlers:
+// `?theme=`, else the cookie of the theme link, else the light theme. The
+// charts loaded by the pages get the cookie too, so they match the page.
+fn with_theme() -> impl Filter<Extract = (Theme,), Error = warp::Rejection> + Clone {
+    warp::query::<ThemeQuery>()
+        .and(warp::cookie::optional::<String>(THEME_COOKIE))
+        .map(|query: ThemeQuery, cookie: Option<String>| {
+            query
+                .theme
+                .as_deref()
+                .and_then(Theme::from_name)
+                .or_else(|| cookie.as_deref().and_then(Theme::from_name))
+                .unwrap_or_default()
+        })
+}
+
+// The default chart configuration in a theme
+fn themed(theme: Theme) -> PlotConfig {
+    PlotConfig { theme, ..PlotConfig::default() }
+}
+
+// The context of a page with its language, the messages in it, and its theme
+fn page_context(lang: Lang, theme: Theme) -> Context {
+    let mut context = Context::new();
+    context.insert("lang", &lang);
+    context.insert("t", &catalog(lang));
+    context.insert("theme", theme.name());
+    context
+}
+
+// Helper function to inject the response cache into route handlers
+fn with_cache(
+    state: State,
+) -> impl Filter<Extract = (Arc<ResponseCache>,), Error = std::convert::Infallible> + Clone {
+    warp::any().map(move || state.cache.clone())
+}
+
+// Reply with a JSON body which is already serialized
+fn json_reply(body: String) -> warp::reply::Response {
+    warp::reply::with_header(body, "Content-Type", "application/json").into_response()
+}
+
+// Reply with a file download of the given content type
+fn download_reply(body: String, content_type: &str, filename: &str) -> warp::reply::Response {
+    let reply = warp::reply::with_header(body, "Content-Type", content_type);
+    warp::reply::with_header(
+        reply,
+        "Content-Disposition",
+        format!("attachment; filename=\"{filename}\""),
+    )
+    .into_response()
+}
+
+// Serialize a response, remember it in the cache, and reply with it
+fn cached_reply(
+    cache: &ResponseCache,
+    key: String,
+    response: &impl Serialize,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let body = serde_json::to_string(response).map_err(|e| {
+        warp::reject::custom(ApiError::internal(
+            "Serialization failed",
+            format!("Error serializing response: {e}"),
+        ))
+    })?;
+    Ok(json_reply(cache.insert(key, body)))
 }
 
 // Handler for the main index page
 async fn handle_index(
     tera: Arc<Tera>,
-    _data: RegattaData,
+    _data: Arc<RegattaData>,
+    lang: Lang,
+    theme: Theme,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let mut context = Context::new();
+    let mut context = page_context(lang, theme);
     context.insert("version", env!("CARGO_PKG_VERSION"));
     context.insert("authors", "Claude and Max Neunhöffer");
     
-    let rendered_html = tera.render("index.html", &context).map_err(|e| {
-        eprintln!("Template rendering error: {e}");
-        warp::reject::custom(TemplateError)
-    })?;
+    let rendered_html = render_template(&tera, "index.html", &context)?;
 
     Ok(html(rendered_html))
 }
@@ -226,428 +2170,2607 @@ async fn handle_index(
 // Handler for the estimate form page
 async fn handle_estimate_form(
     tera: Arc<Tera>,
-    data: RegattaData,
+    data: Arc<RegattaData>,
+    lang: Lang,
+    theme: Theme,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let mut context = Context::new();
+    let mut context = page_context(lang, theme);
+
+    // The buoys with a start or leg from them, the destinations are looked
+    // up with the neighbors endpoint once one is chosen
+    context.insert("starts", &departure_buoys(&data));
+
+    let rendered_html = render_template(&tera, "estimate.html", &context)?;
+
+    Ok(html(rendered_html))
+}
+
+// Handler for the estimate leg form page
+async fn handle_estimate_leg_form(
+    tera: Arc<Tera>,
+    data: Arc<RegattaData>,
+    lang: Lang,
+    theme: Theme,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut context = page_context(lang, theme);
+
+    // Get legs sorted alphabetically by from, then to
+    let mut legs = data.rakken.clone();
+    legs.sort_by(|a, b| a.from.cmp(&b.from).then_with(|| a.to.cmp(&b.to)));
+
+    // Convert Rak structs to a format that Tera can understand
+    let legs_for_template: Vec<serde_json::Value> = legs
+        .iter()
+        .map(|rak| {
+            json!({
+                "from": rak.from,
+                "to": rak.to,
+                "distance": rak.distance
+            })
+        })
+        .collect();
+
+    context.insert("legs", &legs_for_template);
+
+    let rendered_html = render_template(&tera, "estimate-leg.html", &context)?;
+
+    Ok(html(rendered_html))
+}
+
+// Handler for the find paths form page
+async fn handle_find_paths_form(
+    tera: Arc<Tera>,
+    data: Arc<RegattaData>,
+    lang: Lang,
+    theme: Theme,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut context = page_context(lang, theme);
 
     // Get boeien names for the dropdown
     let boeien: Vec<String> = data.boeien.iter().map(|boei| boei.name.clone()).collect();
 
     context.insert("boeien", &boeien);
 
-    let rendered_html = tera.render("estimate.html", &context).map_err(|e| {
-        eprintln!("Template rendering error: {e}");
-        warp::reject::custom(TemplateError)
-    })?;
+    context.insert("starts", &departure_buoys(&data));
+
+    let rendered_html = render_template(&tera, "find-paths.html", &context)?;
+
+    Ok(html(rendered_html))
+}
+
+// Handler for the find target form page
+async fn handle_find_target_form(
+    tera: Arc<Tera>,
+    data: Arc<RegattaData>,
+    lang: Lang,
+    theme: Theme,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut context = page_context(lang, theme);
+
+    // Get boeien names for the dropdown
+    let boeien: Vec<String> = data.boeien.iter().map(|boei| boei.name.clone()).collect();
+
+    context.insert("boeien", &boeien);
+
+    context.insert("starts", &departure_buoys(&data));
+
+    let rendered_html = render_template(&tera, "find-target.html", &context)?;
 
     Ok(html(rendered_html))
 }
 
-// Handler for the estimate leg form page
-async fn handle_estimate_leg_form(
-    tera: Arc<Tera>,
-    data: RegattaData,
+// Handler for the course map page
+async fn handle_map_page(
+    tera: Arc<Tera>,
+    data: Arc<RegattaData>,
+    lang: Lang,
+    theme: Theme,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut context = page_context(lang, theme);
+
+    // Get boeien names for the dropdowns
+    let boeien: Vec<String> = data.boeien.iter().map(|boei| boei.name.clone()).collect();
+
+    context.insert("boeien", &boeien);
+
+    let rendered_html = render_template(&tera, "map.html", &context)?;
+
+    Ok(html(rendered_html))
+}
+
+// Handler for the wind editor page
+async fn handle_wind_page(
+    tera: Arc<Tera>,
+    data: Arc<RegattaData>,
+    lang: Lang,
+    theme: Theme,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut context = page_context(lang, theme);
+
+    // The hours of the forecast refer to the first start, like the clock
+    let offset = data.wind_data.start_offset;
+    let wind: Vec<_> = data
+        .wind_data
+        .get_all_conditions()
+        .iter()
+        .map(|condition| {
+            json!({
+                "time": condition.time,
+                "clock": data.race_clock.format_clock(condition.time as f64 - offset),
+                "wind_speed": condition.wind_speed,
+                "wind_angle": condition.wind_angle,
+            })
+        })
+        .collect();
+
+    context.insert("wind", &wind);
+
+    let rendered_html = render_template(&tera, "wind.html", &context)?;
+
+    Ok(html(rendered_html))
+}
+
+// Handler for the dashboard page
+async fn handle_dashboard_page(
+    tera: Arc<Tera>,
+    data: Arc<RegattaData>,
+    lang: Lang,
+    theme: Theme,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut context = page_context(lang, theme);
+
+    // Get boeien names for the leg selection
+    let boeien: Vec<String> = data.boeien.iter().map(|boei| boei.name.clone()).collect();
+
+    context.insert("boeien", &boeien);
+
+    let rendered_html = render_template(&tera, "dashboard.html", &context)?;
+
+    Ok(html(rendered_html))
+}
+
+// Handler for the next leg page, which fetches everything it shows itself
+async fn handle_next_page(tera: Arc<Tera>, lang: Lang, theme: Theme) -> Result<impl warp::Reply, warp::Rejection> {
+    let rendered_html = render_template(&tera, "next.html", &page_context(lang, theme))?;
+
+    Ok(html(rendered_html))
+}
+
+// Handler for the fleet overview page
+async fn handle_fleet_page(tera: Arc<Tera>, lang: Lang, theme: Theme) -> Result<impl warp::Reply, warp::Rejection> {
+    let rendered_html = render_template(&tera, "fleet.html", &page_context(lang, theme))?;
+
+    Ok(html(rendered_html))
+}
+
+// Handler for the finish approach page, which fetches the departures itself
+async fn handle_finish_page(tera: Arc<Tera>, lang: Lang, theme: Theme) -> Result<impl warp::Reply, warp::Rejection> {
+    let rendered_html = render_template(&tera, "finish.html", &page_context(lang, theme))?;
+
+    Ok(html(rendered_html))
+}
+
+// Handler for the leaderboard page, which fetches the standings itself
+async fn handle_leaderboard_page(tera: Arc<Tera>, lang: Lang, theme: Theme) -> Result<impl warp::Reply, warp::Rejection> {
+    let rendered_html = render_template(&tera, "leaderboard.html", &page_context(lang, theme))?;
+
+    Ok(html(rendered_html))
+}
+
+// Handler for the virtual regatta page
+async fn handle_training_page(
+    tera: Arc<Tera>,
+    data: Arc<RegattaData>,
+    lang: Lang,
+    theme: Theme,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut context = page_context(lang, theme);
+
+    // Start buoys to begin a game at
+    let mut starts: Vec<String> = data.starts.iter().map(|start| start.from.clone()).collect();
+    starts.sort();
+    starts.dedup();
+
+    context.insert("starts", &starts);
+
+    let rendered_html = render_template(&tera, "training.html", &context)?;
+
+    Ok(html(rendered_html))
+}
+
+// Handler for the path explorer page
+async fn handle_explore_page(
+    tera: Arc<Tera>,
+    data: Arc<RegattaData>,
+    lang: Lang,
+    theme: Theme,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let mut context = page_context(lang, theme);
+    context.insert("deadline", &data.rules.scoring.deadline);
+
+    let rendered_html = render_template(&tera, "explore.html", &context)?;
+
+    Ok(html(rendered_html))
+}
+
+// Handler for the estimate endpoint
+async fn handle_estimate(
+    query: EstimateQuery,
+    snapshot: Snapshot,
+    cache: Arc<ResponseCache>,
+    lang: Lang,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let key = format!("{}:estimate:{}:{:?}", snapshot.cache_prefix(), lang.code(), query);
+    if let Some(body) = cache.get(&key) {
+        return Ok(json_reply(body.as_ref().clone()));
+    }
+    let data = snapshot.data;
+
+    // Get boei indices by name
+    let from_idx = match data.get_boei_index(&query.from) {
+        Some(idx) => idx,
+        None => {
+            return Err(warp::reject::custom(ApiError::not_found(
+                "Boei not found",
+                format!("Boei '{}' not found", query.from),
+            )));
+        }
+    };
+
+    let to_idx = match data.get_boei_index(&query.to) {
+        Some(idx) => idx,
+        None => {
+            return Err(warp::reject::custom(ApiError::not_found(
+                "Boei not found",
+                format!("Boei '{}' not found", query.to),
+            )));
+        }
+    };
+
+    if from_idx == to_idx {
+        return Err(warp::reject::custom(ApiError::bad_request(
+            "Invalid request",
+            "From and to buoys must be different",
+        )));
+    }
+
+    // Validate time parameter
+    let time = race_hours(&data, &query.time).map_err(warp::reject::custom)?;
+
+    // Estimate leg performance
+    let performance = estimate_leg_performance(&data, from_idx, to_idx, time)
+        .map_err(|e| warp::reject::custom(ApiError::from(e)))?;
+
+    // Return the result as JSON
+    let response = EstimateResponse::new(&data, &query.from, &query.to, time, &performance, lang);
+
+    cached_reply(&cache, key, &response)
+}
+
+// Handler for the estimate leg endpoint
+async fn handle_estimate_leg(
+    query: EstimateLegQuery,
+    snapshot: Snapshot,
+    cache: Arc<ResponseCache>,
+    lang: Lang,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let key = format!("{}:estimateleg:{}:{:?}", snapshot.cache_prefix(), lang.code(), query);
+    if let Some(body) = cache.get(&key) {
+        return Ok(json_reply(body.as_ref().clone()));
+    }
+    let data = snapshot.data;
+
+    // Handle reverse direction by swapping from and to
+    let (from_name, to_name) = if query.reverse.unwrap_or(false) {
+        (query.to.clone(), query.from.clone())
+    } else {
+        (query.from.clone(), query.to.clone())
+    };
+
+    // Get boei indices by name
+    let from_idx = match data.get_boei_index(&from_name) {
+        Some(idx) => idx,
+        None => {
+            return Err(warp::reject::custom(ApiError::not_found(
+                "Boei not found",
+                format!("Boei '{}' not found", from_name),
+            )));
+        }
+    };
+
+    let to_idx = match data.get_boei_index(&to_name) {
+        Some(idx) => idx,
+        None => {
+            return Err(warp::reject::custom(ApiError::not_found(
+                "Boei not found",
+                format!("Boei '{}' not found", to_name),
+            )));
+        }
+    };
+
+    // Only starts and legs, in the direction they may be sailed
+    check_leg(&data, from_idx, to_idx).map_err(warp::reject::custom)?;
+
+    // Validate time parameter
+    let time = race_hours(&data, &query.time).map_err(warp::reject::custom)?;
+
+    // Estimate leg performance
+    let performance = estimate_leg_performance(&data, from_idx, to_idx, time)
+        .map_err(|e| warp::reject::custom(ApiError::from(e)))?;
+
+    // Return the result as JSON
+    let response = EstimateResponse::new(&data, &from_name, &to_name, time, &performance, lang);
+
+    cached_reply(&cache, key, &response)
+}
+
+// Handler for the neighbors endpoint, the destinations the forms offer
+async fn handle_neighbors(
+    query: NeighborsQuery,
+    data: Arc<RegattaData>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let from = data.get_boei_index(&query.buoy).ok_or_else(|| {
+        warp::reject::custom(ApiError::not_found(
+            "Buoy not found",
+            format!("Buoy '{}' not found", query.buoy),
+        ))
+    })?;
+    let neighbors: Vec<ConnectionResponse> = data
+        .neighbors(from)
+        .into_iter()
+        .map(|(to, distance)| ConnectionResponse {
+            from: query.buoy.clone(),
+            to: data.boeien[to].name.clone(),
+            distance,
+        })
+        .collect();
+    Ok(warp::reply::json(&neighbors))
+}
+
+// Handler for the arrival profile endpoint
+async fn handle_arrival_profile(
+    query: ArrivalProfileQuery,
+    snapshot: Snapshot,
+    cache: Arc<ResponseCache>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let key = format!("{}:arrival-profile:{:?}", snapshot.cache_prefix(), query);
+    if let Some(body) = cache.get(&key) {
+        return Ok(json_reply(body.as_ref().clone()));
+    }
+    let response = profile_arrivals(&query, &snapshot.data).map_err(warp::reject::custom)?;
+    cached_reply(&cache, key, &response)
+}
+
+// Earliest arrivals at every buoy from the start of the query for its
+// departures, from race start to the end of the race by default
+fn profile_arrivals(query: &ArrivalProfileQuery, data: &RegattaData) -> Result<ArrivalProfileResponse, ApiError> {
+    let start = data.get_boei_index(&query.start).ok_or_else(|| {
+        ApiError::not_found("Buoy not found", format!("Starting buoy '{}' not found", query.start))
+    })?;
+    let from = query.from.as_ref().map(|time| race_hours(data, time)).transpose()?.unwrap_or(0.0);
+    let until = match &query.until {
+        Some(time) => race_hours(data, time)?,
+        None => data.rules.scoring.deadline.max(from),
+    };
+    let interval = query.interval.map_or(DEFAULT_INTERVAL, |minutes| minutes / 60.0);
+    let constraints = avoid_constraints(data, &query.avoid, &query.avoid_leg)?;
+    check_departures(data, start)?;
+    let profile = arrival_profile(data, start, from, until, interval, &constraints)?;
+    Ok(ArrivalProfileResponse::new(data, &profile, interval))
+}
+
+// Handler for the find paths endpoint
+async fn handle_find_paths(
+    query: FindPathsQuery,
+    snapshot: Snapshot,
+    cache: Arc<ResponseCache>,
+    metrics: Arc<Metrics>,
+    remote: Option<SocketAddr>,
+    limits: Arc<SearchLimits>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let key = format!("{}:find-paths:{:?}", snapshot.cache_prefix(), query);
+    if let Some(body) = cache.get(&key) {
+        return Ok(json_reply(body.as_ref().clone()));
+    }
+    let response = find_paths(query, &snapshot.data, metrics, remote, &limits)
+        .await
+        .map_err(warp::reject::custom)?;
+    cached_reply(&cache, key, &response)
+}
+
+// Handler for the table of the paths found, rendered for the find-paths page
+// with the parameters of the find-paths endpoint
+async fn handle_find_paths_html(
+    query: FindPathsQuery,
+    tera: Arc<Tera>,
+    snapshot: Snapshot,
+    metrics: Arc<Metrics>,
+    remote: Option<SocketAddr>,
+    limits: Arc<SearchLimits>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let sort = query.sort.clone();
+    let response = find_paths(query, &snapshot.data, metrics, remote, &limits)
+        .await
+        .map_err(warp::reject::custom)?;
+
+    let mut context = Context::new();
+    context.insert("result", &response);
+    context.insert("sort", &sort);
+
+    let rendered_html = render_template(&tera, "find-paths-results.html", &context)?;
+
+    Ok(html(rendered_html))
+}
+
+// Search the paths from a starting buoy which pass the filters of the query,
+// in the order it asks for
+async fn find_paths(
+    query: FindPathsQuery,
+    data: &Arc<RegattaData>,
+    metrics: Arc<Metrics>,
+    remote: Option<SocketAddr>,
+    limits: &Arc<SearchLimits>,
+) -> Result<FindPathsResponse, ApiError> {
+    // Get starting buoy index by name
+    let start_idx = data.get_boei_index(&query.start).ok_or_else(|| {
+        ApiError::not_found("Buoy not found", format!("Starting buoy '{}' not found", query.start))
+    })?;
+
+    // Validate the parameters
+    let time = race_hours(data, &query.time)?;
+    let steps = step_count(data, query.steps)?;
+    let max_paths = max_paths(data, "max_paths", query.max_paths)?;
+    let buoy = |name: &Option<String>, what: &str| {
+        name.as_deref()
+            .map(|name| {
+                data.get_boei_index(name).ok_or_else(|| {
+                    ApiError::not_found("Buoy not found", format!("{what} buoy '{name}' not found"))
+                })
+            })
+            .transpose()
+    };
+    let filter = PathFilter {
+        include: buoy(&query.include, "Included")?,
+        finish: buoy(&query.finish, "Finish")?,
+    };
+    let constraints = avoid_constraints(data, &query.avoid, &query.avoid_leg)?;
+    check_departures(data, start_idx)?;
+    let order = query
+        .sort
+        .as_deref()
+        .map(|name| {
+            PathOrder::from_name(name).ok_or_else(|| {
+                ApiError::bad_request(
+                    "Invalid sort",
+                    format!("Unknown sort '{name}', expected distance, end_time, or speed"),
+                )
+            })
+        })
+        .transpose()?;
+
+    // Explore paths
+    let permit = admit_search(limits, remote, data, steps)?;
+    let search_data = data.clone();
+    let budget = limits.budget();
+    let results = limits
+        .run(permit, move || {
+            let monitor = SearchMonitor::new();
+            let options = SearchOptions {
+                max_paths,
+                constraints,
+                monitor: Some(&monitor),
+                budget,
+                filter,
+                ..Default::default()
+            };
+            monitored_search(&metrics, &monitor, || {
+                search_paths(&search_data, start_idx, time, steps, &options)
+            })
+        })
+        .await
+        .map_err(search_failed)??;
+    let mut paths = results.paths(data);
+    if let Some(order) = order {
+        order.sort_paths(data, &mut paths);
+    }
+    Ok(FindPathsResponse {
+        start: query.start,
+        target: None,
+        start_time: time,
+        steps: query.steps,
+        truncated: results.truncated,
+        stats: results.stats,
+        paths: paths.iter().map(|path| PathResponse::new(data, path)).collect(),
+    })
+}
+
+// Handler for the find target endpoint
+async fn handle_find_target(
+    query: FindTargetQuery,
+    snapshot: Snapshot,
+    cache: Arc<ResponseCache>,
+    metrics: Arc<Metrics>,
+    remote: Option<SocketAddr>,
+    limits: Arc<SearchLimits>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let key = format!("{}:find-targets:{:?}", snapshot.cache_prefix(), query);
+    if let Some(body) = cache.get(&key) {
+        return Ok(json_reply(body.as_ref().clone()));
+    }
+    let data = snapshot.data;
+
+    let results = target_search(&data, &query, metrics, remote, &limits)
+        .await
+        .map_err(warp::reject::custom)?;
+    let response = FindPathsResponse {
+        start: query.start,
+        target: Some(query.target),
+        start_time: resolve_time(&data, &query.time).map_err(warp::reject::custom)?,
+        steps: query.steps,
+        truncated: results.truncated,
+        stats: results.stats,
+        paths: results.paths(&data).iter().map(|path| PathResponse::new(&data, path)).collect(),
+    };
+
+    cached_reply(&cache, key, &response)
+}
+
+// Handler for the find target endpoint returning the paths as CSV leg table
+async fn handle_find_target_csv(
+    query: FindTargetQuery,
+    snapshot: Snapshot,
+    metrics: Arc<Metrics>,
+    remote: Option<SocketAddr>,
+    limits: Arc<SearchLimits>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let data = snapshot.data;
+    let results = target_search(&data, &query, metrics, remote, &limits)
+        .await
+        .map_err(warp::reject::custom)?;
+
+    let csv = paths_to_csv(&data, &results.paths(&data)).map_err(|e| {
+        warp::reject::custom(ApiError::internal(
+            "CSV export failed",
+            format!("Error writing CSV: {e}"),
+        ))
+    })?;
+    Ok(download_reply(
+        csv,
+        "text/csv; charset=utf-8",
+        &format!("paths-{}-{}.csv", query.start, query.target),
+    ))
+}
+
+// Validate a find target query and search the paths to the target
+async fn target_search(
+    data: &Arc<RegattaData>,
+    query: &FindTargetQuery,
+    metrics: Arc<Metrics>,
+    remote: Option<SocketAddr>,
+    limits: &Arc<SearchLimits>,
+) -> Result<SearchResults, ApiError> {
+    // Get starting buoy index by name
+    let start_idx = match data.get_boei_index(&query.start) {
+        Some(idx) => idx,
+        None => {
+            return Err(ApiError::not_found(
+                "Buoy not found",
+                format!("Starting buoy '{}' not found", query.start),
+            ));
+        }
+    };
+
+    // Get target buoy index by name
+    let target_idx = match data.get_boei_index(&query.target) {
+        Some(idx) => idx,
+        None => {
+            return Err(ApiError::not_found(
+                "Buoy not found",
+                format!("Target buoy '{}' not found", query.target),
+            ));
+        }
+    };
+
+    // Validate the parameters
+    let time = race_hours(data, &query.time)?;
+    let steps = step_count(data, query.steps)?;
+    let max_paths = max_paths(data, "max_paths", query.max_paths)?;
+    let constraints = avoid_constraints(data, &query.avoid, &query.avoid_leg)?;
+
+    // Check if start and target are the same
+    if start_idx == target_idx {
+        return Err(ApiError::bad_request(
+            "Invalid request",
+            "Starting and target buoys must be different",
+        ));
+    }
+    check_departures(data, start_idx)?;
+    if !data.leg_distances.keys().any(|&(_, to)| to == target_idx) {
+        return Err(ApiError::unprocessable(
+            "No leg",
+            format!("There is no start or leg to '{}', no path can reach it", query.target),
+        ));
+    }
+
+    // Explore paths to target
+    let permit = admit_search(limits, remote, data, steps)?;
+    let data = data.clone();
+    let budget = limits.budget();
+    let result = limits
+        .run(permit, move || {
+            let monitor = SearchMonitor::new();
+            let options = SearchOptions {
+                max_paths,
+                constraints,
+                monitor: Some(&monitor),
+                budget,
+                ..Default::default()
+            };
+            monitored_search(&metrics, &monitor, || {
+                search_target_paths(&data, start_idx, target_idx, time, steps, &options)
+            })
+        })
+        .await
+        .map_err(search_failed)?;
+    result.map_err(ApiError::from)
+}
+
+// Resolve a time parameter, given in hours or as clock time, to hours
+// since the race start
+fn resolve_time(data: &RegattaData, time: &RaceTime) -> Result<f64, ApiError> {
+    data.race_clock.resolve(time).map_err(|e| ApiError::bad_request("Invalid time", e.to_string()))
+}
+
+// Resolve a time parameter which must be within the race
+fn race_hours(data: &RegattaData, time: &RaceTime) -> Result<f64, ApiError> {
+    RaceHours::resolve(data, time)
+        .map(RaceHours::hours)
+        .map_err(|e| ApiError::bad_request("Invalid time", e.to_string()))
+}
+
+// Check the steps of a search against the limits of the rules
+fn step_count(data: &RegattaData, steps: usize) -> Result<usize, ApiError> {
+    StepCount::new(data, steps)
+        .map(StepCount::get)
+        .map_err(|e| ApiError::bad_request("Invalid steps", e.to_string()))
+}
+
+// Check the longest wait at a buoy against the deadline of the rules
+fn wait_hours(data: &RegattaData, hours: f64) -> Result<f64, ApiError> {
+    WaitHours::new(data, hours)
+        .map(WaitHours::hours)
+        .map_err(|e| ApiError::bad_request("Invalid max_wait", e.to_string()))
+}
+
+// Resolve the avoid lists of a query to the constraints of a search
+fn avoid_constraints(
+    data: &RegattaData,
+    avoid: &Option<String>,
+    avoid_leg: &Option<String>,
+) -> Result<SearchConstraints, ApiError> {
+    SearchConstraints::from_avoid_lists(data, avoid.as_deref(), avoid_leg.as_deref()).map_err(ApiError::from)
+}
+
+// Look up the objective of a search by its name
+fn plan_objective(name: &str) -> Result<PlanObjective, ApiError> {
+    PlanObjective::from_name(name).ok_or_else(|| {
+        ApiError::bad_request(
+            "Invalid objective",
+            format!(
+                "Unknown objective '{name}', expected 'earliest_arrival', 'max_distance', 'qualify', 'max_margin', \
+                 or 'weighted:' with weights of {}, e.g. 'weighted:scored_distance=1,margin=2'",
+                ObjectiveWeights::MEASURES.join(", ")
+            ),
+        )
+    })
+}
+
+// Check an optional number of paths against the limits of the rules
+fn max_paths(data: &RegattaData, name: &str, max_paths: Option<usize>) -> Result<Option<usize>, ApiError> {
+    MaxPaths::optional(data, max_paths)
+        .map_err(|e| ApiError::bad_request(format!("Invalid {name}"), e.to_string()))
+}
+
+// Check that there is a start or leg from one buoy to another, naming the
+// buoys which can be sailed to instead
+fn check_leg(data: &RegattaData, from: usize, to: usize) -> Result<(), ApiError> {
+    if data.leg_distance(from, to).is_some() {
+        return Ok(());
+    }
+    check_departures(data, from)?;
+    let names: Vec<&str> = data
+        .neighbors(from)
+        .into_iter()
+        .map(|(to, _)| data.boeien[to].name.as_str())
+        .collect();
+    Err(ApiError::unprocessable(
+        "No leg",
+        format!(
+            "There is no start or leg from '{}' to '{}'; from '{}' the legs go to {}",
+            data.boeien[from].name,
+            data.boeien[to].name,
+            data.boeien[from].name,
+            names.join(", ")
+        ),
+    ))
+}
+
+// Check that a path can start at a buoy
+fn check_departures(data: &RegattaData, from: usize) -> Result<(), ApiError> {
+    if data.neighbors(from).is_empty() {
+        return Err(ApiError::unprocessable(
+            "No leg",
+            format!("There is no start or leg from '{}'", data.boeien[from].name),
+        ));
+    }
+    Ok(())
+}
+
+// Names of the buoys with a start or leg from them, where paths can start
+fn departure_buoys(data: &RegattaData) -> Vec<String> {
+    (0..data.boeien.len())
+        .filter(|&index| !data.neighbors(index).is_empty())
+        .map(|index| data.boeien[index].name.clone())
+        .collect()
+}
+
+// Resolve a comma separated list of buoy names into buoy indices
+fn parse_route(data: &RegattaData, route: &str) -> Result<Vec<usize>, String> {
+    route
+        .split(',')
+        .map(|name| {
+            let name = name.trim();
+            data.get_boei_index(name)
+                .ok_or_else(|| format!("Buoy '{name}' not found"))
+        })
+        .collect()
+}
+
+// Resolve and evaluate the route of a route query
+fn evaluate_route_query(data: &RegattaData, query: &RouteProfileQuery) -> Result<Path, ApiError> {
+    let route = parse_route(data, &query.path)
+        .map_err(|message| ApiError::not_found("Buoy not found", message))?;
+
+    // Validate time parameter
+    let start_time = match &query.time {
+        Some(time) => race_hours(data, time)?,
+        None => 0.0,
+    };
+
+    evaluate_route(data, &route, start_time).map_err(ApiError::from)
+}
+
+// Handler for the route profile chart
+async fn handle_route_profile(
+    query: RouteProfileQuery,
+    data: Arc<RegattaData>,
+    theme: Theme,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let path = evaluate_route_query(&data, &query).map_err(warp::reject::custom)?;
+
+    match create_route_profile_plot(&data, &path, themed(theme)) {
+        Ok(svg_content) => Ok(Box::new(warp::reply::with_header(
+            svg_content,
+            "Content-Type",
+            "image/svg+xml",
+        ))),
+        Err(e) => Err(warp::reject::custom(ApiError::internal(
+            "Chart generation failed",
+            format!("Error generating route profile: {e}"),
+        ))),
+    }
+}
+
+// Handler for the course map with a route drawn over it
+async fn handle_route_map(
+    query: RouteProfileQuery,
+    data: Arc<RegattaData>,
+    theme: Theme,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let path = evaluate_route_query(&data, &query).map_err(warp::reject::custom)?;
+
+    match create_route_map(&data, &path, themed(theme)) {
+        Ok(svg_content) => Ok(Box::new(warp::reply::with_header(
+            svg_content,
+            "Content-Type",
+            "image/svg+xml",
+        ))),
+        Err(e) => Err(warp::reject::custom(ApiError::internal(
+            "Chart generation failed",
+            format!("Error generating route map: {e}"),
+        ))),
+    }
+}
+
+// Handler for the GPX route download
+async fn handle_route_gpx(
+    query: RouteProfileQuery,
+    data: Arc<RegattaData>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let path = evaluate_route_query(&data, &query).map_err(warp::reject::custom)?;
+
+    let name = query
+        .path
+        .split(',')
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" - ");
+    let gpx = path_to_gpx(&data, &path, &name).map_err(|e| {
+        warp::reject::custom(ApiError::unprocessable(
+            "GPX export failed",
+            format!("Error writing GPX route: {e}"),
+        ))
+    })?;
+    Ok(download_reply(gpx, "application/gpx+xml", "route.gpx"))
+}
+
+// Handler for the GeoJSON route
+async fn handle_route_geojson(
+    query: RouteProfileQuery,
+    data: Arc<RegattaData>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let path = evaluate_route_query(&data, &query).map_err(warp::reject::custom)?;
+
+    let name = query
+        .path
+        .split(',')
+        .map(str::trim)
+        .collect::<Vec<_>>()
+        .join(" - ");
+    let geojson = path_to_geojson(&data, &path, &name).map_err(|e| {
+        warp::reject::custom(ApiError::unprocessable(
+            "GeoJSON export failed",
+            format!("Error writing GeoJSON route: {e}"),
+        ))
+    })?;
+    Ok(warp::reply::with_header(
+        geojson.to_string(),
+        "Content-Type",
+        "application/geo+json",
+    ))
+}
+
+// Admit a search with the given number of steps for a client, rejecting it
+// if it is too large or the client already runs too many searches
+fn admit_search(
+    limits: &Arc<SearchLimits>,
+    remote: Option<SocketAddr>,
+    data: &RegattaData,
+    steps: usize,
+) -> Result<SearchPermit, ApiError> {
+    limits
+        .admit(remote.map(|addr| addr.ip()), estimate_search_nodes(data, steps))
+        .map_err(ApiError::from)
+}
+
+// Admit a plan request like `admit_search`, estimating its size by its strategy
+fn admit_plan(
+    limits: &Arc<SearchLimits>,
+    remote: Option<SocketAddr>,
+    data: &RegattaData,
+    request: &PlanRequest,
+) -> Result<SearchPermit, ApiError> {
+    let nodes = match request.strategy.as_deref() {
+        Some("hierarchical") => estimate_hierarchical_nodes(data, request.steps),
+        _ => estimate_search_nodes(data, request.steps),
+    };
+    limits.admit(remote.map(|addr| addr.ip()), nodes).map_err(ApiError::from)
+}
+
+// A search task which panicked or was aborted
+fn search_failed(e: tokio::task::JoinError) -> ApiError {
+    ApiError::internal("Search failed", format!("Search task failed: {e}"))
+}
+
+// Run a search, recording its statistics in the metrics and the log
+fn monitored_search<T>(
+    metrics: &Metrics,
+    monitor: &SearchMonitor,
+    search: impl FnOnce() -> T,
+) -> T {
+    let started = Instant::now();
+    let result = search();
+    let elapsed = started.elapsed();
+    metrics.record_search(monitor, elapsed);
+    tracing::info!(
+        nodes_explored = monitor.nodes_explored(),
+        paths_found = monitor.paths_found(),
+        cancelled = monitor.is_cancelled(),
+        elapsed_ms = elapsed.as_secs_f64() * 1000.0,
+        "search finished"
+    );
+    result
+}
+
+// Resolve the pins of a plan request from buoy names to indices, checking
+// that their places in the route fit the steps and follow each other
+fn plan_pins(data: &RegattaData, pins: &[PinRef], steps: usize) -> Result<Vec<Pin>, ApiError> {
+    let buoy = |name: &str| {
+        data.get_boei_index(name).ok_or_else(|| {
+            ApiError::not_found("Buoy not found", format!("Pinned buoy '{name}' not found"))
+        })
+    };
+    let mut resolved: Vec<Pin> = Vec::with_capacity(pins.len());
+    for pin in pins {
+        let to = buoy(&pin.buoy)?;
+        let from = pin.from.as_deref().map(buoy).transpose()?;
+        if let Some(from) = from {
+            check_leg(data, from, to)?;
+        }
+        if let Some(slot) = pin.slot {
+            if slot > steps || (slot == 0 && from.is_some()) {
+                return Err(ApiError::bad_request(
+                    "Invalid pin",
+                    format!("The slot of '{}' must be between {} and the {steps} steps", pin.buoy, usize::from(from.is_some())),
+                ));
+            }
+            if resolved.iter().filter_map(|earlier| earlier.slot).any(|earlier| earlier >= slot) {
+                return Err(ApiError::bad_request(
+                    "Invalid pin",
+                    format!("The slot of '{}' must come after the slots of the pins before it", pin.buoy),
+                ));
+            }
+        }
+        let tolerance = pin.tolerance.unwrap_or(PIN_TOLERANCE);
+        let deadline = data.rules.scoring.deadline;
+        if !(0.0..=deadline).contains(&tolerance) {
+            return Err(ApiError::bad_request(
+                "Invalid pin",
+                format!("The tolerance of '{}' must be between 0 and {deadline} hours", pin.buoy),
+            ));
+        }
+        resolved.push(Pin {
+            buoy: to,
+            from,
+            slot: pin.slot,
+            time: pin.time.as_ref().map(|time| race_hours(data, time)).transpose()?,
+            tolerance,
+        });
+    }
+    Ok(resolved)
+}
+
+// Validate a plan request and run the search
+fn plan_paths(
+    data: &RegattaData,
+    request: &PlanRequest,
+    monitor: &SearchMonitor,
+    metrics: &Metrics,
+    budget: SearchBudget,
+) -> Result<PlanResponse, ApiError> {
+    // Get starting buoy index by name
+    let start_idx = data.get_boei_index(&request.start).ok_or_else(|| {
+        ApiError::not_found(
+            "Buoy not found",
+            format!("Starting buoy '{}' not found", request.start),
+        )
+    })?;
+
+    // Get target buoy index by name, if any
+    let target_idx = match &request.target {
+        Some(target) => Some(data.get_boei_index(target).ok_or_else(|| {
+            ApiError::not_found("Buoy not found", format!("Target buoy '{target}' not found"))
+        })?),
+        None => None,
+    };
+
+    // Exhaustive search by default, hierarchical planning for routes longer
+    // than a search can look ahead
+    let strategy = request.strategy.as_deref().unwrap_or("exhaustive");
+    let hierarchical = match strategy {
+        "exhaustive" => false,
+        "hierarchical" => true,
+        _ => {
+            return Err(ApiError::bad_request(
+                "Invalid strategy",
+                format!("Unknown strategy '{strategy}', expected 'exhaustive' or 'hierarchical'"),
+            ));
+        }
+    };
+
+    // Validate the parameters
+    let start_time = race_hours(data, &request.start_time)?;
+    if hierarchical {
+        if request.steps == 0 {
+            return Err(ApiError::bad_request("Invalid steps", "A route needs at least one leg"));
+        }
+        check_route_length(data, request.steps)
+            .map_err(|e| ApiError::bad_request("Invalid steps", e.to_string()))?;
+        if !request.pins.is_empty() {
+            return Err(ApiError::bad_request("Invalid pins", "Pins need the exhaustive strategy"));
+        }
+    } else {
+        step_count(data, request.steps)?;
+    }
+    max_paths(data, "max_paths", request.max_paths)?;
+    max_paths(data, "max_results", request.max_results)?;
+
+    let max_wait = wait_hours(data, request.max_wait.unwrap_or(0.0))?;
+    let diversity = request.diversity.unwrap_or(0.0);
+    if !(0.0..=1.0).contains(&diversity) {
+        return Err(ApiError::bad_request("Invalid diversity", "diversity must be between 0 and 1"));
+    }
+
+    let objective = plan_objective(request.objective.as_deref().unwrap_or("earliest_arrival"))?;
+
+    // Resolve the constraints from buoy names to indices
+    let mut constraints = SearchConstraints::default();
+    for name in &request.constraints.forbidden_buoys {
+        match data.get_boei_index(name) {
+            Some(idx) => constraints.forbidden_buoys.push(idx),
+            None => {
+                return Err(ApiError::not_found(
+                    "Buoy not found",
+                    format!("Forbidden buoy '{}' not found", name),
+                ));
+            }
+        }
+    }
+    for leg in &request.constraints.forbidden_legs {
+        match (data.get_boei_index(&leg.from), data.get_boei_index(&leg.to)) {
+            (Some(from), Some(to)) => constraints.forbidden_legs.push((from, to)),
+            _ => {
+                return Err(ApiError::not_found(
+                    "Buoy not found",
+                    format!("Forbidden leg '{}' -> '{}' refers to an unknown buoy", leg.from, leg.to),
+                ));
+            }
+        }
+    }
+
+    let pins = plan_pins(data, &request.pins, request.steps)?;
+
+    let options = SearchOptions {
+        max_paths: request.max_paths,
+        constraints,
+        monitor: Some(monitor),
+        max_wait,
+        budget,
+        pins,
+        ..Default::default()
+    };
+    let selection = PathSelection {
+        objective: Arc::new(objective),
+        routes: request.max_results,
+        min_distance: diversity,
+    };
+    let (paths_found, truncated, stats, paths) = if hierarchical {
+        let plan = monitored_search(metrics, monitor, || {
+            plan_hierarchical(data, start_idx, target_idx, start_time, request.steps, &objective, &options)
+        })?;
+        (plan.paths.len(), false, plan.stats, selection.select(data, plan.paths))
+    } else {
+        let results = monitored_search(metrics, monitor, || match target_idx {
+            Some(target_idx) => search_target_paths(
+                data,
+                start_idx,
+                target_idx,
+                start_time,
+                request.steps,
+                &options,
+            ),
+            None => search_paths(data, start_idx, start_time, request.steps, &options),
+        })?;
+        // Only the paths returned are built from the compact results,
+        // unless they must differ from each other
+        (results.len(), results.truncated, results.stats, results.select(data, &selection))
+    };
+
+    Ok(PlanResponse {
+        start: request.start.clone(),
+        target: request.target.clone(),
+        start_time,
+        steps: request.steps,
+        strategy: strategy.to_string(),
+        objective: objective.name(),
+        paths_found,
+        truncated,
+        stats,
+        paths: paths.iter().map(|path| PathResponse::new(data, path)).collect(),
+    })
+}
+
+// Handler for the plan endpoint
+async fn handle_plan(
+    request: PlanRequest,
+    snapshot: Snapshot,
+    cache: Arc<ResponseCache>,
+    metrics: Arc<Metrics>,
+    remote: Option<SocketAddr>,
+    limits: Arc<SearchLimits>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let key = format!("{}:plan:{:?}", snapshot.cache_prefix(), request);
+    if let Some(body) = cache.get(&key) {
+        return Ok(json_reply(body.as_ref().clone()));
+    }
+
+    let permit = admit_plan(&limits, remote, &snapshot.data, &request)
+        .map_err(warp::reject::custom)?;
+    let data = snapshot.data;
+    let budget = limits.budget();
+    let response = limits
+        .run(permit, move || plan_paths(&data, &request, &SearchMonitor::new(), &metrics, budget))
+        .await
+        .map_err(|e| warp::reject::custom(search_failed(e)))?
+        .map_err(warp::reject::custom)?;
+    cached_reply(&cache, key, &response)
+}
+
+// Handler creating an asynchronous search job
+async fn handle_create_job(
+    request: PlanRequest,
+    data: Arc<RegattaData>,
+    jobs: Jobs,
+    metrics: Arc<Metrics>,
+    remote: Option<SocketAddr>,
+    limits: Arc<SearchLimits>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // The permit moves into the search task and counts until the job finishes
+    let permit =
+        admit_plan(&limits, remote, &data, &request).map_err(warp::reject::custom)?;
+    let monitor = Arc::new(SearchMonitor::new());
+
+    let id = {
+        let mut registry = jobs.lock().unwrap();
+        registry.next_id += 1;
+        let id = registry.next_id;
+        registry.jobs.insert(
+            id,
+            Job {
+                status: JobStatus::Running,
+                monitor: monitor.clone(),
+                result: None,
+                error: None,
+                created: chrono::Utc::now(),
+            },
+        );
+        id
+    };
+
+    // Run the search on the blocking thread pool so it doesn't stall the server
+    let job_jobs = jobs.clone();
+    tokio::spawn(async move {
+        let search_monitor = monitor.clone();
+        let budget = limits.budget();
+        let outcome = limits
+            .run(permit, move || plan_paths(&data, &request, &search_monitor, &metrics, budget))
+            .await
+            .unwrap_or_else(|e| Err(search_failed(e)));
+        let mut registry = job_jobs.lock().unwrap();
+        if let Some(job) = registry.jobs.get_mut(&id) {
+            match outcome {
+                Ok(response) => {
+                    job.status = JobStatus::Completed;
+                    job.result = Some(response);
+                }
+                Err(error) => {
+                    job.status = if monitor.is_cancelled() {
+                        JobStatus::Cancelled
+                    } else {
+                        JobStatus::Failed
+                    };
+                    job.error = Some(error.to_response());
+                }
+            }
+        }
+    });
+
+    let response = JobCreatedResponse {
+        id,
+        status: JobStatus::Running,
+    };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&response),
+        StatusCode::ACCEPTED,
+    ))
+}
+
+// Handler reporting the status, progress, and result of a search job
+async fn handle_get_job(id: u64, jobs: Jobs) -> Result<impl warp::Reply, warp::Rejection> {
+    let registry = jobs.lock().unwrap();
+    let job = registry.jobs.get(&id).ok_or_else(|| {
+        warp::reject::custom(ApiError::not_found("Job not found", format!("Job {id} not found")))
+    })?;
+    let response = JobResponse {
+        id,
+        status: job.status,
+        created: job.created.to_rfc3339(),
+        progress: JobProgress {
+            nodes_explored: job.monitor.nodes_explored(),
+            paths_found: job.monitor.paths_found(),
+        },
+        result: job.result.clone(),
+        error: job.error.clone(),
+    };
+    Ok(warp::reply::json(&response))
+}
+
+// Handler cancelling a running search job
+async fn handle_cancel_job(id: u64, jobs: Jobs) -> Result<impl warp::Reply, warp::Rejection> {
+    let registry = jobs.lock().unwrap();
+    let job = registry.jobs.get(&id).ok_or_else(|| {
+        warp::reject::custom(ApiError::not_found("Job not found", format!("Job {id} not found")))
+    })?;
+
+    // The search notices the cancellation at its next node and then
+    // marks the job as cancelled
+    job.monitor.cancel();
+    let response = JobCreatedResponse {
+        id,
+        status: if job.status == JobStatus::Running {
+            JobStatus::Cancelled
+        } else {
+            job.status
+        },
+    };
+    Ok(warp::reply::json(&response))
+}
+
+// Handler reloading the data files and templates
+async fn handle_reload(state: State) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = state.reload().map_err(warp::reject::custom)?;
+    tracing::info!(
+        "Reloaded data and templates (version {}): {} buoys, {} legs",
+        response.version, response.buoys, response.legs
+    );
+    Ok(warp::reply::json(&response))
+}
+
+// Handler applying a change of the course, allowed like reloading
+async fn handle_course_update(
+    update: CourseUpdate,
+    state: State,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let response = state.update_course(update.clone()).map_err(warp::reject::custom)?;
+    tracing::info!(
+        "Updated the course (version {}): {update:?}, kept {} cached responses",
+        response.version, response.cached_responses
+    );
+    Ok(warp::reply::json(&response))
+}
+
+// Handler replacing hours of the wind forecast, allowed like reloading
+async fn handle_wind_override(
+    body: WindOverride,
+    state: State,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let conditions: Vec<WindCondition> = body
+        .wind
+        .into_iter()
+        .map(|hour| WindCondition {
+            time: hour.time,
+            wind_speed: hour.wind_speed,
+            wind_angle: hour.wind_angle,
+        })
+        .collect();
+    let response = state.override_wind(&conditions).map_err(warp::reject::custom)?;
+    tracing::info!(
+        "Changed {} hours of the wind forecast (version {})",
+        response.hours, response.version
+    );
+    Ok(warp::reply::json(&response))
+}
+
+// Race time of the current wall clock, through the race clock of the data
+fn current_race_time(state: &ServerState) -> Option<f64> {
+    state.snapshot().data.race_clock.race_time_of(&chrono::Local::now())
+}
+
+// Store a reading of the onboard instruments in the live state
+fn handle_instrument_reading(state: &ServerState, reading: InstrumentReading) {
+    let Some(time) = current_race_time(state) else {
+        tracing::warn!("Ignoring NMEA reading, the race clock doesn't cover the current time");
+        return;
+    };
+    match reading {
+        InstrumentReading::Position {
+            lat,
+            long,
+            speed,
+            course,
+        } => {
+            let point = TrackPoint {
+                lat,
+                long,
+                time,
+                speed,
+                course,
+                received: chrono::Utc::now(),
+            };
+            if let Err(e) = point.validate(&state.snapshot().data) {
+                tracing::warn!("Ignoring NMEA position: {e}");
+            } else if let Err(e) = state.track.add(point) {
+                tracing::warn!("Failed to add NMEA position to the track: {e}");
+            } else {
+                state.publish(ServerEvent::Position {
+                    boat: None,
+                    time,
+                    lat,
+                    long,
+                });
+            }
+        }
+        InstrumentReading::Wind { direction, speed } => {
+            *state.wind.lock().unwrap() = Some(WindObservation {
+                direction,
+                speed,
+                time,
+                received: chrono::Utc::now(),
+            });
+        }
+    }
+}
+
+// Check that a session refers to buoys and legs of the course
+fn validate_session(data: &RegattaData, input: &SessionInput) -> Result<(), ApiError> {
+    if input.name.trim().is_empty() || input.name.len() > 100 || input.name.chars().any(char::is_control) {
+        return Err(ApiError::bad_request(
+            "Invalid name",
+            "Session name must have between 1 and 100 characters and no control characters",
+        ));
+    }
+    if input.notes.len() > 10_000 {
+        return Err(ApiError::bad_request(
+            "Invalid notes",
+            "Notes must have at most 10000 characters",
+        ));
+    }
+    if let Some(start) = &input.start
+        && data.get_boei_index(start).is_none()
+    {
+        return Err(ApiError::not_found(
+            "Buoy not found",
+            format!("Starting buoy '{start}' not found"),
+        ));
+    }
+    if let Some(time) = input.start_time {
+        RaceHours::new(data, time).map_err(|e| ApiError::bad_request("Invalid time", e.to_string()))?;
+    }
+    for leg in input.pinned_legs.iter().chain(&input.completed_legs) {
+        let route = parse_route(data, &format!("{},{}", leg.from, leg.to))
+            .map_err(|message| ApiError::not_found("Buoy not found", message))?;
+        if evaluate_route(data, &route, 0.0).is_err() {
+            return Err(ApiError::unprocessable(
+                "Invalid leg",
+                format!("There is no leg from '{}' to '{}'", leg.from, leg.to),
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Error for a session store which failed to save the sessions file
+fn session_store_error(e: UursError) -> warp::Rejection {
+    warp::reject::custom(ApiError::internal(
+        "Saving sessions failed",
+        format!("Error writing the sessions file: {e}"),
+    ))
+}
+
+// Resolve the buoys of a saved plan and evaluate its route
+fn evaluate_plan(data: &RegattaData, route: &[String], start_time: f64) -> Result<Path, ApiError> {
+    let route = route
+        .iter()
+        .map(|name| {
+            data.get_boei_index(name)
+                .ok_or_else(|| ApiError::not_found("Buoy not found", format!("Buoy '{name}' not found")))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    evaluate_route(data, &route, start_time).map_err(ApiError::from)
+}
+
+// Handler saving a plan under a short id, evaluated first so that only
+// routes which can be sailed are shared
+async fn handle_save_plan(
+    body: PlanSave,
+    boat: Option<Arc<Boat>>,
+    state: State,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let data = state.boat_snapshot(boat.as_deref()).data;
+    let start_time = match &body.time {
+        Some(time) => race_hours(&data, time).map_err(warp::reject::custom)?,
+        None => 0.0,
+    };
+    let path = evaluate_plan(&data, &body.route, start_time).map_err(warp::reject::custom)?;
+
+    let input = PlanInput {
+        name: body.name.unwrap_or_else(|| body.route.join(" - ")),
+        route: body.route,
+        start_time,
+        boat: boat.map(|boat| boat.name.clone()),
+        notes: body.notes.unwrap_or_default(),
+    };
+    let plan = state.plans.save(input).map_err(|e| {
+        warp::reject::custom(ApiError::internal(
+            "Saving the plan failed",
+            format!("Error writing the plans file: {e}"),
+        ))
+    })?;
+    let response = SavedPlanResponse {
+        url: format!("{}/plans/{}", state.base_path, plan.id),
+        path: PathResponse::new(&data, &path),
+        plan,
+    };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&response),
+        StatusCode::CREATED,
+    ))
+}
+
+// Handler for the read-only page of a shared plan. The route is evaluated
+// anew, so the page follows reloads and changes of the wind, with the
+// polars of its boat as long as the boat is registered.
+async fn handle_shared_plan_page(
+    id: String,
+    tera: Arc<Tera>,
+    state: State,
+    lang: Lang,
+    theme: Theme,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let Some(plan) = state.plans.get(&id) else {
+        return Err(warp::reject::custom(ApiError::not_found(
+            "Plan not found",
+            format!("There is no plan '{id}'"),
+        )));
+    };
+    let boat = plan.input.boat.as_deref().and_then(|name| state.fleet.get(name));
+    let data = state.boat_snapshot(boat.as_deref()).data;
+
+    let mut context = page_context(lang, theme);
+    context.insert("plan", &plan);
+    context.insert("start_clock", &data.race_clock.format_clock(plan.input.start_time));
+    // A plan whose buoys or legs are gone after a reload still shows its route
+    match evaluate_plan(&data, &plan.input.route, plan.input.start_time) {
+        Ok(path) => {
+            context.insert("path", &PathResponse::new(&data, &path));
+            match create_route_map(&data, &path, themed(theme)) {
+                Ok(map) => context.insert("map", &map),
+                Err(e) => tracing::warn!("Plan {id}: error generating route map: {e}"),
+            }
+        }
+        Err(e) => context.insert("error", &e.message),
+    }
+
+    let rendered_html = render_template(&tera, "plan.html", &context)?;
+
+    Ok(html(rendered_html))
+}
+
+// Handler for creating a planning session
+async fn handle_create_session(
+    input: SessionInput,
+    data: Arc<RegattaData>,
+    sessions: Arc<SessionStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    validate_session(&data, &input).map_err(warp::reject::custom)?;
+    let session = sessions.create(input).map_err(session_store_error)?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&session),
+        StatusCode::CREATED,
+    ))
+}
+
+// Handler for listing the planning sessions
+async fn handle_list_sessions(
+    sessions: Arc<SessionStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&sessions.list()))
+}
+
+// Handler for getting a planning session
+async fn handle_get_session(
+    id: u64,
+    sessions: Arc<SessionStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let session = sessions.get(id).ok_or_else(|| {
+        warp::reject::custom(ApiError::not_found(
+            "Session not found",
+            format!("Session {id} not found"),
+        ))
+    })?;
+    Ok(warp::reply::json(&session))
+}
+
+// Handler for replacing the plan of a planning session
+async fn handle_update_session(
+    id: u64,
+    input: SessionInput,
+    data: Arc<RegattaData>,
+    sessions: Arc<SessionStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    validate_session(&data, &input).map_err(warp::reject::custom)?;
+    let session = sessions
+        .update(id, input)
+        .map_err(session_store_error)?
+        .ok_or_else(|| {
+            warp::reject::custom(ApiError::not_found(
+                "Session not found",
+                format!("Session {id} not found"),
+            ))
+        })?;
+    Ok(warp::reply::json(&session))
+}
+
+// Handler for simulating a route in fixed time steps, answering with the
+// track as JSON or as GeoJSON
+async fn handle_simulate(
+    body: SimulateRequest,
+    data: Arc<RegattaData>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let geojson = match body.format.as_deref() {
+        None | Some("json") => false,
+        Some("geojson") => true,
+        Some(other) => {
+            return Err(warp::reject::custom(ApiError::bad_request(
+                "Invalid format",
+                format!("Unknown format '{other}', use json or geojson"),
+            )));
+        }
+    };
+    let step_minutes = body.step_minutes.unwrap_or(1.0);
+    if !(0.1..=60.0).contains(&step_minutes) {
+        return Err(warp::reject::custom(ApiError::bad_request(
+            "Invalid time step",
+            "The time step must be between 0.1 and 60 minutes",
+        )));
+    }
+    let rounding_penalty = body.rounding_penalty.unwrap_or(0.0);
+    if rounding_penalty < 0.0 {
+        return Err(warp::reject::custom(ApiError::bad_request(
+            "Invalid rounding penalty",
+            "The rounding penalty can't be negative",
+        )));
+    }
+    let route = parse_route(&data, &body.route.join(","))
+        .map_err(|message| warp::reject::custom(ApiError::not_found("Buoy not found", message)))?;
+    let start_time = match &body.time {
+        Some(time) => race_hours(&data, time).map_err(warp::reject::custom)?,
+        None => 0.0,
+    };
+    validate_route(&data, &route, &SearchConstraints::default())
+        .map_err(|e| warp::reject::custom(ApiError::from(e)))?;
+
+    let options = SimulationOptions {
+        time_step: step_minutes / 60.0,
+        current: body.current,
+        rounding_penalty: rounding_penalty / 60.0,
+        ..SimulationOptions::default()
+    };
+    let simulation = simulate_route(&data, &route, start_time, &options)
+        .map_err(|e| warp::reject::custom(ApiError::from(e)))?;
+    if geojson {
+        let geojson = simulation_to_geojson(&data, &simulation, &body.route.join(" - "));
+        return Ok(warp::reply::with_header(geojson.to_string(), "Content-Type", "application/geo+json").into_response());
+    }
+    Ok(warp::reply::json(&SimulationResponse::new(&data, simulation)).into_response())
+}
+
+// Handler for the route validation endpoint
+async fn handle_validate_route(
+    body: ValidateRouteRequest,
+    data: Arc<RegattaData>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let route = parse_route(&data, &body.route.join(","))
+        .map_err(|message| warp::reject::custom(ApiError::not_found("Buoy not found", message)))?;
+    let start_time = match &body.time {
+        Some(time) => resolve_time(&data, time).map_err(warp::reject::custom)?,
+        None => 0.0,
+    };
+    let passing_times = body
+        .passing_times
+        .iter()
+        .flatten()
+        .map(|time| resolve_time(&data, time))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(warp::reject::custom)?;
+    let path = route_path(&data, &route, start_time, body.passing_times.as_ref().map(|_| passing_times.as_slice()))
+        .map_err(|e| warp::reject::custom(ApiError::from(e)))?;
+
+    let violations = check_route(&data, &path);
+    Ok(warp::reply::json(&ValidateRouteResponse {
+        valid: violations.is_empty(),
+        edition: data.rules.edition,
+        path: PathResponse::new(&data, &path),
+        violations,
+    }))
+}
+
+fn training_not_found(id: u64) -> warp::Rejection {
+    warp::reject::custom(ApiError::not_found(
+        "Game not found",
+        format!("Training game {id} not found"),
+    ))
+}
+
+// Handler for starting a training game
+async fn handle_start_training(
+    body: TrainingStart,
+    data: Arc<RegattaData>,
+    training: Arc<TrainingGames>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let time = match &body.time {
+        Some(time) => resolve_time(&data, time).map_err(warp::reject::custom)?,
+        None => 0.0,
+    };
+    let game = training
+        .start(&data, &body.start, time)
+        .map_err(|e| warp::reject::custom(ApiError::from(e)))?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&TrainingResponse::new(&data, game, None)),
+        StatusCode::CREATED,
+    ))
+}
+
+// Handler for getting a training game
+async fn handle_get_training(
+    id: u64,
+    data: Arc<RegattaData>,
+    training: Arc<TrainingGames>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let game = training.get(id).ok_or_else(|| training_not_found(id))?;
+    Ok(warp::reply::json(&TrainingResponse::new(&data, game, None)))
+}
+
+// Handler for sailing the next leg of a training game. The leg is simulated
+// minute by minute, the page plays the samples back in accelerated time.
+async fn handle_sail_training(
+    id: u64,
+    body: TrainingMove,
+    data: Arc<RegattaData>,
+    training: Arc<TrainingGames>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (game, simulation) = training
+        .sail(id, &data, &body.to, &SimulationOptions::default())
+        .map_err(|e| warp::reject::custom(ApiError::from(e)))?
+        .ok_or_else(|| training_not_found(id))?;
+    Ok(warp::reply::json(&TrainingResponse::new(&data, game, Some(simulation.samples))))
+}
+
+fn exploration_not_found(id: u64) -> warp::Rejection {
+    warp::reject::custom(ApiError::not_found(
+        "Exploration not found",
+        format!("Exploration {id} not found"),
+    ))
+}
+
+// Handler for starting an exploration
+async fn handle_start_exploration(
+    body: ExplorationStart,
+    data: Arc<RegattaData>,
+    explorations: Arc<Explorations>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let time = match &body.time {
+        Some(time) => resolve_time(&data, time).map_err(warp::reject::custom)?,
+        None => 0.0,
+    };
+    let exploration = explorations
+        .start(&data, &body.start, time)
+        .map_err(|e| warp::reject::custom(ApiError::from(e)))?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&ExplorationResponse::new(&data, exploration)),
+        StatusCode::CREATED,
+    ))
+}
+
+// Handler for getting an exploration. The options are estimated anew, so
+// they follow reloads and course updates.
+async fn handle_get_exploration(
+    id: u64,
+    data: Arc<RegattaData>,
+    explorations: Arc<Explorations>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let exploration = explorations.get(id).ok_or_else(|| exploration_not_found(id))?;
+    Ok(warp::reply::json(&ExplorationResponse::new(&data, exploration)))
+}
+
+// Handler for adding the next leg to an exploration
+async fn handle_extend_exploration(
+    id: u64,
+    body: ExplorationLeg,
+    data: Arc<RegattaData>,
+    explorations: Arc<Explorations>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let exploration = explorations
+        .extend(id, &data, &body.to)
+        .map_err(|e| warp::reject::custom(ApiError::from(e)))?
+        .ok_or_else(|| exploration_not_found(id))?;
+    Ok(warp::reply::json(&ExplorationResponse::new(&data, exploration)))
+}
+
+// Handler for taking back the last leg of an exploration
+async fn handle_retract_exploration(
+    id: u64,
+    data: Arc<RegattaData>,
+    explorations: Arc<Explorations>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let exploration = explorations
+        .retract(id)
+        .map_err(|e| warp::reject::custom(ApiError::from(e)))?
+        .ok_or_else(|| exploration_not_found(id))?;
+    Ok(warp::reply::json(&ExplorationResponse::new(&data, exploration)))
+}
+
+// Summary of a boat with its latest position
+fn boat_response(boat: &Boat) -> BoatResponse {
+    let points = boat.track.points_since(0.0);
+    BoatResponse {
+        name: boat.name.clone(),
+        custom_polars: boat.polar.is_some(),
+        class: boat.class.clone(),
+        session: boat.session,
+        registered: boat.registered.to_rfc3339(),
+        points: points.len(),
+        distance_sailed: track_distance(&points),
+        position: points.last().cloned(),
+    }
+}
+
+// Handler for registering a boat, registering it again replaces its polars,
+// class, and session but keeps its track
+async fn handle_register_boat(
+    registration: BoatRegistration,
+    data: Arc<RegattaData>,
+    fleet: Arc<Fleet>,
+    sessions: Arc<SessionStore>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    validate_boat_name(&registration.name)
+        .map_err(|e| warp::reject::custom(ApiError::bad_request("Invalid boat name", e)))?;
+    let polar = match &registration.polars {
+        Some(polars) => {
+            let polar =
+                parse_polar_data(polars).map_err(|e| warp::reject::custom(ApiError::from(e)))?;
+            if polar.wind_angles.len() < 2 || polar.wind_speeds.is_empty() {
+                return Err(warp::reject::custom(ApiError::bad_request(
+                    "Invalid polars",
+                    "The polars need at least one wind speed and two wind angles",
+                )));
+            }
+            Some(polar)
+        }
+        None => None,
+    };
+    if let Some(class) = &registration.class
+        && data.start_offset(class).is_none()
+    {
+        return Err(warp::reject::custom(ApiError::bad_request(
+            "Invalid class",
+            format!("Unknown class '{class}', see data/classes.csv"),
+        )));
+    }
+    if let Some(id) = registration.session
+        && sessions.get(id).is_none()
+    {
+        return Err(warp::reject::custom(ApiError::not_found(
+            "Session not found",
+            format!("Session {id} not found"),
+        )));
+    }
+
+    let boat = fleet
+        .register(&registration.name, polar, registration.class, registration.session)
+        .map_err(|e| {
+            warp::reject::custom(ApiError::internal(
+                "Registration failed",
+                format!("Error opening the track log of the boat: {e}"),
+            ))
+        })?;
+    Ok(warp::reply::with_status(
+        warp::reply::json(&boat_response(&boat)),
+        StatusCode::CREATED,
+    ))
+}
+
+// Handler for listing the boats of the fleet
+async fn handle_list_boats(fleet: Arc<Fleet>) -> Result<impl warp::Reply, warp::Rejection> {
+    let boats: Vec<BoatResponse> = fleet.list().iter().map(|boat| boat_response(boat)).collect();
+    Ok(warp::reply::json(&boats))
+}
+
+// Handler for the leaderboard endpoint
+async fn handle_leaderboard(data: Arc<RegattaData>, fleet: Arc<Fleet>) -> Result<impl warp::Reply, warp::Rejection> {
+    let tracks: Vec<_> = fleet
+        .list()
+        .iter()
+        .map(|boat| (boat.name.clone(), boat.track.points_since(0.0)))
+        .collect();
+    Ok(warp::reply::json(&LeaderboardResponse::new(&data, leaderboard(&data, &tracks))))
+}
+
+// Handler for removing a boat from the fleet
+async fn handle_remove_boat(
+    name: String,
+    fleet: Arc<Fleet>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let mut context = Context::new();
-
-    // Get legs sorted alphabetically by from, then to
-    let mut legs = data.rakken.clone();
-    legs.sort_by(|a, b| a.from.cmp(&b.from).then_with(|| a.to.cmp(&b.to)));
+    let boat = fleet.remove(&name).ok_or_else(|| {
+        warp::reject::custom(ApiError::not_found(
+            "Boat not found",
+            format!("Boat '{name}' is not registered"),
+        ))
+    })?;
+    Ok(warp::reply::json(&boat_response(&boat)))
+}
 
-    // Convert Rak structs to a format that Tera can understand
-    let legs_for_template: Vec<serde_json::Value> = legs
+// Handler for the course map with the tracks of all boats
+async fn handle_fleet_svg(
+    data: Arc<RegattaData>,
+    fleet: Arc<Fleet>,
+    theme: Theme,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let boats: Vec<BoatTrack> = fleet
+        .list()
         .iter()
-        .map(|rak| {
-            json!({
-                "from": rak.from,
-                "to": rak.to,
-                "distance": rak.distance
-            })
+        .map(|boat| BoatTrack {
+            name: boat.name.clone(),
+            positions: boat
+                .track
+                .points_since(0.0)
+                .iter()
+                .map(|point| (point.lat, point.long))
+                .collect(),
         })
         .collect();
 
-    context.insert("legs", &legs_for_template);
-
-    let rendered_html = tera.render("estimate-leg.html", &context).map_err(|e| {
-        eprintln!("Template rendering error: {e:?}");
-        warp::reject::custom(TemplateError)
-    })?;
+    match create_fleet_plot(&data, &boats, themed(theme)) {
+        Ok(svg_content) => Ok(Box::new(warp::reply::with_header(
+            svg_content,
+            "Content-Type",
+            "image/svg+xml",
+        ))),
+        Err(e) => Err(warp::reject::custom(ApiError::internal(
+            "Chart generation failed",
+            format!("Error generating fleet map: {e}"),
+        ))),
+    }
+}
 
-    Ok(html(rendered_html))
+// Handler for the course map of the path explorer, drawn from the data
+// served, so it shows moved buoys at once
+async fn handle_course_svg(data: Arc<RegattaData>, theme: Theme) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    match create_regatta_plot(&data, themed(theme)) {
+        Ok(svg_content) => Ok(Box::new(warp::reply::with_header(
+            svg_content,
+            "Content-Type",
+            "image/svg+xml",
+        ))),
+        Err(e) => Err(warp::reject::custom(ApiError::internal(
+            "Chart generation failed",
+            format!("Error generating course map: {e}"),
+        ))),
+    }
 }
 
-// Handler for the find paths form page
-async fn handle_find_paths_form(
-    tera: Arc<Tera>,
-    data: RegattaData,
+// Handler for position reports
+async fn handle_position(
+    report: PositionReport,
+    boat: Option<Arc<Boat>>,
+    state: State,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    let mut context = Context::new();
+    let point = TrackPoint {
+        lat: report.lat,
+        long: report.long,
+        time: report.time,
+        speed: report.speed,
+        course: report.course,
+        received: chrono::Utc::now(),
+    };
+    point
+        .validate(&state.snapshot().data)
+        .map_err(|message| warp::reject::custom(ApiError::bad_request("Invalid position", message)))?;
 
-    // Get boeien names for the dropdown
-    let boeien: Vec<String> = data.boeien.iter().map(|boei| boei.name.clone()).collect();
+    let event = ServerEvent::Position {
+        boat: boat.as_ref().map(|boat| boat.name.clone()),
+        time: point.time,
+        lat: point.lat,
+        long: point.long,
+    };
+    let track = boat.as_ref().map_or(&state.track, |boat| &boat.track);
+    let points = track.add(point).map_err(|e| {
+        warp::reject::custom(ApiError::internal(
+            "Track log failed",
+            format!("Error writing track log: {e}"),
+        ))
+    })?;
+    state.publish(event);
+    Ok(warp::reply::with_status(
+        warp::reply::json(&PositionResponse { points }),
+        StatusCode::CREATED,
+    ))
+}
 
-    context.insert("boeien", &boeien);
+// Handler for the observation endpoint
+async fn handle_observation(
+    report: ObservationReport,
+    state: State,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let observation = LegObservation {
+        from: report.from,
+        to: report.to,
+        time: report.time,
+        speed: report.speed,
+        wind_direction: report.wind_direction,
+        wind_speed: report.wind_speed,
+        boat: report.boat,
+        received: chrono::Utc::now(),
+    };
+    observation
+        .validate(&state.snapshot().data)
+        .map_err(|message| warp::reject::custom(ApiError::bad_request("Invalid observation", message)))?;
 
-    let rendered_html = tera.render("find-paths.html", &context).map_err(|e| {
-        eprintln!("Template rendering error: {e}");
-        warp::reject::custom(TemplateError)
+    let observations = state.observations.add(observation).map_err(|e| {
+        warp::reject::custom(ApiError::internal(
+            "Observations log failed",
+            format!("Error writing observations log: {e}"),
+        ))
     })?;
-
-    Ok(html(rendered_html))
+    Ok(warp::reply::with_status(
+        warp::reply::json(&ObservationResponse { observations }),
+        StatusCode::CREATED,
+    ))
 }
 
-// Handler for the find target form page
-async fn handle_find_target_form(
-    tera: Arc<Tera>,
-    data: RegattaData,
-) -> Result<impl warp::Reply, warp::Rejection> {
-    let mut context = Context::new();
+// Handler for listing the observations
+async fn handle_list_observations(state: State) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&state.observations.all()))
+}
 
-    // Get boeien names for the dropdown
-    let boeien: Vec<String> = data.boeien.iter().map(|boei| boei.name.clone()).collect();
+// Handler for the calibration fitted to the current observations
+async fn handle_calibration(state: State) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(warp::reply::json(&calibration_response(&state)))
+}
 
-    context.insert("boeien", &boeien);
+// Handler for fitting and applying the calibration, allowed like reloading
+async fn handle_calibrate(state: State) -> Result<impl warp::Reply, warp::Rejection> {
+    let version = state.recalibrate();
+    let response = calibration_response(&state);
+    tracing::info!(
+        "Calibrated {} legs from {} observations (version {version})",
+        response.legs.iter().filter(|leg| leg.factor.is_some()).count(),
+        response.observations
+    );
+    Ok(warp::reply::json(&response))
+}
 
-    let rendered_html = tera.render("find-target.html", &context).map_err(|e| {
-        eprintln!("Template rendering error: {e}");
-        warp::reject::custom(TemplateError)
-    })?;
+// Fitted and applied correction factors of all observed legs
+fn calibration_response(state: &ServerState) -> CalibrationResponse {
+    let snapshot = state.snapshot();
+    let data = &snapshot.data;
+    let observations = state.observations.all();
+    let legs = calibrate(data, &observations)
+        .into_iter()
+        .map(|leg| LegCalibrationResponse {
+            from: data.boeien[leg.from].name.clone(),
+            to: data.boeien[leg.to].name.clone(),
+            observations: leg.observations,
+            factor: leg.factor,
+            applied: data.leg_correction(leg.from, leg.to),
+        })
+        .collect();
+    CalibrationResponse {
+        version: snapshot.version,
+        observations: observations.len(),
+        min_observations: MIN_OBSERVATIONS,
+        legs,
+    }
+}
 
-    Ok(html(rendered_html))
+// Handler for the track endpoint
+async fn handle_track(
+    query: TrackQuery,
+    track: Arc<Track>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let points = track.points_since(query.since.unwrap_or(0.0));
+    let response = TrackResponse {
+        distance: track_distance(&points),
+        points,
+    };
+    Ok(warp::reply::json(&response))
 }
 
-// Handler for the estimate endpoint
-async fn handle_estimate(
-    query: EstimateQuery,
-    data: RegattaData,
+// Handler for the dashboard endpoint: where the boat is on its leg, when it
+// reaches the next buoy, and how to continue from there
+async fn handle_dashboard(
+    query: DashboardQuery,
+    snapshot: Snapshot,
+    track: Arc<Track>,
+    measured_wind: Arc<Mutex<Option<WindObservation>>>,
+    metrics: Arc<Metrics>,
+    remote: Option<SocketAddr>,
+    limits: Arc<SearchLimits>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    // Get boei indices by name
-    let from_idx = match data.get_boei_index(&query.from) {
-        Some(idx) => idx,
-        None => {
-            let error_response = json!({
-                "error": "Boei not found",
-                "message": format!("Boei '{}' not found", query.from)
-            });
-            return Ok(warp::reply::json(&error_response));
-        }
+    let data = snapshot.data;
+    let points = track.points_since(0.0);
+    let Some(position) = points.last().cloned() else {
+        return Err(warp::reject::custom(ApiError::not_found(
+            "No position",
+            "No position has been reported yet, see POST /api/position",
+        )));
     };
 
-    let to_idx = match data.get_boei_index(&query.to) {
-        Some(idx) => idx,
-        None => {
-            let error_response = json!({
-                "error": "Boei not found",
-                "message": format!("Boei '{}' not found", query.to)
-            });
-            return Ok(warp::reply::json(&error_response));
+    // Use the leg given by the client or guess it from the track
+    let (from, to, inferred) = match (&query.from, &query.to) {
+        (Some(from), Some(to)) => {
+            let from_idx = data.get_boei_index(from).ok_or_else(|| {
+                warp::reject::custom(ApiError::not_found(
+                    "Buoy not found",
+                    format!("Buoy '{from}' not found"),
+                ))
+            })?;
+            let to_idx = data.get_boei_index(to).ok_or_else(|| {
+                warp::reject::custom(ApiError::not_found(
+                    "Buoy not found",
+                    format!("Buoy '{to}' not found"),
+                ))
+            })?;
+            (from_idx, to_idx, false)
+        }
+        (None, None) => match locate_leg(&data, &points) {
+            Some((from_idx, to_idx)) => (from_idx, to_idx, true),
+            None => {
+                return Err(warp::reject::custom(ApiError::unprocessable(
+                    "No leg found",
+                    "No leg with known coordinates is near the reported position",
+                )));
+            }
+        },
+        _ => {
+            return Err(warp::reject::custom(ApiError::bad_request(
+                "Invalid leg",
+                "Give both from and to, or neither to guess the leg from the track",
+            )));
         }
     };
 
-    // Validate time parameter
-    if query.time < 0.0 {
-        let error_response = json!({
-            "error": "Invalid time",
-            "message": "Time must be non-negative"
-        });
-        return Ok(warp::reply::json(&error_response));
-    }
-
-    // Estimate leg performance
-    let performance = estimate_leg_performance(&data, from_idx, to_idx, query.time);
+    // Validate steps parameter
+    let steps = step_count(&data, query.steps.unwrap_or(3)).map_err(warp::reject::custom)?;
 
-    // Return the result as JSON
-    let response = json!({
-        "from": query.from,
-        "to": query.to,
-        "time": query.time,
-        "estimated_speed": performance.estimated_speed,
-        "course_bearing": performance.course_bearing,
-        "wind_direction": performance.wind_direction,
-        "relative_bearing": performance.relative_bearing,
-        "wind_speed": performance.wind_speed
-    });
+    let objective =
+        plan_objective(query.objective.as_deref().unwrap_or("max_distance")).map_err(warp::reject::custom)?;
 
-    Ok(warp::reply::json(&response))
-}
+    let constraints =
+        avoid_constraints(&data, &query.avoid, &query.avoid_leg).map_err(warp::reject::custom)?;
 
-// Handler for the estimate leg endpoint
-async fn handle_estimate_leg(
-    query: EstimateLegQuery,
-    data: RegattaData,
-) -> Result<impl warp::Reply, warp::Rejection> {
-    // Handle reverse direction by swapping from and to
-    let (from_name, to_name) = if query.reverse.unwrap_or(false) {
-        (query.to.clone(), query.from.clone())
-    } else {
-        (query.from.clone(), query.to.clone())
-    };
+    let progress = leg_progress(&data, &position, from, to)
+        .map_err(|message| warp::reject::custom(ApiError::unprocessable("Invalid leg", message)))?;
+    let wind = estimate_leg_performance(&data, from, to, position.time)
+        .map_err(|e| warp::reject::custom(ApiError::from(e)))?;
 
-    // Get boei indices by name
-    let from_idx = match data.get_boei_index(&from_name) {
-        Some(idx) => idx,
-        None => {
-            let error_response = json!({
-                "error": "Boei not found",
-                "message": format!("Boei '{}' not found", from_name)
-            });
-            return Ok(warp::reply::json(&error_response));
+    // Replan from the next buoy once it is reached within the race
+    let continuation = match progress.eta.filter(|eta| *eta < data.rules.scoring.deadline) {
+        Some(eta) => {
+            let permit =
+                admit_search(&limits, remote, &data, steps).map_err(warp::reject::custom)?;
+            let search_data = data.clone();
+            let budget = limits.budget();
+            let result = limits
+                .run(permit, move || {
+                    let monitor = SearchMonitor::with_objective(objective);
+                    let options = SearchOptions {
+                        constraints,
+                        monitor: Some(&monitor),
+                        budget,
+                        ..Default::default()
+                    };
+                    monitored_search(&metrics, &monitor, || {
+                        best_continuation(&search_data, to, eta, steps, &objective, &options)
+                    })
+                })
+                .await
+                .map_err(|e| warp::reject::custom(search_failed(e)))?;
+            result.map_err(|e| warp::reject::custom(ApiError::from(e)))?
         }
+        None => None,
     };
 
-    let to_idx = match data.get_boei_index(&to_name) {
-        Some(idx) => idx,
-        None => {
-            let error_response = json!({
-                "error": "Boei not found",
-                "message": format!("Boei '{}' not found", to_name)
-            });
-            return Ok(warp::reply::json(&error_response));
-        }
+    let response = DashboardResponse {
+        time: position.time,
+        clock: data.race_clock.format_clock(position.time),
+        wind_direction: wind.wind_direction,
+        wind_speed: wind.wind_speed,
+        measured_wind: measured_wind.lock().unwrap().clone(),
+        distance_sailed: track_distance(&points),
+        leg: ActiveLegResponse {
+            from: data.boeien[from].name.clone(),
+            to: data.boeien[to].name.clone(),
+            inferred,
+            distance_to_go: progress.distance_to_go,
+            course: progress.course,
+            speed: progress.speed,
+            eta: progress.eta,
+            eta_clock: progress.eta.and_then(|eta| data.race_clock.format_clock(eta)),
+        },
+        objective: objective.name(),
+        continuation: continuation.map(|path| PathResponse::new(&data, &path)),
+        position,
     };
+    Ok(warp::reply::json(&response))
+}
 
-    // Validate time parameter
-    if query.time < 0.0 {
-        let error_response = json!({
-            "error": "Invalid time",
-            "message": "Time must be non-negative"
-        });
-        return Ok(warp::reply::json(&error_response));
+// The route of the plan of a session: the completed legs followed by the
+// pinned legs, which must join up, sailed from the start time of the session
+fn session_plan(data: &RegattaData, session: &Session) -> Result<Path, ApiError> {
+    let input = &session.input;
+    let legs: Vec<_> = input.completed_legs.iter().chain(&input.pinned_legs).collect();
+    let Some(first) = legs.first() else {
+        return Err(ApiError::unprocessable(
+            "No plan",
+            format!("Session {} has no completed or pinned legs", session.id),
+        ));
+    };
+    let start = input.start.as_ref().unwrap_or(&first.from);
+    let mut route = vec![start.clone()];
+    for leg in &legs {
+        if leg.from != route[route.len() - 1] {
+            return Err(ApiError::unprocessable(
+                "Invalid plan",
+                format!(
+                    "The legs of session {} don't join up: '{}' follows '{}'",
+                    session.id,
+                    leg.from,
+                    route[route.len() - 1]
+                ),
+            ));
+        }
+        route.push(leg.to.clone());
     }
 
-    // Estimate leg performance
-    let performance = estimate_leg_performance(&data, from_idx, to_idx, query.time);
+    let route = parse_route(data, &route.join(","))
+        .map_err(|message| ApiError::not_found("Buoy not found", message))?;
+    evaluate_route(data, &route, input.start_time.unwrap_or(0.0)).map_err(ApiError::from)
+}
 
-    // Return the result as JSON
-    let response = json!({
-        "from": from_name,
-        "to": to_name,
-        "time": query.time,
-        "estimated_speed": performance.estimated_speed,
-        "course_bearing": performance.course_bearing,
-        "wind_direction": performance.wind_direction,
-        "relative_bearing": performance.relative_bearing,
-        "wind_speed": performance.wind_speed
-    });
+// Compare a track with the plan of a session
+fn session_progress(
+    data: &RegattaData,
+    session: &Session,
+    track: &Track,
+) -> Result<ProgressResponse, ApiError> {
+    let plan = session_plan(data, session)?;
+    let points = track.points_since(0.0);
+    let progress = plan_progress(data, &plan, &points)
+        .map_err(|message| ApiError::unprocessable("Invalid plan", message))?
+        .ok_or_else(|| {
+            ApiError::not_found(
+                "No position",
+                "No position has been reported yet, see POST /api/position",
+            )
+        })?;
 
-    Ok(warp::reply::json(&response))
+    let time = points[points.len() - 1].time;
+    let step = &plan.steps[progress.step];
+    Ok(ProgressResponse {
+        session: session.id,
+        time,
+        clock: data.race_clock.format_clock(time),
+        from: data.boeien[step.from].name.clone(),
+        to: data.boeien[step.to].name.clone(),
+        distance_along: progress.distance_along,
+        total_distance: plan.total_distance,
+        time_behind: progress.time_behind,
+        planned_finish: plan.end_time,
+        projected_finish: progress.projected_finish,
+        projected_clock: progress
+            .projected_finish
+            .and_then(|finish| data.race_clock.format_clock(finish)),
+        late: progress.projected_finish.is_none_or(|finish| finish > data.rules.scoring.deadline),
+        marks: progress
+            .marks
+            .iter()
+            .map(|mark| MarkResponse {
+                buoy: data.boeien[mark.buoy].name.clone(),
+                planned: mark.planned,
+                actual: mark.actual,
+                delta: mark.actual.map(|actual| actual - mark.planned),
+            })
+            .collect(),
+    })
 }
 
-// Handler for the find paths endpoint
-async fn handle_find_paths(
-    query: FindPathsQuery,
-    data: RegattaData,
+// Handler for the progress endpoint: the plan is the given session, or the
+// session of the selected boat, or the one of the server's own boat
+async fn handle_progress(
+    query: ProgressQuery,
+    snapshot: Snapshot,
+    track: Arc<Track>,
+    boat: Option<Arc<Boat>>,
+    state: State,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    // Get starting buoy index by name
-    let start_idx = match data.get_boei_index(&query.start) {
-        Some(idx) => idx,
-        None => {
-            let error_response = json!({
-                "error": "Buoy not found",
-                "message": format!("Starting buoy '{}' not found", query.start)
-            });
-            return Ok(warp::reply::json(&error_response));
-        }
+    let id = match &boat {
+        Some(boat) => query.session.or(boat.session),
+        None => query.session.or(state.plan_session),
     };
+    let Some(id) = id else {
+        return Err(warp::reject::custom(ApiError::bad_request(
+            "No plan",
+            "Give the planning session with the plan as session=ID",
+        )));
+    };
+    let session = state.sessions.get(id).ok_or_else(|| {
+        warp::reject::custom(ApiError::not_found(
+            "Session not found",
+            format!("Session {id} not found"),
+        ))
+    })?;
 
-    // Validate time parameter
-    if query.time < 0.0 || query.time > 24.0 {
-        let error_response = json!({
-            "error": "Invalid time",
-            "message": "Time must be between 0 and 24 hours"
-        });
-        return Ok(warp::reply::json(&error_response));
-    }
+    let response =
+        session_progress(&snapshot.data, &session, &track).map_err(warp::reject::custom)?;
+    Ok(warp::reply::json(&response))
+}
 
-    // Validate steps parameter
-    if query.steps == 0 || query.steps > 10 {
-        let error_response = json!({
-            "error": "Invalid steps",
-            "message": "Number of steps must be between 1 and 10"
-        });
-        return Ok(warp::reply::json(&error_response));
-    }
+// Where alerts go besides the log, and when boats raise them
+#[derive(Debug, Clone, Default)]
+struct Alerts {
+    webhook: Option<Webhook>,
+    mqtt: Option<MqttTopic>,
+    margin: f64, // hours to spare at the projected finish
+    position_timeout: Option<Duration>,
+}
 
-    // Validate max_paths parameter
-    let max_paths = query.max_paths;
-    if let Some(max_paths_val) = max_paths {
-        if max_paths_val == 0 || max_paths_val > 100000 {
-            let error_response = json!({
-                "error": "Invalid max_paths",
-                "message": "Maximum number of paths must be between 1 and 100000"
-            });
-            return Ok(warp::reply::json(&error_response));
-        }
+impl Alerts {
+    fn enabled(&self) -> bool {
+        self.webhook.is_some() || self.mqtt.is_some()
     }
 
-    // Explore paths
-    match explore_paths(&data, start_idx, query.time, query.steps, query.max_paths) {
-        Ok(paths) => {
-            // Convert paths to JSON-friendly format
-            let paths_json: Vec<serde_json::Value> = paths
-                .iter()
-                .map(|path| {
-                    let steps_json: Vec<serde_json::Value> = path
-                        .steps
-                        .iter()
-                        .map(|step| {
-                            json!({
-                                "from": step.from,
-                                "to": step.to,
-                                "from_name": data.boeien[step.from].name,
-                                "to_name": data.boeien[step.to].name,
-                                "distance": step.distance,
-                                "speed": step.speed,
-                                "start_time": step.start_time,
-                                "end_time": step.end_time
-                            })
-                        })
-                        .collect();
-
-                    json!({
-                        "steps": steps_json,
-                        "total_distance": path.total_distance,
-                        "end_time": path.end_time
-                    })
-                })
-                .collect();
-
-            let response = json!({
-                "start": query.start,
-                "start_time": query.time,
-                "steps": query.steps,
-                "paths": paths_json
-            });
-
-            Ok(warp::reply::json(&response))
+    // Deliver an alert to the webhook and the MQTT topic, a failure of one
+    // doesn't keep it from the other
+    async fn send(&self, alert: serde_json::Value) {
+        if let Some(webhook) = &self.webhook
+            && let Err(e) = webhook.post(&alert).await
+        {
+            tracing::warn!(alert = %alert["alert"], "alert webhook failed: {e}");
         }
-        Err(e) => {
-            let error_response = json!({
-                "error": "Path exploration failed",
-                "message": format!("Error exploring paths: {}", e)
-            });
-            Ok(warp::reply::json(&error_response))
+        if let Some(mqtt) = &self.mqtt
+            && let Err(e) = mqtt.publish(&alert).await
+        {
+            tracing::warn!(alert = %alert["alert"], "alert MQTT topic failed: {e}");
         }
     }
 }
 
-// Handler for the find target endpoint
-async fn handle_find_target(
-    query: FindTargetQuery,
-    data: RegattaData,
+// Handler for the finish approach endpoint: the points of no return of the
+// buoys from the given time, or else from the latest reported position with
+// the leg the boat sails measured against them
+async fn handle_finish_approach(
+    query: ApproachQuery,
+    snapshot: Snapshot,
+    track: Arc<Track>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    // Get starting buoy index by name
-    let start_idx = match data.get_boei_index(&query.start) {
-        Some(idx) => idx,
-        None => {
-            let error_response = json!({
-                "error": "Buoy not found",
-                "message": format!("Starting buoy '{}' not found", query.start)
-            });
-            return Ok(warp::reply::json(&error_response));
-        }
+    let data = snapshot.data;
+    let finish = finish_buoy(&data).map_err(warp::reject::custom)?;
+    let points = track.points_since(0.0);
+    let (time, position) = match &query.time {
+        Some(time) => (race_hours(&data, time).map_err(warp::reject::custom)?, None),
+        None => (points.last().map_or(0.0, |point| point.time), points.last()),
     };
+    let deadline = data.rules.scoring.deadline;
+    let departures = latest_departures(&data, finish, deadline, time);
 
-    // Get target buoy index by name
-    let target_idx = match data.get_boei_index(&query.target) {
-        Some(idx) => idx,
-        None => {
-            let error_response = json!({
-                "error": "Buoy not found",
-                "message": format!("Target buoy '{}' not found", query.target)
-            });
-            return Ok(warp::reply::json(&error_response));
-        }
+    let position = position.and_then(|position| {
+        let (from, to) = locate_leg(&data, &points)?;
+        let progress = leg_progress(&data, position, from, to).ok()?;
+        let latest = match departures.iter().find(|departure| departure.buoy == to) {
+            Some(departure) => Some(departure.latest),
+            None => (to == finish).then_some(deadline),
+        };
+        Some(ApproachPositionResponse {
+            lat: position.lat,
+            long: position.long,
+            from: data.boeien[from].name.clone(),
+            to: data.boeien[to].name.clone(),
+            eta: progress.eta,
+            eta_clock: progress.eta.and_then(|eta| data.race_clock.format_clock(eta)),
+            margin: progress.eta.zip(latest).map(|(eta, latest)| latest - eta),
+        })
+    });
+    Ok(warp::reply::json(&FinishApproachResponse::new(&data, time, finish, &departures, position)))
+}
+
+// Handler for the course map with the points of no return of the buoys from
+// the given time, or else from the latest reported position
+async fn handle_finish_approach_svg(
+    query: ApproachQuery,
+    snapshot: Snapshot,
+    track: Arc<Track>,
+    theme: Theme,
+) -> Result<Box<dyn warp::Reply>, warp::Rejection> {
+    let data = snapshot.data;
+    let finish = finish_buoy(&data).map_err(warp::reject::custom)?;
+    let time = match &query.time {
+        Some(time) => race_hours(&data, time).map_err(warp::reject::custom)?,
+        None => track.points_since(0.0).last().map_or(0.0, |point| point.time),
     };
+    let departures = latest_departures(&data, finish, data.rules.scoring.deadline, time);
 
-    // Validate time parameter
-    if query.time < 0.0 || query.time > 24.0 {
-        let error_response = json!({
-            "error": "Invalid time",
-            "message": "Time must be between 0 and 24 hours"
-        });
-        return Ok(warp::reply::json(&error_response));
+    match create_deadline_map(&data, &departures, themed(theme)) {
+        Ok(svg_content) => Ok(Box::new(warp::reply::with_header(
+            svg_content,
+            "Content-Type",
+            "image/svg+xml",
+        ))),
+        Err(e) => Err(warp::reject::custom(ApiError::internal(
+            "Chart generation failed",
+            format!("Error generating deadline map: {e}"),
+        ))),
     }
+}
 
-    // Validate steps parameter
-    if query.steps == 0 || query.steps > 10 {
-        let error_response = json!({
-            "error": "Invalid steps",
-            "message": "Maximum number of steps must be between 1 and 10"
-        });
-        return Ok(warp::reply::json(&error_response));
+// The buoy of the finish line, which the data of some courses lack
+fn finish_buoy(data: &RegattaData) -> Result<usize, ApiError> {
+    data.get_boei_index(FINISH_BUOY).ok_or_else(|| {
+        ApiError::unprocessable("No finish", format!("The data has no buoy {FINISH_BUOY}"))
+    })
+}
+
+// Check every minute whether a boat with a plan is projected to finish with
+// less than the margin to spare, and whether a boat which reported positions
+// stopped doing so, and raise an alert once each time that happens
+async fn watch_boats(state: State, alerts: Alerts) {
+    let mut late_boats: HashMap<Option<String>, bool> = HashMap::new();
+    let mut silent_boats: HashMap<Option<String>, bool> = HashMap::new();
+    let mut interval = tokio::time::interval(Duration::from_secs(60));
+    loop {
+        interval.tick().await;
+
+        let own = (None, state.track.clone(), state.plan_session);
+        let fleet = state
+            .fleet
+            .list()
+            .into_iter()
+            .map(|boat| (Some(boat.clone()), boat.track.clone(), boat.session));
+        for (boat, track, session) in std::iter::once(own).chain(fleet) {
+            let name = boat.as_ref().map(|boat| boat.name.clone());
+            let label = name.as_deref().unwrap_or("own boat");
+
+            if let Some(timeout) = alerts.position_timeout {
+                let last = track.points_since(0.0).into_iter().max_by_key(|point| point.received);
+                let silent = last.as_ref().is_some_and(|point| {
+                    (chrono::Utc::now() - point.received).to_std().unwrap_or_default() > timeout
+                });
+                let was_silent = silent_boats.insert(name.clone(), silent).unwrap_or(false);
+                if let Some(point) = last.filter(|_| silent && !was_silent) {
+                    tracing::warn!(boat = label, last_position = %point.received, "no position reported");
+                    alerts
+                        .send(json!({
+                            "alert": "no_position",
+                            "boat": name,
+                            "minutes": timeout.as_secs() / 60,
+                            "last_position": point,
+                        }))
+                        .await;
+                }
+            }
+
+            let Some(session) = session.and_then(|id| state.sessions.get(id)) else {
+                continue;
+            };
+            let snapshot = state.boat_snapshot(boat.as_deref());
+            let Ok(progress) = session_progress(&snapshot.data, &session, &track) else {
+                continue;
+            };
+            let margin = progress.projected_finish.map(|finish| snapshot.data.rules.scoring.deadline - finish);
+            let late = margin.is_none_or(|margin| margin < alerts.margin);
+            let was_late = late_boats.insert(name.clone(), late).unwrap_or(false);
+            if !late || was_late {
+                continue;
+            }
+
+            tracing::warn!(
+                boat = label,
+                session = session.id,
+                time_behind = progress.time_behind,
+                projected_finish = ?progress.projected_finish,
+                "projected finish too close to the end of the race"
+            );
+            alerts
+                .send(json!({
+                    "alert": "late",
+                    "boat": name,
+                    "margin": margin,
+                    "progress": progress,
+                }))
+                .await;
+        }
     }
+}
 
-    // Validate max_paths parameter
-    let max_paths = query.max_paths;
-    if let Some(max_paths_val) = max_paths {
-        if max_paths_val == 0 || max_paths_val > 100000 {
-            let error_response = json!({
-                "error": "Invalid max_paths",
-                "message": "Maximum number of paths must be between 1 and 100000"
-            });
-            return Ok(warp::reply::json(&error_response));
+// Handler for the event feed: every event published from now on, until the
+// client goes away. A client which falls too far behind skips the events it
+// missed; the pages fetch the current state on the next one anyway.
+fn handle_events(state: State) -> impl warp::Reply {
+    let events = futures_util::stream::unfold(state.events.subscribe(), |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => {
+                    let sse = warp::sse::Event::default().event(event.name()).json_data(&event);
+                    return Some((sse, receiver));
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return None,
+            }
+        }
+    });
+    warp::sse::reply(warp::sse::keep_alive().stream(events))
+}
+
+// Steps of the continuation recommended in the event feed, as the dashboard
+// plans by default
+const RECOMMENDATION_STEPS: usize = 3;
+
+// The route the own boat is recommended: the leg it sails, guessed from the
+// track, followed by the best continuation from the next buoy, and the ETA
+// there. `None` without a position near a leg.
+async fn recommendation(state: &ServerState) -> Option<ServerEvent> {
+    let data = state.snapshot().data;
+    let points = state.track.points_since(0.0);
+    let position = points.last()?;
+    let (from, to) = locate_leg(&data, &points)?;
+    let progress = leg_progress(&data, position, from, to).ok()?;
+    let mut route = vec![data.boeien[from].name.clone(), data.boeien[to].name.clone()];
+
+    if let Some(eta) = progress.eta.filter(|eta| *eta < data.rules.scoring.deadline) {
+        let permit = admit_search(&state.limits, None, &data, RECOMMENDATION_STEPS).ok()?;
+        let search_data = data.clone();
+        let budget = state.limits.budget();
+        let path = state
+            .limits
+            .run(permit, move || {
+                let options = SearchOptions {
+                    budget,
+                    ..Default::default()
+                };
+                best_continuation(&search_data, to, eta, RECOMMENDATION_STEPS, &PlanObjective::MaxDistance, &options)
+            })
+            .await
+            .ok()?
+            .ok()?;
+        if let Some(path) = path {
+            route.extend(path.steps.iter().map(|step| data.boeien[step.to].name.clone()));
         }
     }
+    Some(ServerEvent::Recommendation {
+        route,
+        eta: progress.eta,
+        clock: progress.eta.and_then(|eta| data.race_clock.format_clock(eta)),
+    })
+}
 
-    // Check if start and target are the same
-    if start_idx == target_idx {
-        let error_response = json!({
-            "error": "Invalid request",
-            "message": "Starting and target buoys must be different"
-        });
-        return Ok(warp::reply::json(&error_response));
+// Work out the recommendation again whenever the own boat reports a position
+// or the wind or data change, and publish it when its route changed. Nothing
+// is searched while nobody listens to the feed but this task, except for
+// changes of the wind with alerts: when one of them changes the buoys
+// recommended after the leg being sailed, the crew is alerted.
+async fn watch_recommendation(state: State, alerts: Alerts) {
+    let mut receiver = state.events.subscribe();
+    let mut last_route: Option<Vec<String>> = None;
+    loop {
+        let wind_changed = match receiver.recv().await {
+            Ok(ServerEvent::Wind { .. }) => true,
+            Ok(ServerEvent::Position { boat: None, .. } | ServerEvent::Data { .. })
+            | Err(broadcast::error::RecvError::Lagged(_)) => false,
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Closed) => return,
+        };
+        if state.events.receiver_count() < 2 && !(wind_changed && alerts.enabled()) {
+            continue;
+        }
+        let Some(event) = recommendation(&state).await else {
+            continue;
+        };
+        if let ServerEvent::Recommendation { route, eta, clock } = &event {
+            if last_route.as_ref() == Some(route) {
+                continue;
+            }
+            // Only a different continuation of the same leg is the wind's doing
+            let previous = last_route.replace(route.clone());
+            if let Some(previous) = previous.filter(|previous| wind_changed && previous[..2] == route[..2]) {
+                tracing::info!(previous = ?previous, route = ?route, "wind changed the recommendation");
+                alerts
+                    .send(json!({
+                        "alert": "recommendation",
+                        "boat": null,
+                        "previous": previous,
+                        "route": route,
+                        "eta": eta,
+                        "clock": clock,
+                    }))
+                    .await;
+            }
+        }
+        state.publish(event);
     }
+}
 
-    // Explore paths to target
-    match explore_target_paths(&data, start_idx, target_idx, query.time, query.steps, max_paths) {
-        Ok(paths) => {
-            // Convert paths to JSON-friendly format
-            let paths_json: Vec<serde_json::Value> = paths
-                .iter()
-                .map(|path| {
-                    let steps_json: Vec<serde_json::Value> = path
-                        .steps
-                        .iter()
-                        .map(|step| {
-                            json!({
-                                "from": step.from,
-                                "to": step.to,
-                                "from_name": data.boeien[step.from].name,
-                                "to_name": data.boeien[step.to].name,
-                                "distance": step.distance,
-                                "speed": step.speed,
-                                "start_time": step.start_time,
-                                "end_time": step.end_time
-                            })
-                        })
-                        .collect();
-
-                    json!({
-                        "steps": steps_json,
-                        "total_distance": path.total_distance,
-                        "end_time": path.end_time
-                    })
-                })
-                .collect();
-
-            let response = json!({
-                "start": query.start,
-                "target": query.target,
-                "start_time": query.time,
-                "steps": query.steps,
-                "paths": paths_json
-            });
+// Handler for the search WebSocket: the client sends a plan request as its
+// first message and receives progress updates until the final result
+async fn handle_search_socket(
+    socket: WebSocket,
+    data: Arc<RegattaData>,
+    metrics: Arc<Metrics>,
+    remote: Option<SocketAddr>,
+    limits: Arc<SearchLimits>,
+) {
+    let (mut sender, mut receiver) = socket.split();
 
-            Ok(warp::reply::json(&response))
+    let request = match receiver.next().await {
+        Some(Ok(message)) => match message.to_str() {
+            Ok(text) => serde_json::from_str::<PlanRequest>(text)
+                .map_err(|e| ApiError::bad_request("Invalid request", e.to_string())),
+            Err(()) => Err(ApiError::bad_request(
+                "Invalid request",
+                "Expected a plan request as JSON text message",
+            )),
+        },
+        _ => return,
+    };
+    let admitted = request.and_then(|request| {
+        let permit = admit_plan(&limits, remote, &data, &request)?;
+        Ok((request, permit))
+    });
+    let (request, permit) = match admitted {
+        Ok(admitted) => admitted,
+        Err(error) => {
+            send_search_update(&mut sender, &SearchUpdate::Error(error.to_response())).await;
+            let _ = sender.close().await;
+            return;
         }
-        Err(e) => {
-            let error_response = json!({
-                "error": "Path exploration failed",
-                "message": format!("Error exploring paths to target: {}", e)
-            });
-            Ok(warp::reply::json(&error_response))
+    };
+
+    // Run the search on the blocking thread pool, the monitor tracks the best
+    // path so far for the progress reports
+    let objective = request
+        .objective
+        .as_deref()
+        .and_then(PlanObjective::from_name)
+        .unwrap_or_default();
+    let monitor = Arc::new(SearchMonitor::with_objective(objective));
+    let search_monitor = monitor.clone();
+    let search_data = data.clone();
+    let budget = limits.budget();
+    let search = limits.run(permit, move || plan_paths(&search_data, &request, &search_monitor, &metrics, budget));
+    tokio::pin!(search);
+
+    let mut interval = tokio::time::interval(std::time::Duration::from_millis(250));
+    let mut listening = true;
+    let outcome = loop {
+        tokio::select! {
+            outcome = &mut search => break outcome,
+            _ = interval.tick() => {
+                let update = SearchUpdate::Progress {
+                    nodes_explored: monitor.nodes_explored(),
+                    paths_found: monitor.paths_found(),
+                    best: monitor.best_path().map(|path| PathResponse::new(&data, &path)),
+                };
+                if !send_search_update(&mut sender, &update).await {
+                    monitor.cancel();
+                }
+            }
+            // Closing the socket or sending "cancel" stops the search
+            message = receiver.next(), if listening => match message {
+                Some(Ok(message)) if !message.is_close() && message.to_str() != Ok("cancel") => {}
+                _ => {
+                    listening = false;
+                    monitor.cancel();
+                }
+            }
         }
+    };
+
+    let update = match outcome {
+        Ok(Ok(response)) => SearchUpdate::Result(response),
+        Ok(Err(error)) => SearchUpdate::Error(error.to_response()),
+        Err(e) => SearchUpdate::Error(search_failed(e).to_response()),
+    };
+    send_search_update(&mut sender, &update).await;
+    let _ = sender.close().await;
+}
+
+// Send a search update as JSON text message, returning false if the client is gone
+async fn send_search_update(
+    sender: &mut SplitSink<WebSocket, Message>,
+    update: &SearchUpdate,
+) -> bool {
+    match serde_json::to_string(update) {
+        Ok(text) => sender.send(Message::text(text)).await.is_ok(),
+        Err(_) => false,
     }
 }
 
@@ -655,12 +4778,10 @@ async fn handle_find_target(
 async fn handle_pdf() -> Result<Box<dyn warp::Reply>, warp::Rejection> {
     // Check if the PDF file exists
     if !std::path::Path::new("regatta_graph.pdf").exists() {
-        // Return an error response
-        let error_response = json!({
-            "error": "PDF file not found",
-            "message": "The regatta graph PDF file does not exist. Please generate it first using the 'graph' subcommand."
-        });
-        return Ok(Box::new(warp::reply::json(&error_response)));
+        return Err(warp::reject::custom(ApiError::not_found(
+            "PDF file not found",
+            "The regatta graph PDF file does not exist. Please generate it first using the 'graph' subcommand.",
+        )));
     }
 
     // Read the PDF file
@@ -673,14 +4794,10 @@ async fn handle_pdf() -> Result<Box<dyn warp::Reply>, warp::Rejection> {
                 "application/pdf",
             )))
         }
-        Err(_) => {
-            // Return an error response if we can't read the file
-            let error_response = json!({
-                "error": "File read error",
-                "message": "Could not read the PDF file"
-            });
-            Ok(Box::new(warp::reply::json(&error_response)))
-        }
+        Err(_) => Err(warp::reject::custom(ApiError::internal(
+            "File read error",
+            "Could not read the PDF file",
+        ))),
     }
 }
 
@@ -688,12 +4805,10 @@ async fn handle_pdf() -> Result<Box<dyn warp::Reply>, warp::Rejection> {
 async fn handle_svg() -> Result<Box<dyn warp::Reply>, warp::Rejection> {
     // Check if the SVG file exists
     if !std::path::Path::new("regatta_course.svg").exists() {
-        // Return an error response
-        let error_response = json!({
-            "error": "SVG file not found",
-            "message": "The regatta course SVG file does not exist. Please generate it first using the 'plot' subcommand."
-        });
-        return Ok(Box::new(warp::reply::json(&error_response)));
+        return Err(warp::reject::custom(ApiError::not_found(
+            "SVG file not found",
+            "The regatta course SVG file does not exist. Please generate it first using the 'plot' subcommand.",
+        )));
     }
 
     // Read the SVG file
@@ -706,19 +4821,133 @@ async fn handle_svg() -> Result<Box<dyn warp::Reply>, warp::Rejection> {
                 "image/svg+xml",
             )))
         }
-        Err(_) => {
-            // Return an error response if we can't read the file
-            let error_response = json!({
-                "error": "File read error",
-                "message": "Could not read the SVG file"
-            });
-            Ok(Box::new(warp::reply::json(&error_response)))
-        }
+        Err(_) => Err(warp::reject::custom(ApiError::internal(
+            "File read error",
+            "Could not read the SVG file",
+        ))),
     }
 }
 
-// Custom error type for template rendering
+// Render a page template, rejecting the request if it fails
+fn render_template(tera: &Tera, template: &str, context: &Context) -> Result<String, warp::Rejection> {
+    tera.render(template, context).map_err(|e| {
+        tracing::error!("Template rendering error: {e}");
+        warp::reject::custom(ApiError::from(UursError::TemplateRender {
+            template: template.to_string(),
+            message: e.to_string(),
+        }))
+    })
+}
+
+// Error reported by the API, carrying the HTTP status code it maps to
 #[derive(Debug)]
-struct TemplateError;
+struct ApiError {
+    status: StatusCode,
+    error: String,
+    message: String,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, error: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            error: error.into(),
+            message: message.into(),
+        }
+    }
+
+    // Malformed or out-of-range request parameters
+    fn bad_request(error: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, error, message)
+    }
+
+    // Unknown buoys, jobs, or files
+    fn not_found(error: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, error, message)
+    }
+
+    // Well-formed requests that make no sense for the course, like a route
+    // using a leg which doesn't exist
+    fn unprocessable(error: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNPROCESSABLE_ENTITY, error, message)
+    }
+
+    // Failures on the server side
+    fn internal(error: impl Into<String>, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, error, message)
+    }
+
+    fn to_response(&self) -> ErrorResponse {
+        ErrorResponse {
+            status: self.status.as_u16(),
+            error: self.error.clone(),
+            message: self.message.clone(),
+        }
+    }
+}
+
+impl warp::reject::Reject for ApiError {}
 
-impl warp::reject::Reject for TemplateError {}
+// Map library errors to status codes: mistakes in the request are client
+// errors, failing data files and templates are server errors
+impl From<UursError> for ApiError {
+    fn from(e: UursError) -> Self {
+        let (status, error) = match &e {
+            UursError::UnknownBuoy(_) => (StatusCode::NOT_FOUND, "Buoy not found"),
+            UursError::MissingCoordinates(_) => (StatusCode::UNPROCESSABLE_ENTITY, "Missing coordinates"),
+            UursError::NoLeg { .. } | UursError::LegOverused { .. } | UursError::ForbiddenSequence(_) => {
+                (StatusCode::UNPROCESSABLE_ENTITY, "Invalid route")
+            }
+            UursError::InvalidParameter(_) => (StatusCode::BAD_REQUEST, "Invalid parameter"),
+            UursError::CoordinateParse { .. } => (StatusCode::BAD_REQUEST, "Invalid coordinate"),
+            UursError::InvalidPolars(_) => (StatusCode::BAD_REQUEST, "Invalid polars"),
+            UursError::InvalidTrack(_) => (StatusCode::BAD_REQUEST, "Invalid track"),
+            UursError::SearchLimitExceeded { .. } => {
+                (StatusCode::PAYLOAD_TOO_LARGE, "Search too large")
+            }
+            UursError::TooManySearches { .. } => (StatusCode::TOO_MANY_REQUESTS, "Too many searches"),
+            UursError::Cancelled => (StatusCode::SERVICE_UNAVAILABLE, "Search cancelled"),
+            UursError::DataLoad { .. } => (StatusCode::INTERNAL_SERVER_ERROR, "Data load failed"),
+            UursError::TemplateRender { .. } => (StatusCode::INTERNAL_SERVER_ERROR, "Template error"),
+            UursError::Storage(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Storage error"),
+            UursError::Export(_) => (StatusCode::INTERNAL_SERVER_ERROR, "Export failed"),
+            UursError::Io(_) => (StatusCode::INTERNAL_SERVER_ERROR, "I/O error"),
+        };
+        Self::new(status, error, e.to_string())
+    }
+}
+
+// Turn rejections into JSON error responses with the proper status code
+async fn handle_rejection(
+    rejection: warp::Rejection,
+) -> Result<impl warp::Reply, std::convert::Infallible> {
+    let error = if let Some(api_error) = rejection.find::<ApiError>() {
+        ApiError::new(api_error.status, &api_error.error, &api_error.message)
+    } else if rejection.is_not_found() {
+        ApiError::not_found("Not found", "The requested resource does not exist")
+    } else if let Some(e) = rejection.find::<warp::reject::InvalidQuery>() {
+        ApiError::bad_request("Invalid query", e.to_string())
+    } else if let Some(e) = rejection.find::<warp::body::BodyDeserializeError>() {
+        ApiError::bad_request("Invalid body", e.to_string())
+    } else if let Some(e) = rejection.find::<warp::reject::PayloadTooLarge>() {
+        ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, "Payload too large", e.to_string())
+    } else if let Some(e) = rejection.find::<warp::reject::UnsupportedMediaType>() {
+        ApiError::new(StatusCode::UNSUPPORTED_MEDIA_TYPE, "Unsupported media type", e.to_string())
+    } else if let Some(e) = rejection.find::<warp::reject::MethodNotAllowed>() {
+        ApiError::new(StatusCode::METHOD_NOT_ALLOWED, "Method not allowed", e.to_string())
+    } else {
+        tracing::warn!("Unhandled rejection: {rejection:?}");
+        ApiError::internal("Internal error", "An unexpected error occurred")
+    };
+
+    let mut response =
+        warp::reply::with_status(warp::reply::json(&error.to_response()), error.status)
+            .into_response();
+    if error.status == StatusCode::UNAUTHORIZED {
+        response.headers_mut().insert(
+            warp::http::header::WWW_AUTHENTICATE,
+            warp::http::HeaderValue::from_static("Bearer"),
+        );
+    }
+    Ok(response)
+}
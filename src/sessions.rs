@@ -0,0 +1,162 @@
+use crate::error::UursError;
+use crate::storage::{JsonFiles, MemoryStorage, Storage};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// A leg between two buoys given by name
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct SessionLeg {
+    pub from: String,
+    pub to: String,
+}
+
+/// The editable part of a planning session, as sent by the client
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionInput {
+    pub name: String,
+    pub start: Option<String>,   // chosen starting buoy
+    pub start_time: Option<f64>, // in hours since race start
+    #[serde(default)]
+    pub pinned_legs: Vec<SessionLeg>, // legs the crew wants to sail
+    #[serde(default)]
+    pub completed_legs: Vec<SessionLeg>, // legs already sailed, in order
+    #[serde(default)]
+    pub notes: String,
+}
+
+/// A named planning session with the crew's working plan
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub id: u64,
+    #[serde(flatten)]
+    pub input: SessionInput,
+    pub created: DateTime<Utc>,
+    pub updated: DateTime<Utc>,
+}
+
+/// All planning sessions, with every change handed to a storage
+#[derive(Debug)]
+pub struct SessionStore {
+    storage: Arc<dyn Storage>,
+    sessions: Mutex<BTreeMap<u64, Session>>,
+}
+
+impl Default for SessionStore {
+    fn default() -> Self {
+        Self {
+            storage: Arc::new(MemoryStorage::new()),
+            sessions: Mutex::default(),
+        }
+    }
+}
+
+impl SessionStore {
+    /// Create an empty store which is only kept in memory
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a sessions file, starting empty if it doesn't exist yet
+    pub fn open(path: &Path) -> Result<Self, UursError> {
+        let storage = JsonFiles {
+            sessions_file: Some(path.to_path_buf()),
+            ..JsonFiles::default()
+        };
+        Self::with_storage(Arc::new(storage))
+    }
+
+    /// Load the sessions of a storage and keep saving changes to it
+    pub fn with_storage(storage: Arc<dyn Storage>) -> Result<Self, UursError> {
+        let sessions = storage.load_sessions()?.into_iter().map(|s| (s.id, s)).collect();
+        Ok(Self {
+            storage,
+            sessions: Mutex::new(sessions),
+        })
+    }
+
+    /// List all sessions ordered by id
+    pub fn list(&self) -> Vec<Session> {
+        self.sessions.lock().unwrap().values().cloned().collect()
+    }
+
+    /// Get a session by id
+    pub fn get(&self, id: u64) -> Option<Session> {
+        self.sessions.lock().unwrap().get(&id).cloned()
+    }
+
+    /// Create a session and return it with its new id. Sessions are never
+    /// removed, so the new id follows the highest one.
+    pub fn create(&self, input: SessionInput) -> Result<Session, UursError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let now = Utc::now();
+        let session = Session {
+            id: sessions.keys().next_back().map_or(1, |id| id + 1),
+            input,
+            created: now,
+            updated: now,
+        };
+        self.storage.save_session(&session)?;
+        sessions.insert(session.id, session.clone());
+        Ok(session)
+    }
+
+    /// Replace the plan of a session, `None` if there is no session with the id
+    pub fn update(&self, id: u64, input: SessionInput) -> Result<Option<Session>, UursError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let Some(session) = sessions.get(&id) else {
+            return Ok(None);
+        };
+        let session = Session {
+            input,
+            updated: Utc::now(),
+            ..session.clone()
+        };
+        self.storage.save_session(&session)?;
+        sessions.insert(id, session.clone());
+        Ok(Some(session))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_persist() {
+        let path = std::env::temp_dir().join(format!("uurs24-sessions-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let input = SessionInput {
+            name: "Night watch".to_string(),
+            start: Some("SPORT-A".to_string()),
+            start_time: Some(0.0),
+            pinned_legs: vec![SessionLeg {
+                from: "SPORT-A".to_string(),
+                to: "KR-A".to_string(),
+            }],
+            completed_legs: Vec::new(),
+            notes: String::new(),
+        };
+
+        let store = SessionStore::open(&path).unwrap();
+        let session = store.create(input.clone()).unwrap();
+        let notes = SessionInput {
+            notes: "Reef before midnight".to_string(),
+            ..input
+        };
+        store.update(session.id, notes).unwrap().unwrap();
+        assert!(store.update(session.id + 1, session.input.clone()).unwrap().is_none());
+
+        // A new store reads the sessions back from the file
+        let reopened = SessionStore::open(&path).unwrap();
+        let loaded = reopened.get(session.id).unwrap();
+        assert_eq!(loaded.input.notes, "Reef before midnight");
+        assert_eq!(loaded.input.pinned_legs, session.input.pinned_legs);
+        assert_eq!(reopened.create(session.input).unwrap().id, session.id + 1);
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
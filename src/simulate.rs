@@ -0,0 +1,506 @@
+use crate::data::{PolarData, RegattaData, WindCondition};
+use crate::error::UursError;
+use crate::geo::{destination, distance_nm, initial_bearing};
+use crate::optimize::{forecast_wind, relative_bearing};
+use crate::scoring::corrected_distance;
+use serde::{Deserialize, Serialize};
+
+/// Water moving over the whole course at a constant rate
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct Current {
+    pub direction: f64, // where the water flows to in degrees
+    pub speed: f64,     // in knots
+}
+
+/// Options of a simulation
+#[derive(Clone, Debug)]
+pub struct SimulationOptions {
+    pub time_step: f64,          // in hours
+    pub current: Option<Current>,
+    pub rounding_penalty: f64,   // hours lost at every mark rounding
+    pub lost_time: Vec<(usize, f64)>, // index of a leg and the hours lost on it in addition
+}
+
+impl Default for SimulationOptions {
+    fn default() -> Self {
+        Self {
+            time_step: 1.0 / 60.0,
+            current: None,
+            rounding_penalty: 0.0,
+            lost_time: Vec::new(),
+        }
+    }
+}
+
+/// State of the boat at one time of a simulation
+#[derive(Clone, Debug, Serialize)]
+pub struct SimulationSample {
+    pub time: f64,             // in hours since race start
+    pub lat: f64,
+    pub long: f64,
+    pub leg: usize,            // index of the leg being sailed
+    pub speed: f64,            // through the water in knots, 0 at the finish
+    pub speed_over_ground: f64, // along the leg in knots
+    pub distance: f64,         // sailed since the start of the route in nm
+    pub wind_direction: f64,   // in degrees
+    pub wind_speed: f64,       // in knots
+}
+
+/// A leg of a simulated route with its times
+#[derive(Clone, Debug, Serialize)]
+pub struct SimulatedLeg {
+    pub from: usize,
+    pub to: usize,
+    pub distance: f64,   // in nm
+    pub start_time: f64, // in hours since race start
+    pub end_time: f64,   // rounding of `to` in hours since race start
+    pub lost_time: f64,  // in hours, included in the end time
+}
+
+/// Result of sailing a route forward in time
+#[derive(Clone, Debug, Serialize)]
+pub struct Simulation {
+    pub legs: Vec<SimulatedLeg>,
+    pub samples: Vec<SimulationSample>, // every time step and every rounding
+    pub total_distance: f64,            // in nm
+    pub end_time: f64,                  // in hours since race start
+}
+
+impl Simulation {
+    /// Distance sailed and position at a time, interpolated between the
+    /// samples; before the start at the start, after the finish at the finish
+    pub fn position_at(&self, time: f64) -> (f64, f64, f64) {
+        let index = self.samples.partition_point(|sample| sample.time <= time);
+        let (Some(before), Some(after)) = (index.checked_sub(1).map(|i| &self.samples[i]), self.samples.get(index)) else {
+            let sample = &self.samples[index.saturating_sub(1)];
+            return (sample.distance, sample.lat, sample.long);
+        };
+        let fraction = (time - before.time) / (after.time - before.time);
+        (
+            before.distance + fraction * (after.distance - before.distance),
+            before.lat + fraction * (after.lat - before.lat),
+            before.long + fraction * (after.long - before.long),
+        )
+    }
+}
+
+/// A boat of a fleet simulation with its own polars and route
+#[derive(Clone, Debug)]
+pub struct FleetBoat {
+    pub name: String,
+    pub polar: Option<PolarData>, // `None` sails with the polars of the data
+    pub route: Vec<usize>,
+    pub start_time: f64,          // in hours since the start of its class
+    pub rating: Option<f64>,      // handicap correcting the miles, `None` for none
+    pub class: Option<String>,    // `None` starts with the class of the data
+}
+
+/// Position of a boat in the fleet at one time
+#[derive(Clone, Debug, Serialize)]
+pub struct Standing {
+    pub name: String,
+    pub rank: usize,    // 1 for the boat which has sailed the farthest
+    pub distance: f64,  // sailed in nm
+    pub behind: f64,    // nm less than the leader
+    pub corrected_distance: f64, // sailed in nm, corrected by the rating of the boat
+    pub corrected_rank: usize,   // 1 for the boat with the most corrected miles
+    pub lat: f64,
+    pub long: f64,
+    pub finished: bool,
+}
+
+/// Standings of the fleet at a full hour
+#[derive(Clone, Debug, Serialize)]
+pub struct FleetStandings {
+    pub time: f64, // in hours since race start
+    pub standings: Vec<Standing>,
+}
+
+/// Result of simulating a fleet, timed by the clock of the data except the
+/// simulations of the boats, which run on the clock of their class
+#[derive(Clone, Debug, Serialize)]
+pub struct FleetSimulation {
+    pub boats: Vec<(String, Simulation)>,
+    pub hours: Vec<FleetStandings>,
+    pub finishes: Vec<(String, f64)>, // names and end times ordered by the end time
+}
+
+/// Simulate the boats of a fleet on their routes and rank them at every full
+/// hour by the distance they have sailed, the score of the race, and by the
+/// distance corrected with their ratings
+///
+/// Boats which have finished keep their distance; between boats which sailed
+/// the same distance the one which finished first ranks higher. Boats of
+/// classes which start later are compared at the same moment, not after the
+/// same time sailed.
+pub fn simulate_fleet(
+    data: &RegattaData,
+    boats: &[FleetBoat],
+    options: &SimulationOptions,
+) -> Result<FleetSimulation, UursError> {
+    if boats.is_empty() {
+        return Err(UursError::InvalidParameter("The fleet has no boats".to_string()));
+    }
+    let mut simulations = Vec::with_capacity(boats.len());
+    let mut shifts = Vec::with_capacity(boats.len());
+    for boat in boats {
+        let mut boat_data = None;
+        if let Some(polar) = &boat.polar {
+            boat_data.get_or_insert_with(|| data.clone()).polar_data = polar.clone();
+        }
+        if let Some(class) = &boat.class {
+            boat_data.get_or_insert_with(|| data.clone()).select_class(class)?;
+        }
+        let boat_data = boat_data.as_ref().unwrap_or(data);
+        simulations.push((boat.name.clone(), simulate_route(boat_data, &boat.route, boat.start_time, options)?));
+        // Hours the clock of the class of the boat runs behind the clock of the data
+        shifts.push(boat_data.race_clock.start_offset - data.race_clock.start_offset);
+    }
+
+    let first = simulations
+        .iter()
+        .zip(&shifts)
+        .map(|((_, simulation), shift)| simulation.legs[0].start_time + shift)
+        .fold(f64::INFINITY, f64::min);
+    let last = simulations
+        .iter()
+        .zip(&shifts)
+        .map(|((_, simulation), shift)| simulation.end_time + shift)
+        .fold(0.0, f64::max);
+    let mut hours = Vec::new();
+    let mut hour = first.ceil();
+    while hour <= last.ceil() {
+        let mut standings: Vec<(Standing, f64)> = simulations
+            .iter()
+            .zip(boats.iter().zip(&shifts))
+            .map(|((name, simulation), (boat, shift))| {
+                let (distance, lat, long) = simulation.position_at(hour - shift);
+                let end_time = simulation.end_time + shift;
+                let finished = end_time <= hour;
+                let standing = Standing {
+                    name: name.clone(),
+                    rank: 0,
+                    distance,
+                    behind: 0.0,
+                    corrected_distance: corrected_distance(distance, boat.rating),
+                    corrected_rank: 0,
+                    lat,
+                    long,
+                    finished,
+                };
+                (standing, if finished { end_time } else { f64::INFINITY })
+            })
+            .collect();
+        standings.sort_by(|(a, a_end), (b, b_end)| {
+            b.distance.total_cmp(&a.distance).then(a_end.total_cmp(b_end))
+        });
+        let mut corrected: Vec<usize> = (0..standings.len()).collect();
+        corrected.sort_by(|&a, &b| {
+            let ((a, a_end), (b, b_end)) = (&standings[a], &standings[b]);
+            b.corrected_distance.total_cmp(&a.corrected_distance).then(a_end.total_cmp(b_end))
+        });
+        let mut corrected_ranks = vec![0; standings.len()];
+        for (rank, &i) in corrected.iter().enumerate() {
+            corrected_ranks[i] = rank + 1;
+        }
+        let leader = standings[0].0.distance;
+        let standings = standings
+            .into_iter()
+            .zip(corrected_ranks)
+            .enumerate()
+            .map(|(i, ((standing, _), corrected_rank))| Standing {
+                rank: i + 1,
+                behind: leader - standing.distance,
+                corrected_rank,
+                ..standing
+            })
+            .collect();
+        hours.push(FleetStandings { time: hour, standings });
+        hour += 1.0;
+    }
+
+    let mut finishes: Vec<(String, f64)> = simulations
+        .iter()
+        .zip(&shifts)
+        .map(|((name, simulation), shift)| (name.clone(), simulation.end_time + shift))
+        .collect();
+    finishes.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    Ok(FleetSimulation {
+        boats: simulations,
+        hours,
+        finishes,
+    })
+}
+
+/// Time steps of a leg whose boat speeds are looked up in the polars at
+/// once: the first batch of a leg is small, for the short legs, and every
+/// further one twice as large up to the largest
+const SPEED_BATCHES: (usize, usize) = (8, 64);
+
+/// The wind and the boat speed through the water on a leg for `count` time
+/// steps from `time` on, the last first
+///
+/// The wind only depends on the time, so the speeds of the steps ahead are
+/// known before the boat sails them and the polars can be looked up in one
+/// batch. They are the speeds [`crate::optimize::estimate_leg_performance`]
+/// estimates at the same times.
+fn leg_speeds(
+    data: &RegattaData,
+    (from, to): (usize, usize),
+    course: f64, // initial bearing of the leg in degrees
+    time: f64,
+    time_step: f64,
+    count: usize,
+) -> Vec<(f64, WindCondition)> {
+    let winds: Vec<WindCondition> = (0..count)
+        .scan(time, |time, _| {
+            let wind = forecast_wind(data, *time);
+            *time += time_step;
+            Some(wind)
+        })
+        .collect();
+    let lookups: Vec<(f64, f64)> = winds
+        .iter()
+        .map(|wind| (relative_bearing(wind.wind_angle, course), wind.wind_speed))
+        .collect();
+    let correction = data.leg_correction(from, to);
+    let speeds = data.polar_data.get_boat_speeds(&lookups);
+    let mut batch: Vec<(f64, WindCondition)> = speeds
+        .into_iter()
+        .map(|speed| speed * correction)
+        .zip(winds)
+        .collect();
+    batch.reverse();
+    batch
+}
+
+/// Sail a route forward in time in fixed time steps
+///
+/// At every step the boat speed is estimated from the polars and the wind
+/// interpolated for that moment, so the wind may change along a leg, unlike
+/// in `evaluate_route` which takes the speed at the start of a leg. A current
+/// adds its component along the leg to the speed over ground, and every mark
+/// rounding costs the rounding penalty. Positions are interpolated along the
+/// straight line between the buoys, over the official distance of each leg.
+/// The lost time of a leg delays the rounding at its end, which lets the
+/// crew play through what happens if they are late at a mark.
+pub fn simulate_route(
+    data: &RegattaData,
+    route: &[usize],  // indices of the buoys in the order they are sailed
+    start_time: f64,  // time in hours since race start
+    options: &SimulationOptions,
+) -> Result<Simulation, UursError> {
+    if route.len() < 2 {
+        return Err(UursError::InvalidParameter("A route needs at least two buoys".to_string()));
+    }
+    if options.time_step <= 0.0 {
+        return Err(UursError::InvalidParameter("The time step must be positive".to_string()));
+    }
+    if let Some(&invalid) = route.iter().find(|&&idx| idx >= data.boeien.len()) {
+        return Err(UursError::UnknownBuoy(invalid.to_string()));
+    }
+
+    let mut legs = Vec::with_capacity(route.len() - 1);
+    let mut positions = Vec::with_capacity(route.len() - 1);
+    for pair in route.windows(2) {
+        let (from, to) = (pair[0], pair[1]);
+        let distance = data.leg_distance(from, to).ok_or_else(|| UursError::NoLeg {
+            from: data.boeien[from].name.clone(),
+            to: data.boeien[to].name.clone(),
+        })?;
+        let coordinates = |buoy: usize| {
+            data.boeien[buoy].coordinates().ok_or_else(|| {
+                UursError::InvalidParameter(format!("Buoy '{}' has no coordinates", data.boeien[buoy].name))
+            })
+        };
+        positions.push((coordinates(from)?, coordinates(to)?));
+        legs.push(SimulatedLeg {
+            from,
+            to,
+            distance,
+            start_time: 0.0,
+            end_time: 0.0,
+            lost_time: 0.0,
+        });
+    }
+
+    let mut samples = Vec::new();
+    let mut time = start_time;
+    let mut sailed = 0.0;
+    for (index, leg) in legs.iter_mut().enumerate() {
+        if index > 0 {
+            time += options.rounding_penalty;
+        }
+        leg.start_time = time;
+        let (from, to) = positions[index];
+        let (course, length) = (initial_bearing(from, to), distance_nm(from, to));
+        let mut along = 0.0;
+        let mut speeds = Vec::new();
+        let mut batch = SPEED_BATCHES.0;
+        loop {
+            if speeds.is_empty() {
+                speeds = leg_speeds(data, (leg.from, leg.to), course, time, options.time_step, batch);
+                batch = (batch * 2).min(SPEED_BATCHES.1);
+            }
+            let (estimated_speed, wind) = speeds.pop().unwrap();
+            let speed = if estimated_speed > 0.0 {
+                estimated_speed
+            } else {
+                1.0 // 1 knot as fallback
+            };
+            let drift = options.current.map_or(0.0, |current| {
+                current.speed * (current.direction - course).to_radians().cos()
+            });
+            let speed_over_ground = (speed + drift).max(0.1);
+
+            // On the great circle between the buoys, in proportion to the
+            // official distance sailed
+            let fraction = if leg.distance > 0.0 { along / leg.distance } else { 1.0 };
+            let (lat, long) = destination(from, course, fraction * length);
+            samples.push(SimulationSample {
+                time,
+                lat,
+                long,
+                leg: index,
+                speed,
+                speed_over_ground,
+                distance: sailed + along,
+                wind_direction: wind.wind_angle,
+                wind_speed: wind.wind_speed,
+            });
+
+            let remaining = leg.distance - along;
+            if remaining <= speed_over_ground * options.time_step {
+                time += remaining / speed_over_ground;
+                break;
+            }
+            along += speed_over_ground * options.time_step;
+            time += options.time_step;
+        }
+        sailed += leg.distance;
+        leg.lost_time = options
+            .lost_time
+            .iter()
+            .filter(|&&(lost_leg, _)| lost_leg == index)
+            .fold(0.0, |lost, &(_, hours)| lost + hours);
+        time += leg.lost_time;
+        leg.end_time = time;
+    }
+
+    // The boat at the finish
+    let last = legs.len() - 1;
+    let (_, (lat, long)) = positions[last];
+    let wind = data.wind_data.get_wind_at_time(time);
+    samples.push(SimulationSample {
+        time,
+        lat,
+        long,
+        leg: last,
+        speed: 0.0,
+        speed_over_ground: 0.0,
+        distance: sailed,
+        wind_direction: wind.as_ref().map_or(0.0, |wind| wind.wind_angle),
+        wind_speed: wind.as_ref().map_or(0.0, |wind| wind.wind_speed),
+    });
+
+    Ok(Simulation {
+        legs,
+        samples,
+        total_distance: sailed,
+        end_time: time,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimize::evaluate_route;
+
+    #[test]
+    fn test_simulate_route() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let route = ["OEVE", "WV12", "GVS3-M8"].map(|name| data.get_boei_index(name).unwrap());
+        let plan = evaluate_route(&data, &route, 0.0).unwrap();
+
+        // Close to the per-leg estimate, which differs by the wind changing along the legs
+        let simulation = simulate_route(&data, &route, 0.0, &SimulationOptions::default()).unwrap();
+        assert_eq!(simulation.legs.len(), 2);
+        assert!((simulation.total_distance - plan.total_distance).abs() < 1e-9);
+        assert!((simulation.end_time - plan.end_time).abs() < 0.25);
+        assert!(simulation.samples.windows(2).all(|pair| pair[0].time <= pair[1].time));
+        // The speeds looked up in batches are those estimated step by step
+        for sample in simulation.samples.iter().filter(|sample| sample.speed > 0.0) {
+            let leg = &simulation.legs[sample.leg];
+            let performance = crate::optimize::estimate_leg_performance(&data, leg.from, leg.to, sample.time).unwrap();
+            assert_eq!(sample.speed, performance.estimated_speed);
+            assert_eq!(sample.wind_speed, performance.wind_speed);
+        }
+
+        // A rounding penalty and a foul current make the boat later
+        let options = SimulationOptions {
+            current: Some(Current {
+                direction: 0.0,
+                speed: 1.0,
+            }),
+            rounding_penalty: 0.1,
+            ..SimulationOptions::default()
+        };
+        let slower = simulate_route(&data, &route, 0.0, &options).unwrap();
+        assert!(slower.legs[1].start_time - slower.legs[0].end_time > 0.09);
+
+        // Time lost on the first leg delays its rounding and everything after it
+        let options = SimulationOptions {
+            lost_time: vec![(0, 0.5)],
+            ..SimulationOptions::default()
+        };
+        let late = simulate_route(&data, &route, 0.0, &options).unwrap();
+        assert!((late.legs[0].end_time - simulation.legs[0].end_time - 0.5).abs() < 1e-9);
+        assert!(late.end_time > simulation.end_time + 0.4);
+        assert!(simulate_route(&data, &[route[1], route[0]], 0.0, &options).is_err());
+    }
+
+    #[test]
+    fn test_leg_speeds() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let (from, to) = (data.get_boei_index("OEVE").unwrap(), data.get_boei_index("WV12").unwrap());
+        let course = crate::optimize::estimate_leg_performance(&data, from, to, 0.0).unwrap().course_bearing;
+        // Over several hours, so the wind changes between the steps
+        let mut speeds = leg_speeds(&data, (from, to), course, 0.5, 0.1, 64);
+        assert_eq!(speeds.len(), 64);
+        let mut time = 0.5;
+        while let Some((speed, wind)) = speeds.pop() {
+            let performance = crate::optimize::estimate_leg_performance(&data, from, to, time).unwrap();
+            assert_eq!(speed, performance.estimated_speed);
+            assert_eq!((wind.wind_speed, wind.wind_angle), (performance.wind_speed, performance.wind_direction));
+            time += 0.1;
+        }
+    }
+
+    #[test]
+    fn test_simulate_fleet() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let route: Vec<usize> = ["OEVE", "WV12", "GVS3-M8"].iter().map(|name| data.get_boei_index(name).unwrap()).collect();
+        let mut slow = data.polar_data.clone();
+        for speeds in &mut slow.boat_speeds {
+            speeds.iter_mut().for_each(|speed| *speed *= 0.8);
+        }
+        let boats = [
+            FleetBoat { name: "Slow".to_string(), polar: Some(slow), route: route.clone(), start_time: 0.0, rating: Some(130.0), class: None },
+            FleetBoat { name: "Fast".to_string(), polar: None, route, start_time: 0.0, rating: None, class: None },
+        ];
+        let fleet = simulate_fleet(&data, &boats, &SimulationOptions::default()).unwrap();
+        assert_eq!(fleet.finishes[0].0, "Fast");
+        let first_hour = &fleet.hours[1];
+        assert_eq!(first_hour.time, 1.0);
+        assert_eq!(first_hour.standings[0].name, "Fast");
+        assert_eq!(first_hour.standings[1].rank, 2);
+        assert!(first_hour.standings[1].behind > 0.5);
+        // Both have finished after the last hour and sailed the same distance
+        let last_hour = fleet.hours.last().unwrap();
+        assert!(last_hour.standings.iter().all(|standing| standing.finished && standing.behind.abs() < 1e-9));
+        assert_eq!(last_hour.standings[0].name, "Fast");
+        // Corrected by its rating the slow boat wins
+        assert_eq!(last_hour.standings[1].corrected_rank, 1);
+        assert!((last_hour.standings[1].corrected_distance - 1.3 * last_hour.standings[1].distance).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,429 @@
+use crate::error::UursError;
+use crate::plans::SavedPlan;
+use crate::sessions::Session;
+use crate::track::{TrackPoint, read_track_log};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{Debug, Display};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Where the tracks, planning sessions, and shared plans are kept
+///
+/// The stores of the server hold their records in memory and only read them
+/// from the storage when they are created, then hand every change to it.
+/// Tracks are named by boat, `None` is the track of the server's own boat.
+pub trait Storage: Debug + Send + Sync {
+    /// Load the points of a track ordered by time
+    fn load_track(&self, boat: Option<&str>) -> Result<Vec<TrackPoint>, UursError>;
+    /// Add a point to a track
+    fn append_point(&self, boat: Option<&str>, point: &TrackPoint) -> Result<(), UursError>;
+    /// Load all planning sessions ordered by id
+    fn load_sessions(&self) -> Result<Vec<Session>, UursError>;
+    /// Add a session or replace the one with its id
+    fn save_session(&self, session: &Session) -> Result<(), UursError>;
+    /// Load all shared plans ordered by id
+    fn load_plans(&self) -> Result<Vec<SavedPlan>, UursError>;
+    /// Add a plan or replace the one with its id
+    fn save_plan(&self, plan: &SavedPlan) -> Result<(), UursError>;
+}
+
+/// Storage which only lives as long as the process
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    tracks: Mutex<BTreeMap<Option<String>, Vec<TrackPoint>>>,
+    sessions: Mutex<BTreeMap<u64, Session>>,
+    plans: Mutex<BTreeMap<String, SavedPlan>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl Storage for MemoryStorage {
+    fn load_track(&self, boat: Option<&str>) -> Result<Vec<TrackPoint>, UursError> {
+        let tracks = self.tracks.lock().unwrap();
+        Ok(tracks.get(&boat.map(str::to_string)).cloned().unwrap_or_default())
+    }
+
+    fn append_point(&self, boat: Option<&str>, point: &TrackPoint) -> Result<(), UursError> {
+        let mut tracks = self.tracks.lock().unwrap();
+        let points = tracks.entry(boat.map(str::to_string)).or_default();
+        let index = points.partition_point(|p| p.time <= point.time);
+        points.insert(index, point.clone());
+        Ok(())
+    }
+
+    fn load_sessions(&self) -> Result<Vec<Session>, UursError> {
+        Ok(self.sessions.lock().unwrap().values().cloned().collect())
+    }
+
+    fn save_session(&self, session: &Session) -> Result<(), UursError> {
+        self.sessions.lock().unwrap().insert(session.id, session.clone());
+        Ok(())
+    }
+
+    fn load_plans(&self) -> Result<Vec<SavedPlan>, UursError> {
+        Ok(self.plans.lock().unwrap().values().cloned().collect())
+    }
+
+    fn save_plan(&self, plan: &SavedPlan) -> Result<(), UursError> {
+        self.plans.lock().unwrap().insert(plan.id.clone(), plan.clone());
+        Ok(())
+    }
+}
+
+/// Storage in the files the server has always written: track logs with one
+/// JSON point per line, the track of a boat of the fleet next to the log of
+/// the own boat as `<log>.<boat>`, and a JSON file each for the sessions and
+/// the plans, rewritten on every change. Records without a file are not
+/// kept.
+#[derive(Debug, Default)]
+pub struct JsonFiles {
+    pub track_log: Option<PathBuf>,
+    pub sessions_file: Option<PathBuf>,
+    pub plans_file: Option<PathBuf>,
+}
+
+/// Contents of the sessions file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SessionFile {
+    next_id: u64,
+    sessions: BTreeMap<u64, Session>,
+}
+
+/// Contents of the plans file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct PlanFile {
+    plans: BTreeMap<String, SavedPlan>,
+}
+
+impl JsonFiles {
+    fn track_path(&self, boat: Option<&str>) -> Option<PathBuf> {
+        let log = self.track_log.as_ref()?;
+        Some(match boat {
+            Some(boat) => {
+                let mut path = log.clone().into_os_string();
+                path.push(format!(".{boat}"));
+                path.into()
+            }
+            None => log.clone(),
+        })
+    }
+
+    fn read_sessions(path: &Path) -> Result<SessionFile, UursError> {
+        if !path.exists() {
+            return Ok(SessionFile::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| storage_error(format!("{}: invalid sessions file: {e}", path.display())))
+    }
+
+    fn read_plans(path: &Path) -> Result<PlanFile, UursError> {
+        if !path.exists() {
+            return Ok(PlanFile::default());
+        }
+        let content = std::fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| storage_error(format!("{}: invalid plans file: {e}", path.display())))
+    }
+}
+
+// Error of a storage which failed to encode, decode, or keep a record
+fn storage_error(e: impl Display) -> UursError {
+    UursError::Storage(e.to_string())
+}
+
+// Write a file to a temporary one and move it over the old one, so a crash
+// never leaves a half written file behind
+fn replace_file(path: &Path, content: &impl Serialize) -> Result<(), UursError> {
+    let temporary = path.with_extension("tmp");
+    std::fs::write(&temporary, serde_json::to_string_pretty(content).map_err(storage_error)?)?;
+    std::fs::rename(&temporary, path)?;
+    Ok(())
+}
+
+impl Storage for JsonFiles {
+    fn load_track(&self, boat: Option<&str>) -> Result<Vec<TrackPoint>, UursError> {
+        match self.track_path(boat) {
+            Some(path) if path.exists() => read_track_log(&path),
+            _ => Ok(Vec::new()),
+        }
+    }
+
+    fn append_point(&self, boat: Option<&str>, point: &TrackPoint) -> Result<(), UursError> {
+        let Some(path) = self.track_path(boat) else {
+            return Ok(());
+        };
+        let mut line = serde_json::to_string(point).map_err(storage_error)?;
+        line.push('\n');
+        OpenOptions::new().create(true).append(true).open(path)?.write_all(line.as_bytes())?;
+        Ok(())
+    }
+
+    fn load_sessions(&self) -> Result<Vec<Session>, UursError> {
+        match &self.sessions_file {
+            Some(path) => Ok(Self::read_sessions(path)?.sessions.into_values().collect()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn save_session(&self, session: &Session) -> Result<(), UursError> {
+        let Some(path) = &self.sessions_file else {
+            return Ok(());
+        };
+        let mut file = Self::read_sessions(path)?;
+        file.next_id = file.next_id.max(session.id);
+        file.sessions.insert(session.id, session.clone());
+        replace_file(path, &file)
+    }
+
+    fn load_plans(&self) -> Result<Vec<SavedPlan>, UursError> {
+        match &self.plans_file {
+            Some(path) => Ok(Self::read_plans(path)?.plans.into_values().collect()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn save_plan(&self, plan: &SavedPlan) -> Result<(), UursError> {
+        let Some(path) = &self.plans_file else {
+            return Ok(());
+        };
+        let mut file = Self::read_plans(path)?;
+        file.plans.insert(plan.id.clone(), plan.clone());
+        replace_file(path, &file)
+    }
+}
+
+/// Storage in a single SQLite database, with the records as JSON in tables
+/// of track points, sessions, and plans
+/// Records which are only kept in memory under increasing ids, like the
+/// training games and the explorations, the oldest ones dropped beyond `MAX`
+#[derive(Debug)]
+pub struct Registry<T, const MAX: usize> {
+    records: Mutex<Records<T>>,
+}
+
+#[derive(Debug)]
+struct Records<T> {
+    next_id: u64,
+    records: BTreeMap<u64, T>,
+}
+
+impl<T, const MAX: usize> Default for Registry<T, MAX> {
+    fn default() -> Self {
+        Self {
+            records: Mutex::new(Records {
+                next_id: 0,
+                records: BTreeMap::new(),
+            }),
+        }
+    }
+}
+
+impl<T: Clone, const MAX: usize> Registry<T, MAX> {
+    /// Add the record made for the next id
+    pub fn insert(&self, record: impl FnOnce(u64) -> T) -> T {
+        let mut records = self.records.lock().unwrap();
+        records.next_id += 1;
+        let id = records.next_id;
+        let record = record(id);
+        records.records.insert(id, record.clone());
+        while records.records.len() > MAX {
+            records.records.pop_first();
+        }
+        record
+    }
+
+    /// Get a record by id
+    pub fn get(&self, id: u64) -> Option<T> {
+        self.records.lock().unwrap().records.get(&id).cloned()
+    }
+
+    /// Change a record, `None` if there is none with the id
+    pub fn update<R, E>(&self, id: u64, change: impl FnOnce(&mut T) -> Result<R, E>) -> Result<Option<(T, R)>, E> {
+        let mut records = self.records.lock().unwrap();
+        let Some(record) = records.records.get_mut(&id) else {
+            return Ok(None);
+        };
+        let result = change(record)?;
+        Ok(Some((record.clone(), result)))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+#[derive(Debug)]
+pub struct SqliteStorage {
+    connection: Mutex<rusqlite::Connection>,
+}
+
+#[cfg(feature = "sqlite")]
+impl SqliteStorage {
+    /// Open a database, creating it and its tables if they don't exist yet
+    pub fn open(path: &Path) -> Result<Self, UursError> {
+        let connection = rusqlite::Connection::open(path).map_err(storage_error)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS track_points (
+                 boat TEXT NOT NULL, -- empty for the server's own boat
+                 time REAL NOT NULL,
+                 point TEXT NOT NULL
+             );
+             CREATE INDEX IF NOT EXISTS track_points_by_boat ON track_points (boat, time);
+             CREATE TABLE IF NOT EXISTS sessions (id INTEGER PRIMARY KEY, session TEXT NOT NULL);
+             CREATE TABLE IF NOT EXISTS plans (id TEXT PRIMARY KEY, plan TEXT NOT NULL);",
+        )
+        .map_err(storage_error)?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+        })
+    }
+
+    // Rows of a query with a single column of JSON records
+    fn records<T: serde::de::DeserializeOwned>(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> Result<Vec<T>, UursError> {
+        let connection = self.connection.lock().unwrap();
+        let mut statement = connection.prepare(sql).map_err(storage_error)?;
+        let rows = statement.query_map(params, |row| row.get::<_, String>(0)).map_err(storage_error)?;
+        let mut records = Vec::new();
+        for row in rows {
+            records.push(serde_json::from_str(&row.map_err(storage_error)?).map_err(storage_error)?);
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl Storage for SqliteStorage {
+    fn load_track(&self, boat: Option<&str>) -> Result<Vec<TrackPoint>, UursError> {
+        self.records(
+            "SELECT point FROM track_points WHERE boat = ?1 ORDER BY time, rowid",
+            [boat.unwrap_or_default()],
+        )
+    }
+
+    fn append_point(&self, boat: Option<&str>, point: &TrackPoint) -> Result<(), UursError> {
+        self.connection.lock().unwrap().execute(
+            "INSERT INTO track_points (boat, time, point) VALUES (?1, ?2, ?3)",
+            rusqlite::params![boat.unwrap_or_default(), point.time, serde_json::to_string(point).map_err(storage_error)?],
+        )
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    fn load_sessions(&self) -> Result<Vec<Session>, UursError> {
+        self.records("SELECT session FROM sessions ORDER BY id", [])
+    }
+
+    fn save_session(&self, session: &Session) -> Result<(), UursError> {
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO sessions (id, session) VALUES (?1, ?2)",
+            rusqlite::params![session.id as i64, serde_json::to_string(session).map_err(storage_error)?],
+        )
+        .map_err(storage_error)?;
+        Ok(())
+    }
+
+    fn load_plans(&self) -> Result<Vec<SavedPlan>, UursError> {
+        self.records("SELECT plan FROM plans ORDER BY id", [])
+    }
+
+    fn save_plan(&self, plan: &SavedPlan) -> Result<(), UursError> {
+        self.connection.lock().unwrap().execute(
+            "INSERT OR REPLACE INTO plans (id, plan) VALUES (?1, ?2)",
+            rusqlite::params![plan.id, serde_json::to_string(plan).map_err(storage_error)?],
+        )
+        .map_err(storage_error)?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::plans::PlanInput;
+    use crate::sessions::SessionInput;
+    use chrono::Utc;
+
+    // Store a few records and read them back from a second storage opened
+    // on the same place
+    fn check_storage(storage: &dyn Storage, reopened: &dyn Storage) {
+        let point = |time: f64| TrackPoint {
+            lat: 52.9,
+            long: 5.1,
+            time,
+            speed: None,
+            course: None,
+            received: Utc::now(),
+        };
+        storage.append_point(None, &point(2.0)).unwrap();
+        storage.append_point(None, &point(1.0)).unwrap();
+        storage.append_point(Some("Zeezwaluw"), &point(3.0)).unwrap();
+
+        let session = |notes: &str| Session {
+            id: 1,
+            input: SessionInput {
+                name: "Night watch".to_string(),
+                start: None,
+                start_time: None,
+                pinned_legs: Vec::new(),
+                completed_legs: Vec::new(),
+                notes: notes.to_string(),
+            },
+            created: Utc::now(),
+            updated: Utc::now(),
+        };
+        storage.save_session(&session("")).unwrap();
+        storage.save_session(&session("Reef before midnight")).unwrap();
+        storage
+            .save_plan(&SavedPlan {
+                id: "abc23456".to_string(),
+                input: PlanInput {
+                    name: "Night route".to_string(),
+                    route: vec!["OEVE".to_string(), "WV12".to_string()],
+                    start_time: 1.5,
+                    boat: None,
+                    notes: String::new(),
+                },
+                created: Utc::now(),
+            })
+            .unwrap();
+
+        let storage = reopened;
+        let times: Vec<f64> = storage.load_track(None).unwrap().iter().map(|p| p.time).collect();
+        assert_eq!(times, [1.0, 2.0]);
+        assert_eq!(storage.load_track(Some("Zeezwaluw")).unwrap().len(), 1);
+        assert!(storage.load_track(Some("Waterhoen")).unwrap().is_empty());
+        let sessions = storage.load_sessions().unwrap();
+        assert_eq!(sessions.len(), 1);
+        assert_eq!(sessions[0].input.notes, "Reef before midnight");
+        assert_eq!(storage.load_plans().unwrap()[0].input.route, ["OEVE", "WV12"]);
+    }
+
+    #[test]
+    fn test_storages_keep_records() {
+        let memory = MemoryStorage::new();
+        check_storage(&memory, &memory);
+
+        let directory = std::env::temp_dir().join(format!("uurs24-storage-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let files = || JsonFiles {
+            track_log: Some(directory.join("track.jsonl")),
+            sessions_file: Some(directory.join("sessions.json")),
+            plans_file: Some(directory.join("plans.json")),
+        };
+        check_storage(&files(), &files());
+
+        #[cfg(feature = "sqlite")]
+        {
+            let database = directory.join("uurs24.db");
+            check_storage(&SqliteStorage::open(&database).unwrap(), &SqliteStorage::open(&database).unwrap());
+        }
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,460 @@
+use crate::data::RegattaData;
+use crate::error::UursError;
+use crate::geo::distance_nm;
+use crate::params::RaceHours;
+use crate::storage::{JsonFiles, MemoryStorage, Storage};
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+/// Conversion factor of m/s, the speed unit of GPX and some instruments, to knots
+pub const KNOTS_PER_MS: f64 = 1.943844;
+
+/// A reported position of the boat
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct TrackPoint {
+    pub lat: f64,            // in decimal degrees, north positive
+    pub long: f64,           // in decimal degrees, east positive
+    pub time: f64,           // in hours since race start
+    pub speed: Option<f64>,  // speed over ground in knots, if reported
+    pub course: Option<f64>, // course over ground in degrees, if reported
+    pub received: DateTime<Utc>,
+}
+
+impl TrackPoint {
+    /// Check that the position and time are in range
+    pub fn validate(&self, data: &RegattaData) -> Result<(), String> {
+        if !(-90.0..=90.0).contains(&self.lat) {
+            return Err(format!("Latitude {} must be between -90 and 90 degrees", self.lat));
+        }
+        if !(-180.0..=180.0).contains(&self.long) {
+            return Err(format!("Longitude {} must be between -180 and 180 degrees", self.long));
+        }
+        RaceHours::new(data, self.time).map_err(|e| e.to_string())?;
+        if self.speed.is_some_and(|speed| !(0.0..=100.0).contains(&speed)) {
+            return Err("Speed must be between 0 and 100 knots".to_string());
+        }
+        if self.course.is_some_and(|course| !(0.0..=360.0).contains(&course)) {
+            return Err("Course must be between 0 and 360 degrees".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// The track sailed so far, ordered by race time, with every point handed to
+/// a storage
+#[derive(Debug)]
+pub struct Track {
+    points: Mutex<Vec<TrackPoint>>,
+    storage: Arc<dyn Storage>,
+    boat: Option<String>, // boat of the fleet, `None` for the server's own boat
+}
+
+impl Default for Track {
+    fn default() -> Self {
+        Self {
+            points: Mutex::default(),
+            storage: Arc::new(MemoryStorage::new()),
+            boat: None,
+        }
+    }
+}
+
+impl Track {
+    /// Create an empty track which is only kept in memory
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Open a track log file, loading the points already in it, and append
+    /// new points to it
+    pub fn open(path: &Path) -> Result<Self, UursError> {
+        let storage = JsonFiles {
+            track_log: Some(path.to_path_buf()),
+            ..JsonFiles::default()
+        };
+        Self::with_storage(Arc::new(storage), None)
+    }
+
+    /// Load the track of a boat, or of the server's own boat for `None`, from
+    /// a storage and keep adding its points to it
+    pub fn with_storage(storage: Arc<dyn Storage>, boat: Option<&str>) -> Result<Self, UursError> {
+        let mut points = storage.load_track(boat)?;
+        points.sort_by(|a, b| a.time.total_cmp(&b.time));
+        Ok(Self {
+            points: Mutex::new(points),
+            storage,
+            boat: boat.map(str::to_string),
+        })
+    }
+
+    /// Add a point, keeping the track ordered by time, and return the number
+    /// of points in the track. The point is stored before it is added, so a
+    /// failure to store it leaves the track unchanged.
+    pub fn add(&self, point: TrackPoint) -> Result<usize, UursError> {
+        self.storage.append_point(self.boat.as_deref(), &point)?;
+
+        let mut points = self.points.lock().unwrap();
+        let index = points.partition_point(|p| p.time <= point.time);
+        points.insert(index, point);
+        Ok(points.len())
+    }
+
+    /// Get the points at or after a race time
+    pub fn points_since(&self, since: f64) -> Vec<TrackPoint> {
+        let points = self.points.lock().unwrap();
+        let start = points.partition_point(|p| p.time < since);
+        points[start..].to_vec()
+    }
+}
+
+/// Read the points of a track log with one JSON point per line, ordered by time
+pub(crate) fn read_track_log(path: &Path) -> Result<Vec<TrackPoint>, UursError> {
+    let mut points = Vec::new();
+    let reader = BufReader::new(File::open(path)?);
+    for (line_number, line) in reader.lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let point: TrackPoint = serde_json::from_str(&line).map_err(|e| {
+            invalid_track(format!("{}:{}: invalid track point: {e}", path.display(), line_number + 1))
+        })?;
+        points.push(point);
+    }
+    points.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(points)
+}
+
+/// A position read from a recorded track before its race time is known
+struct RecordedPoint {
+    lat: f64,
+    long: f64,
+    time: RecordedTime,
+    speed: Option<f64>,  // in knots
+    course: Option<f64>, // in degrees
+}
+
+enum RecordedTime {
+    Race(f64), // in hours since race start
+    Stamp(DateTime<FixedOffset>),
+}
+
+/// Read a track recorded on board, chosen by the extension of the file: GPX
+/// track points (`.gpx`), a CSV table (`.csv`), or a track log as written by
+/// the server with one JSON point per line
+///
+/// CSV files need `lat` and `long` columns (`latitude`, `lon` and `longitude`
+/// work as well) and either a `time` column in hours since race start or a
+/// `timestamp` column in RFC 3339. `speed` in knots and `course` in degrees
+/// are optional. Timestamps are converted with the race clock, positions
+/// before the start or after the deadline of the rules are dropped.
+pub fn read_track_file(path: &Path, data: &RegattaData) -> Result<Vec<TrackPoint>, UursError> {
+    let extension = path
+        .extension()
+        .map(|e| e.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let recorded = match extension.as_str() {
+        "gpx" => parse_gpx_track(&std::fs::read_to_string(path)?)?,
+        "csv" => parse_csv_track(&std::fs::read_to_string(path)?)?,
+        _ => return read_track_log(path),
+    };
+    if recorded.is_empty() {
+        return Err(invalid_track(format!("{}: no track points found", path.display())));
+    }
+    to_race_time(recorded, data)
+}
+
+/// Convert recorded points to track points in race time, ordered by time
+///
+/// Race hours are elapsed hours, so timestamps only pass the race clock once:
+/// the first one fixes the start of the race, assuming a recording begins
+/// at most an hour before the start, and the others are counted from it.
+fn to_race_time(recorded: Vec<RecordedPoint>, data: &RegattaData) -> Result<Vec<TrackPoint>, UursError> {
+    let anchor = recorded.iter().find_map(|point| match point.time {
+        RecordedTime::Stamp(stamp) => Some(stamp),
+        RecordedTime::Race(_) => None,
+    });
+    let start = match anchor {
+        Some(stamp) => {
+            let mut time = data
+                .race_clock
+                .race_time_of(&stamp)
+                .ok_or_else(|| invalid_track("The race clock doesn't cover the time of the track"))?;
+            if time > 23.0 {
+                time -= 24.0;
+            }
+            Some(stamp - chrono::Duration::milliseconds((time * 3_600_000.0).round() as i64))
+        }
+        None => None,
+    };
+
+    let mut points = Vec::with_capacity(recorded.len());
+    for point in recorded {
+        let (time, received) = match (point.time, start) {
+            (RecordedTime::Race(time), _) => (time, Utc::now()),
+            (RecordedTime::Stamp(stamp), Some(start)) => (
+                (stamp - start).num_milliseconds() as f64 / 3_600_000.0,
+                stamp.with_timezone(&Utc),
+            ),
+            (RecordedTime::Stamp(_), None) => unreachable!("a timestamp sets the start"),
+        };
+        if !(0.0..=data.rules.scoring.deadline).contains(&time) {
+            continue;
+        }
+        let point = TrackPoint {
+            lat: point.lat,
+            long: point.long,
+            time,
+            speed: point.speed,
+            course: point.course,
+            received,
+        };
+        point.validate(data).map_err(invalid_track)?;
+        points.push(point);
+    }
+    points.sort_by(|a, b| a.time.total_cmp(&b.time));
+    Ok(points)
+}
+
+/// Parse the track points of a GPX file, which must carry a time. Speed in
+/// m/s and course are read if present.
+fn parse_gpx_track(content: &str) -> Result<Vec<RecordedPoint>, UursError> {
+    let mut points = Vec::new();
+    let mut rest = content;
+    while let Some(start) = rest.find("<trkpt") {
+        rest = &rest[start..];
+        let tag_end = rest.find('>').ok_or_else(|| invalid_track("GPX: unterminated <trkpt> element"))?;
+        let tag = &rest[..tag_end];
+        let (body, next) = if tag.ends_with('/') {
+            ("", &rest[tag_end + 1..])
+        } else {
+            let end = rest.find("</trkpt>").ok_or_else(|| invalid_track("GPX: missing </trkpt>"))?;
+            (&rest[tag_end + 1..end], &rest[end + "</trkpt>".len()..])
+        };
+
+        let lat = xml_attribute(tag, "lat").ok_or_else(|| invalid_track("GPX: track point without lat"))?;
+        let long = xml_attribute(tag, "lon").ok_or_else(|| invalid_track("GPX: track point without lon"))?;
+        let time = xml_element(body, "time").ok_or_else(|| invalid_track("GPX: track point without time"))?;
+        let number = |text: &str| {
+            text.parse::<f64>()
+                .map_err(|_| invalid_track(format!("GPX: invalid number '{text}'")))
+        };
+        let speed = xml_element(body, "speed").map(number).transpose()?;
+        let course = xml_element(body, "course").map(number).transpose()?;
+        points.push(RecordedPoint {
+            lat: number(lat)?,
+            long: number(long)?,
+            time: RecordedTime::Stamp(
+                DateTime::parse_from_rfc3339(time)
+                    .map_err(|e| invalid_track(format!("GPX: invalid time '{time}': {e}")))?,
+            ),
+            speed: speed.map(|speed| speed * KNOTS_PER_MS),
+            course,
+        });
+        rest = next;
+    }
+    Ok(points)
+}
+
+/// Error of a malformed recorded track
+fn invalid_track(message: impl Into<String>) -> UursError {
+    UursError::InvalidTrack(message.into())
+}
+
+/// Value of an attribute of an XML start tag
+fn xml_attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    ["\"", "'"].iter().find_map(|quote| {
+        let pattern = format!(" {name}={quote}");
+        let start = tag.find(&pattern)? + pattern.len();
+        let end = tag[start..].find(quote)?;
+        Some(&tag[start..start + end])
+    })
+}
+
+/// Text of the first child element with a name, ignoring namespace prefixes
+/// such as `<gpxtpx:speed>`
+fn xml_element<'a>(body: &'a str, name: &str) -> Option<&'a str> {
+    let mut rest = body;
+    while let Some(open) = rest.find('<') {
+        rest = &rest[open + 1..];
+        let close = rest.find('>')?;
+        let tag = &rest[..close];
+        let local = tag.rsplit(':').next().unwrap_or(tag);
+        if local == name {
+            let content = &rest[close + 1..];
+            return Some(content[..content.find("</")?].trim());
+        }
+    }
+    None
+}
+
+/// Parse a CSV track, see `read_track_file` for the columns
+fn parse_csv_track(content: &str) -> Result<Vec<RecordedPoint>, UursError> {
+    let mut reader = csv::Reader::from_reader(content.as_bytes());
+    let headers: Vec<String> = reader
+        .headers()
+        .map_err(|e| invalid_track(format!("CSV track: {e}")))?
+        .iter().map(|h| h.trim().to_lowercase()).collect();
+    let column = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+    let lat = column(&["lat", "latitude"]).ok_or_else(|| invalid_track("CSV track needs a lat column"))?;
+    let long = column(&["long", "lon", "longitude"]).ok_or_else(|| invalid_track("CSV track needs a long column"))?;
+    let time = column(&["time"]);
+    let timestamp = column(&["timestamp"]);
+    if time.is_none() && timestamp.is_none() {
+        return Err(invalid_track("CSV track needs a time or timestamp column"));
+    }
+    let speed = column(&["speed"]);
+    let course = column(&["course"]);
+
+    let mut points = Vec::new();
+    for (row, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| invalid_track(format!("CSV track row {}: {e}", row + 2)))?;
+        let field = |index: usize| record.get(index).map(str::trim).unwrap_or_default();
+        let number = |index: usize| {
+            field(index)
+                .parse::<f64>()
+                .map_err(|_| invalid_track(format!("CSV track row {}: invalid number '{}'", row + 2, field(index))))
+        };
+        let optional = |index: Option<usize>| match index {
+            Some(index) if !field(index).is_empty() => number(index).map(Some),
+            _ => Ok(None),
+        };
+        let time = match (time, timestamp) {
+            (Some(time), _) => RecordedTime::Race(number(time)?),
+            (None, Some(timestamp)) => RecordedTime::Stamp(
+                DateTime::parse_from_rfc3339(field(timestamp))
+                    .map_err(|e| invalid_track(format!("CSV track row {}: invalid timestamp: {e}", row + 2)))?,
+            ),
+            (None, None) => unreachable!("checked above"),
+        };
+        points.push(RecordedPoint {
+            lat: number(lat)?,
+            long: number(long)?,
+            time,
+            speed: optional(speed)?,
+            course: optional(course)?,
+        });
+    }
+    Ok(points)
+}
+
+/// Distance sailed along a track in nautical miles
+pub fn track_distance(points: &[TrackPoint]) -> f64 {
+    points
+        .windows(2)
+        .map(|pair| distance_nm((pair[0].lat, pair[0].long), (pair[1].lat, pair[1].long)))
+        .fold(0.0, |total, distance| total + distance)
+}
+
+/// A track along a path at its planned times, with ten points a leg, for the
+/// tests of the modules which check or score sailed tracks
+#[cfg(test)]
+pub(crate) fn track_along(data: &crate::data::RegattaData, path: &crate::optimize::Path) -> Vec<TrackPoint> {
+    let mut track = Vec::new();
+    for step in &path.steps {
+        let (from, to) = (data.boeien[step.from].coordinates().unwrap(), data.boeien[step.to].coordinates().unwrap());
+        for i in 0..=10 {
+            let t = i as f64 / 10.0;
+            track.push(TrackPoint {
+                lat: from.0 + t * (to.0 - from.0),
+                long: from.1 + t * (to.1 - from.1),
+                time: step.start_time + t * (step.end_time - step.start_time),
+                speed: None,
+                course: None,
+                received: Utc::now(),
+            });
+        }
+    }
+    track
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(lat: f64, long: f64, time: f64) -> TrackPoint {
+        TrackPoint {
+            lat,
+            long,
+            time,
+            speed: None,
+            course: None,
+            received: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_track_orders_points_by_time() {
+        let track = Track::new();
+        assert_eq!(track.add(point(52.9, 5.1, 1.0)).unwrap(), 1);
+        assert_eq!(track.add(point(53.0, 5.2, 2.0)).unwrap(), 2);
+        // A late report is inserted in time order
+        assert_eq!(track.add(point(52.95, 5.15, 1.5)).unwrap(), 3);
+
+        let times: Vec<f64> = track.points_since(0.0).iter().map(|p| p.time).collect();
+        assert_eq!(times, vec![1.0, 1.5, 2.0]);
+        assert_eq!(track.points_since(1.5).len(), 2);
+
+        // One minute of latitude is one nautical mile
+        let distance = track_distance(&[point(52.0, 5.0, 0.0), point(52.5, 5.0, 1.0)]);
+        assert!((distance - 30.0).abs() < 0.1);
+    }
+
+    #[test]
+    fn test_read_recorded_tracks() {
+        let mut data = RegattaData::default();
+        data.race_clock.entries = (0..25)
+            .map(|time| crate::data::ClockEntry {
+                time,
+                real: ((18 + time) % 24) * 100,
+            })
+            .collect();
+        let local = |hour: u32, minute: u32| {
+            use chrono::TimeZone;
+            chrono::Local
+                .with_ymd_and_hms(2025, 6, 7, hour, minute, 0)
+                .unwrap()
+                .fixed_offset()
+                .to_rfc3339()
+        };
+
+        // The point before the start is dropped, speeds are converted to knots
+        let gpx = format!(
+            r#"<gpx><trk><trkseg>
+            <trkpt lat="52.90" lon="5.10"><time>{}</time></trkpt>
+            <trkpt lat="52.95" lon="5.15"><time>{}</time><extensions><gpxtpx:speed>2.5</gpxtpx:speed></extensions></trkpt>
+            <trkpt lat='53.00' lon='5.20'><time>{}</time></trkpt>
+            </trkseg></trk></gpx>"#,
+            local(17, 30),
+            local(18, 30),
+            local(20, 0)
+        );
+        let points = to_race_time(parse_gpx_track(&gpx).unwrap(), &data).unwrap();
+        assert_eq!(points.len(), 2);
+        assert!((points[0].time - 0.5).abs() < 1e-9);
+        assert!((points[0].speed.unwrap() - 4.86).abs() < 0.01);
+        assert!((points[1].time - 2.0).abs() < 1e-9);
+        assert_eq!((points[1].lat, points[1].long), (53.0, 5.2));
+
+        let csv = "Latitude,Longitude,time,speed\n53.0,5.2,2.0,\n52.9,5.1,1.0,5.5\n";
+        let points = to_race_time(parse_csv_track(csv).unwrap(), &data).unwrap();
+        assert_eq!(points.iter().map(|p| p.time).collect::<Vec<_>>(), vec![1.0, 2.0]);
+        assert_eq!(points[0].speed, Some(5.5));
+        assert!(parse_csv_track("lat,long\n52.9,5.1\n").is_err());
+    }
+
+    #[test]
+    fn test_track_point_validation() {
+        let mut data = RegattaData::default();
+        assert!(point(52.9, 5.1, 1.0).validate(&data).is_ok());
+        assert!(point(91.0, 5.1, 1.0).validate(&data).is_err());
+        assert!(point(52.9, 181.0, 1.0).validate(&data).is_err());
+        assert!(point(52.9, 5.1, 25.0).validate(&data).is_err());
+        data.rules.scoring.deadline = 26.0;
+        assert!(point(52.9, 5.1, 25.0).validate(&data).is_ok());
+    }
+}
@@ -0,0 +1,185 @@
+use crate::data::RegattaData;
+use crate::error::UursError;
+use crate::optimize::{SearchConstraints, validate_route};
+use crate::params::RaceHours;
+use crate::simulate::{Simulation, SimulationOptions, simulate_route};
+use crate::storage::Registry;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Most games kept at the same time, the oldest ones are dropped beyond
+const MAX_GAMES: usize = 1000;
+
+/// A leg sailed in a training game
+#[derive(Clone, Debug, Serialize)]
+pub struct TrainingLeg {
+    pub from: String,
+    pub to: String,
+    pub distance: f64,   // in nm
+    pub start_time: f64, // in hours since race start
+    pub end_time: f64,
+    pub scored: bool,    // false for a leg which ended after the end of the race
+}
+
+/// A leg the navigator may choose next
+#[derive(Clone, Debug, Serialize)]
+pub struct TrainingChoice {
+    pub to: String,
+    pub distance: f64, // in nm
+}
+
+/// A virtual regatta in which a navigator sails the course leg by leg, each
+/// leg simulated in the forecast wind
+///
+/// The buoys are kept by name, so a game survives a reload of the data.
+#[derive(Clone, Debug, Serialize)]
+pub struct TrainingGame {
+    pub id: u64,
+    pub route: Vec<String>, // buoys rounded so far, starting with the start buoy
+    pub time: f64,          // in hours since race start
+    pub legs: Vec<TrainingLeg>,
+    pub finished: bool,     // the race is over or there is no leg left to sail
+    pub created: DateTime<Utc>,
+}
+
+impl TrainingGame {
+    /// Distance of the legs which ended within the race, the score of the race
+    pub fn score(&self) -> f64 {
+        self.legs
+            .iter()
+            .filter(|leg| leg.scored)
+            .fold(0.0, |score, leg| score + leg.distance)
+    }
+
+    /// The legs which may be sailed next: starts and legs from the current
+    /// buoy which are not sailed as often as allowed already, ordered by name
+    pub fn choices(&self, data: &RegattaData) -> Vec<TrainingChoice> {
+        if self.finished {
+            return Vec::new();
+        }
+        let Ok(mut route) = data.route_indices(&self.route) else {
+            return Vec::new();
+        };
+        let current = route[route.len() - 1];
+        let mut choices = Vec::new();
+        for (&(from, to), &distance) in &data.leg_distances {
+            if from != current {
+                continue;
+            }
+            route.push(to);
+            if validate_route(data, &route, &SearchConstraints::default()).is_ok() {
+                choices.push(TrainingChoice {
+                    to: data.boeien[to].name.clone(),
+                    distance,
+                });
+            }
+            route.pop();
+        }
+        choices.sort_by(|a, b| a.to.cmp(&b.to));
+        choices
+    }
+
+    /// Sail from the current buoy to the next one and return the simulation
+    /// of the leg. A leg which ends after the end of the race doesn't count
+    /// and ends the game.
+    pub fn sail(
+        &mut self,
+        data: &RegattaData,
+        to: &str,
+        options: &SimulationOptions,
+    ) -> Result<Simulation, UursError> {
+        if self.finished {
+            return Err(UursError::InvalidParameter("The game is over".to_string()));
+        }
+        let mut route = data.route_indices(&self.route)?;
+        let from = route[route.len() - 1];
+        route.push(data.get_boei_index(to).ok_or_else(|| UursError::UnknownBuoy(to.to_string()))?);
+        validate_route(data, &route, &SearchConstraints::default())?;
+
+        let simulation = simulate_route(data, &route[route.len() - 2..], self.time, options)?;
+        let scored = simulation.end_time <= data.rules.scoring.deadline;
+        self.legs.push(TrainingLeg {
+            from: data.boeien[from].name.clone(),
+            to: to.to_string(),
+            distance: simulation.total_distance,
+            start_time: self.time,
+            end_time: simulation.end_time,
+            scored,
+        });
+        self.route.push(to.to_string());
+        self.time = simulation.end_time;
+        self.finished = !scored || self.choices(data).is_empty();
+        Ok(simulation)
+    }
+}
+
+/// The training games of all navigators, kept in memory only
+#[derive(Debug, Default)]
+pub struct TrainingGames {
+    games: Registry<TrainingGame, MAX_GAMES>,
+}
+
+impl TrainingGames {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start a game at a start buoy at a time in hours since race start
+    pub fn start(&self, data: &RegattaData, start: &str, time: f64) -> Result<TrainingGame, UursError> {
+        if !data.starts.iter().any(|s| s.from == start) {
+            return Err(UursError::InvalidParameter(format!("'{start}' is not a start buoy")));
+        }
+        let time = RaceHours::new(data, time)?.hours();
+        Ok(self.games.insert(|id| TrainingGame {
+            id,
+            route: vec![start.to_string()],
+            time,
+            legs: Vec::new(),
+            finished: false,
+            created: Utc::now(),
+        }))
+    }
+
+    /// Get a game by id
+    pub fn get(&self, id: u64) -> Option<TrainingGame> {
+        self.games.get(id)
+    }
+
+    /// Sail the next leg of a game, `None` if there is no game with the id
+    pub fn sail(
+        &self,
+        id: u64,
+        data: &RegattaData,
+        to: &str,
+        options: &SimulationOptions,
+    ) -> Result<Option<(TrainingGame, Simulation)>, UursError> {
+        self.games.update(id, |game| game.sail(data, to, options))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_training_game() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let games = TrainingGames::new();
+        assert!(games.start(&data, "WV12", 0.0).is_err());
+
+        let game = games.start(&data, "OEVE", 0.0).unwrap();
+        let choices = game.choices(&data);
+        assert_eq!(choices.len(), 1);
+        assert_eq!(choices[0].to, "WV12");
+
+        let options = SimulationOptions::default();
+        assert!(games.sail(game.id, &data, "GVS3-M8", &options).is_err());
+        let (game, simulation) = games.sail(game.id, &data, "WV12", &options).unwrap().unwrap();
+        assert_eq!(game.route, ["OEVE", "WV12"]);
+        assert_eq!(game.time, simulation.end_time);
+        assert!((game.score() - 2.716).abs() < 1e-9);
+        // The start can't be sailed again
+        assert!(game.choices(&data).iter().all(|choice| choice.to != "OEVE"));
+        assert!(games.sail(game.id + 1, &data, "WV12", &options).unwrap().is_none());
+    }
+}
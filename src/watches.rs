@@ -0,0 +1,208 @@
+use crate::data::RegattaData;
+use crate::error::UursError;
+use crate::optimize::{Path, estimate_leg_performance};
+use serde::Serialize;
+
+/// True wind angle in degrees up to which a leg is sailed close-hauled
+const BEAT_ANGLE: f64 = 60.0;
+
+/// Distance in hours between the times tried for a watch change
+const CANDIDATE_STEP: f64 = 5.0 / 60.0;
+
+/// Watches taking turns in blocks of equal length from the start of a route
+#[derive(Clone, Debug)]
+pub struct WatchRotation {
+    pub names: Vec<String>, // in the order they take over, the first one starts
+    pub length: f64,        // of a block in hours
+}
+
+impl WatchRotation {
+    /// The regular blocks of the rotation from `start` to `end`, the last one
+    /// cut short at `end`
+    pub fn blocks(&self, start: f64, end: f64) -> Result<Vec<WatchBlock>, UursError> {
+        if self.names.is_empty() || self.length <= 0.0 {
+            return Err(UursError::InvalidParameter(
+                "A watch rotation needs at least one watch and a positive length".to_string(),
+            ));
+        }
+        let mut blocks = Vec::new();
+        let mut block_start = start;
+        while block_start < end {
+            let block_end = (block_start + self.length).min(end);
+            blocks.push(WatchBlock {
+                watch: self.names[blocks.len() % self.names.len()].clone(),
+                start: block_start,
+                end: block_end,
+            });
+            block_start = block_end;
+        }
+        Ok(blocks)
+    }
+}
+
+/// A watch on deck from `start` to `end`
+#[derive(Clone, Debug, Serialize)]
+pub struct WatchBlock {
+    pub watch: String,
+    pub start: f64, // in hours since race start
+    pub end: f64,
+}
+
+/// How far watch changes may move to stay clear of roundings and beats
+#[derive(Clone, Debug, Serialize)]
+pub struct WatchPlanOptions {
+    pub rounding_margin: f64, // hours before and after a rounding without watch change
+    pub max_shift: f64,       // hours a change may move away from its regular time
+}
+
+impl Default for WatchPlanOptions {
+    fn default() -> Self {
+        Self {
+            rounding_margin: 0.25,
+            max_shift: 0.5,
+        }
+    }
+}
+
+/// A handover from one watch to the next
+#[derive(Clone, Debug, Serialize)]
+pub struct WatchChange {
+    pub time: f64,         // in hours since race start
+    pub regular_time: f64, // of the change in the regular rotation
+    pub from: String,
+    pub to: String,
+    pub conflict: Option<String>, // what is going on at the change if none could be avoided
+}
+
+/// Watch schedule of a route
+#[derive(Clone, Debug, Serialize)]
+pub struct WatchPlan {
+    pub blocks: Vec<WatchBlock>,
+    pub changes: Vec<WatchChange>,
+}
+
+/// What is going on at a time of the route which makes a bad moment for a
+/// watch change, with a penalty: a rounding weighs more than a beat
+fn conflict_at(data: &RegattaData, path: &Path, time: f64, options: &WatchPlanOptions) -> Option<(u32, String)> {
+    if let Some(step) = path
+        .steps
+        .iter()
+        .find(|step| (step.end_time - time).abs() < options.rounding_margin)
+    {
+        let clock = data.race_clock.format_clock(step.end_time).unwrap_or_else(|| format!("{:.2}h", step.end_time));
+        return Some((2, format!("rounding {} at {clock}", data.boeien[step.to].name)));
+    }
+    let step = path.steps.iter().find(|step| step.start_time <= time && time < step.end_time)?;
+    let performance = estimate_leg_performance(data, step.from, step.to, time).ok()?;
+    (performance.relative_bearing <= BEAT_ANGLE).then(|| {
+        (
+            1,
+            format!("beat from {} to {}", data.boeien[step.from].name, data.boeien[step.to].name),
+        )
+    })
+}
+
+/// Plan the watches of a route
+///
+/// Starting from the regular rotation, every change moves by at most
+/// `max_shift` to the closest time which is neither near a rounding nor on a
+/// beat. If there is no such time, the change goes where it disturbs least,
+/// on a beat rather than at a rounding, and reports the conflict. A change
+/// never moves closer than half a block to the previous one.
+pub fn plan_watches(
+    data: &RegattaData,
+    path: &Path,
+    rotation: &WatchRotation,
+    options: &WatchPlanOptions,
+) -> Result<WatchPlan, UursError> {
+    if options.rounding_margin < 0.0 || options.max_shift < 0.0 {
+        return Err(UursError::InvalidParameter(
+            "The rounding margin and the shift of watch changes can't be negative".to_string(),
+        ));
+    }
+    let Some(first) = path.steps.first() else {
+        return Ok(WatchPlan {
+            blocks: Vec::new(),
+            changes: Vec::new(),
+        });
+    };
+    let regular = rotation.blocks(first.start_time, path.end_time)?;
+
+    let mut blocks: Vec<WatchBlock> = Vec::with_capacity(regular.len());
+    let mut changes = Vec::new();
+    for (i, block) in regular.iter().enumerate() {
+        if i == 0 {
+            blocks.push(block.clone());
+            continue;
+        }
+        let previous = blocks.last_mut().unwrap();
+        let earliest = (block.start - options.max_shift).max(previous.start + rotation.length / 2.0);
+        let latest = (block.start + options.max_shift).min(block.end);
+
+        let mut candidates = vec![block.start];
+        let mut shift = CANDIDATE_STEP;
+        while shift <= options.max_shift + 1e-9 {
+            candidates.extend([block.start - shift, block.start + shift]);
+            shift += CANDIDATE_STEP;
+        }
+        let (time, conflict) = candidates
+            .into_iter()
+            .filter(|&time| (earliest..latest).contains(&time) || time == block.start)
+            .map(|time| (time, conflict_at(data, path, time, options)))
+            .min_by(|(a, a_conflict), (b, b_conflict)| {
+                let penalty = |conflict: &Option<(u32, String)>| conflict.as_ref().map_or(0, |c| c.0);
+                penalty(a_conflict)
+                    .cmp(&penalty(b_conflict))
+                    .then((a - block.start).abs().total_cmp(&(b - block.start).abs()))
+            })
+            .unwrap();
+
+        previous.end = time;
+        changes.push(WatchChange {
+            time,
+            regular_time: block.start,
+            from: previous.watch.clone(),
+            to: block.watch.clone(),
+            conflict: conflict.map(|c| c.1),
+        });
+        blocks.push(WatchBlock {
+            watch: block.watch.clone(),
+            start: time,
+            end: block.end,
+        });
+    }
+    Ok(WatchPlan { blocks, changes })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_watches() {
+        let data = crate::data::load_regatta_data().unwrap();
+        let route = ["OEVE", "WV12", "GVS3-M8"].map(|name| data.get_boei_index(name).unwrap());
+        let path = crate::optimize::evaluate_route(&data, &route, 0.0).unwrap();
+        let rotation = WatchRotation {
+            names: vec!["A".to_string(), "B".to_string()],
+            length: 0.5,
+        };
+        let options = WatchPlanOptions::default();
+        let plan = plan_watches(&data, &path, &rotation, &options).unwrap();
+
+        // The regular change at 0.5h is 8 minutes after the rounding of WV12
+        let change = &plan.changes[0];
+        assert_eq!((change.from.as_str(), change.to.as_str()), ("A", "B"));
+        assert!((change.time - path.steps[0].end_time).abs() >= options.rounding_margin);
+        assert!((change.time - change.regular_time).abs() <= options.max_shift + 1e-9);
+        assert!(change.conflict.is_none());
+
+        // The blocks cover the route without gaps
+        assert_eq!(plan.blocks[0].start, path.steps[0].start_time);
+        assert_eq!(plan.blocks.last().unwrap().end, path.end_time);
+        assert!(plan.blocks.windows(2).all(|pair| pair[0].end == pair[1].start));
+        assert_eq!(plan.blocks.len(), plan.changes.len() + 1);
+
+        assert!(plan_watches(&data, &path, &WatchRotation { names: Vec::new(), length: 3.0 }, &options).is_err());
+    }
+}
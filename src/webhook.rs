@@ -0,0 +1,119 @@
+use http_body_util::Full;
+use hyper::body::Bytes;
+use hyper::{Method, Request, Uri, header};
+use hyper_rustls::{HttpsConnector, HttpsConnectorBuilder};
+use hyper_util::client::legacy::Client;
+use hyper_util::client::legacy::connect::HttpConnector;
+use hyper_util::rt::TokioExecutor;
+use std::time::Duration;
+
+/// Time allowed for delivering a notification
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An HTTP or HTTPS endpoint receiving JSON notifications. Certificates of
+/// HTTPS endpoints are checked against the Mozilla root certificates.
+#[derive(Clone, Debug)]
+pub struct Webhook {
+    uri: Uri,
+    client: Client<HttpsConnector<HttpConnector>, Full<Bytes>>,
+}
+
+impl PartialEq for Webhook {
+    fn eq(&self, other: &Self) -> bool {
+        self.uri == other.uri
+    }
+}
+
+impl Webhook {
+    /// Parse a URL of the form `http[s]://host[:port][/path]`
+    pub fn parse(url: &str) -> Result<Self, String> {
+        let uri: Uri = url.parse().map_err(|e| format!("Invalid webhook URL '{url}': {e}"))?;
+        if !matches!(uri.scheme_str(), Some("http" | "https")) {
+            return Err(format!("Webhook URL '{url}' must start with http:// or https://"));
+        }
+        if uri.host().is_none_or(str::is_empty) {
+            return Err(format!("Webhook URL '{url}' has no host"));
+        }
+        // The URI parser drops a port that is not a number instead of failing
+        if let Some((_, port)) = uri.authority().and_then(|a| a.as_str().rsplit_once(':'))
+            && !port.ends_with(']')
+            && uri.port_u16().is_none()
+        {
+            return Err(format!("Invalid port '{port}' in webhook URL '{url}'"));
+        }
+        let connector = HttpsConnectorBuilder::new()
+            .with_webpki_roots()
+            .https_or_http()
+            .enable_http1()
+            .build();
+        Ok(Self {
+            uri,
+            client: Client::builder(TokioExecutor::new()).build(connector),
+        })
+    }
+
+    /// POST a JSON body, failing unless the endpoint answers with a 2xx status
+    pub async fn post(&self, body: &serde_json::Value) -> Result<(), String> {
+        tokio::time::timeout(TIMEOUT, self.send(body.to_string()))
+            .await
+            .map_err(|_| format!("No answer from {} within {TIMEOUT:?}", self.uri))?
+    }
+
+    async fn send(&self, body: String) -> Result<(), String> {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(self.uri.clone())
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Full::new(Bytes::from(body)))
+            .map_err(|e| format!("Building the notification failed: {e}"))?;
+        let response = self
+            .client
+            .request(request)
+            .await
+            .map_err(|e| format!("Sending the notification to {} failed: {e}", self.uri))?;
+        match response.status() {
+            status if status.is_success() => Ok(()),
+            status => Err(format!("Webhook answered '{status}'")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    #[test]
+    fn test_parse_webhook_url() {
+        let webhook = Webhook::parse("http://10.0.0.2:8080/hooks/uurs24").unwrap();
+        assert_eq!((webhook.uri.host(), webhook.uri.port_u16(), webhook.uri.path()), (Some("10.0.0.2"), Some(8080), "/hooks/uurs24"));
+        assert_eq!(Webhook::parse("http://relay.local").unwrap().uri.path(), "/");
+        assert_eq!(Webhook::parse("https://example.org/hook").unwrap().uri.scheme_str(), Some("https"));
+        assert!(Webhook::parse("ftp://example.org/hook").is_err());
+        assert!(Webhook::parse("http://host:port/").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_post_webhook() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buffer = [0; 1024];
+            while !String::from_utf8_lossy(&request).contains("\"alert\"") {
+                let n = stream.read(&mut buffer).await.unwrap();
+                request.extend_from_slice(&buffer[..n]);
+            }
+            stream.write_all(b"HTTP/1.1 204 No Content\r\n\r\n").await.unwrap();
+            String::from_utf8(request).unwrap()
+        });
+
+        let webhook = Webhook::parse(&format!("http://127.0.0.1:{port}/hooks")).unwrap();
+        webhook.post(&serde_json::json!({"alert": "test"})).await.unwrap();
+        let request = server.await.unwrap();
+        assert!(request.starts_with("POST /hooks HTTP/1.1\r\n"));
+        assert!(request.contains("content-type: application/json"));
+    }
+}
@@ -0,0 +1,743 @@
+//! End-to-end tests of the HTTP server on the small course in
+//! `tests/fixtures/small`: every kind of route is requested through
+//! `warp::test`, without listening on a port, and the status codes, content
+//! types, and response bodies are checked, so that a refactoring of the
+//! server can't change what the clients see unnoticed.
+
+#![cfg(feature = "server")]
+
+use serde_json::{Value, json};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use uurs24::data::{RegattaData, load_regatta_data};
+use uurs24::i18n::Lang;
+use uurs24::optimize::SearchBudget;
+use uurs24::server::{ServerConfig, routes};
+use warp::http::Response;
+use warp::Reply;
+use warp::test::RequestBuilder;
+
+/// The data of the fixture course, loaded once from its directory, which
+/// all tests of this file share as the working directory
+fn fixture() -> RegattaData {
+    static DATA: OnceLock<RegattaData> = OnceLock::new();
+    DATA.get_or_init(|| {
+        std::env::set_current_dir(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/small")).unwrap();
+        load_regatta_data().unwrap()
+    })
+    .clone()
+}
+
+async fn get(config: &ServerConfig, path: &str) -> Response<Vec<u8>> {
+    send(warp::test::request().path(path), config).await
+}
+
+async fn post(config: &ServerConfig, path: &str, body: &Value) -> Response<Vec<u8>> {
+    let request = warp::test::request()
+        .method("POST")
+        .path(path)
+        .json(body);
+    send(request, config).await
+}
+
+async fn send(request: RequestBuilder, config: &ServerConfig) -> Response<Vec<u8>> {
+    request.reply(&routes(fixture(), config.clone()).unwrap()).await.map(|body| body.to_vec())
+}
+
+fn content_type(response: &Response<Vec<u8>>) -> &str {
+    response.headers()["content-type"].to_str().unwrap()
+}
+
+/// The JSON body of a response with the expected status
+fn json_body(response: &Response<Vec<u8>>, status: u16) -> Value {
+    assert_eq!(response.status(), status, "{:?}", response.body());
+    assert_eq!(content_type(response), "application/json");
+    serde_json::from_slice(response.body()).unwrap()
+}
+
+/// The error response of a failed request, checking that its body names the
+/// status too
+fn error_body(response: &Response<Vec<u8>>, status: u16) -> Value {
+    let body = json_body(response, status);
+    assert_eq!(body["status"], status);
+    assert!(body["message"].is_string());
+    body
+}
+
+#[tokio::test]
+async fn test_pages() {
+    let config = ServerConfig::default();
+    for path in ["/", "/estimate", "/estimate-leg", "/find-paths", "/find-target", "/dashboard", "/next", "/finish", "/fleet", "/leaderboard", "/training", "/explore", "/map", "/wind"] {
+        let response = get(&config, path).await;
+        assert_eq!(response.status(), 200, "{path}");
+        assert!(content_type(&response).starts_with("text/html"), "{path}");
+    }
+
+    let version = json_body(&get(&config, "/version").await, 200);
+    assert_eq!(version["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(json_body(&get(&config, "/health").await, 200)["status"], "ok");
+    let openapi = json_body(&get(&config, "/api/openapi.json").await, 200);
+    assert!(openapi["paths"]["/api/estimate"].is_object());
+
+    let metrics = get(&config, "/metrics").await;
+    assert_eq!(metrics.status(), 200);
+    assert!(content_type(&metrics).starts_with("text/plain"));
+
+    // The course map is served once the plot command has written it
+    error_body(&get(&config, "/regatta-course.svg").await, 404);
+}
+
+#[tokio::test]
+async fn test_data() {
+    let config = ServerConfig::default();
+    let data = json_body(&get(&config, "/api/data").await, 200);
+    assert_eq!(data["buoys"].as_array().unwrap().len(), fixture().boeien.len());
+    assert_eq!(data["legs"].as_array().unwrap().len(), fixture().rakken.len());
+    let warnings = json_body(&get(&config, "/api/warnings").await, 200);
+    assert!(warnings.as_array().unwrap().iter().all(|w| w["kind"].is_string() && w["message"].is_string()));
+}
+
+#[tokio::test]
+async fn test_estimate() {
+    let config = ServerConfig::default();
+    let estimate = json_body(&get(&config, "/api/estimate?from=WV12&to=LC1&time=2").await, 200);
+    assert_eq!(estimate["from"], "WV12");
+    assert_eq!(estimate["to"], "LC1");
+    assert_eq!(estimate["time"], 2.0);
+    assert_eq!(estimate["official_distance"], 7.5983);
+    let (speed, travel_time) = (estimate["estimated_speed"].as_f64().unwrap(), estimate["travel_time"].as_f64().unwrap());
+    assert!(speed > 0.0 && (travel_time - 7.5983 / speed).abs() < 1e-9);
+
+    // The leg form of the endpoint, sailed backwards
+    let leg = json_body(&get(&config, "/api/estimateleg?from=WV12&to=LC1&reverse=true&time=2").await, 200);
+    assert_eq!(leg["from"], "LC1");
+    assert_eq!(leg["to"], "WV12");
+
+    // Only the starts and legs from a buoy are offered and estimated as legs
+    let neighbors = json_body(&get(&config, "/api/neighbors?buoy=OEVE").await, 200);
+    assert_eq!(neighbors, json!([{"from": "OEVE", "to": "WV12", "distance": neighbors[0]["distance"]}]));
+    error_body(&get(&config, "/api/neighbors?buoy=NOWHERE").await, 404);
+    let no_leg = error_body(&get(&config, "/api/estimateleg?from=OEVE&to=LC1&time=2").await, 422);
+    assert!(no_leg["message"].as_str().unwrap().ends_with("the legs go to WV12"));
+    error_body(&get(&config, "/api/estimate?from=WV12&to=WV12&time=2").await, 400);
+
+    let unknown = error_body(&get(&config, "/api/estimate?from=NOWHERE&to=LC1&time=2").await, 404);
+    assert!(unknown["message"].as_str().unwrap().contains("NOWHERE"));
+    error_body(&get(&config, "/api/estimate?from=WV12&to=LC1&time=99").await, 400);
+    error_body(&get(&config, "/api/estimate?from=WV12&to=LC1").await, 400);
+    error_body(&get(&config, "/api/estimate?from=WV12&to=LC1&time=soon").await, 400);
+}
+
+#[tokio::test]
+async fn test_languages() {
+    let config = ServerConfig::default();
+    let path = "/api/estimate?from=WV12&to=LC1&time=2";
+    let english = json_body(&get(&config, path).await, 200);
+    let request = warp::test::request().path(path).header("accept-language", "nl-NL,nl;q=0.9,en;q=0.8");
+    let dutch = json_body(&send(request, &config).await, 200);
+    assert_eq!(english["point_of_sail"], dutch["point_of_sail"]);
+    assert!(english["interpretation"].as_str().unwrap().starts_with("Sailing"));
+    assert_ne!(english["interpretation"], dutch["interpretation"]);
+
+    // `?lang=` goes before the cookie of the language links and the header
+    let request = warp::test::request()
+        .path("/estimate?lang=nl")
+        .header("cookie", "uurs24-lang=en")
+        .header("accept-language", "en");
+    let page = String::from_utf8(send(request, &config).await.into_body()).unwrap();
+    assert!(page.contains("<html lang=\"nl\">"));
+    assert!(page.contains("Snelheid schatten"));
+    let request = warp::test::request().path("/").header("cookie", "uurs24-lang=nl");
+    let page = String::from_utf8(send(request, &config).await.into_body()).unwrap();
+    assert!(page.contains("Hoofdmenu"));
+
+    let config = ServerConfig { lang: Some(Lang::Nl), ..ServerConfig::default() };
+    let request = warp::test::request().path("/").header("accept-language", "en");
+    let page = String::from_utf8(send(request, &config).await.into_body()).unwrap();
+    assert!(page.contains("Hoofdmenu"));
+}
+
+#[tokio::test]
+async fn test_find_paths() {
+    let config = ServerConfig::default();
+    let found = json_body(&get(&config, "/api/find-paths?start=OEVE&time=0&steps=3").await, 200);
+    assert_eq!(found["start"], "OEVE");
+    assert_eq!(found["steps"], 3);
+    assert_eq!(found["truncated"], false);
+    let paths = found["paths"].as_array().unwrap();
+    assert!(!paths.is_empty());
+    assert!(found.get("target").is_none());
+
+    // At the path limit the search says that there are more
+    let limited = json_body(&get(&config, "/api/find-paths?start=OEVE&time=0&steps=3&max_paths=1").await, 200);
+    assert!(paths.len() > 1);
+    assert_eq!(limited["paths"].as_array().unwrap().len(), 1);
+    assert_eq!(limited["truncated"], true);
+
+    // The server's budget stops a search with the paths found so far
+    assert_eq!(found["stats"]["resident_paths"], paths.len());
+    assert!(found["stats"]["exhausted"].is_null());
+    let budgeted = ServerConfig {
+        search_budget: SearchBudget { max_paths: 2, ..SearchBudget::default() },
+        ..ServerConfig::default()
+    };
+    let partial = json_body(&get(&budgeted, "/api/find-paths?start=OEVE&time=0&steps=3").await, 200);
+    assert_eq!(partial["paths"].as_array().unwrap().len(), 2);
+    assert_eq!(partial["truncated"], true);
+    assert_eq!(partial["stats"]["exhausted"], "paths");
+
+    // Filtered paths all pass the buoys asked for, sorted they come longest first
+    let filtered = json_body(
+        &get(&config, "/api/find-paths?start=OEVE&time=0&steps=3&include=GVS3-M8&finish=WV12&sort=distance").await,
+        200,
+    );
+    let filtered = filtered["paths"].as_array().unwrap();
+    assert!(!filtered.is_empty() && filtered.len() < paths.len());
+    for path in filtered {
+        let steps = path["steps"].as_array().unwrap();
+        assert!(steps.iter().any(|step| step["to_name"] == "GVS3-M8"));
+        assert_eq!(steps.last().unwrap()["to_name"], "WV12");
+    }
+    let distances: Vec<f64> = filtered.iter().map(|path| path["total_distance"].as_f64().unwrap()).collect();
+    assert!(distances.windows(2).all(|pair| pair[0] >= pair[1]));
+    error_body(&get(&config, "/api/find-paths?start=OEVE&time=0&steps=3&sort=fun").await, 400);
+    error_body(&get(&config, "/api/find-paths?start=OEVE&time=0&steps=3&finish=NOWHERE").await, 404);
+
+    let table = get(&config, "/api/find-paths.html?start=OEVE&time=0&steps=3&sort=speed").await;
+    assert_eq!(table.status(), 200);
+    assert!(content_type(&table).starts_with("text/html"));
+    let table = String::from_utf8(table.body().clone()).unwrap();
+    assert_eq!(table.matches(r#"class="path-row""#).count(), paths.len());
+
+    let target = json_body(&get(&config, "/api/find-targets?start=OEVE&target=LC1&time=0&steps=4").await, 200);
+    assert_eq!(target["target"], "LC1");
+    for path in target["paths"].as_array().unwrap() {
+        assert_eq!(path["steps"].as_array().unwrap().last().unwrap()["to_name"], "LC1");
+    }
+
+    // Avoiding the direct leg to LC1 and the bank at VF-A leaves the paths around them
+    let avoiding = "/api/find-targets?start=OEVE&target=LC1&time=0&steps=4&avoid=VF-A&avoid_leg=WV12-LC1";
+    let avoiding = json_body(&get(&config, avoiding).await, 200);
+    assert!(avoiding["stats"]["pruned"]["constraints"].as_u64().unwrap() > 0);
+    assert!(avoiding["stats"]["elapsed_ms"].as_f64().is_some());
+    let avoiding = avoiding["paths"].as_array().unwrap();
+    assert!(!avoiding.is_empty() && avoiding.len() < target["paths"].as_array().unwrap().len());
+    for path in avoiding {
+        for step in path["steps"].as_array().unwrap() {
+            assert_ne!(step["to_name"], "VF-A");
+            assert_ne!((&step["from_name"], &step["to_name"]), (&json!("WV12"), &json!("LC1")));
+        }
+    }
+    error_body(&get(&config, "/api/find-paths?start=OEVE&time=0&steps=3&avoid=NOWHERE").await, 404);
+    error_body(&get(&config, "/api/find-paths?start=OEVE&time=0&steps=3&avoid_leg=WV12").await, 400);
+
+    let csv = get(&config, "/api/find-targets.csv?start=OEVE&target=LC1&time=0&steps=4").await;
+    assert_eq!(csv.status(), 200);
+    assert_eq!(content_type(&csv), "text/csv; charset=utf-8");
+
+    error_body(&get(&config, "/api/find-paths?start=OEVE&time=0&steps=0").await, 400);
+    error_body(&get(&config, "/api/find-paths?start=OEVE&time=0&steps=11").await, 400);
+    error_body(&get(&config, "/api/find-paths?start=OEVE&time=0&steps=3&max_paths=0").await, 400);
+    error_body(&get(&config, "/api/find-paths?start=NOWHERE&time=0&steps=3").await, 404);
+    error_body(&get(&config, "/api/find-targets?start=OEVE&target=NOWHERE&time=0&steps=3").await, 404);
+}
+
+#[tokio::test]
+async fn test_arrival_profile() {
+    let config = ServerConfig::default();
+    let profile = json_body(&get(&config, "/api/arrival-profile?start=OEVE&from=0&until=2&interval=60").await, 200);
+    assert_eq!(profile["start"], "OEVE");
+    assert_eq!(profile["departures"], json!([0.0, 1.0, 2.0]));
+    assert_eq!(profile["departure_clocks"].as_array().unwrap().len(), 3);
+    let buoys = profile["buoys"].as_array().unwrap();
+    let lc1 = buoys.iter().find(|buoy| buoy["buoy"] == "LC1").unwrap();
+    let arrivals: Vec<f64> = lc1["arrivals"].as_array().unwrap().iter().map(|time| time.as_f64().unwrap()).collect();
+    assert_eq!(arrivals.len(), 3);
+    assert!(arrivals.iter().zip([0.0, 1.0, 2.0]).all(|(arrival, departure)| *arrival > departure));
+
+    // No path to a buoy arrives before its earliest arrival
+    let target = json_body(&get(&config, "/api/find-targets?start=OEVE&target=LC1&time=0&steps=4").await, 200);
+    for path in target["paths"].as_array().unwrap() {
+        assert!(path["end_time"].as_f64().unwrap() >= arrivals[0] - 1e-9);
+    }
+
+    // An avoided buoy is never reached
+    let avoiding = json_body(&get(&config, "/api/arrival-profile?start=OEVE&until=2&avoid=LC1").await, 200);
+    assert_eq!(avoiding["departures"].as_array().unwrap().len(), 5);
+    assert!(avoiding["unreachable"].as_array().unwrap().iter().any(|buoy| buoy["name"] == "LC1"));
+
+    error_body(&get(&config, "/api/arrival-profile?start=OEVE&interval=1").await, 400);
+    error_body(&get(&config, "/api/arrival-profile?start=OEVE&from=3&until=2").await, 400);
+    error_body(&get(&config, "/api/arrival-profile?start=NOWHERE").await, 404);
+}
+
+#[tokio::test]
+async fn test_plan_and_routes() {
+    let config = ServerConfig::default();
+    let plan = post(&config, "/api/plan", &json!({"start": "OEVE", "target": "LC1", "start_time": 0, "steps": 4})).await;
+    let plan = json_body(&plan, 200);
+    assert_eq!(plan["paths_found"], plan["paths"].as_array().unwrap().len());
+    let invalid = post(&config, "/api/plan", &json!({"start": "OEVE", "start_time": 0})).await;
+    error_body(&invalid, 400);
+
+    // Only the paths rounding VF-B as their second buoy
+    let request = json!({"start": "OEVE", "target": "LC1", "start_time": 0, "steps": 4, "pins": [{"buoy": "VF-B", "slot": 2}]});
+    let pinned = json_body(&post(&config, "/api/plan", &request).await, 200);
+    let paths = pinned["paths"].as_array().unwrap();
+    assert!(!paths.is_empty() && paths.len() < plan["paths"].as_array().unwrap().len());
+    assert!(paths.iter().all(|path| path["steps"][1]["to_name"] == "VF-B"));
+    let late = json!({"start": "OEVE", "start_time": 0, "steps": 2, "pins": [{"buoy": "WV12", "slot": 3}]});
+    error_body(&post(&config, "/api/plan", &late).await, 400);
+
+    // Diverse paths share at most half of their legs with each other
+    let request = json!({"start": "OEVE", "start_time": 0, "steps": 4, "max_results": 3, "diversity": 0.5});
+    let diverse = json_body(&post(&config, "/api/plan", &request).await, 200);
+    let legs: Vec<Vec<(String, String)>> = diverse["paths"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|path| {
+            let steps = path["steps"].as_array().unwrap();
+            let mut legs: Vec<(String, String)> = steps
+                .iter()
+                .map(|step| {
+                    let mut ends = [step["from_name"].to_string(), step["to_name"].to_string()];
+                    ends.sort();
+                    (ends[0].clone(), ends[1].clone())
+                })
+                .collect();
+            legs.sort();
+            legs.dedup();
+            legs
+        })
+        .collect();
+    assert!(legs.len() > 1);
+    for (i, a) in legs.iter().enumerate() {
+        for b in &legs[..i] {
+            let shared = a.iter().filter(|leg| b.contains(leg)).count();
+            assert!(shared as f64 <= 0.5 * (a.len() + b.len() - shared) as f64, "{a:?} {b:?}");
+        }
+    }
+    let request = json!({"start": "OEVE", "start_time": 0, "steps": 4, "diversity": 2});
+    error_body(&post(&config, "/api/plan", &request).await, 400);
+
+    // Objectives are chosen by name, weighted ones by their measures
+    let request = json!({"start": "OEVE", "start_time": 0, "steps": 3, "objective": "weighted:scored_distance=1,margin=0.5"});
+    let weighted = json_body(&post(&config, "/api/plan", &request).await, 200);
+    assert!(!weighted["paths"].as_array().unwrap().is_empty());
+    let request = json!({"start": "OEVE", "start_time": 0, "steps": 3, "objective": "weighted:speed=1"});
+    error_body(&post(&config, "/api/plan", &request).await, 400);
+
+    // Hierarchical plans may have more legs than a search may look ahead
+    let request = json!({"start": "OEVE", "target": "LC1", "start_time": 0, "steps": 15, "strategy": "hierarchical"});
+    let hierarchical = json_body(&post(&config, "/api/plan", &request).await, 200);
+    assert_eq!(hierarchical["strategy"], "hierarchical");
+    let paths = hierarchical["paths"].as_array().unwrap();
+    assert!(!paths.is_empty());
+    for path in paths {
+        let steps = path["steps"].as_array().unwrap();
+        assert!(steps.len() <= 15 && steps.last().unwrap()["to_name"] == "LC1");
+    }
+    let request = json!({"start": "OEVE", "target": "LC1", "start_time": 0, "steps": 15});
+    error_body(&post(&config, "/api/plan", &request).await, 413);
+    let request = json!({"start": "OEVE", "start_time": 0, "steps": 4, "strategy": "hierarchical", "pins": [{"buoy": "VF-B"}]});
+    error_body(&post(&config, "/api/plan", &request).await, 400);
+
+    let valid = json_body(
+        &post(&config, "/api/validate-route", &json!({"route": ["OEVE", "WV12", "LC1"]})).await,
+        200,
+    );
+    assert_eq!(valid["valid"], true);
+    assert_eq!(valid["violations"], json!([]));
+    let no_leg = post(&config, "/api/validate-route", &json!({"route": ["OEVE", "LC1"]})).await;
+    assert_eq!(json_body(&no_leg, 200)["valid"], false);
+
+    let simulation = json_body(&post(&config, "/api/simulate", &json!({"route": ["OEVE", "WV12", "LC1"]})).await, 200);
+    assert!(simulation.is_object());
+
+    let gpx = get(&config, "/api/route.gpx?path=OEVE,WV12,LC1&time=0").await;
+    assert_eq!(gpx.status(), 200);
+    assert_eq!(content_type(&gpx), "application/gpx+xml");
+    let geojson = get(&config, "/api/route.geojson?path=OEVE,WV12,LC1&time=0").await;
+    assert_eq!(geojson.status(), 200);
+    let profile = get(&config, "/api/route-profile.svg?path=OEVE,WV12,LC1&time=0").await;
+    assert_eq!(profile.status(), 200);
+    assert_eq!(content_type(&profile), "image/svg+xml");
+    let map = get(&config, "/api/route-map.svg?path=OEVE,WV12,LC1&time=0").await;
+    assert_eq!(content_type(&map), "image/svg+xml");
+    assert_eq!(String::from_utf8(map.body().clone()).unwrap().matches(r#"class="route-leg""#).count(), 2);
+    error_body(&get(&config, "/api/route-map.svg?path=OEVE,NOWHERE&time=0").await, 404);
+
+    // The charts follow the theme cookie of the pages unless one is asked for
+    let request = warp::test::request().path("/api/route-map.svg?path=OEVE,WV12&time=0").header("cookie", "uurs24-theme=dark");
+    let dark = String::from_utf8(send(request, &config).await.into_body()).unwrap();
+    assert!(dark.contains(r##"fill="#0b0000""##) && !dark.contains(r#"fill="black""#));
+    let request = warp::test::request().path("/map?theme=light").header("cookie", "uurs24-theme=dark");
+    let page = String::from_utf8(send(request, &config).await.into_body()).unwrap();
+    assert!(page.contains(r#"<body class="theme-light">"#));
+}
+
+#[tokio::test]
+async fn test_explore() {
+    let config = ServerConfig::default();
+    let map = get(&config, "/api/course.svg").await;
+    assert_eq!(map.status(), 200);
+    assert_eq!(content_type(&map), "image/svg+xml");
+    let svg = String::from_utf8(map.body().clone()).unwrap();
+    assert!(svg.contains(r#"data-buoy="OEVE""#) && svg.contains(r#"class="leg""#));
+
+    let exploration = json_body(&post(&config, "/api/explore", &json!({"start": "OEVE", "time": 0})).await, 201);
+    assert_eq!(exploration["route"], json!(["OEVE"]));
+    assert_eq!(exploration["distance"], 0.0);
+    assert_eq!(exploration["options"][0]["to"], "WV12");
+    assert!(exploration["options"][0]["speed"].as_f64().unwrap() > 0.0);
+    error_body(&post(&config, "/api/explore", &json!({"start": "NOWHERE"})).await, 404);
+    error_body(&post(&config, "/api/explore", &json!({"start": "OEVE", "time": 99})).await, 400);
+    error_body(&get(&config, "/api/explore/12345").await, 404);
+    error_body(&post(&config, "/api/explore/12345/legs", &json!({"to": "WV12"})).await, 404);
+}
+
+#[tokio::test]
+async fn test_share_plan() {
+    let config = ServerConfig::default();
+    let plan = json!({"route": ["OEVE", "WV12"], "time": 0, "notes": "Reef early"});
+    let plan = json_body(&post(&config, "/api/plans", &plan).await, 201);
+    assert_eq!(plan["name"], "OEVE - WV12");
+    assert_eq!(plan["url"], format!("/plans/{}", plan["id"].as_str().unwrap()));
+    assert_eq!(plan["path"]["steps"][0]["to_name"], "WV12");
+    error_body(&post(&config, "/api/plans", &json!({"route": ["OEVE"]})).await, 400);
+    error_body(&post(&config, "/api/plans", &json!({"route": ["OEVE", "NOWHERE"]})).await, 404);
+    error_body(&get(&config, "/plans/nothing").await, 404);
+}
+
+#[tokio::test]
+async fn test_leaderboard() {
+    let leaderboard = json_body(&get(&ServerConfig::default(), "/api/leaderboard").await, 200);
+    assert_eq!(leaderboard, json!({"rated": false, "boats": []}));
+
+    // One server for all requests, so the boats and their tracks stay
+    let api = routes(fixture(), ServerConfig::default()).unwrap();
+    let post = |path: &str, body: Value| warp::test::request().method("POST").path(path).json(&body).reply(&api);
+    for name in ["Alpha", "Bravo"] {
+        assert_eq!(post("/api/boats", json!({"name": name})).await.status(), 201);
+    }
+    let data = fixture();
+    let at = |buoy: &str, time: f64| {
+        let (lat, long) = data.boeien[data.get_boei_index(buoy).unwrap()].coordinates().unwrap();
+        json!({"lat": lat, "long": long, "time": time})
+    };
+    // Bravo rounded WV12 after the start, Alpha only started
+    for position in [at("OEVE", 0.0), at("WV12", 0.5)] {
+        assert_eq!(post("/api/position?boat=Bravo", position).await.status(), 201);
+    }
+    assert_eq!(post("/api/position?boat=Alpha", at("OEVE", 0.1)).await.status(), 201);
+
+    let response = warp::test::request().path("/api/leaderboard").reply(&api).await.map(|body| body.to_vec());
+    let leaderboard = json_body(&response, 200);
+    let boats = leaderboard["boats"].as_array().unwrap();
+    assert_eq!((&boats[0]["boat"], &boats[0]["rank"], &boats[0]["legs"]), (&json!("Bravo"), &json!(1), &json!(1)));
+    assert_eq!(boats[0]["scored_distance"], 2.716);
+    assert_eq!(boats[0]["last_mark"], "WV12");
+    assert_eq!((&boats[1]["boat"], &boats[1]["rank"], &boats[1]["last_position"]), (&json!("Alpha"), &json!(2), &json!(0.1)));
+}
+
+#[tokio::test]
+async fn test_sessions_and_jobs() {
+    let config = ServerConfig::default();
+    let session = post(&config, "/api/sessions", &json!({"name": "test", "start": "OEVE", "start_time": 0})).await;
+    assert_eq!(session.status(), 201, "{:?}", session.body());
+    let missing = get(&config, "/api/sessions/12345").await;
+    error_body(&missing, 404);
+    error_body(&get(&config, "/api/jobs/12345").await, 404);
+}
+
+#[tokio::test]
+async fn test_request_errors() {
+    let config = ServerConfig::default();
+    error_body(&get(&config, "/no/such/page").await, 404);
+    error_body(&get(&config, "/api/no-such-endpoint").await, 404);
+
+    // Overlong queries are turned away before their parameters are looked at
+    let long = format!("/api/estimate?from={}&to=LC1&time=2", "A".repeat(3000));
+    error_body(&get(&config, &long).await, 414);
+    let script = error_body(&get(&config, "/api/estimate?from=%3Cscript%3E&to=LC1&time=2").await, 404);
+    assert!(script["message"].as_str().unwrap().contains("<script>"));
+
+    // The wrong method, and a body which isn't JSON
+    let put = send(warp::test::request().method("PUT").path("/api/plan"), &config).await;
+    error_body(&put, 405);
+    let text = warp::test::request()
+        .method("POST")
+        .path("/api/plan")
+        .header("content-type", "text/plain")
+        .body("start=OEVE");
+    let text = send(text, &config).await;
+    error_body(&text, 415);
+    let too_large = warp::test::request()
+        .method("POST")
+        .path("/api/plan")
+        .json(&json!({"start": "x".repeat(100_000)}));
+    let too_large = send(too_large, &config).await;
+    error_body(&too_large, 413);
+
+    // The fixture course has no finish to search back from
+    let approach = error_body(&get(&config, "/api/finish-approach?time=20").await, 422);
+    assert!(approach["message"].as_str().unwrap().contains("FINISH"));
+    error_body(&get(&config, "/api/finish-approach.svg?time=20").await, 422);
+}
+
+#[tokio::test]
+async fn test_access_token_and_base_path() {
+    let config = ServerConfig {
+        auth_token: Some("secret".to_string()),
+        base_path: "uurs24/".to_string(),
+        ..ServerConfig::default()
+    };
+    let path = "/uurs24/api/estimate?from=WV12&to=LC1&time=2";
+
+    let denied = get(&config, path).await;
+    error_body(&denied, 401);
+    assert_eq!(denied.headers()["www-authenticate"], "Bearer");
+    let wrong = send(warp::test::request().path(path).header("authorization", "Bearer guess"), &config).await;
+    error_body(&wrong, 401);
+    let allowed = send(warp::test::request().path(path).header("authorization", "Bearer secret"), &config).await;
+    json_body(&allowed, 200);
+
+    // The event feed also takes the token in the query, for EventSource; its
+    // stream never ends, so only the start of the response is looked at
+    error_body(&get(&config, "/uurs24/api/events").await, 401);
+    error_body(&get(&config, "/uurs24/api/events?token=guess").await, 401);
+    let events = warp::test::request()
+        .path("/uurs24/api/events?token=secret")
+        .filter(&routes(fixture(), config.clone()).unwrap())
+        .await
+        .unwrap()
+        .into_response();
+    assert_eq!(events.status(), 200);
+    assert_eq!(events.headers()["content-type"], "text/event-stream");
+
+    // Changing the course needs the token, or a request from the server machine
+    let update = json!({"kind": "move_buoy", "name": "LC1", "lat": 52.95, "long": 5.1});
+    let course = warp::test::request().method("POST").path("/uurs24/api/course").json(&update);
+    let course = json_body(&send(course.header("authorization", "Bearer secret"), &config).await, 200);
+    assert_eq!(course["version"], 1);
+    let unknown = json!({"kind": "set_max_number", "from": "LC1", "to": "NOWHERE", "max_number": 2});
+    let unknown = warp::test::request().method("POST").path("/uurs24/api/course").json(&unknown);
+    error_body(&send(unknown.header("authorization", "Bearer secret"), &config).await, 422);
+    error_body(&post(&ServerConfig::default(), "/api/course", &update).await, 403);
+
+    // So is changing the wind forecast, which answers with the changed forecast
+    let wind = |body: &Value| warp::test::request().method("POST").path("/uurs24/api/wind").json(body);
+    let changed = json!({"wind": [{"time": 1, "wind_speed": 25.0, "wind_angle": 360.0}]});
+    let forecast = json_body(&send(wind(&changed).header("authorization", "Bearer secret"), &config).await, 200);
+    assert_eq!((&forecast["version"], &forecast["hours"]), (&json!(1), &json!(1)));
+    assert_eq!(forecast["wind"][1], json!({"time": 1, "wind_speed": 25.0, "wind_angle": 0.0}));
+    let unknown = json!({"wind": [{"time": 999, "wind_speed": 25.0, "wind_angle": 90.0}]});
+    error_body(&send(wind(&unknown).header("authorization", "Bearer secret"), &config).await, 400);
+    error_body(&post(&ServerConfig::default(), "/api/wind", &changed).await, 403);
+    for path in ["/api/reload", "/api/calibration"] {
+        error_body(&post(&ServerConfig::default(), path, &json!({})).await, 403);
+    }
+
+    // Nothing is served outside of the base path
+    let outside = warp::test::request()
+        .path("/api/estimate?from=WV12&to=LC1&time=2")
+        .header("authorization", "Bearer secret");
+    error_body(&send(outside, &config).await, 404);
+
+    // A base path which couldn't be written into the pages as it is
+    let quoted = ServerConfig {
+        base_path: "uurs'24".to_string(),
+        ..ServerConfig::default()
+    };
+    assert!(routes(fixture(), quoted).is_err());
+}
+
+/// Check a JSON value against a schema of the OpenAPI document: the types,
+/// the required properties, and that null is only where the schema allows
+/// it and no property is missing from the schema. The first difference is
+/// named by its place in the value.
+fn conforms(document: &Value, schema: &Value, value: &Value, at: &str) -> Result<(), String> {
+    if schema["$ref"].is_string() {
+        return conforms(document, resolve(document, schema), value, at);
+    }
+    if value.is_null() {
+        return if schema["nullable"] == true { Ok(()) } else { Err(format!("{at}: null is not nullable")) };
+    }
+    if let Some(parts) = schema["allOf"].as_array() {
+        // The parts are checked as one object, as each lacks the properties of the others
+        let mut all = json!({ "type": "object", "properties": {}, "required": [] });
+        for part in parts.iter().map(|part| resolve(document, part)) {
+            let properties = part["properties"].as_object().into_iter().flatten();
+            all["properties"].as_object_mut().unwrap().extend(properties.map(|(key, schema)| (key.clone(), schema.clone())));
+            all["required"].as_array_mut().unwrap().extend(part["required"].as_array().into_iter().flatten().cloned());
+        }
+        return conforms(document, &all, value, at);
+    }
+    if let Some(choices) = schema["oneOf"].as_array().or(schema["anyOf"].as_array()) {
+        return match choices.iter().any(|schema| conforms(document, schema, value, at).is_ok()) {
+            true => Ok(()),
+            false => Err(format!("{at}: {value} matches none of the choices")),
+        };
+    }
+    if let Some(values) = schema["enum"].as_array()
+        && !values.contains(value)
+    {
+        return Err(format!("{at}: {value} is not one of {values:?}"));
+    }
+    let matches = match schema["type"].as_str() {
+        Some("number") => value.is_number(),
+        Some("integer") => value.is_i64() || value.is_u64(),
+        Some("string") => value.is_string(),
+        Some("boolean") => value.is_boolean(),
+        Some("array") => value.is_array(),
+        Some("object") => value.is_object(),
+        _ => true,
+    };
+    if !matches {
+        return Err(format!("{at}: {value} is not of type {}", schema["type"]));
+    }
+    if let Some(items) = value.as_array() {
+        for (i, item) in items.iter().enumerate() {
+            conforms(document, &schema["items"], item, &format!("{at}[{i}]"))?;
+        }
+    }
+    if let Some(object) = value.as_object()
+        && (schema["properties"].is_object() || schema["additionalProperties"].is_object())
+    {
+        for required in schema["required"].as_array().into_iter().flatten() {
+            if !object.contains_key(required.as_str().unwrap()) {
+                return Err(format!("{at}: required property {required} is missing"));
+            }
+        }
+        for (key, item) in object {
+            let at = format!("{at}.{key}");
+            match schema["properties"].get(key).or(schema.get("additionalProperties")) {
+                Some(schema) if schema.is_object() => conforms(document, schema, item, &at)?,
+                Some(_) => {}
+                None => return Err(format!("{at}: not in the schema")),
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The schema a reference of the OpenAPI document points to
+fn resolve<'a>(document: &'a Value, schema: &'a Value) -> &'a Value {
+    match schema["$ref"].as_str() {
+        Some(reference) => &document["components"]["schemas"][reference.trim_start_matches("#/components/schemas/")],
+        None => schema,
+    }
+}
+
+#[tokio::test]
+async fn test_openapi_schemas() {
+    let document = uurs24::api::openapi_document();
+    let config = ServerConfig {
+        auth_token: Some("secret".to_string()),
+        ..ServerConfig::default()
+    };
+
+    // One server for all requests, so that the sessions, games, and boats
+    // created are there for the requests after them
+    let api = routes(fixture(), config).unwrap();
+    let mut requested = Vec::new();
+    let mut check = async |method: &str, template: &str, path: &str, body: Option<Value>| {
+        let mut request = warp::test::request().method(method).path(path).header("authorization", "Bearer secret");
+        if let Some(body) = &body {
+            request = request.json(body);
+        }
+        let response = request.reply(&api).await;
+        let status = response.status().as_u16();
+        assert!(response.status().is_success(), "{method} {path}: {status} {:?}", response.body());
+        let value: Value = serde_json::from_slice(response.body()).unwrap();
+        let responses = &document["paths"][template][method.to_lowercase()]["responses"];
+        let schema = &responses[status.to_string()]["content"]["application/json"]["schema"];
+        assert!(schema.is_object(), "{method} {template}: no schema for {status}");
+        if let Err(difference) = conforms(&document, schema, &value, "") {
+            panic!("{method} {path} differs from its schema: {difference}");
+        }
+        requested.push(format!("{method} {template}"));
+        value
+    };
+
+    check("GET", "/version", "/version", None).await;
+    check("GET", "/health", "/health", None).await;
+    check("GET", "/api/data", "/api/data", None).await;
+    check("GET", "/api/warnings", "/api/warnings", None).await;
+    check("GET", "/api/estimate", "/api/estimate?from=WV12&to=LC1&time=2", None).await;
+    check("GET", "/api/estimateleg", "/api/estimateleg?from=WV12&to=LC1&reverse=true&time=2", None).await;
+    check("GET", "/api/neighbors", "/api/neighbors?buoy=WV12", None).await;
+    check("GET", "/api/find-paths", "/api/find-paths?start=OEVE&time=0&steps=3", None).await;
+    check("GET", "/api/find-targets", "/api/find-targets?start=OEVE&target=LC1&time=0&steps=4", None).await;
+    check("GET", "/api/arrival-profile", "/api/arrival-profile?start=OEVE&until=2&avoid=LC1", None).await;
+    let plan = json!({"start": "OEVE", "target": "LC1", "start_time": 0, "steps": 4});
+    check("POST", "/api/plan", "/api/plan", Some(plan.clone())).await;
+    let job = check("POST", "/api/jobs", "/api/jobs", Some(plan)).await;
+    let job = format!("/api/jobs/{}", job["id"]);
+    check("GET", "/api/jobs/{id}", &job, None).await;
+    check("DELETE", "/api/jobs/{id}", &job, None).await;
+
+    let input = json!({"name": "test", "start": "OEVE", "start_time": 0, "pinned_legs": [{"from": "OEVE", "to": "WV12"}]});
+    let session = check("POST", "/api/sessions", "/api/sessions", Some(input.clone())).await;
+    let id = &session["id"];
+    check("GET", "/api/sessions", "/api/sessions", None).await;
+    check("GET", "/api/sessions/{id}", &format!("/api/sessions/{id}"), None).await;
+    check("PUT", "/api/sessions/{id}", &format!("/api/sessions/{id}"), Some(input)).await;
+
+    // The fleet, the own boat's track, and what is derived from them
+    check("POST", "/api/boats", "/api/boats", Some(json!({"name": "Alpha", "session": id}))).await;
+    check("GET", "/api/boats", "/api/boats", None).await;
+    let data = fixture();
+    let (lat, long) = data.boeien[data.get_boei_index("OEVE").unwrap()].coordinates().unwrap();
+    let position = json!({"lat": lat, "long": long, "time": 0.1, "speed": 5.0, "course": 90.0});
+    check("POST", "/api/position", "/api/position", Some(position.clone())).await;
+    check("POST", "/api/position", "/api/position?boat=Alpha", Some(position)).await;
+    check("GET", "/api/track", "/api/track", None).await;
+    check("GET", "/api/dashboard", "/api/dashboard?from=OEVE&to=WV12", None).await;
+    check("GET", "/api/progress", &format!("/api/progress?session={id}"), None).await;
+    check("GET", "/api/leaderboard", "/api/leaderboard", None).await;
+    check("DELETE", "/api/boats/{name}", "/api/boats/Alpha", None).await;
+    let observation = json!({"from": "WV12", "to": "LC1", "time": 2, "speed": 6.5, "wind_direction": 200, "wind_speed": 12});
+    check("POST", "/api/observations", "/api/observations", Some(observation)).await;
+    check("GET", "/api/observations", "/api/observations", None).await;
+    check("GET", "/api/calibration", "/api/calibration", None).await;
+    check("POST", "/api/calibration", "/api/calibration", Some(json!({}))).await;
+
+    // The simulator, the games, and the checks of routes
+    let route = json!({"route": ["OEVE", "WV12", "LC1"]});
+    check("POST", "/api/simulate", "/api/simulate", Some(route.clone())).await;
+    check("POST", "/api/validate-route", "/api/validate-route", Some(route.clone())).await;
+    check("POST", "/api/plans", "/api/plans", Some(route)).await;
+    let game = check("POST", "/api/training", "/api/training", Some(json!({"start": "OEVE", "time": 0}))).await;
+    let game = format!("/api/training/{}", game["id"]);
+    check("GET", "/api/training/{id}", &game, None).await;
+    check("POST", "/api/training/{id}/legs", &format!("{game}/legs"), Some(json!({"to": "WV12"}))).await;
+    let exploration = check("POST", "/api/explore", "/api/explore", Some(json!({"start": "OEVE", "time": 0}))).await;
+    let exploration = format!("/api/explore/{}", exploration["id"]);
+    check("GET", "/api/explore/{id}", &exploration, None).await;
+    check("POST", "/api/explore/{id}/legs", &format!("{exploration}/legs"), Some(json!({"to": "WV12"}))).await;
+    check("DELETE", "/api/explore/{id}/legs", &format!("{exploration}/legs"), None).await;
+
+    // Changes of the served data
+    let update = json!({"kind": "move_buoy", "name": "LC1", "lat": 52.95, "long": 5.1});
+    check("POST", "/api/course", "/api/course", Some(update)).await;
+    let wind = json!({"wind": [{"time": 1, "wind_speed": 25.0, "wind_angle": 90.0}]});
+    check("POST", "/api/wind", "/api/wind", Some(wind)).await;
+    check("POST", "/api/reload", "/api/reload", Some(json!({}))).await;
+
+    // Every JSON answer of the document was checked, but the one of the
+    // finish approach, as the fixture course has no finish
+    for (template, operations) in document["paths"].as_object().unwrap() {
+        for (method, operation) in operations.as_object().unwrap() {
+            let responses = operation["responses"].as_object().unwrap();
+            let json = responses
+                .iter()
+                .any(|(status, response)| status.starts_with('2') && response["content"]["application/json"].is_object());
+            let name = format!("{} {template}", method.to_uppercase());
+            if json && template != "/api/finish-approach" {
+                assert!(requested.contains(&name), "{name} is not checked against its schema");
+            }
+        }
+    }
+}
@@ -0,0 +1,146 @@
+//! Golden file tests of the optimizer on the small course in
+//! `tests/fixtures/small`: the estimates and the paths found are compared
+//! with the JSON files in `tests/golden`, so that a change of the search or
+//! of the interpolation can't change the recommendations unnoticed. After an
+//! intended change, `UPDATE_GOLDEN=1 cargo test --test golden` writes the
+//! files anew, and the diff shows what changed.
+
+use serde_json::{Value, json};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use uurs24::data::{RegattaData, load_regatta_data};
+use uurs24::optimize::{Path, SearchOptions, estimate_leg_performance, explore_paths, explore_target_paths};
+
+/// Largest difference between a number and its golden value
+const TOLERANCE: f64 = 1e-6;
+
+/// The data of the fixture course, loaded once from its directory, which
+/// all tests of this file share as the working directory
+fn fixture() -> &'static RegattaData {
+    static DATA: OnceLock<RegattaData> = OnceLock::new();
+    DATA.get_or_init(|| {
+        std::env::set_current_dir(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/small")).unwrap();
+        load_regatta_data().unwrap()
+    })
+}
+
+fn index(data: &RegattaData, name: &str) -> usize {
+    data.get_boei_index(name).unwrap()
+}
+
+/// The paths with their routes and timings, sorted by route so that only
+/// the paths found count and not the order of the search
+fn paths_json(data: &RegattaData, paths: &[Path]) -> Value {
+    let mut paths: Vec<Value> = paths
+        .iter()
+        .map(|path| {
+            let mut route = vec![data.boeien[path.steps[0].from].name.clone()];
+            route.extend(path.steps.iter().map(|step| data.boeien[step.to].name.clone()));
+            json!({
+                "route": route,
+                "total_distance": path.total_distance,
+                "scored_distance": path.scored_distance,
+                "end_time": path.end_time,
+                "speeds": path.steps.iter().map(|step| step.speed).collect::<Vec<_>>(),
+                "waits": path.steps.iter().map(|step| step.wait).collect::<Vec<_>>(),
+            })
+        })
+        .collect();
+    paths.sort_by_key(|path| path["route"].to_string());
+    json!(paths)
+}
+
+/// Compare a value with its golden file, or write the file with `UPDATE_GOLDEN`
+fn check_golden(name: &str, actual: &Value) {
+    let file = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{name}.json"));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(file.parent().unwrap()).unwrap();
+        std::fs::write(&file, serde_json::to_string_pretty(actual).unwrap() + "\n").unwrap();
+        return;
+    }
+    let content = std::fs::read_to_string(&file)
+        .unwrap_or_else(|e| panic!("{}: {e}, run with UPDATE_GOLDEN=1 to write it", file.display()));
+    let expected: Value = serde_json::from_str(&content).unwrap();
+    if let Err(difference) = compare(&expected, actual, "") {
+        panic!("{name} differs from {}: {difference}", file.display());
+    }
+}
+
+/// Compare JSON values, numbers up to the tolerance, naming the first difference
+fn compare(expected: &Value, actual: &Value, at: &str) -> Result<(), String> {
+    match (expected, actual) {
+        (Value::Number(expected), Value::Number(actual)) => {
+            let (expected, actual) = (expected.as_f64().unwrap(), actual.as_f64().unwrap());
+            if (expected - actual).abs() > TOLERANCE {
+                return Err(format!("{at}: expected {expected}, got {actual}"));
+            }
+        }
+        (Value::Array(expected), Value::Array(actual)) => {
+            if expected.len() != actual.len() {
+                return Err(format!("{at}: expected {} entries, got {}", expected.len(), actual.len()));
+            }
+            for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                compare(expected, actual, &format!("{at}[{i}]"))?;
+            }
+        }
+        (Value::Object(expected), Value::Object(actual)) => {
+            if expected.len() != actual.len() {
+                return Err(format!("{at}: expected the fields {:?}", expected.keys().collect::<Vec<_>>()));
+            }
+            for (key, expected) in expected {
+                let actual = actual.get(key).ok_or_else(|| format!("{at}.{key}: missing"))?;
+                compare(expected, actual, &format!("{at}.{key}"))?;
+            }
+        }
+        _ if expected != actual => return Err(format!("{at}: expected {expected}, got {actual}")),
+        _ => {}
+    }
+    Ok(())
+}
+
+#[test]
+fn test_golden_estimates() {
+    let data = fixture();
+    let mut estimates = Vec::new();
+    for rak in &data.rakken {
+        for (from, to) in [(&rak.from, &rak.to), (&rak.to, &rak.from)] {
+            for time in [0.0, 5.5, 12.0, 20.25] {
+                let leg = estimate_leg_performance(data, index(data, from), index(data, to), time).unwrap();
+                estimates.push(json!({
+                    "from": from,
+                    "to": to,
+                    "time": time,
+                    "estimated_speed": leg.estimated_speed,
+                    "course_bearing": leg.course_bearing,
+                    "wind_direction": leg.wind_direction,
+                    "wind_speed": leg.wind_speed,
+                    "travel_time": leg.travel_time,
+                    "arrival_time": leg.arrival_time,
+                }));
+            }
+        }
+    }
+    check_golden("estimates", &json!(estimates));
+}
+
+#[test]
+fn test_golden_paths() {
+    let data = fixture();
+    let paths = explore_paths(data, index(data, "OEVE"), 0.0, 4, &SearchOptions::default()).unwrap();
+    check_golden("paths_oeve", &paths_json(data, &paths));
+
+    let waiting = SearchOptions {
+        max_wait: 1.0,
+        ..SearchOptions::default()
+    };
+    let paths = explore_paths(data, index(data, "STAV"), 7.0, 3, &waiting).unwrap();
+    check_golden("paths_stav_waiting", &paths_json(data, &paths));
+}
+
+#[test]
+fn test_golden_target_paths() {
+    let data = fixture();
+    let (oeve, lc1) = (index(data, "OEVE"), index(data, "LC1"));
+    let paths = explore_target_paths(data, oeve, lc1, 2.0, 5, &SearchOptions::default()).unwrap();
+    check_golden("target_paths_oeve_lc1", &paths_json(data, &paths));
+}